@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.28;
+
+/// Minimal interface of the ERC20 tokens we transfer
+interface IERC20 {
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+}
+
+/// An erc20 transfer with richer event
+///
+/// The EVM counterpart of the Starknet `InvoicePayment` contract, see `contracts/invoice`.
+/// The payer must first approve this contract to spend `amount` of `asset`.
+///
+/// The `invoiceId` of the event is the one stored by the node with the mint or melt quote,
+/// it is the keccak256 of the abi encoding of `quoteIdHash` and `expiry`.
+contract InvoicePayment {
+    /// A deposit was made for `invoiceId`
+    event Remittance(
+        address indexed asset,
+        address indexed payee,
+        bytes32 invoiceId,
+        address payer,
+        uint256 amount
+    );
+
+    /// Execute an erc20 transfer and emit the rich event
+    function payInvoice(
+        bytes32 quoteIdHash,
+        uint64 expiry,
+        address asset,
+        uint256 amount,
+        address payee
+    ) external {
+        require(expiry >= block.timestamp, "Invoice expired");
+
+        bytes32 invoiceId = keccak256(abi.encode(quoteIdHash, expiry));
+
+        require(IERC20(asset).transferFrom(msg.sender, payee, amount), "Transfer failed");
+
+        emit Remittance(asset, payee, invoiceId, msg.sender, amount);
+    }
+}
@@ -73,6 +73,16 @@ enum NodeCommands {
     )]
     #[clap(name = "ls")]
     List {},
+    /// Check the node proof of liabilities
+    #[command(
+        about = "Check that a node accounts for all your tokens",
+        long_about = "Check that a node accounts for all your tokens. Verify that the signatures you received and the proofs you spent are included in its proof of liabilities."
+    )]
+    CheckLiabilities {
+        /// Id of the node
+        #[arg(long, short)]
+        node_id: u32,
+    },
 }
 
 #[derive(Subcommand)]
@@ -225,6 +235,43 @@ async fn main() -> Result<()> {
                 println!("{} {}", id, url);
             }
         }
+        Commands::Node(NodeCommands::CheckLiabilities { node_id }) => {
+            let (mut node_client, node_url) = connect_to_node(&mut db_conn, node_id).await?;
+            println!("Checking the liabilities of {}", &node_url);
+
+            let check =
+                wallet::liabilities::check_liabilities(pool.clone(), &mut node_client, node_id)
+                    .await?;
+
+            for keyset in &check.keysets {
+                println!(
+                    "  keyset {} ({}): issued {} in {} signatures, burned {} in {} proofs",
+                    keyset.keyset_id,
+                    keyset.unit,
+                    keyset.issued,
+                    keyset.issued_count,
+                    keyset.burned,
+                    keyset.burned_count
+                );
+            }
+            println!(
+                "{} signatures and {} spent proofs verified",
+                check.verified_signatures, check.verified_spent_proofs
+            );
+            if !check.is_valid() {
+                for blinded_secret in &check.missing_signatures {
+                    println!("  missing signature {}", blinded_secret);
+                }
+                for y in &check.missing_spent_proofs {
+                    println!("  missing spent proof {}", y);
+                }
+                return Err(anyhow!(
+                    "the node doesn't account for {} signatures and {} spent proofs",
+                    check.missing_signatures.len(),
+                    check.missing_spent_proofs.len()
+                ));
+            }
+        }
         Commands::Balance { node_id } => match node_id {
             Some(node_id) => {
                 let balances = wallet::db::balance::get_for_node(&db_conn, node_id)?;
@@ -327,7 +374,7 @@ async fn main() -> Result<()> {
             // Format starknet request
             let request = serde_json::to_string(&starknet_liquidity_source::MeltPaymentRequest {
                 payee: payee_address,
-                asset: starknet_types::Asset::Strk,
+                asset: starknet_types::Asset::STRK,
                 amount: on_chain_amount.into(),
             })?;
 
@@ -348,7 +395,7 @@ async fn main() -> Result<()> {
                 &mut node_client,
                 node_id,
                 melt_quote_response.quote.clone(),
-                Amount::from(melt_quote_response.amount),
+                Amount::from(melt_quote_response.amount + melt_quote_response.fee_reserve),
                 method.clone(),
                 unit,
             )
@@ -36,6 +36,18 @@ pub async fn sync_all_pending_operations(pool: Pool<SqliteConnectionManager>) ->
         sync_melt_quotes(&pool, &mut node_client, &pending_quotes).await?;
     }
 
+    let nodes = wallet::db::node::fetch_all(&db_conn)?;
+    drop(db_conn);
+    for (node_id, node_url) in nodes {
+        let (mut node_client, _) = connect_to_node(pool.clone(), node_id).await?;
+        if let Err(e) = sync_expiring_keysets(&pool, &mut node_client, node_id).await {
+            eprintln!(
+                "Failed to swap the proofs of expiring keysets of node {} ({}): {}",
+                node_id, node_url, e
+            );
+        }
+    }
+
     println!("Sync completed for all nodes");
     Ok(())
 }
@@ -98,6 +110,30 @@ async fn sync_mint_quotes(
     Ok(())
 }
 
+async fn sync_expiring_keysets(
+    pool: &Pool<SqliteConnectionManager>,
+    node_client: &mut NodeClient<Channel>,
+    node_id: u32,
+) -> Result<()> {
+    wallet::refresh_node_keysets(pool.clone(), node_client, node_id).await?;
+
+    let swapped = wallet::swap_proofs_of_expiring_keysets(
+        pool.clone(),
+        node_client,
+        node_id,
+        wallet::KEYSET_EXPIRY_SWAP_THRESHOLD,
+    )
+    .await?;
+    for (unit, amount) in swapped {
+        println!(
+            "Warning: node {} is retiring some of its keysets, swapped {} {} for fresh tokens",
+            node_id, amount, unit
+        );
+    }
+
+    Ok(())
+}
+
 async fn sync_melt_quotes(
     pool: &Pool<SqliteConnectionManager>,
     node_client: &mut NodeClient<Channel>,
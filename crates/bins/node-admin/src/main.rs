@@ -0,0 +1,184 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use node_client::{
+    AdminClient, ExpireQuoteRequest, GetQuoteRequest, ListQuotesRequest, Quote, QuoteKind,
+    SetAmountLimitsRequest, SetDisabledRequest,
+};
+use tonic::{Request, Status, metadata::MetadataValue, transport::Channel};
+
+#[derive(Parser)]
+#[command(author, version, about = "Administrate a running node", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    /// Url of the node gRPC server
+    #[arg(long, env = "NODE_URL", default_value = "http://localhost:20001")]
+    node_url: String,
+    /// Token expected by the node admin service
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Mint,
+    Melt,
+}
+
+impl From<Kind> for QuoteKind {
+    fn from(value: Kind) -> Self {
+        match value {
+            Kind::Mint => QuoteKind::QkMint,
+            Kind::Melt => QuoteKind::QkMelt,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum QuoteCommands {
+    /// List the most recent quotes
+    #[clap(name = "ls")]
+    List {
+        #[arg(value_enum)]
+        kind: Kind,
+        /// Only list the quotes in this state (eg. UNPAID)
+        #[arg(long)]
+        state: Option<String>,
+        /// Max number of quotes returned
+        #[arg(long, default_value_t = 100)]
+        limit: u32,
+    },
+    /// Show a single quote
+    Show {
+        #[arg(value_enum)]
+        kind: Kind,
+        /// Id of the quote
+        quote: String,
+    },
+    /// Expire an unpaid quote right away
+    Expire {
+        #[arg(value_enum)]
+        kind: Kind,
+        /// Id of the quote
+        quote: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Disable new mint quotes
+    DisableMint,
+    /// Enable new mint quotes
+    EnableMint,
+    /// Disable new melt quotes
+    DisableMelt,
+    /// Enable new melt quotes
+    EnableMelt,
+    /// Set the amount limits of a method and unit
+    ///
+    /// An omitted limit is removed.
+    SetLimits {
+        #[arg(value_enum)]
+        kind: Kind,
+        #[arg(long, default_value = "starknet")]
+        method: String,
+        #[arg(long)]
+        unit: String,
+        #[arg(long)]
+        min_amount: Option<u64>,
+        #[arg(long)]
+        max_amount: Option<u64>,
+    },
+    #[command(subcommand)]
+    Quote(QuoteCommands),
+}
+
+fn print_quote(quote: &Quote) {
+    println!(
+        "{} {} {} (fee {}) {} expiry {} request {}",
+        quote.id, quote.amount, quote.unit, quote.fee, quote.state, quote.expiry, quote.request
+    );
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let channel = Channel::from_shared(cli.node_url)?.connect().await?;
+    let authorization: MetadataValue<_> = format!("Bearer {}", cli.admin_token).parse()?;
+    let mut client = AdminClient::with_interceptor(channel, move |mut req: Request<()>| {
+        req.metadata_mut()
+            .insert("authorization", authorization.clone());
+        Ok::<_, Status>(req)
+    });
+
+    match cli.command {
+        Commands::DisableMint | Commands::EnableMint => {
+            let disabled = matches!(cli.command, Commands::DisableMint);
+            client
+                .set_mint_disabled(SetDisabledRequest { disabled })
+                .await?;
+            println!("Minting {}", if disabled { "disabled" } else { "enabled" });
+        }
+        Commands::DisableMelt | Commands::EnableMelt => {
+            let disabled = matches!(cli.command, Commands::DisableMelt);
+            client
+                .set_melt_disabled(SetDisabledRequest { disabled })
+                .await?;
+            println!("Melting {}", if disabled { "disabled" } else { "enabled" });
+        }
+        Commands::SetLimits {
+            kind,
+            method,
+            unit,
+            min_amount,
+            max_amount,
+        } => {
+            client
+                .set_amount_limits(SetAmountLimitsRequest {
+                    kind: QuoteKind::from(kind).into(),
+                    method,
+                    unit,
+                    min_amount,
+                    max_amount,
+                })
+                .await?;
+            println!("Amount limits updated");
+        }
+        Commands::Quote(QuoteCommands::List { kind, state, limit }) => {
+            let quotes = client
+                .list_quotes(ListQuotesRequest {
+                    kind: QuoteKind::from(kind).into(),
+                    state,
+                    limit,
+                })
+                .await?
+                .into_inner()
+                .quotes;
+            for quote in &quotes {
+                print_quote(quote);
+            }
+        }
+        Commands::Quote(QuoteCommands::Show { kind, quote }) => {
+            let quote = client
+                .get_quote(GetQuoteRequest {
+                    kind: QuoteKind::from(kind).into(),
+                    quote,
+                })
+                .await?
+                .into_inner();
+            print_quote(&quote);
+        }
+        Commands::Quote(QuoteCommands::Expire { kind, quote }) => {
+            client
+                .expire_quote(ExpireQuoteRequest {
+                    kind: QuoteKind::from(kind).into(),
+                    quote: quote.clone(),
+                })
+                .await?;
+            println!("Quote {} expired", quote);
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,13 @@
+use std::{env, path::PathBuf};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+
+    // Only the public protos are described by the reflection service
     tonic_build::configure()
         .build_client(false)
         .build_server(true)
+        .file_descriptor_set_path(out_dir.join("node_descriptor.bin"))
         .compile_protos(
             &[
                 "../../../proto/node.proto",
@@ -10,5 +16,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ],
             &["../../../proto"],
         )?;
+    tonic_build::configure()
+        .build_client(false)
+        .build_server(true)
+        .compile_protos(&["../../../proto/admin.proto"], &["../../../proto"])?;
     Ok(())
 }
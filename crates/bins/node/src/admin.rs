@@ -0,0 +1,345 @@
+//! Runtime controls for the node operators
+//!
+//! Every request must carry an `authorization: Bearer <ADMIN_TOKEN>` metadata entry.
+use std::str::FromStr;
+
+use node::{
+    Admin, ExpireQuoteRequest, ExpireQuoteResponse, GetQuoteRequest, ListQuotesRequest,
+    ListQuotesResponse, Quote, QuoteKind, SetAmountLimitsRequest, SetAmountLimitsResponse,
+    SetDisabledRequest, SetDisabledResponse,
+};
+use nuts::{Amount, nut04::MintQuoteState, nut05::MeltQuoteState};
+use starknet_types::Unit;
+use tonic::{Request, Response, Status, service::Interceptor};
+use tracing::{Level, event};
+use uuid::Uuid;
+
+use crate::{audit::AuditEvent, grpc_service::GrpcState, methods::Method};
+
+const DEFAULT_LIST_LIMIT: u32 = 100;
+const MAX_LIST_LIMIT: u32 = 1000;
+
+/// Reject the requests not authenticated with the admin token
+#[derive(Debug, Clone)]
+pub struct AdminAuthInterceptor {
+    expected_header: Vec<u8>,
+}
+
+impl AdminAuthInterceptor {
+    pub fn new(token: &str) -> Self {
+        Self {
+            expected_header: format!("Bearer {}", token).into_bytes(),
+        }
+    }
+}
+
+impl Interceptor for AdminAuthInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let header = request
+            .metadata()
+            .get("authorization")
+            .ok_or_else(|| Status::unauthenticated("missing authorization metadata"))?;
+
+        if !constant_time_eq(header.as_bytes(), &self.expected_header) {
+            return Err(Status::unauthenticated("invalid admin token"));
+        }
+
+        Ok(request)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn parse_quote_id(quote: &str) -> Result<Uuid, Status> {
+    Uuid::from_str(quote).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+fn parse_kind(kind: i32) -> Result<QuoteKind, Status> {
+    match QuoteKind::try_from(kind) {
+        Ok(QuoteKind::QkUnspecified) | Err(_) => {
+            Err(Status::invalid_argument("quote kind must be specified"))
+        }
+        Ok(kind) => Ok(kind),
+    }
+}
+
+/// Settings changes are not part of a db transaction, the event is recorded once the change is applied
+async fn record_settings_change(state: &GrpcState, event: AuditEvent) -> Result<(), Status> {
+    let mut conn = state
+        .storage
+        .acquire()
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    event
+        .record(conn.as_mut())
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    drop(conn);
+    state.audit_sealer.notify();
+
+    Ok(())
+}
+
+fn db_error(e: db_node::Error) -> Status {
+    match e {
+        db_node::Error::Sqlx(sqlx::Error::RowNotFound) => Status::not_found("unknown quote"),
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl From<db_node::mint_quote::MintQuoteInfo> for Quote {
+    fn from(value: db_node::mint_quote::MintQuoteInfo) -> Self {
+        Self {
+            id: value.id.to_string(),
+            unit: value.unit,
+            amount: value.amount.into(),
+            fee: 0,
+            state: value.state.to_string(),
+            expiry: value.expiry,
+            request: value.request,
+        }
+    }
+}
+
+impl From<db_node::melt_quote::MeltQuoteInfo> for Quote {
+    fn from(value: db_node::melt_quote::MeltQuoteInfo) -> Self {
+        Self {
+            id: value.id.to_string(),
+            unit: value.unit,
+            amount: value.amount.into(),
+            fee: value.fee.into(),
+            state: value.state.to_string(),
+            expiry: value.expiry,
+            request: value.request,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Admin for GrpcState {
+    async fn set_mint_disabled(
+        &self,
+        request: Request<SetDisabledRequest>,
+    ) -> Result<Response<SetDisabledResponse>, Status> {
+        let disabled = request.into_inner().disabled;
+        self.nuts.write().await.nut04.disabled = disabled;
+
+        event!(name: "admin-set-mint-disabled", Level::WARN, disabled);
+        record_settings_change(self, AuditEvent::MintDisabled { disabled }).await?;
+
+        Ok(Response::new(SetDisabledResponse {}))
+    }
+
+    async fn set_melt_disabled(
+        &self,
+        request: Request<SetDisabledRequest>,
+    ) -> Result<Response<SetDisabledResponse>, Status> {
+        let disabled = request.into_inner().disabled;
+        self.nuts.write().await.nut05.disabled = disabled;
+
+        event!(name: "admin-set-melt-disabled", Level::WARN, disabled);
+        record_settings_change(self, AuditEvent::MeltDisabled { disabled }).await?;
+
+        Ok(Response::new(SetDisabledResponse {}))
+    }
+
+    async fn set_amount_limits(
+        &self,
+        request: Request<SetAmountLimitsRequest>,
+    ) -> Result<Response<SetAmountLimitsResponse>, Status> {
+        let request = request.into_inner();
+        let kind = parse_kind(request.kind)?;
+        let method = Method::from_str(&request.method)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let unit =
+            Unit::from_str(&request.unit).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let min_amount = request.min_amount.map(Amount::from);
+        let max_amount = request.max_amount.map(Amount::from);
+        if let (Some(min), Some(max)) = (min_amount, max_amount) {
+            if min > max {
+                return Err(Status::invalid_argument(
+                    "min amount cannot be greater than max amount",
+                ));
+            }
+        }
+
+        {
+            let mut nuts_write_lock = self.nuts.write().await;
+            match kind {
+                QuoteKind::QkMint => {
+                    let settings = nuts_write_lock
+                        .nut04
+                        .methods
+                        .iter_mut()
+                        .find(|s| s.method == method && s.unit == unit)
+                        .ok_or_else(|| {
+                            Status::not_found("no mint settings for this method and unit")
+                        })?;
+                    settings.min_amount = min_amount;
+                    settings.max_amount = max_amount;
+                }
+                QuoteKind::QkMelt => {
+                    let settings = nuts_write_lock
+                        .nut05
+                        .methods
+                        .iter_mut()
+                        .find(|s| s.method == method && s.unit == unit)
+                        .ok_or_else(|| {
+                            Status::not_found("no melt settings for this method and unit")
+                        })?;
+                    settings.min_amount = min_amount;
+                    settings.max_amount = max_amount;
+                }
+                QuoteKind::QkUnspecified => unreachable!("rejected by parse_kind"),
+            }
+        }
+
+        event!(
+            name: "admin-set-amount-limits",
+            Level::WARN,
+            kind = kind.as_str_name(),
+            method = %method,
+            unit = %unit,
+            min_amount = request.min_amount,
+            max_amount = request.max_amount,
+        );
+        record_settings_change(
+            self,
+            AuditEvent::AmountLimits {
+                kind: kind.as_str_name(),
+                method,
+                unit,
+                min_amount,
+                max_amount,
+            },
+        )
+        .await?;
+
+        Ok(Response::new(SetAmountLimitsResponse {}))
+    }
+
+    async fn list_quotes(
+        &self,
+        request: Request<ListQuotesRequest>,
+    ) -> Result<Response<ListQuotesResponse>, Status> {
+        let request = request.into_inner();
+        let kind = parse_kind(request.kind)?;
+        let limit = match request.limit {
+            0 => DEFAULT_LIST_LIMIT,
+            l => l.min(MAX_LIST_LIMIT),
+        }
+        .into();
+
+        let mut conn = self
+            .storage
+            .acquire()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let quotes = match kind {
+            QuoteKind::QkMint => {
+                let state = request
+                    .state
+                    .as_deref()
+                    .map(MintQuoteState::from_str)
+                    .transpose()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+                conn.list_mint_quotes(state, limit)
+                    .await
+                    .map_err(db_error)?
+                    .into_iter()
+                    .map(Quote::from)
+                    .collect()
+            }
+            QuoteKind::QkMelt => {
+                let state = request
+                    .state
+                    .as_deref()
+                    .map(MeltQuoteState::from_str)
+                    .transpose()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+                conn.list_melt_quotes(state, limit)
+                    .await
+                    .map_err(db_error)?
+                    .into_iter()
+                    .map(Quote::from)
+                    .collect()
+            }
+            QuoteKind::QkUnspecified => unreachable!("rejected by parse_kind"),
+        };
+
+        Ok(Response::new(ListQuotesResponse { quotes }))
+    }
+
+    async fn get_quote(
+        &self,
+        request: Request<GetQuoteRequest>,
+    ) -> Result<Response<Quote>, Status> {
+        let request = request.into_inner();
+        let kind = parse_kind(request.kind)?;
+        let quote_id = parse_quote_id(&request.quote)?;
+
+        let mut conn = self
+            .storage
+            .acquire()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let quote = match kind {
+            QuoteKind::QkMint => conn
+                .get_mint_quote_info(quote_id)
+                .await
+                .map_err(db_error)?
+                .into(),
+            QuoteKind::QkMelt => conn
+                .get_melt_quote_info(quote_id)
+                .await
+                .map_err(db_error)?
+                .into(),
+            QuoteKind::QkUnspecified => unreachable!("rejected by parse_kind"),
+        };
+
+        Ok(Response::new(quote))
+    }
+
+    async fn expire_quote(
+        &self,
+        request: Request<ExpireQuoteRequest>,
+    ) -> Result<Response<ExpireQuoteResponse>, Status> {
+        let request = request.into_inner();
+        let kind = parse_kind(request.kind)?;
+        let quote_id = parse_quote_id(&request.quote)?;
+
+        let mut conn = self
+            .storage
+            .acquire()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let expired = match kind {
+            QuoteKind::QkMint => conn.force_expire_mint_quote(quote_id).await,
+            QuoteKind::QkMelt => conn.force_expire_melt_quote(quote_id).await,
+            QuoteKind::QkUnspecified => unreachable!("rejected by parse_kind"),
+        }
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        if !expired {
+            return Err(Status::failed_precondition("no unpaid quote with this id"));
+        }
+
+        event!(
+            name: "admin-expire-quote",
+            Level::WARN,
+            kind = kind.as_str_name(),
+            quote_id = %quote_id,
+        );
+
+        Ok(Response::new(ExpireQuoteResponse {}))
+    }
+}
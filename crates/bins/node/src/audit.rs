@@ -0,0 +1,162 @@
+//! Events recorded in the audit log
+//!
+//! An event is inserted in the transaction of the operation it describes.
+//! Once that transaction is committed, the [`AuditSealer`] is notified,
+//! and a background task seals the events committed so far into the hash chain.
+//! See [`db_node::audit`].
+use std::{sync::Arc, time::Duration};
+
+use db_node::{SharedStorage, StorageConn};
+use nuts::Amount;
+#[cfg(feature = "keyset-rotation")]
+use nuts::nut02::KeysetId;
+use serde::Serialize;
+use starknet_types::Unit;
+use tokio::sync::Notify;
+use tracing::error;
+use uuid::Uuid;
+
+#[cfg(feature = "admin")]
+use crate::methods::Method;
+
+/// The payload of each event is its fields, serialized as JSON
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum AuditEvent {
+    Mint {
+        quote_id: Uuid,
+        amount: Amount,
+    },
+    Swap {
+        /// Amount swapped for each unit
+        amounts: Vec<(Unit, Amount)>,
+        n_inputs: usize,
+        n_outputs: usize,
+    },
+    Melt {
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+    },
+    /// The payment of the quote failed, the inputs of its melt were unspent
+    MeltReverted {
+        quote_id: Uuid,
+    },
+    /// The unused part of the fee reserve of the quote, returned to the wallet [NUT-08]
+    MeltChange {
+        quote_id: Uuid,
+        amount: Amount,
+    },
+    /// The inputs were converted into outputs of another unit,
+    /// the `surplus` they bought on top of the outputs being kept by the node
+    Exchange {
+        exchange_id: Uuid,
+        input_unit: Unit,
+        input_amount: Amount,
+        output_unit: Unit,
+        output_amount: Amount,
+        rate: String,
+        surplus: Amount,
+    },
+    #[cfg(feature = "keyset-rotation")]
+    KeysetRotation {
+        unit: Unit,
+        prev_keyset_id: KeysetId,
+        new_keyset_id: KeysetId,
+        final_expiry: Option<u64>,
+    },
+    #[cfg(feature = "admin")]
+    MintDisabled {
+        disabled: bool,
+    },
+    #[cfg(feature = "admin")]
+    MeltDisabled {
+        disabled: bool,
+    },
+    #[cfg(feature = "admin")]
+    AmountLimits {
+        kind: &'static str,
+        method: Method,
+        unit: Unit,
+        min_amount: Option<Amount>,
+        max_amount: Option<Amount>,
+    },
+}
+
+impl AuditEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::Mint { .. } => "mint",
+            AuditEvent::Swap { .. } => "swap",
+            AuditEvent::Melt { .. } => "melt",
+            AuditEvent::MeltReverted { .. } => "melt_reverted",
+            AuditEvent::MeltChange { .. } => "melt_change",
+            AuditEvent::Exchange { .. } => "exchange",
+            #[cfg(feature = "keyset-rotation")]
+            AuditEvent::KeysetRotation { .. } => "keyset_rotation",
+            #[cfg(feature = "admin")]
+            AuditEvent::MintDisabled { .. } => "settings.mint_disabled",
+            #[cfg(feature = "admin")]
+            AuditEvent::MeltDisabled { .. } => "settings.melt_disabled",
+            #[cfg(feature = "admin")]
+            AuditEvent::AmountLimits { .. } => "settings.amount_limits",
+        }
+    }
+
+    /// Insert the event, to be called in the transaction of the operation
+    pub async fn record(&self, conn: &mut dyn StorageConn) -> Result<(), db_node::Error> {
+        let payload = serde_json::to_string(self).expect("audit events should be serializable");
+
+        conn.insert_audit_event(self.kind(), &payload).await
+    }
+}
+
+/// Wakes up the task sealing the audit events
+///
+/// The operations only notify it, so that they don't each wait for the hash chain lock.
+/// The notifications received while sealing are coalesced into a single run.
+#[derive(Debug, Clone)]
+pub struct AuditSealer {
+    storage: SharedStorage,
+    notify: Arc<Notify>,
+}
+
+impl AuditSealer {
+    pub fn new(storage: SharedStorage) -> Self {
+        Self {
+            storage,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// To be called once the transaction recording an event is committed
+    pub fn notify(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Seals the events when notified, and at least once per `interval`
+    ///
+    /// The interval catches the events of other nodes sharing the database, and those of a failed run.
+    pub async fn run(self, interval: Duration) {
+        loop {
+            tokio::select! {
+                _ = self.notify.notified() => {}
+                _ = tokio::time::sleep(interval) => {}
+            }
+            self.seal().await;
+        }
+    }
+
+    /// Failures are only logged, the events left unsealed are sealed by the next run
+    async fn seal(&self) {
+        let res = async {
+            let mut conn = self.storage.acquire().await?;
+            conn.seal_audit_events().await
+        }
+        .await;
+
+        if let Err(err) = res {
+            error!(name: "audit-seal", error = %err);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Verification of the audit log hash chain, run by the `check-audit-log` subcommand
+use db_node::{
+    StorageConn,
+    audit::{ChainError, ChainVerifier},
+};
+use serde::Serialize;
+
+use crate::initialization::{self, connect_to_db, read_db_url};
+
+/// Number of events read from db at once
+const BATCH_SIZE: u64 = 1000;
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    /// False if the chain is broken
+    pub valid: bool,
+    /// Number of events verified, before the first break if any
+    pub verified_events: u64,
+    /// Events committed but not chained yet
+    pub unsealed_events: u64,
+    /// Hex encoded hash of the last verified event, to compare against a previously exported one
+    pub head: Option<String>,
+    pub error: Option<String>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn check_audit_log(conn: &mut dyn StorageConn) -> Result<AuditReport, db_node::Error> {
+    let unsealed_events = conn.count_unsealed_audit_events().await?;
+
+    let mut verifier = ChainVerifier::default();
+    let error = 'verify: loop {
+        let events = conn
+            .get_sealed_audit_events(verifier.len(), BATCH_SIZE)
+            .await?;
+        if events.is_empty() {
+            break None;
+        }
+
+        for event in &events {
+            if let Err(err) = verifier.verify(event) {
+                break 'verify Some(err);
+            }
+        }
+    };
+
+    Ok(AuditReport {
+        valid: error.is_none(),
+        verified_events: verifier.len(),
+        unsealed_events,
+        head: (!verifier.is_empty()).then(|| to_hex(&verifier.head())),
+        error: error.as_ref().map(ChainError::to_string),
+    })
+}
+
+/// Entry point of the `check-audit-log` subcommand
+///
+/// Prints the report on stdout, and returns whether the chain is valid.
+pub async fn run() -> Result<bool, initialization::Error> {
+    let storage = connect_to_db(&read_db_url()?).await?;
+    let mut conn = storage
+        .acquire()
+        .await
+        .map_err(initialization::Error::DbConnect)?;
+    let report = check_audit_log(conn.as_mut())
+        .await
+        .map_err(initialization::Error::CheckAuditLog)?;
+
+    println!(
+        "{}",
+        serde_json::to_string(&report).expect("the report should be serializable")
+    );
+
+    Ok(report.valid)
+}
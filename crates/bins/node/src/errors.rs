@@ -15,6 +15,10 @@ pub enum Error {
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
     Tonic(#[from] tonic::transport::Error),
+    #[error("REST server failed: {0}")]
+    Rest(#[source] std::io::Error),
     #[error(transparent)]
     Signer(#[from] tonic::Status),
+    #[error("failed to decode cached response: {0}")]
+    CachedResponseDecode(#[from] prost::DecodeError),
 }
@@ -0,0 +1,105 @@
+//! Conversion of ecash from one unit to another, at the rate given by a price oracle
+//!
+//! Only the pairs listed in the `[node.exchange]` table of the config file can be exchanged,
+//! each with its own spread and fee. See [`crate::initialization::ExchangeConfig`].
+pub mod oracle;
+
+use std::{collections::HashMap, sync::Arc};
+
+use num_traits::CheckedSub;
+use nuts::Amount;
+use oracle::{PriceOracle, Rate};
+use starknet_types::Unit;
+
+use crate::initialization::ExchangeConfig;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("exchanging {0} for {1} is not supported")]
+    PairNotSupported(Unit, Unit),
+    #[error("failed to get the exchange rate: {0}")]
+    Oracle(#[from] oracle::Error),
+    #[error("the spread of {0} basis points takes the whole rate")]
+    InvalidSpread(u16),
+    #[error("the converted amount doesn't fit in a u64")]
+    AmountTooBig,
+    #[error("the converted amount {0} doesn't cover the fee of {1}")]
+    AmountTooLow(Amount, Amount),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PairSettings {
+    spread_bps: u16,
+    fee: Amount,
+}
+
+/// The terms of an exchange at the current rate
+#[derive(Debug, Clone, Copy)]
+pub struct ExchangeQuote {
+    /// Spread included
+    pub rate: Rate,
+    /// In the output unit, already deducted from `output_amount`
+    pub fee: Amount,
+    /// The most that can be issued in the output unit
+    pub output_amount: Amount,
+}
+
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pairs: HashMap<(Unit, Unit), PairSettings>,
+    oracle: Arc<dyn PriceOracle>,
+}
+
+impl Exchange {
+    pub fn new(config: &ExchangeConfig) -> Self {
+        let pairs = config
+            .pairs
+            .iter()
+            .map(|pair| {
+                (
+                    (pair.from, pair.to),
+                    PairSettings {
+                        spread_bps: pair.spread_bps,
+                        fee: pair.fee,
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            pairs,
+            oracle: Arc::from(config.oracle.build()),
+        }
+    }
+
+    /// Price the conversion of `amount` of `from` into `to`
+    pub async fn quote(
+        &self,
+        from: Unit,
+        to: Unit,
+        amount: Amount,
+    ) -> Result<ExchangeQuote, Error> {
+        let pair = self
+            .pairs
+            .get(&(from, to))
+            .ok_or(Error::PairNotSupported(from, to))?;
+
+        let rate = self
+            .oracle
+            .rate(from, to)
+            .await?
+            .with_spread(pair.spread_bps)
+            .ok_or(Error::InvalidSpread(pair.spread_bps))?;
+        let converted = Amount::from(rate.convert(amount.into()).ok_or(Error::AmountTooBig)?);
+        let output_amount = converted
+            .checked_sub(&pair.fee)
+            .filter(|output_amount| *output_amount != Amount::ZERO)
+            .ok_or(Error::AmountTooLow(converted, pair.fee))?;
+
+        Ok(ExchangeQuote {
+            rate,
+            fee: pair.fee,
+            output_amount,
+        })
+    }
+}
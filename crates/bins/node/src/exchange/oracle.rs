@@ -0,0 +1,252 @@
+//! Where the exchange rates come from
+//!
+//! ```toml
+//! [node.exchange.oracle]
+//! kind = "static"
+//! rates = [{ from = "millistrk", to = "gwei", rate = "60.5" }]
+//! ```
+//!
+//! or, for rates updated by another process:
+//!
+//! ```toml
+//! [node.exchange.oracle]
+//! kind = "file"
+//! path = "/var/lib/node/rates.toml"
+//! ```
+//!
+//! the file holding the same `rates` array, read again for every exchange.
+use std::{collections::HashMap, fmt::Display, path::PathBuf, str::FromStr};
+
+use serde::Deserialize;
+use starknet_types::Unit;
+
+/// Max number of decimals of a configured rate
+const MAX_DECIMALS: u32 = 18;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no rate from {0} to {1}")]
+    UnknownPair(Unit, Unit),
+    #[error("failed to read the rates file: {0}")]
+    ReadFile(#[from] std::io::Error),
+    #[error("failed to deserialize the rates file: {0}")]
+    ParseFile(#[from] toml::de::Error),
+}
+
+/// The amount of the output unit worth one of the input unit
+///
+/// Kept as a fraction, so that the rates configured as decimals, and their inverses, are applied exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rate {
+    numerator: u128,
+    denominator: u128,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseRateError {
+    #[error("`{0}` is not a positive decimal number")]
+    Invalid(String),
+    #[error("`{0}` has more than {MAX_DECIMALS} decimals")]
+    TooManyDecimals(String),
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+impl Rate {
+    /// None if either part is zero
+    pub fn new(numerator: u128, denominator: u128) -> Option<Self> {
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+
+        Some(Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    /// The rate of the opposite conversion
+    pub fn inverse(self) -> Self {
+        Self {
+            numerator: self.denominator,
+            denominator: self.numerator,
+        }
+    }
+
+    /// Reduce the rate by `spread_bps` basis points
+    ///
+    /// None if the spread takes the whole rate, or the rate can't be represented anymore.
+    pub fn with_spread(self, spread_bps: u16) -> Option<Self> {
+        let kept_bps = 10_000u128.checked_sub(u128::from(spread_bps))?;
+
+        Self::new(
+            self.numerator.checked_mul(kept_bps)?,
+            self.denominator.checked_mul(10_000)?,
+        )
+    }
+
+    /// Convert `amount`, rounding down
+    ///
+    /// None if the result doesn't fit in a u64.
+    pub fn convert(&self, amount: u64) -> Option<u64> {
+        let converted = u128::from(amount).checked_mul(self.numerator)? / self.denominator;
+
+        u64::try_from(converted).ok()
+    }
+}
+
+impl FromStr for Rate {
+    type Err = ParseRateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseRateError::Invalid(s.to_string());
+
+        let (integer, decimals) = s.split_once('.').unwrap_or((s, ""));
+        if integer.is_empty()
+            || !integer.bytes().all(|b| b.is_ascii_digit())
+            || !decimals.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if decimals.len() > MAX_DECIMALS as usize {
+            return Err(ParseRateError::TooManyDecimals(s.to_string()));
+        }
+
+        let numerator = format!("{integer}{decimals}")
+            .parse::<u128>()
+            .map_err(|_| invalid())?;
+        let denominator = 10u128.pow(decimals.len() as u32);
+
+        Self::new(numerator, denominator).ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for Rate {
+    type Error = ParseRateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+/// Written as a decimal, truncated after [`MAX_DECIMALS`] decimals
+impl Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.numerator / self.denominator)?;
+
+        let mut remainder = self.numerator % self.denominator;
+        if remainder == 0 {
+            return Ok(());
+        }
+        let mut decimals = String::new();
+        while remainder != 0 && decimals.len() < MAX_DECIMALS as usize {
+            // The denominators stay far below u128::MAX / 10, this can't overflow
+            remainder *= 10;
+            decimals.push(char::from(b'0' + (remainder / self.denominator) as u8));
+            remainder %= self.denominator;
+        }
+
+        write!(f, ".{}", decimals.trim_end_matches('0'))
+    }
+}
+
+/// The price of one unit in another
+#[async_trait::async_trait]
+pub trait PriceOracle: Send + Sync + std::fmt::Debug {
+    /// The amount of `to` worth one `from`
+    async fn rate(&self, from: Unit, to: Unit) -> Result<Rate, Error>;
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateConfig {
+    pub from: Unit,
+    pub to: Unit,
+    /// The amount of `to` worth one `from`, as a decimal string
+    pub rate: Rate,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum OracleConfig {
+    /// Rates fixed in the config
+    Static { rates: Vec<RateConfig> },
+    /// Rates read from a file, every time they are needed
+    File { path: PathBuf },
+}
+
+impl OracleConfig {
+    pub fn build(&self) -> Box<dyn PriceOracle> {
+        match self {
+            OracleConfig::Static { rates } => Box::new(StaticPriceOracle::new(rates)),
+            OracleConfig::File { path } => Box::new(FilePriceOracle { path: path.clone() }),
+        }
+    }
+}
+
+/// Rates that never change
+///
+/// The rate of a pair is also used, inverted, for the opposite pair, unless that one is given too.
+#[derive(Debug, Clone, Default)]
+pub struct StaticPriceOracle {
+    rates: HashMap<(Unit, Unit), Rate>,
+}
+
+impl StaticPriceOracle {
+    pub fn new<'a>(rates: impl IntoIterator<Item = &'a RateConfig>) -> Self {
+        let mut oracle = Self::default();
+        for rate_config in rates {
+            oracle
+                .rates
+                .insert((rate_config.from, rate_config.to), rate_config.rate);
+            oracle
+                .rates
+                .entry((rate_config.to, rate_config.from))
+                .or_insert(rate_config.rate.inverse());
+        }
+
+        oracle
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceOracle for StaticPriceOracle {
+    async fn rate(&self, from: Unit, to: Unit) -> Result<Rate, Error> {
+        self.rates
+            .get(&(from, to))
+            .copied()
+            .ok_or(Error::UnknownPair(from, to))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RatesFile {
+    rates: Vec<RateConfig>,
+}
+
+/// Rates read from a file, that can be updated while the node runs
+#[derive(Debug, Clone)]
+pub struct FilePriceOracle {
+    path: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl PriceOracle for FilePriceOracle {
+    async fn rate(&self, from: Unit, to: Unit) -> Result<Rate, Error> {
+        let file_content = tokio::fs::read_to_string(&self.path).await?;
+        let rates_file: RatesFile = toml::from_str(&file_content)?;
+
+        StaticPriceOracle::new(&rates_file.rates)
+            .rate(from, to)
+            .await
+    }
+}
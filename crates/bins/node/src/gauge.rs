@@ -1,27 +1,35 @@
 //! Instrumentation for the amounts at the different steps of the deposit and withdrawal processes
 //!
 //! The values are represented as open-telemetry gauges, and read from db at a fixed time interval.
+//! The reserve ratio of each asset is polled the same way, from db and from the liquidity sources.
 use std::time::Duration;
 
+use db_node::SharedStorage;
 use opentelemetry::{KeyValue, metrics::Gauge};
-use sqlx::{PgPool, Pool, Postgres};
 use starknet_types::Unit;
 use tracing::error;
 
+use crate::liquidity_sources::LiquiditySources;
+
 pub struct DbMetricsObserver {
-    pool: Pool<Postgres>,
+    storage: SharedStorage,
     units: Vec<Unit>,
     gauge: Gauge<u64>,
 }
 
 impl DbMetricsObserver {
-    pub fn new(pool: PgPool, units: Vec<Unit>, gauge: Gauge<u64>) -> Self {
-        Self { pool, units, gauge }
+    pub fn new(storage: SharedStorage, units: Vec<Unit>, gauge: Gauge<u64>) -> Self {
+        Self {
+            storage,
+            units,
+            gauge,
+        }
     }
 
     async fn poll_metrics(&mut self) -> Result<(), anyhow::Error> {
-        let mut conn = self.pool.acquire().await?;
-        let gauges = db_node::gauge::get_all_gauge_metrics_by_units(&mut conn, &self.units).await?;
+        let units: Vec<String> = self.units.iter().map(|u| u.to_string()).collect();
+        let mut conn = self.storage.acquire().await?;
+        let gauges = conn.get_gauge_metrics(&units).await?;
 
         for (unit, metrics) in gauges {
             self.gauge.record(
@@ -80,3 +88,58 @@ pub async fn run_metrics_polling(mut observer: DbMetricsObserver, interval: Dura
         tokio::time::sleep(interval).await;
     }
 }
+
+pub struct ReservesObserver {
+    storage: SharedStorage,
+    liquidity_sources: LiquiditySources,
+    units: Vec<Unit>,
+    gauge: Gauge<f64>,
+}
+
+impl ReservesObserver {
+    pub fn new(
+        storage: SharedStorage,
+        liquidity_sources: LiquiditySources,
+        units: Vec<Unit>,
+        gauge: Gauge<f64>,
+    ) -> Self {
+        Self {
+            storage,
+            liquidity_sources,
+            units,
+            gauge,
+        }
+    }
+
+    async fn poll_reserve_ratio(&mut self) -> Result<(), anyhow::Error> {
+        let reserves = self
+            .liquidity_sources
+            .read_reserves(self.storage.as_ref(), &self.units)
+            .await?;
+
+        for asset_reserves in reserves {
+            // Nothing to back
+            let Some(ratio) = asset_reserves.ratio() else {
+                continue;
+            };
+            self.gauge.record(
+                ratio,
+                &[
+                    KeyValue::new("metric", "reserves.ratio"),
+                    KeyValue::new("asset", asset_reserves.asset.to_string()),
+                ],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn run_reserves_polling(mut observer: ReservesObserver, interval: Duration) {
+    loop {
+        if let Err(err) = observer.poll_reserve_ratio().await {
+            error!(name: "reserves-polling", error = %err);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -1,28 +1,35 @@
 use crate::{
+    audit::AuditSealer,
+    exchange::Exchange,
+    initialization::NodeInfoConfig,
     keyset_cache::CachedKeysetInfo,
+    limits::RequestLimits,
     liquidity_sources::LiquiditySources,
-    response_cache::{CachedResponse, InMemResponseCache, ResponseCache},
+    response_cache::{CachedResponse, SharedResponseCache},
+    routes::LiabilitiesCache,
 };
+use db_node::{SharedStorage, StorageConn};
 use node::{
-    AcknowledgeRequest, AcknowledgeResponse, CheckStateRequest, CheckStateResponse, GetKeysRequest,
-    GetKeysResponse, GetKeysetsRequest, GetKeysetsResponse, GetNodeInfoRequest, Keyset,
+    AcknowledgeRequest, AcknowledgeResponse, AssetReserves, CheckStateRequest, CheckStateResponse,
+    ExchangeEstimateRequest, ExchangeEstimateResponse, ExchangeRequest, ExchangeResponse,
+    GetKeysRequest, GetKeysResponse, GetKeysetsRequest, GetKeysetsResponse, GetNodeInfoRequest,
+    InclusionProof, Keyset, KeysetLiabilities, LiabilitiesRequest, LiabilitiesResponse,
     MeltQuoteRequest, MeltQuoteResponse, MeltQuoteStateRequest, MeltRequest, MeltResponse,
     MintQuoteRequest, MintQuoteResponse, MintRequest, MintResponse, Node, NodeInfoResponse,
-    ProofCheckState, QuoteStateRequest, RestoreRequest, RestoreResponse, SwapRequest, SwapResponse,
-    hash_melt_request, hash_mint_request, hash_swap_request,
+    ProofCheckState, QuoteStateRequest, ReservesRequest, ReservesResponse, RestoreRequest,
+    RestoreResponse, SwapRequest, SwapResponse, UnitEcash, hash_exchange_request,
+    hash_melt_request, hash_mint_request, hash_swap_request, inclusion_proof_to_path,
 };
 use nuts::{
     Amount, QuoteTTLConfig,
-    nut00::{BlindedMessage, Proof, secret::Secret},
+    nut00::{BlindSignature, BlindedMessage, Proof, secret::Secret},
     nut01::{self, PublicKey},
     nut02::{self, KeysetId},
-    nut06::{ContactInfo, NodeInfo, NodeVersion, NutsSettings},
+    nut06::NutsSettings,
     nut19::{CacheResponseKey, Route},
 };
-use signer::GetRootPubKeyRequest;
-use sqlx::PgPool;
-use starknet_types::Unit;
-use std::{str::FromStr, sync::Arc};
+use starknet_types::{StarknetU256, Unit};
+use std::{str::FromStr, sync::Arc, time::Duration};
 use thiserror::Error;
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status};
@@ -37,13 +44,19 @@ use crate::{
 
 #[derive(Debug, Clone)]
 pub struct GrpcState {
-    pub pg_pool: PgPool,
+    pub storage: SharedStorage,
     pub signer: SignerClient,
     pub keyset_cache: KeysetCache,
     pub nuts: NutsSettingsState,
     pub quote_ttl: Arc<QuoteTTLConfigState>,
-    pub liquidity_sources: LiquiditySources<Unit>,
-    pub response_cache: Arc<InMemResponseCache<(Route, u64), CachedResponse>>,
+    pub liquidity_sources: LiquiditySources,
+    pub response_cache: SharedResponseCache,
+    pub node_info: Arc<NodeInfoConfig>,
+    pub request_limits: RequestLimits,
+    /// None when no pair is configured
+    pub exchange: Option<Exchange>,
+    pub liabilities: Arc<LiabilitiesCache>,
+    pub audit_sealer: AuditSealer,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -55,25 +68,35 @@ pub enum InitKeysetError {
     #[error(transparent)]
     Nut02(#[from] nut02::Error),
     #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
+    Db(#[from] db_node::Error),
 }
 
 impl GrpcState {
     pub fn new(
-        pg_pool: PgPool,
+        storage: SharedStorage,
         signer_client: SignerClient,
         nuts_settings: NutsSettings<Method, Unit>,
         quote_ttl: QuoteTTLConfig,
-        liquidity_sources: LiquiditySources<Unit>,
+        liquidity_sources: LiquiditySources,
+        node_info: NodeInfoConfig,
+        response_cache: SharedResponseCache,
+        request_limits: RequestLimits,
+        exchange: Option<Exchange>,
+        liabilities_snapshot_ttl: Duration,
     ) -> Self {
         Self {
-            pg_pool,
+            audit_sealer: AuditSealer::new(storage.clone()),
+            storage,
             keyset_cache: Default::default(),
             nuts: Arc::new(RwLock::new(nuts_settings)),
             quote_ttl: Arc::new(quote_ttl.into()),
             signer: signer_client,
             liquidity_sources,
-            response_cache: Arc::new(InMemResponseCache::new(None)),
+            response_cache,
+            node_info: Arc::new(node_info),
+            request_limits,
+            exchange,
+            liabilities: Arc::new(LiabilitiesCache::new(liabilities_snapshot_ttl)),
         }
     }
 
@@ -83,7 +106,7 @@ impl GrpcState {
         index: u32,
         max_order: u32,
     ) -> Result<(), InitKeysetError> {
-        let mut insert_keysets_query_builder = db_node::InsertKeysetsQueryBuilder::new();
+        let mut new_keysets = Vec::with_capacity(units.len());
 
         for unit in units {
             let response = self
@@ -98,7 +121,7 @@ impl GrpcState {
             let response = response.into_inner();
             let keyset_id = KeysetId::from_bytes(&response.keyset_id)?;
 
-            insert_keysets_query_builder.add_row(keyset_id, unit, max_order, index);
+            new_keysets.push((keyset_id, unit.to_string(), max_order, index));
 
             self.keyset_cache
                 .insert_info(keyset_id, CachedKeysetInfo::new(true, *unit, max_order))
@@ -120,30 +143,78 @@ impl GrpcState {
                 .await;
         }
 
-        let mut conn = self.pg_pool.acquire().await?;
-        insert_keysets_query_builder.execute(&mut conn).await?;
+        let mut conn = self.storage.acquire().await?;
+        conn.insert_keysets(&new_keysets).await?;
 
         Ok(())
     }
 
-    pub fn get_cached_response(&self, cache_key: &CacheResponseKey) -> Option<CachedResponse> {
-        if let Some(cached_response) = self.response_cache.get(cache_key) {
-            return Some(cached_response);
-        }
-
-        None
+    pub async fn get_cached_response(
+        &self,
+        cache_key: &CacheResponseKey,
+    ) -> Result<Option<CachedResponse>, Status> {
+        self.response_cache
+            .get(cache_key)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))
     }
 
-    pub fn cache_response(
+    /// Caches the response of an operation as part of its db transaction
+    ///
+    /// If the cache doesn't live in the database, the response is returned instead,
+    /// to be passed to `cache_response` once the transaction is committed.
+    /// Either way, no response is cached for an operation that didn't happen,
+    /// and no operation happens without its response being cached.
+    pub async fn cache_response_in_tx(
         &self,
-        cache_key: (Route, u64),
+        tx: &mut dyn StorageConn,
+        cache_key: CacheResponseKey,
         response: CachedResponse,
-    ) -> Result<(), Status> {
-        self.response_cache
-            .insert(cache_key, response)
-            .map_err(|e| Status::internal(e.to_string()))?;
+    ) -> Result<Option<(CacheResponseKey, CachedResponse)>, db_node::Error> {
+        if self
+            .response_cache
+            .insert_in_tx(tx, &cache_key, &response)
+            .await?
+        {
+            Ok(None)
+        } else {
+            Ok(Some((cache_key, response)))
+        }
+    }
 
-        Ok(())
+    /// Caches a response returned by `cache_response_in_tx`, once its transaction is committed
+    ///
+    /// The operation already happened, so a failure is only logged.
+    pub async fn cache_response(&self, pending: Option<(CacheResponseKey, CachedResponse)>) {
+        if let Some((cache_key, response)) = pending {
+            if let Err(err) = self.response_cache.insert(cache_key, response).await {
+                tracing::error!(name: "response-cache-insert", error = %err);
+            }
+        }
+    }
+}
+
+/// Converts the signatures returned by an operation into their gRPC representation
+pub fn blind_signatures_to_grpc(promises: &[BlindSignature]) -> Vec<node::BlindSignature> {
+    promises
+        .iter()
+        .map(|p| node::BlindSignature {
+            amount: p.amount.into(),
+            keyset_id: p.keyset_id.to_bytes().to_vec(),
+            blind_signature: p.c.to_bytes().to_vec(),
+        })
+        .collect()
+}
+
+pub fn melt_response_to_grpc(response: &nuts::nut05::MeltResponse) -> MeltResponse {
+    MeltResponse {
+        state: response.state.into(),
+        transfer_ids: response.transfer_ids.clone().unwrap_or_default(),
+        change: response
+            .change
+            .as_deref()
+            .map(blind_signatures_to_grpc)
+            .unwrap_or_default(),
     }
 }
 
@@ -177,18 +248,21 @@ impl Node for GrpcState {
         _request: Request<GetKeysetsRequest>,
     ) -> Result<Response<GetKeysetsResponse>, Status> {
         let mut conn = self
-            .pg_pool
+            .storage
             .acquire()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        let keysets = db_node::keyset::get_keysets(&mut conn)
+        let keysets = conn
+            .get_keysets()
             .await
             .map_err(|e| Status::internal(e.to_string()))?
-            .map(|(id, unit, active)| Keyset {
+            .into_iter()
+            .map(|(id, unit, active, final_expiry)| Keyset {
                 id: id.to_vec(),
                 unit,
                 active,
+                final_expiry,
             })
             .collect();
 
@@ -203,17 +277,17 @@ impl Node for GrpcState {
         let request = request.into_inner();
 
         let mut db_conn = self
-            .pg_pool
+            .storage
             .acquire()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
         let keysets = match request.keyset_id {
             Some(keyset_id) => {
-                self.inner_keys_for_keyset_id(&mut db_conn, keyset_id)
+                self.inner_keys_for_keyset_id(db_conn.as_mut(), keyset_id)
                     .await?
             }
-            None => self.inner_keys_no_keyset_id(&mut db_conn).await?,
+            None => self.inner_keys_no_keyset_id(db_conn.as_mut()).await?,
         };
 
         Ok(Response::new(GetKeysResponse { keysets }))
@@ -228,21 +302,12 @@ impl Node for GrpcState {
 
         let cache_key = (Route::Swap, hash_swap_request(&swap_request));
         // Try to get from cache first
-        if let Some(CachedResponse::Swap(swap_response)) = self.get_cached_response(&cache_key) {
+        if let Some(CachedResponse::Swap(swap_response)) =
+            self.get_cached_response(&cache_key).await?
+        {
             return Ok(Response::new(swap_response));
         }
 
-        if swap_request.inputs.len() > 64 {
-            return Err(Status::invalid_argument(
-                "Too many inputs: maximum allowed is 64",
-            ));
-        }
-        if swap_request.outputs.len() > 64 {
-            return Err(Status::invalid_argument(
-                "Too many outputs: maximum allowed is 64",
-            ));
-        }
-
         if swap_request.inputs.is_empty() {
             return Err(Status::invalid_argument("Inputs cannot be empty"));
         }
@@ -278,23 +343,96 @@ impl Node for GrpcState {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        let promises = self.inner_swap(&inputs, &outputs).await?;
+        // The response is cached by `inner_swap`, along with the operation
+        let promises = self.inner_swap(&inputs, &outputs, Some(cache_key)).await?;
 
-        let swap_response = SwapResponse {
-            signatures: promises
-                .iter()
-                .map(|p| node::BlindSignature {
+        Ok(Response::new(SwapResponse {
+            signatures: blind_signatures_to_grpc(&promises),
+        }))
+    }
+
+    #[instrument]
+    async fn exchange_estimate(
+        &self,
+        exchange_estimate_request: Request<ExchangeEstimateRequest>,
+    ) -> Result<Response<ExchangeEstimateResponse>, Status> {
+        let exchange_estimate_request = exchange_estimate_request.into_inner();
+
+        let input_unit =
+            Unit::from_str(&exchange_estimate_request.input_unit).map_err(ParseGrpcError::Unit)?;
+        let output_unit =
+            Unit::from_str(&exchange_estimate_request.output_unit).map_err(ParseGrpcError::Unit)?;
+        let amount = Amount::from(exchange_estimate_request.amount);
+
+        let quote = self
+            .inner_exchange_estimate(input_unit, output_unit, amount)
+            .await?;
+
+        Ok(Response::new(ExchangeEstimateResponse {
+            amount: quote.output_amount.into(),
+            rate: quote.rate.to_string(),
+            fee: quote.fee.into(),
+        }))
+    }
+
+    #[instrument]
+    async fn exchange(
+        &self,
+        exchange_request: Request<ExchangeRequest>,
+    ) -> Result<Response<ExchangeResponse>, Status> {
+        let exchange_request = exchange_request.into_inner();
+
+        let cache_key = (Route::Exchange, hash_exchange_request(&exchange_request));
+        // Try to get from cache first
+        if let Some(CachedResponse::Exchange(exchange_response)) =
+            self.get_cached_response(&cache_key).await?
+        {
+            return Ok(Response::new(exchange_response));
+        }
+
+        if exchange_request.inputs.is_empty() {
+            return Err(Status::invalid_argument("Inputs cannot be empty"));
+        }
+        if exchange_request.outputs.is_empty() {
+            return Err(Status::invalid_argument("Outputs cannot be empty"));
+        }
+
+        let inputs = exchange_request
+            .inputs
+            .into_iter()
+            .map(|p| -> Result<Proof, ParseGrpcError> {
+                Ok(Proof {
                     amount: p.amount.into(),
-                    keyset_id: p.keyset_id.to_bytes().to_vec(),
-                    blind_signature: p.c.to_bytes().to_vec(),
+                    keyset_id: KeysetId::from_bytes(&p.keyset_id)
+                        .map_err(ParseGrpcError::KeysetId)?,
+                    secret: Secret::new(p.secret).map_err(ParseGrpcError::Secret)?,
+                    c: PublicKey::from_slice(&p.unblind_signature)
+                        .map_err(ParseGrpcError::PublicKey)?,
                 })
-                .collect(),
-        };
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = exchange_request
+            .outputs
+            .into_iter()
+            .map(|bm| -> Result<BlindedMessage, ParseGrpcError> {
+                Ok(BlindedMessage {
+                    amount: bm.amount.into(),
+                    keyset_id: KeysetId::from_bytes(&bm.keyset_id)
+                        .map_err(ParseGrpcError::KeysetId)?,
+                    blinded_secret: PublicKey::from_slice(&bm.blinded_secret)
+                        .map_err(ParseGrpcError::PublicKey)?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Store in cache
-        self.cache_response(cache_key, CachedResponse::Swap(swap_response.clone()))?;
+        let (promises, rate) = self
+            .inner_exchange(&inputs, &outputs, Some(cache_key))
+            .await?;
 
-        Ok(Response::new(swap_response))
+        Ok(Response::new(ExchangeResponse {
+            signatures: blind_signatures_to_grpc(&promises),
+            rate: rate.to_string(),
+        }))
     }
 
     #[instrument]
@@ -330,16 +468,12 @@ impl Node for GrpcState {
 
         let cache_key = (Route::Mint, hash_mint_request(&mint_request));
         // Try to get from cache first
-        if let Some(CachedResponse::Mint(mint_response)) = self.get_cached_response(&cache_key) {
+        if let Some(CachedResponse::Mint(mint_response)) =
+            self.get_cached_response(&cache_key).await?
+        {
             return Ok(Response::new(mint_response));
         }
 
-        if mint_request.outputs.len() > 64 {
-            return Err(Status::invalid_argument(
-                "Too many outputs: maximum allowed is 64",
-            ));
-        }
-
         let method = Method::from_str(&mint_request.method).map_err(ParseGrpcError::Method)?;
 
         if mint_request.outputs.is_empty() {
@@ -362,24 +496,14 @@ impl Node for GrpcState {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        let promises = self.inner_mint(method, quote_id, &outputs).await?;
-        let signatures = promises
-            .iter()
-            .map(|p| node::BlindSignature {
-                amount: p.amount.into(),
-                keyset_id: p.keyset_id.to_bytes().to_vec(),
-                blind_signature: p.c.to_bytes().to_vec(),
-            })
-            .collect::<Vec<_>>();
-
-        let mint_response = MintResponse {
-            signatures: signatures.clone(),
-        };
-
-        // Store in cache
-        self.cache_response(cache_key, CachedResponse::Mint(mint_response.clone()))?;
+        // The response is cached by `inner_mint`, along with the operation
+        let promises = self
+            .inner_mint(method, quote_id, &outputs, Some(cache_key))
+            .await?;
 
-        Ok(Response::new(mint_response))
+        Ok(Response::new(MintResponse {
+            signatures: blind_signatures_to_grpc(&promises),
+        }))
     }
 
     async fn melt_quote(
@@ -402,6 +526,7 @@ impl Node for GrpcState {
             state: response.state.into(),
             expiry: response.expiry,
             transfer_ids: Vec::default(),
+            fee_reserve: response.fee_reserve.into(),
         }))
     }
 
@@ -415,16 +540,12 @@ impl Node for GrpcState {
         let cache_key = (Route::Melt, hash_melt_request(&melt_request));
 
         // Try to get from cache first
-        if let Some(CachedResponse::Melt(melt_response)) = self.get_cached_response(&cache_key) {
+        if let Some(CachedResponse::Melt(melt_response)) =
+            self.get_cached_response(&cache_key).await?
+        {
             return Ok(Response::new(melt_response));
         }
 
-        if melt_request.inputs.len() > 64 {
-            return Err(Status::invalid_argument(
-                "Too many inputs: maximum allowed is 64",
-            ));
-        }
-
         if melt_request.inputs.is_empty() {
             return Err(Status::invalid_argument("Inputs cannot be empty"));
         }
@@ -446,18 +567,26 @@ impl Node for GrpcState {
                 })
             })
             .collect::<Result<Vec<_>, _>>()?;
+        let outputs = melt_request
+            .outputs
+            .into_iter()
+            .map(|bm| -> Result<BlindedMessage, ParseGrpcError> {
+                Ok(BlindedMessage {
+                    amount: bm.amount.into(),
+                    keyset_id: KeysetId::from_bytes(&bm.keyset_id)
+                        .map_err(ParseGrpcError::KeysetId)?,
+                    blinded_secret: PublicKey::from_slice(&bm.blinded_secret)
+                        .map_err(ParseGrpcError::PublicKey)?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let response = self.inner_melt(method, quote_id, &inputs).await?;
-
-        let melt_response = MeltResponse {
-            state: response.state.into(),
-            transfer_ids: response.transfer_ids.unwrap_or_default(),
-        };
-
-        // Store in cache
-        self.cache_response(cache_key, CachedResponse::Melt(melt_response.clone()))?;
+        // The response is cached by `inner_melt`, along with the quote final state
+        let response = self
+            .inner_melt(method, quote_id, &inputs, &outputs, Some(cache_key))
+            .await?;
 
-        Ok(Response::new(melt_response))
+        Ok(Response::new(melt_response_to_grpc(&response)))
     }
 
     #[instrument]
@@ -501,6 +630,7 @@ impl Node for GrpcState {
             state: node::MeltQuoteState::from(response.state).into(),
             expiry: response.expiry,
             transfer_ids: response.transfer_ids.unwrap_or_default(),
+            fee_reserve: response.fee_reserve.into(),
         }))
     }
 
@@ -509,38 +639,7 @@ impl Node for GrpcState {
         &self,
         _node_info_request: Request<GetNodeInfoRequest>,
     ) -> Result<Response<NodeInfoResponse>, Status> {
-        let nuts_config = {
-            let nuts_read_lock = self.nuts.read().await;
-            nuts_read_lock.clone()
-        };
-        let pub_key = self
-            .signer
-            .clone()
-            .get_root_pub_key(Request::new(GetRootPubKeyRequest {}))
-            .await?
-            .into_inner()
-            .root_pubkey;
-        let node_info = NodeInfo {
-            name: Some("Paynet Test Node".to_string()),
-            pubkey: Some(
-                PublicKey::from_str(&pub_key).map_err(|e| Status::internal(e.to_string()))?,
-            ),
-            version: Some(NodeVersion {
-                name: "some_name".to_string(),
-                version: "0.0.0".to_string(),
-            }),
-            description: Some("A test node".to_string()),
-            description_long: Some("This is a longer description of the test node.".to_string()),
-            contact: Some(vec![ContactInfo {
-                method: "some_method".to_string(),
-                info: "some_info".to_string(),
-            }]),
-            nuts: nuts_config,
-            icon_url: Some("http://example.com/icon.png".to_string()),
-            urls: Some(vec!["http://example.com".to_string()]),
-            motd: Some("Welcome to the node!".to_string()),
-            time: Some(std::time::UNIX_EPOCH.elapsed().unwrap().as_secs()),
-        };
+        let node_info = self.inner_node_info().await?;
 
         let node_info_str =
             serde_json::to_string(&node_info).map_err(|e| Status::internal(e.to_string()))?;
@@ -564,10 +663,10 @@ impl Node for GrpcState {
         let cache_key = (path, request_hash);
 
         // check if the request is already in the cache, if so, remove it
-        let exist = self.response_cache.get(&cache_key);
-        if exist.is_some() {
-            self.response_cache.remove(&cache_key);
-        }
+        self.response_cache
+            .remove(&cache_key)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
 
         Ok(Response::new(AcknowledgeResponse {}))
     }
@@ -653,4 +752,94 @@ impl Node for GrpcState {
 
         Ok(Response::new(restore_response))
     }
+
+    async fn liabilities(
+        &self,
+        liabilities_request: Request<LiabilitiesRequest>,
+    ) -> Result<Response<LiabilitiesResponse>, Status> {
+        let liabilities_request = liabilities_request.into_inner();
+
+        if liabilities_request.blinded_secrets.len() + liabilities_request.ys.len() > 100 {
+            return Err(Status::invalid_argument(
+                "Too many inclusion proofs requested: maximum allowed is 100",
+            ));
+        }
+
+        let parse_keys = |keys: &[Vec<u8>]| {
+            keys.iter()
+                .map(|k| PublicKey::from_slice(k).map_err(ParseGrpcError::PublicKey))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let blinded_secrets = parse_keys(&liabilities_request.blinded_secrets)?;
+        let ys = parse_keys(&liabilities_request.ys)?;
+
+        let liabilities = self.inner_liabilities(&blinded_secrets, &ys).await?;
+
+        let to_inclusion_proof =
+            |proof: Option<(KeysetId, nuts::liabilities::InclusionProof)>| match proof {
+                Some((keyset_id, proof)) => InclusionProof {
+                    found: true,
+                    keyset_id: keyset_id.to_bytes().to_vec(),
+                    path: inclusion_proof_to_path(proof),
+                },
+                None => InclusionProof::default(),
+            };
+
+        Ok(Response::new(LiabilitiesResponse {
+            keysets: liabilities
+                .keysets
+                .into_iter()
+                .map(|keyset| KeysetLiabilities {
+                    keyset_id: keyset.keyset_id.to_bytes().to_vec(),
+                    unit: keyset.unit,
+                    issued_root: Some(keyset.issued_root.into()),
+                    issued_count: keyset.issued_count,
+                    burned_root: Some(keyset.burned_root.into()),
+                    burned_count: keyset.burned_count,
+                })
+                .collect(),
+            issued_proofs: liabilities
+                .issued_proofs
+                .into_iter()
+                .map(to_inclusion_proof)
+                .collect(),
+            burned_proofs: liabilities
+                .burned_proofs
+                .into_iter()
+                .map(to_inclusion_proof)
+                .collect(),
+        }))
+    }
+
+    #[instrument]
+    async fn reserves(
+        &self,
+        _reserves_request: Request<ReservesRequest>,
+    ) -> Result<Response<ReservesResponse>, Status> {
+        let reserves = self.inner_reserves().await?;
+
+        Ok(Response::new(ReservesResponse {
+            assets: reserves
+                .into_iter()
+                .map(|asset_reserves| AssetReserves {
+                    ratio: asset_reserves.ratio(),
+                    asset: asset_reserves.asset.to_string(),
+                    balance: StarknetU256::from(asset_reserves.balance)
+                        .to_bytes_be()
+                        .to_vec(),
+                    units: asset_reserves
+                        .units
+                        .into_iter()
+                        .map(|(unit, outstanding_ecash)| UnitEcash {
+                            unit: unit.to_string(),
+                            outstanding_ecash: outstanding_ecash.into(),
+                        })
+                        .collect(),
+                    outstanding_ecash: StarknetU256::from(asset_reserves.outstanding_ecash)
+                        .to_bytes_be()
+                        .to_vec(),
+                })
+                .collect(),
+        }))
+    }
 }
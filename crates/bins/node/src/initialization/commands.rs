@@ -1,10 +1,24 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct ProgramArguments {
     #[arg(long)]
     pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the consistency of the node database, and print the report as JSON
+    ///
+    /// Exits with a non-zero status if an inconsistency is found.
+    CheckLedger,
+    /// Verify the hash chain of the audit log, and print the report as JSON
+    ///
+    /// Exits with a non-zero status if the chain is broken.
+    CheckAuditLog,
 }
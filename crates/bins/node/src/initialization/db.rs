@@ -1,21 +1,40 @@
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use std::sync::Arc;
+
+use db_node::{SharedStorage, postgres::PgStorage, sqlite::SqliteStorage};
+use sqlx::postgres::PgPoolOptions;
 
 use super::Error;
 
 /// Connect to the node database
-pub async fn connect_to_db_and_run_migrations(pg_url: &str) -> Result<PgPool, Error> {
-    let pool = PgPoolOptions::new()
-        // Arbitraries values, to be refined later base on real life perf measurement
-        .max_connections(18)
-        .min_connections(6)
-        .acquire_slow_level(log::LevelFilter::Warn)
-        .connect(pg_url)
-        .await
-        .map_err(Error::DbConnect)?;
+///
+/// `db_url` is either a Postgres url or, for single instance deployments, a `sqlite:` one.
+pub async fn connect_to_db(db_url: &str) -> Result<SharedStorage, Error> {
+    let storage: SharedStorage = if db_url.starts_with("sqlite:") {
+        Arc::new(
+            SqliteStorage::connect(db_url)
+                .await
+                .map_err(Error::DbConnect)?,
+        )
+    } else {
+        let pool = PgPoolOptions::new()
+            // Arbitraries values, to be refined later base on real life perf measurement
+            .max_connections(18)
+            .min_connections(6)
+            .acquire_slow_level(log::LevelFilter::Warn)
+            .connect(db_url)
+            .await
+            .map_err(|e| Error::DbConnect(e.into()))?;
+        Arc::new(PgStorage::new(pool))
+    };
+
+    Ok(storage)
+}
+
+/// Connect to the node database and bring its schema up to date
+pub async fn connect_to_db_and_run_migrations(db_url: &str) -> Result<SharedStorage, Error> {
+    let storage = connect_to_db(db_url).await?;
 
-    db_node::run_migrations(&pool)
-        .await
-        .map_err(Error::DbMigrate)?;
+    storage.run_migrations().await.map_err(Error::DbMigrate)?;
 
-    Ok(pool)
+    Ok(storage)
 }
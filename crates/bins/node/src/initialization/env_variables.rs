@@ -10,18 +10,29 @@ pub fn read_env_variables() -> Result<EnvVariables, Error> {
             .inspect_err(|e| tracing::error!("dotenvy initialization failed: {e}"));
     }
 
-    let pg_url = std::env::var("PG_URL").map_err(|e| Error::Env("PG_URL", e))?;
+    let db_url = read_db_url()?;
     let signer_url = std::env::var("SIGNER_URL").map_err(|e| Error::Env("SIGNER_URL", e))?;
     let grpc_port = std::env::var("GRPC_PORT")
         .map_err(|e| Error::Env("GRPC_PORT", e))?
         .parse()
         .map_err(Error::ParseInt)?;
-    let quote_ttl = match std::env::var("QUOTE_TTL") {
+    let quote_ttl = read_optional_u64("QUOTE_TTL")?;
+    let rest_port = match std::env::var("REST_PORT") {
         Ok(v) => Some(v.parse().map_err(Error::ParseInt)?),
         Err(VarError::NotPresent) => None,
-        Err(e) => return Err(Error::Env("QUOTE_TTL", e)),
+        Err(e) => return Err(Error::Env("REST_PORT", e)),
     };
 
+    #[cfg(feature = "keyset-rotation")]
+    let keyset_rotation_interval = read_optional_u64("KEYSET_ROTATION_INTERVAL")?;
+    #[cfg(feature = "keyset-rotation")]
+    let keyset_rotation_max_signatures = read_optional_u64("KEYSET_ROTATION_MAX_SIGNATURES")?;
+    #[cfg(feature = "keyset-rotation")]
+    let keyset_final_expiry_delay = read_optional_u64("KEYSET_FINAL_EXPIRY_DELAY")?;
+
+    #[cfg(feature = "admin")]
+    let admin_token = std::env::var("ADMIN_TOKEN").map_err(|e| Error::Env("ADMIN_TOKEN", e))?;
+
     #[cfg(feature = "tls")]
     let tls_cert_path =
         std::env::var("TLS_CERT_PATH").map_err(|e| Error::Env("TLS_CERT_PATH", e))?;
@@ -29,10 +40,19 @@ pub fn read_env_variables() -> Result<EnvVariables, Error> {
     let tls_key_path = std::env::var("TLS_KEY_PATH").map_err(|e| Error::Env("TLS_KEY_PATH", e))?;
 
     Ok(EnvVariables {
-        pg_url,
+        db_url,
         signer_url,
         grpc_port,
+        rest_port,
         quote_ttl,
+        #[cfg(feature = "keyset-rotation")]
+        keyset_rotation_interval,
+        #[cfg(feature = "keyset-rotation")]
+        keyset_rotation_max_signatures,
+        #[cfg(feature = "keyset-rotation")]
+        keyset_final_expiry_delay,
+        #[cfg(feature = "admin")]
+        admin_token,
         #[cfg(feature = "tls")]
         tls_cert_path,
         #[cfg(feature = "tls")]
@@ -40,12 +60,44 @@ pub fn read_env_variables() -> Result<EnvVariables, Error> {
     })
 }
 
+/// Read the url of the node database
+pub fn read_db_url() -> Result<String, Error> {
+    // `PG_URL` is still accepted for deployments predating the SQLite backend
+    match std::env::var("DB_URL") {
+        Err(VarError::NotPresent) => std::env::var("PG_URL").map_err(|e| Error::Env("DB_URL", e)),
+        res => res.map_err(|e| Error::Env("DB_URL", e)),
+    }
+}
+
+fn read_optional_u64(name: &'static str) -> Result<Option<u64>, Error> {
+    match std::env::var(name) {
+        Ok(v) => Ok(Some(v.parse().map_err(Error::ParseInt)?)),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(e) => Err(Error::Env(name, e)),
+    }
+}
+
 #[derive(Debug)]
 pub struct EnvVariables {
-    pub pg_url: String,
+    /// Either a `postgres://` or a `sqlite:` url
+    pub db_url: String,
     pub signer_url: String,
     pub grpc_port: u16,
+    /// Port of the Cashu REST gateway, not served if unset
+    pub rest_port: Option<u16>,
     pub quote_ttl: Option<u64>,
+    /// Seconds after which an active keyset is automatically rotated
+    #[cfg(feature = "keyset-rotation")]
+    pub keyset_rotation_interval: Option<u64>,
+    /// Number of signatures after which an active keyset is automatically rotated
+    #[cfg(feature = "keyset-rotation")]
+    pub keyset_rotation_max_signatures: Option<u64>,
+    /// Seconds after an automatic rotation at which the previous keyset is retired
+    #[cfg(feature = "keyset-rotation")]
+    pub keyset_final_expiry_delay: Option<u64>,
+    /// Bearer token expected by the admin service
+    #[cfg(feature = "admin")]
+    pub admin_token: String,
     #[cfg(feature = "tls")]
     pub tls_cert_path: String,
     #[cfg(feature = "tls")]
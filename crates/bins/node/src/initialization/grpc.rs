@@ -1,50 +1,128 @@
+#[cfg(feature = "admin")]
+use node::AdminServer;
 #[cfg(feature = "keyset-rotation")]
 use node::KeysetRotationServiceServer;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower::ServiceBuilder;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_otel::trace;
 use tracing::instrument;
 
+use db_node::SharedStorage;
 use futures::TryFutureExt;
+use http::HeaderName;
 use node::NodeServer;
 use nuts::QuoteTTLConfig;
 use signer::SignerClient;
-use sqlx::Postgres;
-use starknet_types::Unit;
 use tonic::{service::LayerExt, transport::Channel};
 
-use crate::{grpc_service::GrpcState, liquidity_sources::LiquiditySources};
+use crate::{
+    exchange::Exchange,
+    grpc_service::GrpcState,
+    limits::{Grpc, RateLimitLayer, RateLimiter, RequestLimits},
+    liquidity_sources::LiquiditySources,
+    response_cache::{DbResponseCache, InMemResponseCache, SharedResponseCache},
+};
 
-use super::{Error, env_variables::EnvVariables};
+use super::{Error, NodeConfig, ResponseCacheBackend, env_variables::EnvVariables};
+
+const RESPONSE_CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const RATE_LIMITER_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const AUDIT_SEAL_INTERVAL: Duration = Duration::from_secs(10);
+const CORS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
 #[instrument]
 pub async fn launch_tonic_server_task(
-    pg_pool: sqlx::Pool<Postgres>,
+    storage: SharedStorage,
     signer_client: SignerClient<trace::Grpc<Channel>>,
-    liquidity_sources: LiquiditySources<Unit>,
+    liquidity_sources: LiquiditySources,
     env_vars: EnvVariables,
+    node_config: NodeConfig,
 ) -> Result<(SocketAddr, impl Future<Output = Result<(), crate::Error>>), super::Error> {
-    let nuts_settings = super::nuts_settings::nuts_settings();
-    let ttl = env_vars.quote_ttl.unwrap_or(3600);
+    let nuts_settings = super::nuts_settings::nuts_settings(&node_config, &liquidity_sources);
+    let (mint_ttl, melt_ttl) = node_config.quote_ttls(env_vars.quote_ttl);
+    let units = node_config.units();
+    let response_cache_ttl = node_config.ttl.response_cache.map(Duration::from_secs);
+    let response_cache: SharedResponseCache = match node_config.response_cache {
+        ResponseCacheBackend::Database => {
+            let cache = DbResponseCache::new(storage.clone(), response_cache_ttl);
+            let _handle = tokio::spawn(cache.clone().run_sweeper(RESPONSE_CACHE_SWEEP_INTERVAL));
+            Arc::new(cache)
+        }
+        ResponseCacheBackend::Memory => {
+            let cache = Arc::new(InMemResponseCache::new(
+                response_cache_ttl,
+                node_config.response_cache_max_entries(),
+            ));
+            let _handle = tokio::spawn(cache.clone().run_sweeper(RESPONSE_CACHE_SWEEP_INTERVAL));
+            cache
+        }
+    };
+    let request_limits = RequestLimits {
+        max_inputs: node_config.max_inputs(),
+        max_outputs: node_config.max_outputs(),
+    };
+    let rate_limiter = Arc::new(RateLimiter::new(node_config.limits.rate.clone()));
+    let _handle = tokio::spawn(
+        rate_limiter
+            .clone()
+            .run_sweeper(RATE_LIMITER_SWEEP_INTERVAL),
+    );
+    let exchange = node_config.exchange.as_ref().map(Exchange::new);
     let grpc_state = GrpcState::new(
-        pg_pool,
+        storage,
         signer_client,
         nuts_settings,
-        QuoteTTLConfig {
-            mint_ttl: ttl,
-            melt_ttl: ttl,
-        },
+        QuoteTTLConfig { mint_ttl, melt_ttl },
         liquidity_sources,
+        node_config.info,
+        response_cache,
+        request_limits,
+        exchange,
+        node_config.liabilities_snapshot_ttl(),
     );
     let address = format!("[::0]:{}", env_vars.grpc_port)
         .parse()
         .map_err(Error::InvalidGrpcAddress)?;
 
+    let _handle = tokio::spawn(grpc_state.audit_sealer.clone().run(AUDIT_SEAL_INTERVAL));
+
     // TODO: take into account past keyset rotations
     // init node shared
-    grpc_state
-        .init_first_keysets(&[Unit::MilliStrk], 0, 32)
-        .await?;
+    grpc_state.init_first_keysets(&units, 0, 32).await?;
+
+    #[cfg(feature = "keyset-rotation")]
+    {
+        use crate::keyset_rotator::{KeysetRotationPolicy, KeysetRotator, run_keyset_rotator};
+        use db_node::leader::run_as_leader;
+
+        let policy = KeysetRotationPolicy {
+            interval: env_vars.keyset_rotation_interval.map(Duration::from_secs),
+            max_signatures: env_vars.keyset_rotation_max_signatures,
+            final_expiry_delay: env_vars.keyset_final_expiry_delay.map(Duration::from_secs),
+        };
+        if policy.is_enabled() {
+            let state = grpc_state.clone();
+            let _handle = tokio::spawn(run_as_leader(
+                grpc_state.storage.clone(),
+                "keyset-rotator",
+                move || {
+                    run_keyset_rotator(
+                        KeysetRotator::new(state.clone(), policy.clone()),
+                        Duration::from_secs(60),
+                    )
+                },
+            ));
+        }
+
+        // The keysets may be rotated by another node
+        let _handle = tokio::spawn(
+            grpc_state
+                .keyset_cache
+                .clone()
+                .run_refresh(grpc_state.storage.clone(), Duration::from_secs(10)),
+        );
+    }
 
     // init health reporter service
     let health_service = {
@@ -54,9 +132,22 @@ pub async fn launch_tonic_server_task(
         health_reporter
             .set_serving::<KeysetRotationServiceServer<GrpcState>>()
             .await;
+        #[cfg(feature = "admin")]
+        health_reporter
+            .set_serving::<AdminServer<GrpcState>>()
+            .await;
 
         health_service
     };
+    let reflection_service = {
+        let builder = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(node::FILE_DESCRIPTOR_SET)
+            .with_service_name("node.Node");
+        #[cfg(feature = "keyset-rotation")]
+        let builder = builder.with_service_name("keyset_rotation.KeysetRotationService");
+
+        builder.build_v1()?
+    };
     let optl_layer = tower_otel::trace::GrpcLayer::server(tracing::Level::INFO);
     let meter = opentelemetry::global::meter(env!("CARGO_PKG_NAME"));
 
@@ -65,19 +156,36 @@ pub async fn launch_tonic_server_task(
         .layer(optl_layer.clone())
         .named_layer(KeysetRotationServiceServer::new(grpc_state.clone()));
 
+    #[cfg(feature = "admin")]
+    let admin_service =
+        ServiceBuilder::new()
+            .layer(optl_layer.clone())
+            .named_layer(AdminServer::with_interceptor(
+                grpc_state.clone(),
+                crate::admin::AdminAuthInterceptor::new(&env_vars.admin_token),
+            ));
+
     let node_service = ServiceBuilder::new()
         .layer(optl_layer)
         .named_layer(NodeServer::new(grpc_state.clone()));
 
     let tonic_future = {
+        // gRPC-Web requests are sent over HTTP/1.1 by browsers
         let mut tonic_server = tonic::transport::Server::builder()
-            .layer(tower_otel::metrics::HttpLayer::server(&meter));
+            .accept_http1(true)
+            .layer(tower_otel::metrics::HttpLayer::server(&meter))
+            .layer(cors_layer())
+            .layer(tonic_web::GrpcWebLayer::new())
+            .layer(RateLimitLayer::<Grpc>::new(rate_limiter.clone()));
 
         let router = tonic_server
             .add_service(health_service)
+            .add_service(reflection_service)
             .add_service(node_service);
         #[cfg(feature = "keyset-rotation")]
         let router = router.add_service(keyset_rotation_service);
+        #[cfg(feature = "admin")]
+        let router = router.add_service(admin_service);
 
         // create future
         #[cfg(not(feature = "tls"))]
@@ -94,7 +202,52 @@ pub async fn launch_tonic_server_task(
         future
     };
 
-    Ok((address, tonic_future))
+    let rest_future = {
+        let rest_listener = match env_vars.rest_port {
+            Some(port) => {
+                let listener = tokio::net::TcpListener::bind(("::", port))
+                    .await
+                    .map_err(Error::RestBind)?;
+                tracing::trace!(name: "rest-listen", port);
+                Some(listener)
+            }
+            None => None,
+        };
+
+        async move {
+            match rest_listener {
+                Some(listener) => axum::serve(
+                    listener,
+                    crate::rest::router(grpc_state, rate_limiter)
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await
+                .map_err(crate::Error::Rest),
+                None => Ok(()),
+            }
+        }
+    };
+
+    let future = async move {
+        tokio::try_join!(tonic_future, rest_future)?;
+        Ok(())
+    };
+
+    Ok((address, future))
+}
+
+/// Let browser wallets, whatever their origin, call the node through gRPC-Web
+fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::any())
+        .allow_methods([http::Method::POST])
+        .allow_headers(Any)
+        .expose_headers([
+            HeaderName::from_static("grpc-status"),
+            HeaderName::from_static("grpc-message"),
+            HeaderName::from_static("grpc-status-details-bin"),
+        ])
+        .max_age(CORS_MAX_AGE)
 }
 
 #[cfg(feature = "tls")]
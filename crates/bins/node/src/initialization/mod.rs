@@ -1,10 +1,15 @@
 mod commands;
-pub use commands::ProgramArguments;
+pub use commands::{Command, ProgramArguments};
 mod env_variables;
-pub use env_variables::read_env_variables;
+pub use env_variables::{read_db_url, read_env_variables};
 mod db;
+mod node_config;
 mod nuts_settings;
-pub use db::connect_to_db_and_run_migrations;
+pub use db::{connect_to_db, connect_to_db_and_run_migrations};
+pub use node_config::{
+    ExchangeConfig, NodeConfig, NodeInfoConfig, RateLimitConfig, ResponseCacheBackend,
+    TokenBucketConfig, read_node_config,
+};
 mod signer_client;
 pub use signer_client::connect_to_signer;
 mod grpc;
@@ -15,9 +20,13 @@ use crate::grpc_service::InitKeysetError;
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Failed to connect to database: {0}")]
-    DbConnect(#[source] sqlx::Error),
+    DbConnect(#[source] db_node::Error),
     #[error("Failed to run the database migration: {0}")]
-    DbMigrate(#[source] sqlx::migrate::MigrateError),
+    DbMigrate(#[source] db_node::Error),
+    #[error("Failed to check the ledger: {0}")]
+    CheckLedger(#[source] db_node::Error),
+    #[error("Failed to check the audit log: {0}")]
+    CheckAuditLog(#[source] db_node::Error),
     #[error("Failed to read environment variable `{0}`: {1}")]
     Env(&'static str, #[source] std::env::VarError),
     #[error(transparent)]
@@ -31,8 +40,14 @@ pub enum Error {
     OpenSSL(#[from] openssl::error::ErrorStack),
     #[error("failed to bind gRPC server to port: {0}")]
     Bind(#[from] std::io::Error),
+    #[error("failed to bind REST server to port: {0}")]
+    RestBind(#[source] std::io::Error),
+    #[error("failed to build the reflection service: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
     #[error("failed to init first keysets: {0}")]
     InitKeysets(#[from] InitKeysetError),
+    #[error("invalid node config: {0}")]
+    NodeConfig(#[from] node_config::Error),
     #[error("invalid signer uri: {0}")]
     Uri(#[from] http::uri::InvalidUri),
 }
@@ -0,0 +1,648 @@
+//! Node configuration
+//!
+//! Read from the `[node]` table of the file passed with `--config`, next to the liquidity sources settings.
+//! Every field is optional, the defaults describing a local test node.
+//! The top-level `[[assets]]` and `[[units]]` tables of the same file are loaded into the
+//! [`starknet_types::registry`] first, so that the methods can use the units they define.
+//!
+//! ```toml
+//! [node]
+//! response_cache = "memory"
+//! response_cache_max_entries = 10000
+//!
+//! [node.info]
+//! name = "My node"
+//! motd = "Welcome!"
+//! contact = [{ method = "email", info = "admin@example.com" }]
+//!
+//! [node.limits]
+//! max_inputs = 64
+//! max_outputs = 64
+//!
+//! [node.limits.rate]
+//! default = { per_minute = 600, burst = 20 }
+//! rpcs = { MintQuote = { per_minute = 10, burst = 5 } }
+//!
+//! [node.ttl]
+//! mint_quote = 3600
+//! melt_quote = 600
+//! response_cache = 300
+//! quote_archival = 2592000
+//! liabilities_snapshot = 60
+//!
+//! [[node.mint.methods]]
+//! method = "starknet"
+//! unit = "millistrk"
+//! min_amount = 1
+//!
+//! [[node.melt.methods]]
+//! method = "starknet"
+//! unit = "millistrk"
+//! min_amount = 1
+//! max_amount = 1000000
+//!
+//! [[node.exchange.pairs]]
+//! from = "millistrk"
+//! to = "gwei"
+//! spread_bps = 50
+//! fee = 10
+//!
+//! [node.exchange.oracle]
+//! kind = "static"
+//! rates = [{ from = "millistrk", to = "gwei", rate = "60.5" }]
+//! ```
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+use nuts::{Amount, nut06::ContactInfo};
+use serde::Deserialize;
+use starknet_types::Unit;
+
+use crate::{
+    exchange::oracle::OracleConfig,
+    methods::{Method, RegisterMethodsError, register_plugin_methods},
+};
+
+const DEFAULT_QUOTE_TTL: u64 = 3600;
+const DEFAULT_RESPONSE_CACHE_MAX_ENTRIES: usize = 100_000;
+const DEFAULT_MAX_INPUTS: usize = 64;
+const DEFAULT_MAX_OUTPUTS: usize = 64;
+const DEFAULT_LIABILITIES_SNAPSHOT_TTL: u64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read node config file: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("failed to deserialize node config file content: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid units config: {0}")]
+    Units(#[from] starknet_types::registry::RegistryError),
+    #[error("invalid plugins config: {0}")]
+    Plugins(#[from] liquidity_source_plugin::ReadPluginsConfigError),
+    #[error("invalid plugins config: {0}")]
+    PluginMethods(#[from] RegisterMethodsError),
+    #[error("node.{0}.methods: method `{1}` is configured twice for unit `{2}`")]
+    DuplicateMethod(&'static str, Method, Unit),
+    #[error("node.{0}.methods: method `{1}` does not support unit `{2}`")]
+    UnsupportedUnit(&'static str, Method, Unit),
+    #[error("node.{0}.methods: min_amount {3} is greater than max_amount {4} for `{1}` `{2}`")]
+    InvalidAmountRange(&'static str, Method, Unit, Amount, Amount),
+    #[error("node.ttl.{0}: must be greater than 0")]
+    ZeroTtl(&'static str),
+    #[error("node.response_cache_max_entries: must be greater than 0")]
+    ZeroResponseCacheMaxEntries,
+    #[error("node.limits.{0}: must be greater than 0")]
+    ZeroLimit(String),
+    #[error("node: at least one mint or melt method must be configured")]
+    NoMethod,
+    #[error("node.exchange.pairs: pair `{0}` to `{1}` is configured twice")]
+    DuplicateExchangePair(Unit, Unit),
+    #[error("node.exchange.pairs: cannot exchange `{0}` for itself")]
+    SameUnitExchangePair(Unit),
+    #[error("node.exchange.pairs: spread_bps {2} of pair `{0}` to `{1}` must be lower than 10000")]
+    InvalidSpread(Unit, Unit, u16),
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    node: NodeConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    #[serde(default)]
+    pub response_cache: ResponseCacheBackend,
+    /// Only used by the `memory` backend
+    pub response_cache_max_entries: Option<usize>,
+    #[serde(default)]
+    pub info: NodeInfoConfig,
+    #[serde(default)]
+    pub ttl: TtlConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub mint: OperationConfig,
+    #[serde(default)]
+    pub melt: OperationConfig,
+    /// Exchanges are disabled if not set
+    pub exchange: Option<ExchangeConfig>,
+}
+
+/// Where the NUT-19 cached responses are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseCacheBackend {
+    /// Stored in the node database
+    ///
+    /// Survives restarts and, with Postgres, is shared between the node replicas
+    #[default]
+    #[serde(alias = "postgres")]
+    Database,
+    /// Lost on restart, and local to each node: not suited to a deployment of several replicas
+    Memory,
+}
+
+/// The NUT-06 identity fields of the node
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeInfoConfig {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub description_long: Option<String>,
+    pub contact: Option<Vec<ContactInfo>>,
+    pub icon_url: Option<String>,
+    pub urls: Option<Vec<String>>,
+    pub motd: Option<String>,
+}
+
+impl Default for NodeInfoConfig {
+    fn default() -> Self {
+        Self {
+            name: Some("Paynet Test Node".to_string()),
+            description: Some("A test node".to_string()),
+            description_long: Some("This is a longer description of the test node.".to_string()),
+            contact: None,
+            icon_url: None,
+            urls: None,
+            motd: None,
+        }
+    }
+}
+
+/// Durations, in seconds
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TtlConfig {
+    /// For how long a mint quote can be paid
+    pub mint_quote: Option<u64>,
+    /// For how long a melt quote can be used
+    pub melt_quote: Option<u64>,
+    /// For how long the NUT-19 cached responses are kept, forever if not set
+    pub response_cache: Option<u64>,
+    /// For how long the expired, issued or paid quotes are kept after their expiry
+    /// before being moved to the history tables, forever if not set
+    pub quote_archival: Option<u64>,
+    /// For how long a snapshot of the liabilities is served before being rebuilt, 60 if not set
+    pub liabilities_snapshot: Option<u64>,
+}
+
+/// Bounds on the size and rate of the requests
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Max number of inputs of a swap or melt, 64 if not set
+    pub max_inputs: Option<usize>,
+    /// Max number of outputs of a swap or mint, 64 if not set
+    pub max_outputs: Option<usize>,
+    /// Requests allowed to each peer IP, only `Liabilities` is limited if not set
+    pub rate: Option<RateLimitConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Applied to each RPC without a specific limit
+    pub default: TokenBucketConfig,
+    /// Specific limits, by RPC name, e.g. `MintQuote`
+    #[serde(default)]
+    pub rpcs: HashMap<String, TokenBucketConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenBucketConfig {
+    /// Sustained number of requests per minute
+    pub per_minute: u32,
+    /// Number of requests that can be made at once after a period of inactivity
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OperationConfig {
+    #[serde(default)]
+    pub disabled: bool,
+    pub methods: Vec<MethodConfig>,
+}
+
+impl Default for OperationConfig {
+    fn default() -> Self {
+        Self {
+            disabled: false,
+            methods: vec![MethodConfig {
+                method: Method::STARKNET,
+                unit: Unit::MILLI_STRK,
+                min_amount: Some(Amount::ONE),
+                max_amount: None,
+                description: true,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodConfig {
+    #[serde(deserialize_with = "deserialize_method")]
+    pub method: Method,
+    pub unit: Unit,
+    pub min_amount: Option<Amount>,
+    pub max_amount: Option<Amount>,
+    /// Whether mint quotes accept a description, ignored for melt
+    #[serde(default)]
+    pub description: bool,
+}
+
+/// The pairs of units that can be exchanged, and where their rates come from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExchangeConfig {
+    pub pairs: Vec<ExchangePairConfig>,
+    pub oracle: OracleConfig,
+}
+
+/// Only converts `from` into `to`, the opposite conversion needs a pair of its own
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExchangePairConfig {
+    pub from: Unit,
+    pub to: Unit,
+    /// Taken off the oracle rate, in basis points
+    #[serde(default)]
+    pub spread_bps: u16,
+    /// Taken off every exchange, in the `to` unit
+    #[serde(default)]
+    pub fee: Amount,
+}
+
+impl NodeConfig {
+    /// Returns the ttl of mint and melt quotes
+    ///
+    /// Fallback on the `QUOTE_TTL` env variable, then on one hour.
+    pub fn quote_ttls(&self, env_quote_ttl: Option<u64>) -> (u64, u64) {
+        let default = env_quote_ttl.unwrap_or(DEFAULT_QUOTE_TTL);
+
+        (
+            self.ttl.mint_quote.unwrap_or(default),
+            self.ttl.melt_quote.unwrap_or(default),
+        )
+    }
+
+    /// Returns the max number of responses held by the in-memory cache
+    pub fn response_cache_max_entries(&self) -> usize {
+        self.response_cache_max_entries
+            .unwrap_or(DEFAULT_RESPONSE_CACHE_MAX_ENTRIES)
+    }
+
+    /// Returns for how long a snapshot of the liabilities is served
+    pub fn liabilities_snapshot_ttl(&self) -> Duration {
+        Duration::from_secs(
+            self.ttl
+                .liabilities_snapshot
+                .unwrap_or(DEFAULT_LIABILITIES_SNAPSHOT_TTL),
+        )
+    }
+
+    /// Returns the max number of inputs of a swap or melt
+    pub fn max_inputs(&self) -> usize {
+        self.limits.max_inputs.unwrap_or(DEFAULT_MAX_INPUTS)
+    }
+
+    /// Returns the max number of outputs of a swap or mint
+    pub fn max_outputs(&self) -> usize {
+        self.limits.max_outputs.unwrap_or(DEFAULT_MAX_OUTPUTS)
+    }
+
+    /// Returns every unit used by at least one mint or melt method, or exchange pair
+    pub fn units(&self) -> Vec<Unit> {
+        let mut units = Vec::new();
+        for method_config in self.mint.methods.iter().chain(self.melt.methods.iter()) {
+            if !units.contains(&method_config.unit) {
+                units.push(method_config.unit);
+            }
+        }
+        for pair in self.exchange.iter().flat_map(|exchange| &exchange.pairs) {
+            for unit in [pair.from, pair.to] {
+                if !units.contains(&unit) {
+                    units.push(unit);
+                }
+            }
+        }
+
+        units
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.mint.methods.is_empty() && self.melt.methods.is_empty() {
+            return Err(Error::NoMethod);
+        }
+
+        for (name, ttl) in [
+            ("mint_quote", self.ttl.mint_quote),
+            ("melt_quote", self.ttl.melt_quote),
+            ("response_cache", self.ttl.response_cache),
+            ("quote_archival", self.ttl.quote_archival),
+            ("liabilities_snapshot", self.ttl.liabilities_snapshot),
+        ] {
+            if ttl == Some(0) {
+                return Err(Error::ZeroTtl(name));
+            }
+        }
+
+        if self.response_cache_max_entries == Some(0) {
+            return Err(Error::ZeroResponseCacheMaxEntries);
+        }
+
+        for (name, limit) in [
+            ("max_inputs", self.limits.max_inputs),
+            ("max_outputs", self.limits.max_outputs),
+        ] {
+            if limit == Some(0) {
+                return Err(Error::ZeroLimit(name.to_string()));
+            }
+        }
+        if let Some(rate) = &self.limits.rate {
+            let buckets = std::iter::once(("default".to_string(), &rate.default)).chain(
+                rate.rpcs
+                    .iter()
+                    .map(|(rpc, bucket)| (format!("rpcs.{rpc}"), bucket)),
+            );
+            for (name, bucket) in buckets {
+                if bucket.per_minute == 0 || bucket.burst == 0 {
+                    return Err(Error::ZeroLimit(format!("rate.{name}")));
+                }
+            }
+        }
+
+        for (section, operation) in [("mint", &self.mint), ("melt", &self.melt)] {
+            let mut seen = HashSet::new();
+            for method_config in &operation.methods {
+                let method = method_config.method;
+                let unit = method_config.unit;
+                if !seen.insert((method, unit)) {
+                    return Err(Error::DuplicateMethod(section, method, unit));
+                }
+                if !method.supports_unit(unit) {
+                    return Err(Error::UnsupportedUnit(section, method, unit));
+                }
+                if let (Some(min), Some(max)) = (method_config.min_amount, method_config.max_amount)
+                {
+                    if min > max {
+                        return Err(Error::InvalidAmountRange(section, method, unit, min, max));
+                    }
+                }
+            }
+        }
+
+        if let Some(exchange) = &self.exchange {
+            let mut seen = HashSet::new();
+            for pair in &exchange.pairs {
+                if pair.from == pair.to {
+                    return Err(Error::SameUnitExchangePair(pair.from));
+                }
+                if !seen.insert((pair.from, pair.to)) {
+                    return Err(Error::DuplicateExchangePair(pair.from, pair.to));
+                }
+                if pair.spread_bps >= 10_000 {
+                    return Err(Error::InvalidSpread(pair.from, pair.to, pair.spread_bps));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn deserialize_method<'de, D>(deserializer: D) -> Result<Method, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    Method::from_str(&s).map_err(|_| {
+        serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"a supported method")
+    })
+}
+
+/// Read and validate the node config
+///
+/// Returns the default config if no file is provided.
+pub fn read_node_config(path: Option<&Path>) -> Result<NodeConfig, Error> {
+    let config = match path {
+        Some(path) => {
+            // Units and plugins must be registered before the methods referring to them are deserialized
+            starknet_types::registry::register_from_file(path)?;
+            register_plugin_methods(
+                liquidity_source_plugin::read_plugins_config(path)?.into_keys(),
+            )?;
+            let file_content = std::fs::read_to_string(path)?;
+            toml::from_str::<ConfigFile>(&file_content)?.node
+        }
+        None => NodeConfig::default(),
+    };
+
+    config.validate()?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<NodeConfig, Error> {
+        let config = toml::from_str::<ConfigFile>(content)?.node;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    const METHODS: &str = r#"
+        [[node.mint.methods]]
+        method = "starknet"
+        unit = "millistrk"
+
+        [[node.melt.methods]]
+        method = "starknet"
+        unit = "millistrk"
+    "#;
+
+    #[test]
+    fn default_config_is_valid() {
+        NodeConfig::default().validate().unwrap();
+        parse("").unwrap();
+    }
+
+    #[test]
+    fn doc_example_is_valid() {
+        let doc = include_str!("node_config.rs")
+            .lines()
+            .skip_while(|line| *line != "//! ```toml")
+            .skip(1)
+            .take_while(|line| *line != "//! ```")
+            .map(|line| line.trim_start_matches("//!").trim_start())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let config = parse(&doc).unwrap();
+
+        assert_eq!(config.response_cache, ResponseCacheBackend::Memory);
+        assert_eq!(config.quote_ttls(None), (3600, 600));
+        assert_eq!(config.max_inputs(), 64);
+        assert_eq!(config.limits.rate.unwrap().rpcs["MintQuote"].per_minute, 10);
+        assert_eq!(config.units(), vec![Unit::MILLI_STRK, Unit::GWEI]);
+    }
+
+    #[test]
+    fn response_cache_backends() {
+        for (backend, expected) in [
+            ("database", ResponseCacheBackend::Database),
+            ("postgres", ResponseCacheBackend::Database),
+            ("memory", ResponseCacheBackend::Memory),
+        ] {
+            let config = parse(&format!("[node]\nresponse_cache = \"{backend}\"")).unwrap();
+            assert_eq!(config.response_cache, expected);
+        }
+
+        assert!(matches!(
+            parse("[node]\nresponse_cache = \"redis\""),
+            Err(Error::Toml(_))
+        ));
+        assert_eq!(
+            parse("").unwrap().response_cache,
+            ResponseCacheBackend::Database
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(matches!(
+            parse("[node.ttl]\nmint_quotes = 10"),
+            Err(Error::Toml(_))
+        ));
+        assert!(matches!(
+            parse("[node.limits]\nmax_input = 10"),
+            Err(Error::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn zero_ttls_are_rejected() {
+        for name in [
+            "mint_quote",
+            "melt_quote",
+            "response_cache",
+            "quote_archival",
+            "liabilities_snapshot",
+        ] {
+            assert!(matches!(
+                parse(&format!("[node.ttl]\n{name} = 0")),
+                Err(Error::ZeroTtl(n)) if n == name
+            ));
+            parse(&format!("[node.ttl]\n{name} = 1")).unwrap();
+        }
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        assert!(matches!(
+            parse("[node]\nresponse_cache_max_entries = 0"),
+            Err(Error::ZeroResponseCacheMaxEntries)
+        ));
+        for name in ["max_inputs", "max_outputs"] {
+            assert!(matches!(
+                parse(&format!("[node.limits]\n{name} = 0")),
+                Err(Error::ZeroLimit(n)) if n == name
+            ));
+        }
+        assert!(matches!(
+            parse("[node.limits.rate]\ndefault = { per_minute = 0, burst = 1 }"),
+            Err(Error::ZeroLimit(n)) if n == "rate.default"
+        ));
+        assert!(matches!(
+            parse(
+                "[node.limits.rate]\ndefault = { per_minute = 1, burst = 1 }\nrpcs = { Swap = { per_minute = 1, burst = 0 } }"
+            ),
+            Err(Error::ZeroLimit(n)) if n == "rate.rpcs.Swap"
+        ));
+
+        let config = parse("[node.limits]\nmax_inputs = 1\nmax_outputs = 2").unwrap();
+        assert_eq!((config.max_inputs(), config.max_outputs()), (1, 2));
+    }
+
+    #[test]
+    fn invalid_methods_are_rejected() {
+        assert!(matches!(
+            parse("[node.mint]\nmethods = []\n[node.melt]\nmethods = []"),
+            Err(Error::NoMethod)
+        ));
+        assert!(matches!(
+            parse(&format!(
+                "{METHODS}\n[[node.mint.methods]]\nmethod = \"starknet\"\nunit = \"millistrk\""
+            )),
+            Err(Error::DuplicateMethod(
+                "mint",
+                Method::STARKNET,
+                Unit::MILLI_STRK
+            ))
+        ));
+        assert!(matches!(
+            parse("[[node.melt.methods]]\nmethod = \"bolt11\"\nunit = \"millistrk\""),
+            Err(Error::UnsupportedUnit(
+                "melt",
+                Method::BOLT11,
+                Unit::MILLI_STRK
+            ))
+        ));
+        assert!(matches!(
+            parse(
+                "[[node.mint.methods]]\nmethod = \"starknet\"\nunit = \"millistrk\"\nmin_amount = 10\nmax_amount = 5"
+            ),
+            Err(Error::InvalidAmountRange("mint", ..))
+        ));
+        assert!(matches!(
+            parse("[[node.mint.methods]]\nmethod = \"paypal\"\nunit = \"millistrk\""),
+            Err(Error::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_exchange_pairs_are_rejected() {
+        let pair = |from: &str, to: &str, spread_bps: u16| {
+            format!(
+                "[[node.exchange.pairs]]\nfrom = \"{from}\"\nto = \"{to}\"\nspread_bps = {spread_bps}\n"
+            )
+        };
+        let oracle = "[node.exchange.oracle]\nkind = \"static\"\nrates = []\n";
+
+        assert!(matches!(
+            parse(&format!("{}{oracle}", pair("gwei", "gwei", 0))),
+            Err(Error::SameUnitExchangePair(Unit::GWEI))
+        ));
+        assert!(matches!(
+            parse(&format!(
+                "{}{}{oracle}",
+                pair("millistrk", "gwei", 0),
+                pair("millistrk", "gwei", 10)
+            )),
+            Err(Error::DuplicateExchangePair(Unit::MILLI_STRK, Unit::GWEI))
+        ));
+        assert!(matches!(
+            parse(&format!("{}{oracle}", pair("millistrk", "gwei", 10_000))),
+            Err(Error::InvalidSpread(_, _, 10_000))
+        ));
+
+        let config = parse(&format!(
+            "{}{}{oracle}",
+            pair("millistrk", "gwei", 9_999),
+            pair("gwei", "millistrk", 0)
+        ))
+        .unwrap();
+        assert_eq!(config.exchange.unwrap().pairs.len(), 2);
+    }
+}
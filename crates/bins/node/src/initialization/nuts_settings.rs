@@ -1,31 +1,64 @@
-use nuts::{Amount, nut04::MintMethodSettings, nut05::MeltMethodSettings, nut06::NutsSettings};
+use nuts::{nut04::MintMethodSettings, nut05::MeltMethodSettings, nut06::NutsSettings};
 use starknet_types::Unit;
+use tracing::warn;
 
-use crate::methods::Method;
+use crate::{liquidity_sources::LiquiditySources, methods::Method};
+
+use super::node_config::{MethodConfig, NodeConfig};
+
+/// Build the settings advertised by the node
+///
+/// The methods and units of the config that no liquidity source can serve are left out.
+pub(super) fn nuts_settings(
+    config: &NodeConfig,
+    liquidity_sources: &LiquiditySources,
+) -> NutsSettings<Method, Unit> {
+    let is_served = |section: &str, m: &&MethodConfig| {
+        let served = liquidity_sources.supports(m.method, m.unit);
+        if !served {
+            warn!(
+                "{section}: no liquidity source for method {} and unit {}, ignored",
+                m.method, m.unit
+            );
+        }
+        served
+    };
 
-// TODO: make it a compile time const
-pub(super) fn nuts_settings() -> NutsSettings<Method, Unit> {
     NutsSettings {
         nut04: nuts::nut04::Settings {
-            methods: vec![MintMethodSettings {
-                method: Method::Starknet,
-                unit: Unit::MilliStrk,
-                min_amount: Some(Amount::ONE),
-                max_amount: None,
-                description: true,
-            }],
-            disabled: false,
+            methods: config
+                .mint
+                .methods
+                .iter()
+                .filter(|m| is_served("mint", m))
+                .map(|m| MintMethodSettings {
+                    method: m.method,
+                    unit: m.unit,
+                    min_amount: m.min_amount,
+                    max_amount: m.max_amount,
+                    description: m.description,
+                })
+                .collect(),
+            disabled: config.mint.disabled,
         },
         nut05: nuts::nut05::Settings {
-            methods: vec![MeltMethodSettings {
-                method: Method::Starknet,
-                unit: Unit::MilliStrk,
-                min_amount: Some(Amount::ONE),
-                max_amount: None,
-            }],
-            disabled: false,
+            methods: config
+                .melt
+                .methods
+                .iter()
+                .filter(|m| is_served("melt", m))
+                .map(|m| MeltMethodSettings {
+                    method: m.method,
+                    unit: m.unit,
+                    min_amount: m.min_amount,
+                    max_amount: m.max_amount,
+                })
+                .collect(),
+            disabled: config.melt.disabled,
         },
         nut09: nuts::nut06::SupportedSettings { supported: true },
-        nut19: nuts::nut19::Settings { ttl: None },
+        nut19: nuts::nut19::Settings {
+            ttl: config.ttl.response_cache,
+        },
     }
 }
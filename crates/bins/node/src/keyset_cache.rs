@@ -4,15 +4,22 @@ use std::{
     sync::Arc,
 };
 
+#[cfg(feature = "keyset-rotation")]
+use std::time::Duration;
+
+#[cfg(feature = "keyset-rotation")]
+use db_node::SharedStorage;
+use db_node::StorageConn;
 use nuts::{
     Amount,
     nut01::{self, PublicKey},
     nut02::KeysetId,
 };
-use sqlx::PgConnection;
 use starknet_types::Unit;
 use thiserror::Error;
 use tokio::sync::RwLock;
+#[cfg(feature = "keyset-rotation")]
+use tracing::error;
 
 use crate::app_state::SignerClient;
 
@@ -31,6 +38,7 @@ pub struct CachedKeysetInfo {
     active: bool,
     unit: Unit,
     max_order: u32,
+    final_expiry: Option<u64>,
 }
 
 impl CachedKeysetInfo {
@@ -39,6 +47,7 @@ impl CachedKeysetInfo {
             active,
             unit,
             max_order,
+            final_expiry: None,
         }
     }
 
@@ -53,6 +62,12 @@ impl CachedKeysetInfo {
     pub fn max_order(&self) -> u32 {
         self.max_order
     }
+
+    /// Returns true if proofs of this keyset are not redeemable anymore at `now` (unix timestamp)
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.final_expiry
+            .is_some_and(|final_expiry| now >= final_expiry)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -76,20 +91,75 @@ impl KeysetCache {
         write_lock.insert(keyset_id, keys.into_iter().collect());
     }
 
+    /// Insert the new keysets and disable the previous ones
+    ///
+    /// Both caches are locked for the whole operation,
+    /// so that readers never observe a state where a unit has zero or two active keysets.
     #[cfg(feature = "keyset-rotation")]
-    pub async fn disable_keys(&self, keyset_ids: &[KeysetId]) {
-        let mut write_lock = self.infos.write().await;
-
-        for keyset_id in keyset_ids {
-            if let Some(info) = write_lock.get_mut(keyset_id) {
+    pub async fn rotate_keys(
+        &self,
+        new_keysets: Vec<(KeysetId, CachedKeysetInfo, BTreeMap<Amount, PublicKey>)>,
+        prev_keyset_ids: &[KeysetId],
+        final_expiry: Option<u64>,
+    ) {
+        let mut infos_write_lock = self.infos.write().await;
+        let mut keys_write_lock = self.keys.write().await;
+
+        for keyset_id in prev_keyset_ids {
+            if let Some(info) = infos_write_lock.get_mut(keyset_id) {
                 info.active = false;
+                if final_expiry.is_some() {
+                    info.final_expiry = final_expiry;
+                }
+            }
+        }
+
+        for (keyset_id, info, keys) in new_keysets {
+            infos_write_lock.insert(keyset_id, info);
+            keys_write_lock.insert(keyset_id, keys);
+        }
+    }
+
+    /// Reload the state of the cached keysets from db
+    ///
+    /// Required when several nodes share the database, as the keysets may be rotated by another one.
+    /// Keysets missing from the cache are loaded on their first use.
+    #[cfg(feature = "keyset-rotation")]
+    pub async fn refresh(&self, conn: &mut dyn StorageConn) -> Result<(), db_node::Error> {
+        let keysets = conn.get_keysets().await?;
+
+        let mut infos_write_lock = self.infos.write().await;
+        for (keyset_id, _, active, final_expiry) in keysets {
+            let Ok(keyset_id) = KeysetId::from_bytes(&keyset_id) else {
+                continue;
+            };
+            if let Some(info) = infos_write_lock.get_mut(&keyset_id) {
+                info.active = active;
+                info.final_expiry = final_expiry;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "keyset-rotation")]
+    pub async fn run_refresh(self, storage: SharedStorage, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let res = async {
+                let mut conn = storage.acquire().await?;
+                self.refresh(conn.as_mut()).await
+            }
+            .await;
+            if let Err(err) = res {
+                error!(name: "keyset-cache-refresh", error = %err);
             }
         }
     }
 
     pub async fn get_keyset_keys(
         &self,
-        conn: &mut PgConnection,
+        conn: &mut dyn StorageConn,
         signer: SignerClient,
         keyset_id: KeysetId,
     ) -> Result<BTreeMap<Amount, PublicKey>, Error> {
@@ -102,14 +172,15 @@ impl KeysetCache {
         }
 
         // Load the infos from db
-        let db_content = db_node::keyset::get_keyset::<Unit>(conn, &keyset_id)
+        let db_content = conn
+            .get_keyset(&keyset_id)
             .await
             .map_err(|e| Error::UnknownKeysetId(keyset_id, e))?;
 
         let signer_response = signer
             .clone()
             .declare_keyset(signer::DeclareKeysetRequest {
-                unit: db_content.unit().to_string(),
+                unit: db_content.unit(),
                 index: db_content.derivation_path_index(),
                 max_order: db_content.max_order().into(),
             })
@@ -137,7 +208,7 @@ impl KeysetCache {
 
     pub async fn get_keyset_info(
         &self,
-        conn: &mut PgConnection,
+        conn: &mut dyn StorageConn,
         keyset_id: KeysetId,
     ) -> Result<CachedKeysetInfo, Error> {
         // happy path: the infos are already in the cache
@@ -149,8 +220,10 @@ impl KeysetCache {
         }
 
         // Load the infos from db
-        let db_content = db_node::keyset::get_keyset::<Unit>(conn, &keyset_id)
+        let db_content = conn
+            .get_keyset(&keyset_id)
             .await
+            .and_then(|info| info.parse_unit::<Unit>())
             .map_err(|e| Error::UnknownKeysetId(keyset_id, e))?;
 
         // Save the infos in the cache
@@ -158,6 +231,7 @@ impl KeysetCache {
             active: db_content.active(),
             unit: db_content.unit(),
             max_order: db_content.max_order().into(),
+            final_expiry: db_content.final_expiry(),
         };
 
         {
@@ -1,101 +1,170 @@
-use crate::errors::Error;
+use crate::audit::AuditEvent;
 use crate::keyset_cache::CachedKeysetInfo;
-use db_node::keyset::deactivate_keysets;
 use grpc_service::GrpcState;
 use node::{KeysetRotationService, RotateKeysetsRequest, RotateKeysetsResponse};
 
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
+use thiserror::Error;
 use tonic::{Request, Response, Status};
 
-use nuts::{Amount, nut01::PublicKey, nut02::KeysetId};
+use nuts::{
+    Amount,
+    nut01::{self, PublicKey},
+    nut02::{self, KeysetId},
+};
 use starknet_types::Unit;
 
 use crate::grpc_service;
-#[tonic::async_trait]
-impl KeysetRotationService for GrpcState {
-    async fn rotate_keysets(
-        &self,
-        _request: Request<RotateKeysetsRequest>,
-    ) -> Result<Response<RotateKeysetsResponse>, Status> {
-        let mut tx = db_node::begin_db_tx(&self.pg_pool)
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        let keysets_info = db_node::keyset::get_active_keysets::<Unit>(&mut tx)
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
 
-        let mut insert_keysets_query_builder = db_node::InsertKeysetsQueryBuilder::new();
+#[derive(Debug, Error)]
+pub enum RotateKeysetsError {
+    #[error(transparent)]
+    Db(#[from] db_node::Error),
+    #[error(transparent)]
+    Signer(#[from] tonic::Status),
+    #[error(transparent)]
+    Nut01(#[from] nut01::Error),
+    #[error(transparent)]
+    Nut02(#[from] nut02::Error),
+}
 
-        let mut prev_keyset_ids: Vec<KeysetId> = Vec::with_capacity(keysets_info.len());
+impl From<RotateKeysetsError> for Status {
+    fn from(value: RotateKeysetsError) -> Self {
+        match value {
+            RotateKeysetsError::Signer(status) => status,
+            _ => Status::internal(value.to_string()),
+        }
+    }
+}
 
-        // TODO: add concurency
-        for (keyset_id, keyset_info) in keysets_info {
-            let unit = keyset_info.unit();
-            let index = keyset_info.derivation_path_index() + 1;
-            let max_order = keyset_info.max_order() as u32;
+#[derive(Debug, Clone)]
+pub struct RotatedKeyset {
+    pub unit: Unit,
+    pub prev_keyset_id: KeysetId,
+    pub new_keyset_id: KeysetId,
+}
 
-            let response = self
-                .signer
-                .clone()
-                .declare_keyset(signer::DeclareKeysetRequest {
-                    unit: unit.to_string(),
+impl GrpcState {
+    /// Replace the active keysets of `units` by new ones
+    ///
+    /// All the active keysets are rotated if `units` is `None`.
+    /// The previous keysets are deactivated, and will be retired at `final_expiry` if provided.
+    pub async fn rotate_keysets_of_units(
+        &self,
+        units: Option<&[Unit]>,
+        final_expiry: Option<u64>,
+    ) -> Result<Vec<RotatedKeyset>, RotateKeysetsError> {
+        // The keysets are declared to the signer before the transaction,
+        // so that it is only held for the writes
+        let mut conn = self.storage.acquire().await?;
+        let keysets_info = conn
+            .get_active_keysets()
+            .await?
+            .into_iter()
+            .map(|(keyset_id, keyset_info)| Ok((keyset_id, keyset_info.parse_unit::<Unit>()?)))
+            .collect::<Result<Vec<_>, db_node::Error>>()?
+            .into_iter()
+            .filter(|(_, keyset_info)| {
+                units.is_none_or(|units| units.contains(&keyset_info.unit()))
+            });
+        drop(conn);
+
+        let new_keysets = futures::future::try_join_all(keysets_info.map(
+            |(keyset_id, keyset_info)| async move {
+                let unit = keyset_info.unit();
+                let index = keyset_info.derivation_path_index() + 1;
+                let max_order = keyset_info.max_order() as u32;
+
+                let response = self
+                    .signer
+                    .clone()
+                    .declare_keyset(signer::DeclareKeysetRequest {
+                        unit: unit.to_string(),
+                        index,
+                        max_order,
+                    })
+                    .await?
+                    .into_inner();
+
+                let new_keyset_id = KeysetId::from_bytes(&response.keyset_id)?;
+                let keys = response
+                    .keys
+                    .into_iter()
+                    .map(|k| -> Result<(Amount, PublicKey), RotateKeysetsError> {
+                        Ok((Amount::from(k.amount), PublicKey::from_str(&k.pubkey)?))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+                Ok::<_, RotateKeysetsError>((
+                    keyset_id,
+                    new_keyset_id,
+                    unit,
                     index,
                     max_order,
-                })
-                .await?;
-
-            let response = response.into_inner();
+                    keys,
+                ))
+            },
+        ))
+        .await?;
+
+        if new_keysets.is_empty() {
+            return Ok(Vec::new());
+        }
 
-            let new_keyset_id = KeysetId::from_bytes(&response.keyset_id)
-                .map_err(|e| Status::internal(e.to_string()))?;
+        let mut keysets_to_insert = Vec::with_capacity(new_keysets.len());
+        let mut prev_keyset_ids = Vec::with_capacity(new_keysets.len());
+        let mut rotated_keysets = Vec::with_capacity(new_keysets.len());
+        let mut cache_entries = Vec::with_capacity(new_keysets.len());
+        for (prev_keyset_id, new_keyset_id, unit, index, max_order, keys) in new_keysets {
+            keysets_to_insert.push((new_keyset_id, unit.to_string(), max_order, index));
+            cache_entries.push((
+                new_keyset_id,
+                CachedKeysetInfo::new(true, unit, max_order),
+                keys,
+            ));
+            prev_keyset_ids.push(prev_keyset_id);
+            rotated_keysets.push(RotatedKeyset {
+                unit,
+                prev_keyset_id,
+                new_keyset_id,
+            });
+        }
 
-            insert_keysets_query_builder.add_row(new_keyset_id, unit, max_order, index);
+        let mut tx = self.storage.begin().await?;
+        tx.insert_keysets(&keysets_to_insert).await?;
+        tx.deactivate_keysets(&prev_keyset_ids, final_expiry)
+            .await?;
+        for rotated_keyset in &rotated_keysets {
+            AuditEvent::KeysetRotation {
+                unit: rotated_keyset.unit,
+                prev_keyset_id: rotated_keyset.prev_keyset_id,
+                new_keyset_id: rotated_keyset.new_keyset_id,
+                final_expiry,
+            }
+            .record(&mut *tx)
+            .await?;
+        }
 
-            self.keyset_cache
-                .insert_info(new_keyset_id, CachedKeysetInfo::new(true, unit, max_order))
-                .await;
+        tx.commit().await?;
+        self.audit_sealer.notify();
 
-            let keys = response
-                .keys
-                .into_iter()
-                .map(|k| -> Result<(Amount, PublicKey), Error> {
-                    Ok((
-                        Amount::from(k.amount),
-                        PublicKey::from_str(&k.pubkey).map_err(Error::Nut01)?,
-                    ))
-                })
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| Status::internal(e.to_string()))?;
+        self.keyset_cache
+            .rotate_keys(cache_entries, &prev_keyset_ids, final_expiry)
+            .await;
 
-            self.keyset_cache
-                .insert_keys(new_keyset_id, keys.into_iter())
-                .await;
+        Ok(rotated_keysets)
+    }
+}
 
-            prev_keyset_ids.push(keyset_id);
-        }
+#[tonic::async_trait]
+impl KeysetRotationService for GrpcState {
+    async fn rotate_keysets(
+        &self,
+        request: Request<RotateKeysetsRequest>,
+    ) -> Result<Response<RotateKeysetsResponse>, Status> {
+        let final_expiry = request.into_inner().final_expiry;
 
-        insert_keysets_query_builder
-            .execute(&mut tx)
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        deactivate_keysets(
-            &mut tx,
-            &prev_keyset_ids
-                .iter()
-                .map(|keyset_id| keyset_id.as_i64())
-                .collect::<Vec<_>>(),
-        )
-        .await
-        .map_err(|e| Status::internal(e.to_string()))?;
-
-        tx.commit()
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
-
-        // disable prev ids
-        self.keyset_cache.disable_keys(&prev_keyset_ids).await;
+        self.rotate_keysets_of_units(None, final_expiry).await?;
 
         Ok(Response::new(RotateKeysetsResponse {}))
     }
@@ -0,0 +1,220 @@
+//! Policy driven rotation of the node keysets
+//!
+//! The active keyset of a unit is rotated once it has been in use for longer than the configured interval,
+//! or once the signer has produced the configured number of signatures with it.
+//! The last rotation of a unit is the creation of its active keyset, as recorded in db,
+//! so the interval carries over restarts, and leader changes.
+//!
+//! Only the leader node rotates keysets.
+use std::{collections::HashMap, time::Duration};
+
+use opentelemetry::KeyValue;
+use signer::GetKeysetStatsRequest;
+use starknet_types::Unit;
+use tracing::{Level, error, event};
+
+use crate::{grpc_service::GrpcState, keyset_rotation::RotateKeysetsError, utils::unix_time};
+
+#[derive(Debug, Clone, Default)]
+pub struct KeysetRotationPolicy {
+    /// Rotate a keyset after it has been active for this long
+    pub interval: Option<Duration>,
+    /// Rotate a keyset after the signer produced this many signatures with it
+    pub max_signatures: Option<u64>,
+    /// Retire the rotated keysets this long after their rotation
+    pub final_expiry_delay: Option<Duration>,
+}
+
+impl KeysetRotationPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some() || self.max_signatures.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RotationReason {
+    Interval,
+    MaxSignatures,
+}
+
+impl RotationReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RotationReason::Interval => "interval",
+            RotationReason::MaxSignatures => "max-signatures",
+        }
+    }
+}
+
+impl KeysetRotationPolicy {
+    /// Whether a keyset active since `active_since` should be rotated at `now`, both unix timestamps
+    fn interval_elapsed(&self, active_since: u64, now: u64) -> bool {
+        self.interval
+            .is_some_and(|interval| now.saturating_sub(active_since) >= interval.as_secs())
+    }
+
+    /// Whether a keyset that produced `signatures_count` signatures should be rotated
+    fn max_signatures_reached(&self, signatures_count: u64) -> bool {
+        self.max_signatures
+            .is_some_and(|max_signatures| signatures_count >= max_signatures)
+    }
+}
+
+pub struct KeysetRotator {
+    state: GrpcState,
+    policy: KeysetRotationPolicy,
+}
+
+impl KeysetRotator {
+    pub fn new(state: GrpcState, policy: KeysetRotationPolicy) -> Self {
+        Self { state, policy }
+    }
+
+    async fn rotation_reason(
+        &self,
+        keyset_id: nuts::nut02::KeysetId,
+        active_since: u64,
+    ) -> Result<Option<RotationReason>, RotateKeysetsError> {
+        if self.policy.interval_elapsed(active_since, unix_time()) {
+            return Ok(Some(RotationReason::Interval));
+        }
+
+        if self.policy.max_signatures.is_some() {
+            let stats = self
+                .state
+                .signer
+                .clone()
+                .get_keyset_stats(GetKeysetStatsRequest {
+                    keyset_id: keyset_id.to_bytes().to_vec(),
+                })
+                .await?
+                .into_inner();
+            if self.policy.max_signatures_reached(stats.signatures_count) {
+                return Ok(Some(RotationReason::MaxSignatures));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn rotate_due_keysets(&self) -> Result<(), RotateKeysetsError> {
+        let (active_keysets, created_at) = {
+            let mut conn = self.state.storage.acquire().await?;
+            (
+                conn.get_active_keysets().await?,
+                conn.get_active_keysets_created_at()
+                    .await?
+                    .into_iter()
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+
+        let mut due_units = HashMap::new();
+        for (keyset_id, keyset_info) in active_keysets {
+            let unit = keyset_info.parse_unit::<Unit>()?.unit();
+            let active_since = created_at
+                .get(&keyset_id)
+                .copied()
+                .unwrap_or_else(unix_time);
+            if let Some(reason) = self.rotation_reason(keyset_id, active_since).await? {
+                due_units.insert(unit, reason);
+            }
+        }
+        if due_units.is_empty() {
+            return Ok(());
+        }
+
+        let final_expiry = self
+            .policy
+            .final_expiry_delay
+            .map(|delay| unix_time().saturating_add(delay.as_secs()));
+        let units = due_units.keys().copied().collect::<Vec<_>>();
+        let rotated_keysets = self
+            .state
+            .rotate_keysets_of_units(Some(&units), final_expiry)
+            .await?;
+
+        let meter = opentelemetry::global::meter("business");
+        let rotation_counter = meter.u64_counter("keyset.rotation.count").build();
+        for rotated in rotated_keysets {
+            let reason = due_units
+                .get(&rotated.unit)
+                .copied()
+                .unwrap_or(RotationReason::Interval);
+
+            event!(
+                name: "keyset-rotation",
+                Level::INFO,
+                name = "keyset-rotation",
+                unit = %rotated.unit,
+                prev_keyset_id = %rotated.prev_keyset_id,
+                new_keyset_id = %rotated.new_keyset_id,
+                reason = reason.as_str(),
+                final_expiry,
+            );
+            rotation_counter.add(
+                1,
+                &[
+                    KeyValue::new("unit", rotated.unit.to_string()),
+                    KeyValue::new("reason", reason.as_str()),
+                ],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn run_keyset_rotator(rotator: KeysetRotator, check_interval: Duration) {
+    loop {
+        tokio::time::sleep(check_interval).await;
+        if let Err(err) = rotator.rotate_due_keysets().await {
+            error!(name: "keyset-rotator", error = %err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn interval_trigger() {
+        let policy = KeysetRotationPolicy {
+            interval: Some(Duration::from_secs(HOUR)),
+            ..Default::default()
+        };
+
+        assert!(!policy.interval_elapsed(1_000, 1_000));
+        assert!(!policy.interval_elapsed(1_000, 1_000 + HOUR - 1));
+        assert!(policy.interval_elapsed(1_000, 1_000 + HOUR));
+        // A keyset created in the future, from a node with a skewed clock, is not rotated
+        assert!(!policy.interval_elapsed(1_000 + HOUR, 1_000));
+        assert!(!policy.max_signatures_reached(u64::MAX));
+    }
+
+    #[test]
+    fn max_signatures_trigger() {
+        let policy = KeysetRotationPolicy {
+            max_signatures: Some(100),
+            ..Default::default()
+        };
+
+        assert!(!policy.max_signatures_reached(0));
+        assert!(!policy.max_signatures_reached(99));
+        assert!(policy.max_signatures_reached(100));
+        assert!(policy.max_signatures_reached(101));
+        assert!(!policy.interval_elapsed(0, u64::MAX));
+    }
+
+    #[test]
+    fn disabled_policy_never_triggers() {
+        let policy = KeysetRotationPolicy::default();
+
+        assert!(!policy.is_enabled());
+        assert!(!policy.interval_elapsed(0, u64::MAX));
+        assert!(!policy.max_signatures_reached(u64::MAX));
+    }
+}
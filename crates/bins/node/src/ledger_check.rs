@@ -0,0 +1,378 @@
+//! Consistency check of the node ledger, run by the `check-ledger` subcommand
+//!
+//! For each unit, the ecash in circulation (signatures issued minus proofs spent)
+//! should be equal to what was minted through quotes minus what was melted,
+//! plus what exchanges issued in this unit minus what they spent of it.
+//! Swaps spend and issue the same amount of each unit, so they don't move this balance.
+use std::collections::BTreeMap;
+
+use db_node::{
+    StorageConn,
+    ledger::{KeysetAmountLedger, UnitExchangeLedger, UnitQuoteLedger},
+};
+use nuts::{Amount, nut02::KeysetId, nut04::MintQuoteState, nut05::MeltQuoteState};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::initialization::{self, connect_to_db, read_db_url};
+
+#[derive(Debug, Serialize)]
+pub struct LedgerReport {
+    /// False if any issue was found
+    pub consistent: bool,
+    pub keysets: Vec<KeysetBalance>,
+    pub units: Vec<UnitBalance>,
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeysetBalance {
+    pub keyset_id: KeysetId,
+    pub unit: String,
+    /// Total amount of the signatures issued with this keyset
+    pub issued: u128,
+    /// Total amount of the proofs of this keyset spent
+    pub spent: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitBalance {
+    pub unit: String,
+    /// Signatures issued minus proofs spent, for all the keysets of this unit
+    pub in_circulation: i128,
+    pub minted: Amount,
+    /// Fees included
+    pub melted: Amount,
+    pub melt_fees: Amount,
+    /// Issued by exchanges from another unit
+    pub exchanged_in: Amount,
+    /// Spent by exchanges to another unit
+    pub exchanged_out: Amount,
+    /// Minted minus melted, plus exchanged in minus exchanged out
+    pub expected_in_circulation: i128,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// More proofs of this amount were spent than signatures were issued
+    SpentWithoutSignature {
+        keyset_id: KeysetId,
+        amount: Amount,
+        issued: u64,
+        spent: u64,
+    },
+    /// The ecash in circulation doesn't match the quotes
+    UnitBalanceMismatch {
+        unit: String,
+        in_circulation: i128,
+        expected_in_circulation: i128,
+    },
+    /// The quote was marked as paid but no deposit was ever indexed for it
+    MintQuoteWithoutPayment {
+        quote_id: Uuid,
+        state: MintQuoteState,
+    },
+    /// The quote was marked as paid but no withdrawal was ever indexed for it
+    MeltQuoteWithoutPayment {
+        quote_id: Uuid,
+        state: MeltQuoteState,
+    },
+}
+
+pub async fn check_ledger(conn: &mut dyn StorageConn) -> Result<LedgerReport, db_node::Error> {
+    let keyset_amounts = conn.get_keyset_amount_ledger().await?;
+    let unit_quotes = conn.get_unit_quote_ledger().await?;
+    let unit_exchanges = conn.get_unit_exchange_ledger().await?;
+    let mint_quotes = conn.get_mint_quotes_without_payment_event().await?;
+    let melt_quotes = conn.get_melt_quotes_without_payment_event().await?;
+
+    Ok(build_report(
+        keyset_amounts,
+        unit_quotes,
+        unit_exchanges,
+        mint_quotes,
+        melt_quotes,
+    ))
+}
+
+fn build_report(
+    keyset_amounts: Vec<KeysetAmountLedger>,
+    unit_quotes: Vec<UnitQuoteLedger>,
+    unit_exchanges: Vec<UnitExchangeLedger>,
+    mint_quotes_without_payment: Vec<(Uuid, MintQuoteState)>,
+    melt_quotes_without_payment: Vec<(Uuid, MeltQuoteState)>,
+) -> LedgerReport {
+    let mut issues = Vec::new();
+    let mut keysets: Vec<KeysetBalance> = Vec::new();
+
+    // Rows are ordered by keyset id
+    for row in keyset_amounts {
+        if row.spent > row.issued {
+            issues.push(Issue::SpentWithoutSignature {
+                keyset_id: row.keyset_id,
+                amount: row.amount,
+                issued: row.issued,
+                spent: row.spent,
+            });
+        }
+
+        let amount = u128::from(u64::from(row.amount));
+        let issued = amount * u128::from(row.issued);
+        let spent = amount * u128::from(row.spent);
+        match keysets.last_mut() {
+            Some(keyset) if keyset.keyset_id == row.keyset_id => {
+                keyset.issued += issued;
+                keyset.spent += spent;
+            }
+            _ => keysets.push(KeysetBalance {
+                keyset_id: row.keyset_id,
+                unit: row.unit,
+                issued,
+                spent,
+            }),
+        }
+    }
+
+    let mut units: BTreeMap<String, UnitBalance> = BTreeMap::new();
+    for keyset in &keysets {
+        let unit = units
+            .entry(keyset.unit.clone())
+            .or_insert_with(|| UnitBalance::new(keyset.unit.clone()));
+        unit.in_circulation += keyset.issued as i128 - keyset.spent as i128;
+    }
+    for quotes in unit_quotes {
+        let unit = units
+            .entry(quotes.unit.clone())
+            .or_insert_with(|| UnitBalance::new(quotes.unit));
+        unit.minted = quotes.minted;
+        unit.melted = quotes.melted;
+        unit.melt_fees = quotes.melt_fees;
+    }
+    for exchanges in unit_exchanges {
+        let unit = units
+            .entry(exchanges.unit.clone())
+            .or_insert_with(|| UnitBalance::new(exchanges.unit));
+        unit.exchanged_in = exchanges.exchanged_in;
+        unit.exchanged_out = exchanges.exchanged_out;
+    }
+    for unit in units.values_mut() {
+        unit.expected_in_circulation = i128::from(u64::from(unit.minted))
+            - i128::from(u64::from(unit.melted))
+            + i128::from(u64::from(unit.exchanged_in))
+            - i128::from(u64::from(unit.exchanged_out));
+        if unit.in_circulation != unit.expected_in_circulation {
+            issues.push(Issue::UnitBalanceMismatch {
+                unit: unit.unit.clone(),
+                in_circulation: unit.in_circulation,
+                expected_in_circulation: unit.expected_in_circulation,
+            });
+        }
+    }
+
+    issues.extend(
+        mint_quotes_without_payment
+            .into_iter()
+            .map(|(quote_id, state)| Issue::MintQuoteWithoutPayment { quote_id, state }),
+    );
+    issues.extend(
+        melt_quotes_without_payment
+            .into_iter()
+            .map(|(quote_id, state)| Issue::MeltQuoteWithoutPayment { quote_id, state }),
+    );
+
+    LedgerReport {
+        consistent: issues.is_empty(),
+        keysets,
+        units: units.into_values().collect(),
+        issues,
+    }
+}
+
+impl UnitBalance {
+    fn new(unit: String) -> Self {
+        Self {
+            unit,
+            in_circulation: 0,
+            minted: Amount::ZERO,
+            melted: Amount::ZERO,
+            melt_fees: Amount::ZERO,
+            exchanged_in: Amount::ZERO,
+            exchanged_out: Amount::ZERO,
+            expected_in_circulation: 0,
+        }
+    }
+}
+
+/// Entry point of the `check-ledger` subcommand
+///
+/// Prints the report on stdout, and returns whether the ledger is consistent.
+pub async fn run() -> Result<bool, initialization::Error> {
+    let storage = connect_to_db(&read_db_url()?).await?;
+    let mut conn = storage
+        .acquire()
+        .await
+        .map_err(initialization::Error::DbConnect)?;
+    let report = check_ledger(conn.as_mut())
+        .await
+        .map_err(initialization::Error::CheckLedger)?;
+
+    println!(
+        "{}",
+        serde_json::to_string(&report).expect("the report should be serializable")
+    );
+
+    Ok(report.consistent)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const UNIT: &str = "millistrk";
+
+    fn keyset_id() -> KeysetId {
+        KeysetId::from_str("009a1f293253e41e").unwrap()
+    }
+
+    fn keyset_amount(amount: u64, issued: u64, spent: u64) -> KeysetAmountLedger {
+        KeysetAmountLedger {
+            keyset_id: keyset_id(),
+            unit: UNIT.to_string(),
+            amount: Amount::from(amount),
+            issued,
+            spent,
+        }
+    }
+
+    fn unit_quotes(minted: u64, melted: u64) -> UnitQuoteLedger {
+        UnitQuoteLedger {
+            unit: UNIT.to_string(),
+            minted: Amount::from(minted),
+            melted: Amount::from(melted),
+            melt_fees: Amount::ZERO,
+        }
+    }
+
+    fn unit_exchanges(exchanged_in: u64, exchanged_out: u64) -> UnitExchangeLedger {
+        UnitExchangeLedger {
+            unit: UNIT.to_string(),
+            exchanged_in: Amount::from(exchanged_in),
+            exchanged_out: Amount::from(exchanged_out),
+        }
+    }
+
+    struct Case {
+        name: &'static str,
+        keyset_amounts: Vec<KeysetAmountLedger>,
+        unit_quotes: Vec<UnitQuoteLedger>,
+        unit_exchanges: Vec<UnitExchangeLedger>,
+        mint_quotes_without_payment: Vec<(Uuid, MintQuoteState)>,
+        melt_quotes_without_payment: Vec<(Uuid, MeltQuoteState)>,
+        expected_issues: Vec<&'static str>,
+    }
+
+    #[test]
+    fn build_report_detects_each_anomaly() {
+        let cases = [
+            Case {
+                name: "clean ledger",
+                // 3x2 + 2x4 issued, 1x2 + 1x4 spent: 8 in circulation
+                keyset_amounts: vec![keyset_amount(2, 3, 1), keyset_amount(4, 2, 1)],
+                unit_quotes: vec![unit_quotes(10, 4)],
+                unit_exchanges: vec![unit_exchanges(5, 3)],
+                mint_quotes_without_payment: vec![],
+                melt_quotes_without_payment: vec![],
+                expected_issues: vec![],
+            },
+            Case {
+                name: "spent without signature",
+                keyset_amounts: vec![keyset_amount(1, 1, 2)],
+                unit_quotes: vec![unit_quotes(0, 1)],
+                unit_exchanges: vec![],
+                mint_quotes_without_payment: vec![],
+                melt_quotes_without_payment: vec![],
+                expected_issues: vec!["spent_without_signature"],
+            },
+            Case {
+                name: "unit balance mismatch",
+                keyset_amounts: vec![keyset_amount(1, 2, 0)],
+                unit_quotes: vec![unit_quotes(1, 0)],
+                unit_exchanges: vec![],
+                mint_quotes_without_payment: vec![],
+                melt_quotes_without_payment: vec![],
+                expected_issues: vec!["unit_balance_mismatch"],
+            },
+            Case {
+                name: "exchange missing from the balance",
+                keyset_amounts: vec![keyset_amount(1, 2, 0)],
+                unit_quotes: vec![unit_quotes(1, 0)],
+                unit_exchanges: vec![unit_exchanges(2, 0)],
+                mint_quotes_without_payment: vec![],
+                melt_quotes_without_payment: vec![],
+                expected_issues: vec!["unit_balance_mismatch"],
+            },
+            Case {
+                name: "mint quote without payment",
+                keyset_amounts: vec![],
+                unit_quotes: vec![],
+                unit_exchanges: vec![],
+                mint_quotes_without_payment: vec![(Uuid::new_v4(), MintQuoteState::Paid)],
+                melt_quotes_without_payment: vec![],
+                expected_issues: vec!["mint_quote_without_payment"],
+            },
+            Case {
+                name: "melt quote without payment",
+                keyset_amounts: vec![],
+                unit_quotes: vec![],
+                unit_exchanges: vec![],
+                mint_quotes_without_payment: vec![],
+                melt_quotes_without_payment: vec![(Uuid::new_v4(), MeltQuoteState::Paid)],
+                expected_issues: vec!["melt_quote_without_payment"],
+            },
+        ];
+
+        for case in cases {
+            let report = build_report(
+                case.keyset_amounts,
+                case.unit_quotes,
+                case.unit_exchanges,
+                case.mint_quotes_without_payment,
+                case.melt_quotes_without_payment,
+            );
+            let issues: Vec<String> = report
+                .issues
+                .iter()
+                .map(|issue| {
+                    serde_json::to_value(issue).unwrap()["kind"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
+
+            assert_eq!(issues, case.expected_issues, "{}", case.name);
+            assert_eq!(report.consistent, issues.is_empty(), "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn build_report_sums_the_amounts_of_a_keyset() {
+        let report = build_report(
+            vec![keyset_amount(2, 3, 1), keyset_amount(4, 2, 1)],
+            vec![unit_quotes(10, 4)],
+            vec![unit_exchanges(5, 3)],
+            vec![],
+            vec![],
+        );
+
+        assert_eq!(report.keysets.len(), 1);
+        assert_eq!(report.keysets[0].issued, 14);
+        assert_eq!(report.keysets[0].spent, 6);
+        assert_eq!(report.units.len(), 1);
+        assert_eq!(report.units[0].in_circulation, 8);
+        assert_eq!(report.units[0].expected_in_circulation, 8);
+    }
+}
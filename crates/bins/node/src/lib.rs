@@ -1,4 +1,8 @@
-use nuts::{nut04, nut05};
+use nuts::{liabilities, nut04, nut05};
+#[cfg(feature = "admin")]
+pub use proto::admin::admin_server::{Admin, AdminServer};
+#[cfg(feature = "admin")]
+pub use proto::admin::*;
 pub use proto::bdhke::{BlindSignature, BlindedMessage, Proof};
 #[cfg(feature = "keyset-rotation")]
 pub use proto::keyset_rotation::keyset_rotation_service_server::{
@@ -9,7 +13,14 @@ pub use proto::keyset_rotation::*;
 pub use proto::node::node_server::{Node, NodeServer};
 pub use proto::node::*;
 
+/// Encoded descriptors of the public protos, served by the reflection service
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("node_descriptor");
+
 mod proto {
+    #[cfg(feature = "admin")]
+    pub mod admin {
+        tonic::include_proto!("admin");
+    }
     pub mod bdhke {
         tonic::include_proto!("bdhke");
     }
@@ -42,7 +53,10 @@ impl TryFrom<MeltQuoteState> for nut05::MeltQuoteState {
 impl From<nut05::MeltQuoteState> for MeltQuoteState {
     fn from(value: nut05::MeltQuoteState) -> Self {
         match value {
-            nut05::MeltQuoteState::Unpaid => MeltQuoteState::MlqsUnpaid,
+            // The quote `expiry` already tells the client it expired
+            nut05::MeltQuoteState::Unpaid | nut05::MeltQuoteState::Expired => {
+                MeltQuoteState::MlqsUnpaid
+            }
             nut05::MeltQuoteState::Pending => MeltQuoteState::MlqsPending,
             nut05::MeltQuoteState::Paid => MeltQuoteState::MlqsPaid,
         }
@@ -64,13 +78,36 @@ impl TryFrom<MintQuoteState> for nut04::MintQuoteState {
 impl From<nut04::MintQuoteState> for MintQuoteState {
     fn from(value: nut04::MintQuoteState) -> Self {
         match value {
-            nut04::MintQuoteState::Unpaid => MintQuoteState::MnqsUnpaid,
+            // The quote `expiry` already tells the client it expired
+            nut04::MintQuoteState::Unpaid | nut04::MintQuoteState::Expired => {
+                MintQuoteState::MnqsUnpaid
+            }
             nut04::MintQuoteState::Paid => MintQuoteState::MnqsPaid,
             nut04::MintQuoteState::Issued => MintQuoteState::MnqsIssued,
         }
     }
 }
 
+impl From<liabilities::SumNode> for MerkleSumNode {
+    fn from(value: liabilities::SumNode) -> Self {
+        MerkleSumNode {
+            hash: value.hash.to_vec(),
+            sum: value.sum.into(),
+        }
+    }
+}
+
+pub fn inclusion_proof_to_path(proof: liabilities::InclusionProof) -> Vec<MerkleProofStep> {
+    proof
+        .path
+        .into_iter()
+        .map(|step| MerkleProofStep {
+            sibling: Some(step.sibling.into()),
+            sibling_is_left: step.sibling_is_left,
+        })
+        .collect()
+}
+
 use std::hash::{DefaultHasher, Hash, Hasher};
 
 /// Hash MintRequest to a string
@@ -102,6 +139,11 @@ pub fn hash_melt_request(request: &MeltRequest) -> u64 {
         input.secret.hash(&mut hasher);
         input.unblind_signature.hash(&mut hasher);
     }
+    for output in &request.outputs {
+        output.amount.hash(&mut hasher);
+        output.keyset_id.hash(&mut hasher);
+        output.blinded_secret.hash(&mut hasher);
+    }
 
     hasher.finish()
 }
@@ -123,3 +165,21 @@ pub fn hash_swap_request(request: &SwapRequest) -> u64 {
 
     hasher.finish()
 }
+
+pub fn hash_exchange_request(request: &ExchangeRequest) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for input in &request.inputs {
+        input.amount.hash(&mut hasher);
+        input.keyset_id.hash(&mut hasher);
+        input.secret.hash(&mut hasher);
+        input.unblind_signature.hash(&mut hasher);
+    }
+    for output in &request.outputs {
+        output.amount.hash(&mut hasher);
+        output.keyset_id.hash(&mut hasher);
+        output.blinded_secret.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
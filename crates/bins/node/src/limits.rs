@@ -0,0 +1,311 @@
+//! Bounds on what a single client can ask from the node
+//!
+//! Each peer IP gets a token bucket per RPC, refilled at the rate configured in `[node.limits.rate]`.
+//! The REST routes share the bucket of their gRPC counterpart, calls to an unknown gRPC path share an `other` bucket.
+//! Requests arriving on an empty bucket are answered with `RESOURCE_EXHAUSTED`, or a `429` over REST,
+//! without reaching the service.
+//! `Liabilities` is limited to [`LIABILITIES_RATE`] unless configured otherwise, even when no rate is configured.
+//! The number of inputs and outputs of a request is bounded in [`crate::logic`].
+//!
+//! Requests whose peer address is unknown, e.g. behind some TLS acceptors, are not rate limited.
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    net::IpAddr,
+    sync::{Arc, LazyLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::future::{Either, Ready, ready};
+use opentelemetry::{KeyValue, metrics::Counter};
+use parking_lot::Mutex;
+use tonic::{Status, transport::server::TcpConnectInfo};
+use tower::{Layer, Service};
+use tracing::{Level, event};
+
+use crate::initialization::{RateLimitConfig, TokenBucketConfig};
+
+/// Applied to `Liabilities` when it has no specific limit, its responses being large
+pub const LIABILITIES_RATE: TokenBucketConfig = TokenBucketConfig {
+    per_minute: 30,
+    burst: 5,
+};
+
+/// The bucket of the calls to a path that is not one of [`RPCS`]
+///
+/// So that a client can't create new buckets by making up paths.
+pub const OTHER_RPC: &str = "other";
+
+/// The RPCs served by the node, each limited with its own bucket
+const RPCS: &[&str] = &[
+    // node.Node
+    "Keysets",
+    "Keys",
+    "Swap",
+    "ExchangeEstimate",
+    "Exchange",
+    "MintQuote",
+    "Mint",
+    "MintQuoteState",
+    "MeltQuote",
+    "MeltQuoteState",
+    "Melt",
+    "GetNodeInfo",
+    "Acknowledge",
+    "CheckState",
+    "Restore",
+    "Liabilities",
+    "Reserves",
+    // admin.Admin
+    "SetMintDisabled",
+    "SetMeltDisabled",
+    "SetAmountLimits",
+    "ListQuotes",
+    "GetQuote",
+    "ExpireQuote",
+    // keyset_rotation.KeysetRotationService
+    "RotateKeysets",
+    // grpc.health.v1.Health
+    "Check",
+    "Watch",
+    // grpc.reflection.v1.ServerReflection
+    "ServerReflectionInfo",
+];
+
+/// The bucket of `name`, [`OTHER_RPC`] if it is not an RPC of the node
+pub fn rpc_bucket(name: &str) -> &'static str {
+    RPCS.iter()
+        .find(|rpc| **rpc == name)
+        .copied()
+        .unwrap_or(OTHER_RPC)
+}
+
+/// Max number of proofs and blinded messages in a single request
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+}
+
+static REJECTED_REQUESTS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    opentelemetry::global::meter(env!("CARGO_PKG_NAME"))
+        .u64_counter("request.rejected.count")
+        .build()
+});
+
+/// Count a request rejected for exceeding a limit
+pub fn record_rejection(reason: &'static str, rpc: &str) {
+    REJECTED_REQUESTS.add(
+        1,
+        &[
+            KeyValue::new("reason", reason),
+            KeyValue::new("rpc", rpc.to_string()),
+        ],
+    );
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(config: &TokenBucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.burst.into(),
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, config: &TokenBucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        let refill_rate = f64::from(config.per_minute) / 60.0;
+        self.tokens = (self.tokens + elapsed * refill_rate).min(config.burst.into());
+        self.updated_at = now;
+    }
+
+    fn try_take(&mut self, config: &TokenBucketConfig, now: Instant) -> bool {
+        self.refill(config, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Only `Liabilities` is limited if not set
+    config: Option<RateLimitConfig>,
+    buckets: Mutex<HashMap<(IpAddr, &'static str), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: Option<RateLimitConfig>) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The bucket size and refill rate of `rpc`, None if it is not limited
+    fn bucket_config(&self, rpc: &str) -> Option<&TokenBucketConfig> {
+        if let Some(config) = self.config.as_ref().and_then(|c| c.rpcs.get(rpc)) {
+            return Some(config);
+        }
+        if rpc == "Liabilities" {
+            return Some(&LIABILITIES_RATE);
+        }
+
+        self.config.as_ref().map(|c| &c.default)
+    }
+
+    /// Takes a token from the bucket of `peer` for `rpc`, returns false if there was none left
+    fn try_acquire(&self, peer: IpAddr, rpc: &'static str) -> bool {
+        let Some(config) = self.bucket_config(rpc) else {
+            return true;
+        };
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock();
+        match buckets.get_mut(&(peer, rpc)) {
+            Some(bucket) => bucket.try_take(config, now),
+            None => {
+                let mut bucket = Bucket::full(config, now);
+                let acquired = bucket.try_take(config, now);
+                buckets.insert((peer, rpc), bucket);
+                acquired
+            }
+        }
+    }
+
+    /// Removes the buckets refilled to their burst size, returns how many were removed
+    ///
+    /// They are in the same state as a bucket created on the next request.
+    pub fn remove_full(&self) -> usize {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let len_before = buckets.len();
+        buckets.retain(|(_, rpc), bucket| match self.bucket_config(rpc) {
+            Some(config) => {
+                bucket.refill(config, now);
+                bucket.tokens < f64::from(config.burst)
+            }
+            None => false,
+        });
+
+        len_before - buckets.len()
+    }
+
+    /// Removes the full buckets at a fixed interval
+    pub async fn run_sweeper(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let removed = self.remove_full();
+            event!(name: "rate-limiter-sweep", Level::DEBUG, removed);
+        }
+    }
+}
+
+/// How a rate limited service names its RPCs, identifies its peers and rejects their requests
+pub trait Protocol {
+    type Body;
+
+    /// The bucket of the RPC called by `request`
+    fn rpc<B>(request: &http::Request<B>) -> &'static str;
+
+    fn peer<B>(request: &http::Request<B>) -> Option<IpAddr>;
+
+    fn rate_limited(rpc: &str) -> http::Response<Self::Body>;
+}
+
+/// The tonic services, requests to `/node.Node/MintQuote` are limited as `MintQuote`
+#[derive(Debug, Clone, Copy)]
+pub struct Grpc;
+
+impl Protocol for Grpc {
+    type Body = tonic::body::Body;
+
+    fn rpc<B>(request: &http::Request<B>) -> &'static str {
+        rpc_bucket(request.uri().path().rsplit('/').next().unwrap_or_default())
+    }
+
+    fn peer<B>(request: &http::Request<B>) -> Option<IpAddr> {
+        request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(TcpConnectInfo::remote_addr)
+            .map(|addr| addr.ip())
+    }
+
+    fn rate_limited(rpc: &str) -> http::Response<Self::Body> {
+        Status::resource_exhausted(format!("Rate limit exceeded for {rpc}, retry later"))
+            .into_http()
+    }
+}
+
+/// Rate limits the requests of the wrapped service
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer<P> {
+    limiter: Arc<RateLimiter>,
+    protocol: PhantomData<P>,
+}
+
+impl<P> RateLimitLayer<P> {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            limiter,
+            protocol: PhantomData,
+        }
+    }
+}
+
+impl<S, P: Clone> Layer<S> for RateLimitLayer<P> {
+    type Service = RateLimit<S, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            protocol: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit<S, P> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+    protocol: PhantomData<P>,
+}
+
+impl<S, P, B> Service<http::Request<B>> for RateLimit<S, P>
+where
+    P: Protocol,
+    S: Service<http::Request<B>, Response = http::Response<P::Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<S::Response, S::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let rpc = P::rpc(&request);
+
+        if let Some(peer) = P::peer(&request) {
+            if !self.limiter.try_acquire(peer, rpc) {
+                record_rejection("rate_limit", rpc);
+                return Either::Left(ready(Ok(P::rate_limited(rpc))));
+            }
+        }
+
+        Either::Right(self.inner.call(request))
+    }
+}
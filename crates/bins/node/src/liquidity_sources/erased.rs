@@ -0,0 +1,102 @@
+use liquidity_source::{DepositInterface, LiquiditySource, WithdrawInterface};
+use nuts::{Amount, nut05::MeltQuoteState};
+use starknet_types::Unit;
+use uuid::Uuid;
+
+/// A liquidity source whose associated types have been erased
+///
+/// Implemented for every [`LiquiditySource`], so that sources of different types can be
+/// stored together and dispatched to at runtime.
+#[async_trait::async_trait]
+pub trait ErasedLiquiditySource: Send + Sync {
+    /// The units that can be minted and melted through this liquidity source
+    fn units(&self) -> Vec<Unit>;
+
+    /// Returns the invoice id of the quote and the request to pay
+    async fn generate_deposit_payload(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+        expiry: u64,
+    ) -> Result<([u8; 32], String), anyhow::Error>;
+
+    /// Validate the melt request
+    ///
+    /// Returns the amount to melt, fee reserve included, the fee reserve and the invoice id of the quote.
+    async fn prepare_melt(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(Amount, Amount, [u8; 32]), anyhow::Error>;
+
+    /// Pay the request of a melt quote, returns the new state of the quote
+    async fn proceed_to_payment(
+        &self,
+        quote_id: Uuid,
+        request: &str,
+        expiry: u64,
+    ) -> Result<MeltQuoteState, anyhow::Error>;
+}
+
+#[async_trait::async_trait]
+impl<L> ErasedLiquiditySource for L
+where
+    L: LiquiditySource<Unit = Unit> + 'static,
+{
+    fn units(&self) -> Vec<Unit> {
+        LiquiditySource::units(self)
+    }
+
+    async fn generate_deposit_payload(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+        expiry: u64,
+    ) -> Result<([u8; 32], String), anyhow::Error> {
+        let (invoice_id, request) = self
+            .depositer()
+            .generate_deposit_payload(quote_id, unit, amount, expiry)
+            .await?;
+
+        Ok((invoice_id.into(), request))
+    }
+
+    async fn prepare_melt(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(Amount, Amount, [u8; 32]), anyhow::Error> {
+        let withdrawer = self.withdrawer();
+
+        let request = withdrawer.deserialize_payment_request(request)?;
+        let fee_reserve = withdrawer.fee_reserve(&request, unit).await?;
+        let total_amount = withdrawer
+            .compute_total_amount_expected(request, unit, fee_reserve)
+            .await?;
+        let invoice_id = self.compute_invoice_id(quote_id, expiry).await?;
+
+        Ok((total_amount, fee_reserve, invoice_id.into()))
+    }
+
+    async fn proceed_to_payment(
+        &self,
+        quote_id: Uuid,
+        request: &str,
+        expiry: u64,
+    ) -> Result<MeltQuoteState, anyhow::Error> {
+        let mut withdrawer = self.withdrawer();
+
+        let request = withdrawer.deserialize_payment_request(request)?;
+        let state = withdrawer
+            .proceed_to_payment(quote_id, request, expiry)
+            .await?;
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,193 @@
+mod erased;
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+use db_node::{SharedStorage, Storage};
+use evm_liquidity_source::EvmLiquiditySource;
+use lightning_liquidity_source::LightningLiquiditySource;
+use liquidity_source_plugin::{PluginConfig, PluginLiquiditySource};
+use starknet_liquidity_source::{
+    AssetReserves, ReservesError, ReservesReader, StarknetLiquiditySource,
+};
+use starknet_types::Unit;
+
+use crate::{initialization::ProgramArguments, methods::Method};
+
+pub use erased::ErasedLiquiditySource;
+
+/// A liquidity source, along with the units it can mint and melt
+#[derive(Clone)]
+struct RegisteredSource {
+    source: Arc<dyn ErasedLiquiditySource>,
+    units: HashSet<Unit>,
+}
+
+/// The liquidity sources of the node, by method
+///
+/// A plugin, declared in the `[plugins]` table of the config file, takes precedence over the
+/// built-in liquidity source of its method, or serves a method of its own.
+#[derive(Clone)]
+pub struct LiquiditySources {
+    sources: HashMap<Method, RegisteredSource>,
+    /// Only the built-in Starknet liquidity source can read its reserves for now
+    starknet_reserves: Option<ReservesReader>,
+}
+
+impl std::fmt::Debug for LiquiditySources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiquiditySources")
+            .field("methods", &self.sources.keys().collect::<Vec<_>>())
+            .field("starknet_reserves", &self.starknet_reserves)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[cfg(feature = "starknet")]
+    #[error("failed to init starknet liquidity source: {0}")]
+    Starknet(#[from] starknet_liquidity_source::Error),
+    #[error("failed to init lightning liquidity source: {0}")]
+    Lightning(#[from] lightning_liquidity_source::Error),
+    #[error("failed to init evm liquidity source: {0}")]
+    Evm(#[from] evm_liquidity_source::Error),
+    #[error("failed to read the liquidity source plugins config: {0}")]
+    Plugins(#[from] liquidity_source_plugin::ReadPluginsConfigError),
+    #[error("failed to connect to the liquidity source plugin of method `{0}`: {1}")]
+    Plugin(Method, #[source] liquidity_source_plugin::Error),
+    #[error("plugins.{0}: unknown method")]
+    UnknownPluginMethod(String),
+    #[cfg(not(feature = "mock"))]
+    #[error("feature {0} requires the arg `--config` to be given a value")]
+    MissingConfigFile(String),
+}
+
+impl LiquiditySources {
+    pub async fn init(storage: SharedStorage, args: ProgramArguments) -> Result<Self, Error> {
+        let plugins = match args.config.as_deref() {
+            Some(path) => liquidity_source_plugin::read_plugins_config(path)?
+                .into_iter()
+                .map(|(key, config)| {
+                    Method::from_str(&key)
+                        .map(|method| (method, config))
+                        .map_err(|_| Error::UnknownPluginMethod(key))
+                })
+                .collect::<Result<HashMap<Method, PluginConfig>, _>>()?,
+            None => HashMap::new(),
+        };
+        let mut liquidity_sources = Self {
+            sources: HashMap::new(),
+            starknet_reserves: None,
+        };
+
+        // The built-in sources replaced by a plugin are not started at all
+        if !plugins.contains_key(&Method::STARKNET) {
+            #[cfg(not(feature = "mock"))]
+            let starknet = StarknetLiquiditySource::init(
+                storage.clone(),
+                args.config
+                    .clone()
+                    .ok_or(Error::MissingConfigFile(String::from("starknet")))?,
+            )
+            .await?;
+            #[cfg(feature = "mock")]
+            let starknet = StarknetLiquiditySource::new(storage.clone());
+
+            liquidity_sources.starknet_reserves = Some(starknet.reserves.clone());
+            liquidity_sources.register(Method::STARKNET, starknet);
+        }
+
+        if !plugins.contains_key(&Method::BOLT11) {
+            #[cfg(not(feature = "mock"))]
+            let lightning = match args.config.as_deref() {
+                Some(path) => LightningLiquiditySource::init(storage.clone(), path).await?,
+                None => None,
+            };
+            // Invoices are created and paid by a node living in the process,
+            // which charges no routing fee, the 2 sats reserved being returned as change
+            #[cfg(feature = "mock")]
+            let lightning = Some(LightningLiquiditySource::new(
+                storage.clone(),
+                Arc::new(lightning_liquidity_source::FakeNode::default()),
+                2_000,
+            ));
+
+            // Only available if the config file has a `[lightning]` table
+            if let Some(lightning) = lightning {
+                liquidity_sources.register(Method::BOLT11, lightning);
+            }
+        }
+
+        if !plugins.contains_key(&Method::EVM) {
+            let evm = match args.config.as_deref() {
+                Some(path) => EvmLiquiditySource::init(storage.clone(), path).await?,
+                None => None,
+            };
+
+            // Only available if the config file has an `[evm]` table
+            if let Some(evm) = evm {
+                liquidity_sources.register(Method::EVM, evm);
+            }
+        }
+
+        for (method, config) in plugins {
+            let plugin = PluginLiquiditySource::connect(storage.clone(), method.as_str(), &config)
+                .await
+                .map_err(|e| Error::Plugin(method, e))?;
+            liquidity_sources.register(method, plugin);
+        }
+
+        Ok(liquidity_sources)
+    }
+
+    /// Serve `method` with `source`, for the units it supports
+    fn register(&mut self, method: Method, source: impl ErasedLiquiditySource + 'static) {
+        let units = ErasedLiquiditySource::units(&source).into_iter().collect();
+
+        self.sources.insert(
+            method,
+            RegisteredSource {
+                source: Arc::new(source),
+                units,
+            },
+        );
+    }
+
+    /// The liquidity source serving `method`, if any
+    pub fn get(&self, method: Method) -> Option<&dyn ErasedLiquiditySource> {
+        self.sources
+            .get(&method)
+            .map(|registered| registered.source.as_ref())
+    }
+
+    /// Whether amounts of `unit` can be minted and melted through `method`
+    pub fn supports(&self, method: Method, unit: Unit) -> bool {
+        self.sources
+            .get(&method)
+            .is_some_and(|registered| registered.units.contains(&unit))
+    }
+
+    /// Compare the on-chain reserves of the assets backing `units` against their outstanding ecash
+    ///
+    /// Only the units of the built-in `starknet` liquidity source are reported for now.
+    pub async fn read_reserves(
+        &self,
+        storage: &dyn Storage,
+        units: &[Unit],
+    ) -> Result<Vec<AssetReserves>, ReservesError> {
+        let Some(reserves) = &self.starknet_reserves else {
+            return Ok(Vec::new());
+        };
+        let starknet_units: Vec<_> = units
+            .iter()
+            .copied()
+            .filter(|unit| self.supports(Method::STARKNET, *unit))
+            .collect();
+
+        reserves.read_reserves(storage, &starknet_units).await
+    }
+}
@@ -3,13 +3,20 @@ use std::collections::HashSet;
 use thiserror::Error;
 use tonic::Status;
 
-use nuts::{Amount, nut01::PublicKey, nut02::KeysetId};
+use db_node::StorageConn;
+use nuts::{
+    Amount,
+    nut00::{ErrorCode, Proof},
+    nut01::PublicKey,
+    nut02::KeysetId,
+};
 use signer::VerifyProofsRequest;
-use sqlx::PgConnection;
 
 use crate::{
     app_state::SignerClient,
     keyset_cache::{self},
+    limits::record_rejection,
+    rest::RestError,
 };
 
 #[derive(Debug, Error)]
@@ -25,7 +32,7 @@ pub enum Error {
     #[error("the sum off all the inputs' fee must fit in a u64")]
     TotalFeeTooBig,
     #[error(transparent)]
-    Db(#[from] sqlx::Error),
+    Db(#[from] db_node::Error),
     #[error(transparent)]
     KeysetCache(#[from] keyset_cache::Error),
     #[error(transparent)]
@@ -36,6 +43,10 @@ pub enum Error {
     Used,
     #[error("amount {1} exceeds max order {2} of keyset {0}")]
     AmountExceedsMaxOrder(KeysetId, Amount, u64),
+    #[error("keyset {0} has expired, its proofs are not accepted anymore")]
+    ExpiredKeyset(KeysetId),
+    #[error("Too many inputs: got {0}, maximum allowed is {1}")]
+    TooManyInputs(usize, usize),
 }
 
 impl From<Error> for Status {
@@ -48,16 +59,54 @@ impl From<Error> for Status {
             | Error::TotalFeeTooBig
             | Error::Invalid
             | Error::Used
-            | Error::AmountExceedsMaxOrder(_, _, _) => Status::invalid_argument(value.to_string()),
-            Error::Db(sqlx::Error::RowNotFound) => Status::not_found(value.to_string()),
+            | Error::AmountExceedsMaxOrder(_, _, _)
+            | Error::ExpiredKeyset(_) => Status::invalid_argument(value.to_string()),
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                Status::not_found(value.to_string())
+            }
             Error::Db(_) | Error::KeysetCache(_) => Status::internal(value.to_string()),
             Error::Signer(status) => status,
+            Error::TooManyInputs(_, _) => Status::resource_exhausted(value.to_string()),
+        }
+    }
+}
+
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::HashOnCurve | Error::Invalid => {
+                RestError::new(ErrorCode::ProofVerificationFailed, value)
+            }
+            Error::DuplicateInput => RestError::new(ErrorCode::DuplicateInputs, value),
+            Error::Used => RestError::new(ErrorCode::ProofAlreadySpent, value),
+            Error::ExpiredKeyset(_) => RestError::new(ErrorCode::KeysetInactive, value),
+            Error::UnexpectedUnit
+            | Error::TotalAmountTooBig
+            | Error::TotalFeeTooBig
+            | Error::AmountExceedsMaxOrder(_, _, _)
+            | Error::TooManyInputs(_, _) => RestError::bad_request(value),
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                RestError::new(ErrorCode::KeysetNotFound, value)
+            }
+            Error::Db(_) => RestError::internal(value),
+            Error::KeysetCache(error) => error.into(),
+            Error::Signer(status) => status.into(),
         }
     }
 }
 
+/// Rejects a request of `rpc` spending more than `max` inputs
+pub fn check_inputs_count(rpc: &str, count: usize, max: usize) -> Result<(), Error> {
+    if count > max {
+        record_rejection("too_many_inputs", rpc);
+        return Err(Error::TooManyInputs(count, max));
+    }
+
+    Ok(())
+}
+
 pub async fn run_verification_queries(
-    conn: &mut PgConnection,
+    conn: &mut dyn StorageConn,
     secrets: HashSet<PublicKey>,
     mut signer: SignerClient,
     verify_proofs_request: Vec<signer::Proof>,
@@ -69,6 +118,7 @@ pub async fn run_verification_queries(
             })
             .await
     };
+    let ys: Vec<PublicKey> = secrets.into_iter().collect();
 
     // Parallelize the two calls
     let res = tokio::try_join!(
@@ -77,7 +127,7 @@ pub async fn run_verification_queries(
             .map_ok(|r| r.get_ref().is_valid)
             .map_err(Error::Signer),
         // Make sure those inputs were not already used
-        db_node::proof::is_any_already_spent(conn, secrets.into_iter()).map_err(Error::Db),
+        conn.is_any_proof_already_spent(&ys).map_err(Error::Db),
     );
 
     match res {
@@ -87,3 +137,19 @@ pub async fn run_verification_queries(
         Ok((true, false)) => Ok(()),
     }
 }
+
+/// Rejects the inputs if any of them is already spent
+///
+/// The inputs being verified outside of the transaction that spends them,
+/// this is run again in that transaction, in case a concurrent request spent them in the meantime.
+pub async fn check_unspent(conn: &mut dyn StorageConn, inputs: &[Proof]) -> Result<(), Error> {
+    let ys = inputs
+        .iter()
+        .map(|proof| proof.y().map_err(|_| Error::HashOnCurve))
+        .collect::<Result<Vec<_>, _>>()?;
+    if conn.is_any_proof_already_spent(&ys).await? {
+        return Err(Error::Used);
+    }
+
+    Ok(())
+}
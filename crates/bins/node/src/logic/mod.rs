@@ -1,6 +1,10 @@
 mod outputs;
-pub use outputs::{Error as OutputsError, check_outputs_allow_multiple_units, process_outputs};
+pub use outputs::{
+    Error as OutputsError, check_outputs_allow_multiple_units, check_outputs_count,
+    check_unsigned as check_outputs_unsigned, process_outputs,
+};
 mod inputs;
 pub use inputs::{
-    Error as InputsError, run_verification_queries as run_inputs_verification_queries,
+    Error as InputsError, check_inputs_count, check_unspent as check_inputs_unspent,
+    run_verification_queries as run_inputs_verification_queries,
 };
@@ -1,21 +1,23 @@
 use std::collections::HashSet;
 
-use db_node::InsertBlindSignaturesQueryBuilder;
+use db_node::StorageConn;
 use num_traits::CheckedAdd;
 use nuts::{
     Amount,
-    nut00::{BlindSignature, BlindedMessage},
+    nut00::{BlindSignature, BlindedMessage, ErrorCode},
     nut01::PublicKey,
     nut02::KeysetId,
 };
 use signer::SignBlindedMessagesRequest;
-use sqlx::PgConnection;
 use starknet_types::Unit;
 use thiserror::Error;
+use tonic::Status;
 
 use crate::{
     app_state::SignerClient,
     keyset_cache::{self, KeysetCache},
+    limits::record_rejection,
+    rest::RestError,
 };
 
 #[derive(Debug, Error)]
@@ -33,15 +35,66 @@ pub enum Error {
     #[error("amount exceeds max order for keyset {0}: amount {1} exceeds max value {2}")]
     AmountExceedsMaxOrder(KeysetId, Amount, u64),
     #[error(transparent)]
-    Db(#[from] sqlx::Error),
+    Db(#[from] db_node::Error),
     #[error(transparent)]
     Signer(#[from] tonic::Status),
     #[error(transparent)]
     KeysetCache(#[from] keyset_cache::Error),
+    #[error("Too many outputs: got {0}, maximum allowed is {1}")]
+    TooManyOutputs(usize, usize),
+}
+
+impl From<Error> for Status {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::DuplicateOutput
+            | Error::InactiveKeyset(_)
+            | Error::MultipleUnits
+            | Error::TotalAmountTooBig
+            | Error::AlreadySigned
+            | Error::AmountExceedsMaxOrder(_, _, _) => Status::invalid_argument(value.to_string()),
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                Status::not_found(value.to_string())
+            }
+            Error::Db(_) | Error::KeysetCache(_) => Status::internal(value.to_string()),
+            Error::Signer(status) => status,
+            Error::TooManyOutputs(_, _) => Status::resource_exhausted(value.to_string()),
+        }
+    }
+}
+
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::DuplicateOutput => RestError::new(ErrorCode::DuplicateOutputs, value),
+            Error::InactiveKeyset(_) => RestError::new(ErrorCode::KeysetInactive, value),
+            Error::MultipleUnits => RestError::new(ErrorCode::MultipleUnits, value),
+            Error::AlreadySigned => RestError::new(ErrorCode::OutputAlreadySigned, value),
+            Error::TotalAmountTooBig
+            | Error::AmountExceedsMaxOrder(_, _, _)
+            | Error::TooManyOutputs(_, _) => RestError::bad_request(value),
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                RestError::new(ErrorCode::KeysetNotFound, value)
+            }
+            Error::Db(_) => RestError::internal(value),
+            Error::KeysetCache(error) => error.into(),
+            Error::Signer(status) => status.into(),
+        }
+    }
+}
+
+/// Rejects a request of `rpc` asking for more than `max` outputs
+pub fn check_outputs_count(rpc: &str, count: usize, max: usize) -> Result<(), Error> {
+    if count > max {
+        record_rejection("too_many_outputs", rpc);
+        return Err(Error::TooManyOutputs(count, max));
+    }
+
+    Ok(())
 }
 
 pub async fn check_outputs_allow_multiple_units(
-    conn: &mut PgConnection,
+    conn: &mut dyn StorageConn,
     keyset_cache: KeysetCache,
     outputs: &[BlindedMessage],
 ) -> Result<Vec<(Unit, Amount)>, Error> {
@@ -87,20 +140,34 @@ pub async fn check_outputs_allow_multiple_units(
         }
     }
 
-    // Make sure those outputs were not already signed
-    if db_node::is_any_blind_message_already_used(conn, blind_secrets.into_iter()).await? {
-        Err(Error::AlreadySigned)?;
-    }
+    check_unsigned(conn, outputs).await?;
 
     Ok(total_amounts)
 }
 
-pub async fn process_outputs<'a>(
-    mut signer: SignerClient,
+/// Rejects the outputs if any of them is already signed
+///
+/// The outputs being signed outside of the transaction that stores their signatures,
+/// this is run again in that transaction, in case a concurrent request stored them in the meantime.
+pub async fn check_unsigned(
+    conn: &mut dyn StorageConn,
     outputs: &[BlindedMessage],
-) -> Result<(Vec<BlindSignature>, InsertBlindSignaturesQueryBuilder<'a>), Error> {
-    let mut query_builder = InsertBlindSignaturesQueryBuilder::new();
+) -> Result<(), Error> {
+    let blind_secrets: Vec<PublicKey> = outputs.iter().map(|bm| bm.blinded_secret).collect();
+    if conn
+        .is_any_blind_message_already_used(&blind_secrets)
+        .await?
+    {
+        return Err(Error::AlreadySigned);
+    }
 
+    Ok(())
+}
+
+pub async fn process_outputs(
+    mut signer: SignerClient,
+    outputs: &[BlindedMessage],
+) -> Result<Vec<BlindSignature>, Error> {
     let blind_signatures = signer
         .sign_blinded_messages(SignBlindedMessagesRequest {
             messages: outputs
@@ -119,18 +186,12 @@ pub async fn process_outputs<'a>(
     let blind_signatures = outputs
         .iter()
         .zip(blind_signatures)
-        .map(|(bm, bs)| {
-            let blind_signature = BlindSignature {
-                amount: bm.amount,
-                keyset_id: bm.keyset_id,
-                c: PublicKey::from_slice(&bs).expect("the signer should return valid pubkey"),
-            };
-
-            query_builder.add_row(bm.blinded_secret, &blind_signature);
-
-            blind_signature
+        .map(|(bm, bs)| BlindSignature {
+            amount: bm.amount,
+            keyset_id: bm.keyset_id,
+            c: PublicKey::from_slice(&bs).expect("the signer should return valid pubkey"),
         })
         .collect::<Vec<_>>();
 
-    Ok((blind_signatures, query_builder))
+    Ok(blind_signatures)
 }
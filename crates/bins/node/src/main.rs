@@ -4,31 +4,57 @@ compile_error!("At least one liquidity feature should be provided during compila
 use core::panic;
 use std::time::Duration;
 
+use db_node::leader::run_as_leader;
 use errors::Error;
-use gauge::DbMetricsObserver;
+use gauge::{DbMetricsObserver, ReservesObserver};
 use initialization::{
-    connect_to_db_and_run_migrations, connect_to_signer, launch_tonic_server_task,
-    read_env_variables,
+    Command, connect_to_db_and_run_migrations, connect_to_signer, launch_tonic_server_task,
+    read_env_variables, read_node_config,
 };
 use tracing::{info, trace};
 
+#[cfg(feature = "admin")]
+mod admin;
 mod app_state;
+mod audit;
+mod audit_check;
 mod errors;
+mod exchange;
 mod gauge;
 mod grpc_service;
 mod initialization;
 mod keyset_cache;
 #[cfg(feature = "keyset-rotation")]
 mod keyset_rotation;
+#[cfg(feature = "keyset-rotation")]
+mod keyset_rotator;
+mod ledger_check;
+mod limits;
 mod liquidity_sources;
 mod logic;
 mod methods;
+mod quote_expiry;
 mod response_cache;
+mod rest;
 mod routes;
 mod utils;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
+    let args = <initialization::ProgramArguments as clap::Parser>::parse();
+
+    // Runs without telemetry, so that stdout only contains the report
+    if let Some(command) = &args.command {
+        let ok = match command {
+            Command::CheckLedger => ledger_check::run().await?,
+            Command::CheckAuditLog => audit_check::run().await?,
+        };
+        if !ok {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     const PKG_NAME: &str = env!("CARGO_PKG_NAME");
     const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
     let (meter_provider, subscriber) = open_telemetry_tracing::init(PKG_NAME, PKG_VERSION);
@@ -37,41 +63,60 @@ async fn main() -> Result<(), anyhow::Error> {
     opentelemetry::global::set_meter_provider(meter_provider);
 
     info!("Initializing node...");
-    let args = <initialization::ProgramArguments as clap::Parser>::parse();
 
     // Read args and env
     let env_variables = read_env_variables()?;
+    let node_config =
+        read_node_config(args.config.as_deref()).map_err(initialization::Error::NodeConfig)?;
 
     // Connect to db
-    let pg_pool = connect_to_db_and_run_migrations(&env_variables.pg_url).await?;
+    let storage = connect_to_db_and_run_migrations(&env_variables.db_url).await?;
     info!("Connected to node database.");
 
     // Lauch the database metrics polling task
     let meter = opentelemetry::global::meter("business");
     let gauge = meter.u64_gauge("stock").build();
-    let observer = DbMetricsObserver::new(
-        pg_pool.clone(),
-        vec![starknet_types::Unit::MilliStrk],
-        gauge,
-    );
+    let observer = DbMetricsObserver::new(storage.clone(), node_config.units(), gauge);
     let _handle = tokio::spawn(gauge::run_metrics_polling(
         observer,
         Duration::from_secs(60),
     ));
 
+    // Launch the quote expiry and archival task
+    let quote_expiry_job = quote_expiry::QuoteExpiryJob::new(
+        storage.clone(),
+        node_config.ttl.quote_archival.map(Duration::from_secs),
+    );
+    let _handle = tokio::spawn(run_as_leader(storage.clone(), "quote-expiry", move || {
+        quote_expiry::run_quote_expiry_job(quote_expiry_job.clone(), Duration::from_secs(60))
+    }));
+
     // Connect to the signer service
     let signer_client = connect_to_signer(env_variables.signer_url.clone()).await?;
     info!("Connected to signer server.");
 
     let liquidity_sources =
-        liquidity_sources::LiquiditySources::init(pg_pool.clone(), args).await?;
+        liquidity_sources::LiquiditySources::init(storage.clone(), args).await?;
+
+    // Launch the reserve ratio polling task
+    let reserves_observer = ReservesObserver::new(
+        storage.clone(),
+        liquidity_sources.clone(),
+        node_config.units(),
+        meter.f64_gauge("reserve_ratio").build(),
+    );
+    let _handle = tokio::spawn(gauge::run_reserves_polling(
+        reserves_observer,
+        Duration::from_secs(60),
+    ));
 
     // Launch tonic server task
     let (address, grpc_future) = launch_tonic_server_task(
-        pg_pool.clone(),
+        storage.clone(),
         signer_client,
         liquidity_sources,
         env_variables,
+        node_config,
     )
     .await?;
 
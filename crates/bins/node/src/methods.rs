@@ -1,11 +1,83 @@
-use std::str::FromStr;
+//! The payment methods the node can mint and melt through
+//!
+//! The built-in `starknet`, `bolt11` and `evm` methods are always known.
+//! The methods of the plugins, keyed by their name in the `[plugins]` table of the config file,
+//! are registered at startup, then frozen: the first lookup, or the registration, sets them for the rest of the process.
+use std::{str::FromStr, sync::OnceLock};
 
 use serde::{Deserialize, Serialize};
-use starknet_types::STARKNET_STR;
+use starknet_types::{Asset, STARKNET_STR, Unit};
 
+pub const BOLT11_STR: &str = "bolt11";
+pub const EVM_STR: &str = "evm";
+
+/// A value of this type can only be obtained for a known method
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Method {
-    Starknet,
+pub struct Method(&'static str);
+
+impl Method {
+    pub const STARKNET: Method = Method(STARKNET_STR);
+    pub const BOLT11: Method = Method(BOLT11_STR);
+    pub const EVM: Method = Method(EVM_STR);
+    const BUILTIN: [Method; 3] = [Method::STARKNET, Method::BOLT11, Method::EVM];
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+
+    /// Whether amounts of `unit` could be deposited and withdrawn through this method, to check the config early
+    ///
+    /// The units served are the ones of the liquidity source, those of a plugin method are only known once it is connected.
+    pub fn supports_unit(&self, unit: Unit) -> bool {
+        match *self {
+            Method::STARKNET | Method::EVM => unit.asset() != Asset::BTC,
+            Method::BOLT11 => unit.asset() == Asset::BTC,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegisterMethodsError {
+    #[error(
+        "`{0}` is not a valid method name, only lowercase ascii letters and digits are allowed"
+    )]
+    InvalidName(String),
+    #[error("the methods are already frozen, plugins must be registered before they are used")]
+    AlreadyFrozen,
+}
+
+static PLUGIN_METHODS: OnceLock<Vec<Method>> = OnceLock::new();
+
+/// Freezes the methods with no plugin if nothing was registered before
+fn plugin_methods() -> &'static [Method] {
+    PLUGIN_METHODS.get_or_init(Vec::new)
+}
+
+/// Make the methods served by plugins known, and freeze them
+///
+/// A plugin named after a built-in method replaces it, its name is already known.
+pub fn register_plugin_methods(
+    names: impl IntoIterator<Item = String>,
+) -> Result<(), RegisterMethodsError> {
+    let mut methods = Vec::new();
+    for name in names {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        {
+            return Err(RegisterMethodsError::InvalidName(name));
+        }
+        if Method::BUILTIN.iter().all(|method| method.0 != name) {
+            // Registered names live as long as the process, and are only created once at startup
+            methods.push(Method(Box::leak(name.into_boxed_str())));
+        }
+    }
+
+    PLUGIN_METHODS
+        .set(methods)
+        .map_err(|_| RegisterMethodsError::AlreadyFrozen)
 }
 
 impl Serialize for Method {
@@ -13,9 +85,7 @@ impl Serialize for Method {
     where
         S: serde::Serializer,
     {
-        match self {
-            Method::Starknet => Serialize::serialize(STARKNET_STR, serializer),
-        }
+        Serialize::serialize(self.as_str(), serializer)
     }
 }
 
@@ -25,21 +95,15 @@ impl<'de> Deserialize<'de> for Method {
         D: serde::Deserializer<'de>,
     {
         let s = <&str>::deserialize(deserializer)?;
-        match s {
-            STARKNET_STR => Ok(Method::Starknet),
-            _ => Err(serde::de::Error::invalid_value(
-                serde::de::Unexpected::Str(s),
-                &"a supported method",
-            )),
-        }
+        Method::from_str(s).map_err(|_| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(s), &"a supported method")
+        })
     }
 }
 
 impl core::fmt::Display for Method {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        match self {
-            Method::Starknet => core::fmt::Display::fmt(STARKNET_STR, f),
-        }
+        core::fmt::Display::fmt(self.as_str(), f)
     }
 }
 
@@ -51,11 +115,12 @@ impl FromStr for Method {
     type Err = FromStrError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == STARKNET_STR {
-            return Ok(Self::Starknet);
-        };
-
-        Err(FromStrError)
+        Method::BUILTIN
+            .iter()
+            .chain(plugin_methods())
+            .find(|method| method.0 == s)
+            .copied()
+            .ok_or(FromStrError)
     }
 }
 
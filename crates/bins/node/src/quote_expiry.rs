@@ -0,0 +1,76 @@
+//! Background maintenance of the quote tables
+//!
+//! Unpaid quotes are marked `EXPIRED` once their expiry is elapsed,
+//! so that they stop being counted as pending deposits or withdrawals.
+//! If an archival delay is configured, the terminal quotes older than it are then moved,
+//! along with their payment events, to the history tables.
+//! An archived quote is moved back as soon as a payment for it is observed,
+//! so that the delay only bounds the size of the live tables, not when a payment can be received.
+//!
+//! It runs on the leader node only, see [`db_node::leader`].
+use std::time::Duration;
+
+use db_node::SharedStorage;
+use opentelemetry::{KeyValue, metrics::Counter};
+use tracing::{Level, error, event};
+
+#[derive(Clone)]
+pub struct QuoteExpiryJob {
+    storage: SharedStorage,
+    /// Archive the terminal quotes this long after their expiry
+    archive_after: Option<Duration>,
+    expired_counter: Counter<u64>,
+    archived_counter: Counter<u64>,
+}
+
+impl QuoteExpiryJob {
+    pub fn new(storage: SharedStorage, archive_after: Option<Duration>) -> Self {
+        let meter = opentelemetry::global::meter("business");
+
+        Self {
+            storage,
+            archive_after,
+            expired_counter: meter.u64_counter("quote.expired.count").build(),
+            archived_counter: meter.u64_counter("quote.archived.count").build(),
+        }
+    }
+
+    async fn run_once(&self) -> Result<(), db_node::Error> {
+        // Each statement is atomic on its own, no need to lock the quote tables for the whole run
+        let mut conn = self.storage.acquire().await?;
+        let expired_mint_quotes = conn.expire_unpaid_mint_quotes().await?;
+        let expired_melt_quotes = conn.expire_unpaid_melt_quotes().await?;
+        let (archived_mint_quotes, archived_melt_quotes) = match self.archive_after {
+            Some(delay) => (
+                conn.archive_mint_quotes(delay.as_secs()).await?,
+                conn.archive_melt_quotes(delay.as_secs()).await?,
+            ),
+            None => (0, 0),
+        };
+
+        for (kind, expired, archived) in [
+            ("mint", expired_mint_quotes, archived_mint_quotes),
+            ("melt", expired_melt_quotes, archived_melt_quotes),
+        ] {
+            if expired == 0 && archived == 0 {
+                continue;
+            }
+            event!(name: "quote-expiry", Level::INFO, kind, expired, archived);
+            self.expired_counter
+                .add(expired, &[KeyValue::new("kind", kind)]);
+            self.archived_counter
+                .add(archived, &[KeyValue::new("kind", kind)]);
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn run_quote_expiry_job(job: QuoteExpiryJob, interval: Duration) {
+    loop {
+        if let Err(err) = job.run_once().await {
+            error!(name: "quote-expiry", error = %err);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -1,36 +1,99 @@
-use std::{
-    fmt::Debug,
-    time::{Duration, Instant},
-};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
-use crate::errors;
-use dashmap::DashMap;
-use node::{MeltResponse, MintResponse, SwapResponse};
+use crate::{errors, utils::unix_time};
+use db_node::{SharedStorage, StorageConn};
+use hashlink::LruCache;
+use node::{ExchangeResponse, MeltResponse, MintResponse, SwapResponse};
+use nuts::nut19::{CacheResponseKey, Route};
+use opentelemetry::{KeyValue, metrics::Counter};
+use parking_lot::Mutex;
+use prost::Message;
+use tracing::{Level, event};
+
+/// The response cache shared by the gRPC handlers
+pub type SharedResponseCache =
+    Arc<dyn ResponseCache<CacheResponseKey, CachedResponse> + Send + Sync>;
 
 /// A trait that defines a cache for storing and retrieving responses.
-pub trait ResponseCache<K, V> {
+#[tonic::async_trait]
+pub trait ResponseCache<K, V>: Debug {
     /// Retrieves a value from the cache using the specified key.
-    fn get(&self, key: &K) -> Option<V>;
+    async fn get(&self, key: &K) -> Result<Option<V>, errors::Error>;
 
     /// Inserts a key-value pair into the cache.
-    fn insert(&self, key: K, value: V) -> Result<(), errors::Error>;
+    async fn insert(&self, key: K, value: V) -> Result<(), errors::Error>;
+
+    /// Inserts a key-value pair as part of `tx`, the db transaction of the operation that produced the response.
+    ///
+    /// Returns false if the cache is not stored in the database,
+    /// the pair then having to be `insert`ed once `tx` is committed.
+    async fn insert_in_tx(
+        &self,
+        _tx: &mut dyn StorageConn,
+        _key: &K,
+        _value: &V,
+    ) -> Result<bool, db_node::Error> {
+        Ok(false)
+    }
 
     /// Removes a key-value pair from the cache.
-    fn remove(&self, key: &K) -> bool;
+    async fn remove(&self, key: &K) -> Result<bool, errors::Error>;
+}
 
-    // TODO: persistent after shutting down
+/// The time source of the caches, in seconds since the unix epoch
+///
+/// Injected so that the expiry of the responses can be tested without waiting for it.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        unix_time()
+    }
 }
 
 /// An in-memory implementation of the `ResponseCache` trait with optional TTL support.
-#[allow(dead_code)]
+///
+/// Once `max_entries` is reached, the least recently used response is evicted to make room.
+/// Expired responses are removed when read, and by a sweeper running at a fixed interval,
+/// so that those never read again don't hold their memory until they are the least recently used.
 #[derive(Debug)]
 pub struct InMemResponseCache<K, V>
 where
     K: Eq + std::hash::Hash + Debug,
     V: Clone,
 {
-    store: DashMap<K, (V, Instant)>,
+    store: Mutex<LruCache<K, (V, u64)>>,
     ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    metrics: CacheMetrics,
+}
+
+#[derive(Debug)]
+struct CacheMetrics {
+    hits: Counter<u64>,
+    misses: Counter<u64>,
+    evictions: Counter<u64>,
+}
+
+impl CacheMetrics {
+    fn new() -> Self {
+        let meter = opentelemetry::global::meter("business");
+
+        Self {
+            hits: meter.u64_counter("response_cache.hit.count").build(),
+            misses: meter.u64_counter("response_cache.miss.count").build(),
+            evictions: meter.u64_counter("response_cache.eviction.count").build(),
+        }
+    }
+
+    fn record_eviction(&self, reason: &'static str) {
+        self.evictions.add(1, &[KeyValue::new("reason", reason)]);
+    }
 }
 
 impl<K, V> InMemResponseCache<K, V>
@@ -38,33 +101,206 @@ where
     K: Eq + std::hash::Hash + Debug,
     V: Clone,
 {
-    /// Creates a new in-memory response cache with the specified time-to-live duration.
-    pub fn new(ttl: Option<Duration>) -> Self {
+    /// Creates a new in-memory response cache with the specified time-to-live duration,
+    /// holding at most `max_entries` responses.
+    pub fn new(ttl: Option<Duration>, max_entries: usize) -> Self {
         Self {
-            store: DashMap::new(),
+            store: Mutex::new(LruCache::new(max_entries)),
             ttl,
+            clock: Arc::new(SystemClock),
+            metrics: CacheMetrics::new(),
         }
     }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn is_expired(&self, created_at: u64, now: u64) -> bool {
+        self.ttl
+            .is_some_and(|ttl| now.saturating_sub(created_at) >= ttl.as_secs())
+    }
 }
 
-impl<K, V> ResponseCache<K, V> for InMemResponseCache<K, V>
+impl<K, V> InMemResponseCache<K, V>
 where
-    K: Eq + std::hash::Hash + Debug,
+    K: Eq + std::hash::Hash + Debug + Clone,
     V: Clone,
 {
-    fn get(&self, key: &K) -> Option<V> {
-        let entry = self.store.get(key)?;
-        let (value, _created_at) = &*entry;
-        Some(value.clone())
+    /// Remove the expired responses, returns how many were removed
+    pub fn remove_expired(&self) -> usize {
+        if self.ttl.is_none() {
+            return 0;
+        }
+        let now = self.clock.now();
+        let mut store = self.store.lock();
+        let expired: Vec<K> = store
+            .iter()
+            .filter(|(_, (_, created_at))| self.is_expired(*created_at, now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            store.remove(key);
+        }
+        drop(store);
+
+        self.metrics
+            .evictions
+            .add(expired.len() as u64, &[KeyValue::new("reason", "expired")]);
+
+        expired.len()
+    }
+
+    /// Remove the expired responses at a fixed interval
+    pub async fn run_sweeper(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let removed = self.remove_expired();
+            event!(name: "response-cache-sweep", Level::DEBUG, removed);
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<K, V> ResponseCache<K, V> for InMemResponseCache<K, V>
+where
+    K: Eq + std::hash::Hash + Debug + Send + Sync,
+    V: Clone + Debug + Send + Sync,
+{
+    async fn get(&self, key: &K) -> Result<Option<V>, errors::Error> {
+        let now = self.clock.now();
+        let mut store = self.store.lock();
+        let value = match store.get(key) {
+            Some((value, created_at)) if !self.is_expired(*created_at, now) => Some(value.clone()),
+            Some(_) => {
+                store.remove(key);
+                self.metrics.record_eviction("expired");
+                None
+            }
+            None => None,
+        };
+        drop(store);
+
+        match value {
+            Some(_) => self.metrics.hits.add(1, &[]),
+            None => self.metrics.misses.add(1, &[]),
+        }
+
+        Ok(value)
     }
 
-    fn insert(&self, key: K, value: V) -> Result<(), errors::Error> {
-        self.store.insert(key, (value, Instant::now()));
+    async fn insert(&self, key: K, value: V) -> Result<(), errors::Error> {
+        let now = self.clock.now();
+        let mut store = self.store.lock();
+        let evicted = if !store.contains_key(&key) && store.len() >= store.capacity() {
+            store.remove_lru().is_some()
+        } else {
+            false
+        };
+        store.insert(key, (value, now));
+        drop(store);
+
+        if evicted {
+            self.metrics.record_eviction("capacity");
+        }
+
         Ok(())
     }
 
-    fn remove(&self, key: &K) -> bool {
-        self.store.remove(key).is_some()
+    async fn remove(&self, key: &K) -> Result<bool, errors::Error> {
+        Ok(self.store.lock().remove(key).is_some())
+    }
+}
+
+/// An implementation of the `ResponseCache` trait backed by the node database
+///
+/// Unlike `InMemResponseCache`, the cached responses survive a restart of the node.
+#[derive(Debug, Clone)]
+pub struct DbResponseCache {
+    storage: SharedStorage,
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl DbResponseCache {
+    pub fn new(storage: SharedStorage, ttl: Option<Duration>) -> Self {
+        Self {
+            storage,
+            ttl,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn expiry(&self) -> Option<u64> {
+        self.ttl
+            .map(|ttl| self.clock.now().saturating_add(ttl.as_secs()))
+    }
+
+    /// Delete the expired responses, returns how many were removed
+    pub async fn remove_expired(&self) -> Result<u64, db_node::Error> {
+        let mut conn = self.storage.acquire().await?;
+
+        conn.delete_expired_cached_responses(self.clock.now()).await
+    }
+
+    /// Delete the expired responses from the database at a fixed interval
+    pub async fn run_sweeper(self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            match self.remove_expired().await {
+                Ok(removed) => event!(name: "response-cache-sweep", Level::DEBUG, removed),
+                Err(err) => tracing::error!(name: "response-cache-sweeper", error = %err),
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ResponseCache<CacheResponseKey, CachedResponse> for DbResponseCache {
+    async fn get(&self, key: &CacheResponseKey) -> Result<Option<CachedResponse>, errors::Error> {
+        let mut conn = self.storage.acquire().await?;
+        let Some(bytes) = conn.get_cached_response(key, self.clock.now()).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(CachedResponse::decode(&key.0, &bytes)?))
+    }
+
+    async fn insert(
+        &self,
+        key: CacheResponseKey,
+        value: CachedResponse,
+    ) -> Result<(), errors::Error> {
+        let mut conn = self.storage.acquire().await?;
+        conn.insert_cached_response(&key, &value.encode_to_vec(), self.expiry())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert_in_tx(
+        &self,
+        tx: &mut dyn StorageConn,
+        key: &CacheResponseKey,
+        value: &CachedResponse,
+    ) -> Result<bool, db_node::Error> {
+        tx.insert_cached_response(key, &value.encode_to_vec(), self.expiry())
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn remove(&self, key: &CacheResponseKey) -> Result<bool, errors::Error> {
+        let mut conn = self.storage.acquire().await?;
+        let removed = conn.remove_cached_response(key).await?;
+
+        Ok(removed)
     }
 }
 
@@ -77,4 +313,212 @@ pub enum CachedResponse {
     Swap(SwapResponse),
     /// A response from a melt operation.
     Melt(MeltResponse),
+    /// A response from an exchange operation.
+    Exchange(ExchangeResponse),
+}
+
+impl CachedResponse {
+    fn encode_to_vec(&self) -> Vec<u8> {
+        match self {
+            CachedResponse::Mint(r) => r.encode_to_vec(),
+            CachedResponse::Swap(r) => r.encode_to_vec(),
+            CachedResponse::Melt(r) => r.encode_to_vec(),
+            CachedResponse::Exchange(r) => r.encode_to_vec(),
+        }
+    }
+
+    /// Decode a response encoded with `encode_to_vec` for `route`
+    fn decode(route: &Route, bytes: &[u8]) -> Result<Self, prost::DecodeError> {
+        Ok(match route {
+            Route::Mint => CachedResponse::Mint(MintResponse::decode(bytes)?),
+            Route::Swap => CachedResponse::Swap(SwapResponse::decode(bytes)?),
+            Route::Melt => CachedResponse::Melt(MeltResponse::decode(bytes)?),
+            Route::Exchange => CachedResponse::Exchange(ExchangeResponse::decode(bytes)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use db_node::{Storage, sqlite::SqliteStorage};
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[derive(Debug)]
+    struct MockClock(AtomicU64);
+
+    impl MockClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self(AtomicU64::new(1_700_000_000)))
+        }
+
+        fn advance(&self, duration: Duration) {
+            self.0.fetch_add(duration.as_secs(), Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> u64 {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn response(amount: u64) -> CachedResponse {
+        CachedResponse::Swap(SwapResponse {
+            signatures: vec![node::BlindSignature {
+                amount,
+                keyset_id: vec![0; 8],
+                blind_signature: vec![2; 33],
+            }],
+        })
+    }
+
+    fn amount_of(response: Option<CachedResponse>) -> Option<u64> {
+        match response? {
+            CachedResponse::Swap(response) => Some(response.signatures[0].amount),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    async fn db_cache(ttl: Option<Duration>, clock: Arc<MockClock>) -> DbResponseCache {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+
+        DbResponseCache::new(Arc::new(storage), ttl).with_clock(clock)
+    }
+
+    async fn caches(ttl: Option<Duration>) -> Vec<(Arc<MockClock>, SharedResponseCache)> {
+        let mem_clock = MockClock::new();
+        let mem_cache: SharedResponseCache =
+            Arc::new(InMemResponseCache::new(ttl, 10).with_clock(mem_clock.clone()));
+        let db_clock = MockClock::new();
+        let db_cache: SharedResponseCache = Arc::new(db_cache(ttl, db_clock.clone()).await);
+
+        vec![(mem_clock, mem_cache), (db_clock, db_cache)]
+    }
+
+    #[tokio::test]
+    async fn responses_expire_after_ttl() {
+        for (clock, cache) in caches(Some(TTL)).await {
+            let key = (Route::Swap, 1);
+            cache.insert(key, response(1)).await.unwrap();
+
+            clock.advance(TTL - Duration::from_secs(1));
+            assert_eq!(
+                amount_of(cache.get(&key).await.unwrap()),
+                Some(1),
+                "{cache:?}"
+            );
+
+            clock.advance(Duration::from_secs(1));
+            assert_eq!(amount_of(cache.get(&key).await.unwrap()), None, "{cache:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn responses_never_expire_without_ttl() {
+        for (clock, cache) in caches(None).await {
+            let key = (Route::Mint, 1);
+            cache.insert(key, response(1)).await.unwrap();
+
+            clock.advance(Duration::from_secs(10 * 365 * 24 * 3600));
+            assert_eq!(
+                amount_of(cache.get(&key).await.unwrap()),
+                Some(1),
+                "{cache:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn insert_replaces_and_restarts_ttl() {
+        for (clock, cache) in caches(Some(TTL)).await {
+            let key = (Route::Melt, 1);
+            cache.insert(key, response(1)).await.unwrap();
+            clock.advance(TTL - Duration::from_secs(1));
+            cache.insert(key, response(2)).await.unwrap();
+
+            clock.advance(TTL - Duration::from_secs(1));
+            assert_eq!(
+                amount_of(cache.get(&key).await.unwrap()),
+                Some(2),
+                "{cache:?}"
+            );
+
+            assert!(cache.remove(&key).await.unwrap());
+            assert!(!cache.remove(&key).await.unwrap());
+            assert_eq!(amount_of(cache.get(&key).await.unwrap()), None, "{cache:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn in_mem_expired_responses_are_removed_on_read() {
+        let clock = MockClock::new();
+        let cache = InMemResponseCache::new(Some(TTL), 10).with_clock(clock.clone());
+        cache.insert((Route::Swap, 1), response(1)).await.unwrap();
+        cache.insert((Route::Swap, 2), response(2)).await.unwrap();
+
+        clock.advance(TTL);
+        assert!(cache.get(&(Route::Swap, 1)).await.unwrap().is_none());
+
+        assert_eq!(cache.store.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn in_mem_sweeper_removes_expired_responses() {
+        let clock = MockClock::new();
+        let cache = InMemResponseCache::new(Some(TTL), 10).with_clock(clock.clone());
+        cache.insert((Route::Swap, 1), response(1)).await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        cache.insert((Route::Swap, 2), response(2)).await.unwrap();
+
+        clock.advance(TTL - Duration::from_secs(1));
+        assert_eq!(cache.remove_expired(), 1);
+        assert_eq!(cache.remove_expired(), 0);
+        assert_eq!(cache.store.lock().len(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.remove_expired(), 1);
+        assert_eq!(cache.store.lock().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn in_mem_evicts_least_recently_used() {
+        let cache = InMemResponseCache::new(None, 2).with_clock(MockClock::new());
+        cache.insert((Route::Swap, 1), response(1)).await.unwrap();
+        cache.insert((Route::Swap, 2), response(2)).await.unwrap();
+        // Makes the second one the least recently used
+        cache.get(&(Route::Swap, 1)).await.unwrap();
+        cache.insert((Route::Swap, 3), response(3)).await.unwrap();
+
+        assert_eq!(
+            amount_of(cache.get(&(Route::Swap, 1)).await.unwrap()),
+            Some(1)
+        );
+        assert_eq!(amount_of(cache.get(&(Route::Swap, 2)).await.unwrap()), None);
+        assert_eq!(
+            amount_of(cache.get(&(Route::Swap, 3)).await.unwrap()),
+            Some(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn db_sweeper_removes_expired_responses() {
+        let clock = MockClock::new();
+        let cache = db_cache(Some(TTL), clock.clone()).await;
+        cache.insert((Route::Swap, 1), response(1)).await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        cache.insert((Route::Swap, 2), response(2)).await.unwrap();
+
+        clock.advance(TTL - Duration::from_secs(1));
+        assert_eq!(cache.remove_expired().await.unwrap(), 1);
+        assert_eq!(cache.remove_expired().await.unwrap(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.remove_expired().await.unwrap(), 1);
+    }
 }
@@ -0,0 +1,102 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use nuts::nut00::{CashuError, ErrorCode};
+use tonic::{Code, Status};
+
+use crate::keyset_cache;
+
+/// An error returned as a Cashu error body
+///
+/// Rejected requests are answered with a `400` status, as required by NUT-00,
+/// failures of the node itself with a `500`.
+/// Unknown resources, disabled operations and rate limited requests get their own status,
+/// the Cashu error body still telling wallets what went wrong.
+#[derive(Debug)]
+pub struct RestError {
+    status: StatusCode,
+    error: CashuError,
+}
+
+impl RestError {
+    pub fn new(code: ErrorCode, detail: impl ToString) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            error: CashuError::new(code.into(), detail.to_string()),
+        }
+    }
+
+    /// A rejected request, not covered by a specific error code
+    pub fn bad_request(detail: impl ToString) -> Self {
+        Self::new(ErrorCode::Unknown, detail)
+    }
+
+    /// An unknown quote or keyset
+    pub fn not_found(code: ErrorCode, detail: impl ToString) -> Self {
+        Self::new(code, detail).with_status(StatusCode::NOT_FOUND)
+    }
+
+    /// A disabled operation
+    pub fn forbidden(code: ErrorCode, detail: impl ToString) -> Self {
+        Self::new(code, detail).with_status(StatusCode::FORBIDDEN)
+    }
+
+    /// A rate limited request
+    pub fn too_many_requests(detail: impl ToString) -> Self {
+        Self::bad_request(detail).with_status(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// The node took too long to answer
+    pub fn timeout(detail: impl ToString) -> Self {
+        Self::internal(detail).with_status(StatusCode::GATEWAY_TIMEOUT)
+    }
+
+    fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn internal(detail: impl ToString) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: CashuError::new(ErrorCode::Unknown.into(), detail.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.error)).into_response()
+    }
+}
+
+/// For the errors without a more specific mapping
+impl From<Status> for RestError {
+    fn from(value: Status) -> Self {
+        match value.code() {
+            Code::Internal
+            | Code::Unknown
+            | Code::Unavailable
+            | Code::DataLoss
+            | Code::Unimplemented => Self::internal(value.message()),
+            Code::NotFound => Self::not_found(ErrorCode::Unknown, value.message()),
+            Code::PermissionDenied => Self::forbidden(ErrorCode::Unknown, value.message()),
+            Code::ResourceExhausted => Self::too_many_requests(value.message()),
+            _ => Self::bad_request(value.message()),
+        }
+    }
+}
+
+impl From<keyset_cache::Error> for RestError {
+    fn from(value: keyset_cache::Error) -> Self {
+        match value {
+            keyset_cache::Error::UnknownKeysetId(_, _) => {
+                Self::new(ErrorCode::KeysetNotFound, value)
+            }
+            keyset_cache::Error::SignerClient(status) => status.into(),
+            keyset_cache::Error::Nut01(_) => Self::internal(value),
+        }
+    }
+}
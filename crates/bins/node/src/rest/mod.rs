@@ -0,0 +1,388 @@
+//! Cashu compatible HTTP/JSON gateway
+//!
+//! Serves the NUT-standard `/v1` endpoints next to the gRPC service,
+//! so that standard Cashu wallets and plain HTTP clients can use the node.
+//! Each handler calls the same `GrpcState::inner_*` method as its gRPC counterpart,
+//! and shares its rate limit bucket.
+mod error;
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    error_handling::HandleErrorLayer,
+    extract::{ConnectInfo, MatchedPath, Path, State},
+    response::IntoResponse,
+    routing::{get, post},
+};
+use db_node::StorageConn;
+pub use error::RestError;
+use nuts::{
+    nut00::ErrorCode,
+    nut01::SetPubKeys,
+    nut02::{KeySet, KeySetInfo, KeysResponse, KeysetId, KeysetResponse},
+    nut03::{SwapRequest, SwapResponse},
+    nut04::{MintQuoteRequest, MintQuoteResponse, MintQuoteState, MintRequest, MintResponse},
+    nut05::{MeltQuoteRequest, MeltQuoteResponse, MeltQuoteState, MeltRequest, MeltResponse},
+    nut06::NodeInfo,
+    nut07::{CheckStateRequest, CheckStateResponse},
+    nut09::{RestoreRequest, RestoreResponse},
+};
+use starknet_types::Unit;
+use tower::{BoxError, ServiceBuilder, timeout::TimeoutLayer};
+use uuid::Uuid;
+
+use crate::{
+    grpc_service::GrpcState,
+    keyset_cache,
+    limits::{OTHER_RPC, Protocol, RateLimitLayer, RateLimiter},
+    methods::Method,
+};
+
+const MAX_RESTORE_OUTPUTS: usize = 100;
+/// Answered with a `504` past this delay
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Must be served with its connect info, which the rate limiter keys its buckets with
+pub fn router(state: GrpcState, rate_limiter: Arc<RateLimiter>) -> Router {
+    Router::new()
+        .route("/v1/info", get(info))
+        .route("/v1/keys", get(keys))
+        .route("/v1/keys/{keyset_id}", get(keys_for_keyset_id))
+        .route("/v1/keysets", get(keysets))
+        .route("/v1/swap", post(swap))
+        .route("/v1/mint/quote/{method}", post(mint_quote))
+        .route("/v1/mint/quote/{method}/{quote_id}", get(mint_quote_state))
+        .route("/v1/mint/{method}", post(mint))
+        .route("/v1/melt/quote/{method}", post(melt_quote))
+        .route("/v1/melt/quote/{method}/{quote_id}", get(melt_quote_state))
+        .route("/v1/melt/{method}", post(melt))
+        .route("/v1/checkstate", post(check_state))
+        .route("/v1/restore", post(restore))
+        .route_layer(RateLimitLayer::<Rest>::new(rate_limiter))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(timed_out))
+                .layer(TimeoutLayer::new(REQUEST_TIMEOUT)),
+        )
+        .with_state(state)
+}
+
+/// The REST routes, limited as the RPC they serve
+#[derive(Debug, Clone, Copy)]
+pub struct Rest;
+
+impl Protocol for Rest {
+    type Body = axum::body::Body;
+
+    fn rpc<B>(request: &http::Request<B>) -> &'static str {
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(MatchedPath::as_str);
+        match route {
+            Some("/v1/info") => "GetNodeInfo",
+            Some("/v1/keys" | "/v1/keys/{keyset_id}") => "Keys",
+            Some("/v1/keysets") => "Keysets",
+            Some("/v1/swap") => "Swap",
+            Some("/v1/mint/quote/{method}") => "MintQuote",
+            Some("/v1/mint/quote/{method}/{quote_id}") => "MintQuoteState",
+            Some("/v1/mint/{method}") => "Mint",
+            Some("/v1/melt/quote/{method}") => "MeltQuote",
+            Some("/v1/melt/quote/{method}/{quote_id}") => "MeltQuoteState",
+            Some("/v1/melt/{method}") => "Melt",
+            Some("/v1/checkstate") => "CheckState",
+            Some("/v1/restore") => "Restore",
+            _ => OTHER_RPC,
+        }
+    }
+
+    fn peer<B>(request: &http::Request<B>) -> Option<IpAddr> {
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    fn rate_limited(rpc: &str) -> http::Response<Self::Body> {
+        RestError::too_many_requests(format!("Rate limit exceeded for {rpc}, retry later"))
+            .into_response()
+    }
+}
+
+async fn timed_out(error: BoxError) -> RestError {
+    RestError::timeout(error)
+}
+
+fn parse_method(method: &str) -> Result<Method, RestError> {
+    Method::from_str(method).map_err(RestError::bad_request)
+}
+
+fn parse_quote_id(quote_id: &str) -> Result<Uuid, RestError> {
+    Uuid::from_str(quote_id).map_err(RestError::bad_request)
+}
+
+/// Their max length is checked by the `inner_*` methods
+fn check_not_empty<T>(name: &str, items: &[T]) -> Result<(), RestError> {
+    if items.is_empty() {
+        return Err(RestError::bad_request(format!("{name} cannot be empty")));
+    }
+
+    Ok(())
+}
+
+async fn info(State(state): State<GrpcState>) -> Result<Json<NodeInfo<Method, Unit>>, RestError> {
+    Ok(Json(state.inner_node_info().await?))
+}
+
+async fn keys(State(state): State<GrpcState>) -> Result<Json<KeysResponse<Unit>>, RestError> {
+    let mut conn = state.storage.acquire().await.map_err(RestError::internal)?;
+    let active_keysets = conn
+        .get_active_keysets()
+        .await
+        .map_err(RestError::internal)?;
+
+    let mut keysets = Vec::with_capacity(active_keysets.len());
+    for (keyset_id, keyset_info) in active_keysets {
+        let unit = keyset_info
+            .parse_unit::<Unit>()
+            .map_err(RestError::internal)?
+            .unit();
+        let keys = state
+            .keyset_cache
+            .get_keyset_keys(conn.as_mut(), state.signer.clone(), keyset_id)
+            .await?;
+
+        keysets.push(KeySet {
+            id: keyset_id,
+            unit,
+            keys: SetPubKeys::from(keys),
+        });
+    }
+
+    Ok(Json(KeysResponse { keysets }))
+}
+
+async fn keys_for_keyset_id(
+    State(state): State<GrpcState>,
+    Path(keyset_id): Path<String>,
+) -> Result<Json<KeysResponse<Unit>>, RestError> {
+    let keyset_id =
+        KeysetId::from_str(&keyset_id).map_err(|e| RestError::new(ErrorCode::KeysetNotFound, e))?;
+
+    let mut conn = state.storage.acquire().await.map_err(RestError::internal)?;
+    let keys = state
+        .keyset_cache
+        .get_keyset_keys(conn.as_mut(), state.signer.clone(), keyset_id)
+        .await
+        .map_err(keyset_not_found)?;
+    let keyset_info = state
+        .keyset_cache
+        .get_keyset_info(conn.as_mut(), keyset_id)
+        .await
+        .map_err(keyset_not_found)?;
+
+    Ok(Json(KeysResponse {
+        keysets: vec![KeySet {
+            id: keyset_id,
+            unit: keyset_info.unit(),
+            keys: SetPubKeys::from(keys),
+        }],
+    }))
+}
+
+/// The requested keyset is the resource itself here, so its absence is a `404`
+fn keyset_not_found(error: keyset_cache::Error) -> RestError {
+    match error {
+        keyset_cache::Error::UnknownKeysetId(_, db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+            RestError::not_found(ErrorCode::KeysetNotFound, error)
+        }
+        error => error.into(),
+    }
+}
+
+async fn keysets(State(state): State<GrpcState>) -> Result<Json<KeysetResponse<Unit>>, RestError> {
+    let mut conn = state.storage.acquire().await.map_err(RestError::internal)?;
+
+    let keysets = conn
+        .get_keysets()
+        .await
+        .map_err(RestError::internal)?
+        .into_iter()
+        .map(|(id, unit, active, final_expiry)| {
+            Ok(KeySetInfo {
+                id: KeysetId::from_bytes(&id).map_err(RestError::internal)?,
+                unit: Unit::from_str(&unit).map_err(RestError::internal)?,
+                active,
+                input_fee_ppk: 0,
+                final_expiry,
+            })
+        })
+        .collect::<Result<Vec<_>, RestError>>()?;
+
+    Ok(Json(KeysetResponse { keysets }))
+}
+
+async fn swap(
+    State(state): State<GrpcState>,
+    Json(request): Json<SwapRequest>,
+) -> Result<Json<SwapResponse>, RestError> {
+    check_not_empty("inputs", &request.inputs)?;
+    check_not_empty("outputs", &request.outputs)?;
+
+    let signatures = state
+        .inner_swap(&request.inputs, &request.outputs, None)
+        .await?;
+
+    Ok(Json(SwapResponse { signatures }))
+}
+
+async fn mint_quote(
+    State(state): State<GrpcState>,
+    Path(method): Path<String>,
+    Json(request): Json<MintQuoteRequest<Unit>>,
+) -> Result<Json<MintQuoteResponse<Uuid>>, RestError> {
+    let method = parse_method(&method)?;
+
+    let response = state
+        .inner_mint_quote(method, request.amount, request.unit)
+        .await?;
+
+    Ok(Json(response))
+}
+
+async fn mint_quote_state(
+    State(state): State<GrpcState>,
+    Path((method, quote_id)): Path<(String, String)>,
+) -> Result<Json<MintQuoteResponse<Uuid>>, RestError> {
+    let method = parse_method(&method)?;
+    let quote_id = parse_quote_id(&quote_id)?;
+
+    let mut response = state.inner_mint_quote_state(method, quote_id).await?;
+    // Cashu has no expired state, the quote `expiry` already tells the client it expired
+    if response.state == MintQuoteState::Expired {
+        response.state = MintQuoteState::Unpaid;
+    }
+
+    Ok(Json(response))
+}
+
+async fn mint(
+    State(state): State<GrpcState>,
+    Path(method): Path<String>,
+    Json(request): Json<MintRequest<Uuid>>,
+) -> Result<Json<MintResponse>, RestError> {
+    let method = parse_method(&method)?;
+    check_not_empty("outputs", &request.outputs)?;
+
+    let signatures = state
+        .inner_mint(method, request.quote, &request.outputs, None)
+        .await?;
+
+    Ok(Json(MintResponse { signatures }))
+}
+
+async fn melt_quote(
+    State(state): State<GrpcState>,
+    Path(method): Path<String>,
+    Json(request): Json<MeltQuoteRequest<Unit>>,
+) -> Result<Json<MeltQuoteResponse<Uuid, Unit>>, RestError> {
+    let method = parse_method(&method)?;
+
+    let response = state
+        .inner_melt_quote(method, request.unit, request.request)
+        .await?;
+
+    Ok(Json(response))
+}
+
+async fn melt_quote_state(
+    State(state): State<GrpcState>,
+    Path((method, quote_id)): Path<(String, String)>,
+) -> Result<Json<MeltQuoteResponse<Uuid, Unit>>, RestError> {
+    let method = parse_method(&method)?;
+    let quote_id = parse_quote_id(&quote_id)?;
+
+    let mut response = state.inner_melt_quote_state(method, quote_id).await?;
+    // Cashu has no expired state, the quote `expiry` already tells the client it expired
+    if response.state == MeltQuoteState::Expired {
+        response.state = MeltQuoteState::Unpaid;
+    }
+
+    Ok(Json(response))
+}
+
+async fn melt(
+    State(state): State<GrpcState>,
+    Path(method): Path<String>,
+    Json(request): Json<MeltRequest<Uuid>>,
+) -> Result<Json<MeltResponse>, RestError> {
+    let method = parse_method(&method)?;
+    check_not_empty("inputs", &request.inputs)?;
+
+    let response = state
+        .inner_melt(
+            method,
+            request.quote,
+            &request.inputs,
+            request.outputs.as_deref().unwrap_or_default(),
+            None,
+        )
+        .await?;
+
+    Ok(Json(response))
+}
+
+async fn check_state(
+    State(state): State<GrpcState>,
+    Json(request): Json<CheckStateRequest>,
+) -> Result<Json<CheckStateResponse>, RestError> {
+    let response = state
+        .inner_check_state(request.ys)
+        .await
+        .map_err(tonic::Status::from)?;
+
+    Ok(Json(response))
+}
+
+async fn restore(
+    State(state): State<GrpcState>,
+    Json(request): Json<RestoreRequest>,
+) -> Result<Json<RestoreResponse>, RestError> {
+    if request.outputs.len() > MAX_RESTORE_OUTPUTS {
+        return Err(RestError::bad_request(format!(
+            "Too many outputs: maximum allowed is {MAX_RESTORE_OUTPUTS}"
+        )));
+    }
+
+    let (outputs, signatures) = state
+        .inner_restore(request.outputs)
+        .await
+        .map_err(tonic::Status::from)?
+        .into_iter()
+        .map(|res| {
+            (
+                nuts::nut00::BlindedMessage {
+                    amount: res.amount,
+                    keyset_id: res.keyset_id,
+                    blinded_secret: res.blinded_secret,
+                },
+                nuts::nut00::BlindSignature {
+                    amount: res.amount,
+                    keyset_id: res.keyset_id,
+                    c: res.blind_signature,
+                },
+            )
+        })
+        .unzip();
+
+    Ok(Json(RestoreResponse {
+        outputs,
+        signatures,
+    }))
+}
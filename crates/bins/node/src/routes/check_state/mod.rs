@@ -7,9 +7,9 @@ use crate::grpc_service::GrpcState;
 pub enum Error {
     // Database errors
     #[error("failed to acquire database connection: {0}")]
-    DbConnection(#[source] sqlx::Error),
+    DbConnection(#[source] db_node::Error),
     #[error("failed to retrieve proof state: {0}")]
-    ProofStateRetrieval(#[source] sqlx::Error),
+    ProofStateRetrieval(#[source] db_node::Error),
     #[error("invalid public key: {0}")]
     InvalidPublicKey(#[source] nuts::nut01::Error),
 }
@@ -18,7 +18,7 @@ impl From<Error> for Status {
     fn from(error: Error) -> Self {
         match error {
             Error::DbConnection(_) => Status::internal("Database connection error"),
-            Error::ProofStateRetrieval(_) => Status::not_found("Failed to retrieve proof state"),
+            Error::ProofStateRetrieval(_) => Status::internal("Failed to retrieve proof state"),
             Error::InvalidPublicKey(_) => Status::invalid_argument("Invalid public key provided"),
         }
     }
@@ -28,11 +28,9 @@ impl GrpcState {
         &self,
         ys: Vec<PublicKey>,
     ) -> Result<nuts::nut07::CheckStateResponse, Error> {
-        let mut conn = self.pg_pool.acquire().await.map_err(Error::DbConnection)?;
+        let mut conn = self.storage.acquire().await.map_err(Error::DbConnection)?;
 
-        let proof_data = db_node::proof::get_proofs_by_ids(&mut conn, &ys)
-            .await
-            .unwrap();
+        let proof_data = conn.get_proofs_states(&ys).await.unwrap();
 
         let proof_states: Result<Vec<_>, Error> = proof_data
             .iter()
@@ -0,0 +1,203 @@
+use node::ExchangeResponse;
+use nuts::{
+    Amount,
+    nut00::{BlindSignature, BlindedMessage, Proof},
+    nut19::CacheResponseKey,
+};
+use starknet_types::Unit;
+use thiserror::Error;
+use tonic::Status;
+use tracing::{Level, event};
+use uuid::Uuid;
+
+use super::swap::process_swap_inputs;
+use crate::{
+    audit::AuditEvent,
+    exchange::{self, ExchangeQuote, oracle::Rate},
+    grpc_service::{GrpcState, blind_signatures_to_grpc},
+    logic::{
+        InputsError, OutputsError, check_inputs_count, check_inputs_unspent,
+        check_outputs_allow_multiple_units, check_outputs_count, check_outputs_unsigned,
+        process_outputs,
+    },
+    response_cache::CachedResponse,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    // Db errors
+    #[error("failed to commit db tx: {0}")]
+    TxCommit(#[source] db_node::Error),
+    #[error("failed to begin db tx: {0}")]
+    TxBegin(#[source] db_node::Error),
+    #[error(transparent)]
+    Db(#[from] db_node::Error),
+    // Primitive processing errors
+    #[error(transparent)]
+    Outputs(#[from] OutputsError),
+    #[error(transparent)]
+    Inputs(#[from] InputsError),
+    // Exchange specific errors
+    #[error("exchanges are disabled")]
+    ExchangeDisabled,
+    #[error("the inputs should all be of the same unit")]
+    MultipleInputUnits,
+    #[error("the outputs should all be of the same unit")]
+    MultipleOutputUnits,
+    #[error(transparent)]
+    Exchange(#[from] exchange::Error),
+    #[error("the outputs are worth {0}, but the inputs can only buy {1} at the current rate")]
+    OutputsExceedQuote(Amount, Amount),
+}
+
+impl From<Error> for Status {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::TxBegin(error) | Error::TxCommit(error) | Error::Db(error) => {
+                Status::internal(error.to_string())
+            }
+            Error::Outputs(error) => error.into(),
+            Error::Inputs(error) => error.into(),
+            Error::ExchangeDisabled => Status::failed_precondition(value.to_string()),
+            Error::Exchange(exchange::Error::Oracle(_)) => Status::unavailable(value.to_string()),
+            Error::MultipleInputUnits
+            | Error::MultipleOutputUnits
+            | Error::Exchange(_)
+            | Error::OutputsExceedQuote(_, _) => Status::invalid_argument(value.to_string()),
+        }
+    }
+}
+
+/// Returns the only unit of `amounts`, and its amount
+fn single_unit(amounts: &[(Unit, Amount)], error: Error) -> Result<(Unit, Amount), Error> {
+    match amounts {
+        [(unit, amount)] => Ok((*unit, *amount)),
+        _ => Err(error),
+    }
+}
+
+impl GrpcState {
+    /// Price the exchange of `amount` of `input_unit` at the current rate
+    pub async fn inner_exchange_estimate(
+        &self,
+        input_unit: Unit,
+        output_unit: Unit,
+        amount: Amount,
+    ) -> Result<ExchangeQuote, Error> {
+        let exchange = self.exchange.as_ref().ok_or(Error::ExchangeDisabled)?;
+
+        Ok(exchange.quote(input_unit, output_unit, amount).await?)
+    }
+
+    /// Spend inputs of one unit to sign outputs of another, returns the signatures and the rate applied
+    ///
+    /// The response is cached under `cache_key`, if provided, as part of the operation.
+    pub async fn inner_exchange(
+        &self,
+        inputs: &[Proof],
+        outputs: &[BlindedMessage],
+        cache_key: Option<CacheResponseKey>,
+    ) -> Result<(Vec<BlindSignature>, Rate), Error> {
+        let exchange = self.exchange.as_ref().ok_or(Error::ExchangeDisabled)?;
+        check_inputs_count("Exchange", inputs.len(), self.request_limits.max_inputs)?;
+        check_outputs_count("Exchange", outputs.len(), self.request_limits.max_outputs)?;
+
+        // The verification, pricing and signing involve the signer and the oracle,
+        // they are done before the transaction so that it is only held for the writes
+        let mut conn = self.storage.acquire().await?;
+        let outputs_amounts =
+            check_outputs_allow_multiple_units(&mut *conn, self.keyset_cache.clone(), outputs)
+                .await?;
+        let (output_unit, output_amount) =
+            single_unit(&outputs_amounts, Error::MultipleOutputUnits)?;
+
+        let inputs_amounts = process_swap_inputs(
+            &mut *conn,
+            self.signer.clone(),
+            self.keyset_cache.clone(),
+            inputs,
+        )
+        .await?;
+        let (input_unit, input_amount) = single_unit(&inputs_amounts, Error::MultipleInputUnits)?;
+        drop(conn);
+
+        let quote = exchange
+            .quote(input_unit, output_unit, input_amount)
+            .await?;
+        if output_amount > quote.output_amount {
+            return Err(Error::OutputsExceedQuote(
+                output_amount,
+                quote.output_amount,
+            ));
+        }
+
+        // Outputs worth less than the quote are accepted, the difference is not refunded
+        let surplus = quote.output_amount - output_amount;
+
+        // Output process
+        let blind_signatures = process_outputs(self.signer.clone(), outputs).await?;
+
+        let exchange_id = Uuid::new_v4();
+        let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+        check_inputs_unspent(&mut *tx, inputs).await?;
+        check_outputs_unsigned(&mut *tx, outputs).await?;
+        tx.insert_spent_proofs(inputs).await?;
+        tx.insert_blind_signatures(outputs, &blind_signatures)
+            .await?;
+        tx.insert_exchange(&db_node::exchange::Exchange {
+            id: exchange_id,
+            input_unit: input_unit.to_string(),
+            input_amount,
+            output_unit: output_unit.to_string(),
+            output_amount,
+            rate: quote.rate.to_string(),
+            fee: quote.fee,
+            surplus,
+        })
+        .await?;
+        AuditEvent::Exchange {
+            exchange_id,
+            input_unit,
+            input_amount,
+            output_unit,
+            output_amount,
+            rate: quote.rate.to_string(),
+            surplus,
+        }
+        .record(&mut *tx)
+        .await?;
+        let pending_response = match cache_key {
+            Some(cache_key) => {
+                let response = CachedResponse::Exchange(ExchangeResponse {
+                    signatures: blind_signatures_to_grpc(&blind_signatures),
+                    rate: quote.rate.to_string(),
+                });
+                self.cache_response_in_tx(&mut *tx, cache_key, response)
+                    .await?
+            }
+            None => None,
+        };
+
+        tx.commit().await.map_err(Error::TxCommit)?;
+        self.cache_response(pending_response).await;
+        self.audit_sealer.notify();
+
+        event!(
+            name: "exchange",
+            Level::INFO,
+            name = "exchange",
+            %exchange_id,
+            %input_unit,
+            input_amount = u64::from(input_amount),
+            %output_unit,
+            output_amount = u64::from(output_amount),
+            rate = %quote.rate,
+            surplus = u64::from(surplus),
+        );
+        let meter = opentelemetry::global::meter("business");
+        let n_exchange_counter = meter.u64_counter("exchange.operation.count").build();
+        n_exchange_counter.add(1, &[]);
+
+        Ok((blind_signatures, quote.rate))
+    }
+}
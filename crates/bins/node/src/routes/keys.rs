@@ -1,6 +1,6 @@
+use db_node::StorageConn;
 use node::{Key, KeysetKeys};
 use nuts::nut02::KeysetId;
-use sqlx::PgConnection;
 use tonic::Status;
 
 use crate::grpc_service::GrpcState;
@@ -8,7 +8,7 @@ use crate::grpc_service::GrpcState;
 impl GrpcState {
     pub async fn inner_keys_for_keyset_id(
         &self,
-        db_conn: &mut PgConnection,
+        db_conn: &mut dyn StorageConn,
         keyset_id: Vec<u8>,
     ) -> Result<Vec<KeysetKeys>, Status> {
         let keyset_id = KeysetId::from_bytes(&keyset_id)
@@ -40,9 +40,10 @@ impl GrpcState {
 
     pub async fn inner_keys_no_keyset_id(
         &self,
-        db_conn: &mut PgConnection,
+        db_conn: &mut dyn StorageConn,
     ) -> Result<Vec<KeysetKeys>, Status> {
-        let keysets_info = db_node::keyset::get_active_keysets::<String>(db_conn)
+        let keysets_info = db_conn
+            .get_active_keysets()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
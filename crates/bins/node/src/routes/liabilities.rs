@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use db_node::Storage;
+use nuts::{
+    liabilities::{InclusionProof, MerkleSumTree, SumNode},
+    nut01::PublicKey,
+    nut02::{self, KeysetId},
+};
+use tokio::sync::Mutex;
+use tonic::Status;
+
+use crate::grpc_service::GrpcState;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to start a database transaction: {0}")]
+    DbConnection(#[source] db_node::Error),
+    #[error("failed to read the liabilities from database: {0}")]
+    Db(#[from] db_node::Error),
+    #[error("invalid keyset id in database: {0}")]
+    KeysetId(#[from] nut02::Error),
+    #[error("the liabilities of keyset {0} overflow")]
+    Overflow(KeysetId),
+}
+
+impl From<Error> for Status {
+    fn from(value: Error) -> Self {
+        Status::internal(value.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct KeysetLiabilities {
+    pub keyset_id: KeysetId,
+    pub unit: String,
+    pub issued_root: SumNode,
+    pub issued_count: u64,
+    pub burned_root: SumNode,
+    pub burned_count: u64,
+}
+
+#[derive(Debug)]
+pub struct Liabilities {
+    pub keysets: Vec<KeysetLiabilities>,
+    /// In the same order as the requested blinded secrets, None if the node never signed it
+    pub issued_proofs: Vec<Option<(KeysetId, InclusionProof)>>,
+    /// In the same order as the requested ys, None if the proof is not spent
+    pub burned_proofs: Vec<Option<(KeysetId, InclusionProof)>>,
+}
+
+/// The trees of one keyset, and the position of each of their leaves
+#[derive(Debug)]
+struct KeysetTrees {
+    keyset_id: KeysetId,
+    unit: String,
+    issued_tree: MerkleSumTree,
+    /// Leaf index of each blinded secret
+    issued_leaves: HashMap<PublicKey, usize>,
+    burned_tree: MerkleSumTree,
+    /// Leaf index of each y
+    burned_leaves: HashMap<PublicKey, usize>,
+}
+
+/// The Merkle sum trees of all the keysets, at one point in time
+#[derive(Debug)]
+struct LiabilitiesSnapshot {
+    taken_at: Instant,
+    keysets: Vec<KeysetTrees>,
+}
+
+impl LiabilitiesSnapshot {
+    /// Build the trees from the database
+    ///
+    /// They are read in a single read-only transaction so that all keysets are read at the same point in time
+    /// without holding back the writers.
+    async fn build(storage: &dyn Storage) -> Result<Self, Error> {
+        let mut tx = storage.begin_read().await.map_err(Error::DbConnection)?;
+
+        let mut keysets = Vec::new();
+        for (keyset_id, unit, _, _) in tx.get_keysets().await? {
+            let keyset_id = KeysetId::from_bytes(&keyset_id)?;
+
+            let mut issued_leaves = HashMap::new();
+            let leaves = tx
+                .get_keyset_blind_signatures(keyset_id)
+                .await?
+                .into_iter()
+                .enumerate()
+                .map(|(i, (blinded_secret, amount, blind_signature))| {
+                    issued_leaves.insert(blinded_secret, i);
+                    SumNode::issued_leaf(amount, &blinded_secret, &blind_signature)
+                })
+                .collect();
+            let issued_tree = MerkleSumTree::new(leaves).map_err(|_| Error::Overflow(keyset_id))?;
+
+            let mut burned_leaves = HashMap::new();
+            let leaves = tx
+                .get_keyset_spent_proofs(keyset_id)
+                .await?
+                .into_iter()
+                .enumerate()
+                .map(|(i, (y, amount))| {
+                    burned_leaves.insert(y, i);
+                    SumNode::burned_leaf(amount, &y)
+                })
+                .collect();
+            let burned_tree = MerkleSumTree::new(leaves).map_err(|_| Error::Overflow(keyset_id))?;
+
+            keysets.push(KeysetTrees {
+                keyset_id,
+                unit,
+                issued_tree,
+                issued_leaves,
+                burned_tree,
+                burned_leaves,
+            });
+        }
+
+        tx.commit().await?;
+
+        Ok(Self {
+            taken_at: Instant::now(),
+            keysets,
+        })
+    }
+
+    /// Proves the inclusion of each of `items` in the tree picked by `select`, None if absent from all keysets
+    fn inclusion_proofs(
+        &self,
+        items: &[PublicKey],
+        select: impl Fn(&KeysetTrees) -> (&MerkleSumTree, &HashMap<PublicKey, usize>),
+    ) -> Vec<Option<(KeysetId, InclusionProof)>> {
+        items
+            .iter()
+            .map(|item| {
+                self.keysets.iter().find_map(|keyset| {
+                    let (tree, leaves) = select(keyset);
+                    let proof = tree
+                        .inclusion_proof(*leaves.get(item)?)
+                        .expect("the leaf index is in range");
+
+                    Some((keyset.keyset_id, proof))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Serves the liabilities from a snapshot, rebuilt at most once per `ttl`
+///
+/// Building the trees reads every signature issued and proof spent by the node,
+/// which is far too costly to be done on each request.
+/// The snapshot is rebuilt lazily, by the first request made after it expired,
+/// the concurrent requests waiting for it rather than building their own.
+#[derive(Debug)]
+pub struct LiabilitiesCache {
+    ttl: Duration,
+    snapshot: Mutex<Option<Arc<LiabilitiesSnapshot>>>,
+}
+
+impl LiabilitiesCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    async fn snapshot(&self, storage: &dyn Storage) -> Result<Arc<LiabilitiesSnapshot>, Error> {
+        let mut snapshot = self.snapshot.lock().await;
+        if let Some(snapshot) = snapshot.as_ref() {
+            if snapshot.taken_at.elapsed() < self.ttl {
+                return Ok(snapshot.clone());
+            }
+        }
+
+        let new_snapshot = Arc::new(LiabilitiesSnapshot::build(storage).await?);
+        *snapshot = Some(new_snapshot.clone());
+
+        Ok(new_snapshot)
+    }
+}
+
+impl GrpcState {
+    /// The Merkle sum trees of the signatures issued and proofs burned by each keyset
+    ///
+    /// They are served from a snapshot, see [`LiabilitiesCache`],
+    /// so the operations of the last `[node.ttl] liabilities_snapshot` seconds may not be included yet.
+    pub async fn inner_liabilities(
+        &self,
+        blinded_secrets: &[PublicKey],
+        ys: &[PublicKey],
+    ) -> Result<Liabilities, Error> {
+        let snapshot = self.liabilities.snapshot(self.storage.as_ref()).await?;
+
+        Ok(Liabilities {
+            keysets: snapshot
+                .keysets
+                .iter()
+                .map(|keyset| KeysetLiabilities {
+                    keyset_id: keyset.keyset_id,
+                    unit: keyset.unit.clone(),
+                    issued_root: keyset.issued_tree.root(),
+                    issued_count: keyset.issued_tree.len() as u64,
+                    burned_root: keyset.burned_tree.root(),
+                    burned_count: keyset.burned_tree.len() as u64,
+                })
+                .collect(),
+            issued_proofs: snapshot.inclusion_proofs(blinded_secrets, |keyset| {
+                (&keyset.issued_tree, &keyset.issued_leaves)
+            }),
+            burned_proofs: snapshot
+                .inclusion_proofs(ys, |keyset| (&keyset.burned_tree, &keyset.burned_leaves)),
+        })
+    }
+}
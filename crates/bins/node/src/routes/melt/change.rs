@@ -0,0 +1,102 @@
+use nuts::{Amount, nut00::BlindedMessage};
+use primitive_types::U256;
+use starknet_types::Unit;
+
+/// The part of the fee reserve left unused by the payments of a quote
+///
+/// `amount` includes the fee reserve, and the payments are in the on-chain precision of the asset.
+/// As long as the payments don't cover the amount, the fee spent is not known and nothing is returned.
+pub fn unused_fee_reserve(
+    unit: Unit,
+    amount: Amount,
+    fee_reserve: Amount,
+    payments: Vec<(String, String)>,
+) -> Result<Amount, db_node::Error> {
+    let paid = payments
+        .into_iter()
+        .try_fold(U256::zero(), |acc, (low, high)| {
+            let low =
+                U256::from_dec_str(&low).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            let high =
+                U256::from_dec_str(&high).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            Ok::<_, db_node::Error>(acc.saturating_add(low.saturating_add(high << 128)))
+        })?;
+
+    let amount_without_fee = amount - fee_reserve;
+    if paid < unit.convert_amount_into_u256(amount_without_fee) {
+        return Ok(Amount::ZERO);
+    }
+
+    // Round up, a fraction of the unit spent is not returned
+    let scale_factor = U256::from(unit.scale_factor());
+    let spent = paid.saturating_add(scale_factor - 1) / scale_factor;
+    let spent_fee = spent - U256::from(u64::from(amount_without_fee));
+    if spent_fee >= U256::from(u64::from(fee_reserve)) {
+        return Ok(Amount::ZERO);
+    }
+
+    Ok(fee_reserve - Amount::from(spent_fee.as_u64()))
+}
+
+/// Give the amounts of the change to the blank outputs, largest first [NUT-08]
+///
+/// The outputs left over are not signed, if there are too few of them part of the change is not returned.
+pub fn change_outputs(change: Amount, blank_outputs: &[BlindedMessage]) -> Vec<BlindedMessage> {
+    let amounts: Vec<Amount> = change.split().collect();
+
+    blank_outputs
+        .iter()
+        .zip(amounts.into_iter().rev())
+        .map(|(blank_output, amount)| BlindedMessage {
+            amount,
+            ..blank_output.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(amount: u64) -> (String, String) {
+        (amount.to_string(), "0".to_string())
+    }
+
+    #[test]
+    fn returns_the_fee_left_unspent() {
+        // 10 sat with a 3 sat reserve, 1.5 sat of routing fee
+        let change = unused_fee_reserve(
+            Unit::SAT,
+            Amount::from(13u64),
+            Amount::from(3u64),
+            vec![payment(11_500)],
+        )
+        .unwrap();
+        assert_eq!(change, Amount::from(1u64));
+
+        let change = unused_fee_reserve(
+            Unit::SAT,
+            Amount::from(13u64),
+            Amount::from(3u64),
+            vec![payment(10_000)],
+        )
+        .unwrap();
+        assert_eq!(change, Amount::from(3u64));
+
+        let change = unused_fee_reserve(
+            Unit::SAT,
+            Amount::from(13u64),
+            Amount::from(3u64),
+            vec![payment(14_000)],
+        )
+        .unwrap();
+        assert_eq!(change, Amount::ZERO);
+    }
+
+    #[test]
+    fn returns_nothing_until_the_amount_is_paid() {
+        let change =
+            unused_fee_reserve(Unit::SAT, Amount::from(13u64), Amount::from(3u64), vec![]).unwrap();
+        assert_eq!(change, Amount::ZERO);
+    }
+}
@@ -1,19 +1,21 @@
-use nuts::Amount;
+use nuts::{Amount, nut00::ErrorCode};
 use starknet_types::Unit;
 use tonic::Status;
 
-use crate::{logic::InputsError, methods::Method};
+use crate::{
+    logic::{InputsError, OutputsError},
+    methods::Method,
+    rest::RestError,
+};
 
 use uuid::Uuid;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("failed to commit db tx: {0}")]
-    TxCommit(#[source] sqlx::Error),
+    TxCommit(#[source] db_node::Error),
     #[error("failed to begin db tx: {0}")]
-    TxBegin(#[source] sqlx::Error),
-    #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
+    TxBegin(#[source] db_node::Error),
     #[error(transparent)]
     Db(#[from] db_node::Error),
     #[error("melting is disabled")]
@@ -34,24 +36,28 @@ pub enum Error {
     TotalAmountTooBig,
     #[error(transparent)]
     Inputs(#[from] InputsError),
+    #[error(transparent)]
+    Outputs(#[from] OutputsError),
     #[error("total input amount {0} is lower than the minimum required {1}")]
     AmountTooLow(Amount, Amount),
     #[error("total input amount {0} is higher than the maximum allowed {1}")]
     AmountTooHigh(Amount, Amount),
     #[error(transparent)]
     InvalidPaymentRequest(serde_json::Error),
+    #[error("the payment of melt quote `{0}` failed, its inputs were not spent")]
+    PaymentFailed(Uuid),
     #[error("failed to interact with liquidity source: {0}")]
     LiquiditySource(#[source] anyhow::Error),
-    #[error("method '{0}' not supported, try compiling with the appropriate feature.")]
+    #[error(
+        "method '{0}' not supported, try configuring or compiling with the appropriate feature."
+    )]
     MethodNotSupported(Method),
 }
 
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match value {
-            Error::TxBegin(error) | Error::TxCommit(error) | Error::Sqlx(error) => {
-                Status::internal(error.to_string())
-            }
+            Error::TxBegin(error) | Error::TxCommit(error) => Status::internal(error.to_string()),
             Error::UnitNotSupported(_, _)
             | Error::AmountTooLow(_, _)
             | Error::AmountTooHigh(_, _)
@@ -59,8 +65,11 @@ impl From<Error> for Status {
             | Error::MethodNotSupported(_)
             | Error::InvalidPaymentRequest(_) => Status::invalid_argument(value.to_string()),
             Error::Inputs(error) => error.into(),
+            Error::Outputs(error) => error.into(),
             Error::Db(error) => Status::internal(error.to_string()),
-            Error::MeltDisabled => Status::failed_precondition(value.to_string()),
+            Error::MeltDisabled | Error::PaymentFailed(_) => {
+                Status::failed_precondition(value.to_string())
+            }
             Error::LiquiditySource(_) => Status::internal(value.to_string()),
             Error::QuoteNotFound(_) | Error::QuoteExpired(_) | Error::QuoteAlreadyProcessed(_) => {
                 Status::not_found(value.to_string())
@@ -70,3 +79,32 @@ impl From<Error> for Status {
         }
     }
 }
+
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                RestError::not_found(ErrorCode::Unknown, "quote not found")
+            }
+            Error::TxBegin(_) | Error::TxCommit(_) | Error::Db(_) | Error::LiquiditySource(_) => {
+                RestError::internal(value)
+            }
+            Error::UnitNotSupported(_, _) => RestError::new(ErrorCode::UnitNotSupported, value),
+            Error::AmountTooLow(_, _) | Error::AmountTooHigh(_, _) => {
+                RestError::new(ErrorCode::AmountOutsideLimit, value)
+            }
+            Error::InvalidAmount(_, _) => RestError::new(ErrorCode::TransactionUnbalanced, value),
+            Error::QuoteExpired(_) => RestError::new(ErrorCode::QuoteExpired, value),
+            Error::QuoteAlreadyProcessed(_) => RestError::new(ErrorCode::QuotePending, value),
+            Error::Inputs(error) => error.into(),
+            Error::Outputs(error) => error.into(),
+            Error::QuoteNotFound(_) => RestError::not_found(ErrorCode::Unknown, value),
+            Error::MeltDisabled => RestError::forbidden(ErrorCode::Unknown, value),
+            Error::InvalidAssetConversion
+            | Error::TotalAmountTooBig
+            | Error::InvalidPaymentRequest(_)
+            | Error::MethodNotSupported(_)
+            | Error::PaymentFailed(_) => RestError::bad_request(value),
+        }
+    }
+}
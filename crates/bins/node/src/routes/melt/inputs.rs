@@ -2,28 +2,28 @@ use num_traits::CheckedAdd;
 use starknet_types::Unit;
 use std::collections::HashSet;
 
-use db_node::InsertSpentProofsQueryBuilder;
+use db_node::StorageConn;
 use nuts::{Amount, nut00::Proof};
-use sqlx::PgConnection;
 
 use crate::{
     app_state::SignerClient,
     keyset_cache::KeysetCache,
     logic::{InputsError, run_inputs_verification_queries},
+    utils::unix_time,
 };
 
-pub async fn process_melt_inputs<'a>(
-    conn: &mut PgConnection,
+pub async fn process_melt_inputs(
+    conn: &mut dyn StorageConn,
     signer: SignerClient,
     keyset_cache: KeysetCache,
-    inputs: &'a [Proof],
+    inputs: &[Proof],
     expected_unit: Unit,
-) -> Result<(Amount, InsertSpentProofsQueryBuilder<'a>), InputsError> {
+) -> Result<Amount, InputsError> {
     let mut secrets = HashSet::new();
-    let mut query_builder = InsertSpentProofsQueryBuilder::new();
     let mut total_amount = Amount::ZERO;
 
     let mut verify_proofs_request = Vec::with_capacity(inputs.len());
+    let now = unix_time();
 
     for proof in inputs {
         let y = proof.y().map_err(|_| InputsError::HashOnCurve)?;
@@ -37,6 +37,10 @@ pub async fn process_melt_inputs<'a>(
             .await
             .map_err(InputsError::KeysetCache)?;
 
+        if keyset_info.is_expired(now) {
+            return Err(InputsError::ExpiredKeyset(proof.keyset_id));
+        }
+
         // Validate amount doesn't exceed max_order
         let max_order = keyset_info.max_order();
         let max_value = (1u64 << max_order) - 1;
@@ -59,9 +63,6 @@ pub async fn process_melt_inputs<'a>(
             .checked_add(&proof.amount)
             .ok_or(InputsError::TotalAmountTooBig)?;
 
-        // Append to insert query
-        query_builder.add_row(&y, proof);
-
         // Prepare payload for verification
         verify_proofs_request.push(signer::Proof {
             amount: proof.amount.into(),
@@ -73,5 +74,5 @@ pub async fn process_melt_inputs<'a>(
 
     run_inputs_verification_queries(conn, secrets, signer, verify_proofs_request).await?;
 
-    Ok((total_amount, query_builder))
+    Ok(total_amount)
 }
@@ -1,17 +1,29 @@
+mod change;
 mod errors;
 mod inputs;
 
+use change::{change_outputs, unused_fee_reserve};
 use inputs::process_melt_inputs;
-use liquidity_source::{LiquiditySource, WithdrawInterface};
 use nuts::Amount;
-use nuts::nut00::Proof;
+use nuts::nut00::{BlindedMessage, Proof};
 use nuts::nut05::{MeltQuoteState, MeltResponse};
+use nuts::nut19::CacheResponseKey;
 use starknet_types::Unit;
+use std::str::FromStr;
 use tracing::{Level, event};
 use uuid::Uuid;
 
+use crate::audit::AuditEvent;
+use crate::logic::{
+    OutputsError, check_inputs_count, check_inputs_unspent, check_outputs_allow_multiple_units,
+    check_outputs_count, check_outputs_unsigned, process_outputs,
+};
+use crate::response_cache::CachedResponse;
 use crate::utils::unix_time;
-use crate::{grpc_service::GrpcState, methods::Method};
+use crate::{
+    grpc_service::{GrpcState, melt_response_to_grpc},
+    methods::Method,
+};
 
 use errors::Error;
 
@@ -38,36 +50,25 @@ impl GrpcState {
                 .ok_or(Error::UnitNotSupported(unit, method))?
         };
 
-        let liquidity_source = self
-            .liquidity_sources
-            .get_liquidity_source(method)
-            .ok_or(Error::MethodNotSupported(method))?;
-        let withdrawer = liquidity_source.withdrawer();
-
-        // Validate the payment request format
-        let payment_request = withdrawer
-            .deserialize_payment_request(&melt_payment_request)
-            .map_err(|e| Error::LiquiditySource(e.into()))?;
-
-        // No fee for now
-        let fee = Amount::ZERO;
-        let total_amount = withdrawer
-            .compute_total_amount_expected(payment_request, unit, fee)
-            .map_err(|e| Error::LiquiditySource(e.into()))?;
-
         let expiry = unix_time() + self.quote_ttl.melt_ttl();
         let quote_id = Uuid::new_v4();
-        let invoice_id = liquidity_source.compute_invoice_id(quote_id, expiry);
+
+        let (total_amount, fee_reserve, invoice_id) = self
+            .liquidity_sources
+            .get(method)
+            .ok_or(Error::MethodNotSupported(method))?
+            .prepare_melt(quote_id, unit, &melt_payment_request, expiry)
+            .await
+            .map_err(Error::LiquiditySource)?;
 
         // Store the quote in database
-        let mut conn = self.pg_pool.acquire().await?;
-        db_node::melt_quote::insert_new(
-            &mut conn,
+        let mut conn = self.storage.acquire().await?;
+        conn.insert_melt_quote(
             quote_id,
-            &invoice_id.into(),
-            settings.unit,
+            &invoice_id,
+            settings.unit.as_str(),
             total_amount,
-            fee,
+            fee_reserve,
             &melt_payment_request,
             expiry,
         )
@@ -76,7 +77,8 @@ impl GrpcState {
         Ok(nuts::nut05::MeltQuoteResponse {
             quote: quote_id,
             unit,
-            amount: total_amount,
+            amount: total_amount - fee_reserve,
+            fee_reserve,
             state: nuts::nut05::MeltQuoteState::Unpaid,
             expiry,
             transfer_ids: None,
@@ -85,21 +87,34 @@ impl GrpcState {
 
     /// Step 2: Execute the melt using an existing quote ID
     /// This processes the actual payment using the previously created quote
+    ///
+    /// The unused part of the fee reserve is signed on the blank `outputs`,
+    /// if the payment settles before the melt returns (NUT-08).
     pub async fn inner_melt(
         &self,
         method: Method,
         quote_id: Uuid,
         inputs: &[Proof],
+        outputs: &[BlindedMessage],
+        cache_key: Option<CacheResponseKey>,
     ) -> Result<MeltResponse, Error> {
-        let mut conn = self.pg_pool.acquire().await?;
+        check_inputs_count("Melt", inputs.len(), self.request_limits.max_inputs)?;
+        check_outputs_count("Melt", outputs.len(), self.request_limits.max_outputs)?;
 
-        let mut tx = db_node::start_db_tx_from_conn(&mut conn)
-            .await
-            .map_err(Error::TxBegin)?;
+        // The verification of the inputs involves the signer,
+        // it is done before the transaction so that it is only held for the writes
+        let mut conn = self.storage.acquire().await?;
         // Get the existing quote from database
-        // TODO: keep a record of our fees somewhere
-        let (unit, required_amount, _fee, state, expiry, _quote_hash, payment_request) =
-            db_node::melt_quote::get_data::<Unit>(&mut tx, quote_id).await?;
+        let db_node::melt_quote::MeltQuoteData {
+            unit,
+            amount: required_amount,
+            fee: fee_reserve,
+            state,
+            expiry,
+            invoice_id,
+            request: payment_request,
+        } = conn.get_melt_quote_data(quote_id).await?;
+        let unit = Unit::from_str(&unit).map_err(|_| db_node::Error::InvalidUnit(unit))?;
 
         // Check if quote is still valid
         if expiry < unix_time() {
@@ -112,47 +127,128 @@ impl GrpcState {
         }
 
         // Process and validate inputs
-        let (total_amount, insert_spent_proof_query) = process_melt_inputs(
-            &mut tx,
+        let total_amount = process_melt_inputs(
+            &mut *conn,
             self.signer.clone(),
             self.keyset_cache.clone(),
             inputs,
             unit,
         )
         .await?;
+        // The change is signed with keysets of the unit of the quote
+        if !outputs.is_empty()
+            && check_outputs_allow_multiple_units(&mut *conn, self.keyset_cache.clone(), outputs)
+                .await?
+                .iter()
+                .any(|(output_unit, _)| *output_unit != unit)
+        {
+            return Err(OutputsError::MultipleUnits.into());
+        }
+        drop(conn);
 
         // Verify the input amount matches the quote amount
         if total_amount != required_amount {
             return Err(Error::InvalidAmount(total_amount, required_amount));
         }
 
+        let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+        // The quote may have been processed by a concurrent request meanwhile
+        if tx.get_melt_quote_data(quote_id).await?.state != MeltQuoteState::Unpaid {
+            return Err(Error::QuoteAlreadyProcessed(quote_id));
+        }
+        check_inputs_unspent(&mut *tx, inputs).await?;
         // Mark inputs as spent
-        insert_spent_proof_query.execute(&mut tx).await?;
-        db_node::melt_quote::set_state(&mut tx, quote_id, MeltQuoteState::Pending).await?;
-        tx.commit().await?;
+        tx.insert_spent_proofs(inputs).await?;
+        tx.insert_melt_quote_inputs(quote_id, inputs).await?;
+        tx.set_melt_quote_state(quote_id, MeltQuoteState::Pending)
+            .await?;
+        AuditEvent::Melt {
+            quote_id,
+            unit,
+            amount: total_amount,
+        }
+        .record(&mut *tx)
+        .await?;
+        tx.commit().await.map_err(Error::TxCommit)?;
+        self.audit_sealer.notify();
 
         // Process the actual payment
-        let state = {
-            // Get withdrawer and deserialize payment request
-            let mut withdrawer = self
-                .liquidity_sources
-                .get_liquidity_source(method)
-                .ok_or(Error::MethodNotSupported(method))?
-                .withdrawer();
-
-            // Deserialize the payment request
-            let payment_request = withdrawer
-                .deserialize_payment_request(&payment_request)
-                .map_err(|e| Error::LiquiditySource(e.into()))?;
-
-            withdrawer
-                .proceed_to_payment(quote_id, payment_request, expiry)
-                .await
-                .map_err(|e| Error::LiquiditySource(e.into()))?
+        let state = self
+            .liquidity_sources
+            .get(method)
+            .ok_or(Error::MethodNotSupported(method))?
+            .proceed_to_payment(quote_id, &payment_request, expiry)
+            .await
+            .map_err(Error::LiquiditySource)?;
+
+        // The payment failed without anything being sent, the inputs can be spent again
+        if state == MeltQuoteState::Unpaid {
+            let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+            if tx.revert_melt_quote(quote_id).await? {
+                AuditEvent::MeltReverted { quote_id }
+                    .record(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await.map_err(Error::TxCommit)?;
+            self.audit_sealer.notify();
+
+            return Err(Error::PaymentFailed(quote_id));
+        }
+
+        // Return the unused fee reserve, once the payment is known to be done
+        let change_outputs = if state == MeltQuoteState::Paid && !outputs.is_empty() {
+            let payments = self
+                .storage
+                .acquire()
+                .await?
+                .get_melt_current_paid(&invoice_id)
+                .await?;
+            let change = unused_fee_reserve(unit, required_amount, fee_reserve, payments)?;
+
+            change_outputs(change, outputs)
+        } else {
+            Vec::new()
+        };
+        let change = if change_outputs.is_empty() {
+            None
+        } else {
+            Some(process_outputs(self.signer.clone(), &change_outputs).await?)
         };
 
-        // Update quote state and transfer ID
-        db_node::melt_quote::set_state(&mut conn, quote_id, state).await?;
+        // Update quote state, and cache the response along with it
+        let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+        // The payment tracker may have settled or reverted the quote meanwhile, its state is more recent
+        if tx.get_melt_quote_data(quote_id).await?.state == MeltQuoteState::Pending {
+            tx.set_melt_quote_state(quote_id, state).await?;
+        }
+        if let Some(change) = &change {
+            check_outputs_unsigned(&mut *tx, &change_outputs).await?;
+            tx.insert_blind_signatures(&change_outputs, change).await?;
+            let amount = change_outputs
+                .iter()
+                .fold(Amount::ZERO, |acc, output| acc + output.amount);
+            tx.return_melt_quote_change(quote_id, amount).await?;
+            AuditEvent::MeltChange { quote_id, amount }
+                .record(&mut *tx)
+                .await?;
+        }
+        let (state, transfer_ids) = tx.get_melt_quote_state_and_transfer_ids(quote_id).await?;
+        let response = MeltResponse {
+            state,
+            transfer_ids,
+            change,
+        };
+        let pending_response = match cache_key {
+            Some(cache_key) => {
+                let cached = CachedResponse::Melt(melt_response_to_grpc(&response));
+                self.cache_response_in_tx(&mut *tx, cache_key, cached)
+                    .await?
+            }
+            None => None,
+        };
+        tx.commit().await.map_err(Error::TxCommit)?;
+        self.audit_sealer.notify();
+        self.cache_response(pending_response).await;
 
         let meter = opentelemetry::global::meter("business");
         let n_melt_counter = meter.u64_counter("melt.operation.count").build();
@@ -166,12 +262,6 @@ impl GrpcState {
             %quote_id,
         );
 
-        let (state, transfer_ids) =
-            db_node::melt_quote::get_state_and_transfer_ids(&mut conn, quote_id).await?;
-
-        Ok(MeltResponse {
-            state,
-            transfer_ids,
-        })
+        Ok(response)
     }
 }
@@ -1,38 +1,51 @@
-use nuts::nut05::MeltQuoteResponse;
+use nuts::{nut00::ErrorCode, nut05::MeltQuoteResponse};
 use starknet_types::Unit;
 use tonic::Status;
 use uuid::Uuid;
 
-use crate::{grpc_service::GrpcState, methods::Method};
+use crate::{grpc_service::GrpcState, methods::Method, rest::RestError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
     Db(#[from] db_node::Error),
 }
 
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
-        Status::invalid_argument(value.to_string())
+        match value {
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                Status::not_found("quote not found")
+            }
+            Error::Db(_) => Status::internal(value.to_string()),
+        }
+    }
+}
+
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                RestError::not_found(ErrorCode::Unknown, "quote not found")
+            }
+            Error::Db(_) => RestError::internal(value),
+        }
     }
 }
 
 impl GrpcState {
     pub async fn inner_melt_quote_state(
         &self,
-        method: Method,
+        // The quote ids are unique across methods
+        _method: Method,
         quote_id: Uuid,
     ) -> Result<MeltQuoteResponse<Uuid, Unit>, Error> {
-        match method {
-            Method::Starknet => {}
-        }
-
-        let mut conn = self.pg_pool.acquire().await?;
+        let mut conn = self.storage.acquire().await?;
 
-        let melt_quote_response =
-            db_node::melt_quote::build_response_from_db(&mut conn, quote_id).await?;
+        let melt_quote_response = conn
+            .get_melt_quote_response(quote_id)
+            .await?
+            .into_response(quote_id)?;
 
         Ok(melt_quote_response)
     }
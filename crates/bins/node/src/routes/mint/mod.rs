@@ -1,9 +1,11 @@
 mod outputs;
 
+use node::MintResponse;
 use nuts::{
     Amount,
-    nut00::{BlindSignature, BlindedMessage},
+    nut00::{BlindSignature, BlindedMessage, ErrorCode},
     nut04::MintQuoteState,
+    nut19::CacheResponseKey,
 };
 use outputs::check_outputs_allow_single_unit;
 use thiserror::Error;
@@ -12,20 +14,21 @@ use tracing::{Level, event};
 use uuid::Uuid;
 
 use crate::{
-    grpc_service::GrpcState,
-    logic::{OutputsError, process_outputs},
+    audit::AuditEvent,
+    grpc_service::{GrpcState, blind_signatures_to_grpc},
+    logic::{OutputsError, check_outputs_count, check_outputs_unsigned, process_outputs},
     methods::Method,
+    response_cache::CachedResponse,
+    rest::RestError,
 };
 
 #[derive(Debug, Error)]
 pub enum Error {
     // Db errors
     #[error("failed to commit db tx: {0}")]
-    TxCommit(#[source] sqlx::Error),
-    #[error("failed to commit db tx: {0}")]
-    TxBegin(#[source] sqlx::Error),
-    #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
+    TxCommit(#[source] db_node::Error),
+    #[error("failed to begin db tx: {0}")]
+    TxBegin(#[source] db_node::Error),
     #[error(transparent)]
     Db(#[from] db_node::Error),
     #[error(transparent)]
@@ -43,10 +46,9 @@ pub enum Error {
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match value {
-            Error::TxBegin(error) | Error::TxCommit(error) | Error::Sqlx(error) => {
+            Error::TxBegin(error) | Error::TxCommit(error) | Error::Db(error) => {
                 Status::internal(error.to_string())
             }
-            Error::Db(error) => Status::internal(error.to_string()),
             Error::Outputs(error) => match error {
                 OutputsError::DuplicateOutput
                 | OutputsError::InactiveKeyset(_)
@@ -56,11 +58,14 @@ impl From<Error> for Status {
                 | OutputsError::AmountExceedsMaxOrder(_, _, _) => {
                     Status::invalid_argument(error.to_string())
                 }
-                OutputsError::Db(sqlx::Error::RowNotFound) => Status::not_found(error.to_string()),
+                OutputsError::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                    Status::not_found(error.to_string())
+                }
                 OutputsError::Db(_) | OutputsError::KeysetCache(_) => {
                     Status::internal(error.to_string())
                 }
                 OutputsError::Signer(status) => status,
+                OutputsError::TooManyOutputs(_, _) => Status::resource_exhausted(error.to_string()),
             },
             Error::InvalidQuoteStateAtThisPoint(_)
             | Error::OutputsAmount { .. }
@@ -69,28 +74,52 @@ impl From<Error> for Status {
     }
 }
 
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::TxBegin(_) | Error::TxCommit(_) => RestError::internal(value),
+            Error::Db(db_node::Error::Sqlx(sqlx::Error::RowNotFound)) => {
+                RestError::not_found(ErrorCode::Unknown, "quote not found")
+            }
+            Error::Db(_) => RestError::internal(value),
+            Error::Outputs(error) => error.into(),
+            Error::InvalidQuoteStateAtThisPoint(state) => {
+                let code = match state {
+                    MintQuoteState::Unpaid => ErrorCode::QuoteNotPaid,
+                    MintQuoteState::Issued => ErrorCode::TokensAlreadyIssued,
+                    MintQuoteState::Expired => ErrorCode::QuoteExpired,
+                    MintQuoteState::Paid => ErrorCode::Unknown,
+                };
+                RestError::new(code, value)
+            }
+            Error::OutputsAmount { .. } => RestError::new(ErrorCode::TransactionUnbalanced, value),
+            Error::QuoteExpired => RestError::new(ErrorCode::QuoteExpired, value),
+        }
+    }
+}
+
 impl GrpcState {
     pub async fn inner_mint(
         &self,
         method: Method,
         quote: Uuid,
         outputs: &[BlindedMessage],
+        cache_key: Option<CacheResponseKey>,
     ) -> Result<Vec<BlindSignature>, Error> {
-        match method {
-            Method::Starknet => {}
-        }
-
-        let mut tx = db_node::begin_db_tx(&self.pg_pool).await?;
+        check_outputs_count("Mint", outputs.len(), self.request_limits.max_outputs)?;
 
-        let (expected_amount, state) =
-            db_node::mint_quote::get_amount_and_state(&mut tx, quote).await?;
+        // The verification and signing involve the signer,
+        // they are done before the transaction so that it is only held for the writes
+        let mut conn = self.storage.acquire().await?;
+        let (expected_amount, state) = conn.get_mint_quote_amount_and_state(quote).await?;
 
         if state != MintQuoteState::Paid {
             return Err(Error::InvalidQuoteStateAtThisPoint(state));
         }
 
         let total_amount =
-            check_outputs_allow_single_unit(&mut tx, &self.keyset_cache, outputs).await?;
+            check_outputs_allow_single_unit(&mut *conn, &self.keyset_cache, outputs).await?;
+        drop(conn);
 
         if total_amount != expected_amount {
             return Err(Error::OutputsAmount {
@@ -99,15 +128,39 @@ impl GrpcState {
             });
         }
 
-        let (blind_signatures, insert_blind_signatures_query_builder) =
-            process_outputs(self.signer.clone(), outputs).await?;
+        let blind_signatures = process_outputs(self.signer.clone(), outputs).await?;
 
-        insert_blind_signatures_query_builder
-            .execute(&mut tx)
+        let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+        // The quote may have been issued by a concurrent request meanwhile
+        let (_, state) = tx.get_mint_quote_amount_and_state(quote).await?;
+        if state != MintQuoteState::Paid {
+            return Err(Error::InvalidQuoteStateAtThisPoint(state));
+        }
+        check_outputs_unsigned(&mut *tx, outputs).await?;
+        tx.insert_blind_signatures(outputs, &blind_signatures)
+            .await?;
+        tx.set_mint_quote_state(quote, MintQuoteState::Issued)
             .await?;
-        db_node::mint_quote::set_state(&mut tx, quote, MintQuoteState::Issued).await?;
+        AuditEvent::Mint {
+            quote_id: quote,
+            amount: total_amount,
+        }
+        .record(&mut *tx)
+        .await?;
+        let pending_response = match cache_key {
+            Some(cache_key) => {
+                let response = CachedResponse::Mint(MintResponse {
+                    signatures: blind_signatures_to_grpc(&blind_signatures),
+                });
+                self.cache_response_in_tx(&mut *tx, cache_key, response)
+                    .await?
+            }
+            None => None,
+        };
 
-        tx.commit().await?;
+        tx.commit().await.map_err(Error::TxCommit)?;
+        self.cache_response(pending_response).await;
+        self.audit_sealer.notify();
 
         event!(
             name: "mint",
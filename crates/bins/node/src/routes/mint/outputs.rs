@@ -1,13 +1,16 @@
 use num_traits::CheckedAdd;
 use std::collections::HashSet;
 
+use db_node::StorageConn;
 use nuts::{Amount, nut00::BlindedMessage};
-use sqlx::PgConnection;
 
-use crate::{keyset_cache::KeysetCache, logic::OutputsError};
+use crate::{
+    keyset_cache::KeysetCache,
+    logic::{OutputsError, check_outputs_unsigned},
+};
 
 pub async fn check_outputs_allow_single_unit(
-    conn: &mut PgConnection,
+    conn: &mut dyn StorageConn,
     keyset_cache: &KeysetCache,
     outputs: &[BlindedMessage],
 ) -> Result<Amount, OutputsError> {
@@ -42,10 +45,7 @@ pub async fn check_outputs_allow_single_unit(
             .ok_or(OutputsError::TotalAmountTooBig)?;
     }
 
-    // Make sure those outputs were not already signed
-    if db_node::is_any_blind_message_already_used(conn, blind_secrets.into_iter()).await? {
-        return Err(OutputsError::AlreadySigned);
-    }
+    check_outputs_unsigned(conn, outputs).await?;
 
     Ok(total_amount)
 }
@@ -1,27 +1,27 @@
 use crate::grpc_service::GrpcState;
-use liquidity_source::{DepositInterface, LiquiditySource};
+use db_node::StorageConn;
 use nuts::{
     Amount,
+    nut00::ErrorCode,
     nut04::{MintQuoteResponse, MintQuoteState},
 };
-use sqlx::PgConnection;
 use starknet_types::Unit;
 use thiserror::Error;
 use tonic::Status;
 use tracing::{Level, event};
 use uuid::Uuid;
 
-use crate::{methods::Method, utils::unix_time};
+use crate::{
+    liquidity_sources::ErasedLiquiditySource, methods::Method, rest::RestError, utils::unix_time,
+};
 
 #[derive(Debug, Error)]
 pub enum Error {
     // Db errors
     #[error("failed to commit db tx: {0}")]
-    TxCommit(#[source] sqlx::Error),
-    #[error("failed to commit db tx: {0}")]
-    TxBegin(#[source] sqlx::Error),
-    #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
+    TxCommit(#[source] db_node::Error),
+    #[error("failed to begin db tx: {0}")]
+    TxBegin(#[source] db_node::Error),
     #[error(transparent)]
     Db(#[from] db_node::Error),
     #[error("failed to serialize the quote request content")]
@@ -37,17 +37,18 @@ pub enum Error {
     AmountTooHigh(Amount, Amount),
     #[error("failed to interact with liquidity source: {0}")]
     LiquiditySource(#[source] anyhow::Error),
-    #[error("method '{0}' not supported, try compiling with the appropriate feature.")]
+    #[error(
+        "method '{0}' not supported, try configuring or compiling with the appropriate feature."
+    )]
     MethodNotSupported(Method),
 }
 
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match value {
-            Error::TxBegin(error) | Error::TxCommit(error) | Error::Sqlx(error) => {
+            Error::TxBegin(error) | Error::TxCommit(error) | Error::Db(error) => {
                 Status::internal(error.to_string())
             }
-            Error::Db(error) => Status::internal(error.to_string()),
             Error::SerQuoteRequest(error) => Status::internal(error.to_string()),
             Error::MintDisabled => Status::failed_precondition(value.to_string()),
             Error::UnitNotSupported(_, _)
@@ -59,6 +60,24 @@ impl From<Error> for Status {
     }
 }
 
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::TxBegin(_) | Error::TxCommit(_) | Error::Db(_) | Error::SerQuoteRequest(_) => {
+                RestError::internal(value)
+            }
+            Error::MintDisabled => RestError::forbidden(ErrorCode::MintingDisabled, value),
+            Error::UnitNotSupported(_, _) => RestError::new(ErrorCode::UnitNotSupported, value),
+            Error::AmountTooLow(_, _) | Error::AmountTooHigh(_, _) => {
+                RestError::new(ErrorCode::AmountOutsideLimit, value)
+            }
+            Error::MethodNotSupported(_) | Error::LiquiditySource(_) => {
+                RestError::bad_request(value)
+            }
+        }
+    }
+}
+
 impl GrpcState {
     pub async fn inner_mint_quote(
         &self,
@@ -91,21 +110,18 @@ impl GrpcState {
             }
         }
 
+        let mut conn = self.storage.acquire().await?;
         let liquidity_source = self
             .liquidity_sources
-            .get_liquidity_source(method)
+            .get(method)
             .ok_or(Error::MethodNotSupported(method))?;
-
-        let mut conn = self.pg_pool.acquire().await?;
-        let response = match method {
-            Method::Starknet => create_new_mint_quote(
-                &mut conn,
-                liquidity_source.depositer(),
-                amount,
-                unit,
-                self.quote_ttl.mint_ttl(),
-            ),
-        }
+        let response = create_new_mint_quote(
+            conn.as_mut(),
+            liquidity_source,
+            amount,
+            unit,
+            self.quote_ttl.mint_ttl(),
+        )
         .await?;
 
         event!(
@@ -124,8 +140,8 @@ impl GrpcState {
 
 /// Initialize a new mint quote
 async fn create_new_mint_quote(
-    conn: &mut PgConnection,
-    depositer: impl DepositInterface,
+    conn: &mut dyn StorageConn,
+    liquidity_source: &dyn ErasedLiquiditySource,
     amount: Amount,
     unit: Unit,
     mint_ttl: u64,
@@ -133,15 +149,15 @@ async fn create_new_mint_quote(
     let expiry = unix_time() + mint_ttl;
     let quote_id = Uuid::new_v4();
 
-    let (invoice_id, request) = depositer
+    let (invoice_id, request) = liquidity_source
         .generate_deposit_payload(quote_id, unit, amount, expiry)
-        .map_err(|e| Error::LiquiditySource(e.into()))?;
+        .await
+        .map_err(Error::LiquiditySource)?;
 
-    db_node::mint_quote::insert_new(
-        conn,
+    conn.insert_mint_quote(
         quote_id,
-        invoice_id.into(),
-        unit,
+        invoice_id,
+        unit.as_str(),
         amount,
         &request,
         expiry,
@@ -153,12 +169,22 @@ async fn create_new_mint_quote(
         // If running with no backend, we immediatly set the state to paid
         #[cfg(feature = "mock")]
         {
-            use futures::TryFutureExt;
-
+            // Stands for the deposit an indexer would have recorded, so that the ledger is consistent
+            let deposit = unit.convert_amount_into_u256(amount);
+            conn.insert_mint_payment_event(&db_node::PaymentEvent {
+                block_id: "mock".to_string(),
+                tx_hash: quote_id.to_string(),
+                event_idx: 0,
+                asset: unit.asset().to_string(),
+                payee: "mock".to_string(),
+                invoice_id,
+                payer: "mock".to_string(),
+                amount_low: deposit.low_u128().to_string(),
+                amount_high: (deposit >> 128).low_u128().to_string(),
+            })
+            .await?;
             let new_state = MintQuoteState::Paid;
-            db_node::mint_quote::set_state(conn, quote_id, new_state)
-                .map_err(Error::Sqlx)
-                .await?;
+            conn.set_mint_quote_state(quote_id, new_state).await?;
             new_state
         }
 
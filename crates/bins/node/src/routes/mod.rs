@@ -1,9 +1,15 @@
 mod check_state;
+mod exchange;
 mod keys;
+mod liabilities;
 mod melt;
 mod melt_quote_state;
 mod mint;
 mod mint_quote;
 mod mint_quote_state;
+mod node_info;
+mod reserves;
 mod restore;
 mod swap;
+
+pub use liabilities::LiabilitiesCache;
@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+use nuts::{
+    nut01::PublicKey,
+    nut06::{NodeInfo, NodeVersion},
+};
+use signer::GetRootPubKeyRequest;
+use starknet_types::Unit;
+use tonic::{Request, Status};
+
+use crate::{grpc_service::GrpcState, methods::Method};
+
+impl GrpcState {
+    pub async fn inner_node_info(&self) -> Result<NodeInfo<Method, Unit>, Status> {
+        let nuts_config = {
+            let nuts_read_lock = self.nuts.read().await;
+            nuts_read_lock.clone()
+        };
+        let pub_key = self
+            .signer
+            .clone()
+            .get_root_pub_key(Request::new(GetRootPubKeyRequest {}))
+            .await?
+            .into_inner()
+            .root_pubkey;
+
+        Ok(NodeInfo {
+            name: self.node_info.name.clone(),
+            pubkey: Some(
+                PublicKey::from_str(&pub_key).map_err(|e| Status::internal(e.to_string()))?,
+            ),
+            version: Some(NodeVersion {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            }),
+            description: self.node_info.description.clone(),
+            description_long: self.node_info.description_long.clone(),
+            contact: self.node_info.contact.clone(),
+            nuts: nuts_config,
+            icon_url: self.node_info.icon_url.clone(),
+            urls: self.node_info.urls.clone(),
+            motd: self.node_info.motd.clone(),
+            time: Some(std::time::UNIX_EPOCH.elapsed().unwrap().as_secs()),
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use starknet_liquidity_source::{AssetReserves, ReservesError};
+use starknet_types::Unit;
+use tonic::Status;
+
+use crate::grpc_service::GrpcState;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read the reserves: {0}")]
+    Reserves(#[from] ReservesError),
+}
+
+impl From<Error> for Status {
+    fn from(value: Error) -> Self {
+        Status::internal(value.to_string())
+    }
+}
+
+impl GrpcState {
+    /// Read the reserves of each asset backing the units the node mints or melts
+    pub async fn inner_reserves(&self) -> Result<Vec<AssetReserves>, Error> {
+        let units: Vec<Unit> = {
+            let read_nuts_settings_lock = self.nuts.read().await;
+
+            read_nuts_settings_lock
+                .nut04
+                .methods
+                .iter()
+                .map(|m| m.unit)
+                .chain(read_nuts_settings_lock.nut05.methods.iter().map(|m| m.unit))
+                .collect()
+        };
+
+        let reserves = self
+            .liquidity_sources
+            .read_reserves(self.storage.as_ref(), &units)
+            .await?;
+
+        Ok(reserves)
+    }
+}
@@ -7,7 +7,7 @@ use crate::grpc_service::GrpcState;
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("failed to acquire connection from the pool: {0}")]
-    Sqlx(#[from] sqlx::Error),
+    DbConnection(#[source] db_node::Error),
     #[error("failed to get the secrets from database: {0}")]
     Db(#[from] db_node::Error),
 }
@@ -15,7 +15,7 @@ pub enum Error {
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match value {
-            Error::Sqlx(error) => Status::internal(error.to_string()),
+            Error::DbConnection(error) => Status::internal(error.to_string()),
             Error::Db(error) => Status::internal(error.to_string()),
         }
     }
@@ -26,12 +26,9 @@ impl GrpcState {
         &self,
         blind_messages: Vec<BlindedMessage>,
     ) -> Result<Vec<RestoreFromDbResponse>, Error> {
-        let mut conn = self.pg_pool.acquire().await?;
-        let secret_with_signatures = db_node::blind_signature::get_by_blind_secrets(
-            &mut conn,
-            blind_messages.iter().map(|bm| bm.blinded_secret),
-        )
-        .await?;
+        let mut conn = self.storage.acquire().await.map_err(Error::DbConnection)?;
+        let blind_secrets: Vec<_> = blind_messages.iter().map(|bm| bm.blinded_secret).collect();
+        let secret_with_signatures = conn.get_blind_signatures(&blind_secrets).await?;
 
         Ok(secret_with_signatures)
     }
@@ -1,29 +1,29 @@
 use num_traits::CheckedAdd;
 use std::collections::HashSet;
 
-use db_node::InsertSpentProofsQueryBuilder;
+use db_node::StorageConn;
 use nuts::{Amount, nut00::Proof};
-use sqlx::PgConnection;
 use starknet_types::Unit;
 
 use crate::{
     app_state::SignerClient,
     keyset_cache::KeysetCache,
     logic::{InputsError, run_inputs_verification_queries},
+    utils::unix_time,
 };
 
-pub async fn process_swap_inputs<'a>(
-    conn: &mut PgConnection,
+pub async fn process_swap_inputs(
+    conn: &mut dyn StorageConn,
     signer: SignerClient,
     keyset_cache: KeysetCache,
-    inputs: &'a [Proof],
-) -> Result<(Vec<(Unit, Amount)>, InsertSpentProofsQueryBuilder<'a>), InputsError> {
+    inputs: &[Proof],
+) -> Result<Vec<(Unit, Amount)>, InputsError> {
     // Input process
     let mut secrets = HashSet::new();
     let mut amounts_per_unit: Vec<(Unit, Amount)> = Vec::new();
-    let mut query_builder = InsertSpentProofsQueryBuilder::new();
 
     let mut verify_proofs_request = Vec::with_capacity(inputs.len());
+    let now = unix_time();
 
     for proof in inputs {
         let y = proof.y().map_err(|_| InputsError::HashOnCurve)?;
@@ -34,6 +34,10 @@ pub async fn process_swap_inputs<'a>(
 
         let keyset_info = keyset_cache.get_keyset_info(conn, proof.keyset_id).await?;
 
+        if keyset_info.is_expired(now) {
+            return Err(InputsError::ExpiredKeyset(proof.keyset_id));
+        }
+
         let keyset_unit = keyset_info.unit();
 
         // Validate amount doesn't exceed max_order
@@ -57,9 +61,6 @@ pub async fn process_swap_inputs<'a>(
             None => amounts_per_unit.push((keyset_unit, proof.amount)),
         }
 
-        // Append to insert query
-        query_builder.add_row(&y, proof);
-
         // Prepare payload for verification
         verify_proofs_request.push(signer::Proof {
             keyset_id: proof.keyset_id.to_bytes().to_vec(),
@@ -71,5 +72,5 @@ pub async fn process_swap_inputs<'a>(
 
     run_inputs_verification_queries(conn, secrets, signer, verify_proofs_request).await?;
 
-    Ok((amounts_per_unit, query_builder))
+    Ok(amounts_per_unit)
 }
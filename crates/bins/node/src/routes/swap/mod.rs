@@ -1,9 +1,11 @@
 mod inputs;
 
-use inputs::process_swap_inputs;
+pub(super) use inputs::process_swap_inputs;
+use node::SwapResponse;
 use nuts::{
     Amount,
-    nut00::{BlindSignature, BlindedMessage, Proof},
+    nut00::{BlindSignature, BlindedMessage, ErrorCode, Proof},
+    nut19::CacheResponseKey,
 };
 use starknet_types::Unit;
 use thiserror::Error;
@@ -11,19 +13,26 @@ use tonic::Status;
 use tracing::{Level, event};
 
 use crate::{
-    grpc_service::GrpcState,
-    logic::{InputsError, OutputsError, check_outputs_allow_multiple_units, process_outputs},
+    audit::AuditEvent,
+    grpc_service::{GrpcState, blind_signatures_to_grpc},
+    logic::{
+        InputsError, OutputsError, check_inputs_count, check_inputs_unspent,
+        check_outputs_allow_multiple_units, check_outputs_count, check_outputs_unsigned,
+        process_outputs,
+    },
+    response_cache::CachedResponse,
+    rest::RestError,
 };
 
 #[derive(Debug, Error)]
 pub enum Error {
     // Db errors
     #[error("failed to commit db tx: {0}")]
-    TxCommit(#[source] sqlx::Error),
-    #[error("failed to commit db tx: {0}")]
-    TxBegin(#[source] sqlx::Error),
+    TxCommit(#[source] db_node::Error),
+    #[error("failed to begin db tx: {0}")]
+    TxBegin(#[source] db_node::Error),
     #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
+    Db(#[from] db_node::Error),
     // Primitive processing errors
     #[error(transparent)]
     Outputs(#[from] OutputsError),
@@ -41,24 +50,10 @@ pub enum Error {
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match value {
-            Error::TxBegin(error) | Error::TxCommit(error) | Error::Sqlx(error) => {
+            Error::TxBegin(error) | Error::TxCommit(error) | Error::Db(error) => {
                 Status::internal(error.to_string())
             }
-            Error::Outputs(error) => match error {
-                OutputsError::DuplicateOutput
-                | OutputsError::InactiveKeyset(_)
-                | OutputsError::MultipleUnits
-                | OutputsError::TotalAmountTooBig
-                | OutputsError::AlreadySigned
-                | OutputsError::AmountExceedsMaxOrder(_, _, _) => {
-                    Status::invalid_argument(error.to_string())
-                }
-                OutputsError::Db(sqlx::Error::RowNotFound) => Status::not_found(error.to_string()),
-                OutputsError::Db(_) | OutputsError::KeysetCache(_) => {
-                    Status::internal(error.to_string())
-                }
-                OutputsError::Signer(status) => status,
-            },
+            Error::Outputs(error) => error.into(),
             Error::Inputs(error) => error.into(),
             Error::UnbalancedUnits
             | Error::TransactionUnbalanced(_, _, _)
@@ -67,29 +62,50 @@ impl From<Error> for Status {
     }
 }
 
+impl From<Error> for RestError {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::TxBegin(_) | Error::TxCommit(_) | Error::Db(_) => RestError::internal(value),
+            Error::Outputs(error) => error.into(),
+            Error::Inputs(error) => error.into(),
+            Error::UnbalancedUnits | Error::TransactionUnbalanced(_, _, _) => {
+                RestError::new(ErrorCode::TransactionUnbalanced, value)
+            }
+            Error::TotalOutputAndFeeTooBig => RestError::bad_request(value),
+        }
+    }
+}
+
 impl GrpcState {
+    /// Signs `outputs` in exchange of `inputs`
+    ///
+    /// The response is cached under `cache_key`, if provided, as part of the operation.
     pub async fn inner_swap(
         &self,
         inputs: &[Proof],
         outputs: &[BlindedMessage],
+        cache_key: Option<CacheResponseKey>,
     ) -> Result<Vec<BlindSignature>, Error> {
-        let mut tx = db_node::begin_db_tx(&self.pg_pool)
-            .await
-            .map_err(Error::TxBegin)?;
+        check_inputs_count("Swap", inputs.len(), self.request_limits.max_inputs)?;
+        check_outputs_count("Swap", outputs.len(), self.request_limits.max_outputs)?;
 
+        // The verification and signing involve the signer,
+        // they are done before the transaction so that it is only held for the writes
+        let mut conn = self.storage.acquire().await?;
         let outputs_amounts =
-            check_outputs_allow_multiple_units(&mut tx, self.keyset_cache.clone(), outputs)
+            check_outputs_allow_multiple_units(&mut *conn, self.keyset_cache.clone(), outputs)
                 .await
                 .map_err(Error::Outputs)?;
 
-        let (input_fees_and_amount, insert_spent_proofs_query_builder) = process_swap_inputs(
-            &mut tx,
+        let input_fees_and_amount = process_swap_inputs(
+            &mut *conn,
             self.signer.clone(),
             self.keyset_cache.clone(),
             inputs,
         )
         .await
         .map_err(Error::Inputs)?;
+        drop(conn);
 
         // Amount matching
         for (unit, output_amount) in outputs_amounts.iter() {
@@ -108,15 +124,35 @@ impl GrpcState {
         }
 
         // Output process
-        let (blind_signatures, insert_blind_signatures_query_builder) =
-            process_outputs(self.signer.clone(), outputs).await?;
+        let blind_signatures = process_outputs(self.signer.clone(), outputs).await?;
 
-        insert_spent_proofs_query_builder.execute(&mut tx).await?;
-        insert_blind_signatures_query_builder
-            .execute(&mut tx)
+        let mut tx = self.storage.begin().await.map_err(Error::TxBegin)?;
+        check_inputs_unspent(&mut *tx, inputs).await?;
+        check_outputs_unsigned(&mut *tx, outputs).await?;
+        tx.insert_spent_proofs(inputs).await?;
+        tx.insert_blind_signatures(outputs, &blind_signatures)
             .await?;
+        AuditEvent::Swap {
+            amounts: outputs_amounts.clone(),
+            n_inputs: inputs.len(),
+            n_outputs: outputs.len(),
+        }
+        .record(&mut *tx)
+        .await?;
+        let pending_response = match cache_key {
+            Some(cache_key) => {
+                let response = CachedResponse::Swap(SwapResponse {
+                    signatures: blind_signatures_to_grpc(&blind_signatures),
+                });
+                self.cache_response_in_tx(&mut *tx, cache_key, response)
+                    .await?
+            }
+            None => None,
+        };
 
         tx.commit().await.map_err(Error::TxCommit)?;
+        self.cache_response(pending_response).await;
+        self.audit_sealer.notify();
 
         event!(
             name: "swap",
@@ -0,0 +1,22 @@
+use tokio::sync::oneshot;
+use tonic::Status;
+
+/// Run a CPU bound closure on the rayon thread pool and await its result
+///
+/// Curve operations are expensive enough that running them inline would starve the tokio
+/// workers serving other requests, so they are offloaded to a dedicated pool.
+pub async fn run<F, R>(f: F) -> Result<R, Status>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    rayon::spawn(move || {
+        // The receiver is only dropped if the request was cancelled, nothing to do then
+        let _ = tx.send(f());
+    });
+
+    rx.await
+        .map_err(|_| Status::internal("cpu pool task panicked"))
+}
@@ -0,0 +1,554 @@
+//! Per keyset accounting of the amounts signed and verified
+//!
+//! The signer is the only service holding the keys, so it is the last line of defense against
+//! a compromised node trying to issue more tokens than it should.
+//! Operators can configure a cap for any keyset, on its outstanding issuance:
+//! the amount signed minus the amount verified, which is what remains in circulation.
+//! Swaps and melt change, whose inputs are verified before the outputs are signed,
+//! leave it unchanged, only mints increase it. The signer refuses to sign over the cap.
+//!
+//! The counters in memory are authoritative, so that signing never waits on the disk.
+//! When a state file is configured, they are flushed to it periodically, so that they survive restarts,
+//! the batches of the last interval being lost on a crash. Access to the file is serialized with an exclusive lock
+//! on a sibling `.lock` file, which lets several signer replicas share the same counters
+//! through a common volume, each one seeing the batches of the others once they are flushed.
+//! Without a state file, counters only live in memory.
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use fs4::fs_std::FileExt;
+use nuts::{Amount, nut02::KeysetId};
+use opentelemetry::{KeyValue, metrics::Counter};
+use tracing::error;
+
+use crate::server_errors::Error;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseCapsError {
+    #[error("expected `<keyset_id>=<amount>`, got `{0}`")]
+    BadFormat(String),
+    #[error("invalid keyset id `{0}`: {1}")]
+    KeysetId(String, nuts::nut02::Error),
+    #[error("invalid amount `{0}`: {1}")]
+    Amount(String, std::num::ParseIntError),
+}
+
+/// Parse caps from a comma separated list of `<keyset_id>=<amount>` pairs
+///
+/// Keyset ids are hex encoded, as displayed everywhere else.
+pub fn parse_issuance_caps(s: &str) -> Result<HashMap<KeysetId, u64>, ParseCapsError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (keyset_id, amount) = pair
+                .split_once('=')
+                .ok_or_else(|| ParseCapsError::BadFormat(pair.to_string()))?;
+            let keyset_id = KeysetId::from_str(keyset_id.trim())
+                .map_err(|e| ParseCapsError::KeysetId(keyset_id.to_string(), e))?;
+            let amount = amount
+                .trim()
+                .parse()
+                .map_err(|e| ParseCapsError::Amount(amount.to_string(), e))?;
+
+            Ok((keyset_id, amount))
+        })
+        .collect()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("failed to access the issuance state file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {0} of the issuance state file is malformed")]
+    Malformed(usize),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeysetStats {
+    pub amount_signed: u64,
+    pub amount_verified: u64,
+    pub signatures_count: u64,
+    pub verifications_count: u64,
+}
+
+impl KeysetStats {
+    /// The amount signed that has not been verified back yet
+    pub fn outstanding(&self) -> u64 {
+        self.amount_signed.saturating_sub(self.amount_verified)
+    }
+
+    fn add(&mut self, other: &KeysetStats) {
+        self.amount_signed = self.amount_signed.saturating_add(other.amount_signed);
+        self.amount_verified = self.amount_verified.saturating_add(other.amount_verified);
+        self.signatures_count = self.signatures_count.saturating_add(other.signatures_count);
+        self.verifications_count = self
+            .verifications_count
+            .saturating_add(other.verifications_count);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Metrics {
+    amount_signed: Counter<u64>,
+    amount_verified: Counter<u64>,
+    rejected: Counter<u64>,
+}
+
+/// The file the counters are persisted in
+///
+/// One line per keyset:
+/// `<keyset_id> <amount_signed> <amount_verified> <signatures_count> <verifications_count>`.
+#[derive(Debug, Clone)]
+pub struct IssuanceStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl IssuanceStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut lock_path = path.clone().into_os_string();
+        lock_path.push(".lock");
+
+        Self {
+            path,
+            lock_path: lock_path.into(),
+        }
+    }
+
+    /// Hold the exclusive lock, shared with the other processes using the same file
+    fn lock(&self) -> Result<File, StoreError> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)?;
+        lock_file.lock_exclusive()?;
+
+        Ok(lock_file)
+    }
+
+    fn read(&self) -> Result<HashMap<KeysetId, KeysetStats>, StoreError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| parse_line(line).ok_or(StoreError::Malformed(idx + 1)))
+            .collect()
+    }
+
+    /// Replace the file content, atomically
+    fn write(&self, stats: &HashMap<KeysetId, KeysetStats>) -> Result<(), StoreError> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut file = File::create(&tmp_path)?;
+        for (keyset_id, s) in stats {
+            writeln!(
+                file,
+                "{keyset_id} {} {} {} {}",
+                s.amount_signed, s.amount_verified, s.signatures_count, s.verifications_count
+            )?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn parse_line(line: &str) -> Option<(KeysetId, KeysetStats)> {
+    let mut fields = line.split_whitespace();
+    let keyset_id = KeysetId::from_str(fields.next()?).ok()?;
+    let mut next_u64 = || fields.next()?.parse::<u64>().ok();
+    let stats = KeysetStats {
+        amount_signed: next_u64()?,
+        amount_verified: next_u64()?,
+        signatures_count: next_u64()?,
+        verifications_count: next_u64()?,
+    };
+
+    Some((keyset_id, stats))
+}
+
+/// The counters of every keyset, split by how far they made it to the state file
+#[derive(Debug, Default)]
+struct Counters {
+    /// As read back from the state file on the last flush, batches of other replicas included
+    persisted: HashMap<KeysetId, KeysetStats>,
+    /// Recorded by this process, being written by an ongoing flush
+    flushing: HashMap<KeysetId, KeysetStats>,
+    /// Recorded by this process since the last flush started
+    pending: HashMap<KeysetId, KeysetStats>,
+}
+
+impl Counters {
+    fn get(&self, keyset_id: &KeysetId) -> KeysetStats {
+        let mut stats = KeysetStats::default();
+        for counters in [&self.persisted, &self.flushing, &self.pending] {
+            if let Some(s) = counters.get(keyset_id) {
+                stats.add(s);
+            }
+        }
+
+        stats
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedIssuanceTracker {
+    counters: Arc<Mutex<Counters>>,
+    /// Held for the duration of a flush, so that they don't overlap
+    flush_lock: Arc<Mutex<()>>,
+    caps: Arc<HashMap<KeysetId, u64>>,
+    store: Option<IssuanceStore>,
+    metrics: Metrics,
+}
+
+impl SharedIssuanceTracker {
+    /// Load the counters persisted in `store`, if any
+    pub fn new(
+        caps: HashMap<KeysetId, u64>,
+        store: Option<IssuanceStore>,
+    ) -> Result<Self, StoreError> {
+        let meter = opentelemetry::global::meter(env!("CARGO_PKG_NAME"));
+        let persisted = match &store {
+            Some(store) => {
+                let _lock = store.lock()?;
+                store.read()?
+            }
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            counters: Arc::new(Mutex::new(Counters {
+                persisted,
+                ..Default::default()
+            })),
+            flush_lock: Arc::new(Mutex::new(())),
+            caps: Arc::new(caps),
+            store,
+            metrics: Metrics {
+                amount_signed: meter.u64_counter("signer.amount.signed").build(),
+                amount_verified: meter.u64_counter("signer.amount.verified").build(),
+                rejected: meter.u64_counter("signer.issuance.rejected.count").build(),
+            },
+        })
+    }
+
+    pub fn cap(&self, keyset_id: &KeysetId) -> Option<u64> {
+        self.caps.get(keyset_id).copied()
+    }
+
+    /// The counters of this process, on top of the ones of the other replicas as of the last flush
+    pub fn stats(&self, keyset_id: &KeysetId) -> KeysetStats {
+        self.counters.lock().unwrap().get(keyset_id)
+    }
+
+    /// Write the counters recorded since the last flush to the state file,
+    /// and read back the ones written by the other replicas in the meantime
+    ///
+    /// The counters are kept if the write fails, to be written by the next flush.
+    pub fn flush(&self) -> Result<(), StoreError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let _flush_lock = self.flush_lock.lock().unwrap();
+
+        let flushing = {
+            let mut counters = self.counters.lock().unwrap();
+            counters.flushing = std::mem::take(&mut counters.pending);
+            counters.flushing.clone()
+        };
+        let written = store.lock().and_then(|_lock| {
+            // Another replica may have written since we last did
+            let mut persisted = store.read()?;
+            for (keyset_id, stats) in &flushing {
+                persisted.entry(*keyset_id).or_default().add(stats);
+            }
+            store.write(&persisted)?;
+
+            Ok(persisted)
+        });
+
+        let mut counters = self.counters.lock().unwrap();
+        let flushing = std::mem::take(&mut counters.flushing);
+        match written {
+            Ok(persisted) => {
+                counters.persisted = persisted;
+                Ok(())
+            }
+            Err(err) => {
+                for (keyset_id, stats) in &flushing {
+                    counters.pending.entry(*keyset_id).or_default().add(stats);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Flush the counters every `interval`, for the life of the process
+    ///
+    /// The batches recorded since the last flush are lost if the process crashes,
+    /// and are only seen by the other replicas once flushed.
+    pub async fn run_flusher(self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let tracker = self.clone();
+            match tokio::task::spawn_blocking(move || tracker.flush()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    error!(name: "issuance-flush", "failed to flush the issuance counters: {err}")
+                }
+                Err(err) => error!(name: "issuance-flush", "issuance flush task panicked: {err}"),
+            }
+        }
+    }
+
+    /// Account for a batch of signatures
+    ///
+    /// The whole batch is refused if it would bring the outstanding issuance of any keyset over its cap,
+    /// in which case no counter is modified.
+    pub fn record_signed<'a>(
+        &self,
+        signed: impl IntoIterator<Item = (KeysetId, Amount)>,
+    ) -> Result<(), Error<'a>> {
+        let batch = sum_by_keyset(signed);
+
+        {
+            let mut counters = self.counters.lock().unwrap();
+            for (keyset_id, (amount, _)) in &batch {
+                let outstanding = counters.get(keyset_id).outstanding();
+                let cap = self.cap(keyset_id).unwrap_or(u64::MAX);
+
+                if u128::from(outstanding) + amount > u128::from(cap) {
+                    self.metrics
+                        .rejected
+                        .add(1, &[KeyValue::new("keyset_id", keyset_id.to_string())]);
+                    return Err(Error::IssuanceCapExceeded(*keyset_id, Amount::from(cap)));
+                }
+            }
+
+            for (keyset_id, (amount, count)) in &batch {
+                counters
+                    .pending
+                    .entry(*keyset_id)
+                    .or_default()
+                    .add(&KeysetStats {
+                        amount_signed: u64::try_from(*amount).unwrap_or(u64::MAX),
+                        signatures_count: *count,
+                        ..Default::default()
+                    });
+            }
+        }
+
+        for (keyset_id, (amount, _)) in batch {
+            self.metrics.amount_signed.add(
+                u64::try_from(amount).unwrap_or(u64::MAX),
+                &[KeyValue::new("keyset_id", keyset_id.to_string())],
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Account for a batch of successfully verified proofs
+    pub fn record_verified(&self, verified: impl IntoIterator<Item = (KeysetId, Amount)>) {
+        let batch = sum_by_keyset(verified);
+
+        {
+            let mut counters = self.counters.lock().unwrap();
+            for (keyset_id, (amount, count)) in &batch {
+                counters
+                    .pending
+                    .entry(*keyset_id)
+                    .or_default()
+                    .add(&KeysetStats {
+                        amount_verified: u64::try_from(*amount).unwrap_or(u64::MAX),
+                        verifications_count: *count,
+                        ..Default::default()
+                    });
+            }
+        }
+
+        for (keyset_id, (amount, _)) in batch {
+            self.metrics.amount_verified.add(
+                u64::try_from(amount).unwrap_or(u64::MAX),
+                &[KeyValue::new("keyset_id", keyset_id.to_string())],
+            );
+        }
+    }
+}
+
+/// Sum amounts and count items per keyset
+///
+/// Sums are done on u128 so that a batch of big amounts cannot overflow.
+fn sum_by_keyset(
+    items: impl IntoIterator<Item = (KeysetId, Amount)>,
+) -> HashMap<KeysetId, (u128, u64)> {
+    let mut batch: HashMap<KeysetId, (u128, u64)> = HashMap::new();
+    for (keyset_id, amount) in items {
+        let entry = batch.entry(keyset_id).or_default();
+        entry.0 += u128::from(u64::from(amount));
+        entry.1 += 1;
+    }
+
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> IssuanceStore {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        IssuanceStore::new(
+            std::env::temp_dir().join(format!("issuance-{}-{nanos}", std::process::id())),
+        )
+    }
+
+    fn keyset_id() -> KeysetId {
+        KeysetId::from_str("009a1f293253e41e").unwrap()
+    }
+
+    #[test]
+    fn counters_survive_a_restart() {
+        let store = store();
+        let caps = HashMap::from([(keyset_id(), 100)]);
+
+        let tracker = SharedIssuanceTracker::new(caps.clone(), Some(store.clone())).unwrap();
+        tracker
+            .record_signed([(keyset_id(), Amount::from(64u64))])
+            .unwrap();
+        tracker.record_verified([(keyset_id(), Amount::from(8u64))]);
+        tracker.flush().unwrap();
+        drop(tracker);
+
+        let restarted = SharedIssuanceTracker::new(caps, Some(store)).unwrap();
+        assert_eq!(
+            restarted.stats(&keyset_id()),
+            KeysetStats {
+                amount_signed: 64,
+                amount_verified: 8,
+                signatures_count: 1,
+                verifications_count: 1,
+            }
+        );
+        // 64 - 8 outstanding, plus 64 would go over the cap of 100
+        assert!(matches!(
+            restarted.record_signed([(keyset_id(), Amount::from(64u64))]),
+            Err(Error::IssuanceCapExceeded(_, _))
+        ));
+    }
+
+    #[test]
+    fn trackers_sharing_a_store_share_the_cap() {
+        let store = store();
+        let caps = HashMap::from([(keyset_id(), 100)]);
+
+        let first = SharedIssuanceTracker::new(caps.clone(), Some(store.clone())).unwrap();
+        let second = SharedIssuanceTracker::new(caps, Some(store)).unwrap();
+        first
+            .record_signed([(keyset_id(), Amount::from(64u64))])
+            .unwrap();
+        // Only seen by the other replica once both flushed
+        first.flush().unwrap();
+        second.flush().unwrap();
+
+        assert!(matches!(
+            second.record_signed([(keyset_id(), Amount::from(64u64))]),
+            Err(Error::IssuanceCapExceeded(_, _))
+        ));
+        assert_eq!(second.stats(&keyset_id()).amount_signed, 64);
+    }
+
+    #[test]
+    fn swaps_leave_the_outstanding_issuance_unchanged() {
+        let caps = HashMap::from([(keyset_id(), 100)]);
+        let tracker = SharedIssuanceTracker::new(caps, None).unwrap();
+        tracker
+            .record_signed([(keyset_id(), Amount::from(64u64))])
+            .unwrap();
+
+        // Swapping the minted 64 many times over never reaches the cap
+        for _ in 0..4 {
+            tracker.record_verified([(keyset_id(), Amount::from(64u64))]);
+            tracker
+                .record_signed([
+                    (keyset_id(), Amount::from(32u64)),
+                    (keyset_id(), Amount::from(32u64)),
+                ])
+                .unwrap();
+        }
+        let stats = tracker.stats(&keyset_id());
+        assert_eq!(stats.amount_signed, 64 * 5);
+        assert_eq!(stats.outstanding(), 64);
+
+        // Minting on top of it does
+        assert!(matches!(
+            tracker.record_signed([(keyset_id(), Amount::from(64u64))]),
+            Err(Error::IssuanceCapExceeded(_, _))
+        ));
+    }
+
+    #[test]
+    fn failed_flush_is_retried() {
+        let dir = std::env::temp_dir().join(format!(
+            "issuance-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let store = IssuanceStore::new(dir.join("state"));
+        let tracker = SharedIssuanceTracker::new(HashMap::new(), Some(store.clone())).unwrap();
+        tracker
+            .record_signed([(keyset_id(), Amount::from(64u64))])
+            .unwrap();
+
+        // The directory of the state file does not exist yet
+        assert!(tracker.flush().is_err());
+        assert_eq!(tracker.stats(&keyset_id()).amount_signed, 64);
+
+        fs::create_dir(&dir).unwrap();
+        tracker.flush().unwrap();
+        let restarted = SharedIssuanceTracker::new(HashMap::new(), Some(store)).unwrap();
+        assert_eq!(restarted.stats(&keyset_id()).amount_signed, 64);
+    }
+
+    #[test]
+    fn rejected_batch_is_not_persisted() {
+        let store = store();
+        let caps = HashMap::from([(keyset_id(), 100)]);
+
+        let tracker = SharedIssuanceTracker::new(caps.clone(), Some(store.clone())).unwrap();
+        assert!(
+            tracker
+                .record_signed([(keyset_id(), Amount::from(128u64))])
+                .is_err()
+        );
+        tracker.flush().unwrap();
+
+        let restarted = SharedIssuanceTracker::new(caps, Some(store)).unwrap();
+        assert_eq!(restarted.stats(&keyset_id()), KeysetStats::default());
+    }
+}
@@ -1,28 +1,38 @@
 use bitcoin::bip32::Xpriv;
+use issuance::{IssuanceStore, SharedIssuanceTracker};
 use nuts::{
     Amount,
     dhke::{sign_message, verify_message},
-    nut01::PublicKey,
+    nut01::{PublicKey, SetKeyPairs},
     nut02::{KeysetId, MintKeySet},
 };
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use server_errors::Error;
 use signer::{
-    DeclareKeysetRequest, DeclareKeysetResponse, GetRootPubKeyRequest, GetRootPubKeyResponse, Key,
-    SignBlindedMessagesRequest, SignBlindedMessagesResponse, SignerServer, VerifyProofsRequest,
-    VerifyProofsResponse,
+    DeclareKeysetRequest, DeclareKeysetResponse, GetKeysetStatsRequest, GetKeysetStatsResponse,
+    GetRootPubKeyRequest, GetRootPubKeyResponse, Key, SignBlindedMessagesRequest,
+    SignBlindedMessagesResponse, SignerServer, VerifyProofsRequest, VerifyProofsResponse,
 };
 use state::{SharedKeySetCache, SharedRootKey};
-use std::{collections::HashMap, net::SocketAddr, str::FromStr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status, service::LayerExt};
 use tower::ServiceBuilder;
 use tracing::{instrument, trace};
 
+mod cpu_pool;
+mod issuance;
 mod server_errors;
 mod state;
 
 const ROOT_KEY_ENV_VAR: &str = "ROOT_KEY";
 const GRPC_PORT_ENV_VAR: &str = "GRPC_PORT";
+const KEYSET_ISSUANCE_CAPS_ENV_VAR: &str = "KEYSET_ISSUANCE_CAPS";
+const ISSUANCE_STATE_PATH_ENV_VAR: &str = "ISSUANCE_STATE_PATH";
+const UNITS_CONFIG_PATH_ENV_VAR: &str = "UNITS_CONFIG_PATH";
+
+/// The batches signed in this interval are lost if the signer crashes
+const ISSUANCE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
 const PROOFS_FIELD: &str = "proofs";
 const MESSAGES_FIELD: &str = "messages";
@@ -31,6 +41,7 @@ const MESSAGES_FIELD: &str = "messages";
 pub struct SignerState {
     root_key: SharedRootKey,
     keyset_cache: SharedKeySetCache,
+    issuance: SharedIssuanceTracker,
 }
 
 #[tonic::async_trait]
@@ -90,53 +101,31 @@ impl signer::Signer for SignerState {
         sign_blinded_messages_request: Request<SignBlindedMessagesRequest>,
     ) -> Result<Response<SignBlindedMessagesResponse>, Status> {
         let blinded_messages = sign_blinded_messages_request.into_inner().messages;
+        let keysets = self.keyset_cache.snapshot().await;
+
+        let results = cpu_pool::run(move || {
+            blinded_messages
+                .par_iter()
+                .enumerate()
+                .map(|(idx, blinded_message)| {
+                    sign_blinded_message(&keysets, idx, blinded_message).map_err(Status::from)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await?;
 
-        let mut signatures = Vec::with_capacity(blinded_messages.len());
-
-        let keyset_cache_read_lock = self.keyset_cache.0.read().await;
-
-        for (idx, blinded_message) in blinded_messages.into_iter().enumerate() {
-            let amount = Amount::from(blinded_message.amount);
-            if !blinded_message.amount.is_power_of_two() {
-                return Err(Error::AmountNotPowerOfTwo(idx, amount))?;
-            }
-            let keyset_id = KeysetId::from_bytes(&blinded_message.keyset_id).map_err(|e| {
-                Error::BadKeysetId(MESSAGES_FIELD, idx, &blinded_message.keyset_id, e)
-            })?;
-
-            let keyset = keyset_cache_read_lock
-                .get(&keyset_id)
-                .ok_or(Error::KeysetNotFound(MESSAGES_FIELD, idx, keyset_id))?;
-            let max_order: u64 = keyset
-                .last_key_value()
-                .map(|(&k, _)| k)
-                .unwrap_or_default()
-                .into();
-            if u64::from(amount) > max_order {
-                return Err(Error::AmountGreaterThanMax(
-                    idx,
-                    amount,
-                    Amount::from(max_order),
-                ))?;
-            }
-
-            let key_pair = {
-                keyset.get(&amount).ok_or(Error::AmountNotFound(
-                    MESSAGES_FIELD,
-                    idx,
-                    keyset_id,
-                    amount,
-                ))?
-            };
-
-            let blind_secret = PublicKey::from_slice(&blinded_message.blinded_secret)
-                .map_err(|e| Error::BadSecret(idx, e))?;
-
-            let c = sign_message(&key_pair.secret_key, &blind_secret)
-                .map_err(|e| Error::CouldNotSignMessage(idx, blind_secret, e))?;
+        // Results are in input order, so the first error reported is the one with the lowest index
+        let signed = results.into_iter().collect::<Result<Vec<_>, _>>()?;
 
-            signatures.push(c.to_bytes().to_vec());
-        }
+        self.issuance.record_signed(
+            signed
+                .iter()
+                .map(|&(keyset_id, amount, _)| (keyset_id, amount)),
+        )?;
+        let signatures = signed
+            .into_iter()
+            .map(|(_, _, signature)| signature)
+            .collect();
 
         Ok(Response::new(SignBlindedMessagesResponse { signatures }))
     }
@@ -147,52 +136,29 @@ impl signer::Signer for SignerState {
         verify_proofs_request: Request<VerifyProofsRequest>,
     ) -> Result<Response<VerifyProofsResponse>, Status> {
         let proofs = verify_proofs_request.into_inner().proofs;
+        let keysets = self.keyset_cache.snapshot().await;
+
+        let results = cpu_pool::run(move || {
+            proofs
+                .par_iter()
+                .enumerate()
+                .map(|(idx, proof)| verify_proof(&keysets, idx, proof).map_err(Status::from))
+                .collect::<Vec<_>>()
+        })
+        .await?;
 
-        for (idx, proof) in proofs.into_iter().enumerate() {
-            let keyset_id = KeysetId::from_bytes(&proof.keyset_id)
-                .map_err(|e| Error::BadKeysetId(PROOFS_FIELD, idx, &proof.keyset_id, e))?;
-            let amount = Amount::from(proof.amount);
-            if !proof.amount.is_power_of_two() {
-                return Err(Error::AmountNotPowerOfTwo(idx, amount))?;
-            }
-            let (secret_key, max_order) = {
-                let keyset_cache_read_lock = self.keyset_cache.0.read().await;
-
-                let keyset = keyset_cache_read_lock
-                    .get(&keyset_id)
-                    .ok_or(Error::KeysetNotFound(PROOFS_FIELD, idx, keyset_id))?;
-                let max_order: u64 = keyset
-                    .last_key_value()
-                    .map(|(&k, _)| k)
-                    .unwrap_or_default()
-                    .into();
-
-                let keyset = keyset
-                    .get(&amount)
-                    .ok_or(Error::AmountNotFound(PROOFS_FIELD, idx, keyset_id, amount))?
-                    .secret_key
-                    .clone();
-                (keyset, max_order)
-            };
-
-            if u64::from(amount) > max_order {
-                return Err(Error::AmountGreaterThanMax(
-                    idx,
-                    amount,
-                    Amount::from(max_order),
-                ))?;
-            }
-
-            let c = PublicKey::from_slice(&proof.unblind_signature)
-                .map_err(|e| Error::InvalidSignature(idx, e))?;
-
-            if !verify_message(&secret_key, c, proof.secret.as_bytes())
-                .map_err(|e| Error::CouldNotVerifyProof(idx, c, proof.secret, e))?
-            {
+        // Walk the results in input order, so that we answer exactly as a sequential run would
+        let mut verified = Vec::with_capacity(results.len());
+        for result in results {
+            let (keyset_id, amount, is_valid) = result?;
+            if !is_valid {
                 return Ok(Response::new(VerifyProofsResponse { is_valid: false }));
-            };
+            }
+            verified.push((keyset_id, amount));
         }
 
+        self.issuance.record_verified(verified);
+
         Ok(Response::new(VerifyProofsResponse { is_valid: true }))
     }
 
@@ -207,6 +173,30 @@ impl signer::Signer for SignerState {
             root_pubkey: pub_key.to_string(),
         }))
     }
+
+    #[instrument]
+    async fn get_keyset_stats(
+        &self,
+        get_keyset_stats_request: Request<GetKeysetStatsRequest>,
+    ) -> Result<Response<GetKeysetStatsResponse>, Status> {
+        let get_keyset_stats_request = get_keyset_stats_request.get_ref();
+
+        let keyset_id = KeysetId::from_bytes(&get_keyset_stats_request.keyset_id)
+            .map_err(|e| Error::InvalidKeysetId(&get_keyset_stats_request.keyset_id, e))?;
+        if !self.keyset_cache.0.read().await.contains_key(&keyset_id) {
+            return Err(Error::UnknownKeyset(keyset_id))?;
+        }
+
+        let stats = self.issuance.stats(&keyset_id);
+
+        Ok(Response::new(GetKeysetStatsResponse {
+            amount_signed: stats.amount_signed,
+            amount_verified: stats.amount_verified,
+            signatures_count: stats.signatures_count,
+            verifications_count: stats.verifications_count,
+            issuance_cap: self.issuance.cap(&keyset_id),
+        }))
+    }
 }
 
 #[tokio::main]
@@ -235,9 +225,33 @@ async fn main() -> Result<(), anyhow::Error> {
             .expect("content of `ROOT_KEY` env var should be a valid private key")
     };
 
+    // Must be the file the node reads its units from, so that both derive the same keysets
+    if let Ok(path) = std::env::var(UNITS_CONFIG_PATH_ENV_VAR) {
+        starknet_types::registry::register_from_file(std::path::Path::new(&path))
+            .expect("content of the file at `UNITS_CONFIG_PATH` should be a valid units config");
+    }
+
+    let issuance_caps = match std::env::var(KEYSET_ISSUANCE_CAPS_ENV_VAR) {
+        Ok(caps) => issuance::parse_issuance_caps(&caps)
+            .expect("content of `KEYSET_ISSUANCE_CAPS` env var should be valid"),
+        Err(_) => HashMap::new(),
+    };
+    let issuance_store = std::env::var(ISSUANCE_STATE_PATH_ENV_VAR)
+        .ok()
+        .map(IssuanceStore::new);
+    // In memory counters would start back from zero on restart, making the caps meaningless
+    if !issuance_caps.is_empty() && issuance_store.is_none() {
+        panic!("env var `ISSUANCE_STATE_PATH` should be set when `KEYSET_ISSUANCE_CAPS` is");
+    }
+    let issuance = SharedIssuanceTracker::new(issuance_caps, issuance_store)
+        .expect("the file at `ISSUANCE_STATE_PATH` should be readable and well formed");
+
+    let _flusher_handle = tokio::spawn(issuance.clone().run_flusher(ISSUANCE_FLUSH_INTERVAL));
+
     let signer_logic = SignerState {
         root_key: SharedRootKey(Arc::new(root_private_key)),
         keyset_cache: SharedKeySetCache(Arc::new(RwLock::new(HashMap::new()))),
+        issuance: issuance.clone(),
     };
 
     let signer_server_service = ServiceBuilder::new()
@@ -254,9 +268,14 @@ async fn main() -> Result<(), anyhow::Error> {
     tonic::transport::Server::builder()
         .add_service(signer_server_service)
         .add_service(health_service)
-        .serve(socket_addr)
+        .serve_with_shutdown(socket_addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
         .await?;
 
+    // Don't lose the batches signed since the last flush
+    tokio::task::spawn_blocking(move || issuance.flush()).await??;
+
     Ok(())
 }
 
@@ -268,3 +287,91 @@ fn create_new_starknet_keyset(
 ) -> MintKeySet<starknet_types::Unit> {
     root_key.generate_keyset(unit, index, max_order)
 }
+
+fn sign_blinded_message<'a>(
+    keysets: &HashMap<KeysetId, Arc<SetKeyPairs>>,
+    idx: usize,
+    blinded_message: &'a signer::BlindedMessage,
+) -> Result<(KeysetId, Amount, Vec<u8>), Error<'a>> {
+    let amount = Amount::from(blinded_message.amount);
+    if !blinded_message.amount.is_power_of_two() {
+        return Err(Error::AmountNotPowerOfTwo(idx, amount));
+    }
+    let keyset_id = KeysetId::from_bytes(&blinded_message.keyset_id)
+        .map_err(|e| Error::BadKeysetId(MESSAGES_FIELD, idx, &blinded_message.keyset_id, e))?;
+
+    let keyset =
+        keysets
+            .get(&keyset_id)
+            .ok_or(Error::KeysetNotFound(MESSAGES_FIELD, idx, keyset_id))?;
+    let max_order: u64 = keyset
+        .last_key_value()
+        .map(|(&k, _)| k)
+        .unwrap_or_default()
+        .into();
+    if u64::from(amount) > max_order {
+        return Err(Error::AmountGreaterThanMax(
+            idx,
+            amount,
+            Amount::from(max_order),
+        ));
+    }
+
+    let key_pair = keyset.get(&amount).ok_or(Error::AmountNotFound(
+        MESSAGES_FIELD,
+        idx,
+        keyset_id,
+        amount,
+    ))?;
+
+    let blind_secret = PublicKey::from_slice(&blinded_message.blinded_secret)
+        .map_err(|e| Error::BadSecret(idx, e))?;
+
+    let c = sign_message(&key_pair.secret_key, &blind_secret)
+        .map_err(|e| Error::CouldNotSignMessage(idx, blind_secret, e))?;
+
+    Ok((keyset_id, amount, c.to_bytes().to_vec()))
+}
+
+fn verify_proof<'a>(
+    keysets: &HashMap<KeysetId, Arc<SetKeyPairs>>,
+    idx: usize,
+    proof: &'a signer::Proof,
+) -> Result<(KeysetId, Amount, bool), Error<'a>> {
+    let keyset_id = KeysetId::from_bytes(&proof.keyset_id)
+        .map_err(|e| Error::BadKeysetId(PROOFS_FIELD, idx, &proof.keyset_id, e))?;
+    let amount = Amount::from(proof.amount);
+    if !proof.amount.is_power_of_two() {
+        return Err(Error::AmountNotPowerOfTwo(idx, amount));
+    }
+
+    let keyset =
+        keysets
+            .get(&keyset_id)
+            .ok_or(Error::KeysetNotFound(PROOFS_FIELD, idx, keyset_id))?;
+    let max_order: u64 = keyset
+        .last_key_value()
+        .map(|(&k, _)| k)
+        .unwrap_or_default()
+        .into();
+    let secret_key = &keyset
+        .get(&amount)
+        .ok_or(Error::AmountNotFound(PROOFS_FIELD, idx, keyset_id, amount))?
+        .secret_key;
+
+    if u64::from(amount) > max_order {
+        return Err(Error::AmountGreaterThanMax(
+            idx,
+            amount,
+            Amount::from(max_order),
+        ));
+    }
+
+    let c = PublicKey::from_slice(&proof.unblind_signature)
+        .map_err(|e| Error::InvalidSignature(idx, e))?;
+
+    let is_valid = verify_message(secret_key, c, proof.secret.as_bytes())
+        .map_err(|e| Error::CouldNotVerifyProof(idx, c, proof.secret.clone(), e))?;
+
+    Ok((keyset_id, amount, is_valid))
+}
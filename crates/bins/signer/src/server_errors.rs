@@ -20,6 +20,9 @@ pub enum Error<'a> {
     AmountNotFound(&'a str, usize, KeysetId, Amount),
     BadSecret(usize, nut01::Error),
     InvalidSignature(usize, nut01::Error),
+    InvalidKeysetId(&'a [u8], nut02::Error),
+    UnknownKeyset(KeysetId),
+    IssuanceCapExceeded(KeysetId, Amount),
 }
 
 impl<'a> From<Error<'a>> for Status {
@@ -98,7 +101,11 @@ impl<'a> From<Error<'a>> for Status {
                     format!(
                         "{} is not part of the units currently supported: [{}]",
                         unit,
-                        Unit::MilliStrk
+                        starknet_types::registry::units()
+                            .iter()
+                            .map(Unit::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
                     ),
                 )]),
             ),
@@ -130,6 +137,32 @@ impl<'a> From<Error<'a>> for Status {
                     format!("the provided signature is invalid: {}", error),
                 )]),
             ),
+            Error::InvalidKeysetId(bad_keyset_id, error) => Status::with_error_details(
+                Code::InvalidArgument,
+                "invalid keyset id",
+                ErrorDetails::with_bad_request(vec![FieldViolation::new(
+                    "keyset_id",
+                    format!("the provided keyset id '{bad_keyset_id:?}' is invalid: {error}"),
+                )]),
+            ),
+            Error::UnknownKeyset(keyset_id) => Status::with_error_details(
+                Code::NotFound,
+                "keyset not found",
+                ErrorDetails::with_bad_request(vec![FieldViolation::new(
+                    "keyset_id",
+                    format!("the specified keyset id '{keyset_id}' does not exist"),
+                )]),
+            ),
+            Error::IssuanceCapExceeded(keyset_id, cap) => Status::with_error_details(
+                Code::ResourceExhausted,
+                "issuance cap exceeded",
+                ErrorDetails::with_quota_failure_violation(
+                    format!("keyset:{keyset_id}"),
+                    format!(
+                        "signing these messages would bring the outstanding issuance over the cap of {cap}"
+                    ),
+                ),
+            ),
         }
     }
 }
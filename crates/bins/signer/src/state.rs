@@ -49,4 +49,9 @@ impl SharedKeySetCache {
 
         write_lock.insert(keyset_id, Arc::new(key_pairs));
     }
+
+    /// Returns a copy of the current keysets so that the lock isn't held during long computations
+    pub async fn snapshot(&self) -> HashMap<KeysetId, Arc<SetKeyPairs>> {
+        self.0.read().await.clone()
+    }
 }
@@ -0,0 +1,127 @@
+//! The Starknet liquidity source, served as a plugin
+//!
+//! Reads the same config file and env as the node does for its built-in Starknet liquidity source.
+//! The node uses the plugin instead once its config file has a matching entry:
+//!
+//! ```toml
+//! [plugins.starknet]
+//! endpoint = "unix:///tmp/starknet-liquidity-plugin.sock"
+//! ```
+use std::path::PathBuf;
+
+use clap::Parser;
+use liquidity_source_plugin::{
+    PaymentDirection, PaymentEvent, PaymentEventPublisher, PluginEndpoint, PluginServer,
+};
+use starknet_liquidity_source::{
+    IndexerSettings, StarknetLiquiditySource, stream_payments_until_ctrl_c,
+};
+use starknet_types::{STARKNET_STR, constants::on_chain_constants};
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct ProgramArguments {
+    /// The config file holding the Starknet settings
+    #[arg(long)]
+    config: PathBuf,
+    /// Where to serve the plugin, `unix:///path/to/socket` or `http://host:port`
+    #[arg(long)]
+    endpoint: PluginEndpoint,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    const PKG_NAME: &str = env!("CARGO_PKG_NAME");
+    const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+    let (meter_provider, subscriber) = open_telemetry_tracing::init(PKG_NAME, PKG_VERSION);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+    opentelemetry::global::set_meter_provider(meter_provider);
+
+    #[cfg(debug_assertions)]
+    {
+        let _ = dotenvy::from_filename("starknet-liquidity-plugin.env")
+            .inspect_err(|e| tracing::error!("dotenvy initialization failed: {e}"));
+    }
+
+    let args = ProgramArguments::parse();
+    // The node and its plugins must agree on the units
+    starknet_types::registry::register_from_file(&args.config)?;
+    let (liquidity_source, indexer_settings) =
+        StarknetLiquiditySource::from_config(args.config.clone())?;
+
+    let payment_events = PaymentEventPublisher::default();
+    let cloned_payment_events = payment_events.clone();
+    let cloned_indexer_settings = indexer_settings.clone();
+    let indexer = stream_payments_until_ctrl_c(indexer_settings, move |starknet_events| {
+        for starknet_event in starknet_events {
+            if let Some(payment_event) =
+                to_plugin_payment_event(&cloned_indexer_settings, starknet_event)
+            {
+                cloned_payment_events.publish(payment_event);
+            }
+        }
+
+        std::future::ready(Ok(()))
+    });
+
+    info!(name: "plugin-listen", endpoint = %args.endpoint);
+    let server = PluginServer::new(STARKNET_STR, liquidity_source, payment_events).serve(
+        &args.endpoint,
+        async {
+            let _ = tokio::signal::ctrl_c().await;
+        },
+    );
+
+    // Both stop on ctrl-c
+    let (server_res, ()) = tokio::join!(server, indexer);
+    server_res?;
+
+    Ok(())
+}
+
+/// None if the payment is neither to nor from the cashier
+fn to_plugin_payment_event(
+    settings: &IndexerSettings,
+    starknet_event: starknet_payment_indexer::PaymentEvent,
+) -> Option<PaymentEvent> {
+    let direction = if starknet_event.payee == settings.cashier_account_address {
+        PaymentDirection::Deposit
+    } else if starknet_event.payer == settings.cashier_account_address {
+        PaymentDirection::Withdrawal
+    } else {
+        return None;
+    };
+
+    let Some(asset) = on_chain_constants(settings.chain_id.as_str()).and_then(|constants| {
+        constants
+            .assets_contract_address
+            .get_asset_for_contract_address(starknet_event.asset)
+    }) else {
+        error!(
+            "Got an event for token with address {:#x} which doesn't match any known asset.",
+            starknet_event.asset
+        );
+        return None;
+    };
+
+    // The payment events are unique by tx hash and index,
+    // keep the formats of the built-in indexer so that both can run against the same database
+    let tx_hash = if direction == PaymentDirection::Withdrawal {
+        starknet_event.tx_hash.to_hex_string()
+    } else {
+        starknet_event.tx_hash.to_string()
+    };
+
+    Some(PaymentEvent {
+        direction: direction.into(),
+        invoice_id: starknet_event.invoice_id.to_bytes_be().to_vec(),
+        asset: asset.to_string(),
+        amount: primitive_types::U256::from(starknet_event.amount).to_string(),
+        payer: starknet_event.payer.to_string(),
+        payee: starknet_event.payee.to_string(),
+        block_id: starknet_event.block_id,
+        tx_hash,
+        event_idx: starknet_event.event_idx,
+    })
+}
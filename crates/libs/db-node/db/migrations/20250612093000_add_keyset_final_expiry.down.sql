@@ -0,0 +1 @@
+ALTER TABLE keyset DROP COLUMN final_expiry;
@@ -0,0 +1 @@
+ALTER TABLE keyset ADD COLUMN final_expiry TIMESTAMPTZ;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS response_cache (
+    route TEXT NOT NULL,
+    request_hash BIGINT NOT NULL,
+    response BYTEA NOT NULL,
+    expiry TIMESTAMPTZ,
+    PRIMARY KEY (route, request_hash)
+);
+
+CREATE INDEX IF NOT EXISTS response_cache_expiry ON response_cache(expiry);
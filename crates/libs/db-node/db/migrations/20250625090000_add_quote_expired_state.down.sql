@@ -0,0 +1,13 @@
+-- Postgres can't drop an enum value, the types are recreated without it
+
+UPDATE mint_quote SET state = 'UNPAID' WHERE state = 'EXPIRED';
+ALTER TYPE mint_quote_state RENAME TO mint_quote_state_old;
+CREATE TYPE mint_quote_state AS ENUM ('UNPAID', 'PAID', 'ISSUED');
+ALTER TABLE mint_quote ALTER COLUMN state TYPE mint_quote_state USING state::TEXT::mint_quote_state;
+DROP TYPE mint_quote_state_old;
+
+UPDATE melt_quote SET state = 'UNPAID' WHERE state = 'EXPIRED';
+ALTER TYPE melt_quote_state RENAME TO melt_quote_state_old;
+CREATE TYPE melt_quote_state AS ENUM ('UNPAID', 'PENDING', 'PAID');
+ALTER TABLE melt_quote ALTER COLUMN state TYPE melt_quote_state USING state::TEXT::melt_quote_state;
+DROP TYPE melt_quote_state_old;
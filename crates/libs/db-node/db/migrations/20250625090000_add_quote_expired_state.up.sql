@@ -0,0 +1,2 @@
+ALTER TYPE mint_quote_state ADD VALUE IF NOT EXISTS 'EXPIRED';
+ALTER TYPE melt_quote_state ADD VALUE IF NOT EXISTS 'EXPIRED';
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS melt_payment_event_history;
+DROP TABLE IF EXISTS melt_quote_history;
+DROP TABLE IF EXISTS mint_payment_event_history;
+DROP TABLE IF EXISTS mint_quote_history;
@@ -0,0 +1,60 @@
+-- Terminal quotes, and their payment events, moved out of the live tables by the archival job
+
+CREATE TABLE IF NOT EXISTS mint_quote_history (
+    id UUID PRIMARY KEY,
+    invoice_id BYTEA NOT NULL,
+    unit TEXT NOT NULL,
+    amount INT8 NOT NULL,
+    request TEXT NOT NULL,
+    expiry TIMESTAMPTZ NOT NULL,
+    state mint_quote_state NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS mint_quote_history_invoice_id ON mint_quote_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS mint_payment_event_history (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index BIGINT NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BYTEA NOT NULL,
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS mint_payment_event_history_invoice_id ON mint_payment_event_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS melt_quote_history (
+    id UUID PRIMARY KEY,
+    invoice_id BYTEA NOT NULL,
+    unit TEXT NOT NULL,
+    amount INT8 NOT NULL,
+    fee INT8 NOT NULL,
+    request TEXT NOT NULL,
+    expiry TIMESTAMPTZ NOT NULL,
+    state melt_quote_state NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS melt_quote_history_invoice_id ON melt_quote_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS melt_payment_event_history (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index BIGINT NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BYTEA NOT NULL,
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS melt_payment_event_history_invoice_id ON melt_payment_event_history(invoice_id);
@@ -0,0 +1,37 @@
+-- Append-only record of the node operations
+--
+-- Events are inserted in the transaction of the operation they record,
+-- and later sealed into a hash chain, in the order they are committed.
+CREATE TABLE IF NOT EXISTS audit_event (
+    id BIGSERIAL PRIMARY KEY,
+    created_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    -- Set when the event is sealed
+    seq BIGINT UNIQUE,
+    prev_hash BYTEA,
+    hash BYTEA UNIQUE
+);
+
+CREATE INDEX IF NOT EXISTS audit_event_unsealed ON audit_event(id) WHERE seq IS NULL;
+
+-- Events can only be sealed, once
+CREATE OR REPLACE FUNCTION audit_event_append_only() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE'
+        OR OLD.seq IS NOT NULL
+        OR NEW.id <> OLD.id
+        OR NEW.created_at <> OLD.created_at
+        OR NEW.kind <> OLD.kind
+        OR NEW.payload <> OLD.payload
+    THEN
+        RAISE EXCEPTION 'audit events are append-only';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_event_append_only
+    BEFORE UPDATE OR DELETE ON audit_event
+    FOR EACH ROW EXECUTE FUNCTION audit_event_append_only();
@@ -0,0 +1,21 @@
+-- Conversions of ecash from one unit to another
+--
+-- Inserted in the transaction spending the inputs and storing the signatures of the outputs,
+-- so that the ledger of each unit can account for the ecash moved in and out by exchanges.
+CREATE TABLE IF NOT EXISTS exchange (
+    id UUID PRIMARY KEY,
+    created_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT,
+    input_unit TEXT NOT NULL,
+    input_amount INT8 NOT NULL,
+    output_unit TEXT NOT NULL,
+    output_amount INT8 NOT NULL,
+    -- Output per input, spread included, as a decimal string
+    rate TEXT NOT NULL,
+    -- In the output unit
+    fee INT8 NOT NULL,
+    -- Bought by the inputs but not requested as outputs, kept by the node, in the output unit
+    surplus INT8 NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS exchange_input_unit ON exchange(input_unit);
+CREATE INDEX IF NOT EXISTS exchange_output_unit ON exchange(output_unit);
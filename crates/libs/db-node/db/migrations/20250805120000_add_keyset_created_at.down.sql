@@ -0,0 +1 @@
+ALTER TABLE keyset DROP COLUMN created_at;
@@ -0,0 +1 @@
+ALTER TABLE keyset ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
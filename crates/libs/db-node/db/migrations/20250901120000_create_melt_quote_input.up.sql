@@ -0,0 +1,10 @@
+-- The proofs spent by each melt
+--
+-- Inserted in the transaction spending them, so that they can be unspent
+-- if the payment of the quote fails once they are.
+CREATE TABLE IF NOT EXISTS melt_quote_input (
+    y BYTEA PRIMARY KEY REFERENCES proof(y),
+    quote_id UUID NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS melt_quote_input_quote_id ON melt_quote_input(quote_id);
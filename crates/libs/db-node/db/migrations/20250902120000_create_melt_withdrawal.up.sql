@@ -0,0 +1,17 @@
+-- The payments the node still has to send for its pending melt quotes
+--
+-- Inserted once the inputs of the melt are spent, and deleted once the payment is confirmed,
+-- or once the quote expired without being paid, the inputs of the melt being then unspent.
+-- The queue so survives restarts, and is processed by a single node.
+CREATE TABLE IF NOT EXISTS melt_withdrawal (
+    quote_id UUID PRIMARY KEY,
+    created_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT,
+    -- The method of the liquidity source paying it
+    method TEXT NOT NULL,
+    -- Serialized by the liquidity source
+    payment TEXT NOT NULL,
+    -- The last transaction sent for the payment, unset until one is sent or after it failed
+    tx_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS melt_withdrawal_method ON melt_withdrawal(method, created_at);
@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS melt_payment_event_history;
+DROP TABLE IF EXISTS melt_quote_history;
+DROP TABLE IF EXISTS mint_payment_event_history;
+DROP TABLE IF EXISTS mint_quote_history;
+DROP TABLE IF EXISTS response_cache;
+DROP TABLE IF EXISTS melt_payment_event;
+DROP TABLE IF EXISTS mint_payment_event;
+DROP TABLE IF EXISTS melt_quote;
+DROP TABLE IF EXISTS mint_quote;
+DROP TABLE IF EXISTS proof;
+DROP TABLE IF EXISTS blind_signature;
+DROP TABLE IF EXISTS keyset;
@@ -0,0 +1,179 @@
+-- Same schema as the Postgres migrations, as of the quote history tables.
+-- Timestamps are stored as unix timestamps, and enums as checked TEXT.
+
+-- Keyset
+
+CREATE TABLE IF NOT EXISTS keyset (
+    id INTEGER PRIMARY KEY,
+    unit TEXT NOT NULL,
+    active BOOLEAN NOT NULL,
+    max_order INTEGER NOT NULL,
+    derivation_path_index INTEGER NOT NULL,
+    final_expiry INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS keyset_unit_index ON keyset(unit);
+CREATE INDEX IF NOT EXISTS keyset_active_index ON keyset(active);
+
+-- Blind Signature
+
+CREATE TABLE IF NOT EXISTS blind_signature (
+    y BLOB CHECK (length(y) = 33) PRIMARY KEY,
+    amount INTEGER NOT NULL,
+    keyset_id INTEGER NOT NULL REFERENCES keyset(id),
+    c BLOB CHECK (length(c) = 33) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS blind_signature_keyset_id_index ON blind_signature(keyset_id);
+
+-- Proof
+
+CREATE TABLE IF NOT EXISTS proof (
+    y BLOB CHECK (length(y) = 33) PRIMARY KEY,
+    amount INTEGER NOT NULL,
+    keyset_id INTEGER NOT NULL REFERENCES keyset(id),
+    secret TEXT NOT NULL,
+    c BLOB CHECK (length(c) = 33) NOT NULL,
+    state INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS proof_state_index ON proof(state);
+CREATE INDEX IF NOT EXISTS proof_secret_index ON proof(secret);
+
+-- Mint quote
+
+CREATE TABLE IF NOT EXISTS mint_quote (
+    id BLOB PRIMARY KEY,
+    invoice_id BLOB CHECK (length(invoice_id) = 32) NOT NULL UNIQUE,
+    unit TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    request TEXT NOT NULL,
+    expiry INTEGER NOT NULL,
+    state TEXT CHECK (state IN ('UNPAID', 'PAID', 'ISSUED', 'EXPIRED')) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS mint_quote_unit ON mint_quote(unit);
+CREATE INDEX IF NOT EXISTS mint_quote_state ON mint_quote(state);
+CREATE INDEX IF NOT EXISTS mint_quote_expiry ON mint_quote(expiry);
+
+-- Melt quote
+
+CREATE TABLE IF NOT EXISTS melt_quote (
+    id BLOB PRIMARY KEY,
+    invoice_id BLOB CHECK (length(invoice_id) = 32) NOT NULL UNIQUE,
+    unit TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    fee INTEGER NOT NULL,
+    request TEXT NOT NULL,
+    expiry INTEGER NOT NULL,
+    state TEXT CHECK (state IN ('UNPAID', 'PENDING', 'PAID', 'EXPIRED')) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS melt_quote_unit ON melt_quote(unit);
+CREATE INDEX IF NOT EXISTS melt_quote_state ON melt_quote(state);
+CREATE INDEX IF NOT EXISTS melt_quote_expiry ON melt_quote(expiry);
+
+-- Starknet payment events
+
+CREATE TABLE IF NOT EXISTS mint_payment_event (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BLOB NOT NULL REFERENCES mint_quote(invoice_id),
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS mint_payment_event_invoice_id ON mint_payment_event(invoice_id);
+
+CREATE TABLE IF NOT EXISTS melt_payment_event (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BLOB NOT NULL REFERENCES melt_quote(invoice_id),
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS melt_payment_event_invoice_id ON melt_payment_event(invoice_id);
+
+-- NUT-19 response cache
+
+CREATE TABLE IF NOT EXISTS response_cache (
+    route TEXT NOT NULL,
+    request_hash INTEGER NOT NULL,
+    response BLOB NOT NULL,
+    expiry INTEGER,
+    PRIMARY KEY (route, request_hash)
+);
+
+CREATE INDEX IF NOT EXISTS response_cache_expiry ON response_cache(expiry);
+
+-- Terminal quotes, and their payment events, moved out of the live tables by the archival job
+
+CREATE TABLE IF NOT EXISTS mint_quote_history (
+    id BLOB PRIMARY KEY,
+    invoice_id BLOB NOT NULL,
+    unit TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    request TEXT NOT NULL,
+    expiry INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    archived_at INTEGER NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE INDEX IF NOT EXISTS mint_quote_history_invoice_id ON mint_quote_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS mint_payment_event_history (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BLOB NOT NULL,
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    archived_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS mint_payment_event_history_invoice_id ON mint_payment_event_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS melt_quote_history (
+    id BLOB PRIMARY KEY,
+    invoice_id BLOB NOT NULL,
+    unit TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    fee INTEGER NOT NULL,
+    request TEXT NOT NULL,
+    expiry INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    archived_at INTEGER NOT NULL DEFAULT (unixepoch())
+);
+
+CREATE INDEX IF NOT EXISTS melt_quote_history_invoice_id ON melt_quote_history(invoice_id);
+
+CREATE TABLE IF NOT EXISTS melt_payment_event_history (
+    block_id TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    payee TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    invoice_id BLOB NOT NULL,
+    payer TEXT NOT NULL,
+    amount_low TEXT NOT NULL,
+    amount_high TEXT NOT NULL,
+    archived_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    PRIMARY KEY (tx_hash, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS melt_payment_event_history_invoice_id ON melt_payment_event_history(invoice_id);
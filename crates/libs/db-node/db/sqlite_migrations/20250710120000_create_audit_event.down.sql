@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS audit_event_seal_only;
+DROP TRIGGER IF EXISTS audit_event_no_delete;
+DROP TABLE IF EXISTS audit_event;
@@ -0,0 +1,31 @@
+-- Append-only record of the node operations, see the Postgres migration
+
+CREATE TABLE IF NOT EXISTS audit_event (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    seq INTEGER UNIQUE,
+    prev_hash BLOB,
+    hash BLOB UNIQUE
+);
+
+CREATE INDEX IF NOT EXISTS audit_event_unsealed ON audit_event(id) WHERE seq IS NULL;
+
+CREATE TRIGGER IF NOT EXISTS audit_event_no_delete
+    BEFORE DELETE ON audit_event
+BEGIN
+    SELECT RAISE(ABORT, 'audit events are append-only');
+END;
+
+-- Events can only be sealed, once
+CREATE TRIGGER IF NOT EXISTS audit_event_seal_only
+    BEFORE UPDATE ON audit_event
+    WHEN OLD.seq IS NOT NULL
+        OR NEW.id IS NOT OLD.id
+        OR NEW.created_at IS NOT OLD.created_at
+        OR NEW.kind IS NOT OLD.kind
+        OR NEW.payload IS NOT OLD.payload
+BEGIN
+    SELECT RAISE(ABORT, 'audit events are append-only');
+END;
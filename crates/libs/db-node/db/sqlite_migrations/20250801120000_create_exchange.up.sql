@@ -0,0 +1,16 @@
+-- Conversions of ecash from one unit to another, see the Postgres migration
+
+CREATE TABLE IF NOT EXISTS exchange (
+    id BLOB PRIMARY KEY,
+    created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    input_unit TEXT NOT NULL,
+    input_amount INTEGER NOT NULL,
+    output_unit TEXT NOT NULL,
+    output_amount INTEGER NOT NULL,
+    rate TEXT NOT NULL,
+    fee INTEGER NOT NULL,
+    surplus INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS exchange_input_unit ON exchange(input_unit);
+CREATE INDEX IF NOT EXISTS exchange_output_unit ON exchange(output_unit);
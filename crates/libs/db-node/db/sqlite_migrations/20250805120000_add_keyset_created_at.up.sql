@@ -0,0 +1,4 @@
+-- SQLite can't add a column with a non constant default,
+-- the existing keysets are considered created by this migration.
+ALTER TABLE keyset ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+UPDATE keyset SET created_at = unixepoch();
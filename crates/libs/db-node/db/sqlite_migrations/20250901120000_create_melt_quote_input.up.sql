@@ -0,0 +1,8 @@
+-- The proofs spent by each melt, see the Postgres migration
+
+CREATE TABLE IF NOT EXISTS melt_quote_input (
+    y BLOB PRIMARY KEY REFERENCES proof(y),
+    quote_id BLOB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS melt_quote_input_quote_id ON melt_quote_input(quote_id);
@@ -0,0 +1,11 @@
+-- The payments the node still has to send for its pending melt quotes, see the Postgres migration
+
+CREATE TABLE IF NOT EXISTS melt_withdrawal (
+    quote_id BLOB PRIMARY KEY,
+    created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    method TEXT NOT NULL,
+    payment TEXT NOT NULL,
+    tx_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS melt_withdrawal_method ON melt_withdrawal(method, created_at);
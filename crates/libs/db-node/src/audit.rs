@@ -0,0 +1,347 @@
+//! Append-only audit log of the node operations
+//!
+//! Each event is inserted in the same transaction as the operation it records,
+//! so that an operation can't be committed without its event, and the other way around.
+//!
+//! Chaining the events from inside those transactions would make every concurrent operation conflict,
+//! so they are inserted unsealed, and sealed afterwards by [`seal`], one caller at a time:
+//! each committed event gets the next position in the chain,
+//! and a hash committing to its content and to the hash of the event before it.
+//! Modifying, removing or reordering a sealed event breaks the chain after it,
+//! which [`ChainVerifier`] detects.
+//!
+//! Removing events from the end of the chain can only be detected against a copy of a later head,
+//! so the head hash should be regularly exported out of the node database.
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, PgConnection};
+
+use crate::Error;
+
+/// The `prev_hash` of the first event of the chain
+pub const GENESIS_HASH: [u8; 32] = [0; 32];
+
+/// Key of the Postgres advisory lock held while sealing
+const SEAL_LOCK_KEY: i64 = 0x6175_6469_745f_6c6f;
+
+/// An event sealed into the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// Position in the chain, starting at 0
+    pub seq: u64,
+    /// Insertion order, which may differ from the commit order
+    pub id: u64,
+    /// Unix timestamp of the insertion
+    pub created_at: u64,
+    pub kind: String,
+    /// JSON description of the operation
+    pub payload: String,
+    pub prev_hash: [u8; 32],
+    pub hash: [u8; 32],
+}
+
+/// The hash committing to the content of an event and to the hash of the previous one
+pub fn event_hash(
+    prev_hash: &[u8; 32],
+    seq: u64,
+    id: u64,
+    created_at: u64,
+    kind: &str,
+    payload: &str,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash);
+    hasher.update(seq.to_be_bytes());
+    hasher.update(id.to_be_bytes());
+    hasher.update(created_at.to_be_bytes());
+    // The kind is length-prefixed so that its end can't be moved into the payload
+    hasher.update((kind.len() as u64).to_be_bytes());
+    hasher.update(kind.as_bytes());
+    hasher.update(payload.as_bytes());
+
+    hasher.finalize().into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChainError {
+    #[error("expected event {expected}, found event {found}")]
+    MissingEvent { expected: u64, found: u64 },
+    #[error("event {0} doesn't link to the hash of the previous event")]
+    BrokenLink(u64),
+    #[error("the content of event {0} doesn't match its hash")]
+    InvalidHash(u64),
+}
+
+/// Checks the chain one event at a time, in order
+#[derive(Debug, Clone)]
+pub struct ChainVerifier {
+    next_seq: u64,
+    head: [u8; 32],
+}
+
+impl Default for ChainVerifier {
+    fn default() -> Self {
+        Self {
+            next_seq: 0,
+            head: GENESIS_HASH,
+        }
+    }
+}
+
+impl ChainVerifier {
+    pub fn verify(&mut self, event: &AuditEvent) -> Result<(), ChainError> {
+        if event.seq != self.next_seq {
+            return Err(ChainError::MissingEvent {
+                expected: self.next_seq,
+                found: event.seq,
+            });
+        }
+        if event.prev_hash != self.head {
+            return Err(ChainError::BrokenLink(event.seq));
+        }
+        let hash = event_hash(
+            &event.prev_hash,
+            event.seq,
+            event.id,
+            event.created_at,
+            &event.kind,
+            &event.payload,
+        );
+        if hash != event.hash {
+            return Err(ChainError::InvalidHash(event.seq));
+        }
+
+        self.next_seq += 1;
+        self.head = hash;
+
+        Ok(())
+    }
+
+    /// Number of events verified
+    pub fn len(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_seq == 0
+    }
+
+    /// Hash of the last event verified
+    pub fn head(&self) -> [u8; 32] {
+        self.head
+    }
+}
+
+pub(crate) fn hash_from_db(hash: Vec<u8>) -> Result<[u8; 32], Error> {
+    hash.try_into().map_err(|_| Error::DbToRuntimeConversion)
+}
+
+pub(crate) fn u64_from_db(value: i64) -> Result<u64, Error> {
+    u64::try_from(value).map_err(|_| Error::DbToRuntimeConversion)
+}
+
+pub(crate) fn u64_to_db(value: u64) -> Result<i64, Error> {
+    i64::try_from(value).map_err(|_| Error::RuntimeToDbConversion)
+}
+
+pub async fn insert(conn: &mut PgConnection, kind: &str, payload: &str) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO audit_event (kind, payload) VALUES ($1, $2)",
+        kind,
+        payload
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Chain the events committed since the last call, returns how many were sealed
+///
+/// Runs in its own READ COMMITTED transaction, so that it sees the events committed while waiting for the lock.
+/// It is not meant to be called from inside another transaction.
+pub async fn seal(conn: &mut PgConnection) -> Result<u64, Error> {
+    let mut tx = conn.begin().await?;
+
+    // Not a macro, as sqlx can't decode the `void` returned
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(SEAL_LOCK_KEY)
+        .execute(&mut *tx)
+        .await?;
+
+    let head = sqlx::query!(
+        r#"SELECT seq AS "seq!", hash AS "hash!" FROM audit_event
+        WHERE seq IS NOT NULL ORDER BY seq DESC LIMIT 1"#
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (mut next_seq, mut prev_hash) = match head {
+        Some(head) => (u64_from_db(head.seq)? + 1, hash_from_db(head.hash)?),
+        None => (0, GENESIS_HASH),
+    };
+
+    let unsealed = sqlx::query!(
+        "SELECT id, created_at, kind, payload FROM audit_event WHERE seq IS NULL ORDER BY id"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let n_sealed = unsealed.len() as u64;
+    for event in unsealed {
+        let hash = event_hash(
+            &prev_hash,
+            next_seq,
+            u64_from_db(event.id)?,
+            u64_from_db(event.created_at)?,
+            &event.kind,
+            &event.payload,
+        );
+        sqlx::query!(
+            "UPDATE audit_event SET seq = $2, prev_hash = $3, hash = $4 WHERE id = $1",
+            event.id,
+            u64_to_db(next_seq)?,
+            prev_hash.as_slice(),
+            hash.as_slice()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        next_seq += 1;
+        prev_hash = hash;
+    }
+
+    tx.commit().await?;
+
+    Ok(n_sealed)
+}
+
+/// Returns at most `limit` sealed events, starting at position `from_seq`
+pub async fn get_sealed(
+    conn: &mut PgConnection,
+    from_seq: u64,
+    limit: u64,
+) -> Result<Vec<AuditEvent>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT id, created_at, kind, payload, seq AS "seq!", prev_hash AS "prev_hash!", hash AS "hash!"
+        FROM audit_event WHERE seq >= $1 ORDER BY seq LIMIT $2"#,
+        u64_to_db(from_seq)?,
+        u64_to_db(limit)?
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(AuditEvent {
+                seq: u64_from_db(r.seq)?,
+                id: u64_from_db(r.id)?,
+                created_at: u64_from_db(r.created_at)?,
+                kind: r.kind,
+                payload: r.payload,
+                prev_hash: hash_from_db(r.prev_hash)?,
+                hash: hash_from_db(r.hash)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn count_unsealed(conn: &mut PgConnection) -> Result<u64, Error> {
+    let count =
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM audit_event WHERE seq IS NULL"#)
+            .fetch_one(conn)
+            .await?;
+
+    u64_from_db(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditEvent, ChainError, ChainVerifier, GENESIS_HASH, event_hash};
+
+    fn chain(n: u64) -> Vec<AuditEvent> {
+        let mut prev_hash = GENESIS_HASH;
+        (0..n)
+            .map(|seq| {
+                let id = seq * 2 + 1;
+                let payload = format!("{{\"n\":{}}}", seq);
+                let hash = event_hash(&prev_hash, seq, id, 1_700_000_000, "mint", &payload);
+                let event = AuditEvent {
+                    seq,
+                    id,
+                    created_at: 1_700_000_000,
+                    kind: "mint".to_string(),
+                    payload,
+                    prev_hash,
+                    hash,
+                };
+                prev_hash = hash;
+                event
+            })
+            .collect()
+    }
+
+    fn verify_all(events: &[AuditEvent]) -> Result<ChainVerifier, ChainError> {
+        let mut verifier = ChainVerifier::default();
+        for event in events {
+            verifier.verify(event)?;
+        }
+
+        Ok(verifier)
+    }
+
+    #[test]
+    fn valid_chain() {
+        let events = chain(5);
+        let verifier = verify_all(&events).unwrap();
+
+        assert_eq!(verifier.len(), 5);
+        assert_eq!(verifier.head(), events[4].hash);
+    }
+
+    #[test]
+    fn modified_payload_is_detected() {
+        let mut events = chain(5);
+        events[2].payload = "{\"n\":42}".to_string();
+
+        assert_eq!(verify_all(&events).unwrap_err(), ChainError::InvalidHash(2));
+    }
+
+    #[test]
+    fn modified_and_rehashed_event_breaks_the_next_link() {
+        let mut events = chain(5);
+        let event = &mut events[2];
+        event.payload = "{\"n\":42}".to_string();
+        event.hash = event_hash(
+            &event.prev_hash,
+            event.seq,
+            event.id,
+            event.created_at,
+            &event.kind,
+            &event.payload,
+        );
+
+        assert_eq!(verify_all(&events).unwrap_err(), ChainError::BrokenLink(3));
+    }
+
+    #[test]
+    fn removed_event_is_detected() {
+        let mut events = chain(5);
+        events.remove(1);
+
+        assert_eq!(
+            verify_all(&events).unwrap_err(),
+            ChainError::MissingEvent {
+                expected: 1,
+                found: 2
+            }
+        );
+    }
+
+    #[test]
+    fn kind_and_payload_boundary_is_committed() {
+        assert_ne!(
+            event_hash(&GENESIS_HASH, 0, 0, 0, "mint", "{}"),
+            event_hash(&GENESIS_HASH, 0, 0, 0, "min", "t{}"),
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use futures_util::StreamExt;
-use nuts::{Amount, nut01::PublicKey, nut02::KeysetId, traits::Unit};
+use nuts::{Amount, nut01::PublicKey, nut02::KeysetId};
 use sqlx::{PgConnection, Row};
 
 use crate::Error;
@@ -23,9 +23,9 @@ pub async fn is_any_blind_message_already_used(
     Ok(record.exists)
 }
 
-pub async fn sum_amount_of_unit_in_circulation<U: Unit>(
+pub async fn sum_amount_of_unit_in_circulation(
     conn: &mut PgConnection,
-    unit: U,
+    unit: &str,
 ) -> Result<Amount, Error> {
     let record = sqlx::query!(
         r#"
@@ -33,7 +33,7 @@ pub async fn sum_amount_of_unit_in_circulation<U: Unit>(
             INNER JOIN keyset ON blind_signature.keyset_id = keyset.id
             WHERE keyset.unit = $1;
         "#,
-        &unit.to_string()
+        unit
     )
     .fetch_one(conn)
     .await?;
@@ -106,3 +106,29 @@ pub async fn get_by_blind_secrets(
 
     Ok(ret)
 }
+
+/// Returns the blinded secret, amount and signature of every signature issued with this keyset
+///
+/// They are ordered by blinded secret.
+pub async fn get_keyset_blind_signatures(
+    conn: &mut PgConnection,
+    keyset_id: KeysetId,
+) -> Result<Vec<(PublicKey, Amount, PublicKey)>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT y, amount, c FROM blind_signature WHERE keyset_id = $1 ORDER BY y"#,
+        keyset_id.as_i64()
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok((
+                PublicKey::from_slice(&r.y).map_err(|_| Error::DbToRuntimeConversion)?,
+                Amount::from_i64_repr(r.amount),
+                PublicKey::from_slice(&r.c).map_err(|_| Error::DbToRuntimeConversion)?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,46 @@
+//! Conversions of ecash from one unit to another
+use nuts::Amount;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// An exchange, as stored in the transaction of the spent inputs and signed outputs
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub id: Uuid,
+    pub input_unit: String,
+    /// Total amount of the inputs
+    pub input_amount: Amount,
+    pub output_unit: String,
+    /// Total amount of the outputs
+    pub output_amount: Amount,
+    /// Output per input, spread included, as a decimal string
+    pub rate: String,
+    /// Taken by the node, in the output unit
+    pub fee: Amount,
+    /// Bought by the inputs on top of the outputs, kept by the node, in the output unit
+    pub surplus: Amount,
+}
+
+pub async fn insert(conn: &mut PgConnection, exchange: &Exchange) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO exchange
+            (id, input_unit, input_amount, output_unit, output_amount, rate, fee, surplus)
+        VALUES
+            ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+        exchange.id,
+        exchange.input_unit,
+        exchange.input_amount.into_i64_repr(),
+        exchange.output_unit,
+        exchange.output_amount.into_i64_repr(),
+        exchange.rate,
+        exchange.fee.into_i64_repr(),
+        exchange.surplus.into_i64_repr(),
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -1,15 +1,12 @@
 use num_traits::ToPrimitive;
-use nuts::{Amount, traits::Unit};
+use nuts::{Amount, nut07::ProofState};
 use sqlx::Error;
 use sqlx::PgConnection;
 
-pub async fn get_all_gauge_metrics_by_units<U: Unit>(
+pub async fn get_all_gauge_metrics_by_units(
     conn: &mut PgConnection,
-    units: &[U],
+    units: &[String],
 ) -> Result<Vec<(String, GaugeMetrics)>, Error> {
-    // Convert each unit to string
-    let unit_strs: Vec<String> = units.iter().map(|u| u.to_string()).collect();
-
     // Query data for all units at once
     let records = sqlx::query!(
         r#"
@@ -20,10 +17,16 @@ pub async fn get_all_gauge_metrics_by_units<U: Unit>(
                 (SELECT COALESCE(SUM(amount), 0) FROM mint_quote WHERE unit = mq.unit AND state = 'ISSUED') AS "issued_deposits!",
                 (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = mq.unit AND state = 'UNPAID') AS "unpaid_withdrawals!",
                 (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = mq.unit AND state = 'PENDING') AS "pending_withdrawals!",
-                (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = mq.unit AND state = 'PAID') AS "paid_withdrawals!"
+                (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = mq.unit AND state = 'PAID') AS "paid_withdrawals!",
+                GREATEST(
+                    (SELECT COALESCE(SUM(bs.amount), 0) FROM blind_signature bs INNER JOIN keyset k ON bs.keyset_id = k.id WHERE k.unit = mq.unit)
+                    - (SELECT COALESCE(SUM(p.amount), 0) FROM proof p INNER JOIN keyset k ON p.keyset_id = k.id WHERE k.unit = mq.unit AND p.state = $2),
+                    0
+                ) AS "outstanding_ecash!"
             FROM (SELECT DISTINCT unit FROM unnest($1::text[]) AS unit) mq
         "#,
-        &unit_strs
+        units,
+        ProofState::Spent as i16
     )
     .fetch_all(conn)
     .await?;
@@ -39,6 +42,7 @@ pub async fn get_all_gauge_metrics_by_units<U: Unit>(
                 unpaid_withdrawals: Amount::from(record.unpaid_withdrawals.to_u64().unwrap()),
                 pending_withdrawals: Amount::from(record.pending_withdrawals.to_u64().unwrap()),
                 paid_withdrawals: Amount::from(record.paid_withdrawals.to_u64().unwrap()),
+                outstanding_ecash: Amount::from(record.outstanding_ecash.to_u64().unwrap()),
             },
         ));
     }
@@ -55,4 +59,6 @@ pub struct GaugeMetrics {
     pub unpaid_withdrawals: Amount,
     pub pending_withdrawals: Amount,
     pub paid_withdrawals: Amount,
+    /// Signatures issued minus proofs spent, for all the keysets of the unit
+    pub outstanding_ecash: Amount,
 }
@@ -1,16 +1,17 @@
 use std::str::FromStr;
 
 use nuts::nut02::KeysetId;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, types::time::OffsetDateTime};
 
 use crate::Error;
 
 #[derive(Debug, Clone)]
 pub struct KeysetInfo<U> {
-    unit: U,
-    active: bool,
-    max_order: u8,
-    derivation_path_index: u32,
+    pub(crate) unit: U,
+    pub(crate) active: bool,
+    pub(crate) max_order: u8,
+    pub(crate) derivation_path_index: u32,
+    pub(crate) final_expiry: Option<u64>,
 }
 
 impl<U> KeysetInfo<U> {
@@ -23,6 +24,10 @@ impl<U> KeysetInfo<U> {
     pub fn derivation_path_index(&self) -> u32 {
         self.derivation_path_index
     }
+    /// Unix timestamp after which proofs of this keyset are not redeemable anymore
+    pub fn final_expiry(&self) -> Option<u64> {
+        self.final_expiry
+    }
 }
 
 impl<U: Clone> KeysetInfo<U> {
@@ -31,16 +36,48 @@ impl<U: Clone> KeysetInfo<U> {
     }
 }
 
+impl KeysetInfo<String> {
+    /// Convert the unit, as stored in db, into the runtime type
+    pub fn parse_unit<U: FromStr>(self) -> Result<KeysetInfo<U>, Error> {
+        Ok(KeysetInfo {
+            unit: U::from_str(&self.unit).map_err(|_| Error::InvalidUnit(self.unit))?,
+            active: self.active,
+            max_order: self.max_order,
+            derivation_path_index: self.derivation_path_index,
+            final_expiry: self.final_expiry,
+        })
+    }
+}
+
+fn final_expiry_from_db(final_expiry: Option<OffsetDateTime>) -> Result<Option<u64>, Error> {
+    final_expiry
+        .map(|fe| {
+            fe.unix_timestamp()
+                .try_into()
+                .map_err(|_| Error::DbToRuntimeConversion)
+        })
+        .transpose()
+}
+
+#[allow(clippy::type_complexity)]
 pub async fn get_keysets(
     conn: &mut PgConnection,
-) -> Result<impl Iterator<Item = ([u8; 8], String, bool)>, sqlx::Error> {
-    let record = sqlx::query!("SELECT id, unit, active FROM keyset")
+) -> Result<Vec<([u8; 8], String, bool, Option<u64>)>, Error> {
+    let record = sqlx::query!("SELECT id, unit, active, final_expiry FROM keyset")
         .fetch_all(conn)
         .await?;
 
-    Ok(record
+    record
         .into_iter()
-        .map(|r| (r.id.to_be_bytes(), r.unit, r.active)))
+        .map(|r| {
+            Ok((
+                r.id.to_be_bytes(),
+                r.unit,
+                r.active,
+                final_expiry_from_db(r.final_expiry)?,
+            ))
+        })
+        .collect()
 }
 
 pub async fn get_keyset<U: FromStr>(
@@ -48,7 +85,7 @@ pub async fn get_keyset<U: FromStr>(
     keyset_id: &KeysetId,
 ) -> Result<KeysetInfo<U>, Error> {
     let record = sqlx::query!(
-        r#"SELECT unit, active, max_order, derivation_path_index
+        r#"SELECT unit, active, max_order, derivation_path_index, final_expiry
         FROM keyset
         WHERE id = $1"#,
         keyset_id.as_i64()
@@ -61,6 +98,7 @@ pub async fn get_keyset<U: FromStr>(
         active: record.active,
         max_order: u8::try_from(record.max_order).map_err(|_| Error::DbToRuntimeConversion)?,
         derivation_path_index: u32::from_be_bytes(record.derivation_path_index.to_be_bytes()),
+        final_expiry: final_expiry_from_db(record.final_expiry)?,
     };
 
     Ok(info)
@@ -86,7 +124,7 @@ pub async fn get_active_keysets<U: FromStr>(
     conn: &mut PgConnection,
 ) -> Result<Vec<(KeysetId, KeysetInfo<U>)>, Error> {
     let records = sqlx::query!(
-        r#"SELECT id, unit, active, max_order, derivation_path_index
+        r#"SELECT id, unit, active, max_order, derivation_path_index, final_expiry
         FROM keyset
         WHERE active = TRUE"#,
     )
@@ -107,6 +145,7 @@ pub async fn get_active_keysets<U: FromStr>(
                     derivation_path_index: u32::from_be_bytes(
                         record.derivation_path_index.to_be_bytes(),
                     ),
+                    final_expiry: final_expiry_from_db(record.final_expiry)?,
                 },
             ))
         })
@@ -115,10 +154,53 @@ pub async fn get_active_keysets<U: FromStr>(
     Ok(keysets_info)
 }
 
-pub async fn deactivate_keysets(conn: &mut PgConnection, keyset_ids: &[i64]) -> Result<(), Error> {
+/// Returns the id of every active keyset, with the unix timestamp of its creation
+///
+/// The creation of the active keyset of a unit is the last rotation of this unit.
+pub async fn get_active_keysets_created_at(
+    conn: &mut PgConnection,
+) -> Result<Vec<(KeysetId, u64)>, Error> {
+    let records = sqlx::query!(r#"SELECT id, created_at FROM keyset WHERE active = TRUE"#)
+        .fetch_all(conn)
+        .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            Ok((
+                KeysetId::from_bytes(&record.id.to_be_bytes())
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                u64::try_from(record.created_at.unix_timestamp())
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+            ))
+        })
+        .collect()
+}
+
+/// Deactivate the keysets, and optionally schedule their retirement
+///
+/// After `final_expiry` (a unix timestamp), the proofs of those keysets should not be accepted anymore.
+/// An already scheduled retirement is kept if `final_expiry` is `None`.
+pub async fn deactivate_keysets(
+    conn: &mut PgConnection,
+    keyset_ids: &[i64],
+    final_expiry: Option<u64>,
+) -> Result<(), Error> {
+    let final_expiry = final_expiry
+        .map(|fe| {
+            i64::try_from(fe)
+                .ok()
+                .and_then(|fe| OffsetDateTime::from_unix_timestamp(fe).ok())
+                .ok_or(Error::RuntimeToDbConversion)
+        })
+        .transpose()?;
+
     sqlx::query!(
-        "UPDATE keyset SET active = false WHERE id = ANY($1)",
-        keyset_ids
+        r#"UPDATE keyset
+        SET active = false, final_expiry = COALESCE($2, final_expiry)
+        WHERE id = ANY($1)"#,
+        keyset_ids,
+        final_expiry,
     )
     .execute(conn)
     .await?;
@@ -0,0 +1,82 @@
+//! Leader election between the nodes sharing a database
+//!
+//! Some background tasks, such as the payment indexer or the keyset rotator,
+//! must run on a single node at a time, whatever the number of replicas.
+//! Each of them is run through [`run_as_leader`], which only starts it once this node is elected,
+//! and stops it if the leadership is lost, to let another node take over.
+use std::{future::Future, time::Duration};
+
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::SharedStorage;
+
+/// How long to wait before trying again to become the leader
+const ELECTION_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the leader checks that it still holds the leadership
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Key of the Postgres advisory lock electing the leader of `task`
+pub(crate) fn lock_key(task: &str) -> i64 {
+    let hash = Sha256::new()
+        .chain_update(b"leader:")
+        .chain_update(task.as_bytes())
+        .finalize();
+
+    i64::from_be_bytes(
+        hash[..8]
+            .try_into()
+            .expect("a sha256 is longer than 8 bytes"),
+    )
+}
+
+/// Run the future returned by `run` while this node leads `task`
+///
+/// Returns when that future does. If the leadership is lost before,
+/// the future is dropped and the election starts over, a new future being created once elected again.
+pub async fn run_as_leader<F, Fut>(storage: SharedStorage, task: &str, mut run: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    loop {
+        let mut leadership = match storage.try_lead(task).await {
+            Ok(Some(leadership)) => leadership,
+            Ok(None) => {
+                tokio::time::sleep(ELECTION_INTERVAL).await;
+                continue;
+            }
+            Err(err) => {
+                error!(name: "leader-election", task, error = %err);
+                tokio::time::sleep(ELECTION_INTERVAL).await;
+                continue;
+            }
+        };
+        info!(name: "leader-elected", task);
+
+        let heartbeat = async {
+            loop {
+                tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+                if !leadership.is_held().await {
+                    break;
+                }
+            }
+        };
+
+        tokio::select! {
+            () = run() => return,
+            () = heartbeat => warn!(name: "leadership-lost", task),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lock_key;
+
+    #[test]
+    fn lock_keys_differ_between_tasks() {
+        assert_eq!(lock_key("indexer"), lock_key("indexer"));
+        assert_ne!(lock_key("indexer"), lock_key("keyset-rotator"));
+    }
+}
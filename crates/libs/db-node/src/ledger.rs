@@ -0,0 +1,190 @@
+//! Aggregates used to check the consistency of the node ledger
+//!
+//! Archived quotes are taken into account, so that the totals cover the whole life of the node.
+use nuts::{
+    Amount, nut02::KeysetId, nut04::MintQuoteState, nut05::MeltQuoteState, nut07::ProofState,
+};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// The number of signatures issued, and of proofs spent, for one amount of one keyset
+#[derive(Debug, Clone)]
+pub struct KeysetAmountLedger {
+    pub keyset_id: KeysetId,
+    pub unit: String,
+    pub amount: Amount,
+    pub issued: u64,
+    pub spent: u64,
+}
+
+/// The total amount of the quotes of one unit that moved funds in or out of the node
+#[derive(Debug, Clone)]
+pub struct UnitQuoteLedger {
+    pub unit: String,
+    /// Sum of the ISSUED mint quotes
+    pub minted: Amount,
+    /// Sum of the PENDING and PAID melt quotes, fees included
+    pub melted: Amount,
+    /// Part of `melted` kept by the node as fees
+    pub melt_fees: Amount,
+}
+
+/// The total amount of the exchanges that moved ecash of one unit
+#[derive(Debug, Clone)]
+pub struct UnitExchangeLedger {
+    pub unit: String,
+    /// Sum of the outputs issued in this unit
+    pub exchanged_in: Amount,
+    /// Sum of the inputs spent in this unit
+    pub exchanged_out: Amount,
+}
+
+pub(crate) fn count_from_db(count: i64) -> Result<u64, Error> {
+    u64::try_from(count).map_err(|_| Error::DbToRuntimeConversion)
+}
+
+pub async fn get_keyset_amount_ledger(
+    conn: &mut PgConnection,
+) -> Result<Vec<KeysetAmountLedger>, Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT keyset.id AS "keyset_id!", keyset.unit AS "unit!", t.amount AS "amount!",
+            SUM(t.issued)::INT8 AS "issued!", SUM(t.spent)::INT8 AS "spent!"
+        FROM (
+            SELECT keyset_id, amount, COUNT(*) AS issued, 0::INT8 AS spent
+            FROM blind_signature GROUP BY keyset_id, amount
+            UNION ALL
+            SELECT keyset_id, amount, 0::INT8, COUNT(*)
+            FROM proof WHERE state = $1 GROUP BY keyset_id, amount
+        ) AS t
+        INNER JOIN keyset ON keyset.id = t.keyset_id
+        GROUP BY keyset.id, keyset.unit, t.amount
+        ORDER BY keyset.id, t.amount"#,
+        ProofState::Spent as i16
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(KeysetAmountLedger {
+                keyset_id: KeysetId::try_from(r.keyset_id)
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                unit: r.unit,
+                amount: Amount::from_i64_repr(r.amount),
+                issued: count_from_db(r.issued)?,
+                spent: count_from_db(r.spent)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_unit_quote_ledger(conn: &mut PgConnection) -> Result<Vec<UnitQuoteLedger>, Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT unit AS "unit!", SUM(minted)::INT8 AS "minted!", SUM(melted)::INT8 AS "melted!",
+            SUM(fee)::INT8 AS "melt_fees!"
+        FROM (
+            SELECT unit, amount AS minted, 0::INT8 AS melted, 0::INT8 AS fee
+            FROM mint_quote WHERE state = 'ISSUED'
+            UNION ALL
+            SELECT unit, amount, 0, 0 FROM mint_quote_history WHERE state = 'ISSUED'
+            UNION ALL
+            SELECT unit, 0, amount, fee FROM melt_quote WHERE state IN ('PENDING', 'PAID')
+            UNION ALL
+            SELECT unit, 0, amount, fee FROM melt_quote_history WHERE state IN ('PENDING', 'PAID')
+        ) AS t
+        GROUP BY unit
+        ORDER BY unit"#
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| UnitQuoteLedger {
+            unit: r.unit,
+            minted: Amount::from_i64_repr(r.minted),
+            melted: Amount::from_i64_repr(r.melted),
+            melt_fees: Amount::from_i64_repr(r.melt_fees),
+        })
+        .collect())
+}
+
+pub async fn get_unit_exchange_ledger(
+    conn: &mut PgConnection,
+) -> Result<Vec<UnitExchangeLedger>, Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT unit AS "unit!", SUM(exchanged_in)::INT8 AS "exchanged_in!",
+            SUM(exchanged_out)::INT8 AS "exchanged_out!"
+        FROM (
+            SELECT output_unit AS unit, output_amount AS exchanged_in, 0::INT8 AS exchanged_out
+            FROM exchange
+            UNION ALL
+            SELECT input_unit, 0, input_amount FROM exchange
+        ) AS t
+        GROUP BY unit
+        ORDER BY unit"#
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| UnitExchangeLedger {
+            unit: r.unit,
+            exchanged_in: Amount::from_i64_repr(r.exchanged_in),
+            exchanged_out: Amount::from_i64_repr(r.exchanged_out),
+        })
+        .collect())
+}
+
+/// Returns the PAID and ISSUED mint quotes for which no payment was ever received
+pub async fn get_mint_quotes_without_payment_event(
+    conn: &mut PgConnection,
+) -> Result<Vec<(Uuid, MintQuoteState)>, Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT id AS "id!", state AS "state!: MintQuoteState" FROM mint_quote
+        WHERE state IN ('PAID', 'ISSUED') AND NOT EXISTS (
+            SELECT 1 FROM mint_payment_event WHERE mint_payment_event.invoice_id = mint_quote.invoice_id
+        )
+        UNION ALL
+        SELECT id, state FROM mint_quote_history
+        WHERE state = 'ISSUED' AND NOT EXISTS (
+            SELECT 1 FROM mint_payment_event_history
+            WHERE mint_payment_event_history.invoice_id = mint_quote_history.invoice_id
+        )"#
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records.into_iter().map(|r| (r.id, r.state)).collect())
+}
+
+/// Returns the PAID melt quotes for which no payment was ever sent
+pub async fn get_melt_quotes_without_payment_event(
+    conn: &mut PgConnection,
+) -> Result<Vec<(Uuid, MeltQuoteState)>, Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT id AS "id!", state AS "state!: MeltQuoteState" FROM melt_quote
+        WHERE state = 'PAID' AND NOT EXISTS (
+            SELECT 1 FROM melt_payment_event WHERE melt_payment_event.invoice_id = melt_quote.invoice_id
+        )
+        UNION ALL
+        SELECT id, state FROM melt_quote_history
+        WHERE state = 'PAID' AND NOT EXISTS (
+            SELECT 1 FROM melt_payment_event_history
+            WHERE melt_payment_event_history.invoice_id = melt_quote_history.invoice_id
+        )"#
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records.into_iter().map(|r| (r.id, r.state)).collect())
+}
@@ -2,19 +2,31 @@ use nuts::nut01::PublicKey;
 use sqlx::{Connection, PgConnection, Pool, Postgres, Transaction};
 use thiserror::Error;
 
+pub mod audit;
 pub mod gauge;
 mod insert_blind_signatures;
 pub use insert_blind_signatures::InsertBlindSignaturesQueryBuilder;
 mod insert_keysets;
 pub use insert_keysets::InsertKeysetsQueryBuilder;
 pub mod blind_signature;
+pub mod exchange;
 pub mod keyset;
+pub mod leader;
+pub mod ledger;
 pub mod melt_payment_event;
 pub mod melt_quote;
+pub mod melt_withdrawal;
 pub mod mint_payment_event;
 pub mod mint_quote;
+mod payment_event;
+pub mod postgres;
 pub mod proof;
+pub mod response_cache;
+pub mod sqlite;
+mod storage;
+pub use payment_event::PaymentEvent;
 pub use proof::InsertSpentProofsQueryBuilder;
+pub use storage::{Leadership, SharedStorage, Storage, StorageConn, StorageTx};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -24,6 +36,8 @@ pub enum Error {
     HashOnCurve,
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
     #[error("Failed to convert the unit db record to the passed generic Unit type: \"{0}\"")]
     InvalidUnit(String),
     #[error("Failed to convert the db type into the runtime type")]
@@ -1,5 +1,6 @@
 use nuts::{
     Amount,
+    nut01::PublicKey,
     nut05::{MeltQuoteResponse, MeltQuoteState},
     traits::Unit,
 };
@@ -10,11 +11,11 @@ use crate::Error;
 
 // TODO: use a struct and ToSql trait instead
 #[allow(clippy::too_many_arguments)]
-pub async fn insert_new<U: Unit>(
+pub async fn insert_new(
     conn: &mut PgConnection,
     quote_id: Uuid,
     invoice_id: &[u8; 32],
-    unit: U,
+    unit: &str,
     amount: Amount,
     fee: Amount,
     request: &str,
@@ -34,7 +35,7 @@ pub async fn insert_new<U: Unit>(
             ($1, $2, $3, $4, $5, $6, $7, 'UNPAID')"#,
         quote_id,
         invoice_id,
-        &unit.to_string(),
+        unit,
         amount.into_i64_repr(),
         fee.into_i64_repr(),
         request,
@@ -46,20 +47,50 @@ pub async fn insert_new<U: Unit>(
     Ok(())
 }
 
-pub async fn build_response_from_db<U: Unit>(
+/// The content of a `MeltQuoteResponse`, with the unit as stored in db
+#[derive(Debug, Clone)]
+pub struct MeltQuoteResponseRecord {
+    pub unit: String,
+    /// Includes the fee
+    pub amount: Amount,
+    pub fee: Amount,
+    pub state: MeltQuoteState,
+    pub expiry: u64,
+    pub transfer_ids: Option<Vec<String>>,
+}
+
+impl MeltQuoteResponseRecord {
+    pub fn into_response<U: Unit>(
+        self,
+        quote_id: Uuid,
+    ) -> Result<MeltQuoteResponse<Uuid, U>, Error> {
+        Ok(MeltQuoteResponse {
+            quote: quote_id,
+            unit: U::from_str(&self.unit).map_err(|_| Error::InvalidUnit(self.unit))?,
+            amount: self.amount - self.fee,
+            fee_reserve: self.fee,
+            state: self.state,
+            expiry: self.expiry,
+            transfer_ids: self.transfer_ids,
+        })
+    }
+}
+
+pub async fn build_response_from_db(
     conn: &mut PgConnection,
     quote_id: Uuid,
-) -> Result<MeltQuoteResponse<Uuid, U>, Error> {
+) -> Result<MeltQuoteResponseRecord, Error> {
     let record = sqlx::query!(
         r#"SELECT
-            mq.amount, 
+            mq.amount,
+            mq.fee,
             mq.unit,
             mq.state AS "state: MeltQuoteState",
             mq.expiry,
             COALESCE(ARRAY_AGG(mpe.tx_hash) FILTER (WHERE mpe.tx_hash IS NOT NULL), '{}') AS "tx_hashes"
         FROM melt_quote mq LEFT JOIN melt_payment_event mpe ON mq.invoice_id = mpe.invoice_id
         WHERE mq.id = $1
-        GROUP BY mq.amount, mq.unit, mq.state, mq.expiry"#,
+        GROUP BY mq.amount, mq.fee, mq.unit, mq.state, mq.expiry"#,
         quote_id
     )
     .fetch_one(conn)
@@ -72,30 +103,40 @@ pub async fn build_response_from_db<U: Unit>(
         .try_into()
         .map_err(|_| Error::DbToRuntimeConversion)?;
     let amount = Amount::from_i64_repr(record.amount);
-    let unit = U::from_str(&record.unit).map_err(|_| Error::DbToRuntimeConversion)?;
+    let fee = Amount::from_i64_repr(record.fee);
 
-    Ok(MeltQuoteResponse {
-        quote: quote_id,
-        unit,
+    Ok(MeltQuoteResponseRecord {
+        unit: record.unit,
         amount,
+        fee,
         state: record.state,
         expiry,
         transfer_ids: record.tx_hashes,
     })
 }
 
-pub async fn get_data<U: Unit>(
-    conn: &mut PgConnection,
-    quote_id: Uuid,
-) -> Result<(U, Amount, Amount, MeltQuoteState, u64, [u8; 32], String), Error> {
+/// What is needed to process a melt request
+#[derive(Debug, Clone)]
+pub struct MeltQuoteData {
+    pub unit: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub state: MeltQuoteState,
+    pub expiry: u64,
+    pub invoice_id: [u8; 32],
+    pub request: String,
+}
+
+/// Locks the quote row until the end of the transaction,
+/// so that concurrent transactions can't both act on the same state
+pub async fn get_data(conn: &mut PgConnection, quote_id: Uuid) -> Result<MeltQuoteData, Error> {
     let record = sqlx::query!(
-        r#"SELECT unit, amount, fee, state AS "state: MeltQuoteState", invoice_id, expiry, request FROM melt_quote where id = $1"#,
+        r#"SELECT unit, amount, fee, state AS "state: MeltQuoteState", invoice_id, expiry, request FROM melt_quote where id = $1 FOR UPDATE"#,
         quote_id
     )
     .fetch_one(conn)
     .await?;
 
-    let unit = U::from_str(&record.unit).map_err(|_| Error::DbToRuntimeConversion)?;
     let amount = Amount::from_i64_repr(record.amount);
     let fee = Amount::from_i64_repr(record.fee);
     let expiry = record
@@ -109,15 +150,15 @@ pub async fn get_data<U: Unit>(
         .try_into()
         .map_err(|_| Error::DbToRuntimeConversion)?;
 
-    Ok((
-        unit,
+    Ok(MeltQuoteData {
+        unit: record.unit,
         amount,
         fee,
-        record.state,
+        state: record.state,
         expiry,
-        quote_hash,
-        record.request,
-    ))
+        invoice_id: quote_hash,
+        request: record.request,
+    })
 }
 
 pub async fn get_state(conn: &mut PgConnection, quote_id: Uuid) -> Result<MeltQuoteState, Error> {
@@ -151,10 +192,73 @@ pub async fn set_state(
     Ok(())
 }
 
-pub async fn get_quote_infos_by_invoice_id<U: Unit>(
+/// Lower the amount and the fee of a quote by the part of the fee reserve returned as change
+pub async fn return_change(
+    conn: &mut PgConnection,
+    quote_id: Uuid,
+    change: Amount,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE melt_quote SET amount = amount - $2, fee = fee - $2 WHERE id = $1"#,
+        quote_id,
+        change.into_i64_repr(),
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Link the proofs spent by the melt to the quote, to be called in the transaction spending them
+pub async fn insert_inputs(
+    conn: &mut PgConnection,
+    quote_id: Uuid,
+    ys: &[PublicKey],
+) -> Result<(), sqlx::Error> {
+    let ys: Vec<_> = ys.iter().map(|y| y.to_bytes().to_vec()).collect();
+
+    sqlx::query!(
+        r#"INSERT INTO melt_quote_input (y, quote_id) SELECT y, $2 FROM UNNEST($1::BYTEA[]) AS y"#,
+        &ys,
+        quote_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Set a pending quote back to unpaid, unspending the proofs spent by its melt
+///
+/// Returns false if there is no pending quote with this id.
+pub async fn revert(conn: &mut PgConnection, quote_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE melt_quote SET state = 'UNPAID' WHERE id = $1 AND state = 'PENDING'"#,
+        quote_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        r#"WITH input AS (
+            DELETE FROM melt_quote_input WHERE quote_id = $1 RETURNING y
+        )
+        DELETE FROM proof WHERE y IN (SELECT y FROM input)"#,
+        quote_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(true)
+}
+
+pub async fn get_quote_infos_by_invoice_id(
     conn: &mut PgConnection,
     invoice_id: &[u8; 32],
-) -> Result<Option<(Uuid, Amount, U)>, Error> {
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
     let record = sqlx::query!(
         r#"
             SELECT id, amount, unit from melt_quote WHERE invoice_id = $1 LIMIT 1
@@ -164,16 +268,45 @@ pub async fn get_quote_infos_by_invoice_id<U: Unit>(
     .fetch_optional(conn)
     .await?;
 
-    let ret = if let Some(record) = record {
-        let quote_id = record.id;
-        let amount = Amount::from_i64_repr(record.amount);
-        let unit = U::from_str(&record.unit).map_err(|_| Error::DbToRuntimeConversion)?;
-        Some((quote_id, amount, unit))
-    } else {
-        None
-    };
+    Ok(record.map(|r| (r.id, Amount::from_i64_repr(r.amount), r.unit)))
+}
 
-    Ok(ret)
+/// Returns the id, amount and unit of the quote
+///
+/// An archived expired quote is first moved back from the history tables, along with its payment events,
+/// so that a payment received after the archival is still credited to it.
+pub async fn get_or_restore_quote_infos_by_invoice_id(
+    conn: &mut PgConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    let record = sqlx::query!(
+        r#"WITH restored_quote AS (
+            DELETE FROM melt_quote_history
+            WHERE invoice_id = $1 AND state = 'EXPIRED'
+                AND NOT EXISTS (SELECT 1 FROM melt_quote WHERE invoice_id = $1)
+            RETURNING id, invoice_id, unit, amount, fee, request, expiry, state
+        ), restored_event AS (
+            DELETE FROM melt_payment_event_history
+            WHERE invoice_id IN (SELECT invoice_id FROM restored_quote)
+            RETURNING block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        ), inserted_event AS (
+            INSERT INTO melt_payment_event (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+            SELECT * FROM restored_event
+        ), inserted_quote AS (
+            INSERT INTO melt_quote (id, invoice_id, unit, amount, fee, request, expiry, state)
+            SELECT * FROM restored_quote
+            RETURNING id, amount, unit
+        )
+        SELECT id AS "id!", amount AS "amount!", unit AS "unit!" FROM melt_quote WHERE invoice_id = $1
+        UNION ALL
+        SELECT id, amount, unit FROM inserted_quote
+        LIMIT 1"#,
+        invoice_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(record.map(|r| (r.id, Amount::from_i64_repr(r.amount), r.unit)))
 }
 
 pub async fn get_state_and_transfer_ids(
@@ -194,3 +327,140 @@ pub async fn get_state_and_transfer_ids(
 
     Ok((record.state, record.tx_hashes))
 }
+
+#[derive(Debug, Clone)]
+pub struct MeltQuoteInfo {
+    pub id: Uuid,
+    pub unit: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub state: MeltQuoteState,
+    pub expiry: u64,
+    pub request: String,
+}
+
+/// List the most recent quotes, optionally filtered by state
+pub async fn list(
+    conn: &mut PgConnection,
+    state: Option<MeltQuoteState>,
+    limit: i64,
+) -> Result<Vec<MeltQuoteInfo>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT id, unit, amount, fee, state AS "state: MeltQuoteState", expiry, request
+        FROM melt_quote
+        WHERE $1::TEXT IS NULL OR state::TEXT = $1
+        ORDER BY expiry DESC
+        LIMIT $2"#,
+        state.map(|s| s.to_string()),
+        limit,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(MeltQuoteInfo {
+                id: r.id,
+                unit: r.unit,
+                amount: Amount::from_i64_repr(r.amount),
+                fee: Amount::from_i64_repr(r.fee),
+                state: r.state,
+                expiry: r
+                    .expiry
+                    .unix_timestamp()
+                    .try_into()
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                request: r.request,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_info(conn: &mut PgConnection, quote_id: Uuid) -> Result<MeltQuoteInfo, Error> {
+    let record = sqlx::query!(
+        r#"SELECT unit, amount, fee, state AS "state: MeltQuoteState", expiry, request FROM melt_quote WHERE id = $1"#,
+        quote_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(MeltQuoteInfo {
+        id: quote_id,
+        unit: record.unit,
+        amount: Amount::from_i64_repr(record.amount),
+        fee: Amount::from_i64_repr(record.fee),
+        state: record.state,
+        expiry: record
+            .expiry
+            .unix_timestamp()
+            .try_into()
+            .map_err(|_| Error::DbToRuntimeConversion)?,
+        request: record.request,
+    })
+}
+
+/// Set the expiry of an unpaid quote in the past and mark it as expired
+///
+/// Returns false if there is no unpaid quote with this id.
+pub async fn force_expire(conn: &mut PgConnection, quote_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE melt_quote
+        SET expiry = LEAST(expiry, NOW() - INTERVAL '1 second'), state = 'EXPIRED'
+        WHERE id = $1 AND state = 'UNPAID'"#,
+        quote_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Mark the unpaid quotes whose expiry is elapsed as expired
+///
+/// Returns the number of quotes updated.
+pub async fn expire_unpaid(conn: &mut PgConnection) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE melt_quote SET state = 'EXPIRED' WHERE state = 'UNPAID' AND expiry <= NOW()"#
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Move the terminal quotes expired for more than `older_than` seconds,
+/// and their payment events, to the history tables
+///
+/// An expired quote that received a partial payment is kept, its funds still being owed.
+/// Returns the number of quotes archived.
+pub async fn archive(conn: &mut PgConnection, older_than: u64) -> Result<u64, Error> {
+    let older_than = i64::try_from(older_than).map_err(|_| Error::RuntimeToDbConversion)?;
+
+    let result = sqlx::query!(
+        r#"WITH archived_quote AS (
+            DELETE FROM melt_quote
+            WHERE expiry < NOW() - $1::BIGINT * INTERVAL '1 second' AND (
+                state = 'PAID'
+                OR (state = 'EXPIRED' AND NOT EXISTS (
+                    SELECT 1 FROM melt_payment_event e WHERE e.invoice_id = melt_quote.invoice_id
+                ))
+            )
+            RETURNING id, invoice_id, unit, amount, fee, request, expiry, state
+        ), archived_event AS (
+            DELETE FROM melt_payment_event
+            WHERE invoice_id IN (SELECT invoice_id FROM archived_quote)
+            RETURNING block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        ), inserted_event AS (
+            INSERT INTO melt_payment_event_history (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+            SELECT * FROM archived_event
+        )
+        INSERT INTO melt_quote_history (id, invoice_id, unit, amount, fee, request, expiry, state)
+        SELECT * FROM archived_quote"#,
+        older_than
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
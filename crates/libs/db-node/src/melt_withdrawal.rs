@@ -0,0 +1,86 @@
+//! The payments the node still has to send for its pending melt quotes
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// A payment to send, stored once the inputs of its melt are spent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeltWithdrawal {
+    pub quote_id: Uuid,
+    /// Serialized by the liquidity source
+    pub payment: String,
+    /// The last transaction sent for the payment, None until one is sent or after it failed
+    pub tx_hash: Option<String>,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    quote_id: Uuid,
+    method: &str,
+    payment: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"INSERT INTO melt_withdrawal (quote_id, method, payment) VALUES ($1, $2, $3)"#,
+        quote_id,
+        method,
+        payment
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// List the oldest withdrawals of `method` first
+pub async fn list(
+    conn: &mut PgConnection,
+    method: &str,
+    limit: i64,
+) -> Result<Vec<MeltWithdrawal>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"SELECT quote_id, payment, tx_hash FROM melt_withdrawal
+        WHERE method = $1
+        ORDER BY created_at, quote_id
+        LIMIT $2"#,
+        method,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    let withdrawals = records
+        .into_iter()
+        .map(|record| MeltWithdrawal {
+            quote_id: record.quote_id,
+            payment: record.payment,
+            tx_hash: record.tx_hash,
+        })
+        .collect();
+
+    Ok(withdrawals)
+}
+
+pub async fn set_tx_hash(
+    conn: &mut PgConnection,
+    quote_id: Uuid,
+    tx_hash: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE melt_withdrawal SET tx_hash = $2 WHERE quote_id = $1"#,
+        quote_id,
+        tx_hash
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(conn: &mut PgConnection, quote_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM melt_withdrawal WHERE quote_id = $1"#,
+        quote_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use sqlx::PgConnection;
-use starknet_payment_indexer::PaymentEvent;
+
+use crate::PaymentEvent;
 
 pub async fn insert_new_payment_event(
     db_conn: &mut PgConnection,
@@ -12,14 +13,14 @@ pub async fn insert_new_payment_event(
                 ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             ON CONFLICT DO NOTHING"#,
         &payment_event.block_id,
-        &payment_event.tx_hash.to_string(),
+        &payment_event.tx_hash,
         i64::from_be_bytes(payment_event.event_idx.to_be_bytes()),
-        &payment_event.payee.to_string(),
-        &payment_event.asset.to_string(),
-        &payment_event.invoice_id.to_bytes_be(),
-        &payment_event.payer.to_string(),
-        &payment_event.amount.low.to_string(),
-        &payment_event.amount.high.to_string()
+        &payment_event.payee,
+        &payment_event.asset,
+        &payment_event.invoice_id,
+        &payment_event.payer,
+        &payment_event.amount_low,
+        &payment_event.amount_high
     )
     .execute(db_conn)
     .await?;
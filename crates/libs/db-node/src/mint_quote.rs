@@ -1,18 +1,17 @@
 use nuts::{
     Amount,
     nut04::{MintQuoteResponse, MintQuoteState},
-    traits::Unit,
 };
 use sqlx::{PgConnection, types::time::OffsetDateTime};
 use uuid::Uuid;
 
 use crate::Error;
 
-pub async fn insert_new<U: Unit>(
+pub async fn insert_new(
     conn: &mut PgConnection,
     quote_id: Uuid,
     invoice_id: [u8; 32],
-    unit: U,
+    unit: &str,
     amount: Amount,
     request: &str,
     expiry: u64,
@@ -26,7 +25,7 @@ pub async fn insert_new<U: Unit>(
         r#"INSERT INTO mint_quote (id, invoice_id, unit, amount, request, expiry, state) VALUES ($1, $2, $3, $4, $5, $6, 'UNPAID')"#,
         quote_id,
         &invoice_id,
-        &unit.to_string(),
+        unit,
         amount.into_i64_repr(),
         request,
         expiry,
@@ -60,12 +59,14 @@ pub async fn build_response_from_db(
     })
 }
 
+/// Locks the quote row until the end of the transaction,
+/// so that concurrent transactions can't both act on the same state
 pub async fn get_amount_and_state(
     conn: &mut PgConnection,
     quote_id: Uuid,
 ) -> Result<(Amount, MintQuoteState), Error> {
     let record = sqlx::query!(
-        r#"SELECT amount, state AS "state: MintQuoteState" FROM mint_quote where id = $1"#,
+        r#"SELECT amount, state AS "state: MintQuoteState" FROM mint_quote where id = $1 FOR UPDATE"#,
         quote_id
     )
     .fetch_one(conn)
@@ -96,10 +97,10 @@ pub async fn set_state(
     Ok(())
 }
 
-pub async fn get_quote_infos_by_invoice_id<U: Unit>(
+pub async fn get_quote_infos_by_invoice_id(
     conn: &mut PgConnection,
     invoice_id: &[u8; 32],
-) -> Result<Option<(Uuid, Amount, U)>, Error> {
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
     let record = sqlx::query!(
         r#"
             SELECT id, amount, unit from mint_quote WHERE invoice_id = $1 LIMIT 1
@@ -109,14 +110,177 @@ pub async fn get_quote_infos_by_invoice_id<U: Unit>(
     .fetch_optional(conn)
     .await?;
 
-    let ret = if let Some(record) = record {
-        let quote_id = record.id;
-        let amount = Amount::from_i64_repr(record.amount);
-        let unit = U::from_str(&record.unit).map_err(|_| Error::DbToRuntimeConversion)?;
-        Some((quote_id, amount, unit))
-    } else {
-        None
-    };
+    Ok(record.map(|r| (r.id, Amount::from_i64_repr(r.amount), r.unit)))
+}
+
+/// Returns the id, amount and unit of the quote
+///
+/// An archived expired quote is first moved back from the history tables, along with its payment events,
+/// so that a payment received after the archival is still credited to it.
+pub async fn get_or_restore_quote_infos_by_invoice_id(
+    conn: &mut PgConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    let record = sqlx::query!(
+        r#"WITH restored_quote AS (
+            DELETE FROM mint_quote_history
+            WHERE invoice_id = $1 AND state = 'EXPIRED'
+                AND NOT EXISTS (SELECT 1 FROM mint_quote WHERE invoice_id = $1)
+            RETURNING id, invoice_id, unit, amount, request, expiry, state
+        ), restored_event AS (
+            DELETE FROM mint_payment_event_history
+            WHERE invoice_id IN (SELECT invoice_id FROM restored_quote)
+            RETURNING block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        ), inserted_event AS (
+            INSERT INTO mint_payment_event (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+            SELECT * FROM restored_event
+        ), inserted_quote AS (
+            INSERT INTO mint_quote (id, invoice_id, unit, amount, request, expiry, state)
+            SELECT * FROM restored_quote
+            RETURNING id, amount, unit
+        )
+        SELECT id AS "id!", amount AS "amount!", unit AS "unit!" FROM mint_quote WHERE invoice_id = $1
+        UNION ALL
+        SELECT id, amount, unit FROM inserted_quote
+        LIMIT 1"#,
+        invoice_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(record.map(|r| (r.id, Amount::from_i64_repr(r.amount), r.unit)))
+}
+
+#[derive(Debug, Clone)]
+pub struct MintQuoteInfo {
+    pub id: Uuid,
+    pub unit: String,
+    pub amount: Amount,
+    pub state: MintQuoteState,
+    pub expiry: u64,
+    pub request: String,
+}
+
+/// List the most recent quotes, optionally filtered by state
+pub async fn list(
+    conn: &mut PgConnection,
+    state: Option<MintQuoteState>,
+    limit: i64,
+) -> Result<Vec<MintQuoteInfo>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT id, unit, amount, state AS "state: MintQuoteState", expiry, request
+        FROM mint_quote
+        WHERE $1::TEXT IS NULL OR state::TEXT = $1
+        ORDER BY expiry DESC
+        LIMIT $2"#,
+        state.map(|s| s.to_string()),
+        limit,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(MintQuoteInfo {
+                id: r.id,
+                unit: r.unit,
+                amount: Amount::from_i64_repr(r.amount),
+                state: r.state,
+                expiry: r
+                    .expiry
+                    .unix_timestamp()
+                    .try_into()
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                request: r.request,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_info(conn: &mut PgConnection, quote_id: Uuid) -> Result<MintQuoteInfo, Error> {
+    let record = sqlx::query!(
+        r#"SELECT unit, amount, state AS "state: MintQuoteState", expiry, request FROM mint_quote WHERE id = $1"#,
+        quote_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(MintQuoteInfo {
+        id: quote_id,
+        unit: record.unit,
+        amount: Amount::from_i64_repr(record.amount),
+        state: record.state,
+        expiry: record
+            .expiry
+            .unix_timestamp()
+            .try_into()
+            .map_err(|_| Error::DbToRuntimeConversion)?,
+        request: record.request,
+    })
+}
+
+/// Set the expiry of an unpaid quote in the past and mark it as expired
+///
+/// Returns false if there is no unpaid quote with this id.
+pub async fn force_expire(conn: &mut PgConnection, quote_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE mint_quote
+        SET expiry = LEAST(expiry, NOW() - INTERVAL '1 second'), state = 'EXPIRED'
+        WHERE id = $1 AND state = 'UNPAID'"#,
+        quote_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Mark the unpaid quotes whose expiry is elapsed as expired
+///
+/// Returns the number of quotes updated.
+pub async fn expire_unpaid(conn: &mut PgConnection) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"UPDATE mint_quote SET state = 'EXPIRED' WHERE state = 'UNPAID' AND expiry <= NOW()"#
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Move the terminal quotes expired for more than `older_than` seconds,
+/// and their payment events, to the history tables
+///
+/// An expired quote that received a partial payment is kept, its funds still being owed.
+/// Returns the number of quotes archived.
+pub async fn archive(conn: &mut PgConnection, older_than: u64) -> Result<u64, Error> {
+    let older_than = i64::try_from(older_than).map_err(|_| Error::RuntimeToDbConversion)?;
+
+    let result = sqlx::query!(
+        r#"WITH archived_quote AS (
+            DELETE FROM mint_quote
+            WHERE expiry < NOW() - $1::BIGINT * INTERVAL '1 second' AND (
+                state = 'ISSUED'
+                OR (state = 'EXPIRED' AND NOT EXISTS (
+                    SELECT 1 FROM mint_payment_event e WHERE e.invoice_id = mint_quote.invoice_id
+                ))
+            )
+            RETURNING id, invoice_id, unit, amount, request, expiry, state
+        ), archived_event AS (
+            DELETE FROM mint_payment_event
+            WHERE invoice_id IN (SELECT invoice_id FROM archived_quote)
+            RETURNING block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        ), inserted_event AS (
+            INSERT INTO mint_payment_event_history (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+            SELECT * FROM archived_event
+        )
+        INSERT INTO mint_quote_history (id, invoice_id, unit, amount, request, expiry, state)
+        SELECT * FROM archived_quote"#,
+        older_than
+    )
+    .execute(conn)
+    .await?;
 
-    Ok(ret)
+    Ok(result.rows_affected())
 }
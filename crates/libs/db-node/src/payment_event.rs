@@ -0,0 +1,17 @@
+/// A transfer to or from the node, as stored in the `mint_payment_event` and `melt_payment_event` tables
+///
+/// The hashes and addresses are stored as formatted by the liquidity source that observed the transfer.
+#[derive(Debug, Clone)]
+pub struct PaymentEvent {
+    pub block_id: String,
+    pub tx_hash: String,
+    pub event_idx: u64,
+    pub asset: String,
+    pub payee: String,
+    pub invoice_id: [u8; 32],
+    pub payer: String,
+    /// The 128 least significant bits of the amount, as a decimal string
+    pub amount_low: String,
+    /// The 128 most significant bits of the amount, as a decimal string
+    pub amount_high: String,
+}
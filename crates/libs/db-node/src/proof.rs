@@ -1,4 +1,5 @@
 use nuts::{
+    Amount,
     nut00::{Proof, secret::Secret},
     nut01::PublicKey,
     nut02::KeysetId,
@@ -6,6 +7,9 @@ use nuts::{
 };
 
 use sqlx::{PgConnection, Postgres, QueryBuilder, Row};
+
+use crate::Error;
+
 /// Return true if one of the provided secret
 /// is already in db with state = SPENT
 pub async fn is_any_already_spent(
@@ -176,6 +180,32 @@ impl Default for InsertSpentProofsQueryBuilder<'_> {
     }
 }
 
+/// Returns the Y and amount of every spent proof of this keyset
+///
+/// They are ordered by Y.
+pub async fn get_keyset_spent_proofs(
+    conn: &mut PgConnection,
+    keyset_id: KeysetId,
+) -> Result<Vec<(PublicKey, Amount)>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT y, amount FROM proof WHERE keyset_id = $1 AND state = $2 ORDER BY y"#,
+        keyset_id.as_i64(),
+        ProofState::Spent as i16
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok((
+                PublicKey::from_slice(&r.y).map_err(|_| Error::DbToRuntimeConversion)?,
+                Amount::from_i64_repr(r.amount),
+            ))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod query_builder {
     use num_traits::One;
@@ -0,0 +1,89 @@
+//! Storage of the NUT-19 cached responses
+//!
+//! Responses are stored as opaque bytes, the caller being responsible for their encoding.
+use nuts::nut19::CacheResponseKey;
+use sqlx::{PgConnection, types::time::OffsetDateTime};
+
+use crate::Error;
+
+fn request_hash_to_db(request_hash: u64) -> i64 {
+    i64::from_be_bytes(request_hash.to_be_bytes())
+}
+
+fn timestamp_to_db(timestamp: u64) -> Result<OffsetDateTime, Error> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok())
+        .ok_or(Error::RuntimeToDbConversion)
+}
+
+/// Store a response, replacing the one already cached for this key if any
+///
+/// The response won't be returned anymore after `expiry` (a unix timestamp), if provided.
+pub async fn insert(
+    conn: &mut PgConnection,
+    key: &CacheResponseKey,
+    response: &[u8],
+    expiry: Option<u64>,
+) -> Result<(), Error> {
+    let expiry = expiry.map(timestamp_to_db).transpose()?;
+
+    sqlx::query!(
+        r#"INSERT INTO response_cache (route, request_hash, response, expiry)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (route, request_hash) DO UPDATE
+            SET response = excluded.response, expiry = excluded.expiry"#,
+        key.0.to_string(),
+        request_hash_to_db(key.1),
+        response,
+        expiry,
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the cached response for this key, unless it has expired at `now`
+pub async fn get(
+    conn: &mut PgConnection,
+    key: &CacheResponseKey,
+    now: u64,
+) -> Result<Option<Vec<u8>>, Error> {
+    let record = sqlx::query!(
+        r#"SELECT response FROM response_cache
+        WHERE route = $1 AND request_hash = $2 AND (expiry IS NULL OR expiry > $3)"#,
+        key.0.to_string(),
+        request_hash_to_db(key.1),
+        timestamp_to_db(now)?,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(record.map(|r| r.response))
+}
+
+/// Returns true if there was a response cached for this key
+pub async fn remove(conn: &mut PgConnection, key: &CacheResponseKey) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM response_cache WHERE route = $1 AND request_hash = $2",
+        key.0.to_string(),
+        request_hash_to_db(key.1),
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+/// Delete the responses expired at `now`, returns how many were removed
+pub async fn delete_expired(conn: &mut PgConnection, now: u64) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        "DELETE FROM response_cache WHERE expiry <= $1",
+        timestamp_to_db(now)?
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
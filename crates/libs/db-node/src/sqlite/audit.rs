@@ -0,0 +1,103 @@
+use sqlx::SqliteConnection;
+
+use super::begin_write;
+use crate::{
+    Error,
+    audit::{AuditEvent, GENESIS_HASH, event_hash, hash_from_db, u64_from_db, u64_to_db},
+};
+
+pub async fn insert(conn: &mut SqliteConnection, kind: &str, payload: &str) -> Result<(), Error> {
+    sqlx::query("INSERT INTO audit_event (kind, payload) VALUES (?, ?)")
+        .bind(kind)
+        .bind(payload)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Write transactions being serialized by the database write lock, no lock is needed
+pub async fn seal(conn: &mut SqliteConnection) -> Result<u64, Error> {
+    let mut tx = begin_write(conn).await?;
+
+    let head: Option<(i64, Vec<u8>)> = sqlx::query_as(
+        "SELECT seq, hash FROM audit_event WHERE seq IS NOT NULL ORDER BY seq DESC LIMIT 1",
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let (mut next_seq, mut prev_hash) = match head {
+        Some((seq, hash)) => (u64_from_db(seq)? + 1, hash_from_db(hash)?),
+        None => (0, GENESIS_HASH),
+    };
+
+    let unsealed: Vec<(i64, i64, String, String)> = sqlx::query_as(
+        "SELECT id, created_at, kind, payload FROM audit_event WHERE seq IS NULL ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let n_sealed = unsealed.len() as u64;
+    for (id, created_at, kind, payload) in unsealed {
+        let hash = event_hash(
+            &prev_hash,
+            next_seq,
+            u64_from_db(id)?,
+            u64_from_db(created_at)?,
+            &kind,
+            &payload,
+        );
+        sqlx::query("UPDATE audit_event SET seq = ?, prev_hash = ?, hash = ? WHERE id = ?")
+            .bind(u64_to_db(next_seq)?)
+            .bind(prev_hash.as_slice())
+            .bind(hash.as_slice())
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        next_seq += 1;
+        prev_hash = hash;
+    }
+
+    tx.commit().await?;
+
+    Ok(n_sealed)
+}
+
+pub async fn get_sealed(
+    conn: &mut SqliteConnection,
+    from_seq: u64,
+    limit: u64,
+) -> Result<Vec<AuditEvent>, Error> {
+    #[allow(clippy::type_complexity)]
+    let records: Vec<(i64, i64, String, String, i64, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+        r#"SELECT id, created_at, kind, payload, seq, prev_hash, hash
+        FROM audit_event WHERE seq >= ? ORDER BY seq LIMIT ?"#,
+    )
+    .bind(u64_to_db(from_seq)?)
+    .bind(u64_to_db(limit)?)
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|(id, created_at, kind, payload, seq, prev_hash, hash)| {
+            Ok(AuditEvent {
+                seq: u64_from_db(seq)?,
+                id: u64_from_db(id)?,
+                created_at: u64_from_db(created_at)?,
+                kind,
+                payload,
+                prev_hash: hash_from_db(prev_hash)?,
+                hash: hash_from_db(hash)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn count_unsealed(conn: &mut SqliteConnection) -> Result<u64, Error> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_event WHERE seq IS NULL")
+        .fetch_one(conn)
+        .await?;
+
+    u64_from_db(count)
+}
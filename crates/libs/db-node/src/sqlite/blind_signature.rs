@@ -0,0 +1,126 @@
+use nuts::{
+    Amount,
+    nut00::{BlindSignature, BlindedMessage},
+    nut01::PublicKey,
+    nut02::KeysetId,
+};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::{Error, blind_signature::RestoreFromDbResponse};
+
+/// Will return true if this secret has already been signed by us
+pub async fn is_any_blind_message_already_used(
+    conn: &mut SqliteConnection,
+    blinded_secrets: &[PublicKey],
+) -> Result<bool, Error> {
+    if blinded_secrets.is_empty() {
+        return Ok(false);
+    }
+
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("SELECT EXISTS (SELECT * FROM blind_signature WHERE y IN (");
+    let mut separated = builder.separated(", ");
+    for y in blinded_secrets {
+        separated.push_bind(y.to_bytes().to_vec());
+    }
+    builder.push("))");
+
+    let exists: bool = builder.build_query_scalar().fetch_one(conn).await?;
+
+    Ok(exists)
+}
+
+pub async fn insert_blind_signatures(
+    conn: &mut SqliteConnection,
+    blinded_messages: &[BlindedMessage],
+    blind_signatures: &[BlindSignature],
+) -> Result<(), Error> {
+    if blinded_messages.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("INSERT INTO blind_signature (y, amount, keyset_id, c) ");
+    builder.push_values(
+        blinded_messages.iter().zip(blind_signatures),
+        |mut row, (blinded_message, blind_signature)| {
+            row.push_bind(blinded_message.blinded_secret.to_bytes().to_vec())
+                .push_bind(blind_signature.amount.into_i64_repr())
+                .push_bind(blind_signature.keyset_id.as_i64())
+                .push_bind(blind_signature.c.to_bytes().to_vec());
+        },
+    );
+    builder.build().execute(conn).await?;
+
+    Ok(())
+}
+
+pub async fn sum_amount_of_unit_in_circulation(
+    conn: &mut SqliteConnection,
+    unit: &str,
+) -> Result<Amount, Error> {
+    let sum: i64 = sqlx::query_scalar(
+        r#"SELECT COALESCE(SUM(amount), 0) FROM blind_signature
+        INNER JOIN keyset ON blind_signature.keyset_id = keyset.id
+        WHERE keyset.unit = ?"#,
+    )
+    .bind(unit)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(Amount::from_i64_repr(sum))
+}
+
+/// Returns the signatures of the known blinded secrets, in the same order as `blinded_secrets`
+pub async fn get_by_blind_secrets(
+    conn: &mut SqliteConnection,
+    blinded_secrets: &[PublicKey],
+) -> Result<Vec<RestoreFromDbResponse>, Error> {
+    let mut ret = Vec::with_capacity(blinded_secrets.len());
+    for blinded_secret in blinded_secrets {
+        let record: Option<(i64, i64, Vec<u8>)> =
+            sqlx::query_as("SELECT amount, keyset_id, c FROM blind_signature WHERE y = ?")
+                .bind(blinded_secret.to_bytes().to_vec())
+                .fetch_optional(&mut *conn)
+                .await?;
+
+        if let Some((amount, keyset_id, c)) = record {
+            ret.push(RestoreFromDbResponse {
+                amount: Amount::from_i64_repr(amount),
+                keyset_id: keyset_id
+                    .try_into()
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                blinded_secret: *blinded_secret,
+                blind_signature: PublicKey::from_slice(&c)
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+            });
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Returns the blinded secret, amount and signature of every signature issued with this keyset
+///
+/// They are ordered by blinded secret.
+pub async fn get_keyset_blind_signatures(
+    conn: &mut SqliteConnection,
+    keyset_id: KeysetId,
+) -> Result<Vec<(PublicKey, Amount, PublicKey)>, Error> {
+    let records: Vec<(Vec<u8>, i64, Vec<u8>)> =
+        sqlx::query_as("SELECT y, amount, c FROM blind_signature WHERE keyset_id = ? ORDER BY y")
+            .bind(keyset_id.as_i64())
+            .fetch_all(conn)
+            .await?;
+
+    records
+        .into_iter()
+        .map(|(y, amount, c)| {
+            Ok((
+                PublicKey::from_slice(&y).map_err(|_| Error::DbToRuntimeConversion)?,
+                Amount::from_i64_repr(amount),
+                PublicKey::from_slice(&c).map_err(|_| Error::DbToRuntimeConversion)?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,25 @@
+use sqlx::SqliteConnection;
+
+use crate::{Error, exchange::Exchange};
+
+pub async fn insert(conn: &mut SqliteConnection, exchange: &Exchange) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO exchange
+            (id, input_unit, input_amount, output_unit, output_amount, rate, fee, surplus)
+        VALUES
+            (?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(exchange.id)
+    .bind(&exchange.input_unit)
+    .bind(exchange.input_amount.into_i64_repr())
+    .bind(&exchange.output_unit)
+    .bind(exchange.output_amount.into_i64_repr())
+    .bind(&exchange.rate)
+    .bind(exchange.fee.into_i64_repr())
+    .bind(exchange.surplus.into_i64_repr())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use nuts::{Amount, nut07::ProofState};
+use sqlx::SqliteConnection;
+
+use crate::{Error, gauge::GaugeMetrics};
+
+pub async fn get_all_gauge_metrics_by_units(
+    conn: &mut SqliteConnection,
+    units: &[String],
+) -> Result<Vec<(String, GaugeMetrics)>, Error> {
+    let mut results = Vec::with_capacity(units.len());
+    for unit in units {
+        if results.iter().any(|(u, _)| u == unit) {
+            continue;
+        }
+
+        let record: (i64, i64, i64, i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COALESCE(SUM(amount), 0) FROM mint_quote WHERE unit = ?1 AND state = 'UNPAID'),
+                (SELECT COALESCE(SUM(amount), 0) FROM mint_quote WHERE unit = ?1 AND state = 'PAID'),
+                (SELECT COALESCE(SUM(amount), 0) FROM mint_quote WHERE unit = ?1 AND state = 'ISSUED'),
+                (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = ?1 AND state = 'UNPAID'),
+                (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = ?1 AND state = 'PENDING'),
+                (SELECT COALESCE(SUM(amount), 0) FROM melt_quote WHERE unit = ?1 AND state = 'PAID'),
+                MAX(
+                    (SELECT COALESCE(SUM(bs.amount), 0) FROM blind_signature bs INNER JOIN keyset k ON bs.keyset_id = k.id WHERE k.unit = ?1)
+                    - (SELECT COALESCE(SUM(p.amount), 0) FROM proof p INNER JOIN keyset k ON p.keyset_id = k.id WHERE k.unit = ?1 AND p.state = ?2),
+                    0
+                )
+            "#,
+        )
+        .bind(unit)
+        .bind(ProofState::Spent as i16)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        results.push((
+            unit.clone(),
+            GaugeMetrics {
+                pending_deposits: Amount::from_i64_repr(record.0),
+                paid_deposits: Amount::from_i64_repr(record.1),
+                issued_deposits: Amount::from_i64_repr(record.2),
+                unpaid_withdrawals: Amount::from_i64_repr(record.3),
+                pending_withdrawals: Amount::from_i64_repr(record.4),
+                paid_withdrawals: Amount::from_i64_repr(record.5),
+                outstanding_ecash: Amount::from_i64_repr(record.6),
+            },
+        ));
+    }
+
+    Ok(results)
+}
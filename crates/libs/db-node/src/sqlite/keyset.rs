@@ -0,0 +1,145 @@
+use nuts::nut02::KeysetId;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use super::{timestamp_from_db, timestamp_to_db};
+use crate::{Error, keyset::KeysetInfo};
+
+type KeysetRecord = (i64, String, bool, i64, i64, Option<i64>);
+
+fn keyset_info_from_record(record: KeysetRecord) -> Result<(KeysetId, KeysetInfo<String>), Error> {
+    let (id, unit, active, max_order, derivation_path_index, final_expiry) = record;
+
+    Ok((
+        KeysetId::try_from(id).map_err(|_| Error::DbToRuntimeConversion)?,
+        KeysetInfo {
+            unit,
+            active,
+            max_order: u8::try_from(max_order).map_err(|_| Error::DbToRuntimeConversion)?,
+            derivation_path_index: u32::try_from(derivation_path_index)
+                .map_err(|_| Error::DbToRuntimeConversion)?,
+            final_expiry: final_expiry.map(timestamp_from_db).transpose()?,
+        },
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+pub async fn get_keysets(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<([u8; 8], String, bool, Option<u64>)>, Error> {
+    let records: Vec<(i64, String, bool, Option<i64>)> =
+        sqlx::query_as("SELECT id, unit, active, final_expiry FROM keyset")
+            .fetch_all(conn)
+            .await?;
+
+    records
+        .into_iter()
+        .map(|(id, unit, active, final_expiry)| {
+            Ok((
+                id.to_be_bytes(),
+                unit,
+                active,
+                final_expiry.map(timestamp_from_db).transpose()?,
+            ))
+        })
+        .collect()
+}
+
+pub async fn get_keyset(
+    conn: &mut SqliteConnection,
+    keyset_id: &KeysetId,
+) -> Result<KeysetInfo<String>, Error> {
+    let record: KeysetRecord = sqlx::query_as(
+        r#"SELECT id, unit, active, max_order, derivation_path_index, final_expiry
+        FROM keyset
+        WHERE id = ?"#,
+    )
+    .bind(keyset_id.as_i64())
+    .fetch_one(conn)
+    .await?;
+
+    Ok(keyset_info_from_record(record)?.1)
+}
+
+pub async fn get_active_keysets(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<(KeysetId, KeysetInfo<String>)>, Error> {
+    let records: Vec<KeysetRecord> = sqlx::query_as(
+        r#"SELECT id, unit, active, max_order, derivation_path_index, final_expiry
+        FROM keyset
+        WHERE active = TRUE"#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records.into_iter().map(keyset_info_from_record).collect()
+}
+
+pub async fn get_active_keysets_created_at(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<(KeysetId, u64)>, Error> {
+    let records: Vec<(i64, i64)> =
+        sqlx::query_as("SELECT id, created_at FROM keyset WHERE active = TRUE")
+            .fetch_all(conn)
+            .await?;
+
+    records
+        .into_iter()
+        .map(|(id, created_at)| {
+            Ok((
+                KeysetId::try_from(id).map_err(|_| Error::DbToRuntimeConversion)?,
+                timestamp_from_db(created_at)?,
+            ))
+        })
+        .collect()
+}
+
+pub async fn insert_keysets(
+    conn: &mut SqliteConnection,
+    keysets: &[(KeysetId, String, u32, u32)],
+) -> Result<(), Error> {
+    if keysets.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO keyset (id, unit, active, max_order, derivation_path_index, created_at) ",
+    );
+    builder.push_values(keysets, |mut row, (id, unit, max_order, index)| {
+        row.push_bind(id.as_i64())
+            .push_bind(unit)
+            .push("TRUE")
+            .push_bind(i64::from(*max_order))
+            .push_bind(i64::from(*index))
+            .push("unixepoch()");
+    });
+    builder.push(" ON CONFLICT DO NOTHING");
+    builder.build().execute(conn).await?;
+
+    Ok(())
+}
+
+pub async fn deactivate_keysets(
+    conn: &mut SqliteConnection,
+    keyset_ids: &[KeysetId],
+    final_expiry: Option<u64>,
+) -> Result<(), Error> {
+    if keyset_ids.is_empty() {
+        return Ok(());
+    }
+
+    let final_expiry = final_expiry.map(timestamp_to_db).transpose()?;
+
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("UPDATE keyset SET active = FALSE, final_expiry = COALESCE(");
+    builder
+        .push_bind(final_expiry)
+        .push(", final_expiry) WHERE id IN (");
+    let mut separated = builder.separated(", ");
+    for keyset_id in keyset_ids {
+        separated.push_bind(keyset_id.as_i64());
+    }
+    builder.push(")");
+    builder.build().execute(conn).await?;
+
+    Ok(())
+}
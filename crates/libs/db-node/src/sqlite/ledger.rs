@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use nuts::{
+    Amount, nut02::KeysetId, nut04::MintQuoteState, nut05::MeltQuoteState, nut07::ProofState,
+};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    ledger::{KeysetAmountLedger, UnitExchangeLedger, UnitQuoteLedger, count_from_db},
+};
+
+pub async fn get_keyset_amount_ledger(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<KeysetAmountLedger>, Error> {
+    let records: Vec<(i64, String, i64, i64, i64)> = sqlx::query_as(
+        r#"SELECT keyset.id, keyset.unit, t.amount, SUM(t.issued), SUM(t.spent)
+        FROM (
+            SELECT keyset_id, amount, COUNT(*) AS issued, 0 AS spent
+            FROM blind_signature GROUP BY keyset_id, amount
+            UNION ALL
+            SELECT keyset_id, amount, 0, COUNT(*)
+            FROM proof WHERE state = ? GROUP BY keyset_id, amount
+        ) AS t
+        INNER JOIN keyset ON keyset.id = t.keyset_id
+        GROUP BY keyset.id, keyset.unit, t.amount
+        ORDER BY keyset.id, t.amount"#,
+    )
+    .bind(ProofState::Spent as i16)
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|(keyset_id, unit, amount, issued, spent)| {
+            Ok(KeysetAmountLedger {
+                keyset_id: KeysetId::try_from(keyset_id)
+                    .map_err(|_| Error::DbToRuntimeConversion)?,
+                unit,
+                amount: Amount::from_i64_repr(amount),
+                issued: count_from_db(issued)?,
+                spent: count_from_db(spent)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_unit_quote_ledger(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<UnitQuoteLedger>, Error> {
+    let records: Vec<(String, i64, i64, i64)> = sqlx::query_as(
+        r#"SELECT unit, SUM(minted), SUM(melted), SUM(fee)
+        FROM (
+            SELECT unit, amount AS minted, 0 AS melted, 0 AS fee
+            FROM mint_quote WHERE state = 'ISSUED'
+            UNION ALL
+            SELECT unit, amount, 0, 0 FROM mint_quote_history WHERE state = 'ISSUED'
+            UNION ALL
+            SELECT unit, 0, amount, fee FROM melt_quote WHERE state IN ('PENDING', 'PAID')
+            UNION ALL
+            SELECT unit, 0, amount, fee FROM melt_quote_history WHERE state IN ('PENDING', 'PAID')
+        ) AS t
+        GROUP BY unit
+        ORDER BY unit"#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|(unit, minted, melted, melt_fees)| UnitQuoteLedger {
+            unit,
+            minted: Amount::from_i64_repr(minted),
+            melted: Amount::from_i64_repr(melted),
+            melt_fees: Amount::from_i64_repr(melt_fees),
+        })
+        .collect())
+}
+
+pub async fn get_unit_exchange_ledger(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<UnitExchangeLedger>, Error> {
+    let records: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"SELECT unit, SUM(exchanged_in), SUM(exchanged_out)
+        FROM (
+            SELECT output_unit AS unit, output_amount AS exchanged_in, 0 AS exchanged_out
+            FROM exchange
+            UNION ALL
+            SELECT input_unit, 0, input_amount FROM exchange
+        ) AS t
+        GROUP BY unit
+        ORDER BY unit"#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|(unit, exchanged_in, exchanged_out)| UnitExchangeLedger {
+            unit,
+            exchanged_in: Amount::from_i64_repr(exchanged_in),
+            exchanged_out: Amount::from_i64_repr(exchanged_out),
+        })
+        .collect())
+}
+
+pub async fn get_mint_quotes_without_payment_event(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<(Uuid, MintQuoteState)>, Error> {
+    let records: Vec<(Uuid, String)> = sqlx::query_as(
+        r#"SELECT id, state FROM mint_quote
+        WHERE state IN ('PAID', 'ISSUED') AND NOT EXISTS (
+            SELECT 1 FROM mint_payment_event WHERE mint_payment_event.invoice_id = mint_quote.invoice_id
+        )
+        UNION ALL
+        SELECT id, state FROM mint_quote_history
+        WHERE state = 'ISSUED' AND NOT EXISTS (
+            SELECT 1 FROM mint_payment_event_history
+            WHERE mint_payment_event_history.invoice_id = mint_quote_history.invoice_id
+        )"#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|(id, state)| {
+            Ok((
+                id,
+                MintQuoteState::from_str(&state).map_err(|_| Error::DbToRuntimeConversion)?,
+            ))
+        })
+        .collect()
+}
+
+pub async fn get_melt_quotes_without_payment_event(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<(Uuid, MeltQuoteState)>, Error> {
+    let records: Vec<(Uuid, String)> = sqlx::query_as(
+        r#"SELECT id, state FROM melt_quote
+        WHERE state = 'PAID' AND NOT EXISTS (
+            SELECT 1 FROM melt_payment_event WHERE melt_payment_event.invoice_id = melt_quote.invoice_id
+        )
+        UNION ALL
+        SELECT id, state FROM melt_quote_history
+        WHERE state = 'PAID' AND NOT EXISTS (
+            SELECT 1 FROM melt_payment_event_history
+            WHERE melt_payment_event_history.invoice_id = melt_quote_history.invoice_id
+        )"#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|(id, state)| {
+            Ok((
+                id,
+                MeltQuoteState::from_str(&state).map_err(|_| Error::DbToRuntimeConversion)?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,477 @@
+use std::str::FromStr;
+
+use nuts::{Amount, nut01::PublicKey, nut05::MeltQuoteState};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use super::{begin_write, timestamp_from_db, timestamp_to_db, unix_now};
+use crate::{
+    Error,
+    melt_quote::{MeltQuoteData, MeltQuoteInfo, MeltQuoteResponseRecord},
+};
+
+fn state_from_db(state: &str) -> Result<MeltQuoteState, Error> {
+    MeltQuoteState::from_str(state).map_err(|_| Error::DbToRuntimeConversion)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_new(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    invoice_id: &[u8; 32],
+    unit: &str,
+    amount: Amount,
+    fee: Amount,
+    request: &str,
+    expiry: u64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO melt_quote
+            (id, invoice_id, unit, amount, fee, request, expiry, state)
+        VALUES
+            (?, ?, ?, ?, ?, ?, ?, 'UNPAID')"#,
+    )
+    .bind(quote_id)
+    .bind(invoice_id.to_vec())
+    .bind(unit)
+    .bind(amount.into_i64_repr())
+    .bind(fee.into_i64_repr())
+    .bind(request)
+    .bind(timestamp_to_db(expiry)?)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn get_transfer_ids(
+    conn: &mut SqliteConnection,
+    invoice_id: &[u8],
+) -> Result<Vec<String>, Error> {
+    let tx_hashes =
+        sqlx::query_scalar("SELECT tx_hash FROM melt_payment_event WHERE invoice_id = ?")
+            .bind(invoice_id)
+            .fetch_all(conn)
+            .await?;
+
+    Ok(tx_hashes)
+}
+
+pub async fn build_response_from_db(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+) -> Result<MeltQuoteResponseRecord, Error> {
+    let (unit, amount, fee, state, expiry, invoice_id): (String, i64, i64, String, i64, Vec<u8>) =
+        sqlx::query_as(
+            "SELECT unit, amount, fee, state, expiry, invoice_id FROM melt_quote WHERE id = ?",
+        )
+        .bind(quote_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(MeltQuoteResponseRecord {
+        unit,
+        amount: Amount::from_i64_repr(amount),
+        fee: Amount::from_i64_repr(fee),
+        state: state_from_db(&state)?,
+        expiry: timestamp_from_db(expiry)?,
+        transfer_ids: Some(get_transfer_ids(conn, &invoice_id).await?),
+    })
+}
+
+pub async fn get_data(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<MeltQuoteData, Error> {
+    let (unit, amount, fee, state, invoice_id, expiry, request): (
+        String,
+        i64,
+        i64,
+        String,
+        Vec<u8>,
+        i64,
+        String,
+    ) = sqlx::query_as(
+        "SELECT unit, amount, fee, state, invoice_id, expiry, request FROM melt_quote WHERE id = ?",
+    )
+    .bind(quote_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(MeltQuoteData {
+        unit,
+        amount: Amount::from_i64_repr(amount),
+        fee: Amount::from_i64_repr(fee),
+        state: state_from_db(&state)?,
+        expiry: timestamp_from_db(expiry)?,
+        invoice_id: invoice_id
+            .try_into()
+            .map_err(|_| Error::DbToRuntimeConversion)?,
+        request,
+    })
+}
+
+pub async fn get_state(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+) -> Result<MeltQuoteState, Error> {
+    let state: String = sqlx::query_scalar("SELECT state FROM melt_quote WHERE id = ?")
+        .bind(quote_id)
+        .fetch_one(conn)
+        .await?;
+
+    state_from_db(&state)
+}
+
+pub async fn set_state(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    state: MeltQuoteState,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE melt_quote SET state = ? WHERE id = ?")
+        .bind(state.to_string())
+        .bind(quote_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Lower the amount and the fee of a quote by the part of the fee reserve returned as change
+pub async fn return_change(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    change: Amount,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE melt_quote SET amount = amount - ?, fee = fee - ? WHERE id = ?")
+        .bind(change.into_i64_repr())
+        .bind(change.into_i64_repr())
+        .bind(quote_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Link the proofs spent by the melt to the quote, to be called in the transaction spending them
+pub async fn insert_inputs(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    ys: &[PublicKey],
+) -> Result<(), Error> {
+    if ys.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::<Sqlite>::new("INSERT INTO melt_quote_input (y, quote_id) ");
+    builder.push_values(ys, |mut row, y| {
+        row.push_bind(y.to_bytes().to_vec()).push_bind(quote_id);
+    });
+    builder.build().execute(conn).await?;
+
+    Ok(())
+}
+
+/// Set a pending quote back to unpaid, unspending the proofs spent by its melt
+///
+/// Returns false if there is no pending quote with this id.
+pub async fn revert(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<bool, Error> {
+    let mut tx = begin_write(conn).await?;
+
+    let result =
+        sqlx::query("UPDATE melt_quote SET state = 'UNPAID' WHERE id = ? AND state = 'PENDING'")
+            .bind(quote_id)
+            .execute(&mut *tx)
+            .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+
+    // The links are removed first, as they reference the proofs
+    let ys: Vec<Vec<u8>> =
+        sqlx::query_scalar("DELETE FROM melt_quote_input WHERE quote_id = ? RETURNING y")
+            .bind(quote_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    if !ys.is_empty() {
+        let mut builder = QueryBuilder::<Sqlite>::new("DELETE FROM proof WHERE y IN (");
+        let mut separated = builder.separated(", ");
+        for y in ys {
+            separated.push_bind(y);
+        }
+        builder.push(")");
+        builder.build().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn get_quote_infos_by_invoice_id(
+    conn: &mut SqliteConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    let record: Option<(Uuid, i64, String)> =
+        sqlx::query_as("SELECT id, amount, unit FROM melt_quote WHERE invoice_id = ? LIMIT 1")
+            .bind(invoice_id.to_vec())
+            .fetch_optional(conn)
+            .await?;
+
+    Ok(record.map(|(id, amount, unit)| (id, Amount::from_i64_repr(amount), unit)))
+}
+
+/// Returns the id, amount and unit of the quote
+///
+/// An archived expired quote is first moved back from the history tables, along with its payment events,
+/// so that a payment received after the archival is still credited to it.
+pub async fn get_or_restore_quote_infos_by_invoice_id(
+    conn: &mut SqliteConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    if let Some(infos) = get_quote_infos_by_invoice_id(conn, invoice_id).await? {
+        return Ok(Some(infos));
+    }
+
+    let mut tx = begin_write(conn).await?;
+    for statement in [
+        r#"INSERT INTO melt_quote (id, invoice_id, unit, amount, fee, request, expiry, state)
+        SELECT id, invoice_id, unit, amount, fee, request, expiry, state FROM melt_quote_history WHERE invoice_id = ?1 AND state = 'EXPIRED'"#,
+        "DELETE FROM melt_quote_history WHERE invoice_id = ?1 AND state = 'EXPIRED'",
+        r#"INSERT INTO melt_payment_event (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+        SELECT block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        FROM melt_payment_event_history
+        WHERE invoice_id = ?1 AND EXISTS (SELECT 1 FROM melt_quote WHERE invoice_id = ?1)"#,
+        r#"DELETE FROM melt_payment_event_history
+        WHERE invoice_id = ?1 AND EXISTS (SELECT 1 FROM melt_quote WHERE invoice_id = ?1)"#,
+    ] {
+        sqlx::query(statement)
+            .bind(invoice_id.to_vec())
+            .execute(&mut *tx)
+            .await?;
+    }
+    let infos = get_quote_infos_by_invoice_id(&mut tx, invoice_id).await?;
+    tx.commit().await?;
+
+    Ok(infos)
+}
+
+pub async fn get_state_and_transfer_ids(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+) -> Result<(MeltQuoteState, Option<Vec<String>>), Error> {
+    let (state, invoice_id): (String, Vec<u8>) =
+        sqlx::query_as("SELECT state, invoice_id FROM melt_quote WHERE id = ?")
+            .bind(quote_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    Ok((
+        state_from_db(&state)?,
+        Some(get_transfer_ids(conn, &invoice_id).await?),
+    ))
+}
+
+type MeltQuoteRecord = (Uuid, String, i64, i64, String, i64, String);
+
+fn info_from_record(record: MeltQuoteRecord) -> Result<MeltQuoteInfo, Error> {
+    let (id, unit, amount, fee, state, expiry, request) = record;
+
+    Ok(MeltQuoteInfo {
+        id,
+        unit,
+        amount: Amount::from_i64_repr(amount),
+        fee: Amount::from_i64_repr(fee),
+        state: state_from_db(&state)?,
+        expiry: timestamp_from_db(expiry)?,
+        request,
+    })
+}
+
+/// List the most recent quotes, optionally filtered by state
+pub async fn list(
+    conn: &mut SqliteConnection,
+    state: Option<MeltQuoteState>,
+    limit: i64,
+) -> Result<Vec<MeltQuoteInfo>, Error> {
+    let records: Vec<MeltQuoteRecord> = sqlx::query_as(
+        r#"SELECT id, unit, amount, fee, state, expiry, request
+        FROM melt_quote
+        WHERE ?1 IS NULL OR state = ?1
+        ORDER BY expiry DESC
+        LIMIT ?2"#,
+    )
+    .bind(state.map(|s| s.to_string()))
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+
+    records.into_iter().map(info_from_record).collect()
+}
+
+pub async fn get_info(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<MeltQuoteInfo, Error> {
+    let record: MeltQuoteRecord = sqlx::query_as(
+        "SELECT id, unit, amount, fee, state, expiry, request FROM melt_quote WHERE id = ?",
+    )
+    .bind(quote_id)
+    .fetch_one(conn)
+    .await?;
+
+    info_from_record(record)
+}
+
+/// Set the expiry of an unpaid quote in the past and mark it as expired
+///
+/// Returns false if there is no unpaid quote with this id.
+pub async fn force_expire(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"UPDATE melt_quote
+        SET expiry = MIN(expiry, ? - 1), state = 'EXPIRED'
+        WHERE id = ? AND state = 'UNPAID'"#,
+    )
+    .bind(unix_now()?)
+    .bind(quote_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Mark the unpaid quotes whose expiry is elapsed as expired
+///
+/// Returns the number of quotes updated.
+pub async fn expire_unpaid(conn: &mut SqliteConnection) -> Result<u64, Error> {
+    let result = sqlx::query(
+        "UPDATE melt_quote SET state = 'EXPIRED' WHERE state = 'UNPAID' AND expiry <= ?",
+    )
+    .bind(unix_now()?)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Move the terminal quotes expired for more than `older_than` seconds,
+/// and their payment events, to the history tables
+///
+/// An expired quote that received a partial payment is kept, its funds still being owed.
+/// Returns the number of quotes archived.
+pub async fn archive(conn: &mut SqliteConnection, older_than: u64) -> Result<u64, Error> {
+    let older_than = i64::try_from(older_than).map_err(|_| Error::RuntimeToDbConversion)?;
+    let cutoff = unix_now()? - older_than;
+    let archivable = format!(
+        r#"expiry < {cutoff} AND (
+            state = 'PAID'
+            OR (state = 'EXPIRED' AND NOT EXISTS (
+                SELECT 1 FROM melt_payment_event e WHERE e.invoice_id = melt_quote.invoice_id
+            ))
+        )"#
+    );
+
+    let mut tx = begin_write(conn).await?;
+
+    sqlx::query(&format!(
+        r#"INSERT INTO melt_payment_event_history (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+        SELECT block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        FROM melt_payment_event
+        WHERE invoice_id IN (SELECT invoice_id FROM melt_quote WHERE {archivable})"#
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        "DELETE FROM melt_payment_event WHERE invoice_id IN (SELECT invoice_id FROM melt_quote WHERE {archivable})"
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        r#"INSERT INTO melt_quote_history (id, invoice_id, unit, amount, fee, request, expiry, state)
+        SELECT id, invoice_id, unit, amount, fee, request, expiry, state
+        FROM melt_quote
+        WHERE {archivable}"#
+    ))
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query(&format!("DELETE FROM melt_quote WHERE {archivable}"))
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use nuts::{
+        Amount,
+        nut00::{Proof, secret::Secret},
+        nut01::PublicKey,
+        nut02::KeysetId,
+        nut05::MeltQuoteState,
+        nut07::ProofState,
+    };
+    use uuid::Uuid;
+
+    use crate::{Storage, sqlite::SqliteStorage};
+
+    #[tokio::test]
+    async fn revert_unspends_the_inputs_of_a_pending_quote() {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+        let mut conn = storage.acquire().await.unwrap();
+        let conn = conn.as_mut();
+
+        let keyset_id = KeysetId::try_from(0x1i64).unwrap();
+        conn.insert_keysets(&[(keyset_id, "sat".to_string(), 32, 0)])
+            .await
+            .unwrap();
+        let c = PublicKey::from_hex(
+            "02194603ffa36356f4a56b7df9371fc3192472351453ec7398b8da8117e7c3e104",
+        )
+        .unwrap();
+        let proofs: Vec<_> = (0..2)
+            .map(|_| Proof {
+                amount: Amount::ONE,
+                keyset_id,
+                secret: Secret::generate(),
+                c,
+            })
+            .collect();
+        let ys: Vec<_> = proofs.iter().map(|p| p.y().unwrap()).collect();
+
+        let quote_id = Uuid::new_v4();
+        conn.insert_melt_quote(
+            quote_id,
+            &[1; 32],
+            "sat",
+            Amount::from(2u64),
+            Amount::ZERO,
+            "req",
+            u64::MAX >> 1,
+        )
+        .await
+        .unwrap();
+        // Only pending quotes can be reverted
+        assert!(!conn.revert_melt_quote(quote_id).await.unwrap());
+
+        conn.insert_spent_proofs(&proofs).await.unwrap();
+        conn.insert_melt_quote_inputs(quote_id, &proofs)
+            .await
+            .unwrap();
+        conn.set_melt_quote_state(quote_id, MeltQuoteState::Pending)
+            .await
+            .unwrap();
+        assert_eq!(
+            conn.get_proofs_states(&ys).await.unwrap(),
+            vec![ProofState::Spent; 2]
+        );
+
+        assert!(conn.revert_melt_quote(quote_id).await.unwrap());
+        assert_eq!(
+            conn.get_melt_quote_state(quote_id).await.unwrap(),
+            MeltQuoteState::Unpaid
+        );
+        assert_eq!(
+            conn.get_proofs_states(&ys).await.unwrap(),
+            vec![ProofState::Unspent; 2]
+        );
+        assert!(!conn.revert_melt_quote(quote_id).await.unwrap());
+    }
+}
@@ -0,0 +1,112 @@
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::{Error, melt_withdrawal::MeltWithdrawal};
+
+pub async fn insert(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    method: &str,
+    payment: &str,
+) -> Result<(), Error> {
+    sqlx::query("INSERT INTO melt_withdrawal (quote_id, method, payment) VALUES (?, ?, ?)")
+        .bind(quote_id)
+        .bind(method)
+        .bind(payment)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// List the oldest withdrawals of `method` first
+pub async fn list(
+    conn: &mut SqliteConnection,
+    method: &str,
+    limit: i64,
+) -> Result<Vec<MeltWithdrawal>, Error> {
+    let records: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+        "SELECT quote_id, payment, tx_hash FROM melt_withdrawal
+        WHERE method = ?
+        ORDER BY created_at, quote_id
+        LIMIT ?",
+    )
+    .bind(method)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|(quote_id, payment, tx_hash)| MeltWithdrawal {
+            quote_id,
+            payment,
+            tx_hash,
+        })
+        .collect())
+}
+
+pub async fn set_tx_hash(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    tx_hash: Option<&str>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE melt_withdrawal SET tx_hash = ? WHERE quote_id = ?")
+        .bind(tx_hash)
+        .bind(quote_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<(), Error> {
+    sqlx::query("DELETE FROM melt_withdrawal WHERE quote_id = ?")
+        .bind(quote_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{Storage, sqlite::SqliteStorage};
+
+    #[tokio::test]
+    async fn withdrawals_are_listed_by_method() {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+        let mut conn = storage.acquire().await.unwrap();
+        let conn = conn.as_mut();
+
+        let quote_id = Uuid::new_v4();
+        conn.insert_melt_withdrawal(quote_id, "evm", "{}")
+            .await
+            .unwrap();
+        conn.insert_melt_withdrawal(Uuid::new_v4(), "starknet", "{}")
+            .await
+            .unwrap();
+
+        let withdrawals = conn.list_melt_withdrawals("evm", 10).await.unwrap();
+        assert_eq!(withdrawals.len(), 1);
+        assert_eq!(withdrawals[0].quote_id, quote_id);
+        assert_eq!(withdrawals[0].tx_hash, None);
+
+        conn.set_melt_withdrawal_tx_hash(quote_id, Some("0x01"))
+            .await
+            .unwrap();
+        let withdrawals = conn.list_melt_withdrawals("evm", 10).await.unwrap();
+        assert_eq!(withdrawals[0].tx_hash.as_deref(), Some("0x01"));
+
+        conn.delete_melt_withdrawal(quote_id).await.unwrap();
+        assert!(
+            conn.list_melt_withdrawals("evm", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
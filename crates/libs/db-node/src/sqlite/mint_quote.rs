@@ -0,0 +1,417 @@
+use std::str::FromStr;
+
+use nuts::{
+    Amount,
+    nut04::{MintQuoteResponse, MintQuoteState},
+};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use super::{begin_write, timestamp_from_db, timestamp_to_db, unix_now};
+use crate::{Error, mint_quote::MintQuoteInfo};
+
+fn state_from_db(state: &str) -> Result<MintQuoteState, Error> {
+    MintQuoteState::from_str(state).map_err(|_| Error::DbToRuntimeConversion)
+}
+
+pub async fn insert_new(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    invoice_id: [u8; 32],
+    unit: &str,
+    amount: Amount,
+    request: &str,
+    expiry: u64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO mint_quote (id, invoice_id, unit, amount, request, expiry, state) VALUES (?, ?, ?, ?, ?, ?, 'UNPAID')"#,
+    )
+    .bind(quote_id)
+    .bind(invoice_id.to_vec())
+    .bind(unit)
+    .bind(amount.into_i64_repr())
+    .bind(request)
+    .bind(timestamp_to_db(expiry)?)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn build_response_from_db(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+) -> Result<MintQuoteResponse<Uuid>, Error> {
+    let (request, state, expiry): (String, String, i64) =
+        sqlx::query_as("SELECT request, state, expiry FROM mint_quote WHERE id = ?")
+            .bind(quote_id)
+            .fetch_one(conn)
+            .await?;
+
+    Ok(MintQuoteResponse {
+        quote: quote_id,
+        request,
+        state: state_from_db(&state)?,
+        expiry: timestamp_from_db(expiry)?,
+    })
+}
+
+pub async fn get_amount_and_state(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+) -> Result<(Amount, MintQuoteState), Error> {
+    let (amount, state): (i64, String) =
+        sqlx::query_as("SELECT amount, state FROM mint_quote WHERE id = ?")
+            .bind(quote_id)
+            .fetch_one(conn)
+            .await?;
+
+    Ok((Amount::from_i64_repr(amount), state_from_db(&state)?))
+}
+
+pub async fn set_state(
+    conn: &mut SqliteConnection,
+    quote_id: Uuid,
+    state: MintQuoteState,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE mint_quote SET state = ? WHERE id = ?")
+        .bind(state.to_string())
+        .bind(quote_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_quote_infos_by_invoice_id(
+    conn: &mut SqliteConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    let record: Option<(Uuid, i64, String)> =
+        sqlx::query_as("SELECT id, amount, unit FROM mint_quote WHERE invoice_id = ? LIMIT 1")
+            .bind(invoice_id.to_vec())
+            .fetch_optional(conn)
+            .await?;
+
+    Ok(record.map(|(id, amount, unit)| (id, Amount::from_i64_repr(amount), unit)))
+}
+
+/// Returns the id, amount and unit of the quote
+///
+/// An archived expired quote is first moved back from the history tables, along with its payment events,
+/// so that a payment received after the archival is still credited to it.
+pub async fn get_or_restore_quote_infos_by_invoice_id(
+    conn: &mut SqliteConnection,
+    invoice_id: &[u8; 32],
+) -> Result<Option<(Uuid, Amount, String)>, Error> {
+    if let Some(infos) = get_quote_infos_by_invoice_id(conn, invoice_id).await? {
+        return Ok(Some(infos));
+    }
+
+    let mut tx = begin_write(conn).await?;
+    for statement in [
+        r#"INSERT INTO mint_quote (id, invoice_id, unit, amount, request, expiry, state)
+        SELECT id, invoice_id, unit, amount, request, expiry, state FROM mint_quote_history WHERE invoice_id = ?1 AND state = 'EXPIRED'"#,
+        "DELETE FROM mint_quote_history WHERE invoice_id = ?1 AND state = 'EXPIRED'",
+        r#"INSERT INTO mint_payment_event (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+        SELECT block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        FROM mint_payment_event_history
+        WHERE invoice_id = ?1 AND EXISTS (SELECT 1 FROM mint_quote WHERE invoice_id = ?1)"#,
+        r#"DELETE FROM mint_payment_event_history
+        WHERE invoice_id = ?1 AND EXISTS (SELECT 1 FROM mint_quote WHERE invoice_id = ?1)"#,
+    ] {
+        sqlx::query(statement)
+            .bind(invoice_id.to_vec())
+            .execute(&mut *tx)
+            .await?;
+    }
+    let infos = get_quote_infos_by_invoice_id(&mut tx, invoice_id).await?;
+    tx.commit().await?;
+
+    Ok(infos)
+}
+
+type MintQuoteRecord = (Uuid, String, i64, String, i64, String);
+
+fn info_from_record(record: MintQuoteRecord) -> Result<MintQuoteInfo, Error> {
+    let (id, unit, amount, state, expiry, request) = record;
+
+    Ok(MintQuoteInfo {
+        id,
+        unit,
+        amount: Amount::from_i64_repr(amount),
+        state: state_from_db(&state)?,
+        expiry: timestamp_from_db(expiry)?,
+        request,
+    })
+}
+
+/// List the most recent quotes, optionally filtered by state
+pub async fn list(
+    conn: &mut SqliteConnection,
+    state: Option<MintQuoteState>,
+    limit: i64,
+) -> Result<Vec<MintQuoteInfo>, Error> {
+    let records: Vec<MintQuoteRecord> = sqlx::query_as(
+        r#"SELECT id, unit, amount, state, expiry, request
+        FROM mint_quote
+        WHERE ?1 IS NULL OR state = ?1
+        ORDER BY expiry DESC
+        LIMIT ?2"#,
+    )
+    .bind(state.map(|s| s.to_string()))
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+
+    records.into_iter().map(info_from_record).collect()
+}
+
+pub async fn get_info(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<MintQuoteInfo, Error> {
+    let record: MintQuoteRecord = sqlx::query_as(
+        "SELECT id, unit, amount, state, expiry, request FROM mint_quote WHERE id = ?",
+    )
+    .bind(quote_id)
+    .fetch_one(conn)
+    .await?;
+
+    info_from_record(record)
+}
+
+/// Set the expiry of an unpaid quote in the past and mark it as expired
+///
+/// Returns false if there is no unpaid quote with this id.
+pub async fn force_expire(conn: &mut SqliteConnection, quote_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"UPDATE mint_quote
+        SET expiry = MIN(expiry, ? - 1), state = 'EXPIRED'
+        WHERE id = ? AND state = 'UNPAID'"#,
+    )
+    .bind(unix_now()?)
+    .bind(quote_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Mark the unpaid quotes whose expiry is elapsed as expired
+///
+/// Returns the number of quotes updated.
+pub async fn expire_unpaid(conn: &mut SqliteConnection) -> Result<u64, Error> {
+    let result = sqlx::query(
+        "UPDATE mint_quote SET state = 'EXPIRED' WHERE state = 'UNPAID' AND expiry <= ?",
+    )
+    .bind(unix_now()?)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Move the terminal quotes expired for more than `older_than` seconds,
+/// and their payment events, to the history tables
+///
+/// An expired quote that received a partial payment is kept, its funds still being owed.
+/// Returns the number of quotes archived.
+pub async fn archive(conn: &mut SqliteConnection, older_than: u64) -> Result<u64, Error> {
+    let older_than = i64::try_from(older_than).map_err(|_| Error::RuntimeToDbConversion)?;
+    let cutoff = unix_now()? - older_than;
+    let archivable = format!(
+        r#"expiry < {cutoff} AND (
+            state = 'ISSUED'
+            OR (state = 'EXPIRED' AND NOT EXISTS (
+                SELECT 1 FROM mint_payment_event e WHERE e.invoice_id = mint_quote.invoice_id
+            ))
+        )"#
+    );
+
+    let mut tx = begin_write(conn).await?;
+
+    sqlx::query(&format!(
+        r#"INSERT INTO mint_payment_event_history (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+        SELECT block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high
+        FROM mint_payment_event
+        WHERE invoice_id IN (SELECT invoice_id FROM mint_quote WHERE {archivable})"#
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        "DELETE FROM mint_payment_event WHERE invoice_id IN (SELECT invoice_id FROM mint_quote WHERE {archivable})"
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        r#"INSERT INTO mint_quote_history (id, invoice_id, unit, amount, request, expiry, state)
+        SELECT id, invoice_id, unit, amount, request, expiry, state
+        FROM mint_quote
+        WHERE {archivable}"#
+    ))
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query(&format!("DELETE FROM mint_quote WHERE {archivable}"))
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use nuts::{Amount, nut04::MintQuoteState};
+    use uuid::Uuid;
+
+    use crate::{PaymentEvent, Storage, StorageConn, sqlite::SqliteStorage};
+
+    const GRACE: u64 = 3600;
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn payment(invoice_id: [u8; 32]) -> PaymentEvent {
+        PaymentEvent {
+            block_id: "block".to_string(),
+            tx_hash: format!("{:x}", invoice_id[0]),
+            event_idx: 0,
+            asset: "strk".to_string(),
+            payee: "node".to_string(),
+            invoice_id,
+            payer: "payer".to_string(),
+            amount_low: "1".to_string(),
+            amount_high: "0".to_string(),
+        }
+    }
+
+    async fn insert_quote(
+        conn: &mut dyn StorageConn,
+        n: u8,
+        expiry: u64,
+        state: MintQuoteState,
+    ) -> (Uuid, [u8; 32]) {
+        let quote_id = Uuid::new_v4();
+        let invoice_id = [n; 32];
+        conn.insert_mint_quote(
+            quote_id,
+            invoice_id,
+            "millistrk",
+            Amount::ONE,
+            "req",
+            expiry,
+        )
+        .await
+        .unwrap();
+        conn.set_mint_quote_state(quote_id, state).await.unwrap();
+
+        (quote_id, invoice_id)
+    }
+
+    async fn is_live(conn: &mut dyn StorageConn, quote_id: Uuid) -> bool {
+        conn.get_mint_quote_info(quote_id).await.is_ok()
+    }
+
+    #[tokio::test]
+    async fn archive_only_settled_or_unpaid_quotes_past_grace() {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+        let mut conn = storage.acquire().await.unwrap();
+        let conn = conn.as_mut();
+        let long_ago = now() - 2 * GRACE;
+
+        let (issued, _) = insert_quote(conn, 1, long_ago, MintQuoteState::Issued).await;
+        let (expired, _) = insert_quote(conn, 2, long_ago, MintQuoteState::Expired).await;
+        let (partially_paid, invoice_id) =
+            insert_quote(conn, 3, long_ago, MintQuoteState::Expired).await;
+        conn.insert_mint_payment_event(&payment(invoice_id))
+            .await
+            .unwrap();
+        let (in_grace, _) = insert_quote(conn, 4, now() - 60, MintQuoteState::Expired).await;
+        let (paid, _) = insert_quote(conn, 5, long_ago, MintQuoteState::Paid).await;
+        let (unpaid, _) = insert_quote(conn, 6, now() + GRACE, MintQuoteState::Unpaid).await;
+
+        assert_eq!(conn.archive_mint_quotes(GRACE).await.unwrap(), 2);
+
+        assert!(!is_live(conn, issued).await);
+        assert!(!is_live(conn, expired).await);
+        for quote_id in [partially_paid, in_grace, paid, unpaid] {
+            assert!(is_live(conn, quote_id).await);
+        }
+        assert_eq!(
+            conn.get_mint_current_paid(&invoice_id).await.unwrap().len(),
+            1
+        );
+        assert_eq!(conn.archive_mint_quotes(GRACE).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn late_payment_restores_archived_quote() {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+        let mut conn = storage.acquire().await.unwrap();
+        let conn = conn.as_mut();
+
+        let (quote_id, invoice_id) =
+            insert_quote(conn, 1, now() - 2 * GRACE, MintQuoteState::Expired).await;
+        let (issued, issued_invoice_id) =
+            insert_quote(conn, 2, now() - 2 * GRACE, MintQuoteState::Issued).await;
+        assert_eq!(conn.archive_mint_quotes(GRACE).await.unwrap(), 2);
+        assert!(!is_live(conn, quote_id).await);
+
+        // A plain lookup leaves the archive untouched
+        assert_eq!(
+            conn.get_mint_quote_by_invoice_id(&invoice_id)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(!is_live(conn, quote_id).await);
+
+        // An issued quote is never brought back
+        assert_eq!(
+            conn.get_or_restore_mint_quote_by_invoice_id(&issued_invoice_id)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(!is_live(conn, issued).await);
+
+        // What the liquidity sources do when they observe a payment
+        let (found_id, amount, unit) = conn
+            .get_or_restore_mint_quote_by_invoice_id(&invoice_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (found_id, amount, unit.as_str()),
+            (quote_id, Amount::ONE, "millistrk")
+        );
+        conn.insert_mint_payment_event(&payment(invoice_id))
+            .await
+            .unwrap();
+        conn.set_mint_quote_state(quote_id, MintQuoteState::Paid)
+            .await
+            .unwrap();
+
+        assert_eq!(conn.archive_mint_quotes(GRACE).await.unwrap(), 0);
+        let info = conn.get_mint_quote_info(quote_id).await.unwrap();
+        assert_eq!(info.state, MintQuoteState::Paid);
+        assert_eq!(
+            conn.get_mint_current_paid(&invoice_id).await.unwrap().len(),
+            1
+        );
+
+        assert_eq!(
+            conn.get_or_restore_mint_quote_by_invoice_id(&[9; 32])
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,612 @@
+//! SQLite implementation of the storage traits
+//!
+//! Meant for single operator deployments and local tests.
+//! Timestamps are stored as unix timestamps and quote states as text.
+//!
+//! Transactions are opened with `BEGIN IMMEDIATE`, which takes the database write lock upfront.
+//! Writers are so serialized, which trivially provides the SERIALIZABLE guarantees,
+//! while the WAL journal lets the other connections of the pool keep reading.
+use std::{
+    ops::DerefMut,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use nuts::{
+    Amount,
+    nut00::{BlindSignature, BlindedMessage, Proof},
+    nut01::PublicKey,
+    nut02::KeysetId,
+    nut04::{MintQuoteResponse, MintQuoteState},
+    nut05::MeltQuoteState,
+    nut07::ProofState,
+    nut19::CacheResponseKey,
+};
+use sqlx::{
+    Connection, Sqlite, SqliteConnection, SqlitePool, Transaction,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
+use uuid::Uuid;
+
+use crate::{
+    Error, Leadership, PaymentEvent, Storage, StorageConn, StorageTx,
+    audit::AuditEvent,
+    blind_signature::RestoreFromDbResponse,
+    exchange::Exchange,
+    gauge::GaugeMetrics,
+    keyset::KeysetInfo,
+    ledger::{KeysetAmountLedger, UnitExchangeLedger, UnitQuoteLedger},
+    melt_quote::{MeltQuoteData, MeltQuoteInfo, MeltQuoteResponseRecord},
+    melt_withdrawal::MeltWithdrawal,
+    mint_quote::MintQuoteInfo,
+};
+
+pub mod audit;
+pub mod blind_signature;
+pub mod exchange;
+pub mod gauge;
+pub mod keyset;
+pub mod ledger;
+pub mod melt_quote;
+pub mod melt_withdrawal;
+pub mod mint_quote;
+pub mod payment_event;
+pub mod proof;
+pub mod response_cache;
+
+use payment_event::PaymentEventTable;
+
+fn timestamp_to_db(timestamp: u64) -> Result<i64, Error> {
+    i64::try_from(timestamp).map_err(|_| Error::RuntimeToDbConversion)
+}
+
+fn timestamp_from_db(timestamp: i64) -> Result<u64, Error> {
+    u64::try_from(timestamp).map_err(|_| Error::DbToRuntimeConversion)
+}
+
+/// Start a transaction on `conn` that takes the write lock upfront
+///
+/// Unless `conn` is in a transaction already, which then holds it, the new one being a savepoint.
+async fn begin_write(conn: &mut SqliteConnection) -> Result<Transaction<'_, Sqlite>, sqlx::Error> {
+    if conn.is_in_transaction() {
+        conn.begin().await
+    } else {
+        conn.begin_with("BEGIN IMMEDIATE").await
+    }
+}
+
+fn unix_now() -> Result<i64, Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::RuntimeToDbConversion)?
+        .as_secs();
+
+    timestamp_to_db(now)
+}
+
+/// Size of the pool of a file database
+pub const MAX_CONNECTIONS: u32 = 8;
+/// How long a writer waits for the write lock before failing with `SQLITE_BUSY`
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Open the database at `url` (eg. `sqlite://node.db` or `sqlite::memory:`), creating it if needed
+    ///
+    /// File databases are opened in WAL mode with a pool of [`MAX_CONNECTIONS`].
+    /// An in-memory database only lives as long as its connection,
+    /// so it gets a single one that is never closed.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let in_memory = url.contains(":memory:") || url.contains("mode=memory");
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .busy_timeout(BUSY_TIMEOUT);
+        let (options, max_connections) = if in_memory {
+            (options, 1)
+        } else {
+            (
+                options.journal_mode(SqliteJournalMode::Wal),
+                MAX_CONNECTIONS,
+            )
+        };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+
+        Ok(Self::new(pool))
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn acquire(&self) -> Result<Box<dyn StorageConn>, Error> {
+        Ok(Box::new(SqliteConn(self.pool.acquire().await?)))
+    }
+
+    async fn begin(&self) -> Result<Box<dyn StorageTx>, Error> {
+        Ok(Box::new(SqliteConn(
+            self.pool.begin_with("BEGIN IMMEDIATE").await?,
+        )))
+    }
+
+    /// A deferred transaction only takes a read lock, on its first read
+    async fn begin_read(&self) -> Result<Box<dyn StorageTx>, Error> {
+        Ok(Box::new(SqliteConn(self.pool.begin().await?)))
+    }
+
+    async fn run_migrations(&self) -> Result<(), Error> {
+        sqlx::migrate!("./db/sqlite_migrations/")
+            .run(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// A SQLite database is owned by a single node, which always leads
+    async fn try_lead(&self, _task: &str) -> Result<Option<Box<dyn Leadership>>, Error> {
+        Ok(Some(Box::new(SqliteLeadership)))
+    }
+}
+
+pub struct SqliteLeadership;
+
+#[async_trait]
+impl Leadership for SqliteLeadership {
+    async fn is_held(&mut self) -> bool {
+        true
+    }
+}
+
+/// Either a pooled connection or a transaction
+pub struct SqliteConn<C>(C);
+
+#[async_trait]
+impl StorageTx for SqliteConn<Transaction<'static, Sqlite>> {
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        self.0.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: DerefMut<Target = SqliteConnection> + Send> StorageConn for SqliteConn<C> {
+    async fn get_keysets(&mut self) -> Result<Vec<([u8; 8], String, bool, Option<u64>)>, Error> {
+        keyset::get_keysets(&mut self.0).await
+    }
+
+    async fn get_keyset(&mut self, keyset_id: &KeysetId) -> Result<KeysetInfo<String>, Error> {
+        keyset::get_keyset(&mut self.0, keyset_id).await
+    }
+
+    async fn get_active_keysets(&mut self) -> Result<Vec<(KeysetId, KeysetInfo<String>)>, Error> {
+        keyset::get_active_keysets(&mut self.0).await
+    }
+
+    async fn get_active_keysets_created_at(&mut self) -> Result<Vec<(KeysetId, u64)>, Error> {
+        keyset::get_active_keysets_created_at(&mut self.0).await
+    }
+
+    async fn insert_keysets(
+        &mut self,
+        keysets: &[(KeysetId, String, u32, u32)],
+    ) -> Result<(), Error> {
+        keyset::insert_keysets(&mut self.0, keysets).await
+    }
+
+    async fn deactivate_keysets(
+        &mut self,
+        keyset_ids: &[KeysetId],
+        final_expiry: Option<u64>,
+    ) -> Result<(), Error> {
+        keyset::deactivate_keysets(&mut self.0, keyset_ids, final_expiry).await
+    }
+
+    async fn is_any_proof_already_spent(&mut self, ys: &[PublicKey]) -> Result<bool, Error> {
+        proof::is_any_already_spent(&mut self.0, ys).await
+    }
+
+    async fn get_proofs_states(&mut self, ys: &[PublicKey]) -> Result<Vec<ProofState>, Error> {
+        proof::get_proofs_by_ids(&mut self.0, ys).await
+    }
+
+    async fn insert_spent_proofs(&mut self, proofs: &[Proof]) -> Result<(), Error> {
+        let proofs = proofs
+            .iter()
+            .map(|p| Ok((p.y().map_err(|_| Error::HashOnCurve)?, p)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        proof::insert_spent_proofs(&mut self.0, &proofs).await
+    }
+
+    async fn get_keyset_spent_proofs(
+        &mut self,
+        keyset_id: KeysetId,
+    ) -> Result<Vec<(PublicKey, Amount)>, Error> {
+        proof::get_keyset_spent_proofs(&mut self.0, keyset_id).await
+    }
+
+    async fn is_any_blind_message_already_used(
+        &mut self,
+        blinded_secrets: &[PublicKey],
+    ) -> Result<bool, Error> {
+        blind_signature::is_any_blind_message_already_used(&mut self.0, blinded_secrets).await
+    }
+
+    async fn insert_blind_signatures(
+        &mut self,
+        blinded_messages: &[BlindedMessage],
+        blind_signatures: &[BlindSignature],
+    ) -> Result<(), Error> {
+        blind_signature::insert_blind_signatures(&mut self.0, blinded_messages, blind_signatures)
+            .await
+    }
+
+    async fn get_blind_signatures(
+        &mut self,
+        blinded_secrets: &[PublicKey],
+    ) -> Result<Vec<RestoreFromDbResponse>, Error> {
+        blind_signature::get_by_blind_secrets(&mut self.0, blinded_secrets).await
+    }
+
+    async fn sum_amount_of_unit_in_circulation(&mut self, unit: &str) -> Result<Amount, Error> {
+        blind_signature::sum_amount_of_unit_in_circulation(&mut self.0, unit).await
+    }
+
+    async fn get_keyset_blind_signatures(
+        &mut self,
+        keyset_id: KeysetId,
+    ) -> Result<Vec<(PublicKey, Amount, PublicKey)>, Error> {
+        blind_signature::get_keyset_blind_signatures(&mut self.0, keyset_id).await
+    }
+
+    async fn insert_mint_quote(
+        &mut self,
+        quote_id: Uuid,
+        invoice_id: [u8; 32],
+        unit: &str,
+        amount: Amount,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(), Error> {
+        mint_quote::insert_new(
+            &mut self.0,
+            quote_id,
+            invoice_id,
+            unit,
+            amount,
+            request,
+            expiry,
+        )
+        .await
+    }
+
+    async fn get_mint_quote_response(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<MintQuoteResponse<Uuid>, Error> {
+        mint_quote::build_response_from_db(&mut self.0, quote_id).await
+    }
+
+    async fn get_mint_quote_amount_and_state(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<(Amount, MintQuoteState), Error> {
+        mint_quote::get_amount_and_state(&mut self.0, quote_id).await
+    }
+
+    async fn set_mint_quote_state(
+        &mut self,
+        quote_id: Uuid,
+        state: MintQuoteState,
+    ) -> Result<(), Error> {
+        mint_quote::set_state(&mut self.0, quote_id, state).await
+    }
+
+    async fn get_mint_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error> {
+        mint_quote::get_quote_infos_by_invoice_id(&mut self.0, invoice_id).await
+    }
+
+    async fn get_or_restore_mint_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error> {
+        mint_quote::get_or_restore_quote_infos_by_invoice_id(&mut self.0, invoice_id).await
+    }
+
+    async fn list_mint_quotes(
+        &mut self,
+        state: Option<MintQuoteState>,
+        limit: i64,
+    ) -> Result<Vec<MintQuoteInfo>, Error> {
+        mint_quote::list(&mut self.0, state, limit).await
+    }
+
+    async fn get_mint_quote_info(&mut self, quote_id: Uuid) -> Result<MintQuoteInfo, Error> {
+        mint_quote::get_info(&mut self.0, quote_id).await
+    }
+
+    async fn force_expire_mint_quote(&mut self, quote_id: Uuid) -> Result<bool, Error> {
+        mint_quote::force_expire(&mut self.0, quote_id).await
+    }
+
+    async fn expire_unpaid_mint_quotes(&mut self) -> Result<u64, Error> {
+        mint_quote::expire_unpaid(&mut self.0).await
+    }
+
+    async fn archive_mint_quotes(&mut self, older_than: u64) -> Result<u64, Error> {
+        mint_quote::archive(&mut self.0, older_than).await
+    }
+
+    async fn insert_melt_quote(
+        &mut self,
+        quote_id: Uuid,
+        invoice_id: &[u8; 32],
+        unit: &str,
+        amount: Amount,
+        fee: Amount,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(), Error> {
+        melt_quote::insert_new(
+            &mut self.0,
+            quote_id,
+            invoice_id,
+            unit,
+            amount,
+            fee,
+            request,
+            expiry,
+        )
+        .await
+    }
+
+    async fn get_melt_quote_response(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<MeltQuoteResponseRecord, Error> {
+        melt_quote::build_response_from_db(&mut self.0, quote_id).await
+    }
+
+    async fn get_melt_quote_data(&mut self, quote_id: Uuid) -> Result<MeltQuoteData, Error> {
+        melt_quote::get_data(&mut self.0, quote_id).await
+    }
+
+    async fn get_melt_quote_state(&mut self, quote_id: Uuid) -> Result<MeltQuoteState, Error> {
+        melt_quote::get_state(&mut self.0, quote_id).await
+    }
+
+    async fn set_melt_quote_state(
+        &mut self,
+        quote_id: Uuid,
+        state: MeltQuoteState,
+    ) -> Result<(), Error> {
+        melt_quote::set_state(&mut self.0, quote_id, state).await
+    }
+
+    async fn return_melt_quote_change(
+        &mut self,
+        quote_id: Uuid,
+        change: Amount,
+    ) -> Result<(), Error> {
+        melt_quote::return_change(&mut self.0, quote_id, change).await
+    }
+
+    async fn insert_melt_quote_inputs(
+        &mut self,
+        quote_id: Uuid,
+        proofs: &[Proof],
+    ) -> Result<(), Error> {
+        let ys = proofs
+            .iter()
+            .map(|p| p.y().map_err(|_| Error::HashOnCurve))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        melt_quote::insert_inputs(&mut self.0, quote_id, &ys).await
+    }
+
+    async fn revert_melt_quote(&mut self, quote_id: Uuid) -> Result<bool, Error> {
+        melt_quote::revert(&mut self.0, quote_id).await
+    }
+
+    async fn get_melt_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error> {
+        melt_quote::get_quote_infos_by_invoice_id(&mut self.0, invoice_id).await
+    }
+
+    async fn get_or_restore_melt_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error> {
+        melt_quote::get_or_restore_quote_infos_by_invoice_id(&mut self.0, invoice_id).await
+    }
+
+    async fn get_melt_quote_state_and_transfer_ids(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<(MeltQuoteState, Option<Vec<String>>), Error> {
+        melt_quote::get_state_and_transfer_ids(&mut self.0, quote_id).await
+    }
+
+    async fn list_melt_quotes(
+        &mut self,
+        state: Option<MeltQuoteState>,
+        limit: i64,
+    ) -> Result<Vec<MeltQuoteInfo>, Error> {
+        melt_quote::list(&mut self.0, state, limit).await
+    }
+
+    async fn get_melt_quote_info(&mut self, quote_id: Uuid) -> Result<MeltQuoteInfo, Error> {
+        melt_quote::get_info(&mut self.0, quote_id).await
+    }
+
+    async fn force_expire_melt_quote(&mut self, quote_id: Uuid) -> Result<bool, Error> {
+        melt_quote::force_expire(&mut self.0, quote_id).await
+    }
+
+    async fn expire_unpaid_melt_quotes(&mut self) -> Result<u64, Error> {
+        melt_quote::expire_unpaid(&mut self.0).await
+    }
+
+    async fn archive_melt_quotes(&mut self, older_than: u64) -> Result<u64, Error> {
+        melt_quote::archive(&mut self.0, older_than).await
+    }
+
+    async fn insert_mint_payment_event(
+        &mut self,
+        payment_event: &PaymentEvent,
+    ) -> Result<(), Error> {
+        payment_event::insert_new_payment_event(&mut self.0, PaymentEventTable::Mint, payment_event)
+            .await
+    }
+
+    async fn get_mint_current_paid(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Vec<(String, String)>, Error> {
+        payment_event::get_current_paid(&mut self.0, PaymentEventTable::Mint, invoice_id).await
+    }
+
+    async fn insert_melt_payment_event(
+        &mut self,
+        payment_event: &PaymentEvent,
+    ) -> Result<(), Error> {
+        payment_event::insert_new_payment_event(&mut self.0, PaymentEventTable::Melt, payment_event)
+            .await
+    }
+
+    async fn get_melt_current_paid(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Vec<(String, String)>, Error> {
+        payment_event::get_current_paid(&mut self.0, PaymentEventTable::Melt, invoice_id).await
+    }
+
+    async fn insert_melt_withdrawal(
+        &mut self,
+        quote_id: Uuid,
+        method: &str,
+        payment: &str,
+    ) -> Result<(), Error> {
+        melt_withdrawal::insert(&mut self.0, quote_id, method, payment).await
+    }
+
+    async fn list_melt_withdrawals(
+        &mut self,
+        method: &str,
+        limit: i64,
+    ) -> Result<Vec<MeltWithdrawal>, Error> {
+        melt_withdrawal::list(&mut self.0, method, limit).await
+    }
+
+    async fn set_melt_withdrawal_tx_hash(
+        &mut self,
+        quote_id: Uuid,
+        tx_hash: Option<&str>,
+    ) -> Result<(), Error> {
+        melt_withdrawal::set_tx_hash(&mut self.0, quote_id, tx_hash).await
+    }
+
+    async fn delete_melt_withdrawal(&mut self, quote_id: Uuid) -> Result<(), Error> {
+        melt_withdrawal::delete(&mut self.0, quote_id).await
+    }
+
+    async fn insert_exchange(&mut self, exchange: &Exchange) -> Result<(), Error> {
+        exchange::insert(&mut self.0, exchange).await
+    }
+
+    async fn get_gauge_metrics(
+        &mut self,
+        units: &[String],
+    ) -> Result<Vec<(String, GaugeMetrics)>, Error> {
+        gauge::get_all_gauge_metrics_by_units(&mut self.0, units).await
+    }
+
+    async fn get_keyset_amount_ledger(&mut self) -> Result<Vec<KeysetAmountLedger>, Error> {
+        ledger::get_keyset_amount_ledger(&mut self.0).await
+    }
+
+    async fn get_unit_quote_ledger(&mut self) -> Result<Vec<UnitQuoteLedger>, Error> {
+        ledger::get_unit_quote_ledger(&mut self.0).await
+    }
+
+    async fn get_unit_exchange_ledger(&mut self) -> Result<Vec<UnitExchangeLedger>, Error> {
+        ledger::get_unit_exchange_ledger(&mut self.0).await
+    }
+
+    async fn get_mint_quotes_without_payment_event(
+        &mut self,
+    ) -> Result<Vec<(Uuid, MintQuoteState)>, Error> {
+        ledger::get_mint_quotes_without_payment_event(&mut self.0).await
+    }
+
+    async fn get_melt_quotes_without_payment_event(
+        &mut self,
+    ) -> Result<Vec<(Uuid, MeltQuoteState)>, Error> {
+        ledger::get_melt_quotes_without_payment_event(&mut self.0).await
+    }
+
+    async fn insert_cached_response(
+        &mut self,
+        key: &CacheResponseKey,
+        response: &[u8],
+        expiry: Option<u64>,
+    ) -> Result<(), Error> {
+        response_cache::insert(&mut self.0, key, response, expiry).await
+    }
+
+    async fn get_cached_response(
+        &mut self,
+        key: &CacheResponseKey,
+        now: u64,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        response_cache::get(&mut self.0, key, now).await
+    }
+
+    async fn remove_cached_response(&mut self, key: &CacheResponseKey) -> Result<bool, Error> {
+        response_cache::remove(&mut self.0, key).await
+    }
+
+    async fn delete_expired_cached_responses(&mut self, now: u64) -> Result<u64, Error> {
+        response_cache::delete_expired(&mut self.0, now).await
+    }
+
+    async fn insert_audit_event(&mut self, kind: &str, payload: &str) -> Result<(), Error> {
+        audit::insert(&mut self.0, kind, payload).await
+    }
+
+    async fn seal_audit_events(&mut self) -> Result<u64, Error> {
+        audit::seal(&mut self.0).await
+    }
+
+    async fn get_sealed_audit_events(
+        &mut self,
+        from_seq: u64,
+        limit: u64,
+    ) -> Result<Vec<AuditEvent>, Error> {
+        audit::get_sealed(&mut self.0, from_seq, limit).await
+    }
+
+    async fn count_unsealed_audit_events(&mut self) -> Result<u64, Error> {
+        audit::count_unsealed(&mut self.0).await
+    }
+}
@@ -0,0 +1,66 @@
+//! Shared by the `mint_payment_event` and `melt_payment_event` tables, which have the same layout
+use sqlx::SqliteConnection;
+
+use crate::{Error, PaymentEvent};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PaymentEventTable {
+    Mint,
+    Melt,
+}
+
+impl PaymentEventTable {
+    fn name(&self) -> &'static str {
+        match self {
+            PaymentEventTable::Mint => "mint_payment_event",
+            PaymentEventTable::Melt => "melt_payment_event",
+        }
+    }
+}
+
+pub async fn insert_new_payment_event(
+    conn: &mut SqliteConnection,
+    table: PaymentEventTable,
+    payment_event: &PaymentEvent,
+) -> Result<(), Error> {
+    let sql = format!(
+        r#"INSERT INTO {}
+            (block_id, tx_hash, event_index, payee, asset, invoice_id, payer, amount_low, amount_high)
+        VALUES
+            (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT DO NOTHING"#,
+        table.name()
+    );
+    sqlx::query(&sql)
+        .bind(&payment_event.block_id)
+        .bind(&payment_event.tx_hash)
+        .bind(i64::from_be_bytes(payment_event.event_idx.to_be_bytes()))
+        .bind(&payment_event.payee)
+        .bind(&payment_event.asset)
+        .bind(payment_event.invoice_id.to_vec())
+        .bind(&payment_event.payer)
+        .bind(&payment_event.amount_low)
+        .bind(&payment_event.amount_high)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_current_paid(
+    conn: &mut SqliteConnection,
+    table: PaymentEventTable,
+    invoice_id: &[u8; 32],
+) -> Result<Vec<(String, String)>, Error> {
+    let sql = format!(
+        "SELECT amount_low, amount_high FROM {} WHERE invoice_id = ?",
+        table.name()
+    );
+
+    let amounts = sqlx::query_as(&sql)
+        .bind(invoice_id.to_vec())
+        .fetch_all(conn)
+        .await?;
+
+    Ok(amounts)
+}
@@ -0,0 +1,107 @@
+use nuts::{Amount, nut00::Proof, nut01::PublicKey, nut02::KeysetId, nut07::ProofState};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::Error;
+
+/// Return true if one of the provided secret
+/// is already in db with state = SPENT
+pub async fn is_any_already_spent(
+    conn: &mut SqliteConnection,
+    ys: &[PublicKey],
+) -> Result<bool, Error> {
+    if ys.is_empty() {
+        return Ok(false);
+    }
+
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("SELECT EXISTS (SELECT * FROM proof WHERE state = ");
+    builder
+        .push_bind(ProofState::Spent as i16)
+        .push(" AND y IN (");
+    let mut separated = builder.separated(", ");
+    for y in ys {
+        separated.push_bind(y.to_bytes().to_vec());
+    }
+    builder.push("))");
+
+    let exists: bool = builder.build_query_scalar().fetch_one(conn).await?;
+
+    Ok(exists)
+}
+
+pub async fn get_proofs_by_ids(
+    conn: &mut SqliteConnection,
+    ys: &[PublicKey],
+) -> Result<Vec<ProofState>, Error> {
+    let mut ret = Vec::with_capacity(ys.len());
+    for y in ys {
+        let state: Option<i16> = sqlx::query_scalar("SELECT state FROM proof WHERE y = ?")
+            .bind(y.to_bytes().to_vec())
+            .fetch_optional(&mut *conn)
+            .await?;
+        let proof_state = match state {
+            Some(state_val) => {
+                ProofState::from_i32(state_val.into()).ok_or(Error::DbToRuntimeConversion)?
+            }
+            None => ProofState::Unspent,
+        };
+        ret.push(proof_state);
+    }
+
+    Ok(ret)
+}
+
+/// Insert new proofs AS SPENT, or update previously existing UNSPENT proofs to SPENT
+///
+/// Unlike with Postgres, an already SPENT proof is left untouched rather than making the query fail,
+/// so the callers must check them with `is_any_already_spent` beforehand, in the same transaction.
+pub async fn insert_spent_proofs(
+    conn: &mut SqliteConnection,
+    proofs: &[(PublicKey, &Proof)],
+) -> Result<(), Error> {
+    if proofs.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("INSERT INTO proof (y, amount, keyset_id, secret, c, state) ");
+    builder.push_values(proofs, |mut row, (y, proof)| {
+        row.push_bind(y.to_bytes().to_vec())
+            .push_bind(proof.amount.into_i64_repr())
+            .push_bind(proof.keyset_id.as_i64())
+            .push_bind(proof.secret.to_string())
+            .push_bind(proof.c.to_bytes().to_vec())
+            .push_bind(ProofState::Spent as i16);
+    });
+    builder
+        .push(" ON CONFLICT (y) DO UPDATE SET state = excluded.state WHERE proof.state = ")
+        .push_bind(ProofState::Unspent as i16);
+    builder.build().execute(conn).await?;
+
+    Ok(())
+}
+
+/// Returns the Y and amount of every spent proof of this keyset
+///
+/// They are ordered by Y.
+pub async fn get_keyset_spent_proofs(
+    conn: &mut SqliteConnection,
+    keyset_id: KeysetId,
+) -> Result<Vec<(PublicKey, Amount)>, Error> {
+    let records: Vec<(Vec<u8>, i64)> =
+        sqlx::query_as("SELECT y, amount FROM proof WHERE keyset_id = ? AND state = ? ORDER BY y")
+            .bind(keyset_id.as_i64())
+            .bind(ProofState::Spent as i16)
+            .fetch_all(conn)
+            .await?;
+
+    records
+        .into_iter()
+        .map(|(y, amount)| {
+            Ok((
+                PublicKey::from_slice(&y).map_err(|_| Error::DbToRuntimeConversion)?,
+                Amount::from_i64_repr(amount),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+use nuts::nut19::CacheResponseKey;
+use sqlx::SqliteConnection;
+
+use super::timestamp_to_db;
+use crate::Error;
+
+fn request_hash_to_db(request_hash: u64) -> i64 {
+    i64::from_be_bytes(request_hash.to_be_bytes())
+}
+
+pub async fn insert(
+    conn: &mut SqliteConnection,
+    key: &CacheResponseKey,
+    response: &[u8],
+    expiry: Option<u64>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO response_cache (route, request_hash, response, expiry)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (route, request_hash) DO UPDATE
+            SET response = excluded.response, expiry = excluded.expiry"#,
+    )
+    .bind(key.0.to_string())
+    .bind(request_hash_to_db(key.1))
+    .bind(response)
+    .bind(expiry.map(timestamp_to_db).transpose()?)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get(
+    conn: &mut SqliteConnection,
+    key: &CacheResponseKey,
+    now: u64,
+) -> Result<Option<Vec<u8>>, Error> {
+    let response = sqlx::query_scalar(
+        r#"SELECT response FROM response_cache
+        WHERE route = ? AND request_hash = ? AND (expiry IS NULL OR expiry > ?)"#,
+    )
+    .bind(key.0.to_string())
+    .bind(request_hash_to_db(key.1))
+    .bind(timestamp_to_db(now)?)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(response)
+}
+
+pub async fn remove(conn: &mut SqliteConnection, key: &CacheResponseKey) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM response_cache WHERE route = ? AND request_hash = ?")
+        .bind(key.0.to_string())
+        .bind(request_hash_to_db(key.1))
+        .execute(conn)
+        .await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+pub async fn delete_expired(conn: &mut SqliteConnection, now: u64) -> Result<u64, Error> {
+    let result = sqlx::query("DELETE FROM response_cache WHERE expiry <= ?")
+        .bind(timestamp_to_db(now)?)
+        .execute(conn)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use nuts::nut19::Route;
+
+    use crate::{Storage, sqlite::SqliteStorage};
+
+    async fn open(path: &std::path::Path) -> SqliteStorage {
+        let storage = SqliteStorage::connect(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+        storage.run_migrations().await.unwrap();
+
+        storage
+    }
+
+    #[tokio::test]
+    async fn response_written_in_tx_survives_restart() {
+        let path = std::env::temp_dir().join(format!("{}.db", uuid::Uuid::new_v4()));
+        let committed = (Route::Swap, 1);
+        let rolled_back = (Route::Mint, 2);
+
+        let storage = open(&path).await;
+        let mut tx = storage.begin().await.unwrap();
+        tx.insert_cached_response(&committed, b"swap", None)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let mut tx = storage.begin().await.unwrap();
+        tx.insert_cached_response(&rolled_back, b"mint", None)
+            .await
+            .unwrap();
+        drop(tx);
+        drop(storage);
+
+        let storage = open(&path).await;
+        let mut conn = storage.acquire().await.unwrap();
+        assert_eq!(
+            conn.get_cached_response(&committed, 0).await.unwrap(),
+            Some(b"swap".to_vec())
+        );
+        assert_eq!(
+            conn.get_cached_response(&rolled_back, 0).await.unwrap(),
+            None
+        );
+        drop(conn);
+        drop(storage);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
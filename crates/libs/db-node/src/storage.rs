@@ -0,0 +1,399 @@
+//! Database agnostic access to the node data
+//!
+//! The node only talks to its database through those traits.
+//! They are implemented for Postgres by [`crate::postgres::PgStorage`]
+//! and for SQLite by [`crate::sqlite::SqliteStorage`].
+//!
+//! Units are passed and returned as their string representation,
+//! the caller being responsible for parsing them into its own unit type.
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use nuts::{
+    Amount,
+    nut00::{BlindSignature, BlindedMessage, Proof},
+    nut01::PublicKey,
+    nut02::KeysetId,
+    nut04::{MintQuoteResponse, MintQuoteState},
+    nut05::MeltQuoteState,
+    nut07::ProofState,
+    nut19::CacheResponseKey,
+};
+use uuid::Uuid;
+
+use crate::{
+    Error, PaymentEvent,
+    audit::AuditEvent,
+    blind_signature::RestoreFromDbResponse,
+    exchange::Exchange,
+    gauge::GaugeMetrics,
+    keyset::KeysetInfo,
+    ledger::{KeysetAmountLedger, UnitExchangeLedger, UnitQuoteLedger},
+    melt_quote::{MeltQuoteData, MeltQuoteInfo, MeltQuoteResponseRecord},
+    melt_withdrawal::MeltWithdrawal,
+    mint_quote::MintQuoteInfo,
+};
+
+pub type SharedStorage = Arc<dyn Storage>;
+
+/// A pool of connections to the node database
+#[async_trait]
+pub trait Storage: Debug + Send + Sync {
+    /// Get a connection out of the pool
+    async fn acquire(&self) -> Result<Box<dyn StorageConn>, Error>;
+
+    /// Start a transaction
+    ///
+    /// It provides the guarantees of the SERIALIZABLE isolation level:
+    /// if two concurrent transactions were to conflict, one of them fails.
+    /// It is rolled back if dropped without being committed.
+    async fn begin(&self) -> Result<Box<dyn StorageTx>, Error>;
+
+    /// Start a read-only transaction
+    ///
+    /// All its reads see the database at the same point in time,
+    /// without blocking nor being blocked by the concurrent writers.
+    async fn begin_read(&self) -> Result<Box<dyn StorageTx>, Error>;
+
+    /// Bring the database schema up to date
+    async fn run_migrations(&self) -> Result<(), Error>;
+
+    /// Try to become the leader of `task` among the nodes sharing this database
+    ///
+    /// Returns `None` if another node leads it already.
+    /// The leadership is kept until the returned value is dropped. See [`crate::leader`].
+    async fn try_lead(&self, task: &str) -> Result<Option<Box<dyn Leadership>>, Error>;
+}
+
+/// The leadership of a task, released when dropped
+#[async_trait]
+pub trait Leadership: Send {
+    /// Returns false if the leadership was lost, eg. because the database connection was closed
+    async fn is_held(&mut self) -> bool;
+}
+
+/// A transaction on the node database
+#[async_trait]
+pub trait StorageTx: StorageConn {
+    async fn commit(self: Box<Self>) -> Result<(), Error>;
+}
+
+/// The queries the node runs against its database
+#[async_trait]
+pub trait StorageConn: Send {
+    // Keyset
+
+    /// Returns the id, unit, active flag and final expiry of every keyset
+    #[allow(clippy::type_complexity)]
+    async fn get_keysets(&mut self) -> Result<Vec<([u8; 8], String, bool, Option<u64>)>, Error>;
+    async fn get_keyset(&mut self, keyset_id: &KeysetId) -> Result<KeysetInfo<String>, Error>;
+    async fn get_active_keysets(&mut self) -> Result<Vec<(KeysetId, KeysetInfo<String>)>, Error>;
+    /// Returns the id of every active keyset, with the unix timestamp of its creation
+    async fn get_active_keysets_created_at(&mut self) -> Result<Vec<(KeysetId, u64)>, Error>;
+    /// Insert new active keysets, described by their id, unit, max order and derivation path index
+    ///
+    /// Already known keysets are ignored.
+    async fn insert_keysets(
+        &mut self,
+        keysets: &[(KeysetId, String, u32, u32)],
+    ) -> Result<(), Error>;
+    /// Deactivate the keysets, and optionally schedule their retirement
+    ///
+    /// See [`crate::keyset::deactivate_keysets`].
+    async fn deactivate_keysets(
+        &mut self,
+        keyset_ids: &[KeysetId],
+        final_expiry: Option<u64>,
+    ) -> Result<(), Error>;
+
+    // Proof
+
+    /// Return true if one of those proofs is already spent
+    async fn is_any_proof_already_spent(&mut self, ys: &[PublicKey]) -> Result<bool, Error>;
+    /// Returns the state of each proof, in the same order as `ys`
+    async fn get_proofs_states(&mut self, ys: &[PublicKey]) -> Result<Vec<ProofState>, Error>;
+    /// Insert the proofs as spent
+    ///
+    /// Fails if one of them is already spent.
+    async fn insert_spent_proofs(&mut self, proofs: &[Proof]) -> Result<(), Error>;
+    /// Returns the Y and amount of every spent proof of this keyset, ordered by Y
+    async fn get_keyset_spent_proofs(
+        &mut self,
+        keyset_id: KeysetId,
+    ) -> Result<Vec<(PublicKey, Amount)>, Error>;
+
+    // Blind signature
+
+    /// Return true if one of those blinded secrets has already been signed by us
+    async fn is_any_blind_message_already_used(
+        &mut self,
+        blinded_secrets: &[PublicKey],
+    ) -> Result<bool, Error>;
+    /// Insert the signature of each blinded message, `blind_signatures[i]` signing `blinded_messages[i]`
+    async fn insert_blind_signatures(
+        &mut self,
+        blinded_messages: &[BlindedMessage],
+        blind_signatures: &[BlindSignature],
+    ) -> Result<(), Error>;
+    /// Returns the signatures of the known blinded secrets, in the same order as `blinded_secrets`
+    async fn get_blind_signatures(
+        &mut self,
+        blinded_secrets: &[PublicKey],
+    ) -> Result<Vec<RestoreFromDbResponse>, Error>;
+    async fn sum_amount_of_unit_in_circulation(&mut self, unit: &str) -> Result<Amount, Error>;
+    /// Returns the blinded secret, amount and signature of every signature issued with this keyset,
+    /// ordered by blinded secret
+    async fn get_keyset_blind_signatures(
+        &mut self,
+        keyset_id: KeysetId,
+    ) -> Result<Vec<(PublicKey, Amount, PublicKey)>, Error>;
+
+    // Mint quote
+
+    async fn insert_mint_quote(
+        &mut self,
+        quote_id: Uuid,
+        invoice_id: [u8; 32],
+        unit: &str,
+        amount: Amount,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(), Error>;
+    async fn get_mint_quote_response(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<MintQuoteResponse<Uuid>, Error>;
+    /// Locks the quote until the end of the transaction
+    async fn get_mint_quote_amount_and_state(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<(Amount, MintQuoteState), Error>;
+    async fn set_mint_quote_state(
+        &mut self,
+        quote_id: Uuid,
+        state: MintQuoteState,
+    ) -> Result<(), Error>;
+    /// Returns the id, amount and unit of the quote
+    async fn get_mint_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error>;
+    /// Returns the id, amount and unit of the quote, to record a payment to it
+    ///
+    /// An archived expired quote is moved back to the live tables first, so that a late payment is still credited.
+    async fn get_or_restore_mint_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error>;
+    /// List the most recent quotes, optionally filtered by state
+    async fn list_mint_quotes(
+        &mut self,
+        state: Option<MintQuoteState>,
+        limit: i64,
+    ) -> Result<Vec<MintQuoteInfo>, Error>;
+    async fn get_mint_quote_info(&mut self, quote_id: Uuid) -> Result<MintQuoteInfo, Error>;
+    /// Set the expiry of an unpaid quote in the past and mark it as expired
+    ///
+    /// Returns false if there is no unpaid quote with this id.
+    async fn force_expire_mint_quote(&mut self, quote_id: Uuid) -> Result<bool, Error>;
+    /// Mark the unpaid quotes whose expiry is elapsed as expired, returns how many were
+    async fn expire_unpaid_mint_quotes(&mut self) -> Result<u64, Error>;
+    /// Move the terminal quotes expired for more than `older_than` seconds,
+    /// and their payment events, to the history tables
+    ///
+    /// An expired quote that received a partial payment is kept.
+    /// Returns the number of quotes archived.
+    async fn archive_mint_quotes(&mut self, older_than: u64) -> Result<u64, Error>;
+
+    // Melt quote
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_melt_quote(
+        &mut self,
+        quote_id: Uuid,
+        invoice_id: &[u8; 32],
+        unit: &str,
+        amount: Amount,
+        fee: Amount,
+        request: &str,
+        expiry: u64,
+    ) -> Result<(), Error>;
+    async fn get_melt_quote_response(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<MeltQuoteResponseRecord, Error>;
+    /// Locks the quote until the end of the transaction
+    async fn get_melt_quote_data(&mut self, quote_id: Uuid) -> Result<MeltQuoteData, Error>;
+    async fn get_melt_quote_state(&mut self, quote_id: Uuid) -> Result<MeltQuoteState, Error>;
+    async fn set_melt_quote_state(
+        &mut self,
+        quote_id: Uuid,
+        state: MeltQuoteState,
+    ) -> Result<(), Error>;
+    /// Lower the amount and the fee of a quote by the part of the fee reserve returned as change
+    async fn return_melt_quote_change(
+        &mut self,
+        quote_id: Uuid,
+        change: Amount,
+    ) -> Result<(), Error>;
+    /// Link the proofs spent by the melt to the quote, to be called in the transaction spending them
+    async fn insert_melt_quote_inputs(
+        &mut self,
+        quote_id: Uuid,
+        proofs: &[Proof],
+    ) -> Result<(), Error>;
+    /// Set a pending quote back to unpaid, unspending the proofs spent by its melt
+    ///
+    /// For payments known to have failed. Returns false if there is no pending quote with this id.
+    async fn revert_melt_quote(&mut self, quote_id: Uuid) -> Result<bool, Error>;
+    /// Returns the id, amount and unit of the quote
+    async fn get_melt_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error>;
+    /// Returns the id, amount and unit of the quote, to record a payment to it
+    ///
+    /// An archived expired quote is moved back to the live tables first, so that a late payment is still credited.
+    async fn get_or_restore_melt_quote_by_invoice_id(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Option<(Uuid, Amount, String)>, Error>;
+    async fn get_melt_quote_state_and_transfer_ids(
+        &mut self,
+        quote_id: Uuid,
+    ) -> Result<(MeltQuoteState, Option<Vec<String>>), Error>;
+    /// List the most recent quotes, optionally filtered by state
+    async fn list_melt_quotes(
+        &mut self,
+        state: Option<MeltQuoteState>,
+        limit: i64,
+    ) -> Result<Vec<MeltQuoteInfo>, Error>;
+    async fn get_melt_quote_info(&mut self, quote_id: Uuid) -> Result<MeltQuoteInfo, Error>;
+    /// Set the expiry of an unpaid quote in the past and mark it as expired
+    ///
+    /// Returns false if there is no unpaid quote with this id.
+    async fn force_expire_melt_quote(&mut self, quote_id: Uuid) -> Result<bool, Error>;
+    /// Mark the unpaid quotes whose expiry is elapsed as expired, returns how many were
+    async fn expire_unpaid_melt_quotes(&mut self) -> Result<u64, Error>;
+    /// Move the terminal quotes expired for more than `older_than` seconds,
+    /// and their payment events, to the history tables
+    ///
+    /// An expired quote that received a partial payment is kept.
+    /// Returns the number of quotes archived.
+    async fn archive_melt_quotes(&mut self, older_than: u64) -> Result<u64, Error>;
+
+    // Payment event
+
+    /// Store a payment to the node, ignored if already known
+    async fn insert_mint_payment_event(
+        &mut self,
+        payment_event: &PaymentEvent,
+    ) -> Result<(), Error>;
+    /// Returns the low and high parts of every payment made for this invoice
+    async fn get_mint_current_paid(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Vec<(String, String)>, Error>;
+    /// Store a payment from the node, ignored if already known
+    async fn insert_melt_payment_event(
+        &mut self,
+        payment_event: &PaymentEvent,
+    ) -> Result<(), Error>;
+    /// Returns the low and high parts of every payment made for this invoice
+    async fn get_melt_current_paid(
+        &mut self,
+        invoice_id: &[u8; 32],
+    ) -> Result<Vec<(String, String)>, Error>;
+
+    // Melt withdrawal
+
+    /// Queue the payment of a melt quote, serialized by the liquidity source of `method`
+    async fn insert_melt_withdrawal(
+        &mut self,
+        quote_id: Uuid,
+        method: &str,
+        payment: &str,
+    ) -> Result<(), Error>;
+    /// List the queued payments of `method`, oldest first
+    async fn list_melt_withdrawals(
+        &mut self,
+        method: &str,
+        limit: i64,
+    ) -> Result<Vec<MeltWithdrawal>, Error>;
+    /// Record the transaction sent for a payment, or forget it once it failed
+    async fn set_melt_withdrawal_tx_hash(
+        &mut self,
+        quote_id: Uuid,
+        tx_hash: Option<&str>,
+    ) -> Result<(), Error>;
+    /// Remove a payment from the queue, once confirmed or abandoned
+    async fn delete_melt_withdrawal(&mut self, quote_id: Uuid) -> Result<(), Error>;
+
+    // Exchange
+
+    /// Record an exchange, to be called in the transaction spending its inputs
+    async fn insert_exchange(&mut self, exchange: &Exchange) -> Result<(), Error>;
+
+    // Gauge
+
+    /// Returns the amounts of the quotes in each state, and of the ecash outstanding, for each unit
+    async fn get_gauge_metrics(
+        &mut self,
+        units: &[String],
+    ) -> Result<Vec<(String, GaugeMetrics)>, Error>;
+
+    // Ledger
+
+    /// Returns, for each amount of each keyset, how many signatures were issued and proofs spent
+    async fn get_keyset_amount_ledger(&mut self) -> Result<Vec<KeysetAmountLedger>, Error>;
+    /// Returns, for each unit, the total amount minted and melted through quotes
+    async fn get_unit_quote_ledger(&mut self) -> Result<Vec<UnitQuoteLedger>, Error>;
+    /// Returns, for each unit, the total amount issued and spent by exchanges
+    async fn get_unit_exchange_ledger(&mut self) -> Result<Vec<UnitExchangeLedger>, Error>;
+    /// Returns the PAID and ISSUED mint quotes for which no payment was ever received
+    async fn get_mint_quotes_without_payment_event(
+        &mut self,
+    ) -> Result<Vec<(Uuid, MintQuoteState)>, Error>;
+    /// Returns the PAID melt quotes for which no payment was ever sent
+    async fn get_melt_quotes_without_payment_event(
+        &mut self,
+    ) -> Result<Vec<(Uuid, MeltQuoteState)>, Error>;
+
+    // Response cache
+
+    /// Store a response, replacing the one already cached for this key if any
+    ///
+    /// The response won't be returned anymore after `expiry` (a unix timestamp), if provided.
+    async fn insert_cached_response(
+        &mut self,
+        key: &CacheResponseKey,
+        response: &[u8],
+        expiry: Option<u64>,
+    ) -> Result<(), Error>;
+    /// Returns the cached response for this key, unless it has expired at `now` (a unix timestamp)
+    async fn get_cached_response(
+        &mut self,
+        key: &CacheResponseKey,
+        now: u64,
+    ) -> Result<Option<Vec<u8>>, Error>;
+    /// Returns true if there was a response cached for this key
+    async fn remove_cached_response(&mut self, key: &CacheResponseKey) -> Result<bool, Error>;
+    /// Delete the responses expired at `now` (a unix timestamp), returns how many were removed
+    async fn delete_expired_cached_responses(&mut self, now: u64) -> Result<u64, Error>;
+
+    // Audit log
+
+    /// Record an operation, to be called in the transaction of the operation
+    async fn insert_audit_event(&mut self, kind: &str, payload: &str) -> Result<(), Error>;
+    /// Chain the events committed since the last call, returns how many were sealed
+    ///
+    /// Not meant to be called from inside a transaction.
+    async fn seal_audit_events(&mut self) -> Result<u64, Error>;
+    /// Returns at most `limit` sealed events, starting at position `from_seq`
+    async fn get_sealed_audit_events(
+        &mut self,
+        from_seq: u64,
+        limit: u64,
+    ) -> Result<Vec<AuditEvent>, Error>;
+    async fn count_unsealed_audit_events(&mut self) -> Result<u64, Error>;
+}
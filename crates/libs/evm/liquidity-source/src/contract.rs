@@ -0,0 +1,47 @@
+//! Bindings of our `InvoicePayment` contract, see `contracts/evm-invoice`, and of the ERC20 tokens it transfers
+use alloy::{
+    primitives::{B256, keccak256},
+    sol,
+    sol_types::SolValue,
+};
+use bitcoin_hashes::Sha256;
+use uuid::Uuid;
+
+sol! {
+    #[sol(rpc)]
+    interface IInvoicePayment {
+        /// A deposit was made for `invoiceId`
+        event Remittance(
+            address indexed asset,
+            address indexed payee,
+            bytes32 invoiceId,
+            address payer,
+            uint256 amount
+        );
+
+        /// Execute an erc20 transfer and emit the rich event
+        function payInvoice(
+            bytes32 quoteIdHash,
+            uint64 expiry,
+            address asset,
+            uint256 amount,
+            address payee
+        ) external;
+    }
+
+    #[sol(rpc)]
+    interface IERC20 {
+        function approve(address spender, uint256 amount) external returns (bool);
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+/// The `quoteIdHash` passed to `payInvoice`, so that the quote id is not revealed on-chain
+pub fn hash_quote_id(quote_id: Uuid) -> B256 {
+    B256::from(Sha256::hash(quote_id.as_bytes()).to_byte_array())
+}
+
+/// The `invoiceId` emitted by the contract, `keccak256(abi.encode(quoteIdHash, expiry))`
+pub fn compute_invoice_id(quote_id_hash: B256, expiry: u64) -> B256 {
+    keccak256((quote_id_hash, expiry).abi_encode())
+}
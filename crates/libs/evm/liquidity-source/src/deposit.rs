@@ -0,0 +1,96 @@
+use alloy::{
+    primitives::{Address, Bytes},
+    sol_types::SolCall,
+};
+use liquidity_source::DepositInterface;
+use nuts::Amount;
+use serde::{Deserialize, Serialize};
+use starknet_types::Unit;
+use uuid::Uuid;
+
+use crate::{
+    Error, EvmInvoiceId, TokenAddresses,
+    contract::{IERC20, IInvoicePayment, compute_invoice_id, hash_quote_id},
+    to_alloy_u256,
+};
+
+/// A transaction the payer has to send, in the order of the deposit payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Call {
+    pub to: Address,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub struct Depositer {
+    invoice_payment_contract_address: Address,
+    cashier_address: Address,
+    tokens: TokenAddresses,
+}
+
+impl Depositer {
+    pub fn new(
+        invoice_payment_contract_address: Address,
+        cashier_address: Address,
+        tokens: TokenAddresses,
+    ) -> Self {
+        Self {
+            invoice_payment_contract_address,
+            cashier_address,
+            tokens,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DepositInterface for Depositer {
+    type Error = Error;
+    type InvoiceId = EvmInvoiceId;
+
+    /// The calls approving the invoice contract and paying the invoice to the cashier, as a json array
+    async fn generate_deposit_payload(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+        expiry: u64,
+    ) -> Result<(Self::InvoiceId, String), Self::Error> {
+        let asset = unit.asset();
+        let token_address = self
+            .tokens
+            .address_of(asset)
+            .ok_or(Error::AssetNotFound(asset))?;
+        let amount = to_alloy_u256(unit.convert_amount_into_u256(amount));
+        let quote_id_hash = hash_quote_id(quote_id);
+
+        let calls = [
+            Call {
+                to: token_address,
+                data: IERC20::approveCall {
+                    spender: self.invoice_payment_contract_address,
+                    amount,
+                }
+                .abi_encode()
+                .into(),
+            },
+            Call {
+                to: self.invoice_payment_contract_address,
+                data: IInvoicePayment::payInvoiceCall {
+                    quoteIdHash: quote_id_hash,
+                    expiry,
+                    asset: token_address,
+                    amount,
+                    payee: self.cashier_address,
+                }
+                .abi_encode()
+                .into(),
+            },
+        ];
+        let calls_json_string = serde_json::to_string(&calls).map_err(Error::SerializeCalls)?;
+
+        Ok((
+            EvmInvoiceId(compute_invoice_id(quote_id_hash, expiry)),
+            calls_json_string,
+        ))
+    }
+}
@@ -0,0 +1,232 @@
+//! Mark the quotes paid by polling the `Remittance` logs of the invoice contract
+//!
+//! The logs are replayed from the starting block every time the task starts,
+//! the payment events being stored only once.
+use std::{str::FromStr, time::Duration};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::{DynProvider, Provider},
+    rpc::types::{Filter, Log},
+    sol_types::SolEvent,
+};
+use db_node::{SharedStorage, StorageConn};
+use nuts::{nut04::MintQuoteState, nut05::MeltQuoteState};
+use starknet_types::Unit;
+use tracing::{Level, debug, error, event};
+use uuid::Uuid;
+
+use crate::{Error, TokenAddresses, contract::IInvoicePayment::Remittance, to_alloy_u256};
+
+/// Max number of blocks queried by a single `eth_getLogs`, bigger ranges are rejected by most rpc providers
+const MAX_BLOCK_RANGE: u64 = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    pub invoice_payment_contract_address: Address,
+    pub cashier_address: Address,
+    pub tokens: TokenAddresses,
+    pub starting_block: u64,
+    pub confirmations: u64,
+    pub poll_interval: Duration,
+}
+
+pub async fn run(storage: SharedStorage, provider: DynProvider, config: IndexerConfig) {
+    let mut next_block = config.starting_block;
+    loop {
+        match poll(&storage, &provider, &config, next_block).await {
+            Ok(block) => next_block = block,
+            Err(err) => {
+                error!(name: "evm-indexer-error", name = "evm-indexer-error", error = %err);
+            }
+        }
+        tokio::time::sleep(config.poll_interval).await;
+    }
+}
+
+/// Process the logs of the confirmed blocks starting at `from_block`, returns the next block to process
+async fn poll(
+    storage: &SharedStorage,
+    provider: &DynProvider,
+    config: &IndexerConfig,
+    mut from_block: u64,
+) -> Result<u64, Error> {
+    let latest_block = provider.get_block_number().await?;
+    let Some(last_confirmed_block) = latest_block.checked_sub(config.confirmations) else {
+        return Ok(from_block);
+    };
+
+    while from_block <= last_confirmed_block {
+        let to_block = last_confirmed_block.min(from_block + MAX_BLOCK_RANGE - 1);
+        let filter = Filter::new()
+            .address(config.invoice_payment_contract_address)
+            .event_signature(Remittance::SIGNATURE_HASH)
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = provider.get_logs(&filter).await?;
+
+        let mut conn = storage.acquire().await?;
+        for log in logs {
+            process_log(conn.as_mut(), config, &log).await?;
+        }
+        from_block = to_block + 1;
+    }
+
+    Ok(from_block)
+}
+
+async fn process_log(
+    db_conn: &mut dyn StorageConn,
+    config: &IndexerConfig,
+    log: &Log,
+) -> Result<(), Error> {
+    let remittance = match log.log_decode::<Remittance>() {
+        Ok(decoded) => decoded.inner.data,
+        Err(err) => {
+            error!("failed to decode Remittance log: {err}");
+            return Ok(());
+        }
+    };
+
+    let invoice_id = remittance.invoiceId.0;
+    let (is_mint, quote_id, quote_amount, unit) = if let Some((quote_id, amount, unit)) = db_conn
+        .get_or_restore_mint_quote_by_invoice_id(&invoice_id)
+        .await?
+    {
+        (true, quote_id, amount, unit)
+    } else if let Some((quote_id, amount, unit)) = db_conn
+        .get_or_restore_melt_quote_by_invoice_id(&invoice_id)
+        .await?
+    {
+        (false, quote_id, amount, unit)
+    } else {
+        debug!("no quote for invoice_id {:#x}", remittance.invoiceId);
+        return Ok(());
+    };
+    let unit = Unit::from_str(&unit).map_err(|_| db_node::Error::InvalidUnit(unit))?;
+
+    let Some(asset) = config.tokens.asset_of(remittance.asset) else {
+        debug!(
+            "Got payment for quote {}, using token {}, which is not a configured asset.",
+            quote_id, remittance.asset
+        );
+        return Ok(());
+    };
+    if !unit.is_asset_supported(asset) {
+        // Could just be someone reusing an already existing invoice id he saw onchain
+        debug!(
+            "Got payment for quote {}, that expect unit {}, using asset {}, which is not the expected one.",
+            quote_id, unit, asset
+        );
+        return Ok(());
+    }
+
+    let to_pay = to_alloy_u256(unit.convert_amount_into_u256(quote_amount));
+    let payment_event = to_db_payment_event(log, &remittance);
+    #[allow(clippy::collapsible_else_if)]
+    if is_mint {
+        if remittance.payee == config.cashier_address {
+            handle_mint_payment(db_conn, quote_id, &payment_event, to_pay).await?;
+        }
+    } else {
+        if remittance.payer == config.cashier_address {
+            handle_melt_payment(db_conn, quote_id, &payment_event, to_pay).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_mint_payment(
+    db_conn: &mut dyn StorageConn,
+    quote_id: Uuid,
+    payment_event: &db_node::PaymentEvent,
+    to_pay: U256,
+) -> Result<(), Error> {
+    db_conn.insert_mint_payment_event(payment_event).await?;
+    let current_paid = sum_payments(
+        db_conn
+            .get_mint_current_paid(&payment_event.invoice_id)
+            .await?,
+    )?;
+
+    // Logs are replayed on restart, an issued quote must not be paid again.
+    // A payment mined after the quote expired is still owed to the user.
+    if current_paid >= to_pay
+        && matches!(
+            db_conn.get_mint_quote_response(quote_id).await?.state,
+            MintQuoteState::Unpaid | MintQuoteState::Expired
+        )
+    {
+        db_conn
+            .set_mint_quote_state(quote_id, MintQuoteState::Paid)
+            .await?;
+        event!(
+            name: "mint-quote-paid",
+            Level::INFO,
+            name = "mint-quote-paid",
+            %quote_id,
+        );
+    }
+
+    Ok(())
+}
+
+async fn handle_melt_payment(
+    db_conn: &mut dyn StorageConn,
+    quote_id: Uuid,
+    payment_event: &db_node::PaymentEvent,
+    to_pay: U256,
+) -> Result<(), Error> {
+    db_conn.insert_melt_payment_event(payment_event).await?;
+    let current_paid = sum_payments(
+        db_conn
+            .get_melt_current_paid(&payment_event.invoice_id)
+            .await?,
+    )?;
+
+    if current_paid >= to_pay
+        && db_conn.get_melt_quote_response(quote_id).await?.state != MeltQuoteState::Paid
+    {
+        db_conn
+            .set_melt_quote_state(quote_id, MeltQuoteState::Paid)
+            .await?;
+        event!(
+            name: "melt-quote-paid",
+            Level::INFO,
+            name = "melt-quote-paid",
+            %quote_id,
+        );
+    }
+
+    Ok(())
+}
+
+fn to_db_payment_event(log: &Log, remittance: &Remittance) -> db_node::PaymentEvent {
+    let amount_low =
+        u128::try_from(remittance.amount & U256::from(u128::MAX)).expect("masked to 128 bits");
+    let amount_high = u128::try_from(remittance.amount >> 128).expect("shifted to 128 bits");
+
+    db_node::PaymentEvent {
+        block_id: log.block_hash.unwrap_or_default().to_string(),
+        tx_hash: log.transaction_hash.unwrap_or_default().to_string(),
+        event_idx: log.log_index.unwrap_or_default(),
+        asset: remittance.asset.to_string(),
+        payee: remittance.payee.to_string(),
+        invoice_id: remittance.invoiceId.0,
+        payer: remittance.payer.to_string(),
+        amount_low: amount_low.to_string(),
+        amount_high: amount_high.to_string(),
+    }
+}
+
+/// Sum the amounts stored as low and high decimal parts
+fn sum_payments(amounts: Vec<(String, String)>) -> Result<U256, Error> {
+    amounts
+        .into_iter()
+        .try_fold(U256::ZERO, |acc, (low, high)| {
+            let low = U256::from_str(&low).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            let high = U256::from_str(&high).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            acc.checked_add(low + (high << 128)).ok_or(Error::Overflow)
+        })
+}
@@ -0,0 +1,313 @@
+//! EVM liquidity source, for the `evm` method
+//!
+//! Deposits and withdrawals are ERC20 transfers made through our `InvoicePayment` contract,
+//! whose `Remittance` events carry the invoice id of the quote, see `contracts/evm-invoice`.
+//! A single node polls the logs of the contract to mark the quotes paid.
+//! Withdrawals are queued in the `melt_withdrawal` table, and paid by a single node that retries them until their quote expires.
+//! Withdrawals are signed with the cashier private key, read from the `EVM_CASHIER_PRIVATE_KEY` env variable.
+mod contract;
+mod deposit;
+mod indexer;
+mod withdraw;
+
+use std::{
+    collections::HashMap,
+    fmt::{LowerHex, UpperHex},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::{
+    network::EthereumWallet,
+    primitives::{Address, B256, U256},
+    providers::{DynProvider, Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+};
+pub use contract::{IERC20, IInvoicePayment, compute_invoice_id, hash_quote_id};
+use db_node::{SharedStorage, leader::run_as_leader};
+pub use deposit::{Call, Depositer};
+use serde::Deserialize;
+use starknet_types::{Asset, AssetToUnitConversionError, Unit};
+use url::Url;
+pub use withdraw::{MeltPaymentRequest, Withdrawer};
+
+pub const CASHIER_PRIVATE_KEY_ENV_VAR: &str = "EVM_CASHIER_PRIVATE_KEY";
+
+/// Interval between two polls of the contract logs, if not configured
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadEvmConfigError {
+    #[error("failed to read EVM config file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("failed to deserialize EVM config file content: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// The `[evm]` table of the config file
+///
+/// ```toml
+/// [evm]
+/// chain_id = 31337
+/// rpc_url = "http://localhost:8545"
+/// invoice_payment_contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+/// starting_block = 0
+/// assets = { usdc = "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvmConfig {
+    /// Checked against the one of the rpc node at startup
+    pub chain_id: u64,
+    /// The url of the rpc node we want to use
+    pub rpc_url: Url,
+    /// The address of our deployment of the `InvoicePayment` contract
+    pub invoice_payment_contract_address: Address,
+    /// The block to start indexing from, the one containing the invoice contract deployment is enough
+    #[serde(default)]
+    pub starting_block: u64,
+    /// Number of blocks that must be built on top of a block before its logs are processed
+    #[serde(default)]
+    pub confirmations: u64,
+    /// Interval between two polls of the contract logs, in seconds
+    pub poll_interval_secs: Option<u64>,
+    /// The contract address of each registered asset deployed on the chain, keyed by symbol
+    pub assets: HashMap<String, Address>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    evm: Option<EvmConfig>,
+}
+
+/// Read the `[evm]` table of the config file, None if there is none
+pub fn read_evm_config(path: &Path) -> Result<Option<EvmConfig>, ReadEvmConfigError> {
+    let file_content = std::fs::read_to_string(path)?;
+    let config: ConfigFile = toml::from_str(&file_content)?;
+
+    Ok(config.evm)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read environment variable `{0}`: {1}")]
+    Env(&'static str, #[source] std::env::VarError),
+    #[error(transparent)]
+    Config(#[from] ReadEvmConfigError),
+    #[error("invalid private key value")]
+    PrivateKey,
+    #[error("evm.assets: `{0}` is not a registered asset")]
+    UnknownAsset(String),
+    #[error("evm.chain_id: configured {0}, but the rpc node is on chain {1}")]
+    ChainIdMismatch(u64, u64),
+    #[error("failed to call the rpc node: {0}")]
+    Rpc(#[from] alloy::transports::TransportError),
+    #[error("failed to send transaction: {0}")]
+    Contract(#[from] alloy::contract::Error),
+    #[error("failed to get transaction receipt: {0}")]
+    PendingTransaction(#[from] alloy::providers::PendingTransactionError),
+    #[error("transaction {0} reverted")]
+    Reverted(B256),
+    #[error("failed to interact with the node database: {0}")]
+    Db(#[from] db_node::Error),
+    #[error("asset {0} has no contract address on this chain")]
+    AssetNotFound(Asset),
+    #[error("unsupported asset `{0}` for unit `{1}`")]
+    InvalidAssetForUnit(Asset, Unit),
+    #[error("invalid payment request json string: {0}")]
+    InvalidPaymentRequest(#[source] serde_json::Error),
+    #[error("failed to serialize calls: {0}")]
+    SerializeCalls(#[source] serde_json::Error),
+    #[error("the zero address can't be paid")]
+    ZeroAddressPayee,
+    #[error("failed to convert request values to nodes values: {0}")]
+    Conversion(#[from] AssetToUnitConversionError),
+    #[error("amount overflow")]
+    Overflow,
+    #[error("invalid withdraw order: {0}")]
+    WithdrawOrder(#[source] serde_json::Error),
+    #[error("invalid transaction hash `{0}`")]
+    InvalidTxHash(String),
+}
+
+/// The ERC20 contract of each asset supported on the chain
+#[derive(Debug, Clone, Default)]
+pub struct TokenAddresses(Arc<HashMap<Asset, Address>>);
+
+impl TokenAddresses {
+    pub fn new(addresses: HashMap<Asset, Address>) -> Self {
+        Self(Arc::new(addresses))
+    }
+
+    /// Resolve the symbols of the `assets` config table
+    fn from_config(assets: HashMap<String, Address>) -> Result<Self, Error> {
+        let addresses = assets
+            .into_iter()
+            .map(|(symbol, address)| {
+                Asset::from_str(&symbol)
+                    .map(|asset| (asset, address))
+                    .map_err(|_| Error::UnknownAsset(symbol))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(addresses))
+    }
+
+    pub fn address_of(&self, asset: Asset) -> Option<Address> {
+        self.0.get(&asset).copied()
+    }
+
+    pub fn asset_of(&self, address: Address) -> Option<Asset> {
+        self.0
+            .iter()
+            .find(|(_, token)| **token == address)
+            .map(|(asset, _)| *asset)
+    }
+}
+
+fn to_alloy_u256(value: primitive_types::U256) -> U256 {
+    U256::from_be_bytes(value.to_big_endian())
+}
+
+fn from_alloy_u256(value: U256) -> primitive_types::U256 {
+    primitive_types::U256::from_big_endian(&value.to_be_bytes::<32>())
+}
+
+#[derive(Debug, Clone)]
+pub struct EvmInvoiceId(B256);
+
+impl From<EvmInvoiceId> for [u8; 32] {
+    fn from(value: EvmInvoiceId) -> Self {
+        value.0.0
+    }
+}
+
+impl LowerHex for EvmInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        LowerHex::fmt(&self.0, f)
+    }
+}
+impl UpperHex for EvmInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        UpperHex::fmt(&self.0, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EvmLiquiditySource {
+    pub depositer: Depositer,
+    pub withdrawer: Withdrawer,
+    tokens: TokenAddresses,
+}
+
+impl EvmLiquiditySource {
+    /// Connect to the rpc node of the `[evm]` table, None if the config file has none
+    ///
+    /// A single node polls the logs of the invoice contract to mark the quotes paid.
+    pub async fn init(storage: SharedStorage, config_path: &Path) -> Result<Option<Self>, Error> {
+        let Some(config) = read_evm_config(config_path)? else {
+            return Ok(None);
+        };
+        let signer = PrivateKeySigner::from_str(
+            &std::env::var(CASHIER_PRIVATE_KEY_ENV_VAR)
+                .map_err(|e| Error::Env(CASHIER_PRIVATE_KEY_ENV_VAR, e))?,
+        )
+        .map_err(|_| Error::PrivateKey)?;
+
+        Self::connect(storage, config, signer).await.map(Some)
+    }
+
+    /// Connect to the configured rpc node, signing withdrawals with `signer`
+    pub async fn connect(
+        storage: SharedStorage,
+        config: EvmConfig,
+        signer: PrivateKeySigner,
+    ) -> Result<Self, Error> {
+        let tokens = TokenAddresses::from_config(config.assets)?;
+        let cashier_address = signer.address();
+
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(signer))
+            .connect_http(config.rpc_url)
+            .erased();
+        let rpc_chain_id = provider.get_chain_id().await?;
+        if rpc_chain_id != config.chain_id {
+            return Err(Error::ChainIdMismatch(config.chain_id, rpc_chain_id));
+        }
+
+        let indexer_config = indexer::IndexerConfig {
+            invoice_payment_contract_address: config.invoice_payment_contract_address,
+            cashier_address,
+            tokens: tokens.clone(),
+            starting_block: config.starting_block,
+            confirmations: config.confirmations,
+            poll_interval: Duration::from_secs(
+                config
+                    .poll_interval_secs
+                    .unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+            ),
+        };
+        let cloned_provider = provider.clone();
+        let cloned_storage = storage.clone();
+        let _handle = tokio::spawn(run_as_leader(storage.clone(), "evm-indexer", move || {
+            indexer::run(
+                cloned_storage.clone(),
+                cloned_provider.clone(),
+                indexer_config.clone(),
+            )
+        }));
+
+        Ok(Self {
+            depositer: Depositer::new(
+                config.invoice_payment_contract_address,
+                cashier_address,
+                tokens.clone(),
+            ),
+            withdrawer: Withdrawer::new(
+                storage,
+                provider,
+                config.invoice_payment_contract_address,
+                tokens.clone(),
+            ),
+            tokens,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl liquidity_source::LiquiditySource for EvmLiquiditySource {
+    type Error = std::convert::Infallible;
+    type Depositer = Depositer;
+    type Withdrawer = Withdrawer;
+    type InvoiceId = EvmInvoiceId;
+    type Unit = Unit;
+
+    fn depositer(&self) -> Depositer {
+        self.depositer.clone()
+    }
+
+    fn withdrawer(&self) -> Withdrawer {
+        self.withdrawer.clone()
+    }
+
+    /// The registered units whose asset has a token address on the chain
+    fn units(&self) -> Vec<Unit> {
+        starknet_types::registry::units()
+            .into_iter()
+            .filter(|unit| self.tokens.address_of(unit.asset()).is_some())
+            .collect()
+    }
+
+    async fn compute_invoice_id(
+        &self,
+        quote_id: uuid::Uuid,
+        expiry: u64,
+    ) -> Result<Self::InvoiceId, Self::Error> {
+        Ok(EvmInvoiceId(compute_invoice_id(
+            hash_quote_id(quote_id),
+            expiry,
+        )))
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::{DynProvider, Provider},
+    rpc::types::TransactionReceipt,
+};
+use db_node::{SharedStorage, leader::run_as_leader};
+use liquidity_source::WithdrawInterface;
+use num_traits::CheckedAdd;
+use nuts::{Amount, nut05::MeltQuoteState};
+use serde::{Deserialize, Serialize};
+use starknet_types::{Asset, Unit};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    Error, EvmInvoiceId, TokenAddresses,
+    contract::{IERC20, IInvoicePayment, hash_quote_id},
+    from_alloy_u256,
+};
+
+/// The method the orders are queued under
+const METHOD: &str = "evm";
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Bound on the orders looked at per run
+const WITHDRAWALS_LIMIT: i64 = 100;
+/// A payment not mined this long after its quote expired never will be, the contract checking the expiry
+const UNMINED_PAYMENT_GRACE_SECONDS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeltPaymentRequest {
+    pub payee: Address,
+    pub asset: Asset,
+    /// Including the on-chain precision of the asset
+    pub amount: U256,
+}
+
+/// The payment of a melt quote, queued in the `melt_withdrawal` table
+#[derive(Debug, Serialize, Deserialize)]
+struct WithdrawOrder {
+    quote_id_hash: B256,
+    expiry: u64,
+    token_address: Address,
+    amount: U256,
+    payee: Address,
+}
+
+#[derive(Debug, Clone)]
+pub struct Withdrawer {
+    storage: SharedStorage,
+    tokens: TokenAddresses,
+    new_order: Arc<Notify>,
+}
+
+impl Withdrawer {
+    /// Spawn the task paying the queued orders with the wallet of `provider`
+    ///
+    /// A single node pays them, the others only queue theirs.
+    pub fn new(
+        storage: SharedStorage,
+        provider: DynProvider,
+        invoice_payment_contract_address: Address,
+        tokens: TokenAddresses,
+    ) -> Self {
+        let new_order = Arc::new(Notify::new());
+
+        let cloned_storage = storage.clone();
+        let cloned_new_order = new_order.clone();
+        let _join_handle = tokio::spawn(run_as_leader(
+            storage.clone(),
+            "evm-withdrawals",
+            move || {
+                process_withdraw_orders(
+                    cloned_storage.clone(),
+                    provider.clone(),
+                    invoice_payment_contract_address,
+                    cloned_new_order.clone(),
+                )
+            },
+        ));
+
+        Self {
+            storage,
+            tokens,
+            new_order,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WithdrawInterface for Withdrawer {
+    type Error = Error;
+    type Request = MeltPaymentRequest;
+    type Unit = Unit;
+    type InvoiceId = EvmInvoiceId;
+
+    fn deserialize_payment_request(&self, raw_json_string: &str) -> Result<Self::Request, Error> {
+        let pr = serde_json::from_str::<Self::Request>(raw_json_string)
+            .map_err(Error::InvalidPaymentRequest)?;
+
+        if pr.payee == Address::ZERO {
+            return Err(Error::ZeroAddressPayee);
+        }
+
+        Ok(pr)
+    }
+
+    async fn compute_total_amount_expected(
+        &self,
+        request: Self::Request,
+        unit: Unit,
+        fee: Amount,
+    ) -> Result<Amount, Self::Error> {
+        if !unit.is_asset_supported(request.asset) {
+            return Err(Error::InvalidAssetForUnit(request.asset, unit));
+        }
+
+        let (amount, rem) = request
+            .asset
+            .convert_to_amount_of_unit(from_alloy_u256(request.amount), unit)?;
+
+        if fee == Amount::ZERO {
+            if rem.is_zero() {
+                Ok(amount)
+            } else {
+                amount.checked_add(&Amount::ONE).ok_or(Error::Overflow)
+            }
+        } else {
+            amount.checked_add(&fee).ok_or(Error::Overflow)
+        }
+    }
+
+    /// Queue the payment, the quote is marked paid once the indexer sees its `Remittance` event
+    async fn proceed_to_payment(
+        &mut self,
+        quote_id: Uuid,
+        melt_payment_request: MeltPaymentRequest,
+        expiry: u64,
+    ) -> Result<MeltQuoteState, Error> {
+        let token_address = self
+            .tokens
+            .address_of(melt_payment_request.asset)
+            .ok_or(Error::AssetNotFound(melt_payment_request.asset))?;
+        let order = WithdrawOrder {
+            quote_id_hash: hash_quote_id(quote_id),
+            expiry,
+            token_address,
+            amount: melt_payment_request.amount,
+            payee: melt_payment_request.payee,
+        };
+
+        self.storage
+            .acquire()
+            .await?
+            .insert_melt_withdrawal(
+                quote_id,
+                METHOD,
+                &serde_json::to_string(&order).map_err(Error::WithdrawOrder)?,
+            )
+            .await?;
+        self.new_order.notify_one();
+
+        Ok(MeltQuoteState::Pending)
+    }
+}
+
+/// Pay the queued orders one at a time, so that the nonces of the cashier transactions are used in sequence
+///
+/// An order is retried until its payment succeeds, or until its quote expires, the melt being then reverted.
+async fn process_withdraw_orders(
+    storage: SharedStorage,
+    provider: DynProvider,
+    invoice_payment_contract_address: Address,
+    new_order: Arc<Notify>,
+) {
+    loop {
+        if let Err(err) =
+            process_queued_orders(&storage, &provider, invoice_payment_contract_address).await
+        {
+            error!(name: "evm-withdrawals", name = "evm-withdrawals", error = %err);
+        }
+        // The orders queued by the other nodes are only seen on the next retry
+        let _ = tokio::time::timeout(RETRY_INTERVAL, new_order.notified()).await;
+    }
+}
+
+async fn process_queued_orders(
+    storage: &SharedStorage,
+    provider: &DynProvider,
+    invoice_payment_contract_address: Address,
+) -> Result<(), Error> {
+    let withdrawals = storage
+        .acquire()
+        .await?
+        .list_melt_withdrawals(METHOD, WITHDRAWALS_LIMIT)
+        .await?;
+
+    for withdrawal in withdrawals {
+        let quote_id = withdrawal.quote_id;
+        let order: WithdrawOrder =
+            serde_json::from_str(&withdrawal.payment).map_err(Error::WithdrawOrder)?;
+
+        // Marked paid by the indexer
+        if storage
+            .acquire()
+            .await?
+            .get_melt_quote_state(quote_id)
+            .await?
+            != MeltQuoteState::Pending
+        {
+            storage
+                .acquire()
+                .await?
+                .delete_melt_withdrawal(quote_id)
+                .await?;
+            continue;
+        }
+
+        if let Some(tx_hash) = withdrawal.tx_hash {
+            let tx_hash = B256::from_str(&tx_hash).map_err(|_| Error::InvalidTxHash(tx_hash))?;
+            match provider.get_transaction_receipt(tx_hash).await? {
+                Some(receipt) if receipt.status() => {
+                    storage
+                        .acquire()
+                        .await?
+                        .delete_melt_withdrawal(quote_id)
+                        .await?;
+                }
+                // Sent again on the next run
+                Some(_) => {
+                    storage
+                        .acquire()
+                        .await?
+                        .set_melt_withdrawal_tx_hash(quote_id, None)
+                        .await?;
+                    error!(name: "withdraw-tx-result", name = "withdraw-tx-result", %quote_id, %tx_hash, status = "reverted");
+                }
+                // The contract rejects the payments of expired quotes
+                None if order.expiry + UNMINED_PAYMENT_GRACE_SECONDS < unix_time() => {
+                    revert(storage, quote_id, "payment never mined").await?
+                }
+                None => {}
+            }
+            continue;
+        }
+
+        if order.expiry < unix_time() {
+            revert(storage, quote_id, "quote expired before being paid").await?;
+            continue;
+        }
+
+        // A failed order doesn't hold back the next ones
+        match pay(
+            storage,
+            provider,
+            invoice_payment_contract_address,
+            quote_id,
+            &order,
+        )
+        .await
+        {
+            Ok(tx_hash) => {
+                info!(name: "withdraw-tx-result", name = "withdraw-tx-result", %quote_id, %tx_hash, status = "succeeded")
+            }
+            Err(err) => {
+                error!(name: "withdraw-tx-result", name = "withdraw-tx-result", %quote_id, error = %err, status = "failed")
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn pay(
+    storage: &SharedStorage,
+    provider: &DynProvider,
+    invoice_payment_contract_address: Address,
+    quote_id: Uuid,
+    order: &WithdrawOrder,
+) -> Result<B256, Error> {
+    let token = IERC20::new(order.token_address, provider.clone());
+    check_succeeded(
+        token
+            .approve(invoice_payment_contract_address, order.amount)
+            .send()
+            .await?
+            .get_receipt()
+            .await?,
+    )?;
+
+    let pending_tx = IInvoicePayment::new(invoice_payment_contract_address, provider.clone())
+        .payInvoice(
+            order.quote_id_hash,
+            order.expiry,
+            order.token_address,
+            order.amount,
+            order.payee,
+        )
+        .send()
+        .await?;
+    // Recorded before waiting for the receipt, so that the order is not paid twice
+    storage
+        .acquire()
+        .await?
+        .set_melt_withdrawal_tx_hash(quote_id, Some(&pending_tx.tx_hash().to_string()))
+        .await?;
+
+    let receipt = pending_tx.get_receipt().await?;
+    let mut conn = storage.acquire().await?;
+    if receipt.status() {
+        conn.delete_melt_withdrawal(quote_id).await?;
+    } else {
+        conn.set_melt_withdrawal_tx_hash(quote_id, None).await?;
+    }
+
+    check_succeeded(receipt)
+}
+
+/// Unspend the inputs of a quote that can't be paid anymore
+async fn revert(storage: &SharedStorage, quote_id: Uuid, reason: &str) -> Result<(), Error> {
+    let mut tx = storage.begin().await?;
+    tx.delete_melt_withdrawal(quote_id).await?;
+    let reverted = tx.revert_melt_quote(quote_id).await?;
+    if reverted {
+        // The event the node records when the payment fails during the melt
+        tx.insert_audit_event("melt_reverted", &format!(r#"{{"quote_id":"{quote_id}"}}"#))
+            .await?;
+    }
+    tx.commit().await?;
+
+    if reverted {
+        warn!(
+            name: "melt-quote-reverted",
+            name = "melt-quote-reverted",
+            %quote_id,
+            reason,
+        );
+    }
+
+    Ok(())
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn check_succeeded(receipt: TransactionReceipt) -> Result<B256, Error> {
+    if receipt.status() {
+        Ok(receipt.transaction_hash)
+    } else {
+        Err(Error::Reverted(receipt.transaction_hash))
+    }
+}
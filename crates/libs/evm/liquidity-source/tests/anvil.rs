@@ -0,0 +1,274 @@
+//! Deposit and withdraw against a local anvil node
+//!
+//! Requires `anvil` in the PATH, and the contracts built with `forge build` in `contracts/evm-invoice`:
+//! `cargo test -p evm-liquidity-source --test anvil -- --ignored`
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    network::{EthereumWallet, TransactionBuilder},
+    node_bindings::{Anvil, AnvilInstance},
+    primitives::{Address, Bytes, U256},
+    providers::{DynProvider, Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use db_node::{SharedStorage, Storage, sqlite::SqliteStorage};
+use evm_liquidity_source::{Call, EvmConfig, EvmLiquiditySource, IERC20, MeltPaymentRequest};
+use liquidity_source::{DepositInterface, LiquiditySource, WithdrawInterface};
+use nuts::{Amount, nut04::MintQuoteState, nut05::MeltQuoteState};
+use starknet_types::{Asset, Unit};
+use test_utils::liquidity_source::{
+    unix_time, wait_for_melt_quote_state, wait_for_mint_quote_state,
+};
+use uuid::Uuid;
+
+const CONTRACTS_OUT_DIR: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../../../contracts/evm-invoice/out"
+);
+
+fn read_bytecode(contract: &str) -> Bytes {
+    let path = format!("{CONTRACTS_OUT_DIR}/{contract}.sol/{contract}.json");
+    let artifact: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&path)
+            .expect("contracts must be built with `forge build` in contracts/evm-invoice"),
+    )
+    .unwrap();
+
+    artifact["bytecode"]["object"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+async fn deploy(provider: &impl Provider, contract: &str) -> Address {
+    provider
+        .send_transaction(TransactionRequest::default().with_deploy_code(read_bytecode(contract)))
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap()
+        .contract_address
+        .unwrap()
+}
+
+/// A fresh chain with the invoice payment contract and a token held by the user,
+/// indexed by a liquidity source whose cashier is another account
+struct Setup {
+    // Stops the node on drop
+    _anvil: AnvilInstance,
+    user_provider: DynProvider,
+    user_address: Address,
+    usdc: Address,
+    storage: SharedStorage,
+    source: EvmLiquiditySource,
+}
+
+async fn setup() -> Setup {
+    let anvil = Anvil::new().block_time(1).try_spawn().unwrap();
+    let cashier = PrivateKeySigner::from(anvil.keys()[0].clone());
+    let user = PrivateKeySigner::from(anvil.keys()[1].clone());
+    let user_address = user.address();
+    let user_provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(user))
+        .connect_http(anvil.endpoint_url())
+        .erased();
+
+    // The user deploys the token, so it holds the whole supply
+    let invoice_payment = deploy(&user_provider, "InvoicePayment").await;
+    let usdc = deploy(&user_provider, "TestToken").await;
+
+    let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+    storage.run_migrations().await.unwrap();
+    let storage: SharedStorage = Arc::new(storage);
+
+    let source = EvmLiquiditySource::connect(
+        storage.clone(),
+        EvmConfig {
+            chain_id: anvil.chain_id(),
+            rpc_url: anvil.endpoint_url(),
+            invoice_payment_contract_address: invoice_payment,
+            starting_block: 0,
+            confirmations: 0,
+            poll_interval_secs: Some(1),
+            assets: HashMap::from([("usdc".to_string(), usdc)]),
+        },
+        cashier,
+    )
+    .await
+    .unwrap();
+
+    Setup {
+        _anvil: anvil,
+        user_provider,
+        user_address,
+        usdc,
+        storage,
+        source,
+    }
+}
+
+/// Create a mint quote whose invoice expires on chain at `expiry`, and its expiry in the node at `quote_expiry`
+async fn insert_mint_quote(
+    setup: &Setup,
+    amount: Amount,
+    expiry: u64,
+    quote_expiry: u64,
+) -> (Uuid, Vec<Call>) {
+    let quote_id = Uuid::new_v4();
+    let (invoice_id, payload) = setup
+        .source
+        .depositer()
+        .generate_deposit_payload(quote_id, Unit::CENT_USDC, amount, expiry)
+        .await
+        .unwrap();
+    setup
+        .storage
+        .acquire()
+        .await
+        .unwrap()
+        .insert_mint_quote(
+            quote_id,
+            invoice_id.into(),
+            Unit::CENT_USDC.as_str(),
+            amount,
+            &payload,
+            quote_expiry,
+        )
+        .await
+        .unwrap();
+
+    (quote_id, serde_json::from_str(&payload).unwrap())
+}
+
+/// The user sends the calls of a deposit payload
+async fn send_calls(provider: &impl Provider, calls: Vec<Call>) {
+    for call in calls {
+        let receipt = provider
+            .send_transaction(
+                TransactionRequest::default()
+                    .with_to(call.to)
+                    .with_input(call.data),
+            )
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        assert!(receipt.status());
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires anvil and the contracts built with forge"]
+async fn deposit_and_withdraw() {
+    let setup = setup().await;
+    let Setup {
+        user_provider,
+        user_address,
+        usdc,
+        storage,
+        source,
+        ..
+    } = &setup;
+    let user_address = *user_address;
+    let usdc_token = IERC20::new(*usdc, user_provider.clone());
+    let initial_balance = usdc_token.balanceOf(user_address).call().await.unwrap();
+    let expiry = unix_time() + 600;
+
+    // Mint 10 usdc, the user sending the calls of the deposit payload
+    let (mint_quote_id, calls) =
+        insert_mint_quote(&setup, Amount::from(1_000u64), expiry, expiry).await;
+    send_calls(user_provider, calls).await;
+    wait_for_mint_quote_state(storage, mint_quote_id, MintQuoteState::Paid).await;
+
+    // Melt 5 usdc, paid back to the user by the cashier
+    let melt_quote_id = Uuid::new_v4();
+    let raw_request = serde_json::to_string(&MeltPaymentRequest {
+        payee: user_address,
+        asset: Asset::USDC,
+        amount: U256::from(5_000_000u64),
+    })
+    .unwrap();
+    let mut withdrawer = source.withdrawer();
+    let request = withdrawer
+        .deserialize_payment_request(&raw_request)
+        .unwrap();
+    let melt_amount = withdrawer
+        .compute_total_amount_expected(request.clone(), Unit::CENT_USDC, Amount::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(melt_amount, Amount::from(500u64));
+
+    let melt_invoice_id: [u8; 32] = source
+        .compute_invoice_id(melt_quote_id, expiry)
+        .await
+        .unwrap()
+        .into();
+    storage
+        .acquire()
+        .await
+        .unwrap()
+        .insert_melt_quote(
+            melt_quote_id,
+            &melt_invoice_id,
+            Unit::CENT_USDC.as_str(),
+            melt_amount,
+            Amount::ZERO,
+            &raw_request,
+            expiry,
+        )
+        .await
+        .unwrap();
+    // The node marks the quote pending before paying it, the withdrawer drops the orders of the others
+    storage
+        .acquire()
+        .await
+        .unwrap()
+        .set_melt_quote_state(melt_quote_id, MeltQuoteState::Pending)
+        .await
+        .unwrap();
+    assert_eq!(
+        withdrawer
+            .proceed_to_payment(melt_quote_id, request, expiry)
+            .await
+            .unwrap(),
+        MeltQuoteState::Pending
+    );
+    wait_for_melt_quote_state(storage, melt_quote_id, MeltQuoteState::Paid).await;
+
+    let final_balance = usdc_token.balanceOf(user_address).call().await.unwrap();
+    assert_eq!(initial_balance - final_balance, U256::from(5_000_000u64));
+}
+
+#[tokio::test]
+#[ignore = "requires anvil and the contracts built with forge"]
+async fn deposit_after_quote_expiry() {
+    let setup = setup().await;
+
+    // The invoice can still be paid on chain, but the quote has expired in the node
+    let (quote_id, calls) = insert_mint_quote(
+        &setup,
+        Amount::from(1_000u64),
+        unix_time() + 600,
+        unix_time() - 1,
+    )
+    .await;
+    assert_eq!(
+        setup
+            .storage
+            .acquire()
+            .await
+            .unwrap()
+            .expire_unpaid_mint_quotes()
+            .await
+            .unwrap(),
+        1
+    );
+
+    // The funds were received, they can still be minted
+    send_calls(&setup.user_provider, calls).await;
+    wait_for_mint_quote_state(&setup.storage, quote_id, MintQuoteState::Paid).await;
+}
@@ -0,0 +1,13 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(false)
+        .compile_protos(
+            &[
+                "../../../../proto/lnd/lightning.proto",
+                "../../../../proto/lnd/router.proto",
+            ],
+            &["../../../../proto/lnd"],
+        )?;
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! [`LightningBackend`] running in process, for tests
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+
+use bitcoin::{
+    hashes::{Hash, sha256},
+    secp256k1::{Secp256k1, SecretKey},
+};
+use futures::{StreamExt, stream::BoxStream};
+use lightning_invoice::{Bolt11Invoice, Currency, InvoiceBuilder, PaymentSecret};
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use super::{BackendError, CreatedInvoice, LightningBackend, PaymentOutcome, SettledInvoice};
+
+/// Signs the invoices of every fake node
+const NODE_SECRET_KEY: [u8; 32] = [0x42; 32];
+const MIN_FINAL_CLTV_EXPIRY_DELTA: u64 = 144;
+const SETTLEMENTS_CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Default)]
+struct State {
+    /// Amount of the invoices created by the node, by payment hash
+    invoices: HashMap<[u8; 32], u64>,
+    preimages: HashMap<[u8; 32], [u8; 32]>,
+    settled: Vec<SettledInvoice>,
+    paid: Vec<String>,
+    /// Outcome of the payments sent by the node, by payment hash
+    payments: HashMap<[u8; 32], PaymentOutcome>,
+    fail_payments: bool,
+}
+
+/// A Lightning node settling its invoices on demand and succeeding every payment, unless told otherwise
+///
+/// Its invoices are valid regtest bolt11, so they can be handed to the code paying them.
+#[derive(Debug, Clone)]
+pub struct FakeNode {
+    state: Arc<Mutex<State>>,
+    settlements: broadcast::Sender<SettledInvoice>,
+}
+
+impl Default for FakeNode {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            settlements: broadcast::channel(SETTLEMENTS_CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl FakeNode {
+    /// Settle an invoice created by this node, as if it had been paid in full
+    pub fn settle(&self, payment_hash: [u8; 32]) -> Result<SettledInvoice, BackendError> {
+        let mut state = self.state.lock();
+        let amount_paid_msat = *state
+            .invoices
+            .get(&payment_hash)
+            .ok_or_else(|| BackendError::UnknownInvoice(hex::encode(payment_hash)))?;
+        let settled = SettledInvoice {
+            payment_hash,
+            amount_paid_msat,
+            settle_index: state.settled.len() as u64 + 1,
+        };
+        state.settled.push(settled.clone());
+        // Having no subscriber is fine, the settlement can still be replayed
+        let _ = self.settlements.send(settled.clone());
+
+        Ok(settled)
+    }
+
+    /// The bolt11 invoices paid by this node, in order
+    pub fn paid_invoices(&self) -> Vec<String> {
+        self.state.lock().paid.clone()
+    }
+
+    /// Fail the payments sent from now on, as if no route was found, or succeed them again
+    pub fn fail_payments(&self, fail: bool) {
+        self.state.lock().fail_payments = fail;
+    }
+}
+
+#[async_trait::async_trait]
+impl LightningBackend for FakeNode {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+        expiry: Duration,
+    ) -> Result<CreatedInvoice, BackendError> {
+        let preimage: [u8; 32] = rand::random();
+        let payment_hash = sha256::Hash::hash(&preimage);
+        let secret_key = SecretKey::from_slice(&NODE_SECRET_KEY).expect("valid secret key");
+
+        let invoice = InvoiceBuilder::new(Currency::Regtest)
+            .description(description)
+            .payment_hash(payment_hash)
+            .payment_secret(PaymentSecret(rand::random()))
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(MIN_FINAL_CLTV_EXPIRY_DELTA)
+            .amount_milli_satoshis(amount_msat)
+            .expiry_time(expiry)
+            .build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &secret_key))
+            .map_err(|e| BackendError::InvoiceCreation(e.to_string()))?;
+
+        let payment_hash = payment_hash.to_byte_array();
+        let mut state = self.state.lock();
+        state.invoices.insert(payment_hash, amount_msat);
+        state.preimages.insert(payment_hash, preimage);
+
+        Ok(CreatedInvoice {
+            payment_hash,
+            bolt11: invoice.to_string(),
+        })
+    }
+
+    async fn pay_invoice(
+        &self,
+        bolt11: &str,
+        _max_fee_msat: u64,
+    ) -> Result<PaymentOutcome, BackendError> {
+        let invoice = Bolt11Invoice::from_str(bolt11)
+            .map_err(|_| BackendError::InvalidResponse("invalid bolt11 invoice"))?;
+        let payment_hash = invoice.payment_hash().to_byte_array();
+
+        let mut state = self.state.lock();
+        let outcome = if state.fail_payments {
+            PaymentOutcome::Failed("FAILURE_REASON_NO_ROUTE".to_string())
+        } else {
+            state.paid.push(bolt11.to_string());
+            PaymentOutcome::Succeeded {
+                fee_msat: 0,
+                // The preimage of an invoice of another node is not known, any will do
+                preimage: state
+                    .preimages
+                    .get(&payment_hash)
+                    .copied()
+                    .unwrap_or_else(rand::random),
+            }
+        };
+        state.payments.insert(payment_hash, outcome.clone());
+
+        Ok(outcome)
+    }
+
+    async fn track_payment(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<PaymentOutcome>, BackendError> {
+        Ok(self.state.lock().payments.get(&payment_hash).cloned())
+    }
+
+    async fn lookup_invoice(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<SettledInvoice>, BackendError> {
+        Ok(self
+            .state
+            .lock()
+            .settled
+            .iter()
+            .find(|settled| settled.payment_hash == payment_hash)
+            .cloned())
+    }
+
+    async fn settled_invoices(
+        &self,
+        settle_index: u64,
+    ) -> Result<BoxStream<'static, Result<SettledInvoice, BackendError>>, BackendError> {
+        let state = self.state.lock();
+        // Subscribe while holding the lock, so that no settlement falls between the backlog and the live ones
+        let live = BroadcastStream::new(self.settlements.subscribe())
+            .filter_map(|settled| async move { settled.ok().map(Ok) });
+        let backlog: Vec<_> = match settle_index {
+            0 => Vec::new(),
+            _ => state
+                .settled
+                .iter()
+                .filter(|settled| settled.settle_index > settle_index)
+                .cloned()
+                .map(Ok)
+                .collect(),
+        };
+
+        Ok(futures::stream::iter(backlog).chain(live).boxed())
+    }
+}
@@ -0,0 +1,269 @@
+//! [`LightningBackend`] talking to an LND node over gRPC
+use std::{path::PathBuf, time::Duration};
+
+use futures::{StreamExt, stream::BoxStream};
+use serde::Deserialize;
+use tonic::{
+    Code, Request, Status,
+    metadata::AsciiMetadataValue,
+    service::{Interceptor, interceptor::InterceptedService},
+    transport::Channel,
+};
+use url::Url;
+
+use super::{BackendError, CreatedInvoice, LightningBackend, PaymentOutcome, SettledInvoice};
+
+mod lnrpc {
+    tonic::include_proto!("lnrpc");
+}
+mod routerrpc {
+    tonic::include_proto!("routerrpc");
+}
+
+use lnrpc::{invoice::InvoiceState, lightning_client::LightningClient, payment::PaymentStatus};
+use routerrpc::router_client::RouterClient;
+
+/// How long LND keeps looking for a route before failing a payment
+const PAYMENT_TIMEOUT_SECONDS: i32 = 60;
+
+/// The `[lightning.lnd]` table of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LndConfig {
+    /// e.g. `https://localhost:10009`
+    pub grpc_url: Url,
+    /// The self-signed certificate generated by LND, `tls.cert` in its data directory
+    pub tls_cert_path: PathBuf,
+    /// A macaroon allowed to create invoices and send payments, e.g. `admin.macaroon`
+    pub macaroon_path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+struct MacaroonInterceptor(AsciiMetadataValue);
+
+impl Interceptor for MacaroonInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        request.metadata_mut().insert("macaroon", self.0.clone());
+        Ok(request)
+    }
+}
+
+type AuthenticatedChannel = InterceptedService<Channel, MacaroonInterceptor>;
+
+#[derive(Debug, Clone)]
+pub struct LndBackend {
+    lightning: LightningClient<AuthenticatedChannel>,
+    router: RouterClient<AuthenticatedChannel>,
+}
+
+impl LndBackend {
+    pub async fn connect(config: &LndConfig) -> Result<Self, BackendError> {
+        let macaroon = tokio::fs::read(&config.macaroon_path)
+            .await
+            .map_err(|e| BackendError::ReadFile("macaroon", e))?;
+        let interceptor = MacaroonInterceptor(
+            AsciiMetadataValue::try_from(hex::encode(macaroon))
+                .expect("hex is a valid metadata value"),
+        );
+
+        let mut connector = openssl::ssl::SslConnector::builder(openssl::ssl::SslMethod::tls())?;
+        // The certificate is self-signed, so it is trusted as its own authority
+        connector.set_ca_file(&config.tls_cert_path)?;
+        connector.set_alpn_protos(tonic_tls::openssl::ALPN_H2_WIRE)?;
+        let ssl_conn = connector.build();
+
+        let invalid_url = || BackendError::Url(config.grpc_url.to_string());
+        let domain = config.grpc_url.host_str().ok_or_else(invalid_url)?;
+        let socket_address = *config
+            .grpc_url
+            .socket_addrs(|| None)
+            .map_err(|_| invalid_url())?
+            .first()
+            .ok_or_else(invalid_url)?;
+        let uri: tonic::transport::Uri = socket_address
+            .to_string()
+            .parse()
+            .map_err(|_| invalid_url())?;
+
+        let connector = tonic_tls::openssl::TlsConnector::new(uri, ssl_conn, domain.to_string());
+        let channel = tonic_tls::new_endpoint()
+            .connect_with_connector(connector)
+            .await
+            .map_err(tonic_tls::Error::from)?;
+
+        Ok(Self {
+            lightning: LightningClient::with_interceptor(channel.clone(), interceptor.clone()),
+            router: RouterClient::with_interceptor(channel, interceptor),
+        })
+    }
+}
+
+/// The outcome of a payment, None while it is not final
+fn payment_outcome(payment: &lnrpc::Payment) -> Result<Option<PaymentOutcome>, BackendError> {
+    match payment.status() {
+        PaymentStatus::Succeeded => Ok(Some(PaymentOutcome::Succeeded {
+            fee_msat: u64::try_from(payment.fee_msat).unwrap_or_default(),
+            preimage: hex::decode(&payment.payment_preimage)
+                .ok()
+                .and_then(|preimage| preimage.try_into().ok())
+                .ok_or(BackendError::InvalidResponse(
+                    "payment preimage is not 32 bytes long",
+                ))?,
+        })),
+        PaymentStatus::Failed => Ok(Some(PaymentOutcome::Failed(
+            payment.failure_reason().as_str_name().to_string(),
+        ))),
+        PaymentStatus::Unknown | PaymentStatus::InFlight | PaymentStatus::Initiated => Ok(None),
+    }
+}
+
+fn settled_invoice(invoice: lnrpc::Invoice) -> Result<SettledInvoice, BackendError> {
+    Ok(SettledInvoice {
+        payment_hash: invoice
+            .r_hash
+            .try_into()
+            .map_err(|_| BackendError::InvalidResponse("payment hash is not 32 bytes long"))?,
+        amount_paid_msat: u64::try_from(invoice.amt_paid_msat)
+            .map_err(|_| BackendError::InvalidResponse("negative amount paid"))?,
+        settle_index: invoice.settle_index,
+    })
+}
+
+#[async_trait::async_trait]
+impl LightningBackend for LndBackend {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+        expiry: Duration,
+    ) -> Result<CreatedInvoice, BackendError> {
+        let response = self
+            .lightning
+            .clone()
+            .add_invoice(lnrpc::Invoice {
+                memo: description,
+                value_msat: i64::try_from(amount_msat)
+                    .map_err(|_| BackendError::AmountTooBig(amount_msat))?,
+                expiry: i64::try_from(expiry.as_secs()).unwrap_or(i64::MAX),
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+
+        Ok(CreatedInvoice {
+            payment_hash: response
+                .r_hash
+                .try_into()
+                .map_err(|_| BackendError::InvalidResponse("payment hash is not 32 bytes long"))?,
+            bolt11: response.payment_request,
+        })
+    }
+
+    async fn pay_invoice(
+        &self,
+        bolt11: &str,
+        max_fee_msat: u64,
+    ) -> Result<PaymentOutcome, BackendError> {
+        let mut updates = self
+            .router
+            .clone()
+            .send_payment_v2(routerrpc::SendPaymentRequest {
+                payment_request: bolt11.to_string(),
+                timeout_seconds: PAYMENT_TIMEOUT_SECONDS,
+                fee_limit_msat: i64::try_from(max_fee_msat).unwrap_or(i64::MAX),
+            })
+            .await?
+            .into_inner();
+
+        // LND streams every state change of the payment, until a final one
+        while let Some(payment) = updates.message().await? {
+            if let Some(outcome) = payment_outcome(&payment)? {
+                return Ok(outcome);
+            }
+        }
+
+        Ok(PaymentOutcome::InFlight)
+    }
+
+    async fn track_payment(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<PaymentOutcome>, BackendError> {
+        // A payment LND never initiated is reported as not found, by the call or the stream
+        let not_found = |status: &Status| status.code() == Code::NotFound;
+        let mut updates = match self
+            .router
+            .clone()
+            .track_payment_v2(routerrpc::TrackPaymentRequest {
+                payment_hash: payment_hash.to_vec(),
+                no_inflight_updates: false,
+            })
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            Err(status) if not_found(&status) => return Ok(None),
+            Err(status) => return Err(status.into()),
+        };
+
+        // The first update is the current state of the payment
+        match updates.message().await {
+            Ok(Some(payment)) => Ok(Some(
+                payment_outcome(&payment)?.unwrap_or(PaymentOutcome::InFlight),
+            )),
+            Ok(None) => Err(BackendError::InvalidResponse("empty payment stream")),
+            Err(status) if not_found(&status) => Ok(None),
+            Err(status) => Err(status.into()),
+        }
+    }
+
+    async fn lookup_invoice(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<SettledInvoice>, BackendError> {
+        let invoice = match self
+            .lightning
+            .clone()
+            .lookup_invoice(lnrpc::PaymentHash {
+                r_hash: payment_hash.to_vec(),
+            })
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(status.into()),
+        };
+
+        match invoice.state() {
+            InvoiceState::Settled => settled_invoice(invoice).map(Some),
+            InvoiceState::Open | InvoiceState::Canceled | InvoiceState::Accepted => Ok(None),
+        }
+    }
+
+    async fn settled_invoices(
+        &self,
+        settle_index: u64,
+    ) -> Result<BoxStream<'static, Result<SettledInvoice, BackendError>>, BackendError> {
+        let invoices = self
+            .lightning
+            .clone()
+            .subscribe_invoices(lnrpc::InvoiceSubscription {
+                add_index: 0,
+                settle_index,
+            })
+            .await?
+            .into_inner();
+
+        // Newly added invoices are streamed too
+        Ok(invoices
+            .filter_map(|invoice| async move {
+                match invoice {
+                    Ok(invoice) if invoice.state() == InvoiceState::Settled => {
+                        Some(settled_invoice(invoice))
+                    }
+                    Ok(_) => None,
+                    Err(status) => Some(Err(status.into())),
+                }
+            })
+            .boxed())
+    }
+}
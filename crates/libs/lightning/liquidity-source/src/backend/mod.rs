@@ -0,0 +1,98 @@
+//! The Lightning node the liquidity source creates and pays invoices with
+mod fake;
+mod lnd;
+
+pub use fake::FakeNode;
+pub use lnd::{LndBackend, LndConfig};
+
+use std::time::Duration;
+
+use futures::stream::BoxStream;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("failed to read {0}: {1}")]
+    ReadFile(&'static str, #[source] std::io::Error),
+    #[error("failed to configure tls: {0}")]
+    Tls(#[from] openssl::error::ErrorStack),
+    #[error("invalid lnd grpc url: {0}")]
+    Url(String),
+    #[error("failed to connect to the lightning node: {0}")]
+    Connect(#[from] tonic_tls::Error),
+    #[error("lightning node rpc failed: {0}")]
+    Rpc(#[from] tonic::Status),
+    #[error("amount of {0} msat is too big for the lightning node")]
+    AmountTooBig(u64),
+    #[error("failed to create invoice: {0}")]
+    InvoiceCreation(String),
+    #[error("invalid response from the lightning node: {0}")]
+    InvalidResponse(&'static str),
+    #[error("unknown invoice with payment hash {0}")]
+    UnknownInvoice(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatedInvoice {
+    pub payment_hash: [u8; 32],
+    /// The bolt11 encoded invoice, handed to the payer
+    pub bolt11: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettledInvoice {
+    pub payment_hash: [u8; 32],
+    pub amount_paid_msat: u64,
+    /// Increases with each invoice settled by the node
+    pub settle_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentOutcome {
+    Succeeded {
+        fee_msat: u64,
+        /// Proves that the payee was paid
+        preimage: [u8; 32],
+    },
+    /// The payment was sent but its outcome is not known yet
+    InFlight,
+    Failed(String),
+}
+
+/// A Lightning node able to receive and send payments
+#[async_trait::async_trait]
+pub trait LightningBackend: std::fmt::Debug + Send + Sync {
+    /// Create an invoice of `amount_msat`, payable until `expiry` has elapsed
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+        expiry: Duration,
+    ) -> Result<CreatedInvoice, BackendError>;
+
+    /// Pay a bolt11 invoice, spending at most `max_fee_msat` in routing fees
+    async fn pay_invoice(
+        &self,
+        bolt11: &str,
+        max_fee_msat: u64,
+    ) -> Result<PaymentOutcome, BackendError>;
+
+    /// The outcome of a payment sent by the node, None if it never sent one for `payment_hash`
+    async fn track_payment(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<PaymentOutcome>, BackendError>;
+
+    /// The invoice of `payment_hash` if it was created by the node and is settled
+    async fn lookup_invoice(
+        &self,
+        payment_hash: [u8; 32],
+    ) -> Result<Option<SettledInvoice>, BackendError>;
+
+    /// Stream the invoices settled after `settle_index`, then the ones settled from now on
+    ///
+    /// With a `settle_index` of 0, only the invoices settled from now on are streamed.
+    async fn settled_invoices(
+        &self,
+        settle_index: u64,
+    ) -> Result<BoxStream<'static, Result<SettledInvoice, BackendError>>, BackendError>;
+}
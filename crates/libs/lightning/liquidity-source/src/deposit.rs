@@ -0,0 +1,48 @@
+use std::{sync::Arc, time::Duration};
+
+use liquidity_source::DepositInterface;
+use nuts::Amount;
+use starknet_types::Unit;
+use uuid::Uuid;
+
+use crate::{
+    Error, LightningInvoiceId, amount_to_msat, backend::LightningBackend, utils::unix_time,
+};
+
+#[derive(Debug, Clone)]
+pub struct Depositer {
+    backend: Arc<dyn LightningBackend>,
+}
+
+impl Depositer {
+    pub fn new(backend: Arc<dyn LightningBackend>) -> Self {
+        Self { backend }
+    }
+}
+
+#[async_trait::async_trait]
+impl DepositInterface for Depositer {
+    type Error = Error;
+    type InvoiceId = LightningInvoiceId;
+
+    /// Create an invoice for the quote, identified by its payment hash
+    async fn generate_deposit_payload(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+        expiry: u64,
+    ) -> Result<(Self::InvoiceId, String), Self::Error> {
+        let amount_msat = amount_to_msat(unit, amount)?;
+        let invoice = self
+            .backend
+            .create_invoice(
+                amount_msat,
+                format!("Mint quote {quote_id}"),
+                Duration::from_secs(expiry.saturating_sub(unix_time())),
+            )
+            .await?;
+
+        Ok((LightningInvoiceId(invoice.payment_hash), invoice.bolt11))
+    }
+}
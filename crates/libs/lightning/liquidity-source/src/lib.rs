@@ -0,0 +1,224 @@
+//! Lightning liquidity source, for the `bolt11` method
+//!
+//! Deposits are made by paying an invoice created by the backend node,
+//! the mint quote being marked paid once the invoice is settled.
+//! Withdrawals pay the bolt11 invoice given as melt request,
+//! the payments still in flight once the melt returns being tracked until they are final.
+//!
+//! The backend is an LND node reached over gRPC, or a [`FakeNode`] running in process for tests.
+mod backend;
+mod deposit;
+mod payments;
+mod settlement;
+mod utils;
+mod withdraw;
+
+use std::{
+    fmt::{LowerHex, UpperHex},
+    path::Path,
+    sync::Arc,
+};
+
+pub use backend::{
+    BackendError, CreatedInvoice, FakeNode, LightningBackend, LndBackend, LndConfig,
+    PaymentOutcome, SettledInvoice,
+};
+use bitcoin::hashes::{Hash, sha256};
+use db_node::{SharedStorage, leader::run_as_leader};
+pub use deposit::Depositer;
+use nuts::Amount;
+use serde::Deserialize;
+use starknet_types::{Asset, Unit};
+pub use withdraw::{MeltPaymentRequest, Withdrawer};
+
+/// Bound on the routing fees paid for a melt, if not configured
+const DEFAULT_MAX_FEE_MSAT: u64 = 10_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadLightningConfigError {
+    #[error("failed to read Lightning config file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("failed to deserialize Lightning config file content: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// The `[lightning]` table of the config file
+///
+/// ```toml
+/// [lightning]
+/// max_fee_msat = 10000
+///
+/// [lightning.lnd]
+/// grpc_url = "https://localhost:10009"
+/// tls_cert_path = "/root/.lnd/tls.cert"
+/// macaroon_path = "/root/.lnd/data/chain/bitcoin/regtest/admin.macaroon"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightningConfig {
+    /// Bound on the routing fees paid by the node for a melt
+    ///
+    /// Reserved on top of the amount of the melt quotes, the part left unused is returned as change.
+    pub max_fee_msat: Option<u64>,
+    pub lnd: LndConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    lightning: Option<LightningConfig>,
+}
+
+/// Read the `[lightning]` table of the config file, None if there is none
+pub fn read_lightning_config(
+    path: &Path,
+) -> Result<Option<LightningConfig>, ReadLightningConfigError> {
+    let file_content = std::fs::read_to_string(path)?;
+    let config: ConfigFile = toml::from_str(&file_content)?;
+
+    Ok(config.lightning)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Config(#[from] ReadLightningConfigError),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error("failed to interact with the node database: {0}")]
+    Db(#[from] db_node::Error),
+    #[error("unit `{0}` is not an amount of btc")]
+    UnsupportedUnit(Unit),
+    #[error("amount overflow")]
+    Overflow,
+    #[error("invalid bolt11 invoice: {0}")]
+    InvalidInvoice(String),
+    #[error("the invoice has expired")]
+    ExpiredInvoice,
+    #[error("invoices without an amount are not supported")]
+    AmountlessInvoice,
+}
+
+/// Convert an amount of unit to millisatoshis
+fn amount_to_msat(unit: Unit, amount: Amount) -> Result<u64, Error> {
+    if !unit.is_asset_supported(Asset::BTC) {
+        return Err(Error::UnsupportedUnit(unit));
+    }
+
+    u64::from(amount)
+        .checked_mul(unit.scale_factor())
+        .ok_or(Error::Overflow)
+}
+
+/// The payment hash of a mint quote invoice
+#[derive(Debug, Clone)]
+pub struct LightningInvoiceId([u8; 32]);
+
+impl From<LightningInvoiceId> for [u8; 32] {
+    fn from(value: LightningInvoiceId) -> Self {
+        value.0
+    }
+}
+
+impl LowerHex for LightningInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+impl UpperHex for LightningInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode_upper(self.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LightningLiquiditySource {
+    pub depositer: Depositer,
+    pub withdrawer: Withdrawer,
+}
+
+/// Identifies melt quotes, the ones of mint quotes being the payment hash of their invoice
+fn melt_invoice_id(quote_id: uuid::Uuid, expiry: u64) -> LightningInvoiceId {
+    let preimage = [quote_id.as_bytes().as_slice(), &expiry.to_be_bytes()].concat();
+
+    LightningInvoiceId(sha256::Hash::hash(&preimage).to_byte_array())
+}
+
+impl LightningLiquiditySource {
+    /// The payments are recorded in `storage`, for the melt quotes it knows of
+    pub fn new(
+        storage: SharedStorage,
+        backend: Arc<dyn LightningBackend>,
+        max_fee_msat: u64,
+    ) -> Self {
+        Self {
+            depositer: Depositer::new(backend.clone()),
+            withdrawer: Withdrawer::new(storage, backend, max_fee_msat),
+        }
+    }
+
+    /// Connect to the LND node of the `[lightning]` table, None if the config file has none
+    ///
+    /// A single node follows the settled invoices to mark the mint quotes paid,
+    /// and a single node tracks the payments of the pending melt quotes.
+    pub async fn init(storage: SharedStorage, config_path: &Path) -> Result<Option<Self>, Error> {
+        let Some(config) = read_lightning_config(config_path)? else {
+            return Ok(None);
+        };
+
+        let backend: Arc<dyn LightningBackend> = Arc::new(LndBackend::connect(&config.lnd).await?);
+
+        let cloned_backend = backend.clone();
+        let cloned_storage = storage.clone();
+        let _handle = tokio::spawn(run_as_leader(
+            storage.clone(),
+            "lightning-settlements",
+            move || settlement::run(cloned_storage.clone(), cloned_backend.clone()),
+        ));
+        let cloned_backend = backend.clone();
+        let cloned_storage = storage.clone();
+        let _handle = tokio::spawn(run_as_leader(
+            storage.clone(),
+            "lightning-payments",
+            move || payments::run(cloned_storage.clone(), cloned_backend.clone()),
+        ));
+
+        Ok(Some(Self::new(
+            storage,
+            backend,
+            config.max_fee_msat.unwrap_or(DEFAULT_MAX_FEE_MSAT),
+        )))
+    }
+}
+
+#[async_trait::async_trait]
+impl liquidity_source::LiquiditySource for LightningLiquiditySource {
+    type Error = std::convert::Infallible;
+    type Depositer = Depositer;
+    type Withdrawer = Withdrawer;
+    type InvoiceId = LightningInvoiceId;
+    type Unit = Unit;
+
+    fn depositer(&self) -> Depositer {
+        self.depositer.clone()
+    }
+
+    fn withdrawer(&self) -> Withdrawer {
+        self.withdrawer.clone()
+    }
+
+    /// Every registered unit of bitcoin
+    fn units(&self) -> Vec<Unit> {
+        starknet_types::registry::units()
+            .into_iter()
+            .filter(|unit| unit.asset() == Asset::BTC)
+            .collect()
+    }
+
+    async fn compute_invoice_id(
+        &self,
+        quote_id: uuid::Uuid,
+        expiry: u64,
+    ) -> Result<Self::InvoiceId, Self::Error> {
+        Ok(melt_invoice_id(quote_id, expiry))
+    }
+}
@@ -0,0 +1,172 @@
+//! Resolve the melt quotes whose payment was still in flight when the melt returned
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use bitcoin::hashes::Hash;
+use db_node::{PaymentEvent, SharedStorage, StorageConn};
+use lightning_invoice::Bolt11Invoice;
+use nuts::nut05::MeltQuoteState;
+use starknet_types::Asset;
+use tracing::{Level, error, event, warn};
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    backend::{LightningBackend, PaymentOutcome},
+    utils::unix_time,
+};
+
+const TRACK_INTERVAL: Duration = Duration::from_secs(30);
+/// Bound on the pending quotes looked at per run
+const PENDING_QUOTES_LIMIT: i64 = 1000;
+/// A payment unknown to the backend is only considered failed this long after its quote expired,
+/// the melt checking the expiry right before paying
+const UNKNOWN_PAYMENT_GRACE_SECONDS: u64 = 300;
+
+/// The payment of a melt quote, identified by its preimage
+///
+/// The amount includes the routing fee, so that the node returns the rest of the fee reserve.
+fn melt_payment_event(
+    invoice_id: [u8; 32],
+    payment_hash: [u8; 32],
+    amount_msat: u64,
+    preimage: [u8; 32],
+) -> PaymentEvent {
+    PaymentEvent {
+        block_id: hex::encode(payment_hash),
+        tx_hash: hex::encode(preimage),
+        event_idx: 0,
+        asset: Asset::BTC.to_string(),
+        payee: String::new(),
+        invoice_id,
+        payer: String::new(),
+        amount_low: amount_msat.to_string(),
+        amount_high: "0".to_string(),
+    }
+}
+
+/// Check the payments of the pending bolt11 melt quotes, marking them paid or reverting them
+pub async fn run(storage: SharedStorage, backend: Arc<dyn LightningBackend>) {
+    loop {
+        if let Err(err) = track_pending_payments(&storage, backend.as_ref()).await {
+            error!(name: "lightning-payments", name = "lightning-payments", error = %err);
+        }
+        tokio::time::sleep(TRACK_INTERVAL).await;
+    }
+}
+
+async fn track_pending_payments(
+    storage: &SharedStorage,
+    backend: &dyn LightningBackend,
+) -> Result<(), Error> {
+    let pending_quotes = storage
+        .acquire()
+        .await?
+        .list_melt_quotes(Some(MeltQuoteState::Pending), PENDING_QUOTES_LIMIT)
+        .await?;
+
+    for quote in pending_quotes {
+        // The pending quotes of the other methods
+        let Ok(invoice) = Bolt11Invoice::from_str(quote.request.trim()) else {
+            continue;
+        };
+        let payment_hash = invoice.payment_hash().to_byte_array();
+
+        match backend.track_payment(payment_hash).await? {
+            Some(PaymentOutcome::Succeeded { fee_msat, preimage }) => {
+                let amount_msat = invoice.amount_milli_satoshis().unwrap_or_default() + fee_msat;
+                mark_paid(storage, quote.id, payment_hash, amount_msat, preimage).await?;
+            }
+            Some(PaymentOutcome::Failed(reason)) => revert(storage, quote.id, &reason).await?,
+            None if quote.expiry + UNKNOWN_PAYMENT_GRACE_SECONDS < unix_time() => {
+                revert(storage, quote.id, "payment never sent").await?
+            }
+            Some(PaymentOutcome::InFlight) | None => {}
+        }
+    }
+
+    Ok(())
+}
+
+async fn mark_paid(
+    storage: &SharedStorage,
+    quote_id: Uuid,
+    payment_hash: [u8; 32],
+    amount_msat: u64,
+    preimage: [u8; 32],
+) -> Result<(), Error> {
+    let mut tx = storage.begin().await?;
+    // Locks the quote, the melt that paid it may still be updating it
+    let quote = tx.get_melt_quote_data(quote_id).await?;
+    if quote.state != MeltQuoteState::Pending {
+        return Ok(());
+    }
+    tx.insert_melt_payment_event(&melt_payment_event(
+        quote.invoice_id,
+        payment_hash,
+        amount_msat,
+        preimage,
+    ))
+    .await?;
+    tx.set_melt_quote_state(quote_id, MeltQuoteState::Paid)
+        .await?;
+    tx.commit().await?;
+
+    event!(
+        name: "melt-quote-paid",
+        Level::INFO,
+        name = "melt-quote-paid",
+        %quote_id,
+    );
+
+    Ok(())
+}
+
+/// Unspend the inputs of a quote whose payment failed
+async fn revert(storage: &SharedStorage, quote_id: Uuid, reason: &str) -> Result<(), Error> {
+    let mut tx = storage.begin().await?;
+    if !tx.revert_melt_quote(quote_id).await? {
+        return Ok(());
+    }
+    // The event the node records when the payment fails during the melt
+    tx.insert_audit_event("melt_reverted", &format!(r#"{{"quote_id":"{quote_id}"}}"#))
+        .await?;
+    tx.commit().await?;
+
+    warn!(
+        name: "melt-quote-reverted",
+        name = "melt-quote-reverted",
+        %quote_id,
+        reason,
+    );
+
+    Ok(())
+}
+
+/// Record the payment of a melt quote known to `conn`
+///
+/// The quote may be unknown when the liquidity source is served to another node, as a plugin.
+pub(crate) async fn record_melt_payment(
+    conn: &mut dyn StorageConn,
+    invoice_id: [u8; 32],
+    payment_hash: [u8; 32],
+    amount_msat: u64,
+    preimage: [u8; 32],
+) -> Result<(), Error> {
+    if conn
+        .get_melt_quote_by_invoice_id(&invoice_id)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    conn.insert_melt_payment_event(&melt_payment_event(
+        invoice_id,
+        payment_hash,
+        amount_msat,
+        preimage,
+    ))
+    .await?;
+
+    Ok(())
+}
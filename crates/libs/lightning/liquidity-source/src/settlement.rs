@@ -0,0 +1,132 @@
+//! Mark the mint quotes paid as their invoices get settled
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use bitcoin::hashes::Hash;
+use db_node::{PaymentEvent, SharedStorage, StorageConn};
+use futures::TryStreamExt;
+use lightning_invoice::Bolt11Invoice;
+use nuts::nut04::MintQuoteState;
+use starknet_types::{Asset, Unit};
+use tracing::{Level, debug, error, event};
+
+use crate::{
+    Error, amount_to_msat,
+    backend::{LightningBackend, SettledInvoice},
+};
+
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(5);
+/// Bound on the unpaid quotes looked up when catching up
+const UNPAID_QUOTES_LIMIT: i64 = 1000;
+
+/// Follow the settled invoices, resubscribing from the last one seen if the stream breaks
+///
+/// The settle index is not persisted, so the invoices of the unpaid quotes are looked up
+/// once subscribed, to catch up on the ones settled while no node was following them.
+pub async fn run(storage: SharedStorage, backend: Arc<dyn LightningBackend>) {
+    let mut settle_index = 0;
+    loop {
+        if let Err(err) = listen(&storage, backend.as_ref(), &mut settle_index).await {
+            error!(name: "lightning-settlements", name = "lightning-settlements", error = %err);
+        }
+        tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+    }
+}
+
+async fn listen(
+    storage: &SharedStorage,
+    backend: &dyn LightningBackend,
+    settle_index: &mut u64,
+) -> Result<(), Error> {
+    let mut settled_invoices = backend.settled_invoices(*settle_index).await?;
+    // Until a first invoice is seen, there is no index to replay the missed ones from
+    if *settle_index == 0 {
+        catch_up(storage, backend).await?;
+    }
+    while let Some(settled_invoice) = settled_invoices.try_next().await? {
+        let mut conn = storage.acquire().await?;
+        handle_settled_invoice(conn.as_mut(), &settled_invoice).await?;
+        *settle_index = settled_invoice.settle_index;
+    }
+
+    Ok(())
+}
+
+async fn catch_up(storage: &SharedStorage, backend: &dyn LightningBackend) -> Result<(), Error> {
+    let mut conn = storage.acquire().await?;
+    let unpaid_quotes = conn
+        .list_mint_quotes(Some(MintQuoteState::Unpaid), UNPAID_QUOTES_LIMIT)
+        .await?;
+
+    for quote in unpaid_quotes {
+        // The unpaid quotes of the other methods
+        let Ok(invoice) = Bolt11Invoice::from_str(&quote.request) else {
+            continue;
+        };
+        if let Some(settled_invoice) = backend
+            .lookup_invoice(invoice.payment_hash().to_byte_array())
+            .await?
+        {
+            handle_settled_invoice(conn.as_mut(), &settled_invoice).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The payment of a mint quote, identified by the payment hash of its invoice
+fn mint_payment_event(settled_invoice: &SettledInvoice) -> PaymentEvent {
+    PaymentEvent {
+        block_id: settled_invoice.settle_index.to_string(),
+        tx_hash: hex::encode(settled_invoice.payment_hash),
+        event_idx: 0,
+        asset: Asset::BTC.to_string(),
+        payee: String::new(),
+        invoice_id: settled_invoice.payment_hash,
+        payer: String::new(),
+        amount_low: settled_invoice.amount_paid_msat.to_string(),
+        amount_high: "0".to_string(),
+    }
+}
+
+async fn handle_settled_invoice(
+    conn: &mut dyn StorageConn,
+    settled_invoice: &SettledInvoice,
+) -> Result<(), Error> {
+    let Some((quote_id, amount, unit)) = conn
+        .get_or_restore_mint_quote_by_invoice_id(&settled_invoice.payment_hash)
+        .await?
+    else {
+        // Invoices created by someone else on the same node
+        debug!(
+            "no mint quote for payment hash {}",
+            hex::encode(settled_invoice.payment_hash)
+        );
+        return Ok(());
+    };
+    let unit = Unit::from_str(&unit).map_err(|_| db_node::Error::InvalidUnit(unit))?;
+    conn.insert_mint_payment_event(&mint_payment_event(settled_invoice))
+        .await?;
+
+    // Settlements may be streamed again after a resubscription
+    if conn.get_mint_quote_response(quote_id).await?.state != MintQuoteState::Unpaid {
+        return Ok(());
+    }
+    if settled_invoice.amount_paid_msat < amount_to_msat(unit, amount)? {
+        error!(
+            "invoice of mint quote {} settled for {} msat, less than the quote amount",
+            quote_id, settled_invoice.amount_paid_msat
+        );
+        return Ok(());
+    }
+
+    conn.set_mint_quote_state(quote_id, MintQuoteState::Paid)
+        .await?;
+    event!(
+        name: "mint-quote-paid",
+        Level::INFO,
+        name = "mint-quote-paid",
+        %quote_id,
+    );
+
+    Ok(())
+}
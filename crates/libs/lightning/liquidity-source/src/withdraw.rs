@@ -0,0 +1,254 @@
+use std::{str::FromStr, sync::Arc};
+
+use bitcoin::hashes::Hash;
+use db_node::SharedStorage;
+use lightning_invoice::Bolt11Invoice;
+use liquidity_source::WithdrawInterface;
+use num_traits::CheckedAdd;
+use nuts::{Amount, nut05::MeltQuoteState};
+use serde::{Deserialize, Serialize};
+use starknet_types::{Asset, Unit};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    Error, LightningInvoiceId,
+    backend::{LightningBackend, PaymentOutcome},
+    melt_invoice_id,
+    payments::record_melt_payment,
+};
+
+/// A bolt11 invoice with an amount
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeltPaymentRequest {
+    pub bolt11: String,
+    pub amount_msat: u64,
+    pub payment_hash: [u8; 32],
+}
+
+#[derive(Debug, Clone)]
+pub struct Withdrawer {
+    storage: SharedStorage,
+    backend: Arc<dyn LightningBackend>,
+    max_fee_msat: u64,
+}
+
+impl Withdrawer {
+    pub fn new(
+        storage: SharedStorage,
+        backend: Arc<dyn LightningBackend>,
+        max_fee_msat: u64,
+    ) -> Self {
+        Self {
+            storage,
+            backend,
+            max_fee_msat,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WithdrawInterface for Withdrawer {
+    type Error = Error;
+    type Request = MeltPaymentRequest;
+    type Unit = Unit;
+    type InvoiceId = LightningInvoiceId;
+
+    /// The melt request is the bolt11 invoice itself, as in the Cashu NUT-23
+    fn deserialize_payment_request(&self, raw_request: &str) -> Result<Self::Request, Error> {
+        let bolt11 = raw_request.trim();
+        let invoice =
+            Bolt11Invoice::from_str(bolt11).map_err(|e| Error::InvalidInvoice(e.to_string()))?;
+        if invoice.is_expired() {
+            return Err(Error::ExpiredInvoice);
+        }
+        let amount_msat = invoice
+            .amount_milli_satoshis()
+            .ok_or(Error::AmountlessInvoice)?;
+
+        Ok(MeltPaymentRequest {
+            bolt11: bolt11.to_string(),
+            amount_msat,
+            payment_hash: invoice.payment_hash().to_byte_array(),
+        })
+    }
+
+    /// The routing fee limit of the payments
+    async fn fee_reserve(&self, _request: &Self::Request, unit: Unit) -> Result<Amount, Error> {
+        if !unit.is_asset_supported(Asset::BTC) {
+            return Err(Error::UnsupportedUnit(unit));
+        }
+
+        Ok(Amount::from(
+            self.max_fee_msat.div_ceil(unit.scale_factor()),
+        ))
+    }
+
+    async fn compute_total_amount_expected(
+        &self,
+        request: Self::Request,
+        unit: Unit,
+        fee: Amount,
+    ) -> Result<Amount, Self::Error> {
+        if !unit.is_asset_supported(Asset::BTC) {
+            return Err(Error::UnsupportedUnit(unit));
+        }
+
+        // Round up, the node can't pay less than requested
+        let amount = Amount::from(request.amount_msat.div_ceil(unit.scale_factor()));
+
+        amount.checked_add(&fee).ok_or(Error::Overflow)
+    }
+
+    /// Unpaid if the payment failed, Pending if its outcome is not known yet
+    async fn proceed_to_payment(
+        &mut self,
+        quote_id: Uuid,
+        request: MeltPaymentRequest,
+        expiry: u64,
+    ) -> Result<MeltQuoteState, Error> {
+        match self
+            .backend
+            .pay_invoice(&request.bolt11, self.max_fee_msat)
+            .await?
+        {
+            PaymentOutcome::Succeeded { fee_msat, preimage } => {
+                let mut conn = self.storage.acquire().await?;
+                record_melt_payment(
+                    conn.as_mut(),
+                    melt_invoice_id(quote_id, expiry).into(),
+                    request.payment_hash,
+                    request.amount_msat + fee_msat,
+                    preimage,
+                )
+                .await?;
+
+                Ok(MeltQuoteState::Paid)
+            }
+            // Tracked until it is final, see `payments::run`
+            PaymentOutcome::InFlight => Ok(MeltQuoteState::Pending),
+            PaymentOutcome::Failed(reason) => {
+                warn!(
+                    name: "melt-payment-failed",
+                    name = "melt-payment-failed",
+                    %quote_id,
+                    %reason,
+                );
+
+                Ok(MeltQuoteState::Unpaid)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use db_node::{Storage, sqlite::SqliteStorage};
+
+    use crate::FakeNode;
+
+    use super::*;
+
+    async fn withdrawer(node: Arc<FakeNode>, max_fee_msat: u64) -> Withdrawer {
+        let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+        storage.run_migrations().await.unwrap();
+
+        Withdrawer::new(Arc::new(storage), node, max_fee_msat)
+    }
+
+    #[tokio::test]
+    async fn pay_fake_node_invoice() {
+        let node = Arc::new(FakeNode::default());
+        let invoice = node
+            .create_invoice(1_500, "test".to_string(), Duration::from_secs(600))
+            .await
+            .unwrap();
+
+        let mut withdrawer = withdrawer(node.clone(), 0).await;
+        let request = withdrawer
+            .deserialize_payment_request(&invoice.bolt11)
+            .unwrap();
+        assert_eq!(request.amount_msat, 1_500);
+        assert_eq!(request.payment_hash, invoice.payment_hash);
+        assert_eq!(
+            withdrawer
+                .compute_total_amount_expected(request.clone(), Unit::SAT, Amount::ZERO)
+                .await
+                .unwrap(),
+            Amount::from(2u64)
+        );
+        assert_eq!(
+            withdrawer
+                .compute_total_amount_expected(request.clone(), Unit::MSAT, Amount::ZERO)
+                .await
+                .unwrap(),
+            Amount::from(1_500u64)
+        );
+        assert!(matches!(
+            withdrawer
+                .compute_total_amount_expected(request.clone(), Unit::GWEI, Amount::ZERO)
+                .await,
+            Err(Error::UnsupportedUnit(_))
+        ));
+
+        let state = withdrawer
+            .proceed_to_payment(Uuid::new_v4(), request, 0)
+            .await
+            .unwrap();
+        assert_eq!(state, MeltQuoteState::Paid);
+        assert_eq!(node.paid_invoices(), vec![invoice.bolt11]);
+    }
+
+    #[tokio::test]
+    async fn failed_payment_leaves_the_quote_unpaid() {
+        let node = Arc::new(FakeNode::default());
+        let invoice = node
+            .create_invoice(1_500, "test".to_string(), Duration::from_secs(600))
+            .await
+            .unwrap();
+        node.fail_payments(true);
+
+        let mut withdrawer = withdrawer(node.clone(), 0).await;
+        let request = withdrawer
+            .deserialize_payment_request(&invoice.bolt11)
+            .unwrap();
+        let state = withdrawer
+            .proceed_to_payment(Uuid::new_v4(), request, 0)
+            .await
+            .unwrap();
+        assert_eq!(state, MeltQuoteState::Unpaid);
+        assert!(node.paid_invoices().is_empty());
+        assert!(matches!(
+            node.track_payment(invoice.payment_hash).await.unwrap(),
+            Some(PaymentOutcome::Failed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fee_reserve_is_the_fee_limit_rounded_up() {
+        let node = Arc::new(FakeNode::default());
+        let invoice = node
+            .create_invoice(1_500, "test".to_string(), Duration::from_secs(600))
+            .await
+            .unwrap();
+
+        let withdrawer = withdrawer(node, 2_500).await;
+        let request = withdrawer
+            .deserialize_payment_request(&invoice.bolt11)
+            .unwrap();
+        assert_eq!(
+            withdrawer.fee_reserve(&request, Unit::SAT).await.unwrap(),
+            Amount::from(3u64)
+        );
+        assert_eq!(
+            withdrawer.fee_reserve(&request, Unit::MSAT).await.unwrap(),
+            Amount::from(2_500u64)
+        );
+        assert!(matches!(
+            withdrawer.fee_reserve(&request, Unit::GWEI).await,
+            Err(Error::UnsupportedUnit(_))
+        ));
+    }
+}
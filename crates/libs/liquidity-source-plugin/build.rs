@@ -0,0 +1,10 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .compile_protos(
+            &["../../../proto/liquidity_source.proto"],
+            &["../../../proto"],
+        )?;
+    Ok(())
+}
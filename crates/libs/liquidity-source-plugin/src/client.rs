@@ -0,0 +1,253 @@
+use std::{
+    fmt::{LowerHex, UpperHex},
+    str::FromStr,
+};
+
+use db_node::{SharedStorage, leader::run_as_leader};
+use liquidity_source::{DepositInterface, LiquiditySource, WithdrawInterface};
+use nuts::{Amount, nut05::MeltQuoteState};
+use starknet_types::Unit;
+use tonic::transport::Channel;
+use uuid::Uuid;
+
+use crate::{
+    Error, PluginConfig,
+    proto::{
+        ComputeInvoiceIdRequest, ComputeTotalAmountExpectedRequest, FeeReserveRequest,
+        GenerateDepositPayloadRequest, HandshakeRequest, ProceedToPaymentRequest,
+        liquidity_source_plugin_client::LiquiditySourcePluginClient,
+    },
+    settlement,
+};
+
+type Client = LiquiditySourcePluginClient<Channel>;
+
+/// The invoice id computed by a plugin
+#[derive(Debug, Clone)]
+pub struct PluginInvoiceId([u8; 32]);
+
+impl TryFrom<Vec<u8>> for PluginInvoiceId {
+    type Error = Error;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        let len = value.len();
+
+        value
+            .try_into()
+            .map(Self)
+            .map_err(|_| Error::InvalidInvoiceId(len))
+    }
+}
+
+impl From<PluginInvoiceId> for [u8; 32] {
+    fn from(value: PluginInvoiceId) -> Self {
+        value.0
+    }
+}
+
+impl LowerHex for PluginInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+impl UpperHex for PluginInvoiceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode_upper(self.0))
+    }
+}
+
+/// A liquidity source served by a plugin
+#[derive(Debug, Clone)]
+pub struct PluginLiquiditySource {
+    client: Client,
+    units: Vec<Unit>,
+}
+
+impl PluginLiquiditySource {
+    /// Connect to the plugin serving `method`, and check that it does
+    ///
+    /// A single node subscribes to the payment events of the plugin to settle the quotes.
+    pub async fn connect(
+        storage: SharedStorage,
+        method: &str,
+        config: &PluginConfig,
+    ) -> Result<Self, Error> {
+        let mut client = LiquiditySourcePluginClient::new(config.endpoint.connect_lazy());
+
+        let handshake = client.handshake(HandshakeRequest {}).await?.into_inner();
+        if handshake.method != method {
+            return Err(Error::MethodMismatch(method.to_string(), handshake.method));
+        }
+        let units = handshake
+            .units
+            .iter()
+            .map(|unit| Unit::from_str(unit).map_err(|_| Error::UnknownUnit(unit.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let task = format!("{method}-plugin-settlements");
+        let cloned_client = client.clone();
+        let cloned_storage = storage.clone();
+        let _handle = tokio::spawn(async move {
+            run_as_leader(storage, &task, move || {
+                settlement::run(cloned_storage.clone(), cloned_client.clone())
+            })
+            .await
+        });
+
+        Ok(Self { client, units })
+    }
+}
+
+#[async_trait::async_trait]
+impl LiquiditySource for PluginLiquiditySource {
+    type Error = Error;
+    type Depositer = PluginDepositer;
+    type Withdrawer = PluginWithdrawer;
+    type InvoiceId = PluginInvoiceId;
+    type Unit = Unit;
+
+    fn depositer(&self) -> PluginDepositer {
+        PluginDepositer {
+            client: self.client.clone(),
+        }
+    }
+
+    fn withdrawer(&self) -> PluginWithdrawer {
+        PluginWithdrawer {
+            client: self.client.clone(),
+        }
+    }
+
+    /// Advertised by the plugin during the handshake
+    fn units(&self) -> Vec<Unit> {
+        self.units.clone()
+    }
+
+    async fn compute_invoice_id(
+        &self,
+        quote_id: Uuid,
+        expiry: u64,
+    ) -> Result<Self::InvoiceId, Self::Error> {
+        let response = self
+            .client
+            .clone()
+            .compute_invoice_id(ComputeInvoiceIdRequest {
+                quote_id: quote_id.to_string(),
+                expiry,
+            })
+            .await?
+            .into_inner();
+
+        PluginInvoiceId::try_from(response.invoice_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginDepositer {
+    client: Client,
+}
+
+#[async_trait::async_trait]
+impl DepositInterface for PluginDepositer {
+    type Error = Error;
+    type InvoiceId = PluginInvoiceId;
+
+    async fn generate_deposit_payload(
+        &self,
+        quote_id: Uuid,
+        unit: Unit,
+        amount: Amount,
+        expiry: u64,
+    ) -> Result<(Self::InvoiceId, String), Self::Error> {
+        let response = self
+            .client
+            .clone()
+            .generate_deposit_payload(GenerateDepositPayloadRequest {
+                quote_id: quote_id.to_string(),
+                unit: unit.to_string(),
+                amount: amount.into(),
+                expiry,
+            })
+            .await?
+            .into_inner();
+
+        Ok((
+            PluginInvoiceId::try_from(response.invoice_id)?,
+            response.payload,
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginWithdrawer {
+    client: Client,
+}
+
+#[async_trait::async_trait]
+impl WithdrawInterface for PluginWithdrawer {
+    type Error = Error;
+    /// The raw melt request, only the plugin knows how to read it
+    type Request = String;
+    type Unit = Unit;
+    type InvoiceId = PluginInvoiceId;
+
+    /// Validated by the plugin when computing the amount to melt
+    fn deserialize_payment_request(&self, raw_request: &str) -> Result<Self::Request, Error> {
+        Ok(raw_request.to_string())
+    }
+
+    async fn fee_reserve(&self, request: &Self::Request, unit: Unit) -> Result<Amount, Error> {
+        let response = self
+            .client
+            .clone()
+            .fee_reserve(FeeReserveRequest {
+                request: request.clone(),
+                unit: unit.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(Amount::from(response.fee_reserve))
+    }
+
+    async fn compute_total_amount_expected(
+        &self,
+        request: Self::Request,
+        unit: Unit,
+        fee: Amount,
+    ) -> Result<Amount, Self::Error> {
+        let response = self
+            .client
+            .clone()
+            .compute_total_amount_expected(ComputeTotalAmountExpectedRequest {
+                request,
+                unit: unit.to_string(),
+                fee: fee.into(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(Amount::from(response.amount))
+    }
+
+    async fn proceed_to_payment(
+        &mut self,
+        quote_id: Uuid,
+        request: Self::Request,
+        expiry: u64,
+    ) -> Result<MeltQuoteState, Self::Error> {
+        let response = self
+            .client
+            .proceed_to_payment(ProceedToPaymentRequest {
+                quote_id: quote_id.to_string(),
+                request,
+                expiry,
+            })
+            .await?
+            .into_inner();
+
+        crate::proto::MeltQuoteState::try_from(response.state)
+            .map_err(|_| Error::InvalidMeltQuoteState(response.state))?
+            .try_into()
+    }
+}
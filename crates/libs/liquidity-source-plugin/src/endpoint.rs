@@ -0,0 +1,105 @@
+use std::{path::PathBuf, str::FromStr};
+
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use tokio::net::UnixStream;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+const UNIX_PREFIX: &str = "unix://";
+
+/// Where a plugin listens, `unix:///path/to/socket` or `http://host:port`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum PluginEndpoint {
+    Unix(PathBuf),
+    Http(Uri),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseEndpointError {
+    #[error("invalid plugin endpoint uri: {0}")]
+    Uri(#[from] http::uri::InvalidUri),
+    #[error("expected a `unix://` or `http://` plugin endpoint, got `{0}`")]
+    UnsupportedScheme(String),
+}
+
+impl FromStr for PluginEndpoint {
+    type Err = ParseEndpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix(UNIX_PREFIX) {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+
+        let uri = Uri::from_str(s)?;
+        if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+            return Err(ParseEndpointError::UnsupportedScheme(s.to_string()));
+        }
+
+        Ok(Self::Http(uri))
+    }
+}
+
+impl TryFrom<String> for PluginEndpoint {
+    type Error = ParseEndpointError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl std::fmt::Display for PluginEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginEndpoint::Unix(path) => write!(f, "{UNIX_PREFIX}{}", path.display()),
+            PluginEndpoint::Http(uri) => write!(f, "{uri}"),
+        }
+    }
+}
+
+impl PluginEndpoint {
+    /// A channel connecting on first use, and reconnecting whenever the plugin restarts
+    pub fn connect_lazy(&self) -> Channel {
+        match self {
+            PluginEndpoint::Unix(path) => {
+                let path = path.clone();
+                // The uri is required by tonic but never used, the connector ignores it
+                Endpoint::from_static("http://[::]:50051").connect_with_connector_lazy(service_fn(
+                    move |_: Uri| {
+                        let path = path.clone();
+                        async move {
+                            Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(path).await?))
+                        }
+                    },
+                ))
+            }
+            PluginEndpoint::Http(uri) => Endpoint::from(uri.clone()).connect_lazy(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_endpoints() {
+        assert_eq!(
+            PluginEndpoint::from_str("unix:///tmp/plugin.sock").unwrap(),
+            PluginEndpoint::Unix(PathBuf::from("/tmp/plugin.sock"))
+        );
+        assert_eq!(
+            PluginEndpoint::from_str("http://localhost:7000").unwrap(),
+            PluginEndpoint::Http(Uri::from_static("http://localhost:7000"))
+        );
+        assert!(matches!(
+            PluginEndpoint::from_str("https://localhost:7000"),
+            Err(ParseEndpointError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            PluginEndpoint::from_str("ftp://localhost:7000"),
+            Err(ParseEndpointError::UnsupportedScheme(_))
+        ));
+    }
+}
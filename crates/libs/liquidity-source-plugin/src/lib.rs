@@ -0,0 +1,115 @@
+//! Liquidity sources running out of the node process, as plugins
+//!
+//! A plugin is a gRPC server implementing the `LiquiditySourcePlugin` service of
+//! `proto/liquidity_source.proto`, reached over a unix socket or tcp.
+//! The node talks to it through [`PluginLiquiditySource`], and settles the quotes
+//! according to the payment events the plugin pushes.
+//! On connection, the plugin advertises the method it serves and its units, the method being an opaque name
+//! the node only checks against the key of the plugin in its config file.
+//! Plugin binaries serve any [`LiquiditySource`](liquidity_source::LiquiditySource) with [`PluginServer`].
+mod client;
+mod endpoint;
+mod server;
+mod settlement;
+
+use std::{collections::HashMap, path::Path};
+
+pub use client::{PluginDepositer, PluginInvoiceId, PluginLiquiditySource, PluginWithdrawer};
+pub use endpoint::{ParseEndpointError, PluginEndpoint};
+pub use proto::{PaymentDirection, PaymentEvent};
+use serde::Deserialize;
+pub use server::{PaymentEventPublisher, PluginServer};
+
+pub mod proto {
+    tonic::include_proto!("liquidity_source");
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadPluginsConfigError {
+    #[error("failed to read plugins config file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("failed to deserialize plugins config file content: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+/// An entry of the `[plugins]` table of the config file, keyed by the method the plugin serves
+///
+/// The key is any name, a built-in method being replaced by the plugin named after it.
+///
+/// ```toml
+/// [plugins.starknet]
+/// endpoint = "unix:///tmp/starknet-liquidity-plugin.sock"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub endpoint: PluginEndpoint,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    plugins: HashMap<String, PluginConfig>,
+}
+
+/// Read the `[plugins]` table of the config file, empty if there is none
+pub fn read_plugins_config(
+    path: &Path,
+) -> Result<HashMap<String, PluginConfig>, ReadPluginsConfigError> {
+    let file_content = std::fs::read_to_string(path)?;
+    let config: ConfigFile = toml::from_str(&file_content)?;
+
+    Ok(config.plugins)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Config(#[from] ReadPluginsConfigError),
+    #[error("failed to listen on the plugin endpoint: {0}")]
+    Listen(#[source] std::io::Error),
+    #[error("plugin transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("plugin call failed: {0}")]
+    Plugin(#[from] tonic::Status),
+    #[error("the plugin configured for method `{0}` serves method `{1}`")]
+    MethodMismatch(String, String),
+    #[error("the plugin serves the unknown unit `{0}`")]
+    UnknownUnit(String),
+    #[error("invalid invoice id, expected 32 bytes, got {0}")]
+    InvalidInvoiceId(usize),
+    #[error("invalid payment state `{0}`, only unpaid, pending and paid are allowed")]
+    InvalidMeltQuoteState(i32),
+    #[error("failed to interact with the node database: {0}")]
+    Db(#[from] db_node::Error),
+    #[error("u256 value overflowed during the computation of the total amount paid for invoice")]
+    AmountPaidOverflow,
+}
+
+impl From<nuts::nut05::MeltQuoteState> for proto::MeltQuoteState {
+    fn from(value: nuts::nut05::MeltQuoteState) -> Self {
+        match value {
+            nuts::nut05::MeltQuoteState::Unpaid => Self::Unpaid,
+            nuts::nut05::MeltQuoteState::Pending => Self::Pending,
+            nuts::nut05::MeltQuoteState::Paid => Self::Paid,
+            nuts::nut05::MeltQuoteState::Expired => Self::Expired,
+        }
+    }
+}
+
+/// The state of a payment returned by `ProceedToPayment`, see the protocol for what each one means
+impl TryFrom<proto::MeltQuoteState> for nuts::nut05::MeltQuoteState {
+    type Error = Error;
+
+    fn try_from(value: proto::MeltQuoteState) -> Result<Self, Self::Error> {
+        match value {
+            proto::MeltQuoteState::Unpaid => Ok(Self::Unpaid),
+            proto::MeltQuoteState::Pending => Ok(Self::Pending),
+            proto::MeltQuoteState::Paid => Ok(Self::Paid),
+            // A payment can't expire, the quote of its inputs did not
+            proto::MeltQuoteState::Unspecified | proto::MeltQuoteState::Expired => {
+                Err(Error::InvalidMeltQuoteState(value as i32))
+            }
+        }
+    }
+}
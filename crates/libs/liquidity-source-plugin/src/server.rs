@@ -0,0 +1,377 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use liquidity_source::{DepositInterface, LiquiditySource, WithdrawInterface};
+use nuts::Amount;
+use starknet_types::Unit;
+use tokio::{
+    net::{TcpListener, UnixListener},
+    sync::broadcast,
+};
+use tokio_stream::wrappers::{
+    BroadcastStream, TcpListenerStream, UnixListenerStream, errors::BroadcastStreamRecvError,
+};
+use tonic::{Request, Response, Status, transport::Server};
+use uuid::Uuid;
+
+use crate::{
+    Error, PluginEndpoint,
+    proto::{
+        ComputeInvoiceIdRequest, ComputeInvoiceIdResponse, ComputeTotalAmountExpectedRequest,
+        ComputeTotalAmountExpectedResponse, FeeReserveRequest, FeeReserveResponse,
+        GenerateDepositPayloadRequest, GenerateDepositPayloadResponse, HandshakeRequest,
+        HandshakeResponse, MeltQuoteState, PaymentEvent, ProceedToPaymentRequest,
+        ProceedToPaymentResponse, SequencedPaymentEvent, SubscribePaymentEventsRequest,
+        liquidity_source_plugin_server::{LiquiditySourcePlugin, LiquiditySourcePluginServer},
+    },
+};
+
+/// Number of events a slow subscriber can lag behind before its stream is closed
+const EVENTS_CHANNEL_CAPACITY: usize = 1_024;
+/// Number of past events replayed to the new subscribers
+const EVENTS_HISTORY_CAPACITY: usize = 10_000;
+
+type PaymentEventStream = Pin<Box<dyn Stream<Item = Result<SequencedPaymentEvent, Status>> + Send>>;
+
+#[derive(Debug)]
+struct History {
+    events: VecDeque<SequencedPaymentEvent>,
+    next_sequence: u64,
+}
+
+/// Push the payments seen by the plugin to the subscribed nodes
+///
+/// The last events published are kept, to be replayed to the new subscribers
+/// from the cursor they resume at. Older ones are dropped, the liquidity source
+/// being expected to publish again what it sees after a restart of the plugin.
+#[derive(Debug, Clone)]
+pub struct PaymentEventPublisher {
+    history: Arc<Mutex<History>>,
+    sender: broadcast::Sender<SequencedPaymentEvent>,
+}
+
+impl Default for PaymentEventPublisher {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        // Start from the current time, so that the cursor of a node subscribed
+        // to a previous run of the plugin is behind all the events of this one
+        let next_sequence = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+            .unwrap_or_default();
+
+        Self {
+            history: Arc::new(Mutex::new(History {
+                events: VecDeque::new(),
+                next_sequence,
+            })),
+            sender,
+        }
+    }
+}
+
+impl PaymentEventPublisher {
+    pub fn publish(&self, payment_event: PaymentEvent) {
+        let mut history = self.history.lock().expect("lock should not be poisoned");
+        let event = SequencedPaymentEvent {
+            sequence: history.next_sequence,
+            event: Some(payment_event),
+        };
+        history.next_sequence += 1;
+        if history.events.len() == EVENTS_HISTORY_CAPACITY {
+            history.events.pop_front();
+        }
+        history.events.push_back(event.clone());
+        // Having no subscriber is fine, they will get the event from the history
+        let _ = self.sender.send(event);
+    }
+
+    /// The events published after `after_sequence`, or all the retained ones, then the new ones
+    fn subscribe(&self, after_sequence: Option<u64>) -> PaymentEventStream {
+        // No event can be published between the copy of the history and the subscription
+        let history = self.history.lock().expect("lock should not be poisoned");
+        let receiver = self.sender.subscribe();
+        let past_events: Vec<_> = history
+            .events
+            .iter()
+            .filter(|event| after_sequence.is_none_or(|after| event.sequence > after))
+            .cloned()
+            .collect();
+        drop(history);
+
+        let new_events = BroadcastStream::new(receiver).map(|res| {
+            res.map_err(|BroadcastStreamRecvError::Lagged(n)| {
+                Status::data_loss(format!("lagged behind by {n} events, subscribe again"))
+            })
+        });
+
+        Box::pin(tokio_stream::iter(past_events.into_iter().map(Ok)).chain(new_events))
+    }
+}
+
+/// Serve a liquidity source as a plugin
+#[derive(Debug, Clone)]
+pub struct PluginServer<L> {
+    method: String,
+    liquidity_source: L,
+    payment_events: PaymentEventPublisher,
+}
+
+impl<L> PluginServer<L>
+where
+    L: LiquiditySource<Unit = Unit> + 'static,
+{
+    /// Serve `liquidity_source` through `method`, advertised to the node during the handshake along with its units
+    ///
+    /// `payment_events` should receive every payment made to or by the liquidity source.
+    pub fn new(
+        method: impl Into<String>,
+        liquidity_source: L,
+        payment_events: PaymentEventPublisher,
+    ) -> Self {
+        Self {
+            method: method.into(),
+            liquidity_source,
+            payment_events,
+        }
+    }
+
+    /// Serve the plugin until `signal` completes
+    ///
+    /// A unix socket left behind by a previous run is removed.
+    pub async fn serve(
+        self,
+        endpoint: &PluginEndpoint,
+        signal: impl Future<Output = ()>,
+    ) -> Result<(), Error> {
+        let router = Server::builder().add_service(LiquiditySourcePluginServer::new(self));
+
+        match endpoint {
+            PluginEndpoint::Unix(path) => {
+                if tokio::fs::try_exists(path).await.map_err(Error::Listen)? {
+                    tokio::fs::remove_file(path).await.map_err(Error::Listen)?;
+                }
+                let listener = UnixListener::bind(path).map_err(Error::Listen)?;
+                router
+                    .serve_with_incoming_shutdown(UnixListenerStream::new(listener), signal)
+                    .await?;
+            }
+            PluginEndpoint::Http(uri) => {
+                let authority = uri
+                    .authority()
+                    .expect("http endpoints are parsed with an authority");
+                let listener = TcpListener::bind(authority.as_str())
+                    .await
+                    .map_err(Error::Listen)?;
+                router
+                    .serve_with_incoming_shutdown(TcpListenerStream::new(listener), signal)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_quote_id(quote_id: &str) -> Result<Uuid, Status> {
+    Uuid::from_str(quote_id).map_err(|e| Status::invalid_argument(format!("invalid quote id: {e}")))
+}
+
+fn parse_unit(unit: &str) -> Result<Unit, Status> {
+    Unit::from_str(unit).map_err(|_| Status::invalid_argument(format!("unknown unit `{unit}`")))
+}
+
+#[tonic::async_trait]
+impl<L> LiquiditySourcePlugin for PluginServer<L>
+where
+    L: LiquiditySource<Unit = Unit> + 'static,
+{
+    async fn handshake(
+        &self,
+        _request: Request<HandshakeRequest>,
+    ) -> Result<Response<HandshakeResponse>, Status> {
+        Ok(Response::new(HandshakeResponse {
+            method: self.method.clone(),
+            units: self
+                .liquidity_source
+                .units()
+                .iter()
+                .map(Unit::to_string)
+                .collect(),
+        }))
+    }
+
+    async fn compute_invoice_id(
+        &self,
+        request: Request<ComputeInvoiceIdRequest>,
+    ) -> Result<Response<ComputeInvoiceIdResponse>, Status> {
+        let request = request.into_inner();
+        let quote_id = parse_quote_id(&request.quote_id)?;
+
+        let invoice_id: [u8; 32] = self
+            .liquidity_source
+            .compute_invoice_id(quote_id, request.expiry)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .into();
+
+        Ok(Response::new(ComputeInvoiceIdResponse {
+            invoice_id: invoice_id.to_vec(),
+        }))
+    }
+
+    async fn generate_deposit_payload(
+        &self,
+        request: Request<GenerateDepositPayloadRequest>,
+    ) -> Result<Response<GenerateDepositPayloadResponse>, Status> {
+        let request = request.into_inner();
+        let quote_id = parse_quote_id(&request.quote_id)?;
+        let unit = parse_unit(&request.unit)?;
+
+        let (invoice_id, payload) = self
+            .liquidity_source
+            .depositer()
+            .generate_deposit_payload(quote_id, unit, Amount::from(request.amount), request.expiry)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let invoice_id: [u8; 32] = invoice_id.into();
+
+        Ok(Response::new(GenerateDepositPayloadResponse {
+            invoice_id: invoice_id.to_vec(),
+            payload,
+        }))
+    }
+
+    async fn fee_reserve(
+        &self,
+        request: Request<FeeReserveRequest>,
+    ) -> Result<Response<FeeReserveResponse>, Status> {
+        let request = request.into_inner();
+        let unit = parse_unit(&request.unit)?;
+        let withdrawer = self.liquidity_source.withdrawer();
+
+        let payment_request = withdrawer
+            .deserialize_payment_request(&request.request)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let fee_reserve = withdrawer
+            .fee_reserve(&payment_request, unit)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(FeeReserveResponse {
+            fee_reserve: fee_reserve.into(),
+        }))
+    }
+
+    async fn compute_total_amount_expected(
+        &self,
+        request: Request<ComputeTotalAmountExpectedRequest>,
+    ) -> Result<Response<ComputeTotalAmountExpectedResponse>, Status> {
+        let request = request.into_inner();
+        let unit = parse_unit(&request.unit)?;
+        let withdrawer = self.liquidity_source.withdrawer();
+
+        let payment_request = withdrawer
+            .deserialize_payment_request(&request.request)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let amount = withdrawer
+            .compute_total_amount_expected(payment_request, unit, Amount::from(request.fee))
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(ComputeTotalAmountExpectedResponse {
+            amount: amount.into(),
+        }))
+    }
+
+    async fn proceed_to_payment(
+        &self,
+        request: Request<ProceedToPaymentRequest>,
+    ) -> Result<Response<ProceedToPaymentResponse>, Status> {
+        let request = request.into_inner();
+        let quote_id = parse_quote_id(&request.quote_id)?;
+        let mut withdrawer = self.liquidity_source.withdrawer();
+
+        let payment_request = withdrawer
+            .deserialize_payment_request(&request.request)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let state = withdrawer
+            .proceed_to_payment(quote_id, payment_request, request.expiry)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ProceedToPaymentResponse {
+            state: MeltQuoteState::from(state).into(),
+        }))
+    }
+
+    type SubscribePaymentEventsStream = PaymentEventStream;
+
+    async fn subscribe_payment_events(
+        &self,
+        request: Request<SubscribePaymentEventsRequest>,
+    ) -> Result<Response<Self::SubscribePaymentEventsStream>, Status> {
+        Ok(Response::new(
+            self.payment_events
+                .subscribe(request.into_inner().after_sequence),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment_event(event_idx: u64) -> PaymentEvent {
+        PaymentEvent {
+            event_idx,
+            ..Default::default()
+        }
+    }
+
+    async fn received(stream: PaymentEventStream, n: usize) -> Vec<(u64, u64)> {
+        stream
+            .take(n)
+            .map(|event| {
+                let event = event.unwrap();
+                (event.sequence, event.event.unwrap().event_idx)
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn subscriptions_resume_after_their_cursor() {
+        let publisher = PaymentEventPublisher::default();
+        publisher.publish(payment_event(0));
+        publisher.publish(payment_event(1));
+
+        let all = received(publisher.subscribe(None), 2).await;
+        assert_eq!(all[1].0, all[0].0 + 1);
+
+        let resumed = publisher.subscribe(Some(all[0].0));
+        publisher.publish(payment_event(2));
+        assert_eq!(
+            received(resumed, 2).await,
+            vec![(all[1].0, 1), (all[1].0 + 1, 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn history_is_bounded() {
+        let publisher = PaymentEventPublisher::default();
+        for event_idx in 0..EVENTS_HISTORY_CAPACITY as u64 + 1 {
+            publisher.publish(payment_event(event_idx));
+        }
+
+        let history = publisher.history.lock().unwrap();
+        assert_eq!(history.events.len(), EVENTS_HISTORY_CAPACITY);
+        assert_eq!(history.events[0].event.as_ref().unwrap().event_idx, 1);
+    }
+}
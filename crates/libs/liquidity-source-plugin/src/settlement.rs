@@ -0,0 +1,223 @@
+//! Settle the quotes according to the payment events pushed by a plugin
+//!
+//! An interrupted subscription resumes after the last event received.
+//! The first one replays every event retained by the plugin, the payment events being stored only once.
+use std::{str::FromStr, time::Duration};
+
+use db_node::{SharedStorage, StorageConn};
+use nuts::{Amount, nut04::MintQuoteState, nut05::MeltQuoteState};
+use primitive_types::U256;
+use starknet_types::{Asset, Unit};
+use tonic::transport::Channel;
+use tracing::{Level, debug, error, event};
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    proto::{
+        PaymentDirection, PaymentEvent, SequencedPaymentEvent, SubscribePaymentEventsRequest,
+        liquidity_source_plugin_client::LiquiditySourcePluginClient,
+    },
+};
+
+/// Delay before subscribing again once the stream of events has been interrupted
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+pub async fn run(storage: SharedStorage, mut client: LiquiditySourcePluginClient<Channel>) {
+    let mut last_sequence = None;
+    loop {
+        match listen_to_plugin(&storage, &mut client, &mut last_sequence).await {
+            Ok(()) => {
+                error!(name: "plugin-events-error", name = "plugin-events-error", error = "returned")
+            }
+            Err(err) => {
+                error!(name: "plugin-events-error", name = "plugin-events-error", error = %err)
+            }
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn listen_to_plugin(
+    storage: &SharedStorage,
+    client: &mut LiquiditySourcePluginClient<Channel>,
+    last_sequence: &mut Option<u64>,
+) -> Result<(), Error> {
+    let mut events = client
+        .subscribe_payment_events(SubscribePaymentEventsRequest {
+            after_sequence: *last_sequence,
+        })
+        .await?
+        .into_inner();
+
+    while let Some(SequencedPaymentEvent { sequence, event }) = events.message().await? {
+        match event {
+            Some(payment_event) => {
+                let mut db_conn = storage.acquire().await?;
+                process_payment_event(db_conn.as_mut(), payment_event).await?;
+            }
+            None => error!("got a sequenced payment event without event"),
+        }
+        *last_sequence = Some(sequence);
+    }
+
+    Ok(())
+}
+
+async fn process_payment_event(
+    db_conn: &mut dyn StorageConn,
+    payment_event: PaymentEvent,
+) -> Result<(), Error> {
+    let Ok(invoice_id) = <[u8; 32]>::try_from(payment_event.invoice_id.as_slice()) else {
+        error!(
+            "got a payment event with an invalid invoice id of {} bytes",
+            payment_event.invoice_id.len()
+        );
+        return Ok(());
+    };
+    let Ok(amount) = U256::from_dec_str(&payment_event.amount) else {
+        error!(
+            "got a payment event with an invalid amount `{}`",
+            payment_event.amount
+        );
+        return Ok(());
+    };
+
+    let is_mint = match payment_event.direction() {
+        PaymentDirection::Deposit => true,
+        PaymentDirection::Withdrawal => false,
+        PaymentDirection::Unspecified => {
+            error!("got a payment event without direction");
+            return Ok(());
+        }
+    };
+    let quote = if is_mint {
+        db_conn
+            .get_or_restore_mint_quote_by_invoice_id(&invoice_id)
+            .await?
+    } else {
+        db_conn
+            .get_or_restore_melt_quote_by_invoice_id(&invoice_id)
+            .await?
+    };
+    let Some((quote_id, quote_amount, unit)) = quote else {
+        debug!("no quote for invoice_id {}", hex::encode(invoice_id));
+        return Ok(());
+    };
+    let unit = Unit::from_str(&unit).map_err(|_| db_node::Error::InvalidUnit(unit))?;
+
+    let Ok(asset) = Asset::from_str(&payment_event.asset) else {
+        error!(
+            "Got payment for quote {}, using asset {}, which is not a registered asset.",
+            quote_id, payment_event.asset
+        );
+        return Ok(());
+    };
+    if !unit.is_asset_supported(asset) {
+        // Could just be someone reusing an already existing invoice id he saw onchain
+        debug!(
+            "Got payment for quote {}, that expect unit {}, using asset {}, which is not the expected one.",
+            quote_id, unit, asset
+        );
+        return Ok(());
+    }
+
+    let db_payment_event = db_node::PaymentEvent {
+        block_id: payment_event.block_id.clone(),
+        tx_hash: payment_event.tx_hash.clone(),
+        event_idx: payment_event.event_idx,
+        asset: payment_event.asset.clone(),
+        payee: payment_event.payee.clone(),
+        invoice_id,
+        payer: payment_event.payer.clone(),
+        amount_low: amount.low_u128().to_string(),
+        amount_high: (amount >> 128).low_u128().to_string(),
+    };
+    if is_mint {
+        handle_mint_payment(db_conn, quote_id, &db_payment_event, unit, quote_amount).await
+    } else {
+        handle_melt_payment(db_conn, quote_id, &db_payment_event, unit).await
+    }
+}
+
+async fn handle_mint_payment(
+    db_conn: &mut dyn StorageConn,
+    quote_id: Uuid,
+    payment_event: &db_node::PaymentEvent,
+    unit: Unit,
+    quote_amount: Amount,
+) -> Result<(), Error> {
+    db_conn.insert_mint_payment_event(payment_event).await?;
+    let current_paid = sum_payments(
+        db_conn
+            .get_mint_current_paid(&payment_event.invoice_id)
+            .await?,
+    )?;
+
+    // Events are replayed on the first subscription, an issued quote must not be paid again.
+    // A payment received after the quote expired is still owed to the user.
+    if current_paid >= unit.convert_amount_into_u256(quote_amount)
+        && matches!(
+            db_conn.get_mint_quote_response(quote_id).await?.state,
+            MintQuoteState::Unpaid | MintQuoteState::Expired
+        )
+    {
+        db_conn
+            .set_mint_quote_state(quote_id, MintQuoteState::Paid)
+            .await?;
+        event!(
+            name: "mint-quote-paid",
+            Level::INFO,
+            name = "mint-quote-paid",
+            %quote_id,
+        );
+    }
+
+    Ok(())
+}
+
+async fn handle_melt_payment(
+    db_conn: &mut dyn StorageConn,
+    quote_id: Uuid,
+    payment_event: &db_node::PaymentEvent,
+    unit: Unit,
+) -> Result<(), Error> {
+    db_conn.insert_melt_payment_event(payment_event).await?;
+    let current_paid = sum_payments(
+        db_conn
+            .get_melt_current_paid(&payment_event.invoice_id)
+            .await?,
+    )?;
+
+    // The fee reserve is not necessarily spent
+    let quote = db_conn.get_melt_quote_response(quote_id).await?;
+    if current_paid >= unit.convert_amount_into_u256(quote.amount - quote.fee)
+        && quote.state != MeltQuoteState::Paid
+    {
+        db_conn
+            .set_melt_quote_state(quote_id, MeltQuoteState::Paid)
+            .await?;
+        event!(
+            name: "melt-quote-paid",
+            Level::INFO,
+            name = "melt-quote-paid",
+            %quote_id,
+        );
+    }
+
+    Ok(())
+}
+
+/// Sum the amounts stored as low and high decimal parts
+fn sum_payments(amounts: Vec<(String, String)>) -> Result<U256, Error> {
+    amounts
+        .into_iter()
+        .try_fold(U256::zero(), |acc, (low, high)| {
+            let low =
+                U256::from_dec_str(&low).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            let high =
+                U256::from_dec_str(&high).map_err(|_| db_node::Error::DbToRuntimeConversion)?;
+            acc.checked_add(low + (high << 128))
+                .ok_or(Error::AmountPaidOverflow)
+        })
+}
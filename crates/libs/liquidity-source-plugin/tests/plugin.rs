@@ -0,0 +1,180 @@
+//! Serve an in-process Lightning node as a plugin, and use it from the node side over a unix socket
+use std::{sync::Arc, time::Duration};
+
+use db_node::{SharedStorage, Storage, sqlite::SqliteStorage};
+use lightning_liquidity_source::{FakeNode, LightningBackend, LightningLiquiditySource};
+use liquidity_source::{DepositInterface, LiquiditySource, WithdrawInterface};
+use liquidity_source_plugin::{
+    Error, PaymentDirection, PaymentEvent, PaymentEventPublisher, PluginConfig, PluginEndpoint,
+    PluginLiquiditySource, PluginServer,
+};
+use nuts::{Amount, nut04::MintQuoteState, nut05::MeltQuoteState};
+use starknet_types::{Asset, Unit};
+use test_utils::liquidity_source::{unix_time, wait_for_mint_quote_state};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn lightning_plugin_over_unix_socket() {
+    let storage = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+    storage.run_migrations().await.unwrap();
+    let storage: SharedStorage = Arc::new(storage);
+    let node = Arc::new(FakeNode::default());
+    let liquidity_source = LightningLiquiditySource::new(storage.clone(), node.clone(), 0);
+    let payment_events = PaymentEventPublisher::default();
+
+    let socket_path = std::env::temp_dir().join(format!("plugin-{}.sock", Uuid::new_v4()));
+    let endpoint = PluginEndpoint::Unix(socket_path.clone());
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+    let server = PluginServer::new("bolt11", liquidity_source.clone(), payment_events.clone());
+    let server_endpoint = endpoint.clone();
+    let server_handle = tokio::spawn(async move {
+        server
+            .serve(&server_endpoint, async {
+                let _ = shutdown_receiver.await;
+            })
+            .await
+    });
+    while !socket_path.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // The plugin must serve the method it is configured for
+    assert!(matches!(
+        PluginLiquiditySource::connect(
+            storage.clone(),
+            "lightning",
+            &PluginConfig {
+                endpoint: endpoint.clone()
+            },
+        )
+        .await,
+        Err(Error::MethodMismatch(..))
+    ));
+    let plugin =
+        PluginLiquiditySource::connect(storage.clone(), "bolt11", &PluginConfig { endpoint })
+            .await
+            .unwrap();
+    assert_eq!(plugin.units(), [Unit::SAT, Unit::MSAT]);
+    let expiry = unix_time() + 600;
+
+    // The invoice ids are the ones of the served liquidity source
+    let quote_id = Uuid::new_v4();
+    let invoice_id: [u8; 32] = plugin
+        .compute_invoice_id(quote_id, expiry)
+        .await
+        .unwrap()
+        .into();
+    let expected_invoice_id: [u8; 32] = liquidity_source
+        .compute_invoice_id(quote_id, expiry)
+        .await
+        .unwrap()
+        .into();
+    assert_eq!(invoice_id, expected_invoice_id);
+
+    // Mint, the quote being paid once the plugin pushes the deposit
+    let mint_quote_id = Uuid::new_v4();
+    let mint_amount = Amount::from(10u64);
+    let (invoice_id, payload) = plugin
+        .depositer()
+        .generate_deposit_payload(mint_quote_id, Unit::SAT, mint_amount, expiry)
+        .await
+        .unwrap();
+    let invoice_id: [u8; 32] = invoice_id.into();
+    assert!(payload.starts_with("lnbcrt"));
+    storage
+        .acquire()
+        .await
+        .unwrap()
+        .insert_mint_quote(
+            mint_quote_id,
+            invoice_id,
+            Unit::SAT.as_str(),
+            mint_amount,
+            &payload,
+            expiry,
+        )
+        .await
+        .unwrap();
+
+    payment_events.publish(PaymentEvent {
+        direction: PaymentDirection::Deposit.into(),
+        invoice_id: invoice_id.to_vec(),
+        asset: Asset::BTC.to_string(),
+        amount: Unit::SAT.convert_amount_into_u256(mint_amount).to_string(),
+        payer: "payer".to_string(),
+        payee: "cashier".to_string(),
+        block_id: "block".to_string(),
+        tx_hash: "tx".to_string(),
+        event_idx: 0,
+    });
+    wait_for_mint_quote_state(&storage, mint_quote_id, MintQuoteState::Paid).await;
+
+    // A deposit received after the quote expired is still credited
+    let late_quote_id = Uuid::new_v4();
+    let (late_invoice_id, late_payload) = plugin
+        .depositer()
+        .generate_deposit_payload(late_quote_id, Unit::SAT, mint_amount, expiry)
+        .await
+        .unwrap();
+    let late_invoice_id: [u8; 32] = late_invoice_id.into();
+    let mut conn = storage.acquire().await.unwrap();
+    conn.insert_mint_quote(
+        late_quote_id,
+        late_invoice_id,
+        Unit::SAT.as_str(),
+        mint_amount,
+        &late_payload,
+        unix_time() - 1,
+    )
+    .await
+    .unwrap();
+    assert_eq!(conn.expire_unpaid_mint_quotes().await.unwrap(), 1);
+    drop(conn);
+    payment_events.publish(PaymentEvent {
+        direction: PaymentDirection::Deposit.into(),
+        invoice_id: late_invoice_id.to_vec(),
+        asset: Asset::BTC.to_string(),
+        amount: Unit::SAT.convert_amount_into_u256(mint_amount).to_string(),
+        payer: "payer".to_string(),
+        payee: "cashier".to_string(),
+        block_id: "block".to_string(),
+        tx_hash: "late-tx".to_string(),
+        event_idx: 0,
+    });
+    wait_for_mint_quote_state(&storage, late_quote_id, MintQuoteState::Paid).await;
+
+    // Melt, the request being validated and paid by the plugin
+    let invoice = node
+        .create_invoice(1_500, "melt".to_string(), Duration::from_secs(600))
+        .await
+        .unwrap();
+    let mut withdrawer = plugin.withdrawer();
+    let request = withdrawer
+        .deserialize_payment_request(&invoice.bolt11)
+        .unwrap();
+    assert_eq!(
+        withdrawer
+            .compute_total_amount_expected(request.clone(), Unit::SAT, Amount::ZERO)
+            .await
+            .unwrap(),
+        Amount::from(2u64)
+    );
+    assert!(
+        withdrawer
+            .compute_total_amount_expected("not an invoice".to_string(), Unit::SAT, Amount::ZERO)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        withdrawer
+            .proceed_to_payment(Uuid::new_v4(), request, expiry)
+            .await
+            .unwrap(),
+        MeltQuoteState::Paid
+    );
+    assert_eq!(node.paid_invoices(), vec![invoice.bolt11]);
+
+    shutdown_sender.send(()).unwrap();
+    server_handle.await.unwrap().unwrap();
+}
@@ -2,11 +2,12 @@ use nuts::Amount;
 use starknet_types::Unit;
 use uuid::Uuid;
 
-pub trait DepositInterface: Send {
+#[async_trait::async_trait]
+pub trait DepositInterface: Send + Sync {
     type Error: std::error::Error + Send + Sync + 'static;
     type InvoiceId: Into<[u8; 32]> + Send + Sync + 'static;
 
-    fn generate_deposit_payload(
+    async fn generate_deposit_payload(
         &self,
         quote_id: Uuid,
         unit: Unit,
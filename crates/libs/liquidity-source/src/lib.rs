@@ -7,7 +7,9 @@ use nuts::traits::Unit;
 use uuid::Uuid;
 pub use withdraw::WithdrawInterface;
 
-pub trait LiquiditySource {
+#[async_trait::async_trait]
+pub trait LiquiditySource: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
     type InvoiceId: Into<[u8; 32]> + LowerHex + UpperHex + Clone + Send + Sync + 'static;
     type Unit: Unit;
     type Depositer: DepositInterface<InvoiceId = Self::InvoiceId>;
@@ -15,5 +17,12 @@ pub trait LiquiditySource {
 
     fn depositer(&self) -> Self::Depositer;
     fn withdrawer(&self) -> Self::Withdrawer;
-    fn compute_invoice_id(&self, quote_id: Uuid, expiry: u64) -> Self::InvoiceId;
+    /// The units that can be minted and melted through this liquidity source
+    fn units(&self) -> Vec<Self::Unit>;
+    /// Async so that it can be computed by a liquidity source living out of the process
+    async fn compute_invoice_id(
+        &self,
+        quote_id: Uuid,
+        expiry: u64,
+    ) -> Result<Self::InvoiceId, Self::Error>;
 }
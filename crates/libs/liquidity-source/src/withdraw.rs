@@ -2,13 +2,24 @@ use nuts::{Amount, nut05::MeltQuoteState, traits::Unit};
 use uuid::Uuid;
 
 #[async_trait::async_trait]
-pub trait WithdrawInterface: Send {
+pub trait WithdrawInterface: Send + Sync {
     type Error: std::error::Error + Send + Sync + 'static;
     type Request: std::fmt::Debug + serde::Serialize + for<'de> serde::Deserialize<'de> + Send;
     type Unit: Unit;
     type InvoiceId: Into<[u8; 32]> + Send + Sync + 'static;
 
-    fn compute_total_amount_expected(
+    /// The most the payment of the request may cost on top of its amount
+    ///
+    /// Reserved when quoting, the part left unused is returned to the wallet as change.
+    async fn fee_reserve(
+        &self,
+        _request: &Self::Request,
+        _unit: Self::Unit,
+    ) -> Result<Amount, Self::Error> {
+        Ok(Amount::ZERO)
+    }
+
+    async fn compute_total_amount_expected(
         &self,
         request: Self::Request,
         unit: Self::Unit,
@@ -20,6 +31,9 @@ pub trait WithdrawInterface: Send {
         raw_json_string: &str,
     ) -> Result<Self::Request, Self::Error>;
 
+    /// Pay the request, returns the new state of the quote
+    ///
+    /// Unpaid if the payment failed without anything being sent, the inputs of the melt being then unspent.
     async fn proceed_to_payment(
         &mut self,
         quote_id: Uuid,
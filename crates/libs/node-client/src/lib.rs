@@ -1,4 +1,8 @@
-use nuts::{nut04, nut05};
+use nuts::{liabilities, nut04, nut05};
+#[cfg(feature = "admin")]
+pub use proto::admin::admin_client::AdminClient;
+#[cfg(feature = "admin")]
+pub use proto::admin::*;
 pub use proto::bdhke::{BlindSignature, BlindedMessage, Proof};
 #[cfg(feature = "keyset-rotation")]
 pub use proto::keyset_rotation::keyset_rotation_service_client::KeysetRotationServiceClient;
@@ -8,6 +12,10 @@ pub use proto::node::node_client::NodeClient;
 pub use proto::node::*;
 
 mod proto {
+    #[cfg(feature = "admin")]
+    pub mod admin {
+        tonic::include_proto!("admin");
+    }
     pub mod bdhke {
         tonic::include_proto!("bdhke");
     }
@@ -40,7 +48,10 @@ impl TryFrom<MeltQuoteState> for nut05::MeltQuoteState {
 impl From<nut05::MeltQuoteState> for MeltQuoteState {
     fn from(value: nut05::MeltQuoteState) -> Self {
         match value {
-            nut05::MeltQuoteState::Unpaid => MeltQuoteState::MlqsUnpaid,
+            // The quote `expiry` already tells the client it expired
+            nut05::MeltQuoteState::Unpaid | nut05::MeltQuoteState::Expired => {
+                MeltQuoteState::MlqsUnpaid
+            }
             nut05::MeltQuoteState::Pending => MeltQuoteState::MlqsPending,
             nut05::MeltQuoteState::Paid => MeltQuoteState::MlqsPaid,
         }
@@ -63,13 +74,47 @@ impl TryFrom<MintQuoteState> for nut04::MintQuoteState {
 impl From<nut04::MintQuoteState> for MintQuoteState {
     fn from(value: nut04::MintQuoteState) -> Self {
         match value {
-            nut04::MintQuoteState::Unpaid => MintQuoteState::MnqsUnpaid,
+            // The quote `expiry` already tells the client it expired
+            nut04::MintQuoteState::Unpaid | nut04::MintQuoteState::Expired => {
+                MintQuoteState::MnqsUnpaid
+            }
             nut04::MintQuoteState::Paid => MintQuoteState::MnqsPaid,
             nut04::MintQuoteState::Issued => MintQuoteState::MnqsIssued,
         }
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("The merkle sum node is invalid")]
+pub struct InvalidMerkleSumNode;
+
+impl TryFrom<MerkleSumNode> for liabilities::SumNode {
+    type Error = InvalidMerkleSumNode;
+
+    fn try_from(value: MerkleSumNode) -> Result<Self, InvalidMerkleSumNode> {
+        Ok(liabilities::SumNode {
+            hash: value.hash.try_into().map_err(|_| InvalidMerkleSumNode)?,
+            sum: value.sum.into(),
+        })
+    }
+}
+
+pub fn inclusion_proof_from_path(
+    path: Vec<MerkleProofStep>,
+) -> Result<liabilities::InclusionProof, InvalidMerkleSumNode> {
+    let path = path
+        .into_iter()
+        .map(|step| {
+            Ok(liabilities::ProofStep {
+                sibling: step.sibling.ok_or(InvalidMerkleSumNode)?.try_into()?,
+                sibling_is_left: step.sibling_is_left,
+            })
+        })
+        .collect::<Result<_, InvalidMerkleSumNode>>()?;
+
+    Ok(liabilities::InclusionProof { path })
+}
+
 use std::hash::{DefaultHasher, Hash, Hasher};
 
 /// Hash MintRequest to a string
@@ -101,6 +146,11 @@ pub fn hash_melt_request(request: &MeltRequest) -> u64 {
         input.secret.hash(&mut hasher);
         input.unblind_signature.hash(&mut hasher);
     }
+    for output in &request.outputs {
+        output.amount.hash(&mut hasher);
+        output.keyset_id.hash(&mut hasher);
+        output.blinded_secret.hash(&mut hasher);
+    }
 
     hasher.finish()
 }
@@ -122,3 +172,21 @@ pub fn hash_swap_request(request: &SwapRequest) -> u64 {
 
     hasher.finish()
 }
+
+pub fn hash_exchange_request(request: &ExchangeRequest) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for input in &request.inputs {
+        input.amount.hash(&mut hasher);
+        input.keyset_id.hash(&mut hasher);
+        input.secret.hash(&mut hasher);
+        input.unblind_signature.hash(&mut hasher);
+    }
+    for output in &request.outputs {
+        output.amount.hash(&mut hasher);
+        output.keyset_id.hash(&mut hasher);
+        output.blinded_secret.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
@@ -0,0 +1,248 @@
+//! Proof of liabilities
+//!
+//! For each keyset, a node commits to the blind signatures it issued and to the proofs it burned
+//! by publishing the roots of two Merkle sum trees.
+//! Each node of those trees commits both to a hash and to the total amount of the leaves below it,
+//! so the total published alongside a root can't omit the amount of any of its leaves.
+//!
+//! A wallet checks that the node isn't hiding some of its liabilities by requesting
+//! the inclusion proofs of its own signatures and spent proofs, and verifying them against the roots.
+
+use bitcoin::hashes::Hash;
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use num_traits::CheckedAdd;
+
+use crate::{Amount, Error, nut01::PublicKey};
+
+const LEAF_PREFIX: u8 = 0x00;
+const BRANCH_PREFIX: u8 = 0x01;
+
+/// A node of a Merkle sum tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SumNode {
+    pub hash: [u8; 32],
+    pub sum: Amount,
+}
+
+impl SumNode {
+    /// The root of a tree without leaves
+    pub const EMPTY: SumNode = SumNode {
+        hash: [0; 32],
+        sum: Amount::ZERO,
+    };
+
+    /// The leaf committing to a blind signature issued by the node
+    pub fn issued_leaf(
+        amount: Amount,
+        blinded_secret: &PublicKey,
+        blind_signature: &PublicKey,
+    ) -> Self {
+        let mut bytes = Vec::with_capacity(1 + 8 + 33 + 33);
+        bytes.push(LEAF_PREFIX);
+        bytes.extend_from_slice(&u64::from(amount).to_be_bytes());
+        bytes.extend_from_slice(&blinded_secret.to_bytes());
+        bytes.extend_from_slice(&blind_signature.to_bytes());
+
+        Self {
+            hash: Sha256Hash::hash(&bytes).to_byte_array(),
+            sum: amount,
+        }
+    }
+
+    /// The leaf committing to a proof burned by the node
+    pub fn burned_leaf(amount: Amount, y: &PublicKey) -> Self {
+        let mut bytes = Vec::with_capacity(1 + 8 + 33);
+        bytes.push(LEAF_PREFIX);
+        bytes.extend_from_slice(&u64::from(amount).to_be_bytes());
+        bytes.extend_from_slice(&y.to_bytes());
+
+        Self {
+            hash: Sha256Hash::hash(&bytes).to_byte_array(),
+            sum: amount,
+        }
+    }
+
+    fn branch(left: &SumNode, right: &SumNode) -> Result<Self, Error> {
+        let sum = left
+            .sum
+            .checked_add(&right.sum)
+            .ok_or(Error::AmountOverflow)?;
+
+        let mut bytes = Vec::with_capacity(1 + 2 * (32 + 8));
+        bytes.push(BRANCH_PREFIX);
+        bytes.extend_from_slice(&left.hash);
+        bytes.extend_from_slice(&u64::from(left.sum).to_be_bytes());
+        bytes.extend_from_slice(&right.hash);
+        bytes.extend_from_slice(&u64::from(right.sum).to_be_bytes());
+
+        Ok(Self {
+            hash: Sha256Hash::hash(&bytes).to_byte_array(),
+            sum,
+        })
+    }
+}
+
+/// A Merkle sum tree, kept in memory level by level
+///
+/// When a level has an odd number of nodes, the last one is moved up unchanged.
+#[derive(Debug, Clone)]
+pub struct MerkleSumTree {
+    levels: Vec<Vec<SumNode>>,
+}
+
+impl MerkleSumTree {
+    /// Fails if the sum of the leaves overflows
+    pub fn new(leaves: Vec<SumNode>) -> Result<Self, Error> {
+        let mut levels = vec![leaves];
+
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => SumNode::branch(left, right),
+                    [single] => Ok(*single),
+                    _ => unreachable!("chunks have one or two elements"),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            levels.push(next);
+        }
+
+        Ok(Self { levels })
+    }
+
+    pub fn root(&self) -> SumNode {
+        self.levels[self.levels.len() - 1]
+            .first()
+            .copied()
+            .unwrap_or(SumNode::EMPTY)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Returns the path from the leaf at `index` to the root, or None if out of bounds
+    pub fn inclusion_proof(&self, mut index: usize) -> Option<InclusionProof> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling_index = index ^ 1;
+            // The last node of an odd level has no sibling
+            if let Some(sibling) = level.get(sibling_index) {
+                path.push(ProofStep {
+                    sibling: *sibling,
+                    sibling_is_left: sibling_index < index,
+                });
+            }
+            index /= 2;
+        }
+
+        Some(InclusionProof { path })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofStep {
+    pub sibling: SumNode,
+    pub sibling_is_left: bool,
+}
+
+/// The siblings of each node on the path from a leaf to the root of a [`MerkleSumTree`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InclusionProof {
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Returns true if `leaf` is part of the tree whose root is `root`
+    pub fn verify(&self, leaf: &SumNode, root: &SumNode) -> bool {
+        let computed_root = self.path.iter().try_fold(*leaf, |node, step| {
+            if step.sibling_is_left {
+                SumNode::branch(&step.sibling, &node)
+            } else {
+                SumNode::branch(&node, &step.sibling)
+            }
+        });
+
+        matches!(computed_root, Ok(computed_root) if computed_root == *root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Amount, dhke::hash_to_curve};
+
+    use super::{MerkleSumTree, SumNode};
+
+    fn leaves(n: u64) -> Vec<SumNode> {
+        (0..n)
+            .map(|i| {
+                let y = hash_to_curve(&i.to_be_bytes()).unwrap();
+                SumNode::burned_leaf(Amount::from(1 << (i % 8)), &y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = MerkleSumTree::new(vec![]).unwrap();
+
+        assert_eq!(tree.root(), SumNode::EMPTY);
+        assert!(tree.inclusion_proof(0).is_none());
+    }
+
+    #[test]
+    fn root_sum_is_the_sum_of_the_leaves() {
+        for n in 1..20 {
+            let leaves = leaves(n);
+            let expected = Amount::try_sum(leaves.iter().map(|l| l.sum)).unwrap();
+            let tree = MerkleSumTree::new(leaves).unwrap();
+
+            assert_eq!(tree.root().sum, expected);
+        }
+    }
+
+    #[test]
+    fn every_leaf_has_a_valid_inclusion_proof() {
+        for n in 1..20 {
+            let leaves = leaves(n);
+            let tree = MerkleSumTree::new(leaves.clone()).unwrap();
+            let root = tree.root();
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.inclusion_proof(i).unwrap();
+                assert!(proof.verify(leaf, &root), "leaf {} of {}", i, n);
+            }
+        }
+    }
+
+    #[test]
+    fn proof_does_not_verify_another_leaf_or_sum() {
+        let leaves = leaves(7);
+        let tree = MerkleSumTree::new(leaves.clone()).unwrap();
+        let root = tree.root();
+        let proof = tree.inclusion_proof(2).unwrap();
+
+        assert!(!proof.verify(&leaves[3], &root));
+
+        let mut lower_root = root;
+        lower_root.sum = Amount::from(u64::from(root.sum) - 1);
+        assert!(!proof.verify(&leaves[2], &lower_root));
+    }
+
+    #[test]
+    fn overflowing_sum_is_rejected() {
+        let y = hash_to_curve(b"overflow").unwrap();
+        let leaf = SumNode::burned_leaf(Amount::from(u64::MAX), &y);
+
+        assert!(MerkleSumTree::new(vec![leaf, leaf]).is_err());
+    }
+}
@@ -7,6 +7,7 @@ mod amount;
 mod types;
 
 pub mod dhke;
+pub mod liabilities;
 pub mod nut00;
 pub mod nut01;
 pub mod nut02;
@@ -15,6 +16,7 @@ pub mod nut04;
 pub mod nut05;
 pub mod nut06;
 pub mod nut07;
+pub mod nut09;
 #[cfg(feature = "nut13")]
 pub mod nut13;
 #[cfg(feature = "nut19")]
@@ -26,6 +26,39 @@ impl CashuError {
     }
 }
 
+/// Error codes returned along with a [CashuError]
+///
+/// <https://github.com/cashubtc/nuts/blob/main/error_codes.md>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ErrorCode {
+    /// Not covered by the spec
+    Unknown = 0,
+    OutputAlreadySigned = 10002,
+    ProofVerificationFailed = 10003,
+    ProofAlreadySpent = 11001,
+    TransactionUnbalanced = 11002,
+    UnitNotSupported = 11005,
+    AmountOutsideLimit = 11006,
+    DuplicateInputs = 11007,
+    DuplicateOutputs = 11008,
+    MultipleUnits = 11009,
+    KeysetNotFound = 12001,
+    KeysetInactive = 12002,
+    QuoteNotPaid = 20001,
+    TokensAlreadyIssued = 20002,
+    MintingDisabled = 20003,
+    QuotePending = 20005,
+    InvoiceAlreadyPaid = 20006,
+    QuoteExpired = 20007,
+}
+
+impl From<ErrorCode> for u16 {
+    fn from(value: ErrorCode) -> Self {
+        value as u16
+    }
+}
+
 /// List of [Proof]
 pub type Proofs = Vec<Proof>;
 
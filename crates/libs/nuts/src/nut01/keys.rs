@@ -27,6 +27,16 @@ impl From<SetKeyPairs> for SetPubKeys {
     }
 }
 
+impl From<BTreeMap<Amount, PublicKey>> for SetPubKeys {
+    fn from(keys: BTreeMap<Amount, PublicKey>) -> Self {
+        Self(
+            keys.into_iter()
+                .map(|(amount, public_key)| (AmountStr::from(amount), public_key))
+                .collect(),
+        )
+    }
+}
+
 impl SetPubKeys {
     /// Create new [`Keys`]
     #[inline]
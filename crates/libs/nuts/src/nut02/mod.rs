@@ -81,6 +81,9 @@ pub struct KeySetInfo<U: Unit> {
     /// Input Fee PPK
     #[serde(default = "default_input_fee_ppk")]
     pub input_fee_ppk: u64,
+    /// Unix timestamp after which the mint won't accept proofs of this keyset anymore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_expiry: Option<u64>,
 }
 
 fn default_input_fee_ppk() -> u64 {
@@ -294,7 +297,14 @@ mod test {
     fn test_deserialization_keyset_info() {
         let h = r#"{"id":"009a1f293253e41e","unit":"sat","active":true}"#;
 
-        let _keyset_response: KeySetInfo<TestUnit> = serde_json::from_str(h).unwrap();
+        let keyset_response: KeySetInfo<TestUnit> = serde_json::from_str(h).unwrap();
+        assert_eq!(keyset_response.final_expiry, None);
+
+        let h =
+            r#"{"id":"009a1f293253e41e","unit":"sat","active":false,"final_expiry":1754296607}"#;
+
+        let keyset_response: KeySetInfo<TestUnit> = serde_json::from_str(h).unwrap();
+        assert_eq!(keyset_response.final_expiry, Some(1754296607));
     }
 
     #[test]
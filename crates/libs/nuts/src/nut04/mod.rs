@@ -35,6 +35,10 @@ pub enum MintQuoteState {
     Paid,
     /// ecash issued for quote
     Issued,
+    /// Quote expired before being paid
+    ///
+    /// Reported as unpaid to the clients, which can tell from the quote expiry
+    Expired,
 }
 
 impl core::fmt::Display for MintQuoteState {
@@ -46,11 +50,26 @@ impl core::fmt::Display for MintQuoteState {
                 MintQuoteState::Unpaid => "UNPAID",
                 MintQuoteState::Paid => "PAID",
                 MintQuoteState::Issued => "ISSUED",
+                MintQuoteState::Expired => "EXPIRED",
             }
         )
     }
 }
 
+impl core::str::FromStr for MintQuoteState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UNPAID" => Ok(MintQuoteState::Unpaid),
+            "PAID" => Ok(MintQuoteState::Paid),
+            "ISSUED" => Ok(MintQuoteState::Issued),
+            "EXPIRED" => Ok(MintQuoteState::Expired),
+            _ => Err(Error::UnknownState),
+        }
+    }
+}
+
 #[cfg(feature = "rusqlite")]
 impl ToSql for MintQuoteState {
     fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
@@ -58,6 +77,7 @@ impl ToSql for MintQuoteState {
             MintQuoteState::Unpaid => 1,
             MintQuoteState::Paid => 2,
             MintQuoteState::Issued => 3,
+            MintQuoteState::Expired => 4,
         }))
     }
 }
@@ -69,6 +89,7 @@ impl FromSql for MintQuoteState {
             1 => Ok(MintQuoteState::Unpaid),
             2 => Ok(MintQuoteState::Paid),
             3 => Ok(MintQuoteState::Issued),
+            4 => Ok(MintQuoteState::Expired),
             _ => Err(FromSqlError::Other(Box::new(Error::UnknownState))),
         })
     }
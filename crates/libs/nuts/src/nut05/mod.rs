@@ -1,6 +1,10 @@
 //! NUT-05: Melting Tokens
 
-use crate::{Amount, nut00::Proofs, traits::Unit};
+use crate::{
+    Amount,
+    nut00::{BlindSignature, BlindedMessage, Proofs},
+    traits::Unit,
+};
 #[cfg(feature = "rusqlite")]
 use rusqlite::{
     Result,
@@ -37,6 +41,10 @@ pub enum MeltQuoteState {
     Pending,
     /// Payment has been done on chain
     Paid,
+    /// Quote expired before being used
+    ///
+    /// Reported as unpaid to the clients, which can tell from the quote expiry
+    Expired,
 }
 
 impl From<MeltQuoteState> for i32 {
@@ -45,6 +53,7 @@ impl From<MeltQuoteState> for i32 {
             MeltQuoteState::Unpaid => 1,
             MeltQuoteState::Pending => 2,
             MeltQuoteState::Paid => 3,
+            MeltQuoteState::Expired => 4,
         }
     }
 }
@@ -58,11 +67,26 @@ impl core::fmt::Display for MeltQuoteState {
                 MeltQuoteState::Unpaid => "UNPAID",
                 MeltQuoteState::Pending => "PENDING",
                 MeltQuoteState::Paid => "PAID",
+                MeltQuoteState::Expired => "EXPIRED",
             }
         )
     }
 }
 
+impl core::str::FromStr for MeltQuoteState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UNPAID" => Ok(MeltQuoteState::Unpaid),
+            "PENDING" => Ok(MeltQuoteState::Pending),
+            "PAID" => Ok(MeltQuoteState::Paid),
+            "EXPIRED" => Ok(MeltQuoteState::Expired),
+            _ => Err(Error::UnknownState),
+        }
+    }
+}
+
 #[cfg(feature = "rusqlite")]
 impl ToSql for MeltQuoteState {
     fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
@@ -70,6 +94,7 @@ impl ToSql for MeltQuoteState {
             MeltQuoteState::Unpaid => 1,
             MeltQuoteState::Pending => 2,
             MeltQuoteState::Paid => 3,
+            MeltQuoteState::Expired => 4,
         }))
     }
 }
@@ -81,6 +106,7 @@ impl FromSql for MeltQuoteState {
             1 => Ok(MeltQuoteState::Unpaid),
             2 => Ok(MeltQuoteState::Pending),
             3 => Ok(MeltQuoteState::Paid),
+            4 => Ok(MeltQuoteState::Expired),
             _ => Err(FromSqlError::Other(Box::new(Error::UnknownState))),
         })
     }
@@ -102,6 +128,8 @@ pub struct MeltQuoteResponse<Q, U: Unit> {
     pub quote: Q,
     /// The amount that needs to be provided
     pub amount: Amount,
+    /// The fee reserve that needs to be provided on top of the amount
+    pub fee_reserve: Amount,
     /// The unit that needs to be provided
     pub unit: U,
     /// Quote State
@@ -118,12 +146,17 @@ pub struct MeltRequest<Q> {
     pub quote: Q,
     /// Proofs
     pub inputs: Proofs,
+    /// Blank outputs for the unused fee reserve [NUT-08]
+    #[serde(default)]
+    pub outputs: Option<Vec<BlindedMessage>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct MeltResponse {
     pub state: MeltQuoteState,
     pub transfer_ids: Option<Vec<String>>,
+    /// The signed blank outputs, returning the unused fee reserve [NUT-08]
+    pub change: Option<Vec<BlindSignature>>,
 }
 
 /// Melt Method Settings
@@ -1,7 +1,13 @@
 //! NUT-07: Token state check
+//!
+//! <https://github.com/cashubtc/nuts/blob/main/07.md>
+
+use serde::{Deserialize, Serialize};
 
 use crate::nut01::PublicKey;
-#[derive(Debug, Clone, PartialEq, Eq)]
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum ProofState {
     Unspecified,
     Unspent,
@@ -38,11 +44,45 @@ impl From<ProofState> for i32 {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckStateRequest {
+    #[serde(rename = "Ys")]
+    pub ys: Vec<PublicKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProofCheckState {
+    #[serde(rename = "Y")]
     pub y: PublicKey,
     pub state: ProofState,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CheckStateResponse {
+    #[serde(rename = "states")]
     pub proof_check_states: Vec<ProofCheckState>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckStateRequest, CheckStateResponse, ProofState};
+
+    #[test]
+    fn check_state_request_from_spec() {
+        let request: CheckStateRequest = serde_json::from_str(
+            r#"{"Ys": ["02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.ys.len(), 1);
+    }
+
+    #[test]
+    fn check_state_response_from_spec() {
+        let json = r#"{"states":[{"Y":"02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee","state":"SPENT"}]}"#;
+        let response: CheckStateResponse = serde_json::from_str(json).unwrap();
+
+        assert_eq!(response.proof_check_states[0].state, ProofState::Spent);
+        assert_eq!(serde_json::to_string(&response).unwrap(), json);
+    }
+}
@@ -23,11 +23,14 @@ pub enum Route {
     Melt,
     /// Swap
     Swap,
+    /// Exchange
+    Exchange,
 }
 
 pub const MINT: &str = "mint";
 pub const SWAP: &str = "swap";
 pub const MELT: &str = "melt";
+pub const EXCHANGE: &str = "exchange";
 
 impl Display for Route {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -38,6 +41,7 @@ impl Display for Route {
                 Route::Mint => MINT,
                 Route::Melt => MELT,
                 Route::Swap => SWAP,
+                Route::Exchange => EXCHANGE,
             }
         )
     }
@@ -65,6 +69,7 @@ impl FromStr for Route {
             MINT => Ok(Self::Mint),
             SWAP => Ok(Self::Swap),
             MELT => Ok(Self::Melt),
+            EXCHANGE => Ok(Self::Exchange),
             _ => Err(PathFromStrError::InvalidRoute(s.to_string())),
         }
     }
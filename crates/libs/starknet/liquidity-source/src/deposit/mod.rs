@@ -12,7 +12,7 @@ mod not_mock {
     use liquidity_source::DepositInterface;
     use nuts::Amount;
     use starknet_types::{
-        Asset, Call, ChainId, Unit, compute_invoice_id, constants::ON_CHAIN_CONSTANTS,
+        Asset, Call, ChainId, Unit, compute_invoice_id, constants::on_chain_constants,
         transactions::generate_single_payment_transaction_calls,
     };
     use starknet_types_core::felt::Felt;
@@ -43,11 +43,12 @@ mod not_mock {
         SerdeJson(#[from] serde_json::Error),
     }
 
+    #[async_trait::async_trait]
     impl DepositInterface for Depositer {
         type Error = Error;
         type InvoiceId = StarknetInvoiceId;
 
-        fn generate_deposit_payload(
+        async fn generate_deposit_payload(
             &self,
             quote_id: Uuid,
             unit: Unit,
@@ -56,7 +57,7 @@ mod not_mock {
         ) -> Result<(Self::InvoiceId, String), Self::Error> {
             let asset = unit.asset();
             let amount = unit.convert_amount_into_u256(amount);
-            let on_chain_constants = ON_CHAIN_CONSTANTS.get(self.chain_id.as_str()).unwrap();
+            let on_chain_constants = on_chain_constants(self.chain_id.as_str()).unwrap();
             let token_contract_address = on_chain_constants
                 .assets_contract_address
                 .get_contract_address_for_asset(asset)
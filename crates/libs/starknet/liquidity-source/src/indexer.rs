@@ -1,12 +1,11 @@
+use db_node::{SharedStorage, StorageConn};
 use futures::TryStreamExt;
 use nuts::Amount;
 use nuts::nut04::MintQuoteState;
 use nuts::nut05::MeltQuoteState;
-use sqlx::pool::PoolConnection;
-use sqlx::{PgPool, Postgres};
 use starknet_payment_indexer::{ApibaraIndexerService, Message, PaymentEvent, Uri};
-use starknet_types::constants::ON_CHAIN_CONSTANTS;
-use starknet_types::{Asset, AssetToUnitConversionError, ChainId};
+use starknet_types::constants::on_chain_constants;
+use starknet_types::{AssetToUnitConversionError, ChainId};
 use starknet_types::{StarknetU256, Unit};
 use starknet_types_core::felt::Felt;
 use std::env;
@@ -30,8 +29,6 @@ pub enum Error {
     Indexer(#[from] anyhow::Error),
     #[error("failed to interact with the node database: {0}")]
     DbNode(#[from] db_node::Error),
-    #[error("failed to interact with the node database: {0}")]
-    Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
     Felt(#[from] starknet_types_core::felt::FromStrError),
     #[error("u256 value overflowed during the computation of the total amount paid for invoice")]
@@ -46,28 +43,31 @@ async fn init_indexer_task(
 ) -> Result<ApibaraIndexerService, Error> {
     let conn = rusqlite::Connection::open_in_memory().map_err(Error::OpenSqlite)?;
 
-    let on_chain_constants = starknet_types::constants::ON_CHAIN_CONSTANTS
-        .get(chain_id.as_str())
-        .ok_or(Error::UnknownChainId(chain_id.clone()))?;
-    let strk_token_address = on_chain_constants
+    let on_chain_constants =
+        on_chain_constants(chain_id.as_str()).ok_or(Error::UnknownChainId(chain_id.clone()))?;
+    // Watch the transfers of every registered asset deployed on this chain
+    let token_addresses = on_chain_constants
         .assets_contract_address
-        .get_contract_address_for_asset(Asset::Strk)
-        .expect("asset 'strk' should be part of the constants");
+        .all()
+        .into_iter()
+        .map(|(_, address)| address)
+        .collect();
 
     let uri = match on_chain_constants.apibara.data_stream_uri {
-        Some(uri) => starknet_payment_indexer::Uri::from_static(uri),
+        // Custom chains come with a uri read from config
+        Some(uri) => uri.parse::<Uri>(),
         None => env::var("DNA_URI")
             .map_err(|e| Error::Env("DNA_URI", e))?
-            .parse::<Uri>()
-            .map_err(|e| Error::InitIndexer(starknet_payment_indexer::Error::ParseURI(e)))?,
-    };
+            .parse::<Uri>(),
+    }
+    .map_err(|e| Error::InitIndexer(starknet_payment_indexer::Error::ParseURI(e)))?;
     let service = starknet_payment_indexer::ApibaraIndexerService::init(
         conn,
         apibara_token,
         uri,
         chain_id,
         on_chain_constants.apibara.starting_block,
-        vec![strk_token_address],
+        token_addresses,
     )
     .await
     .map_err(Error::InitIndexer)?;
@@ -75,17 +75,26 @@ async fn init_indexer_task(
     Ok(service)
 }
 
-async fn listen_to_indexer(
-    pg_pool: PgPool,
+/// What the indexer needs to stream the payments made to and by the cashier
+#[derive(Debug, Clone)]
+pub struct IndexerSettings {
+    pub apibara_token: String,
+    pub chain_id: ChainId,
+    pub cashier_account_address: Felt,
+}
+
+async fn listen_to_indexer<F, Fut>(
     mut indexer_service: ApibaraIndexerService,
-    chain_id: ChainId,
-    cashier_account_address: Felt,
-) -> Result<(), Error> {
+    on_payments: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(Vec<PaymentEvent>) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
     while let Some(event) = indexer_service.try_next().await? {
         match event {
             Message::Payment(payment_events) => {
-                process_payment_event(payment_events, &pg_pool, &chain_id, cashier_account_address)
-                    .await?;
+                on_payments(payment_events).await?;
             }
             Message::Invalidate {
                 last_valid_block_number: _,
@@ -99,16 +108,31 @@ async fn listen_to_indexer(
     Ok(())
 }
 
-pub async fn run_in_ctrl_c_cancellable_task(
-    pg_pool: PgPool,
-    apibara_token: String,
-    chain_id: ChainId,
-    cashier_account_address: Felt,
-) {
+/// Index the payments into the node database
+pub async fn run_in_ctrl_c_cancellable_task(storage: SharedStorage, settings: IndexerSettings) {
+    let chain_id = settings.chain_id.clone();
+    let cashier_account_address = settings.cashier_account_address;
+
+    stream_payments_until_ctrl_c(settings, |payment_events| {
+        process_payment_event(payment_events, &storage, &chain_id, cashier_account_address)
+    })
+    .await
+}
+
+/// Pass the payments streamed by the indexer to `on_payments`, until ctrl-c is received
+pub async fn stream_payments_until_ctrl_c<F, Fut>(settings: IndexerSettings, mut on_payments: F)
+where
+    F: FnMut(Vec<PaymentEvent>) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
     // It can happen that the DNA indexer goes down at some point, or close our connection.
     // We should restart then.
     loop {
-        let indexer_service = match init_indexer_task(apibara_token.clone(), chain_id.clone()).await
+        let indexer_service = match init_indexer_task(
+            settings.apibara_token.clone(),
+            settings.chain_id.clone(),
+        )
+        .await
         {
             Ok(ais) => ais,
             Err(e) => {
@@ -119,11 +143,8 @@ pub async fn run_in_ctrl_c_cancellable_task(
         };
         debug!("indexer-service-initialized");
 
-        let cloned_chain_id = chain_id.clone();
-        let cloned_pg_pool = pg_pool.clone();
-
         let should_restart = select! {
-            indexer_res = listen_to_indexer(cloned_pg_pool, indexer_service, cloned_chain_id, cashier_account_address) => match indexer_res {
+            indexer_res = listen_to_indexer(indexer_service, &mut on_payments) => match indexer_res {
                 Ok(()) => {
                     error!(name: "indexer-task-error", name = "indexer-task-error", error = "returned");
                     true
@@ -151,26 +172,23 @@ pub async fn run_in_ctrl_c_cancellable_task(
 
 async fn process_payment_event(
     payment_events: Vec<PaymentEvent>,
-    pg_pool: &PgPool,
+    storage: &SharedStorage,
     chain_id: &ChainId,
     cashier_account_address: Felt,
 ) -> Result<(), Error> {
-    let db_conn = &mut pg_pool.acquire().await?;
+    let mut db_conn = storage.acquire().await?;
+    let db_conn = db_conn.as_mut();
 
     for payment_event in payment_events {
+        let invoice_id = payment_event.invoice_id.to_bytes_be();
         let (is_mint, quote_id, quote_amount, unit) = if let Some((quote_id, amount, unit)) =
-            db_node::mint_quote::get_quote_infos_by_invoice_id::<Unit>(
-                db_conn,
-                &payment_event.invoice_id.to_bytes_be(),
-            )
-            .await?
+            db_conn
+                .get_or_restore_mint_quote_by_invoice_id(&invoice_id)
+                .await?
         {
             (true, quote_id, amount, unit)
-        } else if let Some((quote_id, amount, unit)) =
-            db_node::melt_quote::get_quote_infos_by_invoice_id::<Unit>(
-                db_conn,
-                &payment_event.invoice_id.to_bytes_be(),
-            )
+        } else if let Some((quote_id, amount, unit)) = db_conn
+            .get_or_restore_melt_quote_by_invoice_id(&invoice_id)
             .await?
         {
             (false, quote_id, amount, unit)
@@ -178,10 +196,10 @@ async fn process_payment_event(
             error!("no quote for invoice_id {:#x}", payment_event.invoice_id);
             continue;
         };
+        let unit = Unit::from_str(&unit).map_err(|_| db_node::Error::InvalidUnit(unit))?;
 
-        let on_chain_constants = ON_CHAIN_CONSTANTS
-            .get(chain_id.as_str())
-            .ok_or(Error::UnknownChainId(chain_id.clone()))?;
+        let on_chain_constants =
+            on_chain_constants(chain_id.as_str()).ok_or(Error::UnknownChainId(chain_id.clone()))?;
         let asset = match on_chain_constants
             .assets_contract_address
             .get_asset_for_contract_address(payment_event.asset)
@@ -222,37 +240,58 @@ async fn process_payment_event(
     Ok(())
 }
 
+fn to_db_payment_event(payment_event: &PaymentEvent, tx_hash: String) -> db_node::PaymentEvent {
+    db_node::PaymentEvent {
+        block_id: payment_event.block_id.clone(),
+        tx_hash,
+        event_idx: payment_event.event_idx,
+        asset: payment_event.asset.to_string(),
+        payee: payment_event.payee.to_string(),
+        invoice_id: payment_event.invoice_id.to_bytes_be(),
+        payer: payment_event.payer.to_string(),
+        amount_low: payment_event.amount.low.to_string(),
+        amount_high: payment_event.amount.high.to_string(),
+    }
+}
+
 // Yeah I know it's basically the same code copied and pasted.
 // For now it's fine, better this than adding trait and struct and so on.
 async fn handle_mint_payment(
-    db_conn: &mut PoolConnection<Postgres>,
+    db_conn: &mut dyn StorageConn,
     quote_id: Uuid,
     payment_event: PaymentEvent,
     unit: Unit,
     quote_amount: Amount,
 ) -> Result<(), Error> {
-    db_node::mint_payment_event::insert_new_payment_event(db_conn, &payment_event).await?;
-    let current_paid = db_node::mint_payment_event::get_current_paid(
-        db_conn,
-        &payment_event.invoice_id.to_bytes_be(),
-    )
-    .await?
-    .map(|(low, high)| -> Result<primitive_types::U256, Error> {
-        let amount_as_strk_256 = StarknetU256 {
-            low: Felt::from_str(&low)?,
-            high: Felt::from_str(&high)?,
-        };
+    // Mint tx hashes have always been stored as decimal strings
+    db_conn
+        .insert_mint_payment_event(&to_db_payment_event(
+            &payment_event,
+            payment_event.tx_hash.to_string(),
+        ))
+        .await?;
+    let current_paid = db_conn
+        .get_mint_current_paid(&payment_event.invoice_id.to_bytes_be())
+        .await?
+        .into_iter()
+        .map(|(low, high)| -> Result<primitive_types::U256, Error> {
+            let amount_as_strk_256 = StarknetU256 {
+                low: Felt::from_str(&low)?,
+                high: Felt::from_str(&high)?,
+            };
 
-        Ok(primitive_types::U256::from(amount_as_strk_256))
-    })
-    .try_fold(primitive_types::U256::zero(), |acc, a| match a {
-        Ok(v) => v.checked_add(acc).ok_or(Error::AmountPaidOverflow),
-        Err(e) => Err(e),
-    })?;
+            Ok(primitive_types::U256::from(amount_as_strk_256))
+        })
+        .try_fold(primitive_types::U256::zero(), |acc, a| match a {
+            Ok(v) => v.checked_add(acc).ok_or(Error::AmountPaidOverflow),
+            Err(e) => Err(e),
+        })?;
 
     let to_pay = unit.convert_amount_into_u256(quote_amount);
     if current_paid >= to_pay {
-        db_node::mint_quote::set_state(db_conn, quote_id, MintQuoteState::Paid).await?;
+        db_conn
+            .set_mint_quote_state(quote_id, MintQuoteState::Paid)
+            .await?;
         event!(
             name: "mint-quote-paid",
             Level::INFO,
@@ -265,34 +304,40 @@ async fn handle_mint_payment(
 }
 
 async fn handle_melt_payment(
-    db_conn: &mut PoolConnection<Postgres>,
+    db_conn: &mut dyn StorageConn,
     quote_id: Uuid,
     payment_event: PaymentEvent,
     unit: Unit,
     quote_amount: Amount,
 ) -> Result<(), Error> {
-    db_node::melt_payment_event::insert_new_payment_event(db_conn, &payment_event).await?;
-    let current_paid = db_node::melt_payment_event::get_current_paid(
-        db_conn,
-        &payment_event.invoice_id.to_bytes_be(),
-    )
-    .await?
-    .map(|(low, high)| -> Result<primitive_types::U256, Error> {
-        let amount_as_strk_256 = StarknetU256 {
-            low: Felt::from_str(&low)?,
-            high: Felt::from_str(&high)?,
-        };
+    db_conn
+        .insert_melt_payment_event(&to_db_payment_event(
+            &payment_event,
+            payment_event.tx_hash.to_hex_string(),
+        ))
+        .await?;
+    let current_paid = db_conn
+        .get_melt_current_paid(&payment_event.invoice_id.to_bytes_be())
+        .await?
+        .into_iter()
+        .map(|(low, high)| -> Result<primitive_types::U256, Error> {
+            let amount_as_strk_256 = StarknetU256 {
+                low: Felt::from_str(&low)?,
+                high: Felt::from_str(&high)?,
+            };
 
-        Ok(primitive_types::U256::from(amount_as_strk_256))
-    })
-    .try_fold(primitive_types::U256::zero(), |acc, a| match a {
-        Ok(v) => v.checked_add(acc).ok_or(Error::AmountPaidOverflow),
-        Err(e) => Err(e),
-    })?;
+            Ok(primitive_types::U256::from(amount_as_strk_256))
+        })
+        .try_fold(primitive_types::U256::zero(), |acc, a| match a {
+            Ok(v) => v.checked_add(acc).ok_or(Error::AmountPaidOverflow),
+            Err(e) => Err(e),
+        })?;
 
     let to_pay = unit.convert_amount_into_u256(quote_amount);
     if current_paid >= to_pay {
-        db_node::melt_quote::set_state(db_conn, quote_id, MeltQuoteState::Paid).await?;
+        db_conn
+            .set_melt_quote_state(quote_id, MeltQuoteState::Paid)
+            .await?;
         event!(
             name: "melt-quote-paid",
             Level::INFO,
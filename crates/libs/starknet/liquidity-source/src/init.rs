@@ -1,43 +1,66 @@
 #[cfg(feature = "mock")]
 mod mock_impl {
-    use crate::{Depositer, StarknetLiquiditySource, Withdrawer};
+    use db_node::SharedStorage;
+    use starknet_types::{Asset, registry};
+
+    use crate::{Depositer, ReservesReader, StarknetLiquiditySource, Withdrawer};
 
     impl StarknetLiquiditySource {
-        pub fn new() -> Self {
+        pub fn new(storage: SharedStorage) -> Self {
             StarknetLiquiditySource {
                 depositer: Depositer,
-                withdrawer: Withdrawer,
+                withdrawer: Withdrawer::new(storage),
+                reserves: ReservesReader,
+                // Every asset is deployed on the mocked chain, but the one of Lightning
+                units: registry::units()
+                    .into_iter()
+                    .filter(|unit| unit.asset() != Asset::BTC)
+                    .collect(),
             }
         }
     }
-
-    impl Default for StarknetLiquiditySource {
-        fn default() -> Self {
-            Self::new()
-        }
-    }
 }
 
 #[cfg(not(feature = "mock"))]
 mod not_mock_impl {
     use std::{path::PathBuf, str::FromStr, sync::Arc};
 
-    use sqlx::PgPool;
+    use db_node::{SharedStorage, leader::run_as_leader};
     use starknet::{
         accounts::{ExecutionEncoding, SingleOwnerAccount},
         providers::{JsonRpcClient, jsonrpc::HttpTransport},
         signers::{LocalWallet, SigningKey},
     };
-    use starknet_types::{ChainId, constants::ON_CHAIN_CONSTANTS};
+    use starknet_types::{constants::on_chain_constants, registry};
     use starknet_types_core::felt::Felt;
 
     use crate::{
-        CASHIER_PRIVATE_KEY_ENV_VAR, Depositer, Error, StarknetLiquiditySource, Withdrawer,
-        indexer, read_starknet_config,
+        CASHIER_PRIVATE_KEY_ENV_VAR, Depositer, Error, ReservesReader, StarknetLiquiditySource,
+        Withdrawer,
+        indexer::{self, IndexerSettings},
+        read_starknet_config, register_custom_chain,
     };
 
     impl StarknetLiquiditySource {
-        pub async fn init(pg_pool: PgPool, config_path: PathBuf) -> Result<Self, Error> {
+        pub async fn init(storage: SharedStorage, config_path: PathBuf) -> Result<Self, Error> {
+            let (liquidity_source, indexer_settings) = Self::from_config(config_path)?;
+
+            let cloned_storage = storage.clone();
+            // A single node indexes the payments into the shared database
+            let _handle = tokio::spawn(run_as_leader(storage, "starknet-indexer", move || {
+                indexer::run_in_ctrl_c_cancellable_task(
+                    cloned_storage.clone(),
+                    indexer_settings.clone(),
+                )
+            }));
+
+            Ok(liquidity_source)
+        }
+
+        /// Read the config file and env, without starting the indexer
+        ///
+        /// The returned settings are the ones the indexer should be started with.
+        pub fn from_config(config_path: PathBuf) -> Result<(Self, IndexerSettings), Error> {
             let config = read_starknet_config(config_path)?;
             let private_key = Felt::from_str(
                 &std::env::var(CASHIER_PRIVATE_KEY_ENV_VAR)
@@ -45,10 +68,16 @@ mod not_mock_impl {
             )
             .map_err(|_| Error::PrivateKey)?;
 
-            let apibara_token = match config.chain_id {
-                // Not needed for local DNA service
-                ChainId::Devnet => "".to_string(),
-                _ => std::env::var("APIBARA_TOKEN").map_err(|e| Error::Env("APIBARA_TOKEN", e))?,
+            register_custom_chain(&config)?;
+            let on_chain_constants = on_chain_constants(config.chain_id.as_str())
+                .expect("the chain constants are checked when registering");
+
+            let apibara_token = match on_chain_constants.apibara.data_stream_uri {
+                // Not needed for local DNA service, whose uri is read from `DNA_URI`
+                None => "".to_string(),
+                Some(_) => {
+                    std::env::var("APIBARA_TOKEN").map_err(|e| Error::Env("APIBARA_TOKEN", e))?
+                }
             };
 
             // Create provider
@@ -65,29 +94,40 @@ mod not_mock_impl {
                 ExecutionEncoding::New,
             ));
 
-            let cloned_chain_id = config.chain_id.clone();
-            let cloned_cashier_account_address = config.cashier_account_address;
-            let cloned_pg_pool = pg_pool.clone();
-            let _handle = tokio::spawn(async move {
-                indexer::run_in_ctrl_c_cancellable_task(
-                    cloned_pg_pool,
-                    apibara_token,
-                    cloned_chain_id,
-                    cloned_cashier_account_address,
-                )
-                .await
-            });
-
-            let on_chain_constants = ON_CHAIN_CONSTANTS.get(config.chain_id.as_str()).unwrap();
-
-            Ok(StarknetLiquiditySource {
-                depositer: Depositer::new(config.chain_id.clone(), config.cashier_account_address),
-                withdrawer: Withdrawer::new(
-                    config.chain_id,
-                    account,
-                    on_chain_constants.invoice_payment_contract_address,
-                ),
-            })
+            let indexer_settings = IndexerSettings {
+                apibara_token,
+                chain_id: config.chain_id.clone(),
+                cashier_account_address: config.cashier_account_address,
+            };
+
+            Ok((
+                StarknetLiquiditySource {
+                    depositer: Depositer::new(
+                        config.chain_id.clone(),
+                        config.cashier_account_address,
+                    ),
+                    reserves: ReservesReader::new(
+                        config.chain_id.clone(),
+                        provider.clone(),
+                        config.cashier_account_address,
+                    ),
+                    withdrawer: Withdrawer::new(
+                        config.chain_id,
+                        account,
+                        on_chain_constants.invoice_payment_contract_address,
+                    ),
+                    units: registry::units()
+                        .into_iter()
+                        .filter(|unit| {
+                            on_chain_constants
+                                .assets_contract_address
+                                .get_contract_address_for_asset(unit.asset())
+                                .is_some()
+                        })
+                        .collect(),
+                },
+                indexer_settings,
+            ))
         }
     }
 }
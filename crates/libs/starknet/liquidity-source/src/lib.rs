@@ -2,15 +2,24 @@ mod deposit;
 #[cfg(not(feature = "mock"))]
 mod indexer;
 mod init;
+mod reserves;
 mod withdraw;
 
 use std::{
+    collections::HashMap,
     fmt::{LowerHex, UpperHex},
     path::PathBuf,
+    str::FromStr,
 };
 
 pub use deposit::{Depositer, Error as DepositError};
-use starknet_types::{CairoShortStringToFeltError, Unit};
+#[cfg(not(feature = "mock"))]
+pub use indexer::{Error as IndexerError, IndexerSettings, stream_payments_until_ctrl_c};
+pub use reserves::{AssetReserves, Error as ReservesError, ReservesReader};
+use starknet_types::{
+    Asset, CairoShortStringToFeltError, ChainId, Unit,
+    constants::{ApibaraConstants, AssetsAddress, OnChainConstants},
+};
 use starknet_types_core::{felt::Felt, hash::Poseidon};
 use url::Url;
 pub use withdraw::{Error as WithdrawalError, MeltPaymentRequest, Withdrawer};
@@ -39,6 +48,77 @@ pub struct StarknetCliConfig {
     pub cashier_account_address: starknet_types_core::felt::Felt,
     /// The url of the starknet rpc node we want to use
     pub starknet_rpc_node_url: Url,
+    /// Required if `chain_id` is not a built-in network, overrides its constants otherwise
+    #[serde(default)]
+    pub custom_chain: Option<CustomChainConfig>,
+}
+
+/// Constants of a chain we deployed our contracts on, e.g. a local starknet-devnet or katana instance
+///
+/// ```toml
+/// chain_id = "KATANA"
+/// cashier_account_address = "0x..."
+/// starknet_rpc_node_url = "http://localhost:5050"
+///
+/// [custom_chain]
+/// invoice_payment_contract_address = "0x..."
+/// dna_uri = "http://localhost:7171"
+/// starting_block = 0
+/// assets = { strk = "0x..." }
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomChainConfig {
+    /// The address of our deployment of the `InvoicePayment` contract
+    pub invoice_payment_contract_address: Felt,
+    /// The Apibara DNA stream of the chain, read from the `DNA_URI` env variable if not set
+    pub dna_uri: Option<String>,
+    /// The block to start indexing from, the one containing the invoice contract deployment is enough
+    #[serde(default)]
+    pub starting_block: u64,
+    /// The contract address of each registered asset deployed on the chain, keyed by symbol
+    #[serde(default)]
+    pub assets: HashMap<String, Felt>,
+}
+
+impl CustomChainConfig {
+    fn into_on_chain_constants(self) -> Result<OnChainConstants, Error> {
+        let assets_addresses = self
+            .assets
+            .into_iter()
+            .map(|(symbol, address)| {
+                Asset::from_str(&symbol)
+                    .map(|asset| (asset, address))
+                    .map_err(|_| Error::UnknownAsset(symbol))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(OnChainConstants {
+            apibara: ApibaraConstants {
+                data_stream_uri: self.dna_uri.map(|uri| &*String::leak(uri)),
+                starting_block: self.starting_block,
+            },
+            invoice_payment_contract_address: self.invoice_payment_contract_address,
+            assets_contract_address: AssetsAddress::new(assets_addresses),
+        })
+    }
+}
+
+/// Register the constants of the configured custom chain, if any,
+/// and make sure the constants of the configured chain are known
+pub fn register_custom_chain(config: &StarknetCliConfig) -> Result<(), Error> {
+    if let Some(custom_chain) = config.custom_chain.clone() {
+        starknet_types::constants::register_chain(
+            &config.chain_id,
+            custom_chain.into_on_chain_constants()?,
+        );
+    }
+
+    if starknet_types::constants::on_chain_constants(config.chain_id.as_str()).is_none() {
+        return Err(Error::UnknownChainId(config.chain_id.clone()));
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -54,6 +134,10 @@ pub enum Error {
     PrivateKey,
     #[error("invalid chain id value: {0}")]
     ChainId(CairoShortStringToFeltError),
+    #[error("unknown chain id `{0}`, its constants must be set in the `[custom_chain]` table")]
+    UnknownChainId(ChainId),
+    #[error("custom_chain.assets: `{0}` is not a registered asset")]
+    UnknownAsset(String),
 }
 
 pub const CASHIER_PRIVATE_KEY_ENV_VAR: &str = "CASHIER_PRIVATE_KEY";
@@ -61,6 +145,18 @@ pub const CASHIER_PRIVATE_KEY_ENV_VAR: &str = "CASHIER_PRIVATE_KEY";
 #[derive(Debug, Clone)]
 pub struct StarknetInvoiceId(Felt);
 
+impl StarknetInvoiceId {
+    /// The invoice id of a quote, identifying its payments
+    fn new(quote_id: uuid::Uuid, expiry: u64) -> Self {
+        let quote_id_hash =
+            Felt::from_bytes_be(bitcoin_hashes::Sha256::hash(quote_id.as_bytes()).as_byte_array());
+        let mut values = [quote_id_hash, expiry.into(), 2.into()];
+        Poseidon::hades_permutation(&mut values);
+
+        StarknetInvoiceId(values[0])
+    }
+}
+
 impl From<StarknetInvoiceId> for [u8; 32] {
     fn from(value: StarknetInvoiceId) -> Self {
         value.0.to_bytes_be()
@@ -82,9 +178,14 @@ impl UpperHex for StarknetInvoiceId {
 pub struct StarknetLiquiditySource {
     pub depositer: Depositer,
     pub withdrawer: Withdrawer,
+    pub reserves: ReservesReader,
+    /// The registered units whose asset is deployed on the chain
+    pub units: Vec<Unit>,
 }
 
+#[async_trait::async_trait]
 impl liquidity_source::LiquiditySource for StarknetLiquiditySource {
+    type Error = std::convert::Infallible;
     type Depositer = Depositer;
     type Withdrawer = Withdrawer;
     type InvoiceId = StarknetInvoiceId;
@@ -98,12 +199,15 @@ impl liquidity_source::LiquiditySource for StarknetLiquiditySource {
         self.withdrawer.clone()
     }
 
-    fn compute_invoice_id(&self, quote_id: uuid::Uuid, expiry: u64) -> Self::InvoiceId {
-        let quote_id_hash =
-            Felt::from_bytes_be(bitcoin_hashes::Sha256::hash(quote_id.as_bytes()).as_byte_array());
-        let mut values = [quote_id_hash, expiry.into(), 2.into()];
-        Poseidon::hades_permutation(&mut values);
+    fn units(&self) -> Vec<Unit> {
+        self.units.clone()
+    }
 
-        StarknetInvoiceId(values[0])
+    async fn compute_invoice_id(
+        &self,
+        quote_id: uuid::Uuid,
+        expiry: u64,
+    ) -> Result<Self::InvoiceId, Self::Error> {
+        Ok(StarknetInvoiceId::new(quote_id, expiry))
     }
 }
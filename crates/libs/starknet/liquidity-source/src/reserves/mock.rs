@@ -0,0 +1,29 @@
+use db_node::Storage;
+use starknet_types::Unit;
+
+use super::{AssetReserves, read_outstanding_ecash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read the outstanding ecash from database: {0}")]
+    Db(#[from] db_node::Error),
+}
+
+/// There is no chain to read balances from, so every asset is reported as fully backed
+#[derive(Debug, Clone)]
+pub struct ReservesReader;
+
+impl ReservesReader {
+    pub async fn read_reserves(
+        &self,
+        storage: &dyn Storage,
+        units: &[Unit],
+    ) -> Result<Vec<AssetReserves>, Error> {
+        let mut reserves = read_outstanding_ecash(storage, units).await?;
+        for asset_reserves in reserves.iter_mut() {
+            asset_reserves.balance = asset_reserves.outstanding_ecash;
+        }
+
+        Ok(reserves)
+    }
+}
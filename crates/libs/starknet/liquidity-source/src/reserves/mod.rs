@@ -0,0 +1,226 @@
+//! Proof of reserves
+//!
+//! The reserves of the node are the on-chain balances of the cashier account,
+//! for the asset backing each of the node units.
+//! They are compared against the ecash outstanding in all the units backed by the same asset,
+//! that is the signatures issued minus the proofs spent, converted to the asset precision.
+//!
+//! The ecash is read from db, and the connection released, before the balances are read on-chain.
+//! Deposits paid but not yet issued, and withdrawals not yet executed on-chain,
+//! are part of the reserves but not of the outstanding ecash,
+//! so the ratio is expected to be slightly above 1 while such quotes are being processed.
+#[cfg(feature = "mock")]
+mod mock;
+
+#[cfg(feature = "mock")]
+pub use mock::*;
+#[cfg(not(feature = "mock"))]
+pub use not_mock::*;
+
+use nuts::Amount;
+use primitive_types::U256;
+use starknet_types::{Asset, Unit};
+
+/// The reserves of one asset, against the ecash of all the units it backs
+#[derive(Debug, Clone)]
+pub struct AssetReserves {
+    pub asset: Asset,
+    /// The on-chain balance of the cashier account, including the asset precision
+    pub balance: U256,
+    /// The ecash outstanding in each unit backed by this asset
+    pub units: Vec<(Unit, Amount)>,
+    /// Sum of the ecash outstanding in `units`, converted to the asset precision
+    pub outstanding_ecash: U256,
+}
+
+impl AssetReserves {
+    /// Balance divided by outstanding ecash, None if there is no ecash outstanding
+    pub fn ratio(&self) -> Option<f64> {
+        if self.outstanding_ecash.is_zero() {
+            return None;
+        }
+
+        Some(u256_to_f64(self.balance) / u256_to_f64(self.outstanding_ecash))
+    }
+}
+
+/// Lossy, but precise enough for a ratio
+fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
+
+/// Reads the outstanding ecash of `units` and sums it per asset, in the order of their first unit
+///
+/// The balances are left to zero, for the caller to read them.
+async fn read_outstanding_ecash(
+    storage: &dyn db_node::Storage,
+    units: &[Unit],
+) -> Result<Vec<AssetReserves>, db_node::Error> {
+    let mut distinct_units: Vec<Unit> = Vec::with_capacity(units.len());
+    for unit in units {
+        if !distinct_units.contains(unit) {
+            distinct_units.push(*unit);
+        }
+    }
+    let unit_names: Vec<String> = distinct_units.iter().map(|u| u.to_string()).collect();
+    let metrics = storage
+        .acquire()
+        .await?
+        .get_gauge_metrics(&unit_names)
+        .await?;
+
+    let mut reserves: Vec<AssetReserves> = Vec::new();
+    for (unit, name) in distinct_units.into_iter().zip(unit_names) {
+        let outstanding_ecash = metrics
+            .iter()
+            .find(|(unit, _)| *unit == name)
+            .map(|(_, metrics)| metrics.outstanding_ecash)
+            .unwrap_or(Amount::ZERO);
+        let asset = unit.asset();
+        let asset_reserves = match reserves.iter_mut().find(|r| r.asset == asset) {
+            Some(asset_reserves) => asset_reserves,
+            None => {
+                reserves.push(AssetReserves {
+                    asset,
+                    balance: U256::zero(),
+                    units: Vec::new(),
+                    outstanding_ecash: U256::zero(),
+                });
+                reserves.last_mut().expect("just pushed")
+            }
+        };
+        asset_reserves.units.push((unit, outstanding_ecash));
+        asset_reserves.outstanding_ecash += unit.convert_amount_into_u256(outstanding_ecash);
+    }
+
+    Ok(reserves)
+}
+
+#[cfg(not(feature = "mock"))]
+mod not_mock {
+    use db_node::Storage;
+    use primitive_types::U256;
+    use starknet::{
+        core::types::{BlockId, BlockTag, FunctionCall},
+        macros::selector,
+        providers::{JsonRpcClient, Provider, ProviderError, jsonrpc::HttpTransport},
+    };
+    use starknet_types::{Asset, ChainId, StarknetU256, Unit, constants::on_chain_constants};
+    use starknet_types_core::felt::Felt;
+
+    use super::{AssetReserves, read_outstanding_ecash};
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum Error {
+        #[error("failed to read the outstanding ecash from database: {0}")]
+        Db(#[from] db_node::Error),
+        #[error("no on-chain constants for chain id {0}")]
+        UnknownChainId(ChainId),
+        #[error("asset {0} not found in on-chain constants")]
+        AssetNotFound(Asset),
+        #[error("failed to read the {0} balance of the cashier account: {1}")]
+        BalanceOf(Asset, #[source] ProviderError),
+        #[error("unexpected response to the {0} balance query: {1:?}")]
+        InvalidBalance(Asset, Vec<Felt>),
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ReservesReader {
+        chain_id: ChainId,
+        provider: JsonRpcClient<HttpTransport>,
+        cashier_account_address: Felt,
+    }
+
+    impl ReservesReader {
+        pub fn new(
+            chain_id: ChainId,
+            provider: JsonRpcClient<HttpTransport>,
+            cashier_account_address: Felt,
+        ) -> Self {
+            Self {
+                chain_id,
+                provider,
+                cashier_account_address,
+            }
+        }
+
+        /// Query the ERC20 balance of the cashier account
+        async fn balance_of(&self, asset: Asset) -> Result<U256, Error> {
+            let on_chain_constants = on_chain_constants(self.chain_id.as_str())
+                .ok_or_else(|| Error::UnknownChainId(self.chain_id.clone()))?;
+            let contract_address = on_chain_constants
+                .assets_contract_address
+                .get_contract_address_for_asset(asset)
+                .ok_or(Error::AssetNotFound(asset))?;
+
+            let result = self
+                .provider
+                .call(
+                    FunctionCall {
+                        contract_address,
+                        entry_point_selector: selector!("balance_of"),
+                        calldata: vec![self.cashier_account_address],
+                    },
+                    BlockId::Tag(BlockTag::Latest),
+                )
+                .await
+                .map_err(|e| Error::BalanceOf(asset, e))?;
+
+            // An u256 is returned as its low and high parts
+            match result.as_slice() {
+                [low, high] => Ok(StarknetU256 {
+                    low: *low,
+                    high: *high,
+                }
+                .into()),
+                _ => Err(Error::InvalidBalance(asset, result)),
+            }
+        }
+
+        /// Compare the reserves of the assets backing `units` against their outstanding ecash
+        pub async fn read_reserves(
+            &self,
+            storage: &dyn Storage,
+            units: &[Unit],
+        ) -> Result<Vec<AssetReserves>, Error> {
+            let mut reserves = read_outstanding_ecash(storage, units).await?;
+            for asset_reserves in reserves.iter_mut() {
+                asset_reserves.balance = self.balance_of(asset_reserves.asset).await?;
+            }
+
+            Ok(reserves)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_compares_asset_amounts() {
+        let reserves = AssetReserves {
+            asset: Unit::MILLI_STRK.asset(),
+            balance: Unit::MILLI_STRK.convert_amount_into_u256(Amount::from(150u64)),
+            units: vec![(Unit::MILLI_STRK, Amount::from(100u64))],
+            outstanding_ecash: Unit::MILLI_STRK.convert_amount_into_u256(Amount::from(100u64)),
+        };
+        assert_eq!(reserves.ratio(), Some(1.5));
+
+        let reserves = AssetReserves {
+            outstanding_ecash: U256::zero(),
+            ..reserves
+        };
+        assert_eq!(reserves.ratio(), None);
+    }
+
+    #[test]
+    fn u256_to_f64_reads_all_limbs() {
+        assert_eq!(u256_to_f64(U256::from(3u64)), 3.0);
+        assert_eq!(u256_to_f64(U256::from(u128::MAX) + 1), 2f64.powi(128));
+    }
+}
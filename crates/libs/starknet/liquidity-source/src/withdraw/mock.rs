@@ -1,3 +1,4 @@
+use db_node::{PaymentEvent, SharedStorage};
 use liquidity_source::WithdrawInterface;
 use num_traits::CheckedAdd;
 use nuts::{Amount, nut05::MeltQuoteState};
@@ -21,10 +22,21 @@ pub enum Error {
     InvalidAssetForUnit(Asset, Unit),
     #[error("failed to convert request values to nodes values: {0}")]
     Conversion(#[from] AssetToUnitConversionError),
+    #[error(transparent)]
+    Db(#[from] db_node::Error),
 }
 
+/// Pays every request at once, recording the transfer as the indexer would have
 #[derive(Debug, Clone)]
-pub struct Withdrawer;
+pub struct Withdrawer {
+    storage: SharedStorage,
+}
+
+impl Withdrawer {
+    pub fn new(storage: SharedStorage) -> Self {
+        Self { storage }
+    }
+}
 
 #[async_trait::async_trait]
 impl WithdrawInterface for Withdrawer {
@@ -44,7 +56,7 @@ impl WithdrawInterface for Withdrawer {
         Ok(pr)
     }
 
-    fn compute_total_amount_expected(
+    async fn compute_total_amount_expected(
         &self,
         request: Self::Request,
         unit: Unit,
@@ -71,10 +83,27 @@ impl WithdrawInterface for Withdrawer {
 
     async fn proceed_to_payment(
         &mut self,
-        _quote_id: Uuid,
-        _melt_payment_request: MeltPaymentRequest,
-        _expiry: u64,
+        quote_id: Uuid,
+        melt_payment_request: MeltPaymentRequest,
+        expiry: u64,
     ) -> Result<MeltQuoteState, Error> {
+        let invoice_id = StarknetInvoiceId::new(quote_id, expiry);
+        self.storage
+            .acquire()
+            .await?
+            .insert_melt_payment_event(&PaymentEvent {
+                block_id: "mock".to_string(),
+                tx_hash: quote_id.to_string(),
+                event_idx: 0,
+                asset: melt_payment_request.asset.to_string(),
+                payee: melt_payment_request.payee.to_string(),
+                invoice_id: invoice_id.into(),
+                payer: "mock".to_string(),
+                amount_low: melt_payment_request.amount.low.to_string(),
+                amount_high: melt_payment_request.amount.high.to_string(),
+            })
+            .await?;
+
         Ok(MeltQuoteState::Paid)
     }
 }
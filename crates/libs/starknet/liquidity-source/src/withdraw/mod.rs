@@ -22,7 +22,7 @@ mod not_mock {
     use num_traits::CheckedAdd;
     use nuts::{Amount, nut05::MeltQuoteState};
     use starknet_types::{
-        Asset, AssetToUnitConversionError, ChainId, Unit, constants::ON_CHAIN_CONSTANTS,
+        Asset, AssetToUnitConversionError, ChainId, Unit, constants::on_chain_constants,
     };
 
     use liquidity_source::WithdrawInterface;
@@ -134,7 +134,7 @@ mod not_mock {
             Ok(pr)
         }
 
-        fn compute_total_amount_expected(
+        async fn compute_total_amount_expected(
             &self,
             request: Self::Request,
             unit: Unit,
@@ -169,7 +169,7 @@ mod not_mock {
                 bitcoin_hashes::Sha256::hash(quote_id.as_bytes()).as_byte_array(),
             );
 
-            let on_chain_constants = ON_CHAIN_CONSTANTS.get(self.chain_id.as_str()).unwrap();
+            let on_chain_constants = on_chain_constants(self.chain_id.as_str()).unwrap();
             let asset_contract_address = on_chain_constants
                 .assets_contract_address
                 .get_contract_address_for_asset(melt_payment_request.asset)
@@ -8,7 +8,7 @@ use apibara_sdk::{ClientBuilder, Configuration, DataMessage, InvalidUri};
 use futures::StreamExt;
 use rusqlite::Connection;
 use starknet_core::types::Felt;
-use starknet_types::constants::ON_CHAIN_CONSTANTS;
+use starknet_types::constants::on_chain_constants;
 use starknet_types::{ChainId, StarknetU256};
 use thiserror::Error;
 
@@ -49,9 +49,8 @@ impl ApibaraIndexerService {
     ) -> Result<Self, Error> {
         db::create_tables(&mut db_conn)?;
 
-        let on_chain_constants = ON_CHAIN_CONSTANTS
-            .get(chain_id.as_str())
-            .ok_or(Error::UnknownChainId(chain_id))?;
+        let on_chain_constants =
+            on_chain_constants(chain_id.as_str()).ok_or(Error::UnknownChainId(chain_id))?;
         let invoice_payment_contract_address = on_chain_constants.invoice_payment_contract_address;
 
         let config = Configuration::<Filter>::default()
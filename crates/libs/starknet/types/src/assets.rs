@@ -4,13 +4,23 @@ use nuts::Amount;
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
-use crate::Unit;
+use crate::{Unit, registry};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-pub enum Asset {
-    Strk,
-    Eth,
+/// An on-chain token
+///
+/// A value of this type can only be obtained for an asset of the [`registry`](crate::registry).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Asset(pub(crate) &'static str);
+
+impl Asset {
+    pub const STRK: Asset = Asset("strk");
+    pub const ETH: Asset = Asset("eth");
+    pub const BTC: Asset = Asset("btc");
+    pub const USDC: Asset = Asset("usdc");
+
+    fn info(&self) -> registry::AssetInfo {
+        registry::asset_info(self.0).expect("assets are only built from registered symbols")
+    }
 }
 
 impl core::fmt::Display for Asset {
@@ -19,6 +29,21 @@ impl core::fmt::Display for Asset {
     }
 }
 
+impl Serialize for Asset {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Asset {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Asset::from_str(&s).map_err(|_| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"a registered asset")
+        })
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AssetToUnitConversionError {
     #[error("couldn't convert asset amount to unit: {0}")]
@@ -26,30 +51,21 @@ pub enum AssetToUnitConversionError {
 }
 
 impl Asset {
-    pub fn as_str(&self) -> &str {
-        match self {
-            Asset::Strk => "strk",
-            Asset::Eth => "eth",
-        }
+    pub fn as_str(&self) -> &'static str {
+        self.0
     }
 
     pub fn precision(&self) -> u8 {
-        match self {
-            Asset::Strk | Asset::Eth => 18,
-        }
+        self.info().decimals
     }
 
     pub fn scale_factor(&self) -> U256 {
-        match self {
-            Asset::Strk | Asset::Eth => U256::from(1_000_000_000_000_000_000u64),
-        }
+        U256::from(10).pow(self.precision().into())
     }
 
+    /// Returns the first unit registered for this asset
     pub fn find_best_unit(&self) -> Unit {
-        match self {
-            Asset::Strk => Unit::MilliStrk,
-            Asset::Eth => Unit::Gwei,
-        }
+        registry::default_unit_of(*self).expect("the registry guarantees every asset has a unit")
     }
 
     /// Convert an onchain amount of asset to a protocol amount of unit
@@ -95,11 +111,10 @@ pub struct AssetFromStrError;
 impl FromStr for Asset {
     type Err = AssetFromStrError;
 
+    /// Case insensitive
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "strk" | "STRK" => Ok(Asset::Strk),
-            "eth" | "ETH" => Ok(Asset::Eth),
-            _ => Err(AssetFromStrError),
-        }
+        registry::asset_info(s)
+            .map(|info| info.asset)
+            .ok_or(AssetFromStrError)
     }
 }
@@ -7,27 +7,54 @@
 //!
 //! The `phf` crate is used to create compile-time static maps, which guarantees
 //! zero runtime overhead when accessing these constants.
+//! Chains we deploy ourselves, e.g. a local starknet-devnet or katana instance,
+//! are added at startup with [`register_chain`].
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, RwLock},
+};
 
 use starknet_types_core::felt::Felt;
 
-use crate::Asset;
+use crate::{Asset, ChainId, registry};
 
+/// Contract addresses of the assets on a network
+///
+/// The addresses set in the [`registry`] are used for the assets this network has no address for.
 #[derive(Debug, Clone)]
-pub struct AssetsAddress([(Asset, Felt); 2]);
+pub struct AssetsAddress(&'static [(Asset, Felt)]);
 
 impl AssetsAddress {
+    /// Create the addresses of a chain registered at runtime
+    pub fn new(addresses: Vec<(Asset, Felt)>) -> Self {
+        Self(Vec::leak(addresses))
+    }
+
     pub fn get_contract_address_for_asset(&self, asset: Asset) -> Option<Felt> {
         self.0
             .iter()
             .find(|(a, _)| asset == *a)
             .map(|(_, address)| *address)
+            .or_else(|| registry::asset_info(asset.as_str()).and_then(|info| info.address))
     }
 
     pub fn get_asset_for_contract_address(&self, contract_address: Felt) -> Option<Asset> {
-        self.0
-            .iter()
+        self.all()
+            .into_iter()
             .find(|(_, a)| contract_address == *a)
-            .map(|(asset, _)| *asset)
+            .map(|(asset, _)| asset)
+    }
+
+    /// Returns the address of every registered asset deployed on this network
+    pub fn all(&self) -> Vec<(Asset, Felt)> {
+        registry::assets()
+            .into_iter()
+            .filter_map(|asset| {
+                self.get_contract_address_for_asset(asset)
+                    .map(|address| (asset, address))
+            })
+            .collect()
     }
 }
 
@@ -35,15 +62,15 @@ impl AssetsAddress {
 ///
 /// These addresses are network-specific and have been verified to be the official
 /// token contracts.
-const SEPOLIA_ASSETS_ADDRESSES: AssetsAddress = AssetsAddress([
+const SEPOLIA_ASSETS_ADDRESSES: AssetsAddress = AssetsAddress(&[
     (
-        Asset::Strk,
+        Asset::STRK,
         Felt::from_hex_unchecked(
             "0x4718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
         ),
     ),
     (
-        Asset::Eth,
+        Asset::ETH,
         Felt::from_hex_unchecked(
             "0x49D36570D4E46F48E99674BD3FCC84644DDD6B96F7C741B1562B82F9E004DC7",
         ),
@@ -71,11 +98,11 @@ pub struct ApibaraConstants {
     pub starting_block: u64,
 }
 
-/// Map of all supported networks and their corresponding constants
+/// Map of the public networks and their corresponding constants
 ///
-/// This is the primary entry point for accessing network-specific configuration.
 /// New networks can be added here without modifying the rest of the codebase.
-pub static ON_CHAIN_CONSTANTS: phf::Map<&'static str, OnChainConstants> = phf::phf_map! {
+/// Lookups should go through [`on_chain_constants`], which also knows the registered chains.
+static ON_CHAIN_CONSTANTS: phf::Map<&'static str, OnChainConstants> = phf::phf_map! {
     "SN_SEPOLIA" =>  OnChainConstants {
         // Starting block is the one which contains the invoice_payment_contract deployment
         // Tx: 0x0582cb60c2fc97fd9fbb18a818197611e1971498a3e5a34272d7072d70a009f3
@@ -109,7 +136,74 @@ pub static ON_CHAIN_CONSTANTS: phf::Map<&'static str, OnChainConstants> = phf::p
         // It is automaticaly used when setting up the network using this repo's `docker-compose.yml`
         invoice_payment_contract_address: Felt::from_hex_unchecked("0x026b2c472aa4ea32fc12f6c44707712552eff4aac48dd75c870e79b8a3fb676e"),
         // The default starknet-devnet config reuses Sepolia asset addresses
-        // TODO: will only work for `eth` and `strk` assets. So we will change it later on.
+        // Other assets need their address to be set in the registry
         assets_contract_address: SEPOLIA_ASSETS_ADDRESSES,
     },
 };
+
+static REGISTERED_CHAINS: LazyLock<RwLock<HashMap<String, &'static OnChainConstants>>> =
+    LazyLock::new(Default::default);
+
+/// Register the constants of a chain, overriding the built-in ones if it shares their chain id
+///
+/// They live as long as the process, chains are only registered once at startup.
+pub fn register_chain(chain_id: &ChainId, constants: OnChainConstants) {
+    REGISTERED_CHAINS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(chain_id.to_string(), Box::leak(Box::new(constants)));
+}
+
+/// Returns the constants of a chain, registered ones taking precedence over the built-in ones
+pub fn on_chain_constants(chain_id: &str) -> Option<&'static OnChainConstants> {
+    REGISTERED_CHAINS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(chain_id)
+        .copied()
+        .or_else(|| ON_CHAIN_CONSTANTS.get(chain_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_chain_takes_precedence() {
+        let katana = ChainId::new_custom("KATANA".to_string()).unwrap();
+        assert!(on_chain_constants(katana.as_str()).is_none());
+
+        let invoice_payment_contract_address = Felt::from(0x1234u64);
+        let strk_address = Felt::from(0x5678u64);
+        register_chain(
+            &katana,
+            OnChainConstants {
+                apibara: ApibaraConstants {
+                    data_stream_uri: None,
+                    starting_block: 0,
+                },
+                invoice_payment_contract_address,
+                assets_contract_address: AssetsAddress::new(vec![(Asset::STRK, strk_address)]),
+            },
+        );
+
+        let constants = on_chain_constants(katana.as_str()).unwrap();
+        assert_eq!(
+            constants.invoice_payment_contract_address,
+            invoice_payment_contract_address
+        );
+        assert_eq!(
+            constants
+                .assets_contract_address
+                .get_asset_for_contract_address(strk_address),
+            Some(Asset::STRK)
+        );
+        assert!(
+            constants
+                .assets_contract_address
+                .get_contract_address_for_asset(Asset::ETH)
+                .is_none()
+        );
+        assert!(on_chain_constants(ChainId::Sepolia.as_str()).is_some());
+    }
+}
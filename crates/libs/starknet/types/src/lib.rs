@@ -10,6 +10,7 @@ mod unit;
 pub use unit::{Unit, UnitFromStrError};
 mod chain_id;
 pub mod constants;
+pub mod registry;
 pub use chain_id::ChainId;
 pub mod transactions;
 
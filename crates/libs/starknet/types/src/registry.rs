@@ -0,0 +1,467 @@
+//! Registry of the assets and units known to the protocol
+//!
+//! It starts with the built-in `strk` and `eth` assets, exposed as the `millistrk` and `gwei` units,
+//! the `btc` asset of the Lightning network, exposed as the `sat` and `msat` units,
+//! and the `usdc` stablecoin of EVM chains, exposed as the `centusdc` unit.
+//! It is extended at startup with the `[[assets]]` and `[[units]]` tables of a TOML file,
+//! then frozen: the first lookup, or the registration, sets it for the rest of the process.
+//! The node and the signer must load the same tables, so that they agree on each unit's derivation index.
+//!
+//! ```toml
+//! [[assets]]
+//! symbol = "eurc"
+//! decimals = 6
+//! address = "0x053c91253bc9682c04929ca02ed00b3e423f6710d2ee7e0d5ebb06f3ecf368a8"
+//!
+//! [[units]]
+//! name = "centeurc"
+//! asset = "eurc"
+//! scale_order = 4
+//! derivation_index = 5
+//! ```
+//!
+//! An already registered asset, such as a built-in one, can be listed again with the same decimals to set its contract address.
+//! It is only used on the chains that don't define their own address for the asset.
+use std::{collections::HashMap, path::Path, sync::OnceLock};
+
+use serde::Deserialize;
+use starknet_types_core::felt::Felt;
+
+use crate::{Asset, Unit};
+
+/// The biggest scale order whose scale factor fits in a u64
+const MAX_SCALE_ORDER: u8 = 19;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("failed to read units config file: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("failed to deserialize units config file content: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("asset `{0}` is defined twice")]
+    DuplicateAsset(String),
+    #[error("asset `{0}` is already registered with {1} decimals, it can't be redefined with {2}")]
+    DecimalsMismatch(String, u8, u8),
+    #[error("unit `{0}` is defined twice")]
+    DuplicateUnit(String),
+    #[error("units `{0}` and `{1}` share the derivation index {2}")]
+    DuplicateDerivationIndex(String, String, u32),
+    #[error("unit `{0}` refers to the unknown asset `{1}`")]
+    UnknownAsset(String, String),
+    #[error(
+        "unit `{0}` has a scale order of {1}, greater than the {2} decimals of its asset or than {MAX_SCALE_ORDER}"
+    )]
+    InvalidScaleOrder(String, u8, u8),
+    #[error("asset `{0}` has no unit")]
+    AssetWithoutUnit(String),
+    #[error("`{0}` is not a valid name, only lowercase ascii letters and digits are allowed")]
+    InvalidName(String),
+    #[error("the registry is already frozen, units must be registered before they are used")]
+    AlreadyFrozen,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UnitsConfig {
+    #[serde(default)]
+    pub assets: Vec<AssetConfig>,
+    #[serde(default)]
+    pub units: Vec<UnitConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetConfig {
+    pub symbol: String,
+    /// On-chain precision of the token
+    pub decimals: u8,
+    /// Address of the token contract, on the chain the node is running against
+    pub address: Option<Felt>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnitConfig {
+    pub name: String,
+    /// Symbol of the asset this unit is an amount of
+    pub asset: String,
+    /// One unit is worth `10^scale_order` of the smallest on-chain denomination of the asset
+    pub scale_order: u8,
+    /// Used in the derivation path of the unit's keysets, must be unique
+    pub derivation_index: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AssetInfo {
+    pub asset: Asset,
+    pub decimals: u8,
+    pub address: Option<Felt>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UnitInfo {
+    pub unit: Unit,
+    pub asset: Asset,
+    pub scale_order: u8,
+    pub derivation_index: u32,
+}
+
+#[derive(Debug)]
+struct Registry {
+    assets: Vec<AssetInfo>,
+    units: Vec<UnitInfo>,
+}
+
+impl Registry {
+    fn builtin() -> Self {
+        Self {
+            assets: vec![
+                AssetInfo {
+                    asset: Asset::STRK,
+                    decimals: 18,
+                    address: None,
+                },
+                AssetInfo {
+                    asset: Asset::ETH,
+                    decimals: 18,
+                    address: None,
+                },
+                // Counted in millisatoshis, the smallest amount payable over Lightning
+                AssetInfo {
+                    asset: Asset::BTC,
+                    decimals: 11,
+                    address: None,
+                },
+                AssetInfo {
+                    asset: Asset::USDC,
+                    decimals: 6,
+                    address: None,
+                },
+            ],
+            units: vec![
+                UnitInfo {
+                    unit: Unit::MILLI_STRK,
+                    asset: Asset::STRK,
+                    scale_order: 15,
+                    derivation_index: 0,
+                },
+                UnitInfo {
+                    unit: Unit::GWEI,
+                    asset: Asset::ETH,
+                    scale_order: 9,
+                    derivation_index: 1,
+                },
+                UnitInfo {
+                    unit: Unit::SAT,
+                    asset: Asset::BTC,
+                    scale_order: 3,
+                    derivation_index: 2,
+                },
+                UnitInfo {
+                    unit: Unit::MSAT,
+                    asset: Asset::BTC,
+                    scale_order: 0,
+                    derivation_index: 3,
+                },
+                UnitInfo {
+                    unit: Unit::CENT_USDC,
+                    asset: Asset::USDC,
+                    scale_order: 4,
+                    derivation_index: 4,
+                },
+            ],
+        }
+    }
+
+    fn extend(&mut self, config: UnitsConfig) -> Result<(), RegistryError> {
+        let registered_assets = self.assets.len();
+        for asset_config in config.assets {
+            let symbol = asset_config.symbol.to_lowercase();
+            validate_name(&symbol)?;
+            match self
+                .assets
+                .iter()
+                .position(|info| info.asset.as_str() == symbol)
+            {
+                Some(idx) if idx < registered_assets => {
+                    let info = &mut self.assets[idx];
+                    if info.decimals != asset_config.decimals {
+                        return Err(RegistryError::DecimalsMismatch(
+                            symbol,
+                            info.decimals,
+                            asset_config.decimals,
+                        ));
+                    }
+                    info.address = asset_config.address;
+                }
+                Some(_) => return Err(RegistryError::DuplicateAsset(symbol)),
+                None => self.assets.push(AssetInfo {
+                    asset: Asset(leak(symbol)),
+                    decimals: asset_config.decimals,
+                    address: asset_config.address,
+                }),
+            }
+        }
+
+        for unit_config in config.units {
+            let name = unit_config.name;
+            validate_name(&name)?;
+            if self.units.iter().any(|info| info.unit.as_str() == name) {
+                return Err(RegistryError::DuplicateUnit(name));
+            }
+            if let Some(info) = self
+                .units
+                .iter()
+                .find(|info| info.derivation_index == unit_config.derivation_index)
+            {
+                return Err(RegistryError::DuplicateDerivationIndex(
+                    info.unit.to_string(),
+                    name,
+                    unit_config.derivation_index,
+                ));
+            }
+            let asset_symbol = unit_config.asset.to_lowercase();
+            let Some(asset) = self
+                .assets
+                .iter()
+                .find(|info| info.asset.as_str() == asset_symbol)
+            else {
+                return Err(RegistryError::UnknownAsset(name, asset_symbol));
+            };
+            if unit_config.scale_order > asset.decimals || unit_config.scale_order > MAX_SCALE_ORDER
+            {
+                return Err(RegistryError::InvalidScaleOrder(
+                    name,
+                    unit_config.scale_order,
+                    asset.decimals,
+                ));
+            }
+
+            self.units.push(UnitInfo {
+                unit: Unit(leak(name)),
+                asset: asset.asset,
+                scale_order: unit_config.scale_order,
+                derivation_index: unit_config.derivation_index,
+            });
+        }
+
+        if let Some(asset) = self
+            .assets
+            .iter()
+            .find(|asset| !self.units.iter().any(|unit| unit.asset == asset.asset))
+        {
+            return Err(RegistryError::AssetWithoutUnit(asset.asset.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), RegistryError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    {
+        return Err(RegistryError::InvalidName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Registered names live as long as the process, and are only created once at startup
+fn leak(name: String) -> &'static str {
+    Box::leak(name.into_boxed_str())
+}
+
+/// The registry once it can't change anymore, indexed for the lookups
+#[derive(Debug)]
+struct FrozenRegistry {
+    /// In registration order
+    units: Vec<Unit>,
+    /// In registration order
+    assets: Vec<Asset>,
+    unit_infos: HashMap<&'static str, UnitInfo>,
+    asset_infos: HashMap<&'static str, AssetInfo>,
+    /// The first registered unit of each asset
+    default_units: HashMap<Asset, Unit>,
+}
+
+impl From<Registry> for FrozenRegistry {
+    fn from(registry: Registry) -> Self {
+        let mut default_units = HashMap::new();
+        for info in &registry.units {
+            default_units.entry(info.asset).or_insert(info.unit);
+        }
+
+        Self {
+            units: registry.units.iter().map(|info| info.unit).collect(),
+            assets: registry.assets.iter().map(|info| info.asset).collect(),
+            unit_infos: registry
+                .units
+                .into_iter()
+                .map(|info| (info.unit.as_str(), info))
+                .collect(),
+            asset_infos: registry
+                .assets
+                .into_iter()
+                .map(|info| (info.asset.as_str(), info))
+                .collect(),
+            default_units,
+        }
+    }
+}
+
+static REGISTRY: OnceLock<FrozenRegistry> = OnceLock::new();
+
+/// Freezes the built-in registry if nothing was registered before
+fn read() -> &'static FrozenRegistry {
+    REGISTRY.get_or_init(|| Registry::builtin().into())
+}
+
+/// Add the assets and units of `config` to the registry and freeze it
+///
+/// Nothing is registered if any of them is invalid.
+/// Fails if the registry was already frozen, by a previous registration or by a lookup.
+pub fn register(config: UnitsConfig) -> Result<(), RegistryError> {
+    let mut registry = Registry::builtin();
+    registry.extend(config)?;
+    REGISTRY
+        .set(registry.into())
+        .map_err(|_| RegistryError::AlreadyFrozen)
+}
+
+/// Add the `[[assets]]` and `[[units]]` tables of the TOML file at `path` to the registry
+///
+/// The other tables of the file are ignored.
+pub fn register_from_file(path: &Path) -> Result<(), RegistryError> {
+    let file_content = std::fs::read_to_string(path)?;
+    let config: UnitsConfig = toml::from_str(&file_content)?;
+
+    register(config)
+}
+
+/// Returns every registered unit
+pub fn units() -> Vec<Unit> {
+    read().units.clone()
+}
+
+/// Returns every registered asset
+pub fn assets() -> Vec<Asset> {
+    read().assets.clone()
+}
+
+pub(crate) fn unit_info(name: &str) -> Option<UnitInfo> {
+    read().unit_infos.get(name).copied()
+}
+
+/// Case insensitive
+pub(crate) fn asset_info(symbol: &str) -> Option<AssetInfo> {
+    let asset_infos = &read().asset_infos;
+    match asset_infos.get(symbol) {
+        Some(info) => Some(*info),
+        None => asset_infos.get(symbol.to_lowercase().as_str()).copied(),
+    }
+}
+
+/// Returns the first registered unit of `asset`
+pub(crate) fn default_unit_of(asset: Asset) -> Option<Unit> {
+    read().default_units.get(&asset).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eurc_config(derivation_index: u32) -> UnitsConfig {
+        toml::from_str(&format!(
+            r#"
+            [[assets]]
+            symbol = "eurc"
+            decimals = 6
+
+            [[units]]
+            name = "centeurc"
+            asset = "eurc"
+            scale_order = 4
+            derivation_index = {derivation_index}
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn extend_with_new_unit() {
+        let mut registry = Registry::builtin();
+        registry.extend(eurc_config(5)).unwrap();
+
+        let unit = registry
+            .units
+            .iter()
+            .find(|info| info.unit.as_str() == "centeurc")
+            .unwrap();
+        assert_eq!(unit.asset.as_str(), "eurc");
+        assert_eq!(unit.scale_order, 4);
+        assert_eq!(unit.derivation_index, 5);
+    }
+
+    #[test]
+    fn reject_shared_derivation_index() {
+        let mut registry = Registry::builtin();
+        assert!(matches!(
+            registry.extend(eurc_config(0)),
+            Err(RegistryError::DuplicateDerivationIndex(_, _, 0))
+        ));
+    }
+
+    #[test]
+    fn reject_scale_order_above_decimals() {
+        let mut registry = Registry::builtin();
+        let mut config = eurc_config(5);
+        config.units[0].scale_order = 7;
+        assert!(matches!(
+            registry.extend(config),
+            Err(RegistryError::InvalidScaleOrder(_, 7, 6))
+        ));
+    }
+
+    #[test]
+    fn reject_asset_without_unit() {
+        let mut registry = Registry::builtin();
+        let mut config = eurc_config(5);
+        config.units.clear();
+        assert!(matches!(
+            registry.extend(config),
+            Err(RegistryError::AssetWithoutUnit(_))
+        ));
+    }
+
+    #[test]
+    fn frozen_registry_indexes_the_units() {
+        let mut registry = Registry::builtin();
+        registry.extend(eurc_config(5)).unwrap();
+        let frozen = FrozenRegistry::from(registry);
+
+        let unit = frozen.unit_infos["centeurc"];
+        assert_eq!(unit.asset.as_str(), "eurc");
+        assert_eq!(unit.derivation_index, 5);
+        assert_eq!(frozen.units.last().map(Unit::as_str), Some("centeurc"));
+        assert_eq!(frozen.default_units[&Asset::BTC], Unit::SAT);
+    }
+
+    #[test]
+    fn set_builtin_asset_address() {
+        let mut registry = Registry::builtin();
+        let config = toml::from_str(
+            r#"
+            [[assets]]
+            symbol = "strk"
+            decimals = 18
+            address = "0x1234"
+            "#,
+        )
+        .unwrap();
+        registry.extend(config).unwrap();
+
+        assert_eq!(registry.assets[0].address, Some(Felt::from(0x1234u64)));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! This module provides a type-safe representation of protocol's units and their conversion
 //! to blockchain-native values.
+//! The units are defined at runtime, see the [`registry`](crate::registry) module.
 
 use std::str::FromStr;
 
@@ -9,39 +10,37 @@ use nuts::Amount;
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
-use crate::Asset;
-
-const GWEI_STR: &str = "gwei";
-const MILLI_STR: &str = "millistrk";
+use crate::{Asset, registry};
 
 /// Represents units supported by the node for user-facing operations
 ///
 /// Units provide a domain-specific abstraction layer over raw blockchain assets.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
-#[serde(rename_all = "lowercase")]
-pub enum Unit {
-    MilliStrk,
-    Gwei,
-}
+/// A value of this type can only be obtained for a registered unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Unit(pub(crate) &'static str);
 
-/// Maps a unit to its corresponding blockchain asset
-///
-/// This enables the application to maintain separate concepts for
-/// user-facing units and blockchain assets while providing a clear
-/// relationship between them.
 impl Unit {
+    pub const MILLI_STRK: Unit = Unit("millistrk");
+    pub const GWEI: Unit = Unit("gwei");
+    pub const SAT: Unit = Unit("sat");
+    pub const MSAT: Unit = Unit("msat");
+    pub const CENT_USDC: Unit = Unit("centusdc");
+
+    fn info(&self) -> registry::UnitInfo {
+        registry::unit_info(self.0).expect("units are only built from registered names")
+    }
+
+    /// Maps a unit to its corresponding blockchain asset
+    ///
+    /// This enables the application to maintain separate concepts for
+    /// user-facing units and blockchain assets while providing a clear
+    /// relationship between them.
     pub fn asset(&self) -> Asset {
-        match self {
-            Unit::MilliStrk => Asset::Strk,
-            Unit::Gwei => Asset::Eth,
-        }
+        self.info().asset
     }
 
     pub fn as_str(&self) -> &'static str {
-        match self {
-            Unit::MilliStrk => MILLI_STR,
-            Unit::Gwei => GWEI_STR,
-        }
+        self.0
     }
 }
 
@@ -56,29 +55,22 @@ impl AsRef<str> for Unit {
 /// This guarantee that different units don't share the same signing keys
 impl From<Unit> for u32 {
     fn from(value: Unit) -> Self {
-        match value {
-            Unit::MilliStrk => 0,
-            Unit::Gwei => 1,
-        }
+        value.info().derivation_index
     }
 }
 
 /// Error returned when parsing an unknown unit string
 #[derive(Debug, thiserror::Error)]
-#[error("invalid value for enum `Unit`")]
+#[error("unknown unit")]
 pub struct UnitFromStrError;
 
 impl FromStr for Unit {
     type Err = UnitFromStrError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let unit = match s {
-            MILLI_STR => Self::MilliStrk,
-            GWEI_STR => Self::Gwei,
-            _ => return Err(UnitFromStrError),
-        };
-
-        Ok(unit)
+        registry::unit_info(s)
+            .map(|info| info.unit)
+            .ok_or(UnitFromStrError)
     }
 }
 
@@ -88,33 +80,37 @@ impl std::fmt::Display for Unit {
     }
 }
 
+impl Serialize for Unit {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Unit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Unit::from_str(&s).map_err(|_| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"a registered unit")
+        })
+    }
+}
+
 // Implementing nuts::traits::Unit enables this type to work with the rest of the protocol code.
 // Required because we will be supporting different sets of Units in the future.
 // Most likely, one by network we abstract.
 impl nuts::traits::Unit for Unit {}
 
-// Conversion factor between an `Unit::Strk` `Amount` and its blockchain-native representation.
-// The starknet STRK token has a precision of 18. Meaning that 1 STRK = 10^18 wei.
-// Because this protocol focus on real life payment, we represent user-facing amounts in milli-STRK (e-3 STRK),
-// which is $0,0001786 at the time or writing those lines. I don't think we will ever need a smaller denomination.
-// We could even arguee it's too small, but we really hope the token price will pump in the future.
-//
-// Therefore we need 10^15 as the conversion factor (10^18 / 10^3)
-const MILLI_STRK_UNIT_TO_ASSET_CONVERSION_RATE: u64 = 1_000_000_000_000_000;
-
 impl Unit {
+    /// Conversion factor between an `Amount` of this unit and its blockchain-native representation
+    ///
+    /// e.g. the STRK token has a precision of 18, and we represent user-facing amounts in milli-STRK (e-3 STRK),
+    /// so the `millistrk` factor is 10^15 (10^18 / 10^3).
     pub fn scale_factor(&self) -> u64 {
-        match self {
-            Unit::MilliStrk => MILLI_STRK_UNIT_TO_ASSET_CONVERSION_RATE,
-            Unit::Gwei => 1_000_000_000,
-        }
+        10u64.pow(self.scale_order().into())
     }
 
     pub fn scale_order(&self) -> u8 {
-        match self {
-            Unit::MilliStrk => 15,
-            Unit::Gwei => 9,
-        }
+        self.info().scale_order
     }
 
     /// Converts an amount of unit to its blockchain-native representation
@@ -126,9 +122,36 @@ impl Unit {
     ///
     /// This check helps to catch accidental mismatches between units and assets early.
     pub fn is_asset_supported(&self, asset: Asset) -> bool {
-        matches!(
-            (self, asset),
-            (Unit::MilliStrk, Asset::Strk) | (Unit::Gwei, Asset::Eth)
-        )
+        self.asset() == asset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_units() {
+        assert_eq!(Unit::from_str("millistrk").unwrap(), Unit::MILLI_STRK);
+        assert_eq!(Unit::MILLI_STRK.scale_factor(), 1_000_000_000_000_000);
+        assert_eq!(u32::from(Unit::MILLI_STRK), 0);
+        assert!(Unit::MILLI_STRK.is_asset_supported(Asset::STRK));
+
+        assert_eq!(Unit::from_str("gwei").unwrap(), Unit::GWEI);
+        assert_eq!(Unit::GWEI.scale_factor(), 1_000_000_000);
+        assert_eq!(u32::from(Unit::GWEI), 1);
+        assert!(!Unit::GWEI.is_asset_supported(Asset::STRK));
+
+        assert_eq!(Unit::from_str("sat").unwrap(), Unit::SAT);
+        assert_eq!(Unit::SAT.scale_factor(), 1_000);
+        assert_eq!(u32::from(Unit::SAT), 2);
+        assert_eq!(Unit::MSAT.scale_factor(), 1);
+        assert!(Unit::MSAT.is_asset_supported(Asset::BTC));
+
+        assert_eq!(Unit::from_str("centusdc").unwrap(), Unit::CENT_USDC);
+        assert_eq!(Unit::CENT_USDC.scale_factor(), 100);
+        assert_eq!(u32::from(Unit::CENT_USDC), 4);
+
+        assert!(Unit::from_str("usd").is_err());
     }
 }
@@ -0,0 +1,78 @@
+use starknet_types::{
+    Asset, ChainId,
+    constants::{
+        ApibaraConstants, AssetsAddress, OnChainConstants, on_chain_constants, register_chain,
+    },
+    registry,
+};
+use starknet_types_core::felt::Felt;
+
+// The registry is frozen by its first use, so this is the only test of the binary
+#[test]
+fn chain_address_takes_precedence_over_the_registry() {
+    let config = toml::from_str(
+        r#"
+        [[assets]]
+        symbol = "strk"
+        decimals = 18
+        address = "0x1"
+
+        [[assets]]
+        symbol = "eurc"
+        decimals = 6
+        address = "0x2"
+
+        [[units]]
+        name = "centeurc"
+        asset = "eurc"
+        scale_order = 4
+        derivation_index = 5
+        "#,
+    )
+    .unwrap();
+    registry::register(config).unwrap();
+    let eurc: Asset = "eurc".parse().unwrap();
+
+    let katana = ChainId::new_custom("KATANA".to_string()).unwrap();
+    let katana_strk_address = Felt::from(0x3u64);
+    register_chain(
+        &katana,
+        OnChainConstants {
+            apibara: ApibaraConstants {
+                data_stream_uri: None,
+                starting_block: 0,
+            },
+            invoice_payment_contract_address: Felt::from(0x4u64),
+            assets_contract_address: AssetsAddress::new(vec![(Asset::STRK, katana_strk_address)]),
+        },
+    );
+
+    let katana_addresses = &on_chain_constants(katana.as_str())
+        .unwrap()
+        .assets_contract_address;
+    assert_eq!(
+        katana_addresses.get_contract_address_for_asset(Asset::STRK),
+        Some(katana_strk_address)
+    );
+    assert_eq!(
+        katana_addresses.get_contract_address_for_asset(eurc),
+        Some(Felt::from(0x2u64))
+    );
+    assert_eq!(
+        katana_addresses.get_asset_for_contract_address(Felt::from(0x2u64)),
+        Some(eurc)
+    );
+
+    // The built-in address of a public network is kept too
+    let sepolia_addresses = &on_chain_constants(ChainId::Sepolia.as_str())
+        .unwrap()
+        .assets_contract_address;
+    assert_ne!(
+        sepolia_addresses.get_contract_address_for_asset(Asset::STRK),
+        Some(Felt::from(0x1u64))
+    );
+    assert_eq!(
+        sepolia_addresses.get_contract_address_for_asset(eurc),
+        Some(Felt::from(0x2u64))
+    );
+}
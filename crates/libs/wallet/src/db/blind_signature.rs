@@ -0,0 +1,55 @@
+use nuts::{Amount, nut01::PublicKey, nut02::KeysetId};
+use rusqlite::{Connection, Result, params};
+
+/// The blind signature each proof was unblinded from
+///
+/// Kept so that the wallet can check the node includes them in its proof of liabilities.
+pub const CREATE_TABLE_BLIND_SIGNATURE: &str = r#"
+        CREATE TABLE IF NOT EXISTS blind_signature (
+            y BLOB(33) PRIMARY KEY REFERENCES proof(y) ON DELETE CASCADE,
+            blinded_secret BLOB(33) UNIQUE NOT NULL,
+            blind_signature BLOB(33) NOT NULL
+        );
+    "#;
+
+pub fn insert(
+    conn: &Connection,
+    y: PublicKey,
+    blinded_secret: PublicKey,
+    blind_signature: PublicKey,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO blind_signature (y, blinded_secret, blind_signature) VALUES (?1, ?2, ?3)",
+        params![y, blinded_secret, blind_signature],
+    )?;
+
+    Ok(())
+}
+
+/// Returns the keyset id, amount, blinded secret and blind signature
+/// of every signature received from this node
+#[allow(clippy::type_complexity)]
+pub fn get_for_node(
+    conn: &Connection,
+    node_id: u32,
+) -> Result<Vec<(KeysetId, Amount, PublicKey, PublicKey)>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT p.keyset_id, p.amount, bs.blinded_secret, bs.blind_signature
+           FROM blind_signature bs
+           JOIN proof p ON p.y = bs.y
+           WHERE p.node_id = ?1"#,
+    )?;
+
+    let signatures = stmt
+        .query_map([node_id], |r| {
+            Ok((
+                r.get::<_, KeysetId>(0)?,
+                r.get::<_, Amount>(1)?,
+                r.get::<_, PublicKey>(2)?,
+                r.get::<_, PublicKey>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(signatures)
+}
@@ -1,5 +1,5 @@
 use nuts::nut02::KeysetId;
-use rusqlite::{Connection, OptionalExtension, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, Transaction, params};
 
 pub const CREATE_TABLE_KEYSET: &str = r#"
         CREATE TABLE IF NOT EXISTS keyset (
@@ -13,6 +13,16 @@ pub const CREATE_TABLE_KEYSET: &str = r#"
         CREATE INDEX keyset_unit ON keyset(unit);
         CREATE INDEX keyset_active ON keyset(active);
     "#;
+pub const ALTER_TABLE_KEYSET_ADD_FINAL_EXPIRY: &str = r#"
+        ALTER TABLE keyset ADD COLUMN final_expiry INTEGER;
+    "#;
+
+/// Migration adding the `final_expiry` column
+pub fn add_final_expiry(tx: &Transaction) -> Result<()> {
+    tx.execute(ALTER_TABLE_KEYSET_ADD_FINAL_EXPIRY, ())?;
+
+    Ok(())
+}
 
 pub fn upsert_many_for_node(
     conn: &Connection,
@@ -27,11 +37,11 @@ pub fn upsert_many_for_node(
     )?;
 
     const UPSERT_NODE_KEYSET: &str = r#"
-            INSERT INTO keyset (id, node_id, unit, active)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO keyset (id, node_id, unit, active, final_expiry)
+            VALUES (?1, ?2, ?3, ?4, ?5)
             ON CONFLICT(id) DO UPDATE
-                SET active=excluded.active
-                WHERE active != excluded.active;
+                SET active=excluded.active, final_expiry=excluded.final_expiry
+                WHERE active != excluded.active OR final_expiry IS NOT excluded.final_expiry;
     "#;
 
     for keyset in keysets {
@@ -44,7 +54,7 @@ pub fn upsert_many_for_node(
         })?;
         conn.execute(
             UPSERT_NODE_KEYSET,
-            params![id, node_id, keyset.unit, keyset.active],
+            params![id, node_id, keyset.unit, keyset.active, keyset.final_expiry],
         )?;
     }
 
@@ -1,7 +1,8 @@
 use nuts::nut02::KeysetId;
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, Result, Transaction, params};
 
 pub mod balance;
+pub mod blind_signature;
 pub mod keyset;
 pub mod melt_quote;
 pub mod mint_quote;
@@ -40,6 +41,12 @@ pub const CREATE_TABLE_MELT_QUOTE: &str = r#"
             transfer_ids TEXT
         );"#;
 
+/// Schema changes applied, in order, on top of the tables created by [`create_tables`]
+///
+/// The `user_version` pragma of the database holds the number of migrations already applied.
+/// Only append to this list.
+const MIGRATIONS: &[fn(&Transaction) -> Result<()>] = &[keyset::add_final_expiry];
+
 pub fn create_tables(conn: &mut Connection) -> Result<()> {
     let tx = conn.transaction()?;
 
@@ -49,6 +56,13 @@ pub fn create_tables(conn: &mut Connection) -> Result<()> {
     tx.execute(CREATE_TABLE_MINT_QUOTE, ())?;
     tx.execute(CREATE_TABLE_MELT_QUOTE, ())?;
     tx.execute(proof::CREATE_TABLE_PROOF, ())?;
+    tx.execute(blind_signature::CREATE_TABLE_BLIND_SIGNATURE, ())?;
+
+    let applied: usize = tx.query_row("PRAGMA user_version", (), |row| row.get(0))?;
+    for migration in MIGRATIONS.iter().skip(applied) {
+        migration(&tx)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
 
     tx.commit()?;
 
@@ -71,3 +85,57 @@ pub fn insert_keyset_keys<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keyset table, as created before the `final_expiry` column existed
+    const OLD_CREATE_TABLE_KEYSET: &str = r#"
+        CREATE TABLE keyset (
+            id BLOB(8) PRIMARY KEY,
+            node_id INTEGER NOT NULL REFERENCES node(id) ON DELETE CASCADE,
+            unit TEXT NOT NULL,
+            active BOOL NOT NULL
+        );"#;
+
+    fn keyset() -> node_client::Keyset {
+        node_client::Keyset {
+            id: vec![0, 1, 2, 3, 4, 5, 6, 7],
+            unit: "millistrk".to_string(),
+            active: true,
+            final_expiry: Some(1_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn migrates_a_db_created_with_the_old_keyset_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(node::CREATE_TABLE_NODE, ()).unwrap();
+        conn.execute(OLD_CREATE_TABLE_KEYSET, ()).unwrap();
+        conn.execute("INSERT INTO node (url) VALUES ('http://localhost')", ())
+            .unwrap();
+
+        create_tables(&mut conn).unwrap();
+
+        keyset::upsert_many_for_node(&conn, 1, vec![keyset()]).unwrap();
+        let final_expiry: Option<u64> = conn
+            .query_row("SELECT final_expiry FROM keyset", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(final_expiry, Some(1_000));
+    }
+
+    #[test]
+    fn migrations_run_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        create_tables(&mut conn).unwrap();
+        create_tables(&mut conn).unwrap();
+
+        let version: usize = conn
+            .query_row("PRAGMA user_version", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
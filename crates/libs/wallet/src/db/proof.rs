@@ -147,6 +147,56 @@ pub fn delete_proofs(conn: &Connection, ys: &[PublicKey]) -> Result<()> {
     Ok(())
 }
 
+/// Returns the unspent proofs of keysets whose final expiry is in the `(after, before]` range
+///
+/// Each proof is returned as its `y` along with its amount and unit.
+pub fn get_unspent_proofs_of_keysets_expiring_between(
+    conn: &Connection,
+    node_id: u32,
+    after: u64,
+    before: u64,
+) -> Result<Vec<(PublicKey, Amount, String)>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT p.y, p.amount, k.unit
+           FROM proof p
+           JOIN keyset k ON p.keyset_id = k.id
+           WHERE p.node_id = ?1 AND p.state = ?2 AND k.final_expiry > ?3 AND k.final_expiry <= ?4"#,
+    )?;
+
+    let proofs = stmt
+        .query_map(params![node_id, ProofState::Unspent, after, before], |r| {
+            Ok((
+                r.get::<_, PublicKey>(0)?,
+                r.get::<_, Amount>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(proofs)
+}
+
+/// Returns the y, keyset id and amount of the proofs of this node we know are spent
+pub fn get_spent_proofs_of_node(
+    conn: &Connection,
+    node_id: u32,
+) -> Result<Vec<(PublicKey, KeysetId, Amount)>> {
+    let mut stmt =
+        conn.prepare("SELECT y, keyset_id, amount FROM proof WHERE node_id = ?1 AND state = ?2")?;
+
+    let proofs = stmt
+        .query_map(params![node_id, ProofState::Spent], |r| {
+            Ok((
+                r.get::<_, PublicKey>(0)?,
+                r.get::<_, KeysetId>(1)?,
+                r.get::<_, Amount>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(proofs)
+}
+
 /// Returns the node available amount of unit
 ///
 /// Sum the amount of each unspent proof of unit for this node
@@ -0,0 +1,195 @@
+//! Check that a node includes our signatures and spent proofs in its proof of liabilities
+
+use std::collections::HashMap;
+
+use node_client::{LiabilitiesRequest, NodeClient, inclusion_proof_from_path};
+use nuts::{Amount, liabilities::SumNode, nut01::PublicKey, nut02::KeysetId};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use tonic::transport::Channel;
+
+use crate::{db, errors::Error};
+
+/// The maximum number of inclusion proofs the node accepts to return in one request
+const MAX_PROOFS_PER_REQUEST: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct KeysetLiabilities {
+    pub keyset_id: KeysetId,
+    pub unit: String,
+    pub issued: Amount,
+    pub issued_count: u64,
+    pub burned: Amount,
+    pub burned_count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LiabilitiesCheck {
+    /// The liabilities published by the node, as of its last response
+    pub keysets: Vec<KeysetLiabilities>,
+    pub verified_signatures: usize,
+    pub verified_spent_proofs: usize,
+    /// Blinded secrets of our signatures the node couldn't prove the inclusion of
+    pub missing_signatures: Vec<PublicKey>,
+    /// Ys of our spent proofs the node couldn't prove the inclusion of
+    pub missing_spent_proofs: Vec<PublicKey>,
+}
+
+impl LiabilitiesCheck {
+    pub fn is_valid(&self) -> bool {
+        self.missing_signatures.is_empty() && self.missing_spent_proofs.is_empty()
+    }
+}
+
+/// A signature or spent proof of ours, described by its key and the leaf expected in the tree
+struct Item {
+    key: PublicKey,
+    keyset_id: KeysetId,
+    leaf: SumNode,
+}
+
+/// Returns, for each item, whether its inclusion proof verifies against the root of its keyset
+///
+/// Signatures and spent proofs are requested separately,
+/// so that a single request doesn't link a blinded secret to the Y of the same proof.
+async fn verify_inclusion(
+    node_client: &mut NodeClient<Channel>,
+    items: &[Item],
+    issued: bool,
+    check: &mut LiabilitiesCheck,
+) -> Result<Vec<bool>, Error> {
+    let mut included = Vec::with_capacity(items.len());
+
+    // Always make a request, so that the liabilities are returned even without items to check
+    let chunks: Vec<&[Item]> = if items.is_empty() {
+        vec![&[]]
+    } else {
+        items.chunks(MAX_PROOFS_PER_REQUEST).collect()
+    };
+
+    for chunk in chunks {
+        let keys = chunk.iter().map(|i| i.key.to_bytes().to_vec()).collect();
+        let request = if issued {
+            LiabilitiesRequest {
+                blinded_secrets: keys,
+                ys: vec![],
+            }
+        } else {
+            LiabilitiesRequest {
+                blinded_secrets: vec![],
+                ys: keys,
+            }
+        };
+        let response = node_client.liabilities(request).await?.into_inner();
+
+        let mut roots = HashMap::with_capacity(response.keysets.len());
+        check.keysets = Vec::with_capacity(response.keysets.len());
+        for keyset in response.keysets {
+            let keyset_id = KeysetId::from_bytes(&keyset.keyset_id)?;
+            let issued_root: SumNode = keyset
+                .issued_root
+                .ok_or_else(|| Error::Protocol("missing issued root".to_string()))?
+                .try_into()
+                .map_err(|e| Error::Protocol(format!("invalid issued root: {}", e)))?;
+            let burned_root: SumNode = keyset
+                .burned_root
+                .ok_or_else(|| Error::Protocol("missing burned root".to_string()))?
+                .try_into()
+                .map_err(|e| Error::Protocol(format!("invalid burned root: {}", e)))?;
+
+            roots.insert(keyset_id, if issued { issued_root } else { burned_root });
+            check.keysets.push(KeysetLiabilities {
+                keyset_id,
+                unit: keyset.unit,
+                issued: issued_root.sum,
+                issued_count: keyset.issued_count,
+                burned: burned_root.sum,
+                burned_count: keyset.burned_count,
+            });
+        }
+
+        let proofs = if issued {
+            response.issued_proofs
+        } else {
+            response.burned_proofs
+        };
+        if proofs.len() != chunk.len() {
+            return Err(Error::Protocol(format!(
+                "expected {} inclusion proofs, got {}",
+                chunk.len(),
+                proofs.len()
+            )));
+        }
+
+        for (item, proof) in chunk.iter().zip(proofs) {
+            let is_included = proof.found
+                && proof.keyset_id == item.keyset_id.to_bytes()
+                && match (
+                    roots.get(&item.keyset_id),
+                    inclusion_proof_from_path(proof.path),
+                ) {
+                    (Some(root), Ok(proof)) => proof.verify(&item.leaf, root),
+                    _ => false,
+                };
+            included.push(is_included);
+        }
+    }
+
+    Ok(included)
+}
+
+/// Check that all the signatures we received from this node, and all the proofs we know it burned,
+/// are included in its proof of liabilities
+///
+/// Signatures received before the wallet started recording them are not checked.
+pub async fn check_liabilities(
+    pool: Pool<SqliteConnectionManager>,
+    node_client: &mut NodeClient<Channel>,
+    node_id: u32,
+) -> Result<LiabilitiesCheck, Error> {
+    let (signatures, spent_proofs) = {
+        let db_conn = pool.get()?;
+        let signatures: Vec<_> = db::blind_signature::get_for_node(&db_conn, node_id)?
+            .into_iter()
+            .map(
+                |(keyset_id, amount, blinded_secret, blind_signature)| Item {
+                    key: blinded_secret,
+                    keyset_id,
+                    leaf: SumNode::issued_leaf(amount, &blinded_secret, &blind_signature),
+                },
+            )
+            .collect();
+        let spent_proofs: Vec<_> = db::proof::get_spent_proofs_of_node(&db_conn, node_id)?
+            .into_iter()
+            .map(|(y, keyset_id, amount)| Item {
+                key: y,
+                keyset_id,
+                leaf: SumNode::burned_leaf(amount, &y),
+            })
+            .collect();
+
+        (signatures, spent_proofs)
+    };
+
+    let mut check = LiabilitiesCheck::default();
+
+    let included = verify_inclusion(node_client, &signatures, true, &mut check).await?;
+    for (item, is_included) in signatures.iter().zip(included) {
+        if is_included {
+            check.verified_signatures += 1;
+        } else {
+            check.missing_signatures.push(item.key);
+        }
+    }
+
+    let included = verify_inclusion(node_client, &spent_proofs, false, &mut check).await?;
+    for (item, is_included) in spent_proofs.iter().zip(included) {
+        if is_included {
+            check.verified_spent_proofs += 1;
+        } else {
+            check.missing_spent_proofs.push(item.key);
+        }
+    }
+
+    Ok(check)
+}
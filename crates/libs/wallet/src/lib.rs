@@ -1,5 +1,6 @@
 pub mod db;
 pub mod errors;
+pub mod liabilities;
 pub mod melt;
 pub mod mint;
 mod outputs;
@@ -204,7 +205,7 @@ pub fn store_new_tokens(
     let signatures_iterator = pre_mints
         .into_iter()
         .zip(signatures_iterator)
-        .map(|(pm, signature)| (signature, pm.secret, pm.r, pm.amount));
+        .map(|(pm, signature)| (pm.blinded_secret, signature, pm.secret, pm.r, pm.amount));
 
     store_new_proofs_from_blind_signatures(db_conn, node_id, keyset_id, signatures_iterator)
 }
@@ -213,7 +214,7 @@ pub fn store_new_proofs_from_blind_signatures(
     db_conn: &Connection,
     node_id: u32,
     keyset_id: KeysetId,
-    signatures_iterator: impl IntoIterator<Item = (PublicKey, Secret, SecretKey, Amount)>,
+    signatures_iterator: impl IntoIterator<Item = (PublicKey, PublicKey, Secret, SecretKey, Amount)>,
 ) -> Result<Vec<(PublicKey, Amount)>, Error> {
     const GET_PUBKEY: &str = r#"
         SELECT pubkey FROM key WHERE keyset_id = ?1 and amount = ?2 LIMIT 1;
@@ -229,13 +230,13 @@ pub fn store_new_proofs_from_blind_signatures(
 
     let mut new_tokens = Vec::new();
 
-    for (blinded_message, secret, r, amount) in signatures_iterator {
+    for (blinded_secret, blind_signature, secret, r, amount) in signatures_iterator {
         let node_key_pubkey = PublicKey::from_str(
             &get_pubkey_stmt
                 .query_row(params![keyset_id, amount], |row| row.get::<_, String>(0))?,
         )?;
         let unblinded_signature: PublicKey =
-            unblind_message(&blinded_message, &r, &node_key_pubkey)?;
+            unblind_message(&blind_signature, &r, &node_key_pubkey)?;
 
         let y = hash_to_curve(secret.as_ref())?;
 
@@ -248,6 +249,7 @@ pub fn store_new_proofs_from_blind_signatures(
             &unblinded_signature,
             ProofState::Unspent,
         ])?;
+        db::blind_signature::insert(db_conn, y, blinded_secret, blind_signature)?;
 
         new_tokens.push((y, amount));
     }
@@ -538,6 +540,129 @@ pub async fn receive_wad(
     Ok(total_amount)
 }
 
+/// Proofs of keysets expiring in less than this many seconds should be swapped for fresh ones
+pub const KEYSET_EXPIRY_SWAP_THRESHOLD: u64 = 7 * 24 * 60 * 60;
+
+/// Max number of inputs sent in a single swap request
+const MAX_INPUTS_PER_SWAP: usize = 64;
+
+/// Swap the proofs of keysets nearing their final expiry for proofs of the active keyset
+///
+/// Once its final expiry is reached, the node will refuse the proofs of a keyset,
+/// so they must be exchanged before it happens.
+/// Proofs of keysets expiring in less than `threshold` seconds are swapped.
+/// Returns the amount swapped for each unit.
+pub async fn swap_proofs_of_expiring_keysets(
+    pool: Pool<SqliteConnectionManager>,
+    node_client: &mut NodeClient<Channel>,
+    node_id: u32,
+    threshold: u64,
+) -> Result<Vec<(String, Amount)>, Error> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let proofs_per_unit = {
+        let db_conn = pool.get()?;
+        db::proof::get_unspent_proofs_of_keysets_expiring_between(
+            &db_conn,
+            node_id,
+            now,
+            now.saturating_add(threshold),
+        )?
+        .into_iter()
+        .into_group_map_by(|(_, _, unit)| unit.clone())
+    };
+
+    let mut swapped = Vec::with_capacity(proofs_per_unit.len());
+    for (unit, proofs) in proofs_per_unit {
+        let mut total_amount = Amount::ZERO;
+        for chunk in proofs.chunks(MAX_INPUTS_PER_SWAP) {
+            let ys: Vec<PublicKey> = chunk.iter().map(|(y, _, _)| *y).collect();
+            let amount = chunk
+                .iter()
+                .try_fold(Amount::ZERO, |acc, (_, a, _)| acc.checked_add(a))
+                .ok_or(Error::AmountOverflow)?;
+            log::warn!(
+                "node {}: {} {} are held in keysets about to expire, swapping them",
+                node_id,
+                amount,
+                unit
+            );
+
+            swap_all_proofs(pool.clone(), node_client, node_id, &unit, &ys, amount).await?;
+            total_amount = total_amount
+                .checked_add(&amount)
+                .ok_or(Error::AmountOverflow)?;
+        }
+        swapped.push((unit, total_amount));
+    }
+
+    Ok(swapped)
+}
+
+/// Swap those proofs, worth `amount` in total, for new ones of the active keyset for `unit`
+async fn swap_all_proofs(
+    pool: Pool<SqliteConnectionManager>,
+    node_client: &mut NodeClient<Channel>,
+    node_id: u32,
+    unit: &str,
+    ys: &[PublicKey],
+    amount: Amount,
+) -> Result<(), Error> {
+    let (keyset_id, inputs) = {
+        let db_conn = pool.get()?;
+        let keyset_id = get_active_keyset_for_unit(&db_conn, node_id, unit)?;
+        let inputs = db::proof::get_proofs_by_ids(&db_conn, ys)?
+            .into_iter()
+            .map(
+                |(amount, keyset_id, unblinded_signature, secret)| node_client::Proof {
+                    amount: amount.into(),
+                    keyset_id: keyset_id.to_bytes().to_vec(),
+                    secret: secret.to_string(),
+                    unblind_signature: unblinded_signature.to_bytes().to_vec(),
+                },
+            )
+            .collect();
+        db::proof::set_proofs_to_state(&db_conn, ys, ProofState::Pending)?;
+        (keyset_id, inputs)
+    };
+
+    let pre_mints = PreMint::generate_for_amount(amount, &SplitTarget::None)?;
+    let outputs = build_outputs_from_premints(keyset_id.to_bytes(), &pre_mints);
+
+    let swap_request = node_client::SwapRequest { inputs, outputs };
+    let swap_request_hash = hash_swap_request(&swap_request);
+    let swap_result = node_client.swap(swap_request).await;
+
+    {
+        let mut db_conn = pool.get()?;
+        let swap_response = match swap_result {
+            Ok(r) => r.into_inner(),
+            Err(e) => {
+                db::proof::set_proofs_to_state(&db_conn, ys, ProofState::Unspent)?;
+                return Err(e.into());
+            }
+        };
+
+        let tx = db_conn.transaction()?;
+        db::proof::set_proofs_to_state(&tx, ys, ProofState::Spent)?;
+        let _new_tokens = store_new_tokens(
+            &tx,
+            node_id,
+            keyset_id,
+            pre_mints.into_iter(),
+            swap_response.signatures.into_iter(),
+        )?;
+        tx.commit()?;
+    }
+
+    acknowledge(node_client, nuts::nut19::Route::Swap, swap_request_hash).await?;
+
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RegisterNodeError {
     #[error("failed connect to the node: {0}")]
@@ -56,6 +56,7 @@ pub async fn pay_quote<U: Unit>(
         method: method.clone(),
         quote: quote_id.clone(),
         inputs: convert_inputs(&inputs),
+        outputs: vec![],
     };
 
     let melt_request_hash = hash_melt_request(&melt_request);
@@ -43,6 +43,11 @@ pub async fn melt_quote(
                         return Ok(None);
                     }
                 }
+                MeltQuoteState::Expired => {
+                    db::melt_quote::delete(&tx, &quote_id)?;
+                    tx.commit()?;
+                    return Ok(None);
+                }
                 MeltQuoteState::Pending => {}
                 MeltQuoteState::Paid => {
                     if !response.transfer_ids.is_empty() {
@@ -0,0 +1,14 @@
+use anyhow::Result;
+use concurrency_tests::{
+    connect_to_nodes, read_env_variables, read_second_node_url, run_same_input_operations,
+};
+
+/// The concurrent requests are spread between two nodes sharing the same database,
+/// so that the double spending protection doesn't rely on anything local to a node
+#[tokio::test]
+pub async fn same_input_across_nodes() -> Result<()> {
+    let env = read_env_variables()?;
+    let node_client = connect_to_nodes(&[env.node_url.clone(), read_second_node_url()?])?;
+
+    run_same_input_operations(node_client, env).await
+}
@@ -1,5 +1,13 @@
 use anyhow::Result;
-use test_utils::common::utils::EnvVariables;
+use node_client::NodeClient;
+use test_utils::{
+    common::utils::EnvVariables,
+    concurrency::starknet::operations::{
+        melt_same_input, melt_same_quote, mint_same_output, mint_same_quote, swap_same_input,
+        swap_same_output,
+    },
+};
+use tonic::transport::{Channel, Endpoint};
 
 pub fn read_env_variables() -> Result<EnvVariables> {
     let node_url = std::env::var("NODE_URL")?;
@@ -14,3 +22,41 @@ pub fn read_env_variables() -> Result<EnvVariables> {
         account_address,
     })
 }
+
+/// Url of a second node instance, sharing the database of the one at `NODE_URL`
+pub fn read_second_node_url() -> Result<String> {
+    Ok(std::env::var("SECOND_NODE_URL")?)
+}
+
+/// A single client spreading its requests between all the nodes
+pub fn connect_to_nodes(node_urls: &[String]) -> Result<NodeClient<Channel>> {
+    let endpoints = node_urls
+        .iter()
+        .map(|url| Endpoint::from_shared(url.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(NodeClient::new(Channel::balance_list(
+        endpoints.into_iter(),
+    )))
+}
+
+/// Each operation is attempted many times concurrently, exactly one attempt should succeed
+pub async fn run_same_input_operations(
+    node_client: NodeClient<Channel>,
+    env: EnvVariables,
+) -> Result<()> {
+    println!("mint_same_output");
+    mint_same_output(node_client.clone(), env.clone()).await?;
+    println!("mint_same_quote");
+    mint_same_quote(node_client.clone(), env.clone()).await?;
+    println!("swap_same_input");
+    swap_same_input(node_client.clone(), env.clone()).await?;
+    println!("swap_same_output");
+    swap_same_output(node_client.clone(), env.clone()).await?;
+    println!("melt_same_input");
+    melt_same_input(node_client.clone(), env.clone()).await?;
+    println!("melt_same_quote");
+    melt_same_quote(node_client.clone(), env.clone()).await?;
+
+    Ok(())
+}
@@ -16,13 +16,13 @@ pub async fn run_e2e() -> Result<()> {
     let mut wallet_ops = WalletOps::new(db_pool.clone(), node_id, node_client);
 
     wallet_ops
-        .mint(10.into(), starknet_types::Asset::Strk, env)
+        .mint(10.into(), starknet_types::Asset::STRK, env)
         .await?;
     let wad = wallet_ops
         .send(
             node_url,
             10.into(),
-            starknet_types::Asset::Strk,
+            starknet_types::Asset::STRK,
             Some("Here is some money".to_string()),
         )
         .await?;
@@ -30,7 +30,7 @@ pub async fn run_e2e() -> Result<()> {
     wallet_ops
         .melt(
             10.into(),
-            starknet_types::Asset::Strk,
+            starknet_types::Asset::STRK,
             "0x064b48806902a367c8598f4f95c305e8c1a1acba5f082d294a43793113115691".to_string(),
         )
         .await?;
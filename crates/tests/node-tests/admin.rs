@@ -0,0 +1,190 @@
+use anyhow::Result;
+use node_client::{
+    AdminClient, ExpireQuoteRequest, GetNodeInfoRequest, GetQuoteRequest, ListQuotesRequest,
+    MintQuoteRequest, QuoteKind, SetAmountLimitsRequest, SetDisabledRequest,
+};
+use node_tests::{init_admin_client, init_node_client};
+use serde_json::Value;
+use starknet_types::Unit;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::Code;
+
+fn mint_quote_request(amount: u64) -> MintQuoteRequest {
+    MintQuoteRequest {
+        method: "starknet".to_string(),
+        amount,
+        unit: Unit::MILLI_STRK.to_string(),
+        description: None,
+    }
+}
+
+/// The settings of the shared node that these tests modify
+#[derive(Debug)]
+struct Settings {
+    mint_disabled: bool,
+    melt_disabled: bool,
+    /// Min and max amount of starknet millistrk mint quotes
+    mint_limits: (Option<u64>, Option<u64>),
+}
+
+impl Settings {
+    async fn read() -> Result<Self> {
+        let info = init_node_client()
+            .await?
+            .get_node_info(GetNodeInfoRequest {})
+            .await?
+            .into_inner()
+            .info;
+        let info: Value = serde_json::from_str(&info)?;
+        let mint_method = info["nuts"]["4"]["methods"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|m| m["method"] == "starknet" && m["unit"] == Unit::MILLI_STRK.as_str())
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(Self {
+            mint_disabled: info["nuts"]["4"]["disabled"].as_bool().unwrap_or_default(),
+            melt_disabled: info["nuts"]["5"]["disabled"].as_bool().unwrap_or_default(),
+            mint_limits: (
+                mint_method["min_amount"].as_u64(),
+                mint_method["max_amount"].as_u64(),
+            ),
+        })
+    }
+
+    async fn restore(&self) -> Result<()> {
+        let mut admin_client = init_admin_client().await?;
+        admin_client
+            .set_mint_disabled(SetDisabledRequest {
+                disabled: self.mint_disabled,
+            })
+            .await?;
+        admin_client
+            .set_melt_disabled(SetDisabledRequest {
+                disabled: self.melt_disabled,
+            })
+            .await?;
+        admin_client
+            .set_amount_limits(SetAmountLimitsRequest {
+                kind: QuoteKind::QkMint.into(),
+                method: "starknet".to_string(),
+                unit: Unit::MILLI_STRK.to_string(),
+                min_amount: self.mint_limits.0,
+                max_amount: self.mint_limits.1,
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Run `test`, then put the settings of the node back as they were, even if it failed or panicked
+///
+/// The node is shared with the other tests, which expect it to be configured as in `mock.toml`.
+async fn with_settings_restored(
+    test: impl Future<Output = Result<()>> + Send + 'static,
+) -> Result<()> {
+    let settings = Settings::read().await?;
+    let res = tokio::spawn(test).await;
+    settings.restore().await?;
+
+    match res {
+        Ok(res) => res,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+#[tokio::test]
+async fn unauthenticated_requests_are_refused() -> Result<()> {
+    let grpc_port = std::env::var("GRPC_PORT")?;
+    let mut client = AdminClient::connect(format!("http://[::0]:{}", grpc_port)).await?;
+
+    let res = client
+        .set_mint_disabled(SetDisabledRequest { disabled: true })
+        .await;
+
+    assert!(matches!(res, Err(status) if status.code() == Code::Unauthenticated));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn settings_and_quotes_management() -> Result<()> {
+    with_settings_restored(settings_and_quotes_management_inner()).await
+}
+
+async fn settings_and_quotes_management_inner() -> Result<()> {
+    let mut admin_client = init_admin_client().await?;
+    let mut client = init_node_client().await?;
+
+    // Disable then re-enable minting
+    admin_client
+        .set_mint_disabled(SetDisabledRequest { disabled: true })
+        .await?;
+    let res = client.mint_quote(mint_quote_request(8)).await;
+    assert!(matches!(res, Err(status) if status.code() == Code::FailedPrecondition));
+    admin_client
+        .set_mint_disabled(SetDisabledRequest { disabled: false })
+        .await?;
+
+    // Lower then reset the max amount
+    admin_client
+        .set_amount_limits(SetAmountLimitsRequest {
+            kind: QuoteKind::QkMint.into(),
+            method: "starknet".to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
+            min_amount: Some(1),
+            max_amount: Some(16),
+        })
+        .await?;
+    let res = client.mint_quote(mint_quote_request(32)).await;
+    assert!(matches!(res, Err(status) if status.code() == Code::InvalidArgument));
+    admin_client
+        .set_amount_limits(SetAmountLimitsRequest {
+            kind: QuoteKind::QkMint.into(),
+            method: "starknet".to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
+            min_amount: Some(1),
+            max_amount: None,
+        })
+        .await?;
+
+    // Inspect and expire a quote
+    let quote_id = client
+        .mint_quote(mint_quote_request(32))
+        .await?
+        .into_inner()
+        .quote;
+    let quotes = admin_client
+        .list_quotes(ListQuotesRequest {
+            kind: QuoteKind::QkMint.into(),
+            state: Some("UNPAID".to_string()),
+            limit: 1000,
+        })
+        .await?
+        .into_inner()
+        .quotes;
+    assert!(quotes.iter().any(|q| q.id == quote_id));
+
+    admin_client
+        .expire_quote(ExpireQuoteRequest {
+            kind: QuoteKind::QkMint.into(),
+            quote: quote_id.clone(),
+        })
+        .await?;
+    let quote = admin_client
+        .get_quote(GetQuoteRequest {
+            kind: QuoteKind::QkMint.into(),
+            quote: quote_id,
+        })
+        .await?
+        .into_inner();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    assert_eq!(quote.amount, 32);
+    assert_eq!(quote.state, "EXPIRED");
+    assert!(quote.expiry < now);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::{Result, anyhow};
+use db_node::StorageConn;
+use node_client::{GetKeysetsRequest, RotateKeysetsRequest, SetDisabledRequest};
+use node_tests::{
+    init_admin_client, init_keyset_client, init_node_client, init_storage, operations,
+    run_node_command,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Runs `check-audit-log` until the sealer has chained every committed event
+async fn sealed_audit_log_report() -> Result<Value> {
+    let timeout = Instant::now() + Duration::from_secs(10);
+
+    loop {
+        let (valid, report) = run_node_command("check-audit-log")?;
+        assert!(valid, "the audit chain is broken: {report}");
+        if report["unsealed_events"] == 0 {
+            return Ok(report);
+        }
+        if Instant::now() > timeout {
+            return Err(anyhow!("timeout waiting for the sealer: {report}"));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+// Each operation records one event, chained by the sealer:
+// - mint, swap then melt a proof
+// - rotate the keysets, one event per unit
+// - change a setting
+// - check the chain still verifies and holds those events, in that order
+#[tokio::test]
+async fn operations_are_chained_in_the_audit_log() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let report = sealed_audit_log_report().await?;
+    let first_seq = report["verified_events"]
+        .as_u64()
+        .ok_or(anyhow!("no verified_events in {report}"))?;
+
+    let proof = operations::mint(&mut client, 32).await?;
+    let proof = operations::swap(&mut client, proof).await?;
+    operations::melt(&mut client, proof).await?;
+
+    let n_units = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .filter(|ks| ks.active)
+        .map(|ks| ks.unit)
+        .collect::<HashSet<_>>()
+        .len();
+    init_keyset_client()
+        .await?
+        .rotate_keysets(RotateKeysetsRequest { final_expiry: None })
+        .await?;
+
+    // Melting is enabled in `mock.toml`, enabling it again is still recorded
+    init_admin_client()
+        .await?
+        .set_melt_disabled(SetDisabledRequest { disabled: false })
+        .await?;
+
+    let report = sealed_audit_log_report().await?;
+    let events = init_storage()
+        .await?
+        .acquire()
+        .await?
+        .get_sealed_audit_events(first_seq, 1000)
+        .await?;
+    let mut expected_kinds = vec!["mint", "swap", "melt"];
+    expected_kinds.extend(std::iter::repeat_n("keyset_rotation", n_units));
+    expected_kinds.push("settings.melt_disabled");
+    assert_eq!(
+        events.iter().map(|e| e.kind.as_str()).collect::<Vec<_>>(),
+        expected_kinds
+    );
+    assert_eq!(
+        report["verified_events"],
+        first_seq + expected_kinds.len() as u64
+    );
+
+    Ok(())
+}
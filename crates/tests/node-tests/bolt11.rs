@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use lightning_liquidity_source::{FakeNode, LightningBackend};
+use node_client::{
+    BlindedMessage, GetKeysRequest, GetKeysetsRequest, MeltQuoteRequest, MeltQuoteState,
+    MeltRequest, MintQuoteRequest, MintQuoteState, MintRequest, Proof,
+};
+use node_tests::init_node_client;
+use nuts::Amount;
+use nuts::dhke::{blind_message, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut01::PublicKey;
+use starknet_types::Unit;
+
+// Mint some sats, then melt them by paying a bolt11 invoice.
+// Under the `mock` feature, the node pays and settles invoices with an in-process fake Lightning node,
+// reserving 2 sats of routing fee that it doesn't spend.
+#[tokio::test]
+async fn mint_and_melt_sats() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let invoice_amount = Amount::from_i64_repr(8);
+    let fee_reserve = Amount::from_i64_repr(2);
+    let amounts = [invoice_amount, fee_reserve];
+
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "bolt11".to_string(),
+            amount: (invoice_amount + fee_reserve).into(),
+            unit: Unit::SAT.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+    assert!(mint_quote_response.request.starts_with("lnbcrt"));
+    assert_eq!(
+        MintQuoteState::try_from(mint_quote_response.state)?,
+        MintQuoteState::MnqsPaid
+    );
+
+    let active_keyset = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == Unit::SAT.as_str())
+        .unwrap();
+    let keys = client
+        .keys(GetKeysRequest {
+            keyset_id: Some(active_keyset.id.clone()),
+        })
+        .await?
+        .into_inner()
+        .keysets
+        .remove(0)
+        .keys;
+    let node_pubkey_for_amount = |amount: Amount| -> Result<PublicKey> {
+        let key = keys
+            .iter()
+            .find(|key| Amount::from(key.amount) == amount)
+            .unwrap();
+
+        Ok(PublicKey::from_hex(&key.pubkey)?)
+    };
+
+    let secrets = amounts.map(|_| Secret::generate());
+    let blinded_messages = secrets
+        .iter()
+        .map(|secret| blind_message(secret.as_bytes(), None))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mint_response = client
+        .mint(MintRequest {
+            method: "bolt11".to_string(),
+            quote: mint_quote_response.quote,
+            outputs: amounts
+                .iter()
+                .zip(&blinded_messages)
+                .map(|(amount, (blinded_secret, _))| BlindedMessage {
+                    amount: (*amount).into(),
+                    keyset_id: active_keyset.id.clone(),
+                    blinded_secret: blinded_secret.to_bytes().to_vec(),
+                })
+                .collect(),
+        })
+        .await?
+        .into_inner();
+    let mut proofs = Vec::with_capacity(amounts.len());
+    for (((amount, secret), (_, r)), signature) in amounts
+        .iter()
+        .zip(&secrets)
+        .zip(&blinded_messages)
+        .zip(&mint_response.signatures)
+    {
+        let blind_signature = PublicKey::from_slice(&signature.blind_signature)?;
+        let unblinded_signature =
+            unblind_message(&blind_signature, r, &node_pubkey_for_amount(*amount)?)?;
+        proofs.push(Proof {
+            amount: (*amount).into(),
+            keyset_id: active_keyset.id.clone(),
+            secret: secret.to_string(),
+            unblind_signature: unblinded_signature.to_bytes().to_vec(),
+        });
+    }
+
+    // An invoice of 8 sats, from a node other than the one of the mint
+    let invoice = FakeNode::default()
+        .create_invoice(8_000, "melt".to_string(), Duration::from_secs(600))
+        .await?;
+    let melt_quote_response = client
+        .melt_quote(MeltQuoteRequest {
+            method: "bolt11".to_string(),
+            unit: Unit::SAT.to_string(),
+            request: invoice.bolt11,
+        })
+        .await?
+        .into_inner();
+    assert_eq!(melt_quote_response.amount, u64::from(invoice_amount));
+    assert_eq!(melt_quote_response.fee_reserve, u64::from(fee_reserve));
+
+    // A blank output for the change, its amount is set by the node
+    let (blank_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+    let melt_response = client
+        .melt(MeltRequest {
+            method: "bolt11".to_string(),
+            quote: melt_quote_response.quote,
+            inputs: proofs,
+            outputs: vec![BlindedMessage {
+                amount: 1,
+                keyset_id: active_keyset.id.clone(),
+                blinded_secret: blank_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await?
+        .into_inner();
+    assert_eq!(
+        MeltQuoteState::try_from(melt_response.state)?,
+        MeltQuoteState::MlqsPaid
+    );
+    // No routing fee was spent, the whole reserve is returned
+    assert_eq!(melt_response.change.len(), 1);
+    assert_eq!(melt_response.change[0].amount, u64::from(fee_reserve));
+
+    Ok(())
+}
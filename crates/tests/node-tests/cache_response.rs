@@ -12,6 +12,10 @@ use nuts::nut01::PublicKey;
 use starknet_liquidity_source::MeltPaymentRequest;
 use starknet_types::{StarknetU256, Unit};
 use starknet_types_core::felt::Felt;
+use std::time::Duration;
+
+/// Must match `node.ttl.response_cache` in `config/mock.toml`
+const RESPONSE_CACHE_TTL: Duration = Duration::from_secs(5);
 
 // This tests check that the route that we want to cache are indeed cached.
 //
@@ -46,7 +50,7 @@ async fn works() -> Result<()> {
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: amount.into(),
-        unit: Unit::MilliStrk.to_string(),
+        unit: Unit::MILLI_STRK.to_string(),
         description: None,
     };
     let original_mint_quote_response = client
@@ -68,7 +72,7 @@ async fn works() -> Result<()> {
         .keysets;
     let active_keyset = keysets
         .iter()
-        .find(|ks| ks.active && ks.unit == Unit::MilliStrk.as_str())
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
         .unwrap();
 
     let secret = Secret::generate();
@@ -173,12 +177,12 @@ async fn works() -> Result<()> {
 
     let melt_quote_request = MeltQuoteRequest {
         method: "starknet".to_string(),
-        unit: Unit::MilliStrk.to_string(),
+        unit: Unit::MILLI_STRK.to_string(),
         request: serde_json::to_string(&MeltPaymentRequest {
             payee: Felt::from_hex_unchecked(
                 "0x064b48806902a367c8598f4f95c305e8c1a1acba5f082d294a43793113115691",
             ),
-            asset: starknet_types::Asset::Strk,
+            asset: starknet_types::Asset::STRK,
             amount: StarknetU256 {
                 low: Felt::from_dec_str("32000000000000000").unwrap(),
                 high: Felt::from(0),
@@ -194,6 +198,7 @@ async fn works() -> Result<()> {
         quote: melt_quote_response.quote,
         method: "starknet".to_string(),
         inputs: vec![proof],
+        outputs: vec![],
     };
     let original_melt_response = client.melt(melt_request.clone()).await?.into_inner();
     let cached_melt_response = client.melt(melt_request.clone()).await?.into_inner();
@@ -210,3 +215,54 @@ async fn works() -> Result<()> {
 
     Ok(())
 }
+
+// Check that a cached response is not served anymore once its ttl is elapsed:
+// - mint and check the response is cached
+// - wait for the ttl to elapse
+// - call it again and check the response is an error, the quote being already issued
+#[tokio::test]
+async fn cached_response_expires() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let amount = Amount::from_i64_repr(16);
+
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount: amount.into(),
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+    let keysets = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets;
+    let active_keyset = keysets
+        .iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap();
+
+    let secret = Secret::generate();
+    let (blinded_secret, _r) = blind_message(secret.as_bytes(), None)?;
+    let mint_request = MintRequest {
+        method: "starknet".to_string(),
+        quote: mint_quote_response.quote,
+        outputs: vec![BlindedMessage {
+            amount: amount.into(),
+            keyset_id: active_keyset.id.clone(),
+            blinded_secret: blinded_secret.to_bytes().to_vec(),
+        }],
+    };
+    let original_mint_response = client.mint(mint_request.clone()).await?.into_inner();
+    let cached_mint_response = client.mint(mint_request.clone()).await?.into_inner();
+    assert_eq!(original_mint_response, cached_mint_response);
+
+    tokio::time::sleep(RESPONSE_CACHE_TTL + Duration::from_secs(1)).await;
+
+    let post_expiry_mint_response = client.mint(mint_request).await;
+    assert!(post_expiry_mint_response.is_err());
+
+    Ok(())
+}
@@ -31,7 +31,7 @@ async fn test_multiple_tokens() -> Result<()> {
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: total_amount.into(),
-        unit: Unit::MilliStrk.to_string(),
+        unit: Unit::MILLI_STRK.to_string(),
         description: None,
     };
     let mint_quote_response = client
@@ -47,7 +47,7 @@ async fn test_multiple_tokens() -> Result<()> {
         .keysets;
     let active_keyset = keysets
         .iter()
-        .find(|ks| ks.active && ks.unit == Unit::MilliStrk.as_str())
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
         .unwrap();
 
     // Generate secrets and blind messages for each amount
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use node_client::{
+    BlindedMessage, ExchangeEstimateRequest, ExchangeRequest, GetKeysRequest, GetKeysetsRequest,
+    MintQuoteRequest, MintRequest, NodeClient, Proof,
+};
+
+use node_tests::init_node_client;
+use nuts::dhke::{blind_message, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut01::PublicKey;
+use starknet_types::Unit;
+use tonic::{Code, transport::Channel};
+
+async fn active_keyset_id(client: &mut NodeClient<Channel>, unit: Unit) -> Result<Vec<u8>> {
+    let keyset = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == unit.as_str())
+        .unwrap();
+
+    Ok(keyset.id)
+}
+
+async fn mint_proof(client: &mut NodeClient<Channel>, amount: u64) -> Result<Proof> {
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount,
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+    let keyset_id = active_keyset_id(client, Unit::MILLI_STRK).await?;
+    let secret = Secret::generate();
+    let (blinded_secret, r) = blind_message(secret.as_bytes(), None)?;
+    let signature = client
+        .mint(MintRequest {
+            method: "starknet".to_string(),
+            quote: mint_quote_response.quote,
+            outputs: vec![BlindedMessage {
+                amount,
+                keyset_id: keyset_id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await?
+        .into_inner()
+        .signatures
+        .remove(0);
+
+    let node_pubkey_for_amount = PublicKey::from_hex(
+        &client
+            .keys(GetKeysRequest {
+                keyset_id: Some(keyset_id.clone()),
+            })
+            .await?
+            .into_inner()
+            .keysets
+            .first()
+            .unwrap()
+            .keys
+            .iter()
+            .find(|key| key.amount == amount)
+            .unwrap()
+            .pubkey,
+    )?;
+    let blind_signature = PublicKey::from_slice(&signature.blind_signature)?;
+    let unblinded_signature = unblind_message(&blind_signature, &r, &node_pubkey_for_amount)?;
+
+    Ok(Proof {
+        amount,
+        keyset_id,
+        secret: secret.to_string(),
+        unblind_signature: unblinded_signature.to_bytes().to_vec(),
+    })
+}
+
+#[tokio::test]
+async fn exchange_millistrk_for_gwei() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    // rate 60 minus a 1% spread, then a fee of 1
+    let estimate = client
+        .exchange_estimate(ExchangeEstimateRequest {
+            input_unit: Unit::MILLI_STRK.to_string(),
+            output_unit: Unit::GWEI.to_string(),
+            amount: 32,
+        })
+        .await?
+        .into_inner();
+    assert_eq!(estimate.rate, "59.4");
+    assert_eq!(estimate.fee, 1);
+    assert_eq!(estimate.amount, 1899);
+
+    let input = mint_proof(&mut client, 32).await?;
+    let gwei_keyset_id = active_keyset_id(&mut client, Unit::GWEI).await?;
+    let output = |amount: u64| -> Result<BlindedMessage> {
+        let (blinded_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+        Ok(BlindedMessage {
+            amount,
+            keyset_id: gwei_keyset_id.clone(),
+            blinded_secret: blinded_secret.to_bytes().to_vec(),
+        })
+    };
+
+    // Worth more than the estimate
+    let status = client
+        .exchange(ExchangeRequest {
+            inputs: vec![input.clone()],
+            outputs: vec![output(2048)?],
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // The failed attempt left the input unspent
+    let exchange_request = ExchangeRequest {
+        inputs: vec![input.clone()],
+        outputs: vec![output(1024)?, output(512)?, output(256)?],
+    };
+    let exchange_response = client
+        .exchange(exchange_request.clone())
+        .await?
+        .into_inner();
+    assert_eq!(exchange_response.rate, "59.4");
+    assert_eq!(
+        exchange_response
+            .signatures
+            .iter()
+            .map(|s| s.amount)
+            .collect::<Vec<_>>(),
+        vec![1024, 512, 256]
+    );
+    assert!(
+        exchange_response
+            .signatures
+            .iter()
+            .all(|s| s.keyset_id == gwei_keyset_id)
+    );
+
+    // Cached
+    let cached_exchange_response = client.exchange(exchange_request).await?.into_inner();
+    assert_eq!(exchange_response, cached_exchange_response);
+
+    // Now spent
+    let status = client
+        .exchange(ExchangeRequest {
+            inputs: vec![input],
+            outputs: vec![output(1024)?],
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exchange_unconfigured_pair_is_rejected() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let status = client
+        .exchange_estimate(ExchangeEstimateRequest {
+            input_unit: Unit::GWEI.to_string(),
+            output_unit: Unit::MILLI_STRK.to_string(),
+            amount: 1000,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{Result, anyhow, bail};
+use node_client::{GetNodeInfoRequest, NodeInfoResponse};
+use prost::Message;
+
+const GRPC_WEB_CONTENT_TYPE: &str = "application/grpc-web+proto";
+/// Flag of the frame carrying the trailers, the others carry messages
+const TRAILERS_FLAG: u8 = 0x80;
+
+fn node_url() -> Result<String> {
+    let grpc_port = std::env::var("GRPC_PORT")?;
+    Ok(format!("http://[::0]:{}", grpc_port))
+}
+
+/// Prefix the message with its gRPC-Web frame header
+fn frame(message: impl Message) -> Vec<u8> {
+    let message = message.encode_to_vec();
+    let mut body = Vec::with_capacity(5 + message.len());
+    body.push(0);
+    body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    body.extend_from_slice(&message);
+
+    body
+}
+
+/// Split a gRPC-Web response body into its (flag, payload) frames
+fn unframe(mut body: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut frames = Vec::new();
+    while !body.is_empty() {
+        if body.len() < 5 {
+            bail!("truncated frame header");
+        }
+        let flag = body[0];
+        let len = u32::from_be_bytes(body[1..5].try_into()?) as usize;
+        if body.len() < 5 + len {
+            bail!("truncated frame payload");
+        }
+        frames.push((flag, &body[5..5 + len]));
+        body = &body[5 + len..];
+    }
+
+    Ok(frames)
+}
+
+#[tokio::test]
+async fn get_node_info() -> Result<()> {
+    let client = reqwest::Client::builder().http1_only().build()?;
+    let res = client
+        .post(format!("{}/node.Node/GetNodeInfo", node_url()?))
+        .header("content-type", GRPC_WEB_CONTENT_TYPE)
+        .header("x-grpc-web", "1")
+        .body(frame(GetNodeInfoRequest {}))
+        .send()
+        .await?;
+
+    assert!(res.status().is_success());
+    assert_eq!(
+        res.headers()
+            .get("content-type")
+            .ok_or(anyhow!("missing content-type"))?,
+        GRPC_WEB_CONTENT_TYPE
+    );
+
+    let body = res.bytes().await?;
+    let frames = unframe(&body)?;
+    let [(0, message), (TRAILERS_FLAG, trailers)] = frames.as_slice() else {
+        bail!("expected a message and a trailers frame, got {:?}", frames);
+    };
+
+    let node_info = NodeInfoResponse::decode(*message)?;
+    let node_info: serde_json::Value = serde_json::from_str(&node_info.info)?;
+    assert!(node_info.get("nuts").is_some());
+
+    let trailers = std::str::from_utf8(trailers)?;
+    assert!(
+        trailers
+            .lines()
+            .any(|line| line.trim().eq_ignore_ascii_case("grpc-status:0")),
+        "unexpected trailers: {trailers}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cors_preflight() -> Result<()> {
+    let client = reqwest::Client::builder().http1_only().build()?;
+    let res = client
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/node.Node/GetNodeInfo", node_url()?),
+        )
+        .header("origin", "https://wallet.example")
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type,x-grpc-web")
+        .send()
+        .await?;
+
+    assert!(res.status().is_success());
+    assert_eq!(
+        res.headers()
+            .get("access-control-allow-origin")
+            .ok_or(anyhow!("missing access-control-allow-origin"))?,
+        "*"
+    );
+
+    Ok(())
+}
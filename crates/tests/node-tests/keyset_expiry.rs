@@ -0,0 +1,120 @@
+use anyhow::Result;
+use node_client::{
+    BlindedMessage, GetKeysRequest, GetKeysetsRequest, MintQuoteRequest, MintRequest, Proof,
+    RotateKeysetsRequest, SwapRequest,
+};
+use node_tests::{init_keyset_client, init_node_client};
+use nuts::Amount;
+use nuts::dhke::{blind_message, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut01::PublicKey;
+use starknet_types::Unit;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::Code;
+
+#[tokio::test]
+async fn expired_keyset_inputs_are_refused() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let mut keyset_client = init_keyset_client().await?;
+    let amount = Amount::from_i64_repr(8);
+
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount: amount.into(),
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+
+    let keysets = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets;
+    let old_keyset = keysets
+        .iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap()
+        .clone();
+    assert_eq!(old_keyset.final_expiry, None);
+
+    // Mint a token with the soon to be expired keyset
+    let secret = Secret::generate();
+    let (blinded_secret, r) = blind_message(secret.as_bytes(), None)?;
+    let mint_response = client
+        .mint(MintRequest {
+            method: "starknet".to_string(),
+            quote: mint_quote_response.quote,
+            outputs: vec![BlindedMessage {
+                amount: amount.into(),
+                keyset_id: old_keyset.id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await?
+        .into_inner();
+    let node_pubkey = PublicKey::from_hex(
+        &client
+            .keys(GetKeysRequest {
+                keyset_id: Some(old_keyset.id.clone()),
+            })
+            .await?
+            .into_inner()
+            .keysets[0]
+            .keys
+            .iter()
+            .find(|key| Amount::from(key.amount) == amount)
+            .unwrap()
+            .pubkey,
+    )?;
+    let unblinded_signature = unblind_message(
+        &PublicKey::from_slice(&mint_response.signatures[0].blind_signature)?,
+        &r,
+        &node_pubkey,
+    )?;
+
+    // Retire the keyset right away
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    keyset_client
+        .rotate_keysets(RotateKeysetsRequest {
+            final_expiry: Some(now),
+        })
+        .await?;
+
+    let keysets = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets;
+    let retired_keyset = keysets.iter().find(|ks| ks.id == old_keyset.id).unwrap();
+    assert!(!retired_keyset.active);
+    assert_eq!(retired_keyset.final_expiry, Some(now));
+    let new_keyset = keysets
+        .iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap();
+
+    // Its proofs cannot be swapped anymore
+    let (blinded_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+    let res = client
+        .swap(SwapRequest {
+            inputs: vec![Proof {
+                amount: amount.into(),
+                keyset_id: old_keyset.id.clone(),
+                secret: secret.to_string(),
+                unblind_signature: unblinded_signature.to_bytes().to_vec(),
+            }],
+            outputs: vec![BlindedMessage {
+                amount: amount.into(),
+                keyset_id: new_keyset.id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await;
+
+    assert!(matches!(res, Err(status) if status.code() == Code::InvalidArgument));
+
+    Ok(())
+}
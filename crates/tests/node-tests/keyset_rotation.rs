@@ -28,7 +28,7 @@ async fn ok() -> Result<()> {
 
     // trigger rotate keysets
     let _ = keyset_client
-        .rotate_keysets(RotateKeysetsRequest {})
+        .rotate_keysets(RotateKeysetsRequest { final_expiry: None })
         .await?;
 
     // Check that old keysets are deactivated
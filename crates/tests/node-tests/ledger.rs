@@ -0,0 +1,18 @@
+use anyhow::Result;
+use node_tests::{init_node_client, operations, run_node_command};
+
+// Mint, swap then melt a proof, and check the ledger still balances
+#[tokio::test]
+async fn ledger_is_consistent_after_operations() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let proof = operations::mint(&mut client, 32).await?;
+    let proof = operations::swap(&mut client, proof).await?;
+    operations::melt(&mut client, proof).await?;
+
+    let (consistent, report) = run_node_command("check-ledger")?;
+    assert!(consistent, "the ledger is inconsistent: {report}");
+    assert_eq!(report["consistent"], true);
+
+    Ok(())
+}
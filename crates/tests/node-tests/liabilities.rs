@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use node_client::{
+    BlindedMessage, GetKeysRequest, GetKeysetsRequest, LiabilitiesRequest, MintQuoteRequest,
+    MintRequest, Proof, SwapRequest, inclusion_proof_from_path,
+};
+
+use node_tests::init_node_client;
+use nuts::Amount;
+use nuts::dhke::{blind_message, hash_to_curve, unblind_message};
+use nuts::liabilities::SumNode;
+use nuts::nut00::secret::Secret;
+use nuts::nut01::PublicKey;
+use starknet_types::Unit;
+
+#[tokio::test]
+async fn signatures_and_spent_proofs_are_included() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let amounts = [Amount::from_i64_repr(4), Amount::from_i64_repr(8)];
+
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount: 12,
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+
+    let active_keyset = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap();
+
+    let mut secrets = Vec::new();
+    let mut rs = Vec::new();
+    let mut blinded_secrets = Vec::new();
+    let mut outputs = Vec::new();
+    for amount in &amounts {
+        let secret = Secret::generate();
+        let (blinded_secret, r) = blind_message(secret.as_bytes(), None)?;
+
+        secrets.push(secret);
+        rs.push(r);
+        blinded_secrets.push(blinded_secret);
+        outputs.push(BlindedMessage {
+            amount: (*amount).into(),
+            keyset_id: active_keyset.id.clone(),
+            blinded_secret: blinded_secret.to_bytes().to_vec(),
+        });
+    }
+
+    let mint_response = client
+        .mint(MintRequest {
+            method: "starknet".to_string(),
+            quote: mint_quote_response.quote,
+            outputs,
+        })
+        .await?
+        .into_inner();
+    let blind_signatures = mint_response
+        .signatures
+        .iter()
+        .map(|s| PublicKey::from_slice(&s.blind_signature))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Spend the first token
+    let node_keys = client
+        .keys(GetKeysRequest {
+            keyset_id: Some(active_keyset.id.clone()),
+        })
+        .await?
+        .into_inner()
+        .keysets
+        .remove(0)
+        .keys;
+    let node_pubkey = PublicKey::from_hex(
+        &node_keys
+            .iter()
+            .find(|key| Amount::from(key.amount) == amounts[0])
+            .unwrap()
+            .pubkey,
+    )?;
+    let unblinded_signature = unblind_message(&blind_signatures[0], &rs[0], &node_pubkey)?;
+    let (new_blinded_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+    client
+        .swap(SwapRequest {
+            inputs: vec![Proof {
+                amount: amounts[0].into(),
+                keyset_id: active_keyset.id.clone(),
+                secret: secrets[0].to_string(),
+                unblind_signature: unblinded_signature.to_bytes().to_vec(),
+            }],
+            outputs: vec![BlindedMessage {
+                amount: amounts[0].into(),
+                keyset_id: active_keyset.id.clone(),
+                blinded_secret: new_blinded_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await?;
+    let spent_y = hash_to_curve(secrets[0].as_bytes())?;
+
+    // Wait for the liabilities snapshot to include the operations above
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // A blinded secret the node never signed
+    let (unknown_blinded_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+
+    let response = client
+        .liabilities(LiabilitiesRequest {
+            blinded_secrets: vec![
+                blinded_secrets[0].to_bytes().to_vec(),
+                blinded_secrets[1].to_bytes().to_vec(),
+                unknown_blinded_secret.to_bytes().to_vec(),
+            ],
+            ys: vec![spent_y.to_bytes().to_vec()],
+        })
+        .await?
+        .into_inner();
+
+    let keyset = response
+        .keysets
+        .iter()
+        .find(|ks| ks.keyset_id == active_keyset.id)
+        .unwrap();
+    let issued_root: SumNode = keyset.issued_root.clone().unwrap().try_into()?;
+    let burned_root: SumNode = keyset.burned_root.clone().unwrap().try_into()?;
+    assert!(issued_root.sum >= Amount::from_i64_repr(16));
+    assert!(burned_root.sum >= amounts[0]);
+
+    assert_eq!(response.issued_proofs.len(), 3);
+    for i in 0..2 {
+        let proof = &response.issued_proofs[i];
+        assert!(proof.found);
+        assert_eq!(proof.keyset_id, active_keyset.id);
+        let leaf = SumNode::issued_leaf(amounts[i], &blinded_secrets[i], &blind_signatures[i]);
+        assert!(inclusion_proof_from_path(proof.path.clone())?.verify(&leaf, &issued_root));
+    }
+    assert!(!response.issued_proofs[2].found);
+
+    assert_eq!(response.burned_proofs.len(), 1);
+    let proof = &response.burned_proofs[0];
+    assert!(proof.found);
+    let leaf = SumNode::burned_leaf(amounts[0], &spent_y);
+    assert!(inclusion_proof_from_path(proof.path.clone())?.verify(&leaf, &burned_root));
+    // The proof doesn't verify for another amount
+    let leaf = SumNode::burned_leaf(amounts[1], &spent_y);
+    assert!(!inclusion_proof_from_path(proof.path.clone())?.verify(&leaf, &burned_root));
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use node_client::{BlindedMessage, GetKeysetsRequest, Proof, SwapRequest};
+use node_tests::init_node_client;
+use nuts::dhke::{blind_message, hash_to_curve};
+use nuts::nut00::secret::Secret;
+use starknet_types::Unit;
+use tonic::Code;
+
+/// Default value of `node.limits.max_outputs`
+const MAX_OUTPUTS: usize = 64;
+
+#[tokio::test]
+async fn swap_with_too_many_outputs_is_rejected() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let keysets = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets;
+    let active_keyset = keysets
+        .iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap();
+
+    // The counts are checked before the proofs, this one doesn't need to be valid
+    let secret = Secret::generate();
+    let input = Proof {
+        amount: 1,
+        keyset_id: active_keyset.id.clone(),
+        secret: secret.to_string(),
+        unblind_signature: hash_to_curve(secret.as_bytes())?.to_bytes().to_vec(),
+    };
+    let outputs = (0..=MAX_OUTPUTS)
+        .map(|_| -> Result<BlindedMessage> {
+            let (blinded_secret, _r) = blind_message(Secret::generate().as_bytes(), None)?;
+            Ok(BlindedMessage {
+                amount: 1,
+                keyset_id: active_keyset.id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let res = client
+        .swap(SwapRequest {
+            inputs: vec![input],
+            outputs,
+        })
+        .await;
+
+    assert!(matches!(res, Err(status) if status.code() == Code::ResourceExhausted));
+
+    Ok(())
+}
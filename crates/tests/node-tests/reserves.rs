@@ -0,0 +1,82 @@
+use anyhow::Result;
+use node_client::{
+    BlindedMessage, GetKeysetsRequest, MintQuoteRequest, MintRequest, NodeClient, ReservesRequest,
+};
+
+use node_tests::init_node_client;
+use nuts::dhke::blind_message;
+use nuts::nut00::secret::Secret;
+use starknet_types::Unit;
+use tonic::transport::Channel;
+
+#[tokio::test]
+async fn minted_ecash_is_outstanding_and_backed() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let outstanding_before = unit_ecash(&mut client, Unit::MILLI_STRK).await?;
+
+    let mint_quote_response = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount: 32,
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner();
+    let active_keyset = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .unwrap();
+    let (blinded_secret, _) = blind_message(Secret::generate().as_bytes(), None)?;
+    client
+        .mint(MintRequest {
+            method: "starknet".to_string(),
+            quote: mint_quote_response.quote,
+            outputs: vec![BlindedMessage {
+                amount: 32,
+                keyset_id: active_keyset.id,
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            }],
+        })
+        .await?;
+
+    let reserves = client
+        .reserves(ReservesRequest {})
+        .await?
+        .into_inner()
+        .assets
+        .into_iter()
+        .find(|a| a.asset == Unit::MILLI_STRK.asset().to_string())
+        .unwrap();
+
+    assert_eq!(reserves.balance.len(), 32);
+    assert_eq!(reserves.outstanding_ecash.len(), 32);
+    // Other tests may be minting concurrently
+    let outstanding_after = unit_ecash(&mut client, Unit::MILLI_STRK).await?;
+    assert!(outstanding_after >= outstanding_before + 32);
+    assert!(reserves.balance >= reserves.outstanding_ecash);
+    assert!(reserves.ratio.unwrap() >= 1.0);
+
+    Ok(())
+}
+
+/// The outstanding ecash of `unit`, as reported along the reserves of its asset
+async fn unit_ecash(client: &mut NodeClient<Channel>, unit: Unit) -> Result<u64> {
+    let ecash = client
+        .reserves(ReservesRequest {})
+        .await?
+        .into_inner()
+        .assets
+        .into_iter()
+        .flat_map(|a| a.units)
+        .find(|u| u.unit == unit.as_str())
+        .unwrap()
+        .outstanding_ecash;
+
+    Ok(ecash)
+}
@@ -0,0 +1,445 @@
+use anyhow::{Result, anyhow};
+use node_client::{
+    CheckStateRequest, ExpireQuoteRequest, GetKeysRequest, GetKeysetsRequest, GetNodeInfoRequest,
+    MeltQuoteStateRequest, QuoteKind, QuoteStateRequest, RestoreRequest,
+};
+use node_tests::{init_admin_client, init_node_client};
+use nuts::Amount;
+use nuts::dhke::{blind_message, hash_to_curve, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut00::{BlindSignature, BlindedMessage, CashuError, ErrorCode, Proof};
+use nuts::nut01::{PublicKey, SecretKey};
+use nuts::nut02::{KeysResponse, KeysetId, KeysetResponse};
+use nuts::nut03::SwapResponse;
+use nuts::nut04::{MintQuoteResponse, MintQuoteState, MintResponse};
+use nuts::nut07::{CheckStateResponse, ProofState};
+use nuts::nut09::RestoreResponse;
+use reqwest::StatusCode;
+use serde_json::json;
+use starknet_types::Unit;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::Code;
+
+/// Valid, but not issued by the node
+const UNKNOWN_QUOTE_ID: &str = "00000000-0000-4000-8000-000000000000";
+
+fn rest_url(path: &str) -> Result<String> {
+    let rest_port = std::env::var("REST_PORT")?;
+    Ok(format!("http://[::0]:{}{}", rest_port, path))
+}
+
+async fn get(path: &str) -> Result<reqwest::Response> {
+    Ok(reqwest::get(rest_url(path)?).await?)
+}
+
+async fn post(path: &str, body: serde_json::Value) -> Result<reqwest::Response> {
+    Ok(reqwest::Client::new()
+        .post(rest_url(path)?)
+        .json(&body)
+        .send()
+        .await?)
+}
+
+/// Check the status of a rejected request and return its Cashu error body
+async fn cashu_error(res: reqwest::Response, status: StatusCode) -> Result<CashuError> {
+    assert_eq!(res.status(), status);
+    Ok(res.json().await?)
+}
+
+async fn active_keyset() -> Result<KeysetId> {
+    let keysets: KeysetResponse<Unit> = get("/v1/keysets").await?.json().await?;
+    keysets
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK)
+        .map(|ks| ks.id)
+        .ok_or(anyhow!("no active keyset"))
+}
+
+fn blinded_messages(
+    keyset_id: KeysetId,
+    amounts: &[u64],
+) -> Result<(Vec<Secret>, Vec<SecretKey>, Vec<BlindedMessage>)> {
+    let mut secrets = Vec::new();
+    let mut rs = Vec::new();
+    let mut outputs = Vec::new();
+    for amount in amounts {
+        let secret = Secret::generate();
+        let (blinded_secret, r) = blind_message(secret.as_bytes(), None)?;
+        secrets.push(secret);
+        rs.push(r);
+        outputs.push(BlindedMessage {
+            amount: Amount::from(*amount),
+            keyset_id,
+            blinded_secret,
+        });
+    }
+
+    Ok((secrets, rs, outputs))
+}
+
+fn to_grpc_output(output: &BlindedMessage) -> node_client::BlindedMessage {
+    node_client::BlindedMessage {
+        amount: output.amount.into(),
+        keyset_id: output.keyset_id.to_bytes().to_vec(),
+        blinded_secret: output.blinded_secret.to_bytes().to_vec(),
+    }
+}
+
+fn from_grpc_signature(signature: &node_client::BlindSignature) -> Result<BlindSignature> {
+    Ok(BlindSignature {
+        amount: Amount::from(signature.amount),
+        keyset_id: KeysetId::from_bytes(&signature.keyset_id)?,
+        c: PublicKey::from_slice(&signature.blind_signature)?,
+    })
+}
+
+#[tokio::test]
+async fn info_matches_grpc() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let res = get("/v1/info").await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_info: serde_json::Value = res.json().await?;
+
+    let grpc_info = client
+        .get_node_info(GetNodeInfoRequest {})
+        .await?
+        .into_inner();
+    let grpc_info: serde_json::Value = serde_json::from_str(&grpc_info.info)?;
+
+    assert_eq!(rest_info, grpc_info);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn keysets_match_grpc() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let res = get("/v1/keysets").await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_keysets: KeysetResponse<Unit> = res.json().await?;
+
+    let grpc_keysets = client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets;
+
+    assert_eq!(rest_keysets.keysets.len(), grpc_keysets.len());
+    for grpc_keyset in grpc_keysets {
+        let id = KeysetId::from_bytes(&grpc_keyset.id)?;
+        let rest_keyset = rest_keysets
+            .keysets
+            .iter()
+            .find(|ks| ks.id == id)
+            .ok_or(anyhow!("keyset {} missing from the REST response", id))?;
+        assert_eq!(rest_keyset.unit.as_str(), grpc_keyset.unit);
+        assert_eq!(rest_keyset.active, grpc_keyset.active);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn keys_match_grpc() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let keyset_id = active_keyset().await?;
+
+    let res = get(&format!("/v1/keys/{}", keyset_id)).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_keys: KeysResponse<Unit> = res.json().await?;
+    let [rest_keyset] = rest_keys.keysets.as_slice() else {
+        panic!("expected a single keyset, got {:?}", rest_keys.keysets);
+    };
+    assert_eq!(rest_keyset.id, keyset_id);
+
+    let grpc_keys = client
+        .keys(GetKeysRequest {
+            keyset_id: Some(keyset_id.to_bytes().to_vec()),
+        })
+        .await?
+        .into_inner()
+        .keysets;
+    let [grpc_keyset] = grpc_keys.as_slice() else {
+        panic!("expected a single keyset, got {:?}", grpc_keys);
+    };
+
+    assert_eq!(rest_keyset.unit.as_str(), grpc_keyset.unit);
+    assert_eq!(rest_keyset.keys.iter().count(), grpc_keyset.keys.len());
+    for key in &grpc_keyset.keys {
+        assert_eq!(
+            rest_keyset.keys.amount_key(Amount::from(key.amount)),
+            Some(PublicKey::from_hex(&key.pubkey)?)
+        );
+    }
+
+    // The active keysets only
+    let all_keys: KeysResponse<Unit> = get("/v1/keys").await?.json().await?;
+    assert!(all_keys.keysets.contains(rest_keyset));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_keyset_is_not_found() -> Result<()> {
+    let res = get("/v1/keys/00ffffffffffffff").await?;
+    let error = cashu_error(res, StatusCode::NOT_FOUND).await?;
+    assert_eq!(error.code(), u16::from(ErrorCode::KeysetNotFound));
+
+    let res = get("/v1/keys/not-a-keyset-id").await?;
+    let error = cashu_error(res, StatusCode::BAD_REQUEST).await?;
+    assert_eq!(error.code(), u16::from(ErrorCode::KeysetNotFound));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_quotes_are_not_found() -> Result<()> {
+    let mut client = init_node_client().await?;
+
+    let res = get(&format!("/v1/mint/quote/starknet/{}", UNKNOWN_QUOTE_ID)).await?;
+    cashu_error(res, StatusCode::NOT_FOUND).await?;
+    let status = client
+        .mint_quote_state(QuoteStateRequest {
+            method: "starknet".to_string(),
+            quote: UNKNOWN_QUOTE_ID.to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let res = get(&format!("/v1/melt/quote/starknet/{}", UNKNOWN_QUOTE_ID)).await?;
+    cashu_error(res, StatusCode::NOT_FOUND).await?;
+    let status = client
+        .melt_quote_state(MeltQuoteStateRequest {
+            method: "starknet".to_string(),
+            quote: UNKNOWN_QUOTE_ID.to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let (_, _, outputs) = blinded_messages(active_keyset().await?, &[8])?;
+    let res = post(
+        "/v1/mint/starknet",
+        json!({ "quote": UNKNOWN_QUOTE_ID, "outputs": outputs }),
+    )
+    .await?;
+    cashu_error(res, StatusCode::NOT_FOUND).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_requests_are_rejected() -> Result<()> {
+    let res = get(&format!("/v1/mint/quote/unknown/{}", UNKNOWN_QUOTE_ID)).await?;
+    let error = cashu_error(res, StatusCode::BAD_REQUEST).await?;
+    assert_eq!(error.code(), u16::from(ErrorCode::Unknown));
+
+    let res = get("/v1/mint/quote/starknet/not-a-uuid").await?;
+    cashu_error(res, StatusCode::BAD_REQUEST).await?;
+
+    let res = post(
+        "/v1/mint/quote/starknet",
+        json!({ "amount": 32, "unit": "unknown" }),
+    )
+    .await?;
+    assert!(res.status().is_client_error());
+
+    let res = post("/v1/swap", json!({ "inputs": [], "outputs": [] })).await?;
+    cashu_error(res, StatusCode::BAD_REQUEST).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mint_swap_and_restore() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let keyset_id = active_keyset().await?;
+    let amounts = [8, 16, 32];
+
+    // MINT QUOTE
+    let res = post(
+        "/v1/mint/quote/starknet",
+        json!({ "amount": 56, "unit": Unit::MILLI_STRK.as_str() }),
+    )
+    .await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let quote: MintQuoteResponse<String> = res.json().await?;
+
+    let res = get(&format!("/v1/mint/quote/starknet/{}", quote.quote)).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_quote: MintQuoteResponse<String> = res.json().await?;
+    let grpc_quote = client
+        .mint_quote_state(QuoteStateRequest {
+            method: "starknet".to_string(),
+            quote: quote.quote.clone(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(rest_quote.quote, grpc_quote.quote);
+    assert_eq!(rest_quote.request, grpc_quote.request);
+    assert_eq!(rest_quote.expiry, grpc_quote.expiry);
+    assert_eq!(
+        node_client::MintQuoteState::from(rest_quote.state),
+        grpc_quote.state()
+    );
+
+    // MINT
+    let (secrets, rs, outputs) = blinded_messages(keyset_id, &amounts)?;
+    let res = post(
+        "/v1/mint/starknet",
+        json!({ "quote": quote.quote, "outputs": outputs }),
+    )
+    .await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let minted: MintResponse = res.json().await?;
+    assert_eq!(minted.signatures.len(), outputs.len());
+
+    // The same signatures are served over gRPC
+    let grpc_restored = client
+        .restore(RestoreRequest {
+            outputs: outputs.iter().map(to_grpc_output).collect(),
+        })
+        .await?
+        .into_inner();
+    let grpc_signatures = grpc_restored
+        .signatures
+        .iter()
+        .map(from_grpc_signature)
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(minted.signatures, grpc_signatures);
+
+    let res = get(&format!("/v1/mint/quote/starknet/{}", quote.quote)).await?;
+    let issued_quote: MintQuoteResponse<String> = res.json().await?;
+    assert_eq!(issued_quote.state, MintQuoteState::Issued);
+
+    // The quote can't be minted twice
+    let (_, _, other_outputs) = blinded_messages(keyset_id, &amounts)?;
+    let res = post(
+        "/v1/mint/starknet",
+        json!({ "quote": quote.quote, "outputs": other_outputs }),
+    )
+    .await?;
+    let error = cashu_error(res, StatusCode::BAD_REQUEST).await?;
+    assert_eq!(error.code(), u16::from(ErrorCode::TokensAlreadyIssued));
+
+    // CHECK STATE
+    let keys: KeysResponse<Unit> = get(&format!("/v1/keys/{}", keyset_id))
+        .await?
+        .json()
+        .await?;
+    let keys = &keys.keysets[0].keys;
+    let mut proofs = Vec::new();
+    for (i, signature) in minted.signatures.iter().enumerate() {
+        let node_pubkey = keys
+            .amount_key(signature.amount)
+            .ok_or(anyhow!("no key for amount {}", signature.amount))?;
+        proofs.push(Proof {
+            amount: signature.amount,
+            keyset_id,
+            secret: secrets[i].clone(),
+            c: unblind_message(&signature.c, &rs[i], &node_pubkey)?,
+        });
+    }
+    let ys = secrets
+        .iter()
+        .map(|secret| hash_to_curve(secret.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let res = post("/v1/checkstate", json!({ "Ys": ys })).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_states: CheckStateResponse = res.json().await?;
+    let grpc_states = client
+        .check_state(CheckStateRequest {
+            ys: ys.iter().map(|y| y.to_bytes().to_vec()).collect(),
+        })
+        .await?
+        .into_inner()
+        .states;
+    assert_eq!(rest_states.proof_check_states.len(), grpc_states.len());
+    for (rest_state, grpc_state) in rest_states.proof_check_states.iter().zip(&grpc_states) {
+        assert_eq!(rest_state.y.to_bytes().to_vec(), grpc_state.y);
+        assert_eq!(rest_state.state, ProofState::from(grpc_state.state));
+        assert_eq!(rest_state.state, ProofState::Unspent);
+    }
+
+    // SWAP
+    let (_, _, swap_outputs) = blinded_messages(keyset_id, &amounts)?;
+    let res = post(
+        "/v1/swap",
+        json!({ "inputs": proofs, "outputs": swap_outputs }),
+    )
+    .await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let swapped: SwapResponse = res.json().await?;
+    assert_eq!(swapped.signatures.len(), swap_outputs.len());
+
+    let res = post("/v1/checkstate", json!({ "Ys": ys })).await?;
+    let states: CheckStateResponse = res.json().await?;
+    assert!(
+        states
+            .proof_check_states
+            .iter()
+            .all(|state| state.state == ProofState::Spent)
+    );
+
+    // The inputs can't be spent twice
+    let (_, _, other_outputs) = blinded_messages(keyset_id, &amounts)?;
+    let res = post(
+        "/v1/swap",
+        json!({ "inputs": proofs, "outputs": other_outputs }),
+    )
+    .await?;
+    let error = cashu_error(res, StatusCode::BAD_REQUEST).await?;
+    assert_eq!(error.code(), u16::from(ErrorCode::ProofAlreadySpent));
+
+    // RESTORE
+    let res = post("/v1/restore", json!({ "outputs": swap_outputs })).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let restored: RestoreResponse = res.json().await?;
+    assert_eq!(restored.outputs, swap_outputs);
+    assert_eq!(restored.signatures, swapped.signatures);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_quote_is_reported_unpaid() -> Result<()> {
+    let mut client = init_node_client().await?;
+    let mut admin_client = init_admin_client().await?;
+
+    let res = post(
+        "/v1/mint/quote/starknet",
+        json!({ "amount": 8, "unit": Unit::MILLI_STRK.as_str() }),
+    )
+    .await?;
+    let quote: MintQuoteResponse<String> = res.json().await?;
+    admin_client
+        .expire_quote(ExpireQuoteRequest {
+            kind: QuoteKind::QkMint.into(),
+            quote: quote.quote.clone(),
+        })
+        .await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    let res = get(&format!("/v1/mint/quote/starknet/{}", quote.quote)).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rest_quote: MintQuoteResponse<String> = res.json().await?;
+    assert_eq!(rest_quote.state, MintQuoteState::Unpaid);
+    assert!(rest_quote.expiry < now);
+
+    let grpc_quote = client
+        .mint_quote_state(QuoteStateRequest {
+            method: "starknet".to_string(),
+            quote: quote.quote,
+        })
+        .await?
+        .into_inner();
+    assert_eq!(grpc_quote.state(), node_client::MintQuoteState::MnqsUnpaid);
+    assert!(grpc_quote.expiry < now);
+
+    Ok(())
+}
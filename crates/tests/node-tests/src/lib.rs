@@ -1,11 +1,20 @@
 use anyhow::{Result, anyhow};
+use db_node::{SharedStorage, postgres::PgStorage, sqlite::SqliteStorage};
+use serde_json::Value;
+use std::process::Command;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tonic_health::pb::health_client::HealthClient;
 
+use node_client::AdminClient;
 use node_client::keyset_rotation_service_client::KeysetRotationServiceClient;
 use node_client::node_client::NodeClient;
 
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+pub mod operations;
 
 async fn get_grpc_channel() -> Result<Channel> {
     let grpc_port = std::env::var("GRPC_PORT")?;
@@ -47,3 +56,46 @@ pub async fn init_keyset_client() -> Result<KeysetRotationServiceClient<tonic::t
 
     Ok(client)
 }
+
+pub type AdminInterceptor = Box<dyn FnMut(Request<()>) -> Result<Request<()>, Status> + Send>;
+
+/// Returns an admin client authenticated with the `ADMIN_TOKEN` env variable
+pub async fn init_admin_client()
+-> Result<AdminClient<InterceptedService<tonic::transport::Channel, AdminInterceptor>>> {
+    let channel = get_grpc_channel().await?;
+    let admin_token = std::env::var("ADMIN_TOKEN")?;
+    let authorization: tonic::metadata::MetadataValue<_> =
+        format!("Bearer {}", admin_token).parse()?;
+    let interceptor: AdminInterceptor = Box::new(move |mut req: Request<()>| {
+        req.metadata_mut()
+            .insert("authorization", authorization.clone());
+        Ok(req)
+    });
+    let client = AdminClient::with_interceptor(channel, interceptor);
+
+    Ok(client)
+}
+
+/// Connects to the database of the node, at `DB_URL`
+pub async fn init_storage() -> Result<SharedStorage> {
+    let db_url = std::env::var("DB_URL")?;
+    let storage: SharedStorage = if db_url.starts_with("sqlite:") {
+        Arc::new(SqliteStorage::connect(&db_url).await?)
+    } else {
+        Arc::new(PgStorage::new(sqlx::PgPool::connect(&db_url).await?))
+    };
+
+    Ok(storage)
+}
+
+/// Runs a subcommand of the node binary at `NODE_BIN` against the database at `DB_URL`
+///
+/// Returns whether it succeeded, along with the JSON report it printed.
+pub fn run_node_command(command: &str) -> Result<(bool, Value)> {
+    let node_bin = std::env::var("NODE_BIN")?;
+    let output = Command::new(node_bin).arg(command).output()?;
+    let report = serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("invalid `{command}` report: {e}"))?;
+
+    Ok((output.status.success(), report))
+}
@@ -0,0 +1,148 @@
+//! Mint, swap and melt a single millistrk proof against the mock node
+use anyhow::{Result, anyhow};
+use node_client::{
+    BlindSignature, BlindedMessage, GetKeysRequest, GetKeysetsRequest, MeltQuoteRequest,
+    MeltRequest, MintQuoteRequest, MintRequest, NodeClient, Proof, SwapRequest,
+};
+use nuts::dhke::{blind_message, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut01::{PublicKey, SecretKey};
+use starknet_liquidity_source::MeltPaymentRequest;
+use starknet_types::{StarknetU256, Unit};
+use starknet_types_core::felt::Felt;
+use tonic::transport::Channel;
+
+async fn active_keyset_id(client: &mut NodeClient<Channel>) -> Result<Vec<u8>> {
+    client
+        .keysets(GetKeysetsRequest {})
+        .await?
+        .into_inner()
+        .keysets
+        .into_iter()
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
+        .map(|ks| ks.id)
+        .ok_or(anyhow!("no active millistrk keyset"))
+}
+
+/// A blinded message of `amount`, along with the secret and blinding factor to unblind its signature
+fn output(keyset_id: &[u8], amount: u64) -> Result<(BlindedMessage, Secret, SecretKey)> {
+    let secret = Secret::generate();
+    let (blinded_secret, r) = blind_message(secret.as_bytes(), None)?;
+    let blinded_message = BlindedMessage {
+        amount,
+        keyset_id: keyset_id.to_vec(),
+        blinded_secret: blinded_secret.to_bytes().to_vec(),
+    };
+
+    Ok((blinded_message, secret, r))
+}
+
+async fn unblind(
+    client: &mut NodeClient<Channel>,
+    signature: &BlindSignature,
+    secret: Secret,
+    r: &SecretKey,
+) -> Result<Proof> {
+    let keys = client
+        .keys(GetKeysRequest {
+            keyset_id: Some(signature.keyset_id.clone()),
+        })
+        .await?
+        .into_inner()
+        .keysets
+        .remove(0)
+        .keys;
+    let node_pubkey_for_amount = keys
+        .iter()
+        .find(|key| key.amount == signature.amount)
+        .ok_or(anyhow!("no key for amount {}", signature.amount))?;
+    let node_pubkey_for_amount = PublicKey::from_hex(&node_pubkey_for_amount.pubkey)?;
+    let blind_signature = PublicKey::from_slice(&signature.blind_signature)?;
+    let unblinded_signature = unblind_message(&blind_signature, r, &node_pubkey_for_amount)?;
+
+    Ok(Proof {
+        amount: signature.amount,
+        keyset_id: signature.keyset_id.clone(),
+        secret: secret.to_string(),
+        unblind_signature: unblinded_signature.to_bytes().to_vec(),
+    })
+}
+
+/// Mint a proof of `amount`, which must be a power of two
+pub async fn mint(client: &mut NodeClient<Channel>, amount: u64) -> Result<Proof> {
+    let quote = client
+        .mint_quote(MintQuoteRequest {
+            method: "starknet".to_string(),
+            amount,
+            unit: Unit::MILLI_STRK.to_string(),
+            description: None,
+        })
+        .await?
+        .into_inner()
+        .quote;
+    let keyset_id = active_keyset_id(client).await?;
+    let (output, secret, r) = output(&keyset_id, amount)?;
+    let signature = client
+        .mint(MintRequest {
+            method: "starknet".to_string(),
+            quote,
+            outputs: vec![output],
+        })
+        .await?
+        .into_inner()
+        .signatures
+        .remove(0);
+
+    unblind(client, &signature, secret, &r).await
+}
+
+/// Swap `proof` for a new one of the same amount
+pub async fn swap(client: &mut NodeClient<Channel>, proof: Proof) -> Result<Proof> {
+    let keyset_id = active_keyset_id(client).await?;
+    let (output, secret, r) = output(&keyset_id, proof.amount)?;
+    let signature = client
+        .swap(SwapRequest {
+            inputs: vec![proof],
+            outputs: vec![output],
+        })
+        .await?
+        .into_inner()
+        .signatures
+        .remove(0);
+
+    unblind(client, &signature, secret, &r).await
+}
+
+/// Melt the whole amount of `proof`, the mock node charging no fee
+pub async fn melt(client: &mut NodeClient<Channel>, proof: Proof) -> Result<()> {
+    let wei = u128::from(proof.amount) * u128::from(Unit::MILLI_STRK.scale_factor());
+    let request = MeltPaymentRequest {
+        payee: Felt::from_hex_unchecked(
+            "0x064b48806902a367c8598f4f95c305e8c1a1acba5f082d294a43793113115691",
+        ),
+        asset: starknet_types::Asset::STRK,
+        amount: StarknetU256 {
+            low: Felt::from(wei),
+            high: Felt::from(0),
+        },
+    };
+    let quote = client
+        .melt_quote(MeltQuoteRequest {
+            method: "starknet".to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
+            request: serde_json::to_string(&request)?,
+        })
+        .await?
+        .into_inner()
+        .quote;
+    client
+        .melt(MeltRequest {
+            method: "starknet".to_string(),
+            quote,
+            inputs: vec![proof],
+            outputs: vec![],
+        })
+        .await?;
+
+    Ok(())
+}
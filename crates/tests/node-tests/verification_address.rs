@@ -30,14 +30,14 @@ async fn test_melt_with_valid_address() -> Result<()> {
         .keysets;
     let active_keyset = keysets
         .iter()
-        .find(|ks| ks.active && ks.unit == Unit::MilliStrk.as_str())
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
         .unwrap();
 
     // MINT QUOTE
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: amount.into(),
-        unit: Unit::MilliStrk.as_str().to_string(),
+        unit: Unit::MILLI_STRK.as_str().to_string(),
         description: None,
     };
     let original_mint_quote_response = node_client
@@ -96,7 +96,7 @@ async fn test_melt_with_valid_address() -> Result<()> {
 
     let payment_request = MeltPaymentRequest {
         payee: valid_address,
-        asset: Asset::Strk,
+        asset: Asset::STRK,
         amount: todo!(),
     };
 
@@ -105,6 +105,7 @@ async fn test_melt_with_valid_address() -> Result<()> {
     let melt_request = MeltRequest {
         method: "starknet".to_string(),
         inputs: vec![proof],
+        outputs: vec![],
         quote: todo!(),
     };
 
@@ -159,14 +160,14 @@ async fn test_melt_with_invalid_addresses() -> Result<()> {
         .keysets;
     let active_keyset = keysets
         .iter()
-        .find(|ks| ks.active && ks.unit == Unit::MilliStrk.as_str())
+        .find(|ks| ks.active && ks.unit == Unit::MILLI_STRK.as_str())
         .unwrap();
 
     // MINT QUOTE
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: amount.into(),
-        unit: Unit::MilliStrk.as_str().to_string(),
+        unit: Unit::MILLI_STRK.as_str().to_string(),
         description: None,
     };
     let original_mint_quote_response = node_client
@@ -225,7 +226,7 @@ async fn test_melt_with_invalid_addresses() -> Result<()> {
         // MELT
         let payment_request = MeltPaymentRequest {
             payee: invalid_address,
-            asset: Asset::Strk,
+            asset: Asset::STRK,
             amount: todo!(),
         };
 
@@ -234,6 +235,7 @@ async fn test_melt_with_invalid_addresses() -> Result<()> {
         let melt_request = MeltRequest {
             method: "starknet".to_string(),
             inputs: vec![proof.clone()],
+            outputs: vec![],
             quote: todo!(),
         };
 
@@ -10,7 +10,7 @@ async fn ok() -> Result<()> {
     let mut client = init_signer_client().await?;
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
@@ -61,7 +61,7 @@ async fn exceed_max_order() -> Result<()> {
     let mut client = init_signer_client().await?;
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 300,
         })
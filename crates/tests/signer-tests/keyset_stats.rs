@@ -0,0 +1,88 @@
+use anyhow::Result;
+use nuts::dhke::blind_message;
+use nuts::nut00::secret::Secret;
+use signer::{
+    BlindedMessage, DeclareKeysetRequest, GetKeysetStatsRequest, SignBlindedMessagesRequest,
+};
+use signer_tests::init_signer_client;
+use starknet_types::Unit;
+use tonic::Code;
+
+#[tokio::test]
+async fn signatures_are_accounted() -> Result<()> {
+    let mut client = init_signer_client().await?;
+
+    // Use an index no other test relies on, so that concurrent tests don't pollute the counters
+    let keyset_id = client
+        .declare_keyset(DeclareKeysetRequest {
+            unit: Unit::MILLI_STRK.to_string(),
+            index: 27,
+            max_order: 32,
+        })
+        .await?
+        .into_inner()
+        .keyset_id;
+
+    let before = client
+        .get_keyset_stats(GetKeysetStatsRequest {
+            keyset_id: keyset_id.clone(),
+        })
+        .await?
+        .into_inner();
+
+    let messages = [4, 16]
+        .into_iter()
+        .map(|amount| {
+            let (blinded_secret, _r) = blind_message(Secret::generate().as_bytes(), None).unwrap();
+            BlindedMessage {
+                amount,
+                keyset_id: keyset_id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            }
+        })
+        .collect();
+    client
+        .sign_blinded_messages(SignBlindedMessagesRequest { messages })
+        .await?;
+
+    let after = client
+        .get_keyset_stats(GetKeysetStatsRequest { keyset_id })
+        .await?
+        .into_inner();
+
+    assert_eq!(after.amount_signed, before.amount_signed + 20);
+    assert_eq!(after.signatures_count, before.signatures_count + 2);
+    assert_eq!(after.amount_verified, before.amount_verified);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_keyset() -> Result<()> {
+    let mut client = init_signer_client().await?;
+
+    let res = client
+        .get_keyset_stats(GetKeysetStatsRequest {
+            keyset_id: vec![0, 1, 2, 3, 4, 5, 6, 7],
+        })
+        .await;
+
+    assert!(matches!(res, Err(status) if status.code() == Code::NotFound));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_keyset_id() -> Result<()> {
+    let mut client = init_signer_client().await?;
+
+    let res = client
+        .get_keyset_stats(GetKeysetStatsRequest {
+            keyset_id: vec![0, 1, 2],
+        })
+        .await;
+
+    assert!(matches!(res, Err(status) if status.code() == Code::InvalidArgument));
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use nuts::dhke::{blind_message, unblind_message};
+use nuts::nut00::secret::Secret;
+use nuts::nut01::PublicKey;
+use signer::{
+    BlindedMessage, DeclareKeysetRequest, Proof, SignBlindedMessagesRequest, VerifyProofsRequest,
+};
+use signer_tests::init_signer_client;
+use starknet_types::Unit;
+
+const BATCH_SIZES: [usize; 4] = [1, 10, 100, 1000];
+const AMOUNT: u64 = 32;
+
+struct Batch {
+    messages: Vec<BlindedMessage>,
+    proofs: Vec<Proof>,
+}
+
+async fn build_batch(size: usize) -> Batch {
+    let mut client = init_signer_client().await.unwrap();
+
+    let declare_keyset_response = client
+        .declare_keyset(DeclareKeysetRequest {
+            unit: Unit::MILLI_STRK.to_string(),
+            index: 1,
+            max_order: 32,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let pubkey = declare_keyset_response
+        .keys
+        .iter()
+        .find(|key| key.amount == AMOUNT)
+        .map(|key| PublicKey::from_hex(&key.pubkey).unwrap())
+        .unwrap();
+
+    let (secrets, (messages, blinding_factors)): (Vec<_>, (Vec<_>, Vec<_>)) = (0..size)
+        .map(|_| {
+            let secret = Secret::generate();
+            let (blinded_secret, r) = blind_message(secret.as_bytes(), None).unwrap();
+            let message = BlindedMessage {
+                amount: AMOUNT,
+                keyset_id: declare_keyset_response.keyset_id.clone(),
+                blinded_secret: blinded_secret.to_bytes().to_vec(),
+            };
+
+            (secret, (message, r))
+        })
+        .unzip();
+
+    let signatures = client
+        .sign_blinded_messages(SignBlindedMessagesRequest {
+            messages: messages.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .signatures;
+
+    let proofs = signatures
+        .iter()
+        .zip(blinding_factors)
+        .zip(secrets)
+        .map(|((signature, r), secret)| {
+            let blind_signature = PublicKey::from_slice(signature).unwrap();
+            let unblinded_signature = unblind_message(&blind_signature, &r, &pubkey).unwrap();
+
+            Proof {
+                amount: AMOUNT,
+                keyset_id: declare_keyset_response.keyset_id.clone(),
+                secret: secret.to_string(),
+                unblind_signature: unblinded_signature.to_bytes().to_vec(),
+            }
+        })
+        .collect();
+
+    Batch { messages, proofs }
+}
+
+fn sign_and_verify(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let client = runtime.block_on(init_signer_client()).unwrap();
+    let batches: Vec<_> = BATCH_SIZES
+        .iter()
+        .map(|&size| (size, runtime.block_on(build_batch(size))))
+        .collect();
+
+    let mut sign_group = c.benchmark_group("sign_blinded_messages");
+    for (size, batch) in &batches {
+        sign_group.throughput(Throughput::Elements(*size as u64));
+        sign_group.bench_with_input(BenchmarkId::from_parameter(size), batch, |b, batch| {
+            b.to_async(&runtime).iter(|| {
+                let mut client = client.clone();
+                let messages = batch.messages.clone();
+                async move {
+                    client
+                        .sign_blinded_messages(SignBlindedMessagesRequest { messages })
+                        .await
+                        .unwrap()
+                }
+            });
+        });
+    }
+    sign_group.finish();
+
+    let mut verify_group = c.benchmark_group("verify_proofs");
+    for (size, batch) in &batches {
+        verify_group.throughput(Throughput::Elements(*size as u64));
+        verify_group.bench_with_input(BenchmarkId::from_parameter(size), batch, |b, batch| {
+            b.to_async(&runtime).iter(|| {
+                let mut client = client.clone();
+                let proofs = batch.proofs.clone();
+                async move {
+                    let res = client
+                        .verify_proofs(VerifyProofsRequest { proofs })
+                        .await
+                        .unwrap();
+                    assert!(res.get_ref().is_valid);
+                }
+            });
+        });
+    }
+    verify_group.finish();
+}
+
+criterion_group!(benches, sign_and_verify);
+criterion_main!(benches);
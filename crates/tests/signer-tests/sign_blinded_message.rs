@@ -20,7 +20,7 @@ async fn secret() -> Result<()> {
 
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
@@ -92,7 +92,7 @@ async fn amount() -> Result<()> {
 
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
@@ -144,7 +144,7 @@ async fn non_existent_keysetid() -> Result<()> {
 
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
@@ -200,7 +200,7 @@ async fn bad_version_keysetid() -> Result<()> {
 
     let res = client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
@@ -16,7 +16,7 @@ async fn create_valid_proof(amount: Amount) -> Result<Proof> {
 
     let res = signer_client
         .declare_keyset(DeclareKeysetRequest {
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             index: 1,
             max_order: 32,
         })
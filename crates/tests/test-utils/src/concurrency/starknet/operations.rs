@@ -40,7 +40,7 @@ pub async fn mint_same_quote(node_client: NodeClient<Channel>, env: EnvVariables
     let mut mints_requests: Vec<MintRequest> = Vec::new();
     for _ in 0..100 {
         let active_keyset =
-            get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+            get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
         let secret = Secret::generate();
         let (blinded_secret, _r) =
             blind_message(secret.as_bytes(), None).map_err(|e| Error::Other(e.into()))?;
@@ -83,7 +83,7 @@ pub async fn mint_same_output(
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: amount.into(),
-        unit: Unit::MilliStrk.to_string(),
+        unit: Unit::MILLI_STRK.to_string(),
         description: None,
     };
     let mut mints_quote_response: Vec<MintQuoteResponse> = Vec::new();
@@ -133,7 +133,7 @@ pub async fn mint_same_output(
     }
 
     let active_keyset =
-        get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+        get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
     let secret = Secret::generate();
     let (blinded_secret, _r) =
         blind_message(secret.as_bytes(), None).map_err(|e| Error::Other(e.into()))?;
@@ -176,7 +176,7 @@ pub async fn swap_same_output(
     let total_amount_to_mint = Amount::from(swap_amount * n_concurent);
 
     let active_keyset =
-        get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+        get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
     let node_pubkey_for_amount = PublicKey::from_hex(
         &node_client
             .keys(GetKeysRequest {
@@ -277,7 +277,7 @@ pub async fn swap_same_input(
         mint_quote_and_deposit_and_wait(node_client.clone(), env.clone(), amount).await?;
 
     let active_keyset =
-        get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+        get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
     let secret = Secret::generate();
     let (blinded_secret, r) =
         blind_message(secret.as_bytes(), None).map_err(|e| Error::Other(e.into()))?;
@@ -379,7 +379,7 @@ pub async fn melt_same_input(
     wait_transac(node_client.clone(), &original_mint_quote_response).await?;
 
     let active_keyset =
-        get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+        get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
     let secret = Secret::generate();
     let (blinded_secret, r) =
         blind_message(secret.as_bytes(), None).map_err(|e| Error::Other(e.into()))?;
@@ -446,13 +446,13 @@ pub async fn melt_same_input(
     }
 
     let method = STARKNET_STR.to_string();
-    let asset = starknet_types::Asset::Strk;
+    let asset = starknet_types::Asset::STRK;
     let on_chain_amount = U256::from(32).checked_mul(asset.scale_factor()).unwrap() / 1000;
     for payee in payees.iter() {
         let melt_quote_response = node_client
             .melt_quote(MeltQuoteRequest {
                 method: method.clone(),
-                unit: Unit::MilliStrk.to_string(),
+                unit: Unit::MILLI_STRK.to_string(),
                 request: serde_json::to_string(&starknet_liquidity_source::MeltPaymentRequest {
                     payee: *payee,
                     asset,
@@ -471,6 +471,7 @@ pub async fn melt_same_input(
             method: method.clone(),
             quote: melt_quote_id.clone(),
             inputs: vec![proof.clone()],
+            outputs: vec![],
         };
         multi_melt.push(make_melt(node_client.clone(), melt_request));
     }
@@ -521,7 +522,7 @@ pub async fn melt_same_quote(
 
     // MINTING
     let active_keyset =
-        get_active_keyset(&mut node_client.clone(), Unit::MilliStrk.as_str()).await?;
+        get_active_keyset(&mut node_client.clone(), Unit::MILLI_STRK.as_str()).await?;
 
     let node_pubkey_for_amount = PublicKey::from_hex(
         &node_client
@@ -602,14 +603,14 @@ pub async fn melt_same_quote(
 
     let method = STARKNET_STR.to_string();
 
-    let asset = starknet_types::Asset::Strk;
+    let asset = starknet_types::Asset::STRK;
 
     let on_chain_amount = U256::from(128).checked_mul(asset.scale_factor()).unwrap() / 1000;
 
     let melt_quote_response = node_client
         .melt_quote(MeltQuoteRequest {
             method: method.clone(),
-            unit: Unit::MilliStrk.to_string(),
+            unit: Unit::MILLI_STRK.to_string(),
             request: serde_json::to_string(&starknet_liquidity_source::MeltPaymentRequest {
                 payee,
                 asset,
@@ -627,6 +628,7 @@ pub async fn melt_same_quote(
             method: method.clone(),
             quote: melt_quote_id.clone(),
             inputs: vec![proof],
+            outputs: vec![],
         };
 
         melt_requests.push(make_melt(node_client.clone(), melt_request));
@@ -111,7 +111,7 @@ pub async fn mint_quote_and_deposit_and_wait(
     let mint_quote_request = MintQuoteRequest {
         method: "starknet".to_string(),
         amount: amount.into(),
-        unit: Unit::MilliStrk.to_string(),
+        unit: Unit::MILLI_STRK.to_string(),
         description: None,
     };
 
@@ -161,7 +161,7 @@ impl WalletOps {
             .ok_or(anyhow!("amount too big"))?;
         let request = serde_json::to_string(&starknet_liquidity_source::MeltPaymentRequest {
             payee: payee_address,
-            asset: starknet_types::Asset::Strk,
+            asset: starknet_types::Asset::STRK,
             amount: amount.into(),
         })?;
 
@@ -182,7 +182,7 @@ impl WalletOps {
             &mut self.node_client,
             self.node_id,
             melt_quote_response.quote.clone(),
-            Amount::from(melt_quote_response.amount),
+            Amount::from(melt_quote_response.amount + melt_quote_response.fee_reserve),
             method.clone(),
             unit,
         )
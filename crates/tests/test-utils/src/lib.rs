@@ -5,3 +5,6 @@ pub mod e2e;
 
 #[cfg(feature = "concurrency")]
 pub mod concurrency;
+
+#[cfg(feature = "liquidity-source")]
+pub mod liquidity_source;
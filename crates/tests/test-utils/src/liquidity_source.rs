@@ -0,0 +1,47 @@
+//! Helpers for the tests of the liquidity sources, run against an in-process storage
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use db_node::SharedStorage;
+use nuts::{nut04::MintQuoteState, nut05::MeltQuoteState};
+use uuid::Uuid;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Long enough for a few blocks to be mined and indexed
+const POLL_ATTEMPTS: u32 = 300;
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+pub async fn wait_for_mint_quote_state(
+    storage: &SharedStorage,
+    quote_id: Uuid,
+    state: MintQuoteState,
+) {
+    for _ in 0..POLL_ATTEMPTS {
+        let mut conn = storage.acquire().await.unwrap();
+        if conn.get_mint_quote_response(quote_id).await.unwrap().state == state {
+            return;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    panic!("mint quote {quote_id} never reached state {state:?}");
+}
+
+pub async fn wait_for_melt_quote_state(
+    storage: &SharedStorage,
+    quote_id: Uuid,
+    state: MeltQuoteState,
+) {
+    for _ in 0..POLL_ATTEMPTS {
+        let mut conn = storage.acquire().await.unwrap();
+        if conn.get_melt_quote_response(quote_id).await.unwrap().state == state {
+            return;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    panic!("melt quote {quote_id} never reached state {state:?}");
+}
@@ -39,5 +39,11 @@ pub fn migrations() -> Vec<Migration> {
             sql: wallet::db::proof::CREATE_TABLE_PROOF,
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 7,
+            description: "alter_table_keyset_add_final_expiry",
+            sql: wallet::db::keyset::ALTER_TABLE_KEYSET_ADD_FINAL_EXPIRY,
+            kind: MigrationKind::Up,
+        },
     ]
 }